@@ -1,6 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use fast_ssim2::{
-    compute_frame_ssimulacra2, Blur, ColorPrimaries, Frame, MatrixCoefficients, Plane,
+    compute_frame_ssimulacra2, compute_ssimulacra2, compute_ssimulacra2_with_config, Blur,
+    ColorPrimaries, Frame, LinearRgbImage, MatrixCoefficients, Plane, Ssimulacra2Config,
     TransferCharacteristic, Yuv, YuvConfig,
 };
 use num_traits::clamp;
@@ -126,5 +127,108 @@ fn bench_blur(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_ssimulacra2, bench_blur);
+/// Packed 8-bit sRGB source and a noisy copy, as a decoder would hand them over
+fn make_srgb_pair(width: usize, height: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut rng = rand::thread_rng();
+    let source: Vec<u8> = (0..width * height * 3).map(|_| rng.gen()).collect();
+    let distorted = source
+        .iter()
+        .map(|&v| clamp(i16::from(v) + rng.gen_range(-16..=16), 0, 255) as u8)
+        .collect();
+    (source, distorted)
+}
+
+fn bench_end_to_end(c: &mut Criterion) {
+    // From u8 sRGB bytes to score, including the conversion to linear RGB
+    let mut group = c.benchmark_group("end_to_end");
+    group.sample_size(10);
+    for (name, width, height) in [("512x512", 512, 512), ("1080p", 1920, 1080)] {
+        let (source, distorted) = make_srgb_pair(width, height);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let source =
+                    LinearRgbImage::from_srgb_u8(black_box(&source), width, height).unwrap();
+                let distorted =
+                    LinearRgbImage::from_srgb_u8(black_box(&distorted), width, height).unwrap();
+                compute_ssimulacra2(&source, &distorted).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_long_images(c: &mut Criterion) {
+    // Same pixel count in every shape, so time per comparison should match
+    let mut group = c.benchmark_group("long_images");
+    group.sample_size(10);
+    #[allow(unused_mut)]
+    let mut configs = vec![
+        ("scalar", Ssimulacra2Config::scalar()),
+        ("simd", Ssimulacra2Config::simd()),
+    ];
+    #[cfg(feature = "unsafe-simd")]
+    configs.push(("unsafe-simd", Ssimulacra2Config::unsafe_simd()));
+    for (width, height) in [(724, 724), (16384, 32), (32, 16384)] {
+        let (source, distorted) = make_srgb_pair(width, height);
+        let source = LinearRgbImage::from_srgb_u8(&source, width, height).unwrap();
+        let distorted = LinearRgbImage::from_srgb_u8(&distorted, width, height).unwrap();
+        for (backend, config) in &configs {
+            let name = format!("{}x{}/{}", width, height, backend);
+            group.bench_function(name, |b| {
+                b.iter(|| {
+                    compute_ssimulacra2_with_config(black_box(&source), &distorted, config.clone())
+                        .unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_dark_images(c: &mut Criterion) {
+    // Subnormal linear values slow float arithmetic on many CPUs. The opsin
+    // bias keeps them out of the blurs, but the XYB conversion and the
+    // downscales still see them unless they are flushed first
+    let mut group = c.benchmark_group("dark_images");
+    group.sample_size(10);
+    let (width, height) = (512, 512);
+    let (source, distorted) = make_srgb_pair(width, height);
+    let source = LinearRgbImage::from_srgb_u8(&source, width, height).unwrap();
+    let distorted = LinearRgbImage::from_srgb_u8(&distorted, width, height).unwrap();
+    let scaled = |image: &LinearRgbImage, scale: f32| {
+        let data = image
+            .data()
+            .iter()
+            .map(|pix| pix.map(|v| v * scale))
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    };
+    for (name, scale) in [
+        ("ordinary", 1.0),
+        ("near_black", 1e-6),
+        ("subnormal", 1e-39),
+    ] {
+        let (source, distorted) = (scaled(&source, scale), scaled(&distorted, scale));
+        group.bench_function(name, |b| {
+            b.iter(|| compute_ssimulacra2(black_box(&source), &distorted).unwrap())
+        });
+    }
+    let (source, distorted) = (scaled(&source, 1e-39), scaled(&distorted, 1e-39));
+    let flush = Ssimulacra2Config::default().with_flush_subnormals(true);
+    group.bench_function("subnormal_flushed", |b| {
+        b.iter(|| {
+            compute_ssimulacra2_with_config(black_box(&source), &distorted, flush.clone()).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_ssimulacra2,
+    bench_blur,
+    bench_end_to_end,
+    bench_long_images,
+    bench_dark_images
+);
 criterion_main!(benches);
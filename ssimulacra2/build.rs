@@ -1,8 +1,8 @@
 use std::env;
 use std::f64::consts::PI;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::path::Path;
 
 use yuvxyb_math::{ColVector, Matrix, RowVector};
@@ -11,6 +11,132 @@ fn main() {
     let out_dir = &env::var("OUT_DIR").expect("can read OUT_DIR");
 
     init_recursive_gaussian(out_dir).expect("can init recursive gaussian");
+    init_srgb_tables(out_dir).expect("can init sRGB tables");
+    init_build_info(out_dir).expect("can init build info");
+}
+
+/// Bump when the derivation of the recursive Gaussian constants changes, so
+/// binaries built before and after can be told apart.
+const GAUSSIAN_CONSTANTS_VERSION: u32 = 1;
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn write_const_strs<W: Write>(w: &mut W, doc: &str, name: &str, vals: &[String]) -> io::Result<()> {
+    writeln!(w, "/// {doc}")?;
+    writeln!(w, "pub const {name}: &[&str] = &{vals:?};")
+}
+
+/// Targets listed in the `#[multiversion(targets(...))]` attributes under
+/// `src/`, in the order multiversion tries them.
+fn multiversion_targets(dir: &Path, targets: &mut Vec<String>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            multiversion_targets(&path, targets)?;
+            continue;
+        }
+        if path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        for line in fs::read_to_string(&path)?.lines() {
+            let Some(list) = line.trim().strip_prefix("#[multiversion(targets(") else {
+                continue;
+            };
+            let list = list.split(')').next().unwrap_or_default();
+            for target in list.split(',').map(|t| t.trim().trim_matches('"')) {
+                if !target.is_empty() && !targets.iter().any(|t| t == target) {
+                    targets.push(target.to_string());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn init_build_info(out_path: &str) -> io::Result<()> {
+    let mut features = env::vars()
+        .filter_map(|(key, _)| {
+            let feature = key.strip_prefix("CARGO_FEATURE_")?;
+            Some(feature.to_lowercase().replace('_', "-"))
+        })
+        .filter(|feature| feature != "default")
+        .collect::<Vec<_>>();
+    features.sort();
+
+    let mut target_features = env::var("CARGO_CFG_TARGET_FEATURE")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    target_features.sort();
+
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").expect("can read CARGO_CFG_TARGET_ARCH");
+    let mut targets = Vec::new();
+    // Without the `simd` feature no multiversioned kernel is compiled in
+    if env::var_os("CARGO_FEATURE_SIMD").is_some() {
+        multiversion_targets(Path::new("src"), &mut targets)?;
+    }
+    targets.retain(|target| target.split('+').next() == Some(arch.as_str()));
+
+    let gaussian = fs::read(Path::new(out_path).join("recursive_gaussian.rs"))?;
+
+    let mut w = BufWriter::new(File::create(Path::new(out_path).join("build_info.rs"))?);
+    write_const_strs(
+        &mut w,
+        "Cargo features enabled in this build",
+        "FEATURES",
+        &features,
+    )?;
+    writeln!(w, "/// Target triple the crate was compiled for")?;
+    writeln!(
+        w,
+        "pub const TARGET: &str = {:?};",
+        env::var("TARGET").unwrap_or_default()
+    )?;
+    writeln!(w, "/// Optimization level of the build")?;
+    writeln!(
+        w,
+        "pub const OPT_LEVEL: &str = {:?};",
+        env::var("OPT_LEVEL").unwrap_or_default()
+    )?;
+    write_const_strs(
+        &mut w,
+        "CPU features enabled at compile time, which every kernel may assume",
+        "TARGET_FEATURES",
+        &target_features,
+    )?;
+    write_const_strs(
+        &mut w,
+        "Multiversioned kernel targets compiled in, in the order they are tried at runtime",
+        "MULTIVERSION_TARGETS",
+        &targets,
+    )?;
+    writeln!(
+        w,
+        "/// Version of the recursive Gaussian constant derivation"
+    )?;
+    writeln!(
+        w,
+        "pub const GAUSSIAN_CONSTANTS_VERSION: u32 = {GAUSSIAN_CONSTANTS_VERSION};"
+    )?;
+    writeln!(
+        w,
+        "/// FNV-1a hash of the generated recursive Gaussian constants"
+    )?;
+    writeln!(
+        w,
+        "pub const GAUSSIAN_CONSTANTS_HASH: u64 = {:#018x};",
+        fnv1a(&gaussian)
+    )?;
+    w.flush()
 }
 
 fn write_const_f32<W: Write>(w: &mut W, name: &str, val: f32) -> io::Result<()> {
@@ -21,6 +147,42 @@ fn write_const_usize<W: Write>(w: &mut W, name: &str, val: usize) -> io::Result<
     writeln!(w, "pub const {name}: usize = {val}_usize;")
 }
 
+/// Writes a `[f32; N]` array literal, to be pulled in with `include!`.
+fn write_f32_table(path: &Path, values: impl ExactSizeIterator<Item = f32>) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "[")?;
+    for (i, val) in values.enumerate() {
+        // Debug formatting is the shortest representation that round-trips
+        write!(w, "{val:?}_f32,")?;
+        if i % 8 == 7 {
+            writeln!(w)?;
+        }
+    }
+    writeln!(w, "]")?;
+    w.flush()
+}
+
+/// Must stay identical to `srgb_to_linear` in `src/input.rs`, including the
+/// f32 arithmetic, so table lookups match the function bit for bit.
+fn srgb_to_linear(s: f32) -> f32 {
+    if s <= 0.04045 {
+        s / 12.92
+    } else {
+        ((s + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn init_srgb_tables(out_path: &str) -> io::Result<()> {
+    write_f32_table(
+        &Path::new(out_path).join("srgb_u8_to_linear.rs"),
+        (0..=u8::MAX).map(|v| srgb_to_linear(f32::from(v) / 255.0)),
+    )?;
+    write_f32_table(
+        &Path::new(out_path).join("srgb_u16_to_linear.rs"),
+        (0..=u16::MAX).map(|v| srgb_to_linear(f32::from(v) / 65535.0)),
+    )
+}
+
 fn init_recursive_gaussian(out_path: &str) -> io::Result<()> {
     const SIGMA: f64 = 1.5f64;
 
@@ -8,7 +8,10 @@ use std::alloc::{GlobalAlloc, Layout, System};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
-use fast_ssim2::{compute_frame_ssimulacra2_with_config, Ssimulacra2Config};
+use fast_ssim2::{
+    compute_ssimulacra2_into, compute_ssimulacra2_with_config, LinearRgbImage, Ssimulacra2Config,
+    Ssimulacra2Scratch, ToLinearRgb,
+};
 use yuvxyb::{ColorPrimaries, Rgb, TransferCharacteristic};
 
 // Custom allocator that tracks allocations
@@ -103,12 +106,34 @@ fn benchmark_with_tracking(
     let distorted = create_test_image(width, height, 67890);
 
     // Warmup (don't count)
-    let _ = compute_frame_ssimulacra2_with_config(source.clone(), distorted.clone(), config);
+    let _ = compute_ssimulacra2_with_config(&source, &distorted, config.clone());
 
     // Reset and measure
     reset_counters();
     let start = Instant::now();
-    let _ = compute_frame_ssimulacra2_with_config(source.clone(), distorted.clone(), config);
+    let _ = compute_ssimulacra2_with_config(&source, &distorted, config);
+    let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let (alloc_count, alloc_bytes, _, _) = get_stats();
+
+    (elapsed_ms, alloc_count, alloc_bytes)
+}
+
+fn benchmark_scratch(
+    width: usize,
+    height: usize,
+    config: Ssimulacra2Config,
+) -> (f64, usize, usize) {
+    let source: LinearRgbImage = create_test_image(width, height, 12345).to_linear_rgb();
+    let distorted: LinearRgbImage = create_test_image(width, height, 67890).to_linear_rgb();
+    let mut scratch = Ssimulacra2Scratch::with_config(config);
+
+    // Warmup sizes the scratch (don't count)
+    let _ = compute_ssimulacra2_into(&mut scratch, &source, &distorted);
+
+    reset_counters();
+    let start = Instant::now();
+    let _ = compute_ssimulacra2_into(&mut scratch, &source, &distorted);
     let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
 
     let (alloc_count, alloc_bytes, _, _) = get_stats();
@@ -200,5 +225,25 @@ fn main() {
         }
     }
 
+    println!("\nSIMD with reused Ssimulacra2Scratch (linear RGB inputs):");
+    println!(
+        "{:12} {:>10} {:>12} {:>14} {:>10}",
+        "Size", "Time (ms)", "Allocs", "Bytes", "Bytes/px"
+    );
+    println!("{:-<65}", "");
+
+    for (w, h, name) in &sizes {
+        let (ms, allocs, bytes) = benchmark_scratch(*w, *h, Ssimulacra2Config::simd());
+        let bytes_per_pixel = bytes as f64 / (*w * *h) as f64;
+        println!(
+            "{:12} {:>10.1} {:>12} {:>14} {:>10.1}",
+            name,
+            ms,
+            allocs,
+            format_bytes(bytes),
+            bytes_per_pixel
+        );
+    }
+
     println!("\nDone.");
 }
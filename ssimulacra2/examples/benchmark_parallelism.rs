@@ -0,0 +1,56 @@
+/// Calibrates `PARALLEL_MIN_PIXELS`: times the blur sequentially and on all
+/// cores at plane sizes around the threshold.
+///
+/// Run with `cargo run --release --example benchmark_parallelism --features rayon`.
+/// The crossover is the smallest size where the parallel column wins.
+use fast_ssim2::{Blur, Parallelism, PARALLEL_MIN_PIXELS};
+use std::time::Instant;
+
+fn time_blur(width: usize, height: usize, parallelism: Parallelism) -> f64 {
+    let plane = vec![0.5f32; width * height];
+    let img = [plane.clone(), plane.clone(), plane];
+    let mut blur = Blur::new(width, height);
+    blur.set_parallelism(parallelism);
+
+    // Warmup
+    blur.blur(&img);
+
+    let iterations = (200_000_000 / (width * height)).clamp(3, 200);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        blur.blur(&img);
+    }
+    start.elapsed().as_secs_f64() * 1000.0 / iterations as f64
+}
+
+fn main() {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let sizes = [
+        (256, 256),
+        (640, 480),
+        (1280, 720),
+        (1920, 1080),
+        (2560, 1440),
+        (3840, 2160),
+    ];
+
+    println!(
+        "{} threads, current threshold {} pixels",
+        threads, PARALLEL_MIN_PIXELS
+    );
+    println!(
+        "{:>10} {:>12} {:>12} {:>8}",
+        "size", "sequential", "parallel", "speedup"
+    );
+    for (width, height) in sizes {
+        let sequential = time_blur(width, height, Parallelism::Off);
+        let parallel = time_blur(width, height, Parallelism::Threads(threads));
+        println!(
+            "{:>10} {:>9.3} ms {:>9.3} ms {:>7.2}x",
+            format!("{}x{}", width, height),
+            sequential,
+            parallel,
+            sequential / parallel
+        );
+    }
+}
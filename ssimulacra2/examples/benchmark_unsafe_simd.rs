@@ -3,10 +3,29 @@
 //! Run with:
 //!   cargo run --release --example benchmark_unsafe_simd
 //!   cargo run --release --example benchmark_unsafe_simd --features unsafe-simd
+//!
+//! With `-- --json-bench`, prints one JSON document instead of the tables,
+//! for dashboards that track performance across commits and machines:
+//!
+//! ```json
+//! {"crate":"fast-ssim2","version":"0.6.5","target":"x86_64-unknown-linux-gnu",
+//!  "opt_level":"3","features":["simd","unsafe-simd","yuvxyb"],
+//!  "target_features":["sse2"],"runtime_target":"x86_64+avx2+fma","threads":16,
+//!  "results":[{"stage":"xyb","size":"512x512","width":512,"height":512,
+//!              "backend":"scalar","iterations":100,"ms":1.234},...]}
+//! ```
+//!
+//! Stages are `xyb` (conversion of one full-size image), `blur` (three
+//! full-size planes) and `full` (the whole comparison). Times are
+//! milliseconds per iteration after a warmup.
 
+use std::fmt::Write as _;
 use std::time::Instant;
 
-use fast_ssim2::{compute_frame_ssimulacra2_with_config, Blur, SimdImpl, Ssimulacra2Config};
+use fast_ssim2::{
+    build_info, compute_ssimulacra2_with_config, linear_rgb_to_xyb_with_config, Blur, SimdImpl,
+    Ssimulacra2Config, ToLinearRgb,
+};
 use yuvxyb::{ColorPrimaries, Rgb, TransferCharacteristic};
 
 fn create_test_image(width: usize, height: usize, seed: u64) -> Rgb {
@@ -68,23 +87,139 @@ fn benchmark_full_ssimulacra2(
 
     // Warmup
     for _ in 0..3 {
-        let _ = compute_frame_ssimulacra2_with_config(source.clone(), distorted.clone(), config);
+        let _ = compute_ssimulacra2_with_config(&source, &distorted, config.clone());
     }
 
     // Timed runs
     let start = Instant::now();
     for _ in 0..iterations {
-        let _ = compute_frame_ssimulacra2_with_config(source.clone(), distorted.clone(), config);
+        let _ = compute_ssimulacra2_with_config(&source, &distorted, config.clone());
     }
     let elapsed = start.elapsed();
 
     elapsed.as_secs_f64() * 1000.0 / iterations as f64
 }
 
-fn main() {
-    println!("SSIMULACRA2 Implementation Benchmark");
-    println!("=====================================\n");
+fn benchmark_xyb(width: usize, height: usize, config: Ssimulacra2Config, iterations: usize) -> f64 {
+    let image = create_test_image(width, height, 12345).to_linear_rgb();
+
+    // Warmup
+    for _ in 0..3 {
+        let _ = linear_rgb_to_xyb_with_config(&image, config.clone());
+    }
+
+    // Timed runs
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let _ = linear_rgb_to_xyb_with_config(&image, config.clone());
+    }
+    let elapsed = start.elapsed();
+
+    elapsed.as_secs_f64() * 1000.0 / iterations as f64
+}
+
+/// Backends compiled into this build, with their configs
+fn backends() -> Vec<(&'static str, SimdImpl, Ssimulacra2Config)> {
+    let mut backends = vec![
+        ("scalar", SimdImpl::Scalar, Ssimulacra2Config::scalar()),
+        ("simd", SimdImpl::Simd, Ssimulacra2Config::simd()),
+    ];
+    if cfg!(feature = "unsafe-simd") {
+        backends.push((
+            "unsafe-simd",
+            SimdImpl::UnsafeSimd,
+            Ssimulacra2Config::unsafe_simd(),
+        ));
+    }
+    backends
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_json_strings(out: &mut String, items: &[&str]) {
+    out.push('[');
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_json_string(out, item);
+    }
+    out.push(']');
+}
+
+/// Runs every stage on every size and backend, and prints the timings with
+/// the build and machine they were measured on.
+fn json_bench(sizes: &[(usize, usize, &str, usize)]) {
+    let mut json = String::from("{\"crate\":\"fast-ssim2\",\"version\":");
+    write_json_string(&mut json, build_info::VERSION);
+    json.push_str(",\"target\":");
+    write_json_string(&mut json, build_info::TARGET);
+    json.push_str(",\"opt_level\":");
+    write_json_string(&mut json, build_info::OPT_LEVEL);
+    json.push_str(",\"features\":");
+    write_json_strings(&mut json, build_info::FEATURES);
+    json.push_str(",\"target_features\":");
+    write_json_strings(&mut json, build_info::TARGET_FEATURES);
+    json.push_str(",\"runtime_target\":");
+    match build_info::runtime_target() {
+        Some(target) => write_json_string(&mut json, target),
+        None => json.push_str("null"),
+    }
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let _ = write!(json, ",\"threads\":{},\"results\":[", threads);
 
+    let mut first = true;
+    for &(width, height, name, iters) in sizes {
+        let size = name.split(' ').next().unwrap_or(name);
+        for (backend, simd_impl, config) in backends() {
+            let stages = [
+                (
+                    "xyb",
+                    iters,
+                    benchmark_xyb(width, height, config.clone(), iters),
+                ),
+                (
+                    "blur",
+                    iters,
+                    benchmark_blur(width, height, simd_impl, iters),
+                ),
+                (
+                    "full",
+                    iters / 2,
+                    benchmark_full_ssimulacra2(width, height, config, iters / 2),
+                ),
+            ];
+            for (stage, iterations, ms) in stages {
+                if !first {
+                    json.push(',');
+                }
+                first = false;
+                let _ = write!(
+                    json,
+                    "{{\"stage\":\"{}\",\"size\":\"{}\",\"width\":{},\"height\":{},\"backend\":\"{}\",\"iterations\":{},\"ms\":{:.4}}}",
+                    stage, size, width, height, backend, iterations, ms
+                );
+            }
+        }
+    }
+    json.push_str("]}");
+    println!("{}", json);
+}
+
+fn main() {
     // Test different image sizes
     let sizes = [
         (512, 512, "512x512", 100),
@@ -95,6 +230,14 @@ fn main() {
         (4096, 2160, "4096x2160 (DCI 4K)", 10),
     ];
 
+    if std::env::args().any(|arg| arg == "--json-bench") {
+        json_bench(&sizes);
+        return;
+    }
+
+    println!("SSIMULACRA2 Implementation Benchmark");
+    println!("=====================================\n");
+
     // Blur-only benchmarks
     println!("Blur-only benchmark (3 planes):");
     println!(
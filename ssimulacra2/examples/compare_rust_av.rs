@@ -0,0 +1,207 @@
+//! Score a corpus with both fast-ssim2 and rust-av's `ssimulacra2` crate and
+//! report how far the two implementations diverge.
+//!
+//! Both crates get the same linear RGB pixels, so the report measures the
+//! metric implementations, not sRGB decoding. It lists every pair, then the
+//! mean, RMS and largest absolute difference and how often the two crates
+//! rank a pair of comparisons in opposite order.
+//!
+//! Each corpus directory holds a `source.png` and any number of distorted
+//! versions of it (PNG or JPEG) with the same dimensions. Without arguments,
+//! the JPEG quality ladder and the tank pair from `test_data` are scored.
+//!
+//! Usage:
+//!   cargo run --release --features compare-rust-av --example compare_rust_av [corpus_dir ...]
+
+use fast_ssim2::{compute_ssimulacra2, srgb_u8_to_linear, LinearRgbImage};
+use image::ImageReader;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A source/distorted pair of linear RGB pixels.
+struct Pair {
+    name: String,
+    width: usize,
+    height: usize,
+    source: Vec<[f32; 3]>,
+    distorted: Vec<[f32; 3]>,
+}
+
+/// Scores of one pair from both crates.
+struct Comparison {
+    name: String,
+    fast: f64,
+    rust_av: f64,
+}
+
+fn load_linear(path: &Path) -> (Vec<[f32; 3]>, usize, usize) {
+    let img = ImageReader::open(path)
+        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path.display(), e))
+        .decode()
+        .unwrap_or_else(|e| panic!("Failed to decode {}: {}", path.display(), e))
+        .to_rgb8();
+    let (width, height) = img.dimensions();
+    let pixels = img.pixels().map(|p| p.0.map(srgb_u8_to_linear)).collect();
+    (pixels, width as usize, height as usize)
+}
+
+fn load_pair(name: String, source: &Path, distorted: &Path) -> Pair {
+    let (source, width, height) = load_linear(source);
+    let (distorted, w, h) = load_linear(distorted);
+    assert_eq!(
+        (w, h),
+        (width, height),
+        "{}: distorted image dimensions differ from the source",
+        name
+    );
+    Pair {
+        name,
+        width,
+        height,
+        source,
+        distorted,
+    }
+}
+
+/// Pairs `source.png` in `dir` with every other PNG or JPEG next to it.
+fn load_corpus(dir: &Path) -> Vec<Pair> {
+    let source = dir.join("source.png");
+    let mut distorted: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {}", dir.display(), e))
+        .map(|entry| entry.expect("readable directory entry").path())
+        .filter(|path| {
+            *path != source
+                && path.extension().is_some_and(|ext| {
+                    ["png", "jpg", "jpeg"]
+                        .contains(&ext.to_ascii_lowercase().to_str().unwrap_or(""))
+                })
+        })
+        .collect();
+    distorted.sort();
+
+    let corpus = dir.file_name().map_or_else(
+        || dir.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    distorted
+        .iter()
+        .map(|path| {
+            let name = format!(
+                "{}/{}",
+                corpus,
+                path.file_name().expect("file path").to_string_lossy()
+            );
+            load_pair(name, &source, path)
+        })
+        .collect()
+}
+
+fn default_corpus() -> Vec<Pair> {
+    let test_data = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_data");
+    let mut pairs = load_corpus(&test_data.join("jpeg_quality"));
+    pairs.push(load_pair(
+        "tank".to_string(),
+        &test_data.join("tank_source.png"),
+        &test_data.join("tank_distorted.png"),
+    ));
+    pairs
+}
+
+fn score_pair(pair: &Pair) -> Comparison {
+    let fast = compute_ssimulacra2(
+        LinearRgbImage::new(pair.source.clone(), pair.width, pair.height),
+        LinearRgbImage::new(pair.distorted.clone(), pair.width, pair.height),
+    )
+    .unwrap_or_else(|e| panic!("{}: fast-ssim2 failed: {}", pair.name, e));
+
+    let rust_av_image = |pixels: &[[f32; 3]]| {
+        ssimulacra2::LinearRgb::new(pixels.to_vec(), pair.width, pair.height)
+            .expect("valid dimensions")
+    };
+    let rust_av = ssimulacra2::compute_frame_ssimulacra2(
+        rust_av_image(&pair.source),
+        rust_av_image(&pair.distorted),
+    )
+    .unwrap_or_else(|e| panic!("{}: ssimulacra2 failed: {}", pair.name, e));
+
+    Comparison {
+        name: pair.name.clone(),
+        fast,
+        rust_av,
+    }
+}
+
+/// Number of comparison pairs the two crates order differently, and the
+/// number of pairs checked. Ties in either crate don't count.
+fn rank_inversions(results: &[Comparison]) -> (usize, usize) {
+    let mut inversions = 0;
+    let mut checked = 0;
+    for (i, a) in results.iter().enumerate() {
+        for b in &results[i + 1..] {
+            if a.fast == b.fast || a.rust_av == b.rust_av {
+                continue;
+            }
+            checked += 1;
+            if (a.fast < b.fast) != (a.rust_av < b.rust_av) {
+                inversions += 1;
+            }
+        }
+    }
+    (inversions, checked)
+}
+
+fn main() {
+    let dirs: Vec<PathBuf> = env::args().skip(1).map(PathBuf::from).collect();
+    let pairs = if dirs.is_empty() {
+        default_corpus()
+    } else {
+        dirs.iter().flat_map(|dir| load_corpus(dir)).collect()
+    };
+    if pairs.is_empty() {
+        eprintln!("No image pairs found");
+        std::process::exit(1);
+    }
+
+    println!(
+        "fast-ssim2 {} vs ssimulacra2 (rust-av)",
+        env!("CARGO_PKG_VERSION")
+    );
+    println!();
+    println!(
+        "{:<40} {:>12} {:>12} {:>10}",
+        "pair", "fast-ssim2", "ssimulacra2", "diff"
+    );
+    let results: Vec<Comparison> = pairs
+        .iter()
+        .map(|pair| {
+            let result = score_pair(pair);
+            println!(
+                "{:<40} {:>12.6} {:>12.6} {:>+10.6}",
+                result.name,
+                result.fast,
+                result.rust_av,
+                result.fast - result.rust_av
+            );
+            result
+        })
+        .collect();
+
+    let diffs: Vec<f64> = results.iter().map(|r| (r.fast - r.rust_av).abs()).collect();
+    let n = diffs.len() as f64;
+    let mean = diffs.iter().sum::<f64>() / n;
+    let rms = (diffs.iter().map(|d| d * d).sum::<f64>() / n).sqrt();
+    let (worst, worst_diff) = results
+        .iter()
+        .zip(&diffs)
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .expect("at least one pair");
+    let (inversions, checked) = rank_inversions(&results);
+
+    println!();
+    println!("pairs:             {}", results.len());
+    println!("mean |diff|:       {:.6}", mean);
+    println!("rms diff:          {:.6}", rms);
+    println!("max |diff|:        {:.6} ({})", worst_diff, worst.name);
+    println!("rank inversions:   {} of {} orderings", inversions, checked);
+}
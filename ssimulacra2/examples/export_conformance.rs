@@ -0,0 +1,358 @@
+//! Export a language-agnostic conformance bundle.
+//!
+//! The bundle lets bindings and third-party reimplementations validate against
+//! this crate without linking Rust code. It contains:
+//! 1. Input pairs as 8-bit sRGB PNGs
+//! 2. The same inputs as raw linear RGB planes (`.rgbf32`, interleaved little-endian f32)
+//! 3. `manifest.json` with expected scores per backend profile and tolerances
+//!
+//! A conforming implementation loads each pair (PNG or raw planes), computes the
+//! score and checks `|score - expected| <= tolerance` for the profile it targets.
+//!
+//! Usage:
+//!   cargo run --release --example export_conformance [output_dir]
+//!
+//! The default output directory is `target/conformance`.
+
+use fast_ssim2::{
+    compute_ssimulacra2_with_config, srgb_u8_to_linear, LinearRgbImage, Ssimulacra2Config,
+};
+use image::{ImageReader, RgbImage};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever the bundle layout or manifest schema changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// Absolute tolerance for an implementation targeting the same backend profile.
+const SAME_PROFILE_TOLERANCE: f64 = 1e-4;
+
+/// Relative tolerance between backends (matches the implementation parity tests).
+const CROSS_PROFILE_RELATIVE_TOLERANCE: f64 = 0.01;
+
+/// A single source/distorted pair in 8-bit sRGB.
+struct ConformanceCase {
+    name: String,
+    width: usize,
+    height: usize,
+    source: Vec<u8>,
+    distorted: Vec<u8>,
+}
+
+/// LCG pseudo-random number generator (deterministic across platforms)
+struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u8(&mut self) -> u8 {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.state >> 33) & 0xFF) as u8
+    }
+}
+
+fn gradient(width: usize, height: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let r = (x * 255 / (width - 1)) as u8;
+            let g = (y * 255 / (height - 1)) as u8;
+            let b = ((x + y) * 255 / (width + height - 2)) as u8;
+            data.extend_from_slice(&[r, g, b]);
+        }
+    }
+    data
+}
+
+fn noise(width: usize, height: usize, seed: u64) -> Vec<u8> {
+    let mut lcg = Lcg::new(seed);
+    (0..width * height * 3).map(|_| lcg.next_u8()).collect()
+}
+
+fn checkerboard(width: usize, height: usize, cell_size: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let val = if ((x / cell_size) + (y / cell_size)).is_multiple_of(2) {
+                255
+            } else {
+                0
+            };
+            data.extend_from_slice(&[val, val, val]);
+        }
+    }
+    data
+}
+
+/// Add a deterministic +/- `amplitude` perturbation to every sample.
+fn perturb(input: &[u8], amplitude: u8, seed: u64) -> Vec<u8> {
+    let mut lcg = Lcg::new(seed);
+    input
+        .iter()
+        .map(|&v| {
+            let delta = i16::from(lcg.next_u8() % (2 * amplitude + 1)) - i16::from(amplitude);
+            (i16::from(v) + delta).clamp(0, 255) as u8
+        })
+        .collect()
+}
+
+/// Apply a 3x3 box blur with clamped edges.
+fn box_blur_3x3(input: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut output = vec![0u8; input.len()];
+    for y in 0..height {
+        for x in 0..width {
+            for c in 0..3 {
+                let mut sum = 0u32;
+                for dy in [-1i64, 0, 1] {
+                    for dx in [-1i64, 0, 1] {
+                        let ny = (y as i64 + dy).clamp(0, height as i64 - 1) as usize;
+                        let nx = (x as i64 + dx).clamp(0, width as i64 - 1) as usize;
+                        sum += u32::from(input[(ny * width + nx) * 3 + c]);
+                    }
+                }
+                output[(y * width + x) * 3 + c] = (sum / 9) as u8;
+            }
+        }
+    }
+    output
+}
+
+fn load_rgb8(path: &Path) -> (Vec<u8>, usize, usize) {
+    let img = ImageReader::open(path)
+        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path.display(), e))
+        .decode()
+        .unwrap_or_else(|e| panic!("Failed to decode {}: {}", path.display(), e))
+        .to_rgb8();
+    let (width, height) = img.dimensions();
+    (img.into_raw(), width as usize, height as usize)
+}
+
+fn generate_cases() -> Vec<ConformanceCase> {
+    let mut cases = Vec::new();
+
+    // Synthetic patterns, including odd sizes to exercise partial downscale blocks
+    for (width, height) in [(8, 8), (33, 17), (64, 64), (127, 96)] {
+        let source = gradient(width, height);
+        cases.push(ConformanceCase {
+            name: format!("gradient_identical_{}x{}", width, height),
+            width,
+            height,
+            distorted: source.clone(),
+            source,
+        });
+
+        let source = noise(width, height, 42);
+        cases.push(ConformanceCase {
+            name: format!("noise_perturbed_{}x{}", width, height),
+            width,
+            height,
+            distorted: perturb(&source, 8, 7),
+            source,
+        });
+
+        let source = checkerboard(width, height, 4);
+        cases.push(ConformanceCase {
+            name: format!("checkerboard_blurred_{}x{}", width, height),
+            width,
+            height,
+            distorted: box_blur_3x3(&source, width, height),
+            source,
+        });
+    }
+
+    // Real JPEG artifacts from the test corpus
+    let corpus = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("test_data")
+        .join("jpeg_quality");
+    let (source, width, height) = load_rgb8(&corpus.join("source.png"));
+    for quality in [20, 45, 70, 90] {
+        let (distorted, w, h) = load_rgb8(&corpus.join(format!("q{}.jpg", quality)));
+        assert_eq!((w, h), (width, height), "JPEG corpus dimensions differ");
+        cases.push(ConformanceCase {
+            name: format!("jpeg_q{}_{}x{}", quality, width, height),
+            width,
+            height,
+            source: source.clone(),
+            distorted,
+        });
+    }
+
+    cases
+}
+
+/// Backend profiles available in this build, keyed by their manifest name.
+fn profiles() -> Vec<(&'static str, Ssimulacra2Config)> {
+    #[allow(unused_mut)]
+    let mut profiles = vec![
+        ("scalar", Ssimulacra2Config::scalar()),
+        ("simd", Ssimulacra2Config::simd()),
+    ];
+    #[cfg(feature = "unsafe-simd")]
+    profiles.push(("unsafe_simd", Ssimulacra2Config::unsafe_simd()));
+    profiles
+}
+
+fn to_linear(data: &[u8], width: usize, height: usize) -> LinearRgbImage {
+    let pixels = data
+        .chunks_exact(3)
+        .map(|p| {
+            [
+                srgb_u8_to_linear(p[0]),
+                srgb_u8_to_linear(p[1]),
+                srgb_u8_to_linear(p[2]),
+            ]
+        })
+        .collect();
+    LinearRgbImage::new(pixels, width, height)
+}
+
+fn save_png(path: &Path, data: &[u8], width: usize, height: usize) -> image::ImageResult<()> {
+    RgbImage::from_raw(width as u32, height as u32, data.to_vec())
+        .expect("buffer matches dimensions")
+        .save(path)
+}
+
+fn save_raw_planes(path: &Path, image: &LinearRgbImage) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for pixel in image.data() {
+        for value in pixel {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    writer.flush()
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("target").join("conformance"));
+    let cases_dir = out_dir.join("cases");
+    fs::create_dir_all(&cases_dir)?;
+
+    let cases = generate_cases();
+    let profiles = profiles();
+    println!(
+        "Exporting {} cases x {} profiles to {}",
+        cases.len(),
+        profiles.len(),
+        out_dir.display()
+    );
+
+    let mut manifest = BufWriter::new(File::create(out_dir.join("manifest.json"))?);
+    writeln!(manifest, "{{")?;
+    writeln!(manifest, "  \"format_version\": {},", FORMAT_VERSION)?;
+    writeln!(
+        manifest,
+        "  \"generator\": \"fast-ssim2 {}\",",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(
+        manifest,
+        "  \"raw_layout\": \"interleaved linear RGB, f32 little-endian, row-major\","
+    )?;
+    writeln!(manifest, "  \"png_transfer\": \"sRGB\",")?;
+    writeln!(manifest, "  \"tolerance\": {{")?;
+    writeln!(
+        manifest,
+        "    \"same_profile_absolute\": {:e},",
+        SAME_PROFILE_TOLERANCE
+    )?;
+    writeln!(
+        manifest,
+        "    \"cross_profile_relative\": {}",
+        CROSS_PROFILE_RELATIVE_TOLERANCE
+    )?;
+    writeln!(manifest, "  }},")?;
+    writeln!(manifest, "  \"profiles\": [")?;
+    for (i, (name, config)) in profiles.iter().enumerate() {
+        let comma = if i + 1 < profiles.len() { "," } else { "" };
+        writeln!(
+            manifest,
+            "    {{ \"name\": \"{}\", \"backend\": \"{}\" }}{}",
+            name,
+            config.impl_type.name(),
+            comma
+        )?;
+    }
+    writeln!(manifest, "  ],")?;
+    writeln!(manifest, "  \"cases\": [")?;
+
+    for (i, case) in cases.iter().enumerate() {
+        print!("[{:3}/{}] {:<40}", i + 1, cases.len(), case.name);
+
+        let source_png = format!("{}_source.png", case.name);
+        let distorted_png = format!("{}_distorted.png", case.name);
+        let source_raw = format!("{}_source.rgbf32", case.name);
+        let distorted_raw = format!("{}_distorted.rgbf32", case.name);
+
+        save_png(
+            &cases_dir.join(&source_png),
+            &case.source,
+            case.width,
+            case.height,
+        )?;
+        save_png(
+            &cases_dir.join(&distorted_png),
+            &case.distorted,
+            case.width,
+            case.height,
+        )?;
+
+        let source_linear = to_linear(&case.source, case.width, case.height);
+        let distorted_linear = to_linear(&case.distorted, case.width, case.height);
+        save_raw_planes(&cases_dir.join(&source_raw), &source_linear)?;
+        save_raw_planes(&cases_dir.join(&distorted_raw), &distorted_linear)?;
+
+        writeln!(manifest, "    {{")?;
+        writeln!(manifest, "      \"name\": \"{}\",", case.name)?;
+        writeln!(manifest, "      \"width\": {},", case.width)?;
+        writeln!(manifest, "      \"height\": {},", case.height)?;
+        writeln!(manifest, "      \"source_png\": \"cases/{}\",", source_png)?;
+        writeln!(
+            manifest,
+            "      \"distorted_png\": \"cases/{}\",",
+            distorted_png
+        )?;
+        writeln!(manifest, "      \"source_raw\": \"cases/{}\",", source_raw)?;
+        writeln!(
+            manifest,
+            "      \"distorted_raw\": \"cases/{}\",",
+            distorted_raw
+        )?;
+        writeln!(manifest, "      \"expected\": {{")?;
+        for (j, (name, config)) in profiles.iter().enumerate() {
+            let score = compute_ssimulacra2_with_config(
+                source_linear.clone(),
+                distorted_linear.clone(),
+                config.clone(),
+            )?;
+            print!(" {}={:.6}", name, score);
+            let comma = if j + 1 < profiles.len() { "," } else { "" };
+            writeln!(manifest, "        \"{}\": {:.10}{}", name, score, comma)?;
+        }
+        println!();
+        writeln!(manifest, "      }}")?;
+        let comma = if i + 1 < cases.len() { "," } else { "" };
+        writeln!(manifest, "    }}{}", comma)?;
+    }
+
+    writeln!(manifest, "  ]")?;
+    writeln!(manifest, "}}")?;
+    manifest.flush()?;
+
+    println!(
+        "\nDone! Manifest: {}",
+        out_dir.join("manifest.json").display()
+    );
+    Ok(())
+}
@@ -65,7 +65,11 @@ fn benchmark_config(
 ) -> (f64, f64, f64, f64) {
     // Warmup
     for _ in 0..3 {
-        let _ = compute_frame_ssimulacra2_with_config(source.clone(), distorted.clone(), config);
+        let _ = compute_frame_ssimulacra2_with_config(
+            source.clone(),
+            distorted.clone(),
+            config.clone(),
+        );
     }
 
     // Actual benchmark
@@ -73,8 +77,12 @@ fn benchmark_config(
     let mut score = 0.0;
     for _ in 0..iterations {
         let start = Instant::now();
-        score = compute_frame_ssimulacra2_with_config(source.clone(), distorted.clone(), config)
-            .unwrap();
+        score = compute_frame_ssimulacra2_with_config(
+            source.clone(),
+            distorted.clone(),
+            config.clone(),
+        )
+        .unwrap();
         times.push(start.elapsed().as_secs_f64() * 1000.0); // Convert to ms
     }
 
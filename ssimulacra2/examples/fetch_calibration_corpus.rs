@@ -0,0 +1,180 @@
+//! Download the calibration corpus listed in `test_data/calibration/manifest.txt`.
+//!
+//! Files already present with the expected checksum are skipped, so the tool
+//! can be rerun after adding entries to the manifest. Every download is
+//! verified against its SHA-256 before it is moved into place.
+//!
+//! Prerequisites:
+//! - `curl` in PATH
+//!
+//! Usage:
+//!   cargo run --release --example fetch_calibration_corpus
+//!   cargo run --release --example fetch_calibration_corpus -- path/to/manifest.txt
+//!
+//! Then run the correlation test:
+//!   cargo test --release --test calibration_correlation -- --ignored --nocapture
+
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// One file listed in the manifest
+#[derive(Debug)]
+struct Entry {
+    sha256: String,
+    path: PathBuf,
+    url: String,
+}
+
+/// Parse `<sha256> <path> <url>` lines, skipping blanks and `#` comments
+fn parse_manifest(contents: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [sha256, path, url] = fields[..] else {
+            return Err(format!(
+                "line {}: expected `<sha256> <path> <url>`, got {:?}",
+                i + 1,
+                line
+            ));
+        };
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("line {}: invalid sha256 {:?}", i + 1, sha256));
+        }
+        let path = PathBuf::from(path);
+        if path.is_absolute() || path.components().any(|c| c.as_os_str() == "..") {
+            return Err(format!(
+                "line {}: path must stay inside the corpus directory: {}",
+                i + 1,
+                path.display()
+            ));
+        }
+        entries.push(Entry {
+            sha256: sha256.to_ascii_lowercase(),
+            path,
+            url: url.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+fn sha256_of(path: &Path) -> std::io::Result<String> {
+    Ok(format!("{:x}", Sha256::digest(fs::read(path)?)))
+}
+
+/// Download `url` to `dest` with curl
+fn download(url: &str, dest: &Path) -> Result<(), String> {
+    let output = Command::new("curl")
+        .args([
+            "--fail",
+            "--location",
+            "--silent",
+            "--show-error",
+            "--output",
+        ])
+        .arg(dest)
+        .arg(url)
+        .output()
+        .map_err(|e| format!("Failed to execute curl: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "curl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Ensure `entry` is present under `root` with the expected checksum.
+/// Returns true if it had to be downloaded.
+fn fetch(root: &Path, entry: &Entry) -> Result<bool, String> {
+    let dest = root.join(&entry.path);
+    if dest.exists() && sha256_of(&dest).map_err(|e| e.to_string())? == entry.sha256 {
+        return Ok(false);
+    }
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let partial = dest.with_extension("partial");
+    download(&entry.url, &partial)?;
+
+    let actual = sha256_of(&partial).map_err(|e| e.to_string())?;
+    if actual != entry.sha256 {
+        let _ = fs::remove_file(&partial);
+        return Err(format!(
+            "checksum mismatch: expected {}, got {}",
+            entry.sha256, actual
+        ));
+    }
+    fs::rename(&partial, &dest).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let manifest_path = env::args().nth(1).map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("calibration")
+            .join("manifest.txt")
+    });
+    let root = manifest_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let entries = parse_manifest(&fs::read_to_string(&manifest_path)?)?;
+    println!(
+        "Manifest: {} ({} files)",
+        manifest_path.display(),
+        entries.len()
+    );
+
+    let (mut downloaded, mut failed) = (0, 0);
+    for (i, entry) in entries.iter().enumerate() {
+        print!(
+            "[{:3}/{}] {:<50} ... ",
+            i + 1,
+            entries.len(),
+            entry.path.display()
+        );
+        std::io::stdout().flush()?;
+        match fetch(&root, entry) {
+            Ok(true) => {
+                println!("downloaded");
+                downloaded += 1;
+            }
+            Ok(false) => println!("up to date"),
+            Err(e) => {
+                println!("FAILED: {}", e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "\n{} downloaded, {} up to date, {} failed",
+        downloaded,
+        entries.len() - downloaded - failed,
+        failed
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    if !root.join("scores.csv").exists() {
+        println!(
+            "\nNote: {} has no scores.csv; add a subjective dataset to the manifest \
+             to run the correlation test.",
+            root.display()
+        );
+    }
+    Ok(())
+}
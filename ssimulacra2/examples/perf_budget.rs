@@ -0,0 +1,109 @@
+//! End-to-end performance budgets, checked nightly in CI.
+//!
+//! Times the full path a caller pays for: packed 8-bit sRGB bytes for both
+//! images, conversion to linear RGB, and the score. Pipeline-only benchmarks
+//! start from pre-built `Rgb` values and miss regressions in the conversion
+//! path.
+//!
+//! Each size is run several times and the fastest run is compared against its
+//! budget, which keeps the check stable on shared runners. The budgets are set
+//! for the `ubuntu-latest` GitHub runner with default features; set
+//! `PERF_BUDGET_SCALE` (e.g. `2.0`) to loosen them on slower machines.
+//!
+//! Run with:
+//!   cargo run --release --example perf_budget
+
+use fast_ssim2::{compute_ssimulacra2, LinearRgbImage};
+use std::hint::black_box;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// `(name, width, height, budget in milliseconds)`. About twice the times in
+/// BENCHMARKS.md, so runner noise doesn't trip them but a conversion path
+/// that doubles in cost does.
+const BUDGETS: &[(&str, usize, usize, f64)] = &[
+    ("512x512", 512, 512, 80.0),
+    ("1080p", 1920, 1080, 750.0),
+    ("4K", 3840, 2160, 3000.0),
+];
+
+/// Timed runs per size, after one warmup run
+const RUNS: usize = 5;
+
+/// Packed sRGB test pattern with texture at several frequencies, so every
+/// scale of the pyramid has structure to compare.
+fn srgb_bytes(width: usize, height: usize, seed: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let noise = (x as u32 ^ y as u32)
+                .wrapping_mul(2_654_435_761)
+                .wrapping_add(seed)
+                >> 28;
+            let r = (x * 255 / width) as u32 + noise;
+            let g = (y * 255 / height) as u32 + noise;
+            let b = (((x / 8 + y / 8) % 2) * 128) as u32 + noise;
+            bytes.extend_from_slice(&[r.min(255) as u8, g.min(255) as u8, b.min(255) as u8]);
+        }
+    }
+    bytes
+}
+
+/// Time from bytes to score
+fn end_to_end(source: &[u8], distorted: &[u8], width: usize, height: usize) -> Duration {
+    let start = Instant::now();
+    let source = LinearRgbImage::from_srgb_u8(source, width, height).unwrap();
+    let distorted = LinearRgbImage::from_srgb_u8(distorted, width, height).unwrap();
+    black_box(compute_ssimulacra2(&source, &distorted).unwrap());
+    start.elapsed()
+}
+
+fn main() -> ExitCode {
+    let scale: f64 = std::env::var("PERF_BUDGET_SCALE")
+        .map(|v| v.parse().expect("PERF_BUDGET_SCALE must be a number"))
+        .unwrap_or(1.0);
+
+    println!(
+        "End-to-end budgets (u8 sRGB bytes -> score), best of {}",
+        RUNS
+    );
+    if scale != 1.0 {
+        println!("Budgets scaled by {}", scale);
+    }
+    println!(
+        "{:<10} {:>10} {:>10} {:>8}",
+        "size", "best ms", "budget ms", "status"
+    );
+
+    let mut over_budget = 0;
+    for &(name, width, height, budget_ms) in BUDGETS {
+        let source = srgb_bytes(width, height, 1);
+        let distorted = srgb_bytes(width, height, 2);
+
+        end_to_end(&source, &distorted, width, height);
+        let best = (0..RUNS)
+            .map(|_| end_to_end(&source, &distorted, width, height))
+            .min()
+            .unwrap();
+
+        let best_ms = best.as_secs_f64() * 1000.0;
+        let budget_ms = budget_ms * scale;
+        let ok = best_ms <= budget_ms;
+        if !ok {
+            over_budget += 1;
+        }
+        println!(
+            "{:<10} {:>10.1} {:>10.1} {:>8}",
+            name,
+            best_ms,
+            budget_ms,
+            if ok { "ok" } else { "OVER" }
+        );
+    }
+
+    if over_budget > 0 {
+        eprintln!("\n{} size(s) over budget", over_budget);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,56 @@
+/*
+ * C interface to fast-ssim2, built with the `capi` feature:
+ *
+ *   cargo rustc --release -p fast-ssim2 --features capi --crate-type cdylib
+ *
+ * Images are packed 8-bit sRGB (RGBRGB...) rows, `stride` bytes apart.
+ * Functions return SSIMULACRA2_OK or a negative error code and leave their
+ * out parameters untouched on failure.
+ */
+#ifndef SSIMULACRA2_H
+#define SSIMULACRA2_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SSIMULACRA2_OK 0
+#define SSIMULACRA2_ERROR_NULL_POINTER (-1)
+#define SSIMULACRA2_ERROR_INVALID_STRIDE (-2)
+#define SSIMULACRA2_ERROR_IMAGE_TOO_SMALL (-3)
+#define SSIMULACRA2_ERROR_DIMENSION_MISMATCH (-4)
+#define SSIMULACRA2_ERROR_INTERNAL (-5)
+
+/* Precomputed reference image; safe to compare against from several threads. */
+typedef struct Ssimulacra2Reference Ssimulacra2Reference;
+
+/* Scores `rgb2` against `rgb1`; both are width x height with the same stride. */
+int ssimulacra2_compute(const uint8_t *rgb1, const uint8_t *rgb2, size_t width,
+                        size_t height, size_t stride, double *out_score);
+
+/* Precomputes `rgb` for repeated comparisons. Release with ssimulacra2_reference_free. */
+int ssimulacra2_reference_new(const uint8_t *rgb, size_t width, size_t height,
+                              size_t stride, Ssimulacra2Reference **out_reference);
+
+/* Scores `rgb`, which must have the reference's dimensions. */
+int ssimulacra2_reference_compare(const Ssimulacra2Reference *reference,
+                                  const uint8_t *rgb, size_t width, size_t height,
+                                  size_t stride, double *out_score);
+
+/* Releases a reference. NULL is ignored. */
+void ssimulacra2_reference_free(Ssimulacra2Reference *reference);
+
+/* Static description of a status code. */
+const char *ssimulacra2_error_message(int code);
+
+/* Static library version string. */
+const char *ssimulacra2_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SSIMULACRA2_H */
@@ -0,0 +1,76 @@
+//! The crate's accuracy contract, as constants the parity tests enforce.
+//!
+//! Scores from this crate differ from the C++ `ssimulacra2` tool, and from
+//! one backend to another, by floating-point rounding. The bounds here are
+//! the ones `tests/reference_parity.rs`, `tests/implementation_parity.rs` and
+//! `tests/adversarial_corpus.rs` assert, so a release that stops meeting them
+//! fails CI. Downstream test suites can compare against the same numbers
+//! instead of picking their own:
+//!
+//! ```
+//! use fast_ssim2::accuracy;
+//! # let (scalar, simd) = (71.25, 71.2501);
+//! assert!(accuracy::backends_agree(scalar, simd));
+//! ```
+
+/// Largest difference between backends, relative to the score: 1%.
+pub const BACKEND_RELATIVE_TOLERANCE: f64 = 0.01;
+
+/// Largest difference between backends in score points, which applies where
+/// it is looser than [`BACKEND_RELATIVE_TOLERANCE`]: near 0, and near 100,
+/// where the metric's power law amplifies tiny differences.
+pub const BACKEND_ABSOLUTE_TOLERANCE: f64 = 0.1;
+
+/// Returns `true` if two scores of the same image pair from different
+/// backends are within the cross-backend tolerance of each other, taking the
+/// relative tolerance of the smaller score.
+#[must_use]
+pub fn backends_agree(a: f64, b: f64) -> bool {
+    let diff = (a - b).abs();
+    diff <= BACKEND_ABSOLUTE_TOLERANCE || diff <= a.abs().min(b.abs()) * BACKEND_RELATIVE_TOLERANCE
+}
+
+/// Kinds of content with different worst-case deviations from the C++ tool
+/// in the default (SIMD) configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviationFamily {
+    /// Synthetic patterns (gradients, checkerboards, noise, edges) and
+    /// comparisons between different ones
+    Synthetic,
+    /// Encoder-like distortions of a pattern: box blur, sharpening, YUV
+    /// round trips
+    Distortion,
+    /// Flat images compared with a uniformly shifted copy, where rounding in
+    /// near-zero variances dominates the score
+    UniformShift,
+    /// Anything else
+    Other,
+}
+
+impl DeviationFamily {
+    /// Largest difference in score points from the C++ tool, with the
+    /// default configuration, for content of this family.
+    #[must_use]
+    pub const fn max_cpp_deviation(self) -> f64 {
+        match self {
+            DeviationFamily::Synthetic => 0.01,
+            DeviationFamily::Distortion => 0.5,
+            DeviationFamily::UniformShift => 10.0,
+            DeviationFamily::Other => 0.05,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backends_agree() {
+        assert!(backends_agree(99.95, 100.0));
+        assert!(backends_agree(50.0, 50.4));
+        assert!(!backends_agree(50.0, 50.6));
+        assert!(backends_agree(-0.05, 0.04));
+        assert!(!backends_agree(-0.1, 0.1));
+    }
+}
@@ -0,0 +1,210 @@
+//! Alpha-aware scoring.
+//!
+//! Images carry an optional straight (non-premultiplied) alpha plane, set by
+//! the RGBA inputs or [`LinearRgbImage::with_alpha`]. [`AlphaMode`] decides
+//! what the metric does with it: by default alpha is ignored, as before, so
+//! fully transparent pixels count with whatever color they happen to store.
+//! Compositing over a background scores the images as they would be shown;
+//! coverage weighting keeps transparent regions from contributing at all.
+
+use std::borrow::Cow;
+
+use crate::input::LinearRgbImage;
+use crate::norms::Norms;
+use crate::{NormExponents, ScaleStatistics};
+
+/// How [`Ssimulacra2Config`](crate::Ssimulacra2Config) treats alpha planes.
+///
+/// Images without alpha are opaque in every mode.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AlphaMode {
+    /// Score the color channels as stored; alpha is not read
+    #[default]
+    Ignore,
+    /// Premultiply by alpha against `background` (linear RGB), i.e. score both
+    /// images composited over it
+    Premultiply {
+        /// Linear RGB background color, e.g. `[1.0; 3]` for white
+        background: [f32; 3],
+    },
+    /// Weight each pixel's contribution to the norms by its coverage, the
+    /// larger of the two images' alpha at that pixel. The coverage plane is
+    /// downscaled with the image pyramid. Pixels transparent in both images
+    /// don't affect the score; a pair that is fully transparent scores 100.
+    Coverage,
+}
+
+impl AlphaMode {
+    /// Applies the mode to a pair before the pipeline runs. Composited images
+    /// replace the inputs; coverage mode returns the weight image (the weight
+    /// repeated in all three channels, so it can go through the same resize
+    /// and pyramid filters as the pixels). `None` means unweighted scoring.
+    pub(crate) fn prepare(
+        self,
+        img1: &mut Cow<'_, LinearRgbImage>,
+        img2: &mut Cow<'_, LinearRgbImage>,
+    ) -> Option<LinearRgbImage> {
+        match self {
+            AlphaMode::Ignore => None,
+            AlphaMode::Premultiply { background } => {
+                if let Some(composited) = composite(img1, background) {
+                    *img1 = Cow::Owned(composited);
+                }
+                if let Some(composited) = composite(img2, background) {
+                    *img2 = Cow::Owned(composited);
+                }
+                None
+            }
+            AlphaMode::Coverage => {
+                let weights = match (img1.alpha(), img2.alpha()) {
+                    // An image without alpha is opaque, so the coverage is 1
+                    // everywhere and the weights wouldn't change anything
+                    (None, _) | (_, None) => return None,
+                    (Some(a1), Some(a2)) => a1
+                        .iter()
+                        .zip(a2)
+                        .map(|(&a1, &a2)| [a1.max(a2); 3])
+                        .collect(),
+                };
+                Some(LinearRgbImage::new(weights, img1.width(), img1.height()))
+            }
+        }
+    }
+}
+
+/// Composites `img` over `background` in linear light, or `None` if it has
+/// no alpha
+fn composite(img: &LinearRgbImage, background: [f32; 3]) -> Option<LinearRgbImage> {
+    let alpha = img.alpha()?;
+    let data = img
+        .data()
+        .iter()
+        .zip(alpha)
+        .map(|(px, &a)| {
+            let a = a.clamp(0.0, 1.0);
+            std::array::from_fn(|c| px[c].mul_add(a, background[c] * (1.0 - a)))
+        })
+        .collect();
+    Some(LinearRgbImage::new(data, img.width(), img.height()))
+}
+
+/// Pools the per-pixel error maps of one scale into coverage-weighted norms,
+/// in the layout of the unweighted kernels
+pub(crate) fn weighted_statistics(
+    weights: &[[f32; 3]],
+    exponents: NormExponents,
+    ssim: &[Vec<f32>; 3],
+    artifact: &[Vec<f32>; 3],
+    detail_lost: &[Vec<f32>; 3],
+) -> ScaleStatistics {
+    let total: f64 = weights.iter().map(|w| f64::from(w[0])).sum();
+    let norms = |map: &[f32]| -> [f64; 2] {
+        if total <= 0.0 {
+            return [0.0; 2];
+        }
+        let mut sum = [0.0f64; 2];
+        for (&w, &d) in weights.iter().zip(map) {
+            let (w, d) = (f64::from(w[0]), f64::from(d));
+            sum[0] += w * exponents.low_power(d);
+            sum[1] += w * exponents.high_power(d);
+        }
+        [
+            exponents.low_root(sum[0] / total),
+            exponents.high_root(sum[1] / total),
+        ]
+    };
+
+    let mut stats = ScaleStatistics {
+        avg_ssim: [0.0; 6],
+        avg_edgediff: [0.0; 12],
+    };
+    for c in 0..3 {
+        stats.avg_ssim[c * 2..c * 2 + 2].copy_from_slice(&norms(&ssim[c]));
+        stats.avg_edgediff[c * 4..c * 4 + 2].copy_from_slice(&norms(&artifact[c]));
+        stats.avg_edgediff[c * 4 + 2..c * 4 + 4].copy_from_slice(&norms(&detail_lost[c]));
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_image;
+    use crate::{compute_ssimulacra2_with_config, Ssimulacra2Config};
+
+    /// A 32x32 pair that is identical on the left half and differs on the
+    /// right half, where both are transparent
+    fn masked_pair() -> (LinearRgbImage, LinearRgbImage) {
+        let (width, height) = (32, 32);
+        let alpha: Vec<f32> = (0..width * height)
+            .map(|i| if i % width < 16 { 1.0 } else { 0.0 })
+            .collect();
+        let source = test_image(width, height, 1);
+        let distorted = source
+            .data()
+            .iter()
+            .enumerate()
+            .map(|(i, &px)| if i % width < 16 { px } else { [0.0; 3] })
+            .collect();
+        (
+            source.with_alpha(alpha.clone()).unwrap(),
+            LinearRgbImage::new(distorted, width, height)
+                .with_alpha(alpha)
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_premultiply_hides_transparent_pixels() {
+        let (source, distorted) = masked_pair();
+        let config = Ssimulacra2Config::default().with_alpha_mode(AlphaMode::Premultiply {
+            background: [0.5; 3],
+        });
+        let score = compute_ssimulacra2_with_config(&source, &distorted, config).unwrap();
+        assert_eq!(score, 100.0);
+    }
+
+    #[test]
+    fn test_coverage_hides_transparent_pixels() {
+        let (source, distorted) = masked_pair();
+        let ignored =
+            compute_ssimulacra2_with_config(&source, &distorted, Ssimulacra2Config::default())
+                .unwrap();
+        let config = Ssimulacra2Config::default().with_alpha_mode(AlphaMode::Coverage);
+        let score = compute_ssimulacra2_with_config(&source, &distorted, config.clone()).unwrap();
+        // The blur still mixes a few columns across the alpha edge, so the
+        // score isn't 100, but the transparent half no longer counts
+        assert!(score > ignored, "{} vs {}", score, ignored);
+
+        let transparent = vec![0.0; 32 * 32];
+        let score = compute_ssimulacra2_with_config(
+            source.clone().with_alpha(transparent.clone()).unwrap(),
+            distorted.clone().with_alpha(transparent).unwrap(),
+            config,
+        )
+        .unwrap();
+        assert_eq!(score, 100.0);
+    }
+
+    #[test]
+    fn test_opaque_alpha_matches_plain_images() {
+        let (width, height) = (24, 20);
+        let (source, distorted) = (test_image(width, height, 1), test_image(width, height, 2));
+        let plain =
+            compute_ssimulacra2_with_config(&source, &distorted, Ssimulacra2Config::default())
+                .unwrap();
+        let opaque = vec![1.0; width * height];
+        let source = source.with_alpha(opaque.clone()).unwrap();
+        let distorted = distorted.with_alpha(opaque).unwrap();
+
+        let premultiplied = Ssimulacra2Config::default().with_alpha_mode(AlphaMode::Premultiply {
+            background: [0.0; 3],
+        });
+        let score = compute_ssimulacra2_with_config(&source, &distorted, premultiplied).unwrap();
+        assert_eq!(score, plain);
+
+        let coverage = Ssimulacra2Config::default().with_alpha_mode(AlphaMode::Coverage);
+        let score = compute_ssimulacra2_with_config(&source, &distorted, coverage).unwrap();
+        assert!((score - plain).abs() < 1e-3, "{} vs {}", score, plain);
+    }
+}
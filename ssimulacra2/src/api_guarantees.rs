@@ -0,0 +1,82 @@
+//! Compile-fail checks for items that are intentionally not public.
+//!
+//! Only compiled by rustdoc when collecting doctests. Each block below must
+//! fail to compile with the given error code; if one starts compiling, an
+//! internal item has leaked into the public API and downstream crates could
+//! come to depend on it. Widening one of these on purpose means deleting its
+//! block here, not loosening it. The public surface itself is pinned by
+//! `tests/public_api.rs` and by `cargo semver-checks` in CI.
+//!
+//! The score weights and the function applying them stay internal, so the
+//! weighting can follow upstream metric revisions:
+//!
+//! ```compile_fail,E0603
+//! use fast_ssim2::SCORE_WEIGHTS;
+//! ```
+//!
+//! ```compile_fail,E0603
+//! use fast_ssim2::score_scales;
+//! ```
+//!
+//! ```compile_fail,E0603
+//! use fast_ssim2::NUM_SCALES;
+//! ```
+//!
+//! Kernel modules are reached only through the re-exports at the crate root
+//! and the backend selection in [`Ssimulacra2Config`](crate::Ssimulacra2Config):
+//!
+//! ```compile_fail,E0603
+//! use fast_ssim2::blur;
+//! ```
+//!
+//! ```compile_fail,E0603
+//! use fast_ssim2::simd_ops;
+//! ```
+//!
+//! ```compile_fail,E0603
+//! use fast_ssim2::xyb_simd;
+//! ```
+//!
+//! The precomputed reference planes keep their layout private, so storage
+//! precision and scale data can change:
+//!
+//! ```compile_fail,E0616
+//! # let data = vec![[0.5f32; 3]; 64];
+//! # let source = fast_ssim2::LinearRgbImage::new(data, 8, 8);
+//! let reference = fast_ssim2::Ssimulacra2Reference::new(source).unwrap();
+//! let _ = reference.scales.len();
+//! ```
+//!
+//! Map and scratch buffers are only read through their accessors:
+//!
+//! ```compile_fail,E0616
+//! let buffers = fast_ssim2::MapBuffers::new();
+//! let _ = buffers.scales.len();
+//! ```
+//!
+//! ```compile_fail,E0616
+//! let scratch = fast_ssim2::Ssimulacra2Scratch::new();
+//! let _ = scratch.mul.len();
+//! ```
+//!
+//! Fallback chains are built with `FallbackChain::new` or `FallbackChain::parse`,
+//! which drop duplicates and cap the length:
+//!
+//! ```compile_fail,E0451
+//! use fast_ssim2::{FallbackChain, SimdImpl};
+//! let _ = FallbackChain {
+//!     backends: [SimdImpl::Simd; 3],
+//!     len: 3,
+//! };
+//! ```
+//!
+//! The configuration is built from its default with the `with_*` methods, so
+//! options can be added in minor releases:
+//!
+//! ```compile_fail,E0639
+//! use fast_ssim2::{Ssimulacra2Config, SimdImpl};
+//! let _ = Ssimulacra2Config {
+//!     impl_type: SimdImpl::Scalar,
+//!     ..Default::default()
+//! };
+//! ```
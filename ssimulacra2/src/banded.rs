@@ -0,0 +1,189 @@
+//! Blurring and pooling each scale a band of rows at a time.
+//!
+//! The whole-image pipeline blurs five moment planes per channel (the means
+//! of both images, their variances and the covariance) in full before the
+//! error maps pool them: fifteen full-resolution planes, plus the products
+//! and blur temporaries behind them. With
+//! [`streaming_moments`](crate::Ssimulacra2Config::streaming_moments),
+//! [`BandedMoments`] blurs one band of rows of every moment, pools it into
+//! the running sums, and reuses the buffers for the next band, so the
+//! moments never exist in full.
+//!
+//! The blur runs row by row with the scalar backend's arithmetic, and the
+//! bands are the ones the pooling sums anyway, so the statistics are
+//! bit-identical to a whole-image comparison with the scalar blur.
+
+use crate::blur::StreamingBlur;
+use crate::parallelism;
+use crate::{
+    edge_diff_map_sums, plane_rows, pool_sums, ssim_map_sums, PlanarImage, ScaleStatistics,
+    Ssimulacra2Config, Stage,
+};
+
+/// Streaming blurs of one channel's moments and the band they last wrote:
+/// the means of both images, their variances, then the covariance
+struct ChannelBands {
+    blurs: [StreamingBlur; 5],
+    bands: [Vec<f32>; 5],
+}
+
+impl ChannelBands {
+    fn new() -> Self {
+        Self {
+            blurs: std::array::from_fn(|_| StreamingBlur::new()),
+            bands: Default::default(),
+        }
+    }
+
+    fn reset(&mut self, width: usize, height: usize, band_rows: usize) {
+        for blur in &mut self.blurs {
+            blur.reset(width, height);
+        }
+        for band in &mut self.bands {
+            band.resize(band_rows * width, 0.0);
+        }
+    }
+
+    /// Blurs the next `rows` rows of every moment of planes `img1` and `img2`.
+    fn blur_band(&mut self, img1: &[f32], img2: &[f32], width: usize, rows: usize) {
+        let row1 = |y: usize| &img1[y * width..][..width];
+        let row2 = |y: usize| &img2[y * width..][..width];
+        let product = |a: &[f32], b: &[f32], out: &mut [f32]| {
+            for ((out, a), b) in out.iter_mut().zip(a).zip(b) {
+                *out = a * b;
+            }
+        };
+        let [mu1, mu2, sigma1_sq, sigma2_sq, sigma12] = &mut self.blurs;
+        let [mu1_band, mu2_band, sigma1_sq_band, sigma2_sq_band, sigma12_band] =
+            self.bands.each_mut().map(|band| &mut band[..rows * width]);
+
+        mu1.blur_rows(mu1_band, |y, out| out.copy_from_slice(row1(y)));
+        mu2.blur_rows(mu2_band, |y, out| out.copy_from_slice(row2(y)));
+        sigma1_sq.blur_rows(sigma1_sq_band, |y, out| {
+            product(row1(y), row1(y), out);
+        });
+        sigma2_sq.blur_rows(sigma2_sq_band, |y, out| {
+            product(row2(y), row2(y), out);
+        });
+        sigma12.blur_rows(sigma12_band, |y, out| {
+            product(row1(y), row2(y), out);
+        });
+    }
+}
+
+/// Reusable state for scoring scales in bands (see the [module
+/// documentation](self)).
+pub(crate) struct BandedMoments {
+    channels: [ChannelBands; 3],
+}
+
+impl BandedMoments {
+    pub(crate) fn new() -> Self {
+        Self {
+            channels: std::array::from_fn(|_| ChannelBands::new()),
+        }
+    }
+
+    /// The SSIM and edge-difference norms of one scale, from its planar XYB
+    /// images.
+    pub(crate) fn statistics(
+        &mut self,
+        img1: &PlanarImage,
+        img2: &PlanarImage,
+        config: &Ssimulacra2Config,
+    ) -> ScaleStatistics {
+        let (width, height) = (img1.width(), img1.height());
+        let band_rows = parallelism::band_rows(width);
+        for channel in &mut self.channels {
+            channel.reset(width, height, band_rows);
+        }
+        let (img1, img2) = (img1.planes(), img2.planes());
+        let impl_type = config.backend_for(Stage::Ssim);
+        let exponents = config.norm_exponents;
+        let parallel = config.parallelism.is_parallel_for(width * height);
+
+        let mut ssim_sums = [0.0f64; 3 * 2];
+        let mut edgediff_sums = [0.0f64; 3 * 4];
+        let add = |sums: &mut [f64], band: &[f64]| {
+            for (sum, band) in sums.iter_mut().zip(band) {
+                *sum += band;
+            }
+        };
+        let mut start = 0;
+        while start < height {
+            let rows = band_rows.min(height - start);
+            let [c0, c1, c2] = &mut self.channels;
+            if parallel {
+                parallelism::join3(
+                    &config.parallelism,
+                    || c0.blur_band(&img1[0], &img2[0], width, rows),
+                    || c1.blur_band(&img1[1], &img2[1], width, rows),
+                    || c2.blur_band(&img1[2], &img2[2], width, rows),
+                );
+            } else {
+                for (c, channel) in [c0, c1, c2].into_iter().enumerate() {
+                    channel.blur_band(&img1[c], &img2[c], width, rows);
+                }
+            }
+
+            let moment = |i: usize| -> [&[f32]; 3] {
+                std::array::from_fn(|c| &self.channels[c].bands[i][..rows * width])
+            };
+            let band = start..start + rows;
+            add(
+                &mut ssim_sums,
+                &ssim_map_sums(
+                    width,
+                    moment(0),
+                    moment(1),
+                    moment(2),
+                    moment(3),
+                    moment(4),
+                    impl_type,
+                    exponents,
+                ),
+            );
+            add(
+                &mut edgediff_sums,
+                &edge_diff_map_sums(
+                    width,
+                    plane_rows(img1, width, &band),
+                    moment(0),
+                    plane_rows(img2, width, &band),
+                    moment(1),
+                    impl_type,
+                    exponents,
+                ),
+            );
+            start += rows;
+        }
+
+        ScaleStatistics {
+            avg_ssim: pool_sums(ssim_sums, width * height, exponents),
+            avg_edgediff: pool_sums(edgediff_sums, width * height, exponents),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::ramp;
+    use crate::{compute_ssimulacra2_detailed, Parallelism, Ssimulacra2Config};
+
+    #[test]
+    fn test_streaming_matches_scalar_blur() {
+        // Tall enough for several bands at the first scales
+        let (source, distorted) = (ramp(160, 900, 0), ramp(160, 900, 1));
+        for parallelism in [Parallelism::Off, Parallelism::Threads(3)] {
+            let config = Ssimulacra2Config::scalar().with_parallelism(parallelism);
+            let whole = compute_ssimulacra2_detailed(&source, &distorted, config.clone()).unwrap();
+            let streamed = compute_ssimulacra2_detailed(
+                &source,
+                &distorted,
+                config.with_streaming_moments(true),
+            )
+            .unwrap();
+            assert_eq!(streamed, whole);
+        }
+    }
+}
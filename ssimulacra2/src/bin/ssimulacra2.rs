@@ -0,0 +1,384 @@
+//! Drop-in replacement for the `ssimulacra2` tool from cloudinary/ssimulacra2.
+//!
+//! `ssimulacra2 original.png distorted.png` prints the score with eight
+//! decimals, like the C++ tool. The extra flags choose the backend, switch to
+//! JSON output, explain the score and write an error heatmap. Build with
+//! `--features cli`.
+
+use fast_ssim2::{
+    compute_ssimulacra2_detailed, compute_ssimulacra2_maps, open_linear_rgb, HeatScaling,
+    MapBuffers, MapRequest, SimdImpl, Ssimulacra2Config,
+};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: ssimulacra2 [OPTIONS] orig.png distorted.png
+
+Returns a score in range -inf..100, which correlates to subjective visual quality:
+     negative scores: extremely low quality, very strong distortion
+     10 = very low quality (average output of cjxl -d 14 / -q 12 or libjpeg-turbo quality 14)
+     30 = low quality (average output of cjxl -d 9 / -q 30 or libjpeg-turbo quality 23)
+     50 = medium quality (average output of cjxl -d 5 / -q 50 or libjpeg-turbo quality 35)
+     70 = high quality (hard to notice artifacts without comparison to the original)
+     90 = very high quality (impossible to distinguish from the original in a side-by-side comparison at 1:1)
+    100 = mathematically lossless
+
+Options:
+  --backend <NAME>  scalar, simd or unsafe-simd (default: simd)
+  --json            Print the score as a JSON object
+  --explain         Also print a summary of the kinds of error behind the score
+  --map <FILE>      Write a grayscale PNG of the full-resolution error (brighter is worse)
+  --map-scale <S>   linear (default), log or gamma: brightness curve of --map; log and
+                    gamma saturate at the 99th percentile so faint errors stay visible
+  --map-channels    With --map, also write the error of each XYB channel alone, next to
+                    FILE as FILE-x.png, FILE-y.png and FILE-b.png (minus FILE's extension)
+  -h, --help        Print this help
+  -V, --version     Print version and build information";
+
+struct Args {
+    source: PathBuf,
+    distorted: PathBuf,
+    config: Ssimulacra2Config,
+    json: bool,
+    explain: bool,
+    map: Option<PathBuf>,
+    map_scale: HeatScaling,
+    map_channels: bool,
+}
+
+enum Command {
+    Compare(Box<Args>),
+    Help,
+    Version,
+}
+
+fn parse_backend(name: &str) -> Result<Ssimulacra2Config, String> {
+    match name {
+        "scalar" => Ok(Ssimulacra2Config::scalar()),
+        "simd" => Ok(Ssimulacra2Config::simd()),
+        #[cfg(feature = "unsafe-simd")]
+        "unsafe-simd" => Ok(Ssimulacra2Config::unsafe_simd()),
+        #[cfg(not(feature = "unsafe-simd"))]
+        "unsafe-simd" => Err("this build does not include the unsafe-simd backend".to_string()),
+        _ => Err(format!(
+            "unknown backend '{}' (expected scalar, simd or unsafe-simd)",
+            name
+        )),
+    }
+}
+
+fn parse_map_scale(name: &str) -> Result<HeatScaling, String> {
+    match name {
+        "linear" => Ok(HeatScaling::default()),
+        "log" => Ok(HeatScaling::log()),
+        "gamma" => Ok(HeatScaling::gamma(0.5)),
+        _ => Err(format!(
+            "unknown map scale '{}' (expected linear, log or gamma)",
+            name
+        )),
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut paths = Vec::new();
+    let mut config = Ssimulacra2Config::default();
+    let mut json = false;
+    let mut explain = false;
+    let mut map = None;
+    let mut map_scale = HeatScaling::default();
+    let mut map_channels = false;
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| format!("{} requires a value", flag))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--json" => json = true,
+            "--explain" => explain = true,
+            "--map-channels" => map_channels = true,
+            "--backend" => config = parse_backend(&value("--backend")?)?,
+            "--map" => map = Some(PathBuf::from(value("--map")?)),
+            "--map-scale" => map_scale = parse_map_scale(&value("--map-scale")?)?,
+            flag if flag.starts_with("--backend=") => {
+                config = parse_backend(&flag["--backend=".len()..])?;
+            }
+            flag if flag.starts_with("--map-scale=") => {
+                map_scale = parse_map_scale(&flag["--map-scale=".len()..])?;
+            }
+            flag if flag.starts_with("--map=") => {
+                map = Some(PathBuf::from(&flag["--map=".len()..]));
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option '{}'", flag));
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [source, distorted]: [PathBuf; 2] = paths
+        .try_into()
+        .map_err(|_| "expected exactly two image paths".to_string())?;
+    if map_channels && map.is_none() {
+        return Err("--map-channels requires --map".to_string());
+    }
+    Ok(Command::Compare(Box::new(Args {
+        source,
+        distorted,
+        config,
+        json,
+        explain,
+        map,
+        map_scale,
+        map_channels,
+    })))
+}
+
+/// The `--backend` name of the backend `config` uses
+fn backend_name(config: &Ssimulacra2Config) -> &'static str {
+    match config.impl_type {
+        SimdImpl::Scalar => "scalar",
+        SimdImpl::Simd => "simd",
+        #[cfg(feature = "unsafe-simd")]
+        SimdImpl::UnsafeSimd => "unsafe-simd",
+    }
+}
+
+/// Escapes `value` as a JSON string literal
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Writes the worst of the SSIM, artifact and detail-lost errors of
+/// `channels` at each pixel, quantized with `scaling`.
+fn write_map(
+    path: &Path,
+    buffers: &MapBuffers,
+    channels: std::ops::Range<usize>,
+    scaling: HeatScaling,
+) -> Result<(), String> {
+    let maps = buffers
+        .error_maps(0)
+        .ok_or_else(|| "no error maps were produced".to_string())?;
+    let worst: Vec<f32> = (0..maps.width * maps.height)
+        .map(|i| {
+            [maps.ssim, maps.artifact, maps.detail_lost]
+                .iter()
+                .flat_map(|planes| planes[channels.clone()].iter().map(move |plane| plane[i]))
+                .fold(0.0f32, f32::max)
+        })
+        .collect();
+    let pixels = scaling.quantize(&worst);
+    let image = image::GrayImage::from_raw(maps.width as u32, maps.height as u32, pixels)
+        .ok_or_else(|| "error map has an unexpected size".to_string())?;
+    image
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// `path` with `-suffix.png` in place of its extension
+fn channel_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}-{}.png", stem, suffix))
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let source = open_linear_rgb(&args.source).map_err(|e| e.to_string())?;
+    let distorted = open_linear_rgb(&args.distorted).map_err(|e| e.to_string())?;
+
+    let failed = |e: fast_ssim2::Ssimulacra2Error| {
+        format!(
+            "Failed to compare {} with {}: {}",
+            args.source.display(),
+            args.distorted.display(),
+            e
+        )
+    };
+
+    let explanation = if args.explain {
+        let detailed = compute_ssimulacra2_detailed(&source, &distorted, args.config.clone());
+        Some(detailed.map_err(failed)?.explain())
+    } else {
+        None
+    };
+
+    let mut buffers = MapBuffers::new();
+    let score = match (&explanation, &args.map) {
+        (Some(explanation), None) => explanation.score,
+        _ => {
+            let request = if args.map.is_some() {
+                MapRequest::error_maps().with_scales(0..1)
+            } else {
+                MapRequest::default()
+            };
+            compute_ssimulacra2_maps(
+                &source,
+                &distorted,
+                args.config.clone(),
+                request,
+                &mut buffers,
+            )
+            .map_err(failed)?
+        }
+    };
+
+    if let Some(path) = &args.map {
+        write_map(path, &buffers, 0..3, args.map_scale)?;
+        if args.map_channels {
+            for (channel, suffix) in ["x", "y", "b"].into_iter().enumerate() {
+                let path = channel_path(path, suffix);
+                write_map(&path, &buffers, channel..channel + 1, args.map_scale)?;
+            }
+        }
+    }
+
+    if args.json {
+        let explanation = explanation
+            .map(|e| format!(", \"explanation\": {}", json_string(&e.to_string())))
+            .unwrap_or_default();
+        println!(
+            "{{\"score\": {:.8}, \"backend\": {}, \"source\": {}, \"distorted\": {}{}}}",
+            score,
+            json_string(backend_name(&args.config)),
+            json_string(&args.source.display().to_string()),
+            json_string(&args.distorted.display().to_string()),
+            explanation,
+        );
+    } else {
+        println!("{:.8}", score);
+        if let Some(explanation) = explanation {
+            println!("{}", explanation);
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Version) => {
+            println!("{}", fast_ssim2::build_info::summary());
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Compare(args)) => match run(&args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(message) => {
+                eprintln!("{}", message);
+                ExitCode::FAILURE
+            }
+        },
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn compare(args: &[&str]) -> Args {
+        match parse(args) {
+            Ok(Command::Compare(args)) => *args,
+            Ok(_) => panic!("{:?} did not parse to a comparison", args),
+            Err(e) => panic!("{:?} failed to parse: {}", args, e),
+        }
+    }
+
+    #[test]
+    fn test_flags_take_separate_and_inline_values() {
+        let args = compare(&[
+            "a.png",
+            "--backend",
+            "scalar",
+            "--map-scale=log",
+            "--map=out/err.png",
+            "--map-channels",
+            "--json",
+            "b.png",
+        ]);
+        assert_eq!(args.source, PathBuf::from("a.png"));
+        assert_eq!(args.distorted, PathBuf::from("b.png"));
+        assert_eq!(args.config.impl_type, SimdImpl::Scalar);
+        assert_eq!(args.map_scale, HeatScaling::log());
+        assert_eq!(args.map, Some(PathBuf::from("out/err.png")));
+        assert!(args.map_channels && args.json && !args.explain);
+
+        let args = compare(&[
+            "--backend=scalar",
+            "--map-scale",
+            "gamma",
+            "--map",
+            "err.png",
+            "a.png",
+            "b.png",
+        ]);
+        assert_eq!(args.config.impl_type, SimdImpl::Scalar);
+        assert_eq!(args.map_scale, HeatScaling::gamma(0.5));
+        assert_eq!(args.map, Some(PathBuf::from("err.png")));
+
+        // A lone dash is a path, as for stdin in other tools
+        assert_eq!(compare(&["-", "b.png"]).source, PathBuf::from("-"));
+    }
+
+    #[test]
+    fn test_invalid_arguments_are_reported() {
+        let error = |args: &[&str]| match parse(args) {
+            Err(e) => e,
+            Ok(_) => panic!("{:?} should not parse", args),
+        };
+        assert_eq!(
+            error(&["a.png", "b.png", "--map"]),
+            "--map requires a value"
+        );
+        assert_eq!(
+            error(&["a.png", "b.png", "--backend"]),
+            "--backend requires a value"
+        );
+        assert_eq!(
+            error(&["a.png", "b.png", "--map-channels"]),
+            "--map-channels requires --map"
+        );
+        assert!(error(&["--backend=avx", "a.png", "b.png"]).contains("unknown backend 'avx'"));
+        assert!(error(&["--backend", "avx", "a.png", "b.png"]).contains("unknown backend"));
+        assert!(error(&["--map-scale=cubic", "a.png", "b.png"]).contains("unknown map scale"));
+        assert_eq!(
+            error(&["--fast", "a.png", "b.png"]),
+            "unknown option '--fast'"
+        );
+        assert_eq!(error(&["a.png"]), "expected exactly two image paths");
+    }
+
+    #[test]
+    fn test_help_and_version_win_over_other_arguments() {
+        assert!(matches!(parse(&["a.png", "-h"]), Ok(Command::Help)));
+        assert!(matches!(
+            parse(&["--version", "--backend"]),
+            Ok(Command::Version)
+        ));
+    }
+}
@@ -1,40 +1,28 @@
-mod consts {
+pub(super) mod consts {
     #![allow(clippy::unreadable_literal)]
     include!(concat!(env!("OUT_DIR"), "/recursive_gaussian.rs"));
 }
 
+use crate::parallelism::{for_each_row, Parallelism};
+
 /// Implements "Recursive Implementation of the Gaussian Filter Using Truncated
 /// Cosine Functions" by Charalampidis [2016].
 pub struct RecursiveGaussian;
 
 impl RecursiveGaussian {
-    #[cfg(feature = "rayon")]
-    pub fn horizontal_pass(&self, input: &[f32], output: &mut [f32], width: usize) {
-        use rayon::iter::{IndexedParallelIterator, ParallelIterator};
-        use rayon::prelude::ParallelSliceMut;
-        use rayon::slice::ParallelSlice;
-
-        assert_eq!(input.len(), output.len());
-
-        input
-            .par_chunks_exact(width)
-            .zip(output.par_chunks_exact_mut(width))
-            .for_each(|(input, output)| self.horizontal_row(input, output, width));
-    }
-
-    #[cfg(not(feature = "rayon"))]
-    pub fn horizontal_pass(&self, input: &[f32], output: &mut [f32], width: usize) {
-        assert_eq!(input.len(), output.len());
-
-        for (input, output) in input
-            .chunks_exact(width)
-            .zip(output.chunks_exact_mut(width))
-        {
+    pub fn horizontal_pass(
+        &self,
+        input: &[f32],
+        output: &mut [f32],
+        width: usize,
+        parallelism: &Parallelism,
+    ) {
+        for_each_row(parallelism, input, output, width, |input, output| {
             self.horizontal_row(input, output, width);
-        }
+        });
     }
 
-    fn horizontal_row(&self, input: &[f32], output: &mut [f32], width: usize) {
+    pub(super) fn horizontal_row(&self, input: &[f32], output: &mut [f32], width: usize) {
         let big_n = consts::RADIUS as isize;
         // Use f64 accumulators to reduce rounding error accumulation in IIR filter
         let mut prev_1 = 0f64;
@@ -0,0 +1,92 @@
+//! Experimental blur approximating the recursive Gaussian with box filters
+//! evaluated via summed-area tables (integral images).
+//!
+//! Three passes of a 3x3 box filter give a kernel close to a Gaussian with
+//! σ ≈ 1.41 (the reference uses σ = 1.5). Each pass costs a constant number of
+//! operations per pixel regardless of radius and has no loop-carried state
+//! along rows, which suits targets where the IIR filter vectorizes poorly
+//! (e.g. WASM without SIMD). Like the IIR filter, samples outside the image
+//! are treated as zero.
+//!
+//! Scores deviate from the reference; see `BLUR_BACKEND_EVALUATION.md`.
+
+/// Number of box passes (three boxes are within a few percent of a Gaussian)
+const PASSES: usize = 3;
+
+/// Box radius per pass. A box of radius r has variance r(r + 1) / 3, so three
+/// passes give r(r + 1): 2.0 for r = 1 and 6.0 for r = 2, against the
+/// reference σ² = 2.25. Equal integer boxes can't reach σ = 1.5, and r = 1 is
+/// the closest. The narrower kernel costs up to 3.4 points on the JPEG corpus
+/// (57.03 vs 53.66 at Q20, 0.21 at Q90); `tests/integral_blur.rs` measures it
+/// and bounds it by 5.
+const RADIUS: usize = 1;
+
+/// Normalization for a full box; windows reaching past the edges are not
+/// renormalized, matching the zero padding of the recursive Gaussian
+const AREA: f64 = ((2 * RADIUS + 1) * (2 * RADIUS + 1)) as f64;
+
+pub struct SummedAreaBlur {
+    /// Summed-area table with a zero row and column in front, `(w + 1) * (h + 1)`
+    table: Vec<f64>,
+    temp: Vec<f32>,
+}
+
+impl SummedAreaBlur {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            table: vec![0.0; (width + 1) * (height + 1)],
+            temp: vec![0.0; width * height],
+        }
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.table.resize((width + 1) * (height + 1), 0.0);
+        self.temp.resize(width * height, 0.0);
+    }
+
+    pub fn blur_single_plane_into(
+        &mut self,
+        plane: &[f32],
+        out: &mut [f32],
+        width: usize,
+        height: usize,
+    ) {
+        assert_eq!(plane.len(), width * height);
+        assert_eq!(out.len(), width * height);
+        self.table.resize((width + 1) * (height + 1), 0.0);
+        self.temp.resize(width * height, 0.0);
+
+        box_pass(plane, out, &mut self.table, width, height);
+        for _ in 1..PASSES {
+            self.temp.copy_from_slice(out);
+            box_pass(&self.temp, out, &mut self.table, width, height);
+        }
+    }
+}
+
+fn box_pass(input: &[f32], output: &mut [f32], table: &mut [f64], width: usize, height: usize) {
+    let stride = width + 1;
+
+    // Build the table in f64 so large planes don't lose precision in the sums
+    table[..stride].fill(0.0);
+    for y in 0..height {
+        let mut row_sum = 0.0;
+        table[(y + 1) * stride] = 0.0;
+        for x in 0..width {
+            row_sum += f64::from(input[y * width + x]);
+            table[(y + 1) * stride + x + 1] = table[y * stride + x + 1] + row_sum;
+        }
+    }
+
+    for y in 0..height {
+        let y0 = y.saturating_sub(RADIUS);
+        let y1 = (y + RADIUS + 1).min(height);
+        for x in 0..width {
+            let x0 = x.saturating_sub(RADIUS);
+            let x1 = (x + RADIUS + 1).min(width);
+            let sum = table[y1 * stride + x1] - table[y0 * stride + x1] - table[y1 * stride + x0]
+                + table[y0 * stride + x0];
+            output[y * width + x] = (sum / AREA) as f32;
+        }
+    }
+}
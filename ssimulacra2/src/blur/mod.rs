@@ -1,34 +1,96 @@
 mod gaussian;
+mod pool;
+mod response;
+#[cfg(feature = "simd")]
 mod simd_gaussian;
+mod streaming;
+
+#[cfg(feature = "integral-blur")]
+mod integral;
 
 #[cfg(feature = "unsafe-simd")]
 mod unsafe_simd_gaussian;
 
-use crate::SimdImpl;
+use crate::parallelism::{self, Parallelism};
+use crate::{BlurKernel, SimdImpl, Ssimulacra2Config, Stage};
 use gaussian::RecursiveGaussian;
+pub use pool::{PlanePool, PlaneSet};
+pub use response::{verify_kernel, KernelResponse, KernelVerification};
+#[cfg(feature = "simd")]
 use simd_gaussian::SimdGaussian;
+#[cfg(feature = "simd")]
+pub use simd_gaussian::DEFAULT_MAX_CHUNK_COLUMNS;
+pub(crate) use streaming::StreamingBlur;
+
+/// Default cap on the vertical pass chunk width
+#[cfg(not(feature = "simd"))]
+pub const DEFAULT_MAX_CHUNK_COLUMNS: usize = 128;
 
 #[cfg(feature = "unsafe-simd")]
 use unsafe_simd_gaussian::UnsafeSimdGaussian;
 
+#[cfg(feature = "integral-blur")]
+use integral::SummedAreaBlur;
+
+/// Distance in rows or columns beyond which an input sample no longer affects
+/// a blurred output, for every [`BlurKernel`]. Blurring a window that extends
+/// this far past the rows it keeps gives those rows their whole-image values,
+/// up to rounding.
+pub(crate) const SUPPORT_RADIUS: usize = gaussian::consts::RADIUS + 1;
+
 /// Structure handling image blur with selectable implementation.
 ///
 /// Supports runtime switching between:
 /// - Scalar: f64 IIR baseline (most accurate)
 /// - SIMD: Safe SIMD via wide crate
-/// - UnsafeSimd: Raw x86 intrinsics (fastest)
+/// - UnsafeSimd: Raw x86 or NEON intrinsics (fastest)
+///
+/// With the `integral-blur` feature, [`BlurKernel::SummedArea`] replaces the
+/// recursive Gaussian with an experimental box-filter approximation.
+///
+/// With the `rayon` feature, and when [`Parallelism`] allows it for the
+/// image size, the three planes of [`blur`](Self::blur) and
+/// [`blur_into`](Self::blur_into) are blurred on separate tasks.
+///
+/// [`blur_pooled`](Self::blur_pooled) returns its output in buffers from the
+/// blur's [`PlanePool`], which get reused once the result is dropped.
 pub struct Blur {
     width: usize,
     height: usize,
     impl_type: SimdImpl,
+    kernel: BlurKernel,
+    parallelism: Parallelism,
+    // Scratch per concurrently blurred plane. Sequential blurs only use the
+    // first; the others are created the first time planes run in parallel.
+    planes: Vec<PlaneBlur>,
+    // Output buffers of `blur_pooled`
+    pool: PlanePool,
+}
+
+/// Settings a single plane blur needs from its [Blur]
+#[derive(Clone, Copy)]
+struct PlaneParams<'a> {
+    width: usize,
+    height: usize,
+    impl_type: SimdImpl,
+    kernel: BlurKernel,
+    parallelism: &'a Parallelism,
+}
+
+/// Temporary buffers for blurring one plane
+struct PlaneBlur {
     // Scalar backend
     scalar_kernel: RecursiveGaussian,
     scalar_temp: Vec<f32>,
     // Safe SIMD backend
+    #[cfg(feature = "simd")]
     simd: SimdGaussian,
     // Unsafe SIMD backend
     #[cfg(feature = "unsafe-simd")]
     unsafe_simd: UnsafeSimdGaussian,
+    // Summed-area table approximation
+    #[cfg(feature = "integral-blur")]
+    integral: SummedAreaBlur,
 }
 
 impl Blur {
@@ -45,14 +107,21 @@ impl Blur {
             width,
             height,
             impl_type,
-            scalar_kernel: RecursiveGaussian,
-            scalar_temp: vec![0.0f32; width * height],
-            simd: SimdGaussian::new(width),
-            #[cfg(feature = "unsafe-simd")]
-            unsafe_simd: UnsafeSimdGaussian::new(width),
+            kernel: BlurKernel::default(),
+            parallelism: Parallelism::default(),
+            planes: vec![PlaneBlur::new(width, height)],
+            pool: PlanePool::new(),
         }
     }
 
+    /// Create a new [Blur] with the implementation and kernel from `config`.
+    pub(crate) fn with_config(width: usize, height: usize, config: &Ssimulacra2Config) -> Self {
+        let mut blur = Self::with_simd_impl(width, height, config.backend_for(Stage::Blur));
+        blur.kernel = config.blur_kernel;
+        blur.parallelism = config.parallelism.clone();
+        blur
+    }
+
     /// Get the current implementation type.
     pub fn impl_type(&self) -> SimdImpl {
         self.impl_type
@@ -63,66 +132,226 @@ impl Blur {
         self.impl_type = impl_type;
     }
 
-    /// Truncates the internal buffers to fit images of the given width and height.
-    pub fn shrink_to(&mut self, width: usize, height: usize) {
-        self.scalar_temp.truncate(width * height);
-        self.simd.shrink_to(width, height);
-        #[cfg(feature = "unsafe-simd")]
-        self.unsafe_simd.shrink_to(width, height);
+    /// Get the current blur kernel.
+    pub fn kernel(&self) -> BlurKernel {
+        self.kernel
+    }
+
+    /// Set the blur kernel. A kernel that isn't
+    /// [available](BlurKernel::is_available) in this build blurs with the
+    /// recursive Gaussian.
+    pub fn set_kernel(&mut self, kernel: BlurKernel) {
+        self.kernel = kernel;
+    }
+
+    /// Get the current threading setting.
+    pub fn parallelism(&self) -> &Parallelism {
+        &self.parallelism
+    }
+
+    /// Set the threading setting (see [`Parallelism`]).
+    pub fn set_parallelism(&mut self, parallelism: Parallelism) {
+        self.parallelism = parallelism;
+    }
+
+    /// Widest column chunk the SIMD backend's vertical pass processes at once.
+    pub fn max_chunk_columns(&self) -> usize {
+        #[cfg(feature = "simd")]
+        return self.planes[0].simd.max_chunk_columns();
+        #[cfg(not(feature = "simd"))]
+        DEFAULT_MAX_CHUNK_COLUMNS
+    }
+
+    /// Caps the column chunk width of the SIMD backend's vertical pass,
+    /// rounded down to a power of two between 4 and 128.
+    ///
+    /// Each row is split into the widest chunks that fit, down to 4 columns.
+    /// Wider chunks amortize the per-row loop but keep more filter state hot;
+    /// on CPUs with small L1 caches a lower cap can be faster for very wide
+    /// images. Results are identical for every setting. Without the `simd`
+    /// feature there is no SIMD vertical pass and the cap is ignored.
+    pub fn set_max_chunk_columns(&mut self, columns: usize) {
+        #[cfg(feature = "simd")]
+        for plane in &mut self.planes {
+            plane.simd.set_max_chunk_columns(columns);
+        }
+        #[cfg(not(feature = "simd"))]
+        let _ = columns;
+    }
+
+    /// Sets the image size for subsequent blurs, growing the internal
+    /// buffers if needed.
+    ///
+    /// A [Blur] can be reused for any sequence of sizes, larger or smaller
+    /// than the one it was created with.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        for plane in &mut self.planes {
+            plane.resize(width, height);
+        }
         self.width = width;
         self.height = height;
     }
 
+    /// Sets the image size for subsequent blurs. Same as
+    /// [`resize`](Self::resize), which also grows buffers despite the name.
+    pub fn shrink_to(&mut self, width: usize, height: usize) {
+        self.resize(width, height);
+    }
+
     /// Blur the given image using the selected implementation.
     pub fn blur(&mut self, img: &[Vec<f32>; 3]) -> [Vec<f32>; 3] {
-        [
-            self.blur_plane(&img[0]),
-            self.blur_plane(&img[1]),
-            self.blur_plane(&img[2]),
-        ]
+        let mut out: [Vec<f32>; 3] = Default::default();
+        for plane in &mut out {
+            plane.resize(self.width * self.height, 0.0);
+        }
+        self.blur_into(img, &mut out);
+        out
     }
 
     /// Blur the given image into pre-allocated output buffers (zero-allocation).
     pub fn blur_into(&mut self, img: &[Vec<f32>; 3], out: &mut [Vec<f32>; 3]) {
-        self.blur_plane_into(&img[0], &mut out[0]);
-        self.blur_plane_into(&img[1], &mut out[1]);
-        self.blur_plane_into(&img[2], &mut out[2]);
+        let parallel = self.parallelism.is_parallel_for(self.width * self.height);
+        if parallel {
+            self.ensure_plane_scratch(3);
+        }
+        let params = PlaneParams {
+            width: self.width,
+            height: self.height,
+            impl_type: self.impl_type,
+            kernel: self.kernel,
+            parallelism: &self.parallelism,
+        };
+        if parallel {
+            let [first, second, third] = &mut self.planes[..3] else {
+                unreachable!("scratch for three planes was just created");
+            };
+            let [out0, out1, out2] = out;
+            parallelism::join3(
+                params.parallelism,
+                || first.blur_into(params, &img[0], out0),
+                || second.blur_into(params, &img[1], out1),
+                || third.blur_into(params, &img[2], out2),
+            );
+            return;
+        }
+
+        let plane = &mut self.planes[0];
+        for (img, out) in img.iter().zip(out.iter_mut()) {
+            plane.blur_into(params, img, out);
+        }
     }
 
-    fn blur_plane(&mut self, plane: &[f32]) -> Vec<f32> {
-        let mut out = vec![0f32; self.width * self.height];
-        self.blur_plane_into(plane, &mut out);
+    /// Blur the given image into planes checked out of
+    /// [`pool`](Self::pool), which are returned to it when the result is
+    /// dropped. After the first few calls, chains of blurs no longer
+    /// allocate.
+    pub fn blur_pooled(&mut self, img: &[Vec<f32>; 3]) -> PlaneSet {
+        let mut out = self.pool.checkout(self.width, self.height);
+        self.blur_into(img, &mut out);
         out
     }
 
-    fn blur_plane_into(&mut self, plane: &[f32], out: &mut [f32]) {
-        match self.impl_type {
-            SimdImpl::Scalar => self.blur_plane_scalar_into(plane, out),
-            SimdImpl::Simd => self.blur_plane_simd_into(plane, out),
+    /// The pool [`blur_pooled`](Self::blur_pooled) checks its output out
+    /// of. Other stages of a chain can share it by checking out their own
+    /// planes, e.g. for the products of planes that are blurred next.
+    pub fn pool(&self) -> &PlanePool {
+        &self.pool
+    }
+
+    /// Blurs a single plane, e.g. when only one channel is compared
+    pub(crate) fn blur_plane_into(&mut self, plane: &[f32], out: &mut [f32]) {
+        let params = PlaneParams {
+            width: self.width,
+            height: self.height,
+            impl_type: self.impl_type,
+            kernel: self.kernel,
+            parallelism: &self.parallelism,
+        };
+        self.planes[0].blur_into(params, plane, out);
+    }
+
+    /// Creates scratch until `count` planes can be blurred at once
+    fn ensure_plane_scratch(&mut self, count: usize) {
+        #[cfg(feature = "simd")]
+        let max_chunk_columns = self.max_chunk_columns();
+        while self.planes.len() < count {
+            #[allow(unused_mut)]
+            let mut plane = PlaneBlur::new(self.width, self.height);
+            #[cfg(feature = "simd")]
+            plane.simd.set_max_chunk_columns(max_chunk_columns);
+            self.planes.push(plane);
+        }
+    }
+}
+
+impl PlaneBlur {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            scalar_kernel: RecursiveGaussian,
+            scalar_temp: vec![0.0f32; width * height],
+            #[cfg(feature = "simd")]
+            simd: SimdGaussian::new(width),
             #[cfg(feature = "unsafe-simd")]
-            SimdImpl::UnsafeSimd => self.blur_plane_unsafe_simd_into(plane, out),
+            unsafe_simd: UnsafeSimdGaussian::new(width, height),
+            #[cfg(feature = "integral-blur")]
+            integral: SummedAreaBlur::new(width, height),
         }
     }
 
-    fn blur_plane_scalar_into(&mut self, plane: &[f32], out: &mut [f32]) {
-        self.scalar_kernel
-            .horizontal_pass(plane, &mut self.scalar_temp, self.width);
-        self.scalar_kernel.vertical_pass_chunked::<128, 32>(
-            &self.scalar_temp,
-            out,
-            self.width,
-            self.height,
-        );
+    fn resize(&mut self, width: usize, height: usize) {
+        self.scalar_temp.resize(width * height, 0.0);
+        #[cfg(feature = "simd")]
+        self.simd.resize(width, height);
+        #[cfg(feature = "unsafe-simd")]
+        self.unsafe_simd.resize(width, height);
+        #[cfg(feature = "integral-blur")]
+        self.integral.resize(width, height);
     }
 
-    fn blur_plane_simd_into(&mut self, plane: &[f32], out: &mut [f32]) {
-        self.simd
-            .blur_single_plane_into(plane, out, self.width, self.height);
+    fn blur_into(&mut self, params: PlaneParams<'_>, plane: &[f32], out: &mut [f32]) {
+        #[cfg(feature = "integral-blur")]
+        if params.kernel == BlurKernel::SummedArea {
+            self.integral
+                .blur_single_plane_into(plane, out, params.width, params.height);
+            return;
+        }
+        #[cfg(not(feature = "integral-blur"))]
+        let _ = params.kernel;
+
+        match params.impl_type {
+            SimdImpl::Scalar => self.blur_scalar_into(params, plane, out),
+            #[cfg(feature = "simd")]
+            SimdImpl::Simd => self.simd.blur_single_plane_into(
+                plane,
+                out,
+                params.width,
+                params.height,
+                params.parallelism,
+            ),
+            #[cfg(feature = "unsafe-simd")]
+            SimdImpl::UnsafeSimd => {
+                self.unsafe_simd
+                    .blur_single_plane_into(plane, out, params.width, params.height);
+            }
+            // Without the `simd` feature, `Simd` runs the scalar blur
+            #[cfg(not(feature = "simd"))]
+            SimdImpl::Simd => self.blur_scalar_into(params, plane, out),
+        }
     }
 
-    #[cfg(feature = "unsafe-simd")]
-    fn blur_plane_unsafe_simd_into(&mut self, plane: &[f32], out: &mut [f32]) {
-        self.unsafe_simd
-            .blur_single_plane_into(plane, out, self.width, self.height);
+    fn blur_scalar_into(&mut self, params: PlaneParams<'_>, plane: &[f32], out: &mut [f32]) {
+        self.scalar_temp.resize(params.width * params.height, 0.0);
+        self.scalar_kernel.horizontal_pass(
+            plane,
+            &mut self.scalar_temp,
+            params.width,
+            params.parallelism,
+        );
+        self.scalar_kernel.vertical_pass_chunked::<128, 32>(
+            &self.scalar_temp,
+            out,
+            params.width,
+            params.height,
+        );
     }
 }
@@ -0,0 +1,160 @@
+//! Recycled plane buffers for chains of blurs.
+//!
+//! [`Blur::blur`](super::Blur::blur) allocates its output, and
+//! [`Blur::blur_into`](super::Blur::blur_into) needs the caller to keep
+//! output buffers around. A [`PlanePool`] sits in between: every
+//! [`PlaneSet`] checked out of it returns its buffers when dropped, so a
+//! chain such as multiply, blur, map can hand intermediate results along by
+//! value and still stop allocating once the pool has warmed up.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+/// Buffers returned to a pool, shared by its handles and checked out sets
+type FreeList = Arc<Mutex<Vec<[Vec<f32>; 3]>>>;
+
+/// A shared pool of three-plane buffers.
+///
+/// Cloning the pool gives another handle to the same buffers. Checked out
+/// [`PlaneSet`]s keep the pool alive, so sets may outlive every handle.
+#[derive(Debug, Clone, Default)]
+pub struct PlanePool {
+    free: FreeList,
+}
+
+impl PlanePool {
+    /// Creates an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks out three planes of `width * height` samples, reusing returned
+    /// buffers where there are any.
+    ///
+    /// The samples of reused buffers are left as they were, so callers must
+    /// overwrite them, as [`Blur::blur_pooled`](super::Blur::blur_pooled)
+    /// does.
+    pub fn checkout(&self, width: usize, height: usize) -> PlaneSet {
+        let planes = self.lock().pop().unwrap_or_default();
+        let mut set = PlaneSet {
+            planes,
+            width,
+            height,
+            pool: Some(Arc::clone(&self.free)),
+        };
+        for plane in &mut set.planes {
+            plane.resize(width * height, 0.0);
+        }
+        set
+    }
+
+    /// Number of plane sets waiting to be reused
+    pub fn available(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<[Vec<f32>; 3]>> {
+        self.free.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Three planes checked out of a [`PlanePool`], returned to it on drop.
+///
+/// Dereferences to the planes, so it can be passed wherever
+/// `&[Vec<f32>; 3]` is expected, including back into a blur.
+#[derive(Debug)]
+pub struct PlaneSet {
+    planes: [Vec<f32>; 3],
+    width: usize,
+    height: usize,
+    /// `None` once the planes were taken out of the set
+    pool: Option<FreeList>,
+}
+
+impl PlaneSet {
+    /// Width of each plane
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of each plane
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Takes the planes out of the set; they are not returned to the pool.
+    pub fn into_planes(mut self) -> [Vec<f32>; 3] {
+        self.pool = None;
+        std::mem::take(&mut self.planes)
+    }
+}
+
+impl Deref for PlaneSet {
+    type Target = [Vec<f32>; 3];
+
+    fn deref(&self) -> &Self::Target {
+        &self.planes
+    }
+}
+
+impl DerefMut for PlaneSet {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.planes
+    }
+}
+
+impl Drop for PlaneSet {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            let planes = std::mem::take(&mut self.planes);
+            pool.lock().unwrap_or_else(|e| e.into_inner()).push(planes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Blur;
+
+    #[test]
+    fn test_sets_are_recycled() {
+        let pool = PlanePool::new();
+        let set = pool.checkout(4, 3);
+        assert_eq!((set.width(), set.height(), set[2].len()), (4, 3, 12));
+        let ptr = set[0].as_ptr();
+        drop(set);
+        assert_eq!(pool.available(), 1);
+
+        // The buffers come back, grown to the new size where needed
+        let set = pool.clone().checkout(2, 2);
+        assert_eq!(set[0].as_ptr(), ptr);
+        assert_eq!(set[1].len(), 4);
+        assert_eq!(pool.available(), 0);
+        let planes = set.into_planes();
+        assert_eq!(planes[0].len(), 4);
+        assert_eq!(pool.available(), 0);
+    }
+
+    #[test]
+    fn test_pooled_blur_matches_blur() {
+        let (width, height) = (37, 23);
+        let image: [Vec<f32>; 3] = std::array::from_fn(|c| {
+            (0..width * height)
+                .map(|i| ((i * (c + 3)) % 17) as f32 / 16.0)
+                .collect()
+        });
+        let mut blur = Blur::new(width, height);
+        let expected = blur.blur(&image);
+        let twice = blur.blur(&expected);
+
+        let pool = blur.pool().clone();
+        let once = blur.blur_pooled(&image);
+        assert_eq!(*once, expected);
+        // Chained: the first result feeds the second and goes back to the pool
+        let chained = blur.blur_pooled(&once);
+        drop(once);
+        assert_eq!(*chained, twice);
+        assert_eq!(pool.available(), 1);
+    }
+}
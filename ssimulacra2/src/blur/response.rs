@@ -0,0 +1,127 @@
+//! Impulse response of the compiled recursive Gaussian.
+//!
+//! The filter constants are derived in `build.rs`. [`verify_kernel`] measures
+//! what they actually do, so tests and users can check that a build (or a
+//! future change to the derivation) still blurs like the reference σ = 1.5
+//! Gaussian.
+
+use super::gaussian::{consts, RecursiveGaussian};
+use crate::Parallelism;
+
+/// Impulse response of one pass of the blur.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelResponse {
+    /// Output for a unit impulse at offset `0`, for offsets
+    /// `-(taps.len() / 2)..=taps.len() / 2`
+    pub taps: Vec<f64>,
+    /// Sum of the taps (1.0 for a normalized filter)
+    pub sum: f64,
+    /// Centroid of the response in samples (0.0 for a symmetric filter)
+    pub center: f64,
+    /// Standard deviation of the response, fitted from its second moment
+    pub sigma: f64,
+}
+
+impl KernelResponse {
+    fn from_taps(taps: Vec<f32>) -> Self {
+        let taps: Vec<f64> = taps.into_iter().map(f64::from).collect();
+        let offset = |i: usize| i as f64 - (taps.len() / 2) as f64;
+        let sum: f64 = taps.iter().sum();
+        let center = taps
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| t * offset(i))
+            .sum::<f64>()
+            / sum;
+        let variance = taps
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| t * (offset(i) - center).powi(2))
+            .sum::<f64>()
+            / sum;
+        Self {
+            taps,
+            sum,
+            center,
+            sigma: variance.sqrt(),
+        }
+    }
+}
+
+/// Measured behavior of the compiled blur constants.
+///
+/// The horizontal and vertical passes use separately rounded constants
+/// (f64 accumulation horizontally, f32 vertically), so both are reported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelVerification {
+    /// Support radius `N` the filter was derived for
+    pub radius: usize,
+    /// Response of the horizontal pass
+    pub horizontal: KernelResponse,
+    /// Response of the vertical pass
+    pub vertical: KernelResponse,
+}
+
+impl KernelVerification {
+    /// Sigma of the Gaussian the SSIMULACRA2 reference blurs with
+    pub const REFERENCE_SIGMA: f64 = 1.5;
+
+    /// Returns true if both passes are normalized and centered within
+    /// `tolerance`, and their sigma is within `tolerance` of
+    /// [`REFERENCE_SIGMA`](Self::REFERENCE_SIGMA).
+    pub fn matches_reference(&self, tolerance: f64) -> bool {
+        [&self.horizontal, &self.vertical].iter().all(|pass| {
+            (pass.sum - 1.0).abs() <= tolerance
+                && pass.center.abs() <= tolerance
+                && (pass.sigma - Self::REFERENCE_SIGMA).abs() <= tolerance
+        })
+    }
+}
+
+/// Measures the impulse response and fitted sigma of the compiled recursive
+/// Gaussian constants, using the scalar passes every backend is tested
+/// against.
+pub fn verify_kernel() -> KernelVerification {
+    // Wide enough that the response has decayed to zero well before the
+    // zero-padded edges
+    let len = 8 * consts::RADIUS + 1;
+    let mut impulse = vec![0.0f32; len];
+    impulse[len / 2] = 1.0;
+
+    let mut horizontal = vec![0.0f32; len];
+    RecursiveGaussian.horizontal_pass(&impulse, &mut horizontal, len, &Parallelism::Off);
+
+    // A single column, `len` rows tall
+    let mut vertical = vec![0.0f32; len];
+    RecursiveGaussian.vertical_pass::<1>(&impulse, &mut vertical, 1, len);
+
+    KernelVerification {
+        radius: consts::RADIUS,
+        horizontal: KernelResponse::from_taps(horizontal),
+        vertical: KernelResponse::from_taps(vertical),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compiled_kernel_matches_reference_sigma() {
+        let verification = verify_kernel();
+        assert_eq!(verification.radius, 5);
+        for pass in [&verification.horizontal, &verification.vertical] {
+            assert!((pass.sum - 1.0).abs() < 1e-4, "{:?}", pass);
+            assert!(pass.center.abs() < 1e-4, "{:?}", pass);
+            assert!((pass.sigma - 1.5).abs() < 1e-3, "{:?}", pass);
+            // Symmetric, peaked at the impulse
+            let mid = pass.taps.len() / 2;
+            for d in 1..=mid {
+                assert!((pass.taps[mid - d] - pass.taps[mid + d]).abs() < 1e-6);
+                assert!(pass.taps[mid + d] < pass.taps[mid]);
+            }
+        }
+        assert!(verification.matches_reference(1e-3));
+        assert!(!verification.matches_reference(1e-9));
+    }
+}
@@ -11,6 +11,14 @@ mod consts {
 
 use multiversion::multiversion;
 
+use crate::parallelism::{for_each_row, Parallelism};
+
+/// Column chunk widths of the vertical pass, widest first
+pub(crate) const CHUNK_COLUMNS: [usize; 6] = [128, 64, 32, 16, 8, 4];
+
+/// Default cap on the vertical pass chunk width
+pub const DEFAULT_MAX_CHUNK_COLUMNS: usize = CHUNK_COLUMNS[0];
+
 pub struct SimdGaussian {
     // Pre-allocated temp buffer for horizontal pass output (avoids allocations)
     temp_buffer: Vec<f32>,
@@ -18,25 +26,38 @@ pub struct SimdGaussian {
     // Pre-allocated buffers for vertical pass (avoids allocations)
     prev_buffer: Vec<f32>,
     prev2_buffer: Vec<f32>,
-    out_buffer: Vec<f32>,
+    // Widest chunk the vertical pass may use
+    max_chunk_columns: usize,
 }
 
 impl SimdGaussian {
     pub fn new(max_width: usize) -> Self {
         // Pre-allocate for maximum expected image size
         const MAX_HEIGHT: usize = 4096;
-        const MAX_COLUMNS: usize = 128;
+        const MAX_COLUMNS: usize = CHUNK_COLUMNS[0];
         let max_size = max_width * MAX_HEIGHT;
         Self {
             temp_buffer: vec![0.0; max_size],
             max_size,
             prev_buffer: vec![0.0; 3 * MAX_COLUMNS],
             prev2_buffer: vec![0.0; 3 * MAX_COLUMNS],
-            out_buffer: vec![0.0; 3 * MAX_COLUMNS],
+            max_chunk_columns: DEFAULT_MAX_CHUNK_COLUMNS,
         }
     }
 
-    pub fn shrink_to(&mut self, width: usize, height: usize) {
+    pub fn max_chunk_columns(&self) -> usize {
+        self.max_chunk_columns
+    }
+
+    /// Rounds `columns` down to a supported chunk width (at least 4)
+    pub fn set_max_chunk_columns(&mut self, columns: usize) {
+        self.max_chunk_columns = CHUNK_COLUMNS
+            .into_iter()
+            .find(|&c| c <= columns)
+            .unwrap_or(CHUNK_COLUMNS[CHUNK_COLUMNS.len() - 1]);
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
         // Grow temp buffer if needed, never shrink (to avoid realloc)
         let needed = width * height;
         if needed > self.max_size {
@@ -49,7 +70,7 @@ impl SimdGaussian {
     #[allow(dead_code)]
     pub fn blur_single_plane(&mut self, plane: &[f32], width: usize, height: usize) -> Vec<f32> {
         let mut out = vec![0.0; width * height];
-        self.blur_single_plane_into(plane, &mut out, width, height);
+        self.blur_single_plane_into(plane, &mut out, width, height, &Parallelism::default());
         out
     }
 
@@ -60,6 +81,7 @@ impl SimdGaussian {
         out: &mut [f32],
         width: usize,
         height: usize,
+        parallelism: &Parallelism,
     ) {
         let size = width * height;
 
@@ -70,7 +92,7 @@ impl SimdGaussian {
         }
 
         // Horizontal pass - writes to pre-allocated temp buffer
-        Self::horizontal_pass(plane, &mut self.temp_buffer[..size], width);
+        Self::horizontal_pass(plane, &mut self.temp_buffer[..size], width, parallelism);
 
         // Vertical pass with SIMD - pass buffers explicitly to avoid borrow conflicts
         Self::vertical_pass_simd_chunked_with_buffers(
@@ -78,31 +100,82 @@ impl SimdGaussian {
             out,
             width,
             height,
+            self.max_chunk_columns,
             &mut self.prev_buffer,
             &mut self.prev2_buffer,
-            &mut self.out_buffer,
         );
     }
 
     /// Horizontal pass - same as baseline (IIR is inherently sequential)
-    fn horizontal_pass(input: &[f32], output: &mut [f32], width: usize) {
-        assert_eq!(input.len(), output.len());
-
-        #[cfg(feature = "rayon")]
+    fn horizontal_pass(input: &[f32], output: &mut [f32], width: usize, parallelism: &Parallelism) {
+        #[cfg(target_arch = "wasm32")]
         {
-            use rayon::prelude::*;
-            input
-                .par_chunks_exact(width)
-                .zip(output.par_chunks_exact_mut(width))
-                .for_each(|(input, output)| Self::horizontal_row(input, output, width));
+            // Four rows per pass, then the remaining rows one at a time
+            let split = input.len() - input.len() % (4 * width);
+            let (input, rest_input) = input.split_at(split);
+            let (output, rest_output) = output.split_at_mut(split);
+            for_each_row(parallelism, input, output, 4 * width, |input, output| {
+                Self::horizontal_rows_x4(input, output, width);
+            });
+            for_each_row(
+                parallelism,
+                rest_input,
+                rest_output,
+                width,
+                |input, output| {
+                    Self::horizontal_row(input, output, width);
+                },
+            );
         }
+        #[cfg(not(target_arch = "wasm32"))]
+        for_each_row(parallelism, input, output, width, |input, output| {
+            Self::horizontal_row(input, output, width);
+        });
+    }
 
-        #[cfg(not(feature = "rayon"))]
-        {
-            input
-                .chunks_exact(width)
-                .zip(output.chunks_exact_mut(width))
-                .for_each(|(input, output)| Self::horizontal_row(input, output, width));
+    /// [`horizontal_row`](Self::horizontal_row) on four rows at once, one
+    /// per lane.
+    ///
+    /// `wasm32` has no FMA instruction, so the scalar row's `mul_add` runs
+    /// in software there; with `simd128` this filters four rows for the cost
+    /// of one.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    fn horizontal_rows_x4(input: &[f32], output: &mut [f32], width: usize) {
+        let big_n = consts::RADIUS as isize;
+        let load = |i: isize| {
+            if i >= 0 && (i as usize) < width {
+                f32x4::new(std::array::from_fn(|row| input[row * width + i as usize]))
+            } else {
+                f32x4::splat(0.0)
+            }
+        };
+        let mul_in = [consts::MUL_IN_1, consts::MUL_IN_3, consts::MUL_IN_5].map(f32x4::splat);
+        let mul_prev =
+            [consts::MUL_PREV_1, consts::MUL_PREV_3, consts::MUL_PREV_5].map(f32x4::splat);
+        let mul_prev2 = [
+            consts::MUL_PREV2_1,
+            consts::MUL_PREV2_3,
+            consts::MUL_PREV2_5,
+        ]
+        .map(f32x4::splat);
+
+        let mut prev = [f32x4::splat(0.0); 3];
+        let mut prev2 = [f32x4::splat(0.0); 3];
+        for n in (-big_n + 1)..width as isize {
+            let sum = load(n - big_n - 1) + load(n + big_n - 1);
+            let mut total = f32x4::splat(0.0);
+            for k in 0..3 {
+                let out = mul_prev2[k].mul_add(prev2[k], sum * mul_in[k]);
+                let out = mul_prev[k].mul_add(prev[k], out);
+                prev2[k] = prev[k];
+                prev[k] = out;
+                total += out;
+            }
+            if n >= 0 {
+                for (row, value) in total.to_array().into_iter().enumerate() {
+                    output[row * width + n as usize] = value;
+                }
+            }
         }
     }
 
@@ -162,70 +235,61 @@ impl SimdGaussian {
     }
 
     /// SIMD-optimized vertical pass
-    /// Processes 4 columns at a time using f32x4
+    ///
+    /// Splits the width greedily into the widest chunks in [`CHUNK_COLUMNS`]
+    /// no wider than `max_columns`, so any width leaves at most 3 columns to
+    /// the scalar path and at most one chunk of each narrower size.
+    #[allow(clippy::too_many_arguments)]
     fn vertical_pass_simd_chunked_with_buffers(
         input: &[f32],
         output: &mut [f32],
         width: usize,
         height: usize,
+        max_columns: usize,
         prev_buffer: &mut [f32],
         prev2_buffer: &mut [f32],
-        out_buffer: &mut [f32],
     ) {
         assert_eq!(input.len(), output.len());
 
         let mut x = 0;
-
-        // Process 128 columns at a time (32 SIMD lanes of 4)
-        while x + 128 <= width {
-            Self::vertical_pass_simd::<128>(
-                &input[x..],
-                &mut output[x..],
-                width,
-                height,
-                &mut prev_buffer[..3 * 128],
-                &mut prev2_buffer[..3 * 128],
-                &mut out_buffer[..3 * 128],
-            );
-            x += 128;
-        }
-
-        // Process 32 columns at a time (8 SIMD lanes of 4)
-        while x + 32 <= width {
-            Self::vertical_pass_simd::<32>(
-                &input[x..],
-                &mut output[x..],
-                width,
-                height,
-                &mut prev_buffer[..3 * 32],
-                &mut prev2_buffer[..3 * 32],
-                &mut out_buffer[..3 * 32],
-            );
-            x += 32;
+        for &columns in CHUNK_COLUMNS.iter().filter(|&&c| c <= max_columns) {
+            while x + columns <= width {
+                let (input, output) = (&input[x..], &mut output[x..]);
+                let prev = &mut prev_buffer[..3 * columns];
+                let prev2 = &mut prev2_buffer[..3 * columns];
+                match columns {
+                    128 => {
+                        Self::vertical_pass_simd::<128>(input, output, width, height, prev, prev2)
+                    }
+                    64 => Self::vertical_pass_simd::<64>(input, output, width, height, prev, prev2),
+                    32 => Self::vertical_pass_simd::<32>(input, output, width, height, prev, prev2),
+                    16 => Self::vertical_pass_simd::<16>(input, output, width, height, prev, prev2),
+                    8 => Self::vertical_pass_simd::<8>(input, output, width, height, prev, prev2),
+                    _ => Self::vertical_pass_simd::<4>(input, output, width, height, prev, prev2),
+                }
+                x += columns;
+            }
         }
 
-        // Process 4 columns at a time (1 SIMD lane of 4)
-        while x + 4 <= width {
-            Self::vertical_pass_simd::<4>(
+        // Handle remaining columns with scalar version
+        while x < width {
+            Self::vertical_pass_scalar_static::<1>(
                 &input[x..],
                 &mut output[x..],
                 width,
                 height,
-                &mut prev_buffer[..3 * 4],
-                &mut prev2_buffer[..3 * 4],
-                &mut out_buffer[..3 * 4],
+                &mut prev_buffer[..3],
+                &mut prev2_buffer[..3],
             );
-            x += 4;
-        }
-
-        // Handle remaining columns with scalar version
-        while x < width {
-            Self::vertical_pass_scalar_static::<1>(&input[x..], &mut output[x..], width, height);
             x += 1;
         }
     }
 
     /// SIMD vertical pass - processes COLUMNS columns (must be multiple of 4)
+    ///
+    /// `prev` and `prev2` hold the filter state of each column for the last two
+    /// rows. Columns are independent, so each lane advances its own state in
+    /// place as soon as it has computed a row, with no per-row buffer copies.
     #[inline(always)]
     #[multiversion(targets("x86_64+avx2+fma", "x86_64+sse2", "aarch64+neon"))]
     fn vertical_pass_simd<const COLUMNS: usize>(
@@ -235,7 +299,6 @@ impl SimdGaussian {
         height: usize,
         prev: &mut [f32],
         prev2: &mut [f32],
-        out: &mut [f32],
     ) {
         assert!(
             COLUMNS.is_multiple_of(4),
@@ -244,7 +307,6 @@ impl SimdGaussian {
         assert_eq!(input.len(), output.len());
         assert_eq!(prev.len(), 3 * COLUMNS);
         assert_eq!(prev2.len(), 3 * COLUMNS);
-        assert_eq!(out.len(), 3 * COLUMNS);
 
         let big_n = consts::RADIUS as isize;
         let simd_lanes = COLUMNS / 4;
@@ -252,7 +314,6 @@ impl SimdGaussian {
         // Clear buffers
         prev.fill(0.0);
         prev2.fill(0.0);
-        out.fill(0.0);
 
         let zeroes = f32x4::splat(0.0);
 
@@ -316,14 +377,13 @@ impl SimdGaussian {
                 let out3 = sum.mul_add(mul_in_3, -out3);
                 let out5 = sum.mul_add(mul_in_5, -out5);
 
-                // Store outputs using slice copies
-                let out1_arr = out1.to_array();
-                let out3_arr = out3.to_array();
-                let out5_arr = out5.to_array();
-
-                out[i1..i1 + 4].copy_from_slice(&out1_arr);
-                out[i3..i3 + 4].copy_from_slice(&out3_arr);
-                out[i5..i5 + 4].copy_from_slice(&out5_arr);
+                // Advance this lane's state: prev2 = prev, prev = out
+                prev2[i1..i1 + 4].copy_from_slice(&prev_1_vec.to_array());
+                prev2[i3..i3 + 4].copy_from_slice(&prev_3_vec.to_array());
+                prev2[i5..i5 + 4].copy_from_slice(&prev_5_vec.to_array());
+                prev[i1..i1 + 4].copy_from_slice(&out1.to_array());
+                prev[i3..i3 + 4].copy_from_slice(&out3.to_array());
+                prev[i5..i5 + 4].copy_from_slice(&out5.to_array());
 
                 // Write final output if we're past the padding
                 if n >= 0 {
@@ -334,10 +394,6 @@ impl SimdGaussian {
                 }
             }
 
-            // Swap buffers (prev2 = prev, prev = out)
-            prev2.copy_from_slice(prev);
-            prev.copy_from_slice(out);
-
             n += 1;
         }
     }
@@ -348,16 +404,17 @@ impl SimdGaussian {
         output: &mut [f32],
         width: usize,
         height: usize,
+        prev: &mut [f32],
+        prev2: &mut [f32],
     ) {
         // Same as baseline implementation
         assert_eq!(input.len(), output.len());
 
         let big_n = consts::RADIUS as isize;
 
-        let zeroes = vec![0f32; COLUMNS];
-        let mut prev = vec![0f32; 3 * COLUMNS];
-        let mut prev2 = vec![0f32; 3 * COLUMNS];
-        let mut out = vec![0f32; 3 * COLUMNS];
+        let zeroes = [0f32; COLUMNS];
+        prev.fill(0.0);
+        prev2.fill(0.0);
 
         let mut n = (-big_n) + 1;
         while n < height as isize {
@@ -390,19 +447,159 @@ impl SimdGaussian {
                 let out3 = sum.mul_add(consts::VERT_MUL_IN_3, -out3);
                 let out5 = sum.mul_add(consts::VERT_MUL_IN_5, -out5);
 
-                out[i1] = out1;
-                out[i3] = out3;
-                out[i5] = out5;
+                prev2[i1] = prev[i1];
+                prev2[i3] = prev[i3];
+                prev2[i5] = prev[i5];
+                prev[i1] = out1;
+                prev[i3] = out3;
+                prev[i5] = out5;
 
                 if n >= 0 {
                     output[n as usize * width + i] = out1 + out3 + out5;
                 }
             }
 
-            prev2.copy_from_slice(&prev);
-            prev.copy_from_slice(&out);
-
             n += 1;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The vertical pass as it was before the state moved in place: every
+    /// row writes a separate `out` buffer, then copies `prev` into `prev2`
+    /// and `out` into `prev`. Same arithmetic, so output must be identical.
+    fn copying_vertical_pass(input: &[f32], output: &mut [f32], width: usize, height: usize) {
+        let big_n = consts::RADIUS as isize;
+        let row = |y: isize, x: usize| {
+            if y >= 0 && y < height as isize {
+                input[y as usize * width + x]
+            } else {
+                0.0
+            }
+        };
+        let mul_in = [
+            consts::VERT_MUL_IN_1,
+            consts::VERT_MUL_IN_3,
+            consts::VERT_MUL_IN_5,
+        ];
+        let mul_prev = [
+            consts::VERT_MUL_PREV_1,
+            consts::VERT_MUL_PREV_3,
+            consts::VERT_MUL_PREV_5,
+        ];
+        let simd_width = width - width % 4;
+
+        let mut x = 0;
+        while x < simd_width {
+            let mut prev = [f32x4::splat(0.0); 3];
+            let mut prev2 = [f32x4::splat(0.0); 3];
+            let mut out = [f32x4::splat(0.0); 3];
+            for n in (-big_n + 1)..height as isize {
+                let load = |y: isize| {
+                    if y >= 0 && (y as usize * width + x + 3) < input.len() {
+                        f32x4::new(std::array::from_fn(|i| row(y, x + i)))
+                    } else {
+                        f32x4::splat(0.0)
+                    }
+                };
+                let sum = load(n - big_n - 1) + load(n + big_n - 1);
+                for k in 0..3 {
+                    let o = prev[k].mul_add(f32x4::splat(mul_prev[k]), prev2[k]);
+                    out[k] = sum.mul_add(f32x4::splat(mul_in[k]), -o);
+                }
+                if n >= 0 {
+                    let result = (out[0] + out[1] + out[2]).to_array();
+                    output[n as usize * width + x..][..4].copy_from_slice(&result);
+                }
+                prev2 = prev;
+                prev = out;
+            }
+            x += 4;
+        }
+        for x in simd_width..width {
+            let (mut prev, mut prev2, mut out) = ([0f32; 3], [0f32; 3], [0f32; 3]);
+            for n in (-big_n + 1)..height as isize {
+                let sum = row(n - big_n - 1, x) + row(n + big_n - 1, x);
+                for k in 0..3 {
+                    let o = prev[k].mul_add(mul_prev[k], prev2[k]);
+                    out[k] = sum.mul_add(mul_in[k], -o);
+                }
+                if n >= 0 {
+                    output[n as usize * width + x] = out[0] + out[1] + out[2];
+                }
+                prev2 = prev;
+                prev = out;
+            }
+        }
+    }
+
+    #[test]
+    fn test_four_row_horizontal_pass_matches_rows() {
+        for width in [1, 7, 8, 64, 131] {
+            let input: Vec<f32> = (0..4 * width)
+                .map(|i| ((i * 7_919) % 1_009) as f32 / 1_009.0)
+                .collect();
+            let mut expected = vec![0.0; input.len()];
+            for (input, output) in input
+                .chunks_exact(width)
+                .zip(expected.chunks_exact_mut(width))
+            {
+                SimdGaussian::horizontal_row(input, output, width);
+            }
+            let mut actual = vec![f32::NAN; input.len()];
+            SimdGaussian::horizontal_rows_x4(&input, &mut actual, width);
+            // The rows fuse their multiply-adds; the lanes may not
+            for (i, (a, e)) in actual.iter().zip(&expected).enumerate() {
+                assert!(
+                    (a - e).abs() < 1e-5,
+                    "width {} at {}: {} vs {}",
+                    width,
+                    i,
+                    e,
+                    a
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_in_place_state_matches_copying_pass() {
+        let mut gaussian = SimdGaussian::new(0);
+        for height in [1, 3, 9, 40] {
+            for width in (1..=70).chain([128, 131, 261]) {
+                let input: Vec<f32> = (0..width * height)
+                    .map(|i| ((i * 7_919) % 1_009) as f32 / 1_009.0)
+                    .collect();
+                let mut expected = vec![0.0; input.len()];
+                copying_vertical_pass(&input, &mut expected, width, height);
+
+                for cap in [4, 128] {
+                    gaussian.set_max_chunk_columns(cap);
+                    let mut actual = vec![f32::NAN; input.len()];
+                    SimdGaussian::vertical_pass_simd_chunked_with_buffers(
+                        &input,
+                        &mut actual,
+                        width,
+                        height,
+                        gaussian.max_chunk_columns,
+                        &mut gaussian.prev_buffer,
+                        &mut gaussian.prev2_buffer,
+                    );
+                    assert!(
+                        actual
+                            .iter()
+                            .zip(&expected)
+                            .all(|(a, e)| a.to_bits() == e.to_bits()),
+                        "{}x{} cap {}",
+                        width,
+                        height,
+                        cap
+                    );
+                }
+            }
+        }
+    }
+}
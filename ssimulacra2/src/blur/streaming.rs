@@ -0,0 +1,192 @@
+//! Row-by-row recursive Gaussian for the banded pipeline.
+//!
+//! The vertical pass of the recursive Gaussian only looks [`consts::RADIUS`]
+//! rows ahead and keeps its state per column, so it can stop after any row
+//! and resume later. [`StreamingBlur`] uses that to emit a plane's blurred
+//! rows in bands while holding only a few input rows, with exactly the
+//! arithmetic of the scalar backend's whole-plane blur.
+
+use super::gaussian::{consts, RecursiveGaussian};
+
+/// Horizontally blurred rows the vertical pass reads at once: the rows
+/// `RADIUS + 1` above and `RADIUS - 1` below the output row, and those in
+/// between.
+const RING_ROWS: usize = 2 * consts::RADIUS + 1;
+
+/// Blurs one plane top to bottom, a band of output rows per call.
+pub(crate) struct StreamingBlur {
+    width: usize,
+    height: usize,
+    /// Horizontally blurred input rows; row `y` is kept in slot
+    /// `y % RING_ROWS`
+    ring: Vec<f32>,
+    /// The input row being pulled
+    input: Vec<f32>,
+    /// Stands in for rows outside the image
+    zeros: Vec<f32>,
+    /// The three filter terms of each column at the last two steps, term
+    /// by term
+    prev: Vec<f32>,
+    prev2: Vec<f32>,
+    /// Output row of the next vertical step; negative while the filter
+    /// warms up above the image
+    next: isize,
+}
+
+impl StreamingBlur {
+    pub(crate) fn new() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            ring: Vec::new(),
+            input: Vec::new(),
+            zeros: Vec::new(),
+            prev: Vec::new(),
+            prev2: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Starts a new `width` x `height` plane, keeping the allocations.
+    pub(crate) fn reset(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.ring.resize(RING_ROWS * width, 0.0);
+        self.input.resize(width, 0.0);
+        for buf in [&mut self.zeros, &mut self.prev, &mut self.prev2] {
+            buf.clear();
+        }
+        self.zeros.resize(width, 0.0);
+        self.prev.resize(3 * width, 0.0);
+        self.prev2.resize(3 * width, 0.0);
+        self.next = 1 - consts::RADIUS as isize;
+    }
+
+    /// Writes the next `out.len() / width` blurred rows to `out`.
+    ///
+    /// `input_row(y, row)` fills `row` with input row `y`; rows are pulled
+    /// once each, in order, as the filter reaches them.
+    pub(crate) fn blur_rows<F>(&mut self, out: &mut [f32], mut input_row: F)
+    where
+        F: FnMut(usize, &mut [f32]),
+    {
+        let (width, height) = (self.width, self.height as isize);
+        let radius = consts::RADIUS as isize;
+        let first = self.next.max(0);
+        let end = first + (out.len() / width) as isize;
+        debug_assert!(end <= height, "blurred past the last row");
+
+        while self.next < end {
+            let n = self.next;
+            let (top, bottom) = (n - radius - 1, n + radius - 1);
+            if bottom < height {
+                let bottom = bottom as usize;
+                input_row(bottom, &mut self.input[..]);
+                let slot = &mut self.ring[bottom % RING_ROWS * width..][..width];
+                RecursiveGaussian.horizontal_row(&self.input, slot, width);
+            }
+            let row = |y: isize| {
+                if (0..height).contains(&y) {
+                    &self.ring[y as usize % RING_ROWS * width..][..width]
+                } else {
+                    &self.zeros[..]
+                }
+            };
+            let out_row = (n >= 0).then(|| &mut out[(n - first) as usize * width..][..width]);
+            vertical_step(
+                row(top),
+                row(bottom),
+                &mut self.prev,
+                &mut self.prev2,
+                out_row,
+            );
+            self.next += 1;
+        }
+    }
+}
+
+/// One step of the vertical pass over every column, matching
+/// [`RecursiveGaussian::vertical_pass`] operation for operation.
+#[cfg_attr(
+    feature = "simd",
+    multiversion::multiversion(targets("x86_64+avx2+fma", "x86_64+sse2", "aarch64+neon"))
+)]
+fn vertical_step(
+    top: &[f32],
+    bottom: &[f32],
+    prev: &mut [f32],
+    prev2: &mut [f32],
+    out: Option<&mut [f32]>,
+) {
+    let width = top.len();
+    let (prev1, rest) = prev.split_at_mut(width);
+    let (prev3, prev5) = rest.split_at_mut(width);
+    let (prev2_1, rest) = prev2.split_at_mut(width);
+    let (prev2_3, prev2_5) = rest.split_at_mut(width);
+
+    let mut step = |i: usize| {
+        let sum = top[i] + bottom[i];
+
+        let out1 = prev1[i].mul_add(consts::VERT_MUL_PREV_1, prev2_1[i]);
+        let out3 = prev3[i].mul_add(consts::VERT_MUL_PREV_3, prev2_3[i]);
+        let out5 = prev5[i].mul_add(consts::VERT_MUL_PREV_5, prev2_5[i]);
+
+        let out1 = sum.mul_add(consts::VERT_MUL_IN_1, -out1);
+        let out3 = sum.mul_add(consts::VERT_MUL_IN_3, -out3);
+        let out5 = sum.mul_add(consts::VERT_MUL_IN_5, -out5);
+
+        prev2_1[i] = prev1[i];
+        prev2_3[i] = prev3[i];
+        prev2_5[i] = prev5[i];
+        prev1[i] = out1;
+        prev3[i] = out3;
+        prev5[i] = out5;
+        out1 + out3 + out5
+    };
+    match out {
+        Some(out) => {
+            for (i, out) in out.iter_mut().enumerate() {
+                *out = step(i);
+            }
+        }
+        None => {
+            for i in 0..width {
+                step(i);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Blur, SimdImpl};
+
+    #[test]
+    fn test_bands_match_scalar_blur() {
+        let (width, height) = (37, 53);
+        let plane: Vec<f32> = (0..width * height)
+            .map(|i| ((i * 7919) % 257) as f32 / 257.0)
+            .collect();
+
+        let mut blur = Blur::with_simd_impl(width, height, SimdImpl::Scalar);
+        let expected = blur.blur(&[plane.clone(), plane.clone(), plane.clone()]);
+
+        let mut streaming = StreamingBlur::new();
+        for band_rows in [1, 5, 16, height] {
+            streaming.reset(width, height);
+            let mut blurred = Vec::new();
+            let mut pulled = Vec::new();
+            for start in (0..height).step_by(band_rows) {
+                let mut band = vec![0.0; band_rows.min(height - start) * width];
+                streaming.blur_rows(&mut band, |y, row| {
+                    pulled.push(y);
+                    row.copy_from_slice(&plane[y * width..][..width]);
+                });
+                blurred.extend(band);
+            }
+            assert_eq!(blurred, expected[0], "{} rows per band", band_rows);
+            assert_eq!(pulled, (0..height).collect::<Vec<_>>());
+        }
+    }
+}
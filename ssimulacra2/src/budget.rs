@@ -0,0 +1,249 @@
+//! Best-effort scores within a time budget.
+//!
+//! With [`Ssimulacra2Config::time_budget`] set, a comparison is refined from
+//! coarse to fine. It is first run on the smallest starting resolution the
+//! metric accepts, which is what
+//! [`early_downscale_megapixels`](Ssimulacra2Config::early_downscale_megapixels)
+//! would produce with a tiny limit, then again on each finer start in turn.
+//! Each step has four times the pixels of the one before, so its cost is
+//! predicted from the last step. The next step only runs if it should finish
+//! within the budget. The score is that of the finest step that ran, and
+//! [`BudgetedScore`] reports how far short of full resolution it stopped.
+//!
+//! Together the coarser steps cost about a third of the finest one, plus a
+//! downscale of the inputs each. The coarsest step always runs, so a budget
+//! too small for it is overrun.
+//! Timing needs `std::time::Instant`, which panics on
+//! `wasm32-unknown-unknown`, so there the budget is ignored.
+
+use crate::{Ssimulacra2Error, ToLinearRgb};
+
+/// A score computed within a [`time_budget`](crate::Ssimulacra2Config::time_budget).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BudgetedScore {
+    /// SSIMULACRA2 score of the finest resolution compared in time
+    pub score: f64,
+    /// How many times the inputs were halved before comparing, on top of
+    /// any halvings [`early_downscale_megapixels`](crate::Ssimulacra2Config::early_downscale_megapixels)
+    /// asks for. Zero unless the budget ran out.
+    pub halvings: u32,
+    /// `true` if the budget ran out before the full resolution was compared
+    pub truncated: bool,
+}
+
+/// Computes the SSIMULACRA2 score within `config.time_budget`, reporting
+/// whether it had to settle for a downscaled comparison.
+///
+/// Without a time budget this is the standard score, never truncated. See
+/// the [module documentation](self) for how the budget is spent.
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the images are smaller than 8x8 pixels
+pub fn compute_ssimulacra2_within_budget<S, D>(
+    source: S,
+    distorted: D,
+    config: crate::Ssimulacra2Config,
+) -> Result<BudgetedScore, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    let msssim = crate::compute_linear_msssim(
+        &source.as_linear_rgb(),
+        &distorted.as_linear_rgb(),
+        config,
+        None,
+    )?;
+    Ok(BudgetedScore {
+        score: msssim.score(),
+        halvings: msssim.budget_halvings,
+        truncated: msssim.budget_halvings > 0,
+    })
+}
+
+/// Starting sizes the pyramid can be given: the input size after any
+/// pre-resize, then each halving while both sides are at least 16, the same
+/// limit the early downscale stops at, and the result still fits `pyramid`
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn start_sizes(width: usize, height: usize, pyramid: crate::ScalePyramid) -> Vec<(usize, usize)> {
+    let mut sizes = vec![(width, height)];
+    let (mut width, mut height) = (width, height);
+    while width >= 16 && height >= 16 {
+        (width, height) = (width.div_ceil(2), height.div_ceil(2));
+        if !pyramid.fits(width, height) {
+            break;
+        }
+        sizes.push((width, height));
+    }
+    sizes
+}
+
+/// Runs the comparison in `scratch` coarse to fine until `budget` would run
+/// out, leaving the statistics of the finest step in `scratch.msssim`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn compute_within(
+    img1: &crate::LinearRgbImage,
+    img2: &crate::LinearRgbImage,
+    scratch: &mut crate::Ssimulacra2Scratch,
+    budget: std::time::Duration,
+) -> Result<(), Ssimulacra2Error> {
+    use std::time::Instant;
+
+    let started = Instant::now();
+    let config = scratch.config.clone();
+    let (width, height) = match config.pre_resize {
+        Some(resize) => resize.output_size(img1.width(), img1.height()),
+        None => (img1.width(), img1.height()),
+    };
+    let sizes = start_sizes(width, height, config.pyramid);
+    // Halvings the early downscale would apply anyway: the finest step
+    let finest = config
+        .early_downscale_megapixels
+        .map(|megapixels| {
+            let max_pixels = megapixels * 1_000_000.0;
+            sizes
+                .iter()
+                .position(|&(w, h)| (w * h) as f64 <= max_pixels)
+                .unwrap_or(sizes.len() - 1)
+        })
+        .unwrap_or(0);
+
+    let mut step = config.clone();
+    step.time_budget = None;
+    // Only the score that is kept is recorded
+    step.monitor_label = None;
+    let mut result = Ok(());
+    for halvings in (finest..sizes.len()).rev() {
+        let step_started = Instant::now();
+        let (w, h) = sizes[halvings];
+        // Half a pixel of margin keeps the threshold exact in f64
+        step.early_downscale_megapixels = Some(((w * h) as f64 + 0.5) / 1_000_000.0);
+        scratch.config = step.clone();
+        result = crate::compute_msssim_with_scratch(img1, img2, scratch, None);
+        if result.is_err() {
+            break;
+        }
+        scratch.msssim.budget_halvings = (halvings - finest) as u32;
+
+        // The next step has four times the pixels
+        let predicted = step_started.elapsed() * 4;
+        if started.elapsed() + predicted > budget {
+            break;
+        }
+    }
+    scratch.config = config;
+    result?;
+
+    #[cfg(feature = "monitor")]
+    if let Some(label) = &scratch.config.monitor_label {
+        crate::monitor::ScoreMonitor::global().record(label, scratch.msssim.score());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pattern;
+    use crate::{compute_ssimulacra2_with_config, ScalePyramid, Ssimulacra2Config};
+    use std::time::Duration;
+
+    #[test]
+    fn test_start_sizes() {
+        let standard = ScalePyramid::STANDARD;
+        assert_eq!(start_sizes(70, 33, standard), [(70, 33), (35, 17), (18, 9)]);
+        assert_eq!(start_sizes(15, 100, standard), [(15, 100)]);
+        let pyramid = ScalePyramid::new(3, 20);
+        assert_eq!(start_sizes(70, 44, pyramid), [(70, 44), (35, 22)]);
+    }
+
+    #[test]
+    fn test_generous_budget_matches_full_score() {
+        let (source, distorted) = (pattern(96, 80, 0.0), pattern(96, 80, 0.2));
+        let config = Ssimulacra2Config::default();
+        let full = compute_ssimulacra2_with_config(&source, &distorted, config.clone()).unwrap();
+        let budgeted = compute_ssimulacra2_within_budget(
+            &source,
+            &distorted,
+            config.clone().with_time_budget(Duration::from_secs(3600)),
+        )
+        .unwrap();
+        assert_eq!(
+            budgeted,
+            BudgetedScore {
+                score: full,
+                halvings: 0,
+                truncated: false,
+            }
+        );
+        let unbudgeted = compute_ssimulacra2_within_budget(&source, &distorted, config).unwrap();
+        assert_eq!(unbudgeted.score, full);
+    }
+
+    #[test]
+    fn test_exhausted_budget_returns_coarsest_score() {
+        let (source, distorted) = (pattern(96, 80, 0.0), pattern(96, 80, 0.2));
+        let config = Ssimulacra2Config::default();
+        let budgeted = compute_ssimulacra2_within_budget(
+            &source,
+            &distorted,
+            config.clone().with_time_budget(Duration::ZERO),
+        )
+        .unwrap();
+        // 96x80 halves to 48x40, 24x20 and 12x10
+        assert_eq!(budgeted.halvings, 3);
+        assert!(budgeted.truncated);
+        let coarsest = compute_ssimulacra2_with_config(
+            &source,
+            &distorted,
+            config.clone().with_early_downscale(120.0 / 1_000_000.0),
+        )
+        .unwrap();
+        assert_eq!(budgeted.score, coarsest);
+
+        // The early downscale is the finest step
+        let budgeted = compute_ssimulacra2_within_budget(
+            &source,
+            &distorted,
+            config
+                .clone()
+                .with_early_downscale(0.002)
+                .with_time_budget(Duration::from_secs(3600)),
+        )
+        .unwrap();
+        assert_eq!(budgeted.halvings, 0);
+        assert_eq!(
+            budgeted.score,
+            compute_ssimulacra2_with_config(
+                &source,
+                &distorted,
+                config.with_early_downscale(0.002)
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_exhausted_budget_respects_the_pyramid() {
+        let (source, distorted) = (pattern(96, 80, 0.0), pattern(96, 80, 0.2));
+        let config = Ssimulacra2Config::default().with_pyramid(ScalePyramid::new(4, 32));
+        let budgeted = compute_ssimulacra2_within_budget(
+            &source,
+            &distorted,
+            config.clone().with_time_budget(Duration::ZERO),
+        )
+        .unwrap();
+        // 24x20 is below the pyramid's minimum, so 48x40 is the coarsest step
+        assert_eq!(budgeted.halvings, 1);
+        assert_eq!(
+            budgeted.score,
+            compute_ssimulacra2_with_config(
+                &source,
+                &distorted,
+                config.with_early_downscale(48.0 * 40.0 / 1_000_000.0)
+            )
+            .unwrap()
+        );
+    }
+}
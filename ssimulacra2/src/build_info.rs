@@ -0,0 +1,102 @@
+//! How this copy of the crate was built.
+//!
+//! Scores from two binaries can differ because they were built with
+//! different features, for different CPUs, or against different blur
+//! constants. The constants here are generated by `build.rs`, so a bug
+//! report that includes [`summary`] says exactly which kernels produced the
+//! score.
+
+include!(concat!(env!("OUT_DIR"), "/build_info.rs"));
+
+/// Version of the `fast-ssim2` crate
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The entry of [`MULTIVERSION_TARGETS`] the running CPU supports, i.e. the
+/// one the multiversioned kernels dispatch to. `None` means they run their
+/// generic fallback.
+pub fn runtime_target() -> Option<&'static str> {
+    MULTIVERSION_TARGETS
+        .iter()
+        .copied()
+        .find(|target| target.split('+').skip(1).all(cpu_has))
+}
+
+fn cpu_has(feature: &str) -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        match feature {
+            "avx512f" => is_x86_feature_detected!("avx512f"),
+            "avx2" => is_x86_feature_detected!("avx2"),
+            "fma" => is_x86_feature_detected!("fma"),
+            "sse4.1" => is_x86_feature_detected!("sse4.1"),
+            "sse2" => is_x86_feature_detected!("sse2"),
+            _ => false,
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        match feature {
+            "neon" => std::arch::is_aarch64_feature_detected!("neon"),
+            _ => false,
+        }
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        let _ = feature;
+        false
+    }
+}
+
+/// Multi-line description of the build and the running CPU, for `--version`
+/// output and bug reports.
+pub fn summary() -> String {
+    let list = |items: &[&str]| {
+        if items.is_empty() {
+            "none".to_string()
+        } else {
+            items.join(", ")
+        }
+    };
+    format!(
+        "fast-ssim2 {}\nfeatures: {}\ntarget: {} (opt-level {})\ncompile-time CPU features: {}\nmultiversion targets: {} (running: {})\ngaussian constants: v{} ({:016x})",
+        VERSION,
+        list(FEATURES),
+        TARGET,
+        OPT_LEVEL,
+        list(TARGET_FEATURES),
+        list(MULTIVERSION_TARGETS),
+        runtime_target().unwrap_or("generic"),
+        GAUSSIAN_CONSTANTS_VERSION,
+        GAUSSIAN_CONSTANTS_HASH,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info_matches_this_build() {
+        assert_eq!(FEATURES.contains(&"simd"), cfg!(feature = "simd"));
+        assert_eq!(
+            FEATURES.contains(&"unsafe-simd"),
+            cfg!(feature = "unsafe-simd")
+        );
+        assert!(!FEATURES.contains(&"default"));
+        assert!(TARGET_FEATURES.windows(2).all(|w| w[0] < w[1]));
+
+        #[cfg(not(feature = "simd"))]
+        assert!(MULTIVERSION_TARGETS.is_empty());
+        #[cfg(all(target_arch = "x86_64", feature = "simd"))]
+        {
+            assert_eq!(MULTIVERSION_TARGETS, ["x86_64+avx2+fma", "x86_64+sse2"]);
+            // SSE2 is part of the x86_64 baseline
+            assert!(runtime_target().is_some());
+        }
+        assert_ne!(GAUSSIAN_CONSTANTS_HASH, 0);
+
+        let summary = summary();
+        assert!(summary.starts_with(&format!("fast-ssim2 {}\n", VERSION)));
+        assert!(summary.contains(TARGET));
+    }
+}
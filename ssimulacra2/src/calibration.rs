@@ -0,0 +1,241 @@
+//! Picking backends for the running machine.
+//!
+//! [`SimdImpl::default`] is the safe SIMD backend on every machine, even
+//! where the intrinsics kernels would be faster.
+//! [`Ssimulacra2Config::auto`](crate::Ssimulacra2Config::auto) instead looks
+//! at the CPU's vector extensions, and
+//! [`Ssimulacra2Config::auto_calibrated`](crate::Ssimulacra2Config::auto_calibrated)
+//! also times each available backend on every stage for about a millisecond.
+//! Either decision is made once per process and cached.
+
+use crate::blur::Blur;
+use crate::parallelism::Parallelism;
+use crate::xyb::linear_rgb_to_xyb_data;
+use crate::{
+    edge_diff_map_sums, ssim_map_sums, FallbackChain, NormExponents, SimdImpl, Stage, StageBackends,
+};
+use std::sync::OnceLock;
+
+/// Vector extensions of the running CPU that change the backend choice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct CpuFeatures {
+    /// AVX-512F, which the intrinsics blur and SSIM kernels use when present
+    avx512f: bool,
+    /// AVX2 and FMA, which all x86 intrinsics kernels need
+    avx2_fma: bool,
+    /// NEON, which the aarch64 intrinsics kernels need
+    neon: bool,
+    /// 128-bit vectors the portable SIMD kernels compile to: SSE2 (baseline
+    /// on x86-64), NEON, or WebAssembly SIMD enabled at compile time
+    vectors: bool,
+}
+
+impl CpuFeatures {
+    fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            Self {
+                avx512f: is_x86_feature_detected!("avx512f"),
+                avx2_fma: is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma"),
+                vectors: true,
+                ..Self::default()
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            let neon = std::arch::is_aarch64_feature_detected!("neon");
+            Self {
+                neon,
+                vectors: neon,
+                ..Self::default()
+            }
+        }
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            Self {
+                vectors: cfg!(target_feature = "simd128"),
+                ..Self::default()
+            }
+        }
+    }
+}
+
+/// Backends for `features` in order of expected speed, before any timing
+fn probe(features: CpuFeatures) -> FallbackChain {
+    let intrinsics =
+        cfg!(feature = "unsafe-simd") && (features.avx512f || features.avx2_fma || features.neon);
+    if intrinsics {
+        FallbackChain::default()
+    } else if features.vectors {
+        FallbackChain::new(&[SimdImpl::Simd, SimdImpl::Scalar])
+    } else {
+        // Portable vectors without hardware support are emulated lane by
+        // lane, which is slower than the scalar kernels
+        FallbackChain::new(&[SimdImpl::Scalar, SimdImpl::Simd])
+    }
+}
+
+/// Backends chosen from the CPU features alone, detected once per process
+pub(crate) fn probed() -> StageBackends {
+    static PROBED: OnceLock<StageBackends> = OnceLock::new();
+    *PROBED.get_or_init(|| StageBackends::all(probe(CpuFeatures::detect())))
+}
+
+/// Backends chosen by timing each stage, measured once per process
+pub(crate) fn calibrated() -> StageBackends {
+    static CALIBRATED: OnceLock<StageBackends> = OnceLock::new();
+    *CALIBRATED.get_or_init(calibrate)
+}
+
+/// `std::time::Instant` panics on `wasm32-unknown-unknown`, so there the
+/// probe stands without timing
+#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
+fn calibrate() -> StageBackends {
+    probed()
+}
+
+#[cfg(not(all(target_arch = "wasm32", not(target_os = "wasi"))))]
+fn calibrate() -> StageBackends {
+    use std::hint::black_box;
+    use std::time::{Duration, Instant};
+
+    /// Side of the square test image each stage is timed on
+    const CALIBRATION_SIZE: usize = 64;
+    /// Total time spent timing stages
+    const CALIBRATION_BUDGET: Duration = Duration::from_millis(1);
+
+    let preferred = probed().xyb;
+    let candidates: Vec<SimdImpl> = preferred
+        .backends()
+        .iter()
+        .copied()
+        .filter(SimdImpl::is_available)
+        .collect();
+    let slice = CALIBRATION_BUDGET / (3 * candidates.len()) as u32;
+
+    let (width, height) = (CALIBRATION_SIZE, CALIBRATION_SIZE);
+    let pixels: Vec<[f32; 3]> = (0..width * height)
+        .map(|i| {
+            let v = ((i * 7919) % 257) as f32 / 257.0;
+            [v, 0.5 * v, 1.0 - v]
+        })
+        .collect();
+    let plane = |channel: usize| pixels.iter().map(|p| p[channel]).collect::<Vec<f32>>();
+    let planes = [plane(0), plane(1), plane(2)];
+    let rows = planes.each_ref().map(Vec::as_slice);
+    let mut blurred = planes.clone();
+    let mut blur = Blur::with_simd_impl(width, height, SimdImpl::Scalar);
+    blur.set_parallelism(Parallelism::Off);
+
+    // Shortest of as many runs as fit in `slice`, at least one
+    let time = |run: &mut dyn FnMut()| {
+        let start = Instant::now();
+        let mut best = Duration::MAX;
+        loop {
+            let run_start = Instant::now();
+            run();
+            best = best.min(run_start.elapsed());
+            if start.elapsed() >= slice {
+                return best;
+            }
+        }
+    };
+    // Fastest candidate first, the rest in probe order; ties keep probe order
+    let mut fastest = |stage: Stage| {
+        let mut timed: Vec<_> = candidates
+            .iter()
+            .map(|&backend| {
+                let elapsed = time(&mut || match stage {
+                    Stage::Xyb => {
+                        black_box(linear_rgb_to_xyb_data(
+                            pixels.clone(),
+                            width,
+                            height,
+                            backend,
+                            &Parallelism::Off,
+                        ));
+                    }
+                    Stage::Blur => {
+                        blur.set_impl(backend);
+                        blur.blur_into(&planes, &mut blurred);
+                        black_box(&blurred);
+                    }
+                    Stage::Ssim => {
+                        black_box(ssim_map_sums(
+                            width,
+                            rows,
+                            rows,
+                            rows,
+                            rows,
+                            rows,
+                            backend,
+                            NormExponents::STANDARD,
+                        ));
+                        black_box(edge_diff_map_sums(
+                            width,
+                            rows,
+                            rows,
+                            rows,
+                            rows,
+                            backend,
+                            NormExponents::STANDARD,
+                        ));
+                    }
+                });
+                (backend, elapsed)
+            })
+            .collect();
+        timed.sort_by_key(|&(_, elapsed)| elapsed);
+        let mut order: Vec<SimdImpl> = timed.into_iter().map(|(backend, _)| backend).collect();
+        order.extend_from_slice(preferred.backends());
+        FallbackChain::new(&order)
+    };
+
+    StageBackends {
+        xyb: fastest(Stage::Xyb),
+        blur: fastest(Stage::Blur),
+        ssim: fastest(Stage::Ssim),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_prefers_hardware_vectors() {
+        let scalar = CpuFeatures::default();
+        assert_eq!(probe(scalar).backends()[0], SimdImpl::Scalar);
+
+        let vectors = CpuFeatures {
+            vectors: true,
+            ..scalar
+        };
+        assert_eq!(probe(vectors).backends()[0], SimdImpl::Simd);
+
+        let avx2 = CpuFeatures {
+            avx2_fma: true,
+            ..vectors
+        };
+        let neon = CpuFeatures {
+            neon: true,
+            ..vectors
+        };
+        for intrinsics in [avx2, neon] {
+            #[cfg(feature = "unsafe-simd")]
+            assert_eq!(probe(intrinsics).backends()[0], SimdImpl::UnsafeSimd);
+            #[cfg(not(feature = "unsafe-simd"))]
+            assert_eq!(probe(intrinsics).backends()[0], SimdImpl::Simd);
+        }
+    }
+
+    #[test]
+    fn test_selection_is_cached_and_available() {
+        for backends in [probed(), calibrated()] {
+            for stage in [Stage::Xyb, Stage::Blur, Stage::Ssim] {
+                assert!(backends.resolve(stage).is_available());
+            }
+        }
+        assert_eq!(calibrated(), calibrated());
+    }
+}
@@ -0,0 +1,331 @@
+//! C ABI for use from C, C++ and other languages (requires the `capi`
+//! feature).
+//!
+//! The declarations are in `include/ssimulacra2.h`. Build a shared or static
+//! library with
+//!
+//! ```text
+//! cargo rustc --release -p fast-ssim2 --features capi --crate-type cdylib
+//! cargo rustc --release -p fast-ssim2 --features capi --crate-type staticlib
+//! ```
+//!
+//! Images are passed as packed 8-bit sRGB (`RGBRGB...`) rows, `stride`
+//! bytes apart. Every function returns one of the `SSIMULACRA2_*` status
+//! codes and writes its result through an out pointer, which is left
+//! untouched on failure. Panics are caught at the boundary and reported as
+//! [`SSIMULACRA2_ERROR_INTERNAL`].
+
+use std::ffi::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{compute_ssimulacra2, LinearRgbImage, Ssimulacra2Error, Ssimulacra2Reference};
+
+/// Success
+pub const SSIMULACRA2_OK: c_int = 0;
+/// A required pointer argument was null
+pub const SSIMULACRA2_ERROR_NULL_POINTER: c_int = -1;
+/// `stride` is smaller than `width * 3`, or the image size overflows
+pub const SSIMULACRA2_ERROR_INVALID_STRIDE: c_int = -2;
+/// The image is smaller than 8x8 pixels
+pub const SSIMULACRA2_ERROR_IMAGE_TOO_SMALL: c_int = -3;
+/// The distorted image doesn't have the reference's dimensions
+pub const SSIMULACRA2_ERROR_DIMENSION_MISMATCH: c_int = -4;
+/// Any other failure, including a caught panic
+pub const SSIMULACRA2_ERROR_INTERNAL: c_int = -5;
+
+fn error_code(error: Ssimulacra2Error) -> c_int {
+    match error {
+        Ssimulacra2Error::InvalidImageSize => SSIMULACRA2_ERROR_IMAGE_TOO_SMALL,
+        Ssimulacra2Error::NonMatchingImageDimensions => SSIMULACRA2_ERROR_DIMENSION_MISMATCH,
+        _ => SSIMULACRA2_ERROR_INTERNAL,
+    }
+}
+
+/// Runs `f`, turning panics into [`SSIMULACRA2_ERROR_INTERNAL`]
+fn guard(f: impl FnOnce() -> Result<(), c_int>) -> c_int {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => SSIMULACRA2_OK,
+        Ok(Err(code)) => code,
+        Err(_) => SSIMULACRA2_ERROR_INTERNAL,
+    }
+}
+
+/// Copies a strided 8-bit sRGB image into linear RGB.
+///
+/// # Safety
+/// `rgb` must be null or point to `stride * (height - 1) + width * 3`
+/// readable bytes.
+unsafe fn read_srgb(
+    rgb: *const u8,
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> Result<LinearRgbImage, c_int> {
+    if rgb.is_null() {
+        return Err(SSIMULACRA2_ERROR_NULL_POINTER);
+    }
+    let row_bytes = width
+        .checked_mul(3)
+        .filter(|&row_bytes| row_bytes <= stride)
+        .ok_or(SSIMULACRA2_ERROR_INVALID_STRIDE)?;
+    if height == 0 || width == 0 {
+        return Err(SSIMULACRA2_ERROR_IMAGE_TOO_SMALL);
+    }
+    let len = stride
+        .checked_mul(height - 1)
+        .and_then(|len| len.checked_add(row_bytes))
+        .filter(|&len| len <= isize::MAX as usize)
+        .ok_or(SSIMULACRA2_ERROR_INVALID_STRIDE)?;
+
+    // SAFETY: the caller guarantees `len` readable bytes at `rgb`
+    let bytes = unsafe { std::slice::from_raw_parts(rgb, len) };
+    LinearRgbImage::from_srgb_u8_strided(bytes, width, height, stride)
+        .map_err(|_| SSIMULACRA2_ERROR_INVALID_STRIDE)
+}
+
+/// Computes the SSIMULACRA2 score of `rgb2` against `rgb1`.
+///
+/// Both images are `width` x `height` packed 8-bit sRGB with rows `stride`
+/// bytes apart.
+///
+/// # Safety
+/// `rgb1` and `rgb2` must each point to `stride * (height - 1) + width * 3`
+/// readable bytes, and `out_score` must be valid for writes. Null pointers
+/// are reported as [`SSIMULACRA2_ERROR_NULL_POINTER`].
+#[no_mangle]
+pub unsafe extern "C" fn ssimulacra2_compute(
+    rgb1: *const u8,
+    rgb2: *const u8,
+    width: usize,
+    height: usize,
+    stride: usize,
+    out_score: *mut f64,
+) -> c_int {
+    guard(|| {
+        if out_score.is_null() {
+            return Err(SSIMULACRA2_ERROR_NULL_POINTER);
+        }
+        // SAFETY: guaranteed by the caller
+        let (source, distorted) = unsafe {
+            (
+                read_srgb(rgb1, width, height, stride)?,
+                read_srgb(rgb2, width, height, stride)?,
+            )
+        };
+        let score = compute_ssimulacra2(&source, &distorted).map_err(error_code)?;
+        // SAFETY: checked for null above; validity guaranteed by the caller
+        unsafe { out_score.write(score) };
+        Ok(())
+    })
+}
+
+/// Precomputes the reference-side data of `rgb` for repeated comparisons.
+///
+/// On success `*out_reference` receives a handle that must be released with
+/// [`ssimulacra2_reference_free`].
+///
+/// # Safety
+/// `rgb` must point to `stride * (height - 1) + width * 3` readable bytes,
+/// and `out_reference` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ssimulacra2_reference_new(
+    rgb: *const u8,
+    width: usize,
+    height: usize,
+    stride: usize,
+    out_reference: *mut *mut Ssimulacra2Reference,
+) -> c_int {
+    guard(|| {
+        if out_reference.is_null() {
+            return Err(SSIMULACRA2_ERROR_NULL_POINTER);
+        }
+        // SAFETY: guaranteed by the caller
+        let source = unsafe { read_srgb(rgb, width, height, stride)? };
+        let reference = Ssimulacra2Reference::new(source).map_err(error_code)?;
+        // SAFETY: checked for null above; validity guaranteed by the caller
+        unsafe { out_reference.write(Box::into_raw(Box::new(reference))) };
+        Ok(())
+    })
+}
+
+/// Computes the SSIMULACRA2 score of `rgb` against a precomputed reference.
+///
+/// The image must have the reference's dimensions. A reference may be used
+/// from several threads at once.
+///
+/// # Safety
+/// `reference` must be a live handle from [`ssimulacra2_reference_new`],
+/// `rgb` must point to `stride * (height - 1) + width * 3` readable bytes,
+/// and `out_score` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ssimulacra2_reference_compare(
+    reference: *const Ssimulacra2Reference,
+    rgb: *const u8,
+    width: usize,
+    height: usize,
+    stride: usize,
+    out_score: *mut f64,
+) -> c_int {
+    guard(|| {
+        if out_score.is_null() {
+            return Err(SSIMULACRA2_ERROR_NULL_POINTER);
+        }
+        // SAFETY: a live handle is a valid, shared `Ssimulacra2Reference`
+        let reference = unsafe { reference.as_ref() }.ok_or(SSIMULACRA2_ERROR_NULL_POINTER)?;
+        // SAFETY: guaranteed by the caller
+        let distorted = unsafe { read_srgb(rgb, width, height, stride)? };
+        let score = reference.compare(&distorted).map_err(error_code)?;
+        // SAFETY: checked for null above; validity guaranteed by the caller
+        unsafe { out_score.write(score) };
+        Ok(())
+    })
+}
+
+/// Releases a reference. Null is ignored.
+///
+/// # Safety
+/// `reference` must be null or a handle from [`ssimulacra2_reference_new`]
+/// that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ssimulacra2_reference_free(reference: *mut Ssimulacra2Reference) {
+    if !reference.is_null() {
+        // SAFETY: the handle came from `Box::into_raw` and is freed only once
+        drop(unsafe { Box::from_raw(reference) });
+    }
+}
+
+/// Static, NUL-terminated description of a status code.
+#[no_mangle]
+pub extern "C" fn ssimulacra2_error_message(code: c_int) -> *const c_char {
+    let message = match code {
+        SSIMULACRA2_OK => c"success",
+        SSIMULACRA2_ERROR_NULL_POINTER => c"a required pointer was null",
+        SSIMULACRA2_ERROR_INVALID_STRIDE => c"stride is smaller than width * 3",
+        SSIMULACRA2_ERROR_IMAGE_TOO_SMALL => c"images must be at least 8x8 pixels",
+        SSIMULACRA2_ERROR_DIMENSION_MISMATCH => c"image dimensions do not match",
+        SSIMULACRA2_ERROR_INTERNAL => c"internal error",
+        _ => c"unknown status code",
+    };
+    message.as_ptr()
+}
+
+/// Static, NUL-terminated version of the library.
+#[no_mangle]
+pub extern "C" fn ssimulacra2_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::ptr;
+
+    /// `width` x `height` gradient with `padding` extra bytes per row
+    fn image(width: usize, height: usize, padding: usize, seed: u8) -> (Vec<u8>, usize) {
+        let stride = width * 3 + padding;
+        let bytes = (0..stride * height)
+            .map(|i| ((i * 7) as u8).wrapping_add(seed))
+            .collect();
+        (bytes, stride)
+    }
+
+    #[test]
+    fn test_compute_matches_rust_api() {
+        let (width, height) = (24, 16);
+        let (a, stride) = image(width, height, 5, 0);
+        let (b, _) = image(width, height, 5, 9);
+        let mut score = f64::NAN;
+        let status = unsafe {
+            ssimulacra2_compute(a.as_ptr(), b.as_ptr(), width, height, stride, &mut score)
+        };
+        assert_eq!(status, SSIMULACRA2_OK);
+
+        let unpad = |bytes: &[u8]| -> Vec<u8> {
+            bytes
+                .chunks_exact(stride)
+                .flat_map(|row| &row[..width * 3])
+                .copied()
+                .collect()
+        };
+        let expected = compute_ssimulacra2(
+            LinearRgbImage::from_srgb_u8(&unpad(&a), width, height).unwrap(),
+            LinearRgbImage::from_srgb_u8(&unpad(&b), width, height).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(score, expected);
+
+        let mut reference = ptr::null_mut();
+        let mut reference_score = f64::NAN;
+        unsafe {
+            assert_eq!(
+                ssimulacra2_reference_new(a.as_ptr(), width, height, stride, &mut reference),
+                SSIMULACRA2_OK
+            );
+            assert_eq!(
+                ssimulacra2_reference_compare(
+                    reference,
+                    b.as_ptr(),
+                    width,
+                    height,
+                    stride,
+                    &mut reference_score
+                ),
+                SSIMULACRA2_OK
+            );
+            ssimulacra2_reference_free(reference);
+        }
+        assert!((reference_score - score).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_errors_leave_outputs_untouched() {
+        let (a, stride) = image(16, 16, 0, 0);
+        let mut score = -1.0;
+        let compute = |rgb2: *const u8, width, height, stride, out: *mut f64| unsafe {
+            ssimulacra2_compute(a.as_ptr(), rgb2, width, height, stride, out)
+        };
+        assert_eq!(
+            compute(ptr::null(), 16, 16, stride, &mut score),
+            SSIMULACRA2_ERROR_NULL_POINTER
+        );
+        assert_eq!(
+            compute(a.as_ptr(), 16, 16, stride, ptr::null_mut()),
+            SSIMULACRA2_ERROR_NULL_POINTER
+        );
+        assert_eq!(
+            compute(a.as_ptr(), 16, 16, 47, &mut score),
+            SSIMULACRA2_ERROR_INVALID_STRIDE
+        );
+        assert_eq!(
+            compute(a.as_ptr(), 4, 4, stride, &mut score),
+            SSIMULACRA2_ERROR_IMAGE_TOO_SMALL
+        );
+        assert_eq!(score, -1.0);
+
+        let mut reference = ptr::null_mut();
+        unsafe {
+            assert_eq!(
+                ssimulacra2_reference_new(a.as_ptr(), 16, 16, stride, &mut reference),
+                SSIMULACRA2_OK
+            );
+            assert_eq!(
+                ssimulacra2_reference_compare(reference, a.as_ptr(), 8, 8, stride, &mut score),
+                SSIMULACRA2_ERROR_DIMENSION_MISMATCH
+            );
+            ssimulacra2_reference_free(reference);
+            ssimulacra2_reference_free(ptr::null_mut());
+        }
+        assert_eq!(score, -1.0);
+    }
+
+    #[test]
+    fn test_static_strings() {
+        let message = unsafe { CStr::from_ptr(ssimulacra2_error_message(SSIMULACRA2_OK)) };
+        assert_eq!(message.to_str().unwrap(), "success");
+        let unknown = unsafe { CStr::from_ptr(ssimulacra2_error_message(42)) };
+        assert_eq!(unknown.to_str().unwrap(), "unknown status code");
+        let version = unsafe { CStr::from_ptr(ssimulacra2_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
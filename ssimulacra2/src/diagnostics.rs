@@ -0,0 +1,121 @@
+//! Per-comparison diagnostics emitted through the `log` crate.
+//!
+//! Enabled by the `log` feature and [`Ssimulacra2Config::log_diagnostics`].
+//! Each comparison logs one debug-level record under the `fast_ssim2` target
+//! with the input dimensions, backend, blur kernel, number of scales used and
+//! the wall-clock time, so slow requests can be correlated with their inputs.
+
+use crate::{ScaleStatistics, Ssimulacra2Config, Stage};
+
+// `Instant::now` panics on wasm32-unknown-unknown, so timing is omitted there
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+type Timer = std::time::Instant;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+type Timer = ();
+
+/// Tracks a comparison from input validation to the final statistics.
+pub(crate) struct Comparison {
+    started: Timer,
+    width: usize,
+    height: usize,
+}
+
+impl Comparison {
+    /// Starts tracking if diagnostics are enabled in `config` and debug
+    /// records for this crate would be emitted.
+    pub(crate) fn start(config: &Ssimulacra2Config, width: usize, height: usize) -> Option<Self> {
+        if !config.log_diagnostics || !log::log_enabled!(target: "fast_ssim2", log::Level::Debug) {
+            return None;
+        }
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        let started = Timer::now();
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        let started = ();
+        Some(Self {
+            started,
+            width,
+            height,
+        })
+    }
+
+    /// Logs the finished comparison. `scaled` is the size the pyramid started
+    /// from, which differs from the input size after an early downscale.
+    pub(crate) fn finish(
+        self,
+        config: &Ssimulacra2Config,
+        scaled: (usize, usize),
+        scales: &[ScaleStatistics],
+    ) {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        let elapsed = format!("{:.3} ms", self.started.elapsed().as_secs_f64() * 1000.0);
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        let elapsed = {
+            let () = self.started;
+            "n/a".to_string()
+        };
+
+        log::debug!(
+            target: "fast_ssim2",
+            "compared {}x{} (pyramid from {}x{}), backend {}, blur {}, {} scales, {}",
+            self.width,
+            self.height,
+            scaled.0,
+            scaled.1,
+            config.backend_for(Stage::Blur).name(),
+            config.blur_kernel.name(),
+            scales.len(),
+            elapsed
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compute_ssimulacra2_with_config, LinearRgbImage, Ssimulacra2Config};
+    use std::sync::Mutex;
+
+    static RECORDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct CaptureLogger;
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == "fast_ssim2"
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                RECORDS.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger;
+
+    #[test]
+    fn test_logs_comparison_when_enabled() {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let image = LinearRgbImage::new(vec![[0.25, 0.5, 0.75]; 48 * 40], 48, 40);
+
+        let config = Ssimulacra2Config::scalar();
+        compute_ssimulacra2_with_config(image.clone(), image.clone(), config.clone()).unwrap();
+        assert!(
+            !RECORDS.lock().unwrap().iter().any(|r| r.contains("48x40")),
+            "diagnostics must be off by default"
+        );
+
+        let config = config.with_diagnostics_logging(true);
+        compute_ssimulacra2_with_config(image.clone(), image, config).unwrap();
+        let records = RECORDS.lock().unwrap();
+        let record = records
+            .iter()
+            .find(|r| r.contains("48x40"))
+            .expect("comparison should be logged");
+        assert!(record.contains("backend scalar"), "{}", record);
+        assert!(record.contains("4 scales"), "{}", record);
+    }
+}
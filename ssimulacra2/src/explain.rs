@@ -0,0 +1,339 @@
+//! Human-readable summaries of detailed scores.
+//!
+//! The score is a weighted sum of three kinds of error per channel and scale:
+//! structural (SSIM) differences, artifacts the distorted image adds (ringing,
+//! blocking, noise) and detail it loses (blurring, smoothing). [`ScoreExplanation`]
+//! splits the points below 100 between those kinds in proportion to their
+//! weighted contribution, and records which scales and channels each comes
+//! from, so tools can say *why* an image scored what it did:
+//!
+//! ```text
+//! quality ~60: strong structural differences concentrated at fine scales in luma; moderate detail loss; negligible added artifacts
+//! ```
+
+use std::fmt;
+
+use crate::{DetailedScore, NUM_SCALES, SCORE_WEIGHTS};
+
+/// Channel names in XYB order
+const CHANNELS: [&str; 3] = ["red-green chroma", "luma", "blue-yellow chroma"];
+
+/// Scale bands, two scales each, finest first
+const BANDS: [&str; 3] = ["fine", "medium", "coarse"];
+
+/// Share of a kind's error above which it counts as concentrated in one
+/// band or channel
+const CONCENTRATED: f64 = 0.5;
+
+/// One of the kinds of error SSIMULACRA2 measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Differences in local structure and contrast (the SSIM terms)
+    Structure,
+    /// Edges and texture present in the distorted image but not the source
+    Artifacts,
+    /// Edges and texture present in the source but not the distorted image
+    DetailLoss,
+}
+
+impl ErrorKind {
+    /// Phrase used for this kind in summaries
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Structure => "structural differences",
+            Self::Artifacts => "added artifacts",
+            Self::DetailLoss => "detail loss",
+        }
+    }
+}
+
+/// How much one kind of error costs and where it comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorShare {
+    /// Kind of error
+    pub kind: ErrorKind,
+    /// Points below 100 attributed to this kind
+    pub points: f64,
+    /// Fraction of this kind's error in each channel (X, Y, B)
+    pub channels: [f64; 3],
+    /// Fraction of this kind's error at each scale, finest first
+    pub scales: Vec<f64>,
+}
+
+impl ErrorShare {
+    /// Adjective for the number of points lost
+    pub fn severity(&self) -> &'static str {
+        match self.points {
+            p if p < 1.0 => "negligible",
+            p if p < 5.0 => "slight",
+            p if p < 25.0 => "moderate",
+            p if p < 50.0 => "strong",
+            _ => "severe",
+        }
+    }
+
+    /// Where the error is, e.g. "concentrated at fine scales in luma"
+    fn location(&self) -> String {
+        let mut bands = [0.0; 3];
+        for (scale, share) in self.scales.iter().enumerate() {
+            bands[(scale / 2).min(2)] += share;
+        }
+        let (band, band_share) = largest(&bands);
+        let (channel, channel_share) = largest(&self.channels);
+
+        let scales = if band_share > CONCENTRATED {
+            format!("concentrated at {} scales", BANDS[band])
+        } else {
+            "spread across scales".to_string()
+        };
+        if channel_share > CONCENTRATED {
+            format!("{} in {}", scales, CHANNELS[channel])
+        } else {
+            format!("{} and channels", scales)
+        }
+    }
+}
+
+/// Breakdown of a [`DetailedScore`] by kind of error, scale and channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreExplanation {
+    /// SSIMULACRA2 score
+    pub score: f64,
+    /// Shares of structural differences, added artifacts and detail loss,
+    /// largest first
+    pub errors: [ErrorShare; 3],
+}
+
+impl ScoreExplanation {
+    /// Attributes the points `detailed` lost to the weighted terms they
+    /// come from.
+    pub fn new(detailed: &DetailedScore) -> Self {
+        let kinds = [
+            ErrorKind::Structure,
+            ErrorKind::Artifacts,
+            ErrorKind::DetailLoss,
+        ];
+        // Weighted error per channel and per scale, of each kind
+        let mut channels = [[0.0f64; 3]; 3];
+        let mut scales = [[0.0f64; 3]; NUM_SCALES];
+
+        // Same traversal as `score_scales`, so each term meets its weight
+        let mut i = 0usize;
+        for (c, channel) in channels.iter_mut().enumerate() {
+            for (scale, by_kind) in detailed.scales.iter().zip(scales.iter_mut()) {
+                for n in 0..2 {
+                    let terms = [
+                        scale.avg_ssim[c * 2 + n],
+                        scale.avg_edgediff[c * 4 + n],
+                        scale.avg_edgediff[c * 4 + n + 2],
+                    ];
+                    for (k, term) in terms.iter().enumerate() {
+                        let error = SCORE_WEIGHTS[i] * term.abs();
+                        channel[k] += error;
+                        by_kind[k] += error;
+                        i += 1;
+                    }
+                }
+            }
+        }
+
+        let totals: [f64; 3] = std::array::from_fn(|k| channels.iter().map(|c| c[k]).sum());
+        let total: f64 = totals.iter().sum();
+        let lost = (100.0 - detailed.score).max(0.0);
+        let num_scales = detailed.scales.len().min(NUM_SCALES);
+
+        let mut errors = std::array::from_fn(|k| {
+            let fraction = |v: f64| if totals[k] > 0.0 { v / totals[k] } else { 0.0 };
+            ErrorShare {
+                kind: kinds[k],
+                points: if total > 0.0 {
+                    lost * totals[k] / total
+                } else {
+                    0.0
+                },
+                channels: channels.map(|c| fraction(c[k])),
+                scales: scales[..num_scales]
+                    .iter()
+                    .map(|s| fraction(s[k]))
+                    .collect(),
+            }
+        });
+        errors.sort_by(|a: &ErrorShare, b| b.points.total_cmp(&a.points));
+
+        Self {
+            score: detailed.score,
+            errors,
+        }
+    }
+
+    /// The kind of error that cost the most points
+    pub fn dominant(&self) -> &ErrorShare {
+        &self.errors[0]
+    }
+
+    /// The share of `kind`
+    pub fn share(&self, kind: ErrorKind) -> &ErrorShare {
+        self.errors
+            .iter()
+            .find(|e| e.kind == kind)
+            .expect("every kind has a share")
+    }
+}
+
+impl fmt::Display for ScoreExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "quality ~{:.0}: ", self.score)?;
+        let dominant = self.dominant();
+        if dominant.points <= 0.0 {
+            return write!(f, "no measurable differences");
+        }
+        write!(
+            f,
+            "{} {} {}",
+            dominant.severity(),
+            dominant.kind.description(),
+            dominant.location()
+        )?;
+        for error in &self.errors[1..] {
+            write!(f, "; {} {}", error.severity(), error.kind.description())?;
+        }
+        Ok(())
+    }
+}
+
+impl DetailedScore {
+    /// Summarizes which kinds of error, at which scales and in which channels,
+    /// account for the score.
+    pub fn explain(&self) -> ScoreExplanation {
+        ScoreExplanation::new(self)
+    }
+}
+
+/// Index and value of the largest entry
+fn largest(values: &[f64]) -> (usize, f64) {
+    values.iter().copied().enumerate().fold(
+        (0, f64::MIN),
+        |best, (i, v)| if v > best.1 { (i, v) } else { best },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_ssimulacra2_detailed, LinearRgbImage, Ssimulacra2Config};
+
+    fn pattern(width: usize, height: usize) -> LinearRgbImage {
+        let data = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                let grain = ((i as u32).wrapping_mul(2_654_435_761) >> 24) as f32 / 255.0;
+                let v = 0.4 + 0.2 * (x * 0.2).sin() * (y * 0.15).cos() + 0.2 * grain;
+                [v, v, v]
+            })
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    }
+
+    /// 3x3 box blur
+    fn blurred(image: &LinearRgbImage) -> LinearRgbImage {
+        let (w, h) = (image.width(), image.height());
+        let src = image.data();
+        let data = (0..w * h)
+            .map(|i| {
+                let (x, y) = (i % w, i / w);
+                let mut sum = [0.0f32; 3];
+                let mut count = 0.0;
+                for yy in y.saturating_sub(1)..(y + 2).min(h) {
+                    for xx in x.saturating_sub(1)..(x + 2).min(w) {
+                        for (s, v) in sum.iter_mut().zip(src[yy * w + xx]) {
+                            *s += v;
+                        }
+                        count += 1.0;
+                    }
+                }
+                sum.map(|s| s / count)
+            })
+            .collect();
+        LinearRgbImage::new(data, w, h)
+    }
+
+    fn explain(source: &LinearRgbImage, distorted: &LinearRgbImage) -> ScoreExplanation {
+        compute_ssimulacra2_detailed(source, distorted, Ssimulacra2Config::default())
+            .unwrap()
+            .explain()
+    }
+
+    #[test]
+    fn test_identical_images_have_nothing_to_explain() {
+        let source = pattern(64, 64);
+        let explanation = explain(&source, &source);
+        assert_eq!(
+            explanation.to_string(),
+            "quality ~100: no measurable differences"
+        );
+        assert!(explanation.errors.iter().all(|e| e.points == 0.0));
+    }
+
+    #[test]
+    fn test_points_add_up_to_score_loss() {
+        let source = pattern(256, 256);
+        let explanation = explain(&source, &blurred(&source));
+        let points: f64 = explanation.errors.iter().map(|e| e.points).sum();
+        assert!((points - (100.0 - explanation.score)).abs() < 1e-9);
+        for error in &explanation.errors {
+            if error.points > 0.0 {
+                let channels: f64 = error.channels.iter().sum();
+                let scales: f64 = error.scales.iter().sum();
+                assert!((channels - 1.0).abs() < 1e-9, "{:?}", error);
+                assert!((scales - 1.0).abs() < 1e-9, "{:?}", error);
+            }
+        }
+        assert!(explanation.errors[0].points >= explanation.errors[1].points);
+        assert!(explanation.errors[1].points >= explanation.errors[2].points);
+    }
+
+    #[test]
+    fn test_blur_is_explained_as_detail_loss() {
+        let source = pattern(256, 256);
+        let explanation = explain(&source, &blurred(&source));
+        assert!(
+            explanation.share(ErrorKind::DetailLoss).points
+                > explanation.share(ErrorKind::Artifacts).points
+        );
+        let text = explanation.to_string();
+        assert!(
+            text.starts_with(&format!("quality ~{:.0}: ", explanation.score)),
+            "{}",
+            text
+        );
+        assert!(text.contains("detail loss"), "{}", text);
+        // Blurring removes the finest texture first
+        let detail = explanation.share(ErrorKind::DetailLoss);
+        assert!(detail.scales[0] > CONCENTRATED, "{:?}", detail);
+        assert!(detail.channels[1] > CONCENTRATED, "{:?}", detail);
+    }
+
+    #[test]
+    fn test_blocking_is_explained_as_artifacts() {
+        let source = blurred(&blurred(&pattern(256, 256)));
+        // Offsets alternating between 32x32 blocks add edges the source lacks
+        let data = source
+            .data()
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let block = (i % 256) / 32 + (i / 256) / 32;
+                let offset = if block % 2 == 0 { 0.02 } else { -0.02 };
+                p.map(|v| v + offset)
+            })
+            .collect();
+        let blocky = LinearRgbImage::new(data, 256, 256);
+        let explanation = explain(&source, &blocky);
+        assert!(
+            explanation.share(ErrorKind::Artifacts).points
+                > explanation.share(ErrorKind::DetailLoss).points,
+            "{}",
+            explanation
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Ordered backend preferences per pipeline stage.
+//!
+//! [`Ssimulacra2Config::impl_type`](crate::Ssimulacra2Config::impl_type)
+//! picks one backend for everything, and a backend that is not compiled in
+//! cannot be named at all. A [`FallbackChain`] instead lists backends in order
+//! of preference; the first one that is compiled in and supported by the
+//! running CPU is used. Chains can be parsed from strings such as
+//! `"unsafe-simd > simd > scalar"`, so one configuration (from a config file
+//! or environment variable) works for every build and every machine in a
+//! fleet.
+//!
+//! [`StageBackends`] sets a chain per [`Stage`], e.g. to keep the XYB
+//! conversion on the safe SIMD path while the blur uses intrinsics.
+
+use crate::{SimdImpl, Ssimulacra2Error};
+
+/// Pipeline stage with its own backend selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Linear RGB to XYB conversion, and the 2x downscale between scales
+    Xyb,
+    /// Gaussian blur of the planes and their products
+    Blur,
+    /// Plane multiplication, SSIM and edge difference maps
+    Ssim,
+}
+
+/// Backends that can appear in a chain
+const MAX_CHAIN: usize = 3;
+
+/// Backends in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FallbackChain {
+    backends: [SimdImpl; MAX_CHAIN],
+    len: usize,
+}
+
+impl Default for FallbackChain {
+    /// Fastest first: unsafe SIMD (if compiled in), safe SIMD, scalar
+    fn default() -> Self {
+        Self::new(&[
+            #[cfg(feature = "unsafe-simd")]
+            SimdImpl::UnsafeSimd,
+            SimdImpl::Simd,
+            SimdImpl::Scalar,
+        ])
+    }
+}
+
+impl FallbackChain {
+    /// Creates a chain trying `backends` in order. Duplicates are dropped; an
+    /// empty chain resolves to [`SimdImpl::Scalar`].
+    pub fn new(backends: &[SimdImpl]) -> Self {
+        let mut chain = Self {
+            backends: [SimdImpl::Scalar; MAX_CHAIN],
+            len: 0,
+        };
+        for &backend in backends {
+            if !chain.backends().contains(&backend) && chain.len < MAX_CHAIN {
+                chain.backends[chain.len] = backend;
+                chain.len += 1;
+            }
+        }
+        chain
+    }
+
+    /// Parses backend names separated by `>` or `,`, e.g.
+    /// `"unsafe-simd > simd > scalar"`.
+    ///
+    /// Names are `scalar`, `simd` and `unsafe-simd`. Backends not compiled
+    /// into this build are skipped rather than rejected, so the same string
+    /// works with and without the `unsafe-simd` feature.
+    ///
+    /// # Errors
+    /// - If a name is not one of the above
+    pub fn parse(names: &str) -> Result<Self, Ssimulacra2Error> {
+        let mut backends = Vec::with_capacity(MAX_CHAIN);
+        for name in names.split(['>', ',']).map(str::trim) {
+            match name {
+                "" => {}
+                "scalar" => backends.push(SimdImpl::Scalar),
+                "simd" => backends.push(SimdImpl::Simd),
+                #[cfg(feature = "unsafe-simd")]
+                "unsafe-simd" => backends.push(SimdImpl::UnsafeSimd),
+                #[cfg(not(feature = "unsafe-simd"))]
+                "unsafe-simd" => {}
+                _ => return Err(Ssimulacra2Error::UnknownBackend),
+            }
+        }
+        Ok(Self::new(&backends))
+    }
+
+    /// The backends in order of preference
+    pub fn backends(&self) -> &[SimdImpl] {
+        &self.backends[..self.len]
+    }
+
+    /// Returns the first backend supported by the running CPU, or
+    /// [`SimdImpl::Scalar`] if none is.
+    pub fn resolve(&self) -> SimdImpl {
+        self.backends()
+            .iter()
+            .copied()
+            .find(|backend| backend.is_available())
+            .unwrap_or(SimdImpl::Scalar)
+    }
+}
+
+impl From<SimdImpl> for FallbackChain {
+    fn from(backend: SimdImpl) -> Self {
+        Self::new(&[backend])
+    }
+}
+
+/// A [`FallbackChain`] for each [`Stage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageBackends {
+    /// Chain for [`Stage::Xyb`]
+    pub xyb: FallbackChain,
+    /// Chain for [`Stage::Blur`]
+    pub blur: FallbackChain,
+    /// Chain for [`Stage::Ssim`]
+    pub ssim: FallbackChain,
+}
+
+impl StageBackends {
+    /// Uses `chain` for every stage.
+    pub fn all(chain: FallbackChain) -> Self {
+        Self {
+            xyb: chain,
+            blur: chain,
+            ssim: chain,
+        }
+    }
+
+    /// The chain for `stage`
+    pub fn chain(&self, stage: Stage) -> &FallbackChain {
+        match stage {
+            Stage::Xyb => &self.xyb,
+            Stage::Blur => &self.blur,
+            Stage::Ssim => &self.ssim,
+        }
+    }
+
+    /// The backend `stage` runs on with this machine and build
+    pub fn resolve(&self, stage: Stage) -> SimdImpl {
+        self.chain(stage).resolve()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_ssimulacra2_with_config, LinearRgbImage, Ssimulacra2Config};
+
+    #[test]
+    fn test_parse_skips_missing_backends() {
+        let chain = FallbackChain::parse("unsafe-simd > simd > scalar").unwrap();
+        #[cfg(feature = "unsafe-simd")]
+        assert_eq!(chain.backends()[0], SimdImpl::UnsafeSimd);
+        assert!(chain
+            .backends()
+            .ends_with(&[SimdImpl::Simd, SimdImpl::Scalar]));
+        assert_eq!(chain, FallbackChain::default());
+
+        assert_eq!(
+            FallbackChain::parse(" simd, scalar,simd ")
+                .unwrap()
+                .backends(),
+            &[SimdImpl::Simd, SimdImpl::Scalar]
+        );
+        assert_eq!(
+            FallbackChain::parse("avx9000 > scalar"),
+            Err(Ssimulacra2Error::UnknownBackend)
+        );
+    }
+
+    #[test]
+    fn test_resolve_picks_first_available() {
+        assert_eq!(FallbackChain::new(&[]).resolve(), SimdImpl::Scalar);
+        assert_eq!(
+            FallbackChain::new(&[SimdImpl::Simd, SimdImpl::Scalar]).resolve(),
+            SimdImpl::Simd
+        );
+        #[cfg(feature = "unsafe-simd")]
+        {
+            let expected = if SimdImpl::UnsafeSimd.is_available() {
+                SimdImpl::UnsafeSimd
+            } else {
+                SimdImpl::Scalar
+            };
+            let chain = FallbackChain::new(&[SimdImpl::UnsafeSimd, SimdImpl::Scalar]);
+            assert_eq!(chain.resolve(), expected);
+        }
+    }
+
+    #[test]
+    fn test_per_stage_backends_score_like_single_backend() {
+        let data = |seed: usize| {
+            (0..64 * 48)
+                .map(|i| {
+                    let v = ((i * 7 + seed * 13) % 97) as f32 / 97.0;
+                    [v, 1.0 - v, 0.5 * v]
+                })
+                .collect::<Vec<_>>()
+        };
+        let source = LinearRgbImage::new(data(0), 64, 48);
+        let distorted = LinearRgbImage::new(data(1), 64, 48);
+
+        let backends = StageBackends {
+            xyb: SimdImpl::Scalar.into(),
+            blur: FallbackChain::default(),
+            ssim: SimdImpl::Simd.into(),
+        };
+        let config = Ssimulacra2Config::default().with_backends(backends);
+        assert_eq!(config.backend_for(Stage::Xyb), SimdImpl::Scalar);
+        assert_eq!(config.backend_for(Stage::Ssim), SimdImpl::Simd);
+
+        let mixed = compute_ssimulacra2_with_config(&source, &distorted, config).unwrap();
+        let scalar =
+            compute_ssimulacra2_with_config(&source, &distorted, Ssimulacra2Config::scalar())
+                .unwrap();
+        assert!((mixed - scalar).abs() < 0.1, "{} vs {}", mixed, scalar);
+    }
+}
@@ -0,0 +1,190 @@
+//! Scoring two image files in one call (requires the `image` feature).
+//!
+//! [`compare_files`] decodes both files with the `image` crate, applies EXIF
+//! orientation, converts to linear RGB and scores them. It covers scripts and
+//! quick tools that would otherwise copy the decode and conversion code from
+//! the CLI; callers that already hold decoded pixels should use
+//! [`compute_ssimulacra2_with_config`](crate::compute_ssimulacra2_with_config)
+//! instead.
+
+use crate::input::{SRGB_U16_TO_LINEAR, SRGB_U8_TO_LINEAR};
+use crate::{compute_ssimulacra2_with_config, LinearRgbImage, Ssimulacra2Config, Ssimulacra2Error};
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use std::path::{Path, PathBuf};
+
+/// Error from [`compare_files`] and [`open_linear_rgb`].
+///
+/// The message names the file involved; the underlying decode or scoring
+/// error is available through [`std::error::Error::source`].
+#[derive(Debug, thiserror::Error)]
+pub enum CompareFilesError {
+    /// A file could not be opened, identified or decoded.
+    #[error("Failed to decode {}", path.display())]
+    Decode {
+        /// The file that failed
+        path: PathBuf,
+        /// The decoder error
+        #[source]
+        error: image::ImageError,
+    },
+
+    /// Both files decoded, but they could not be compared (for example
+    /// different dimensions, or smaller than 8x8).
+    #[error("Failed to compare {} with {}", source_path.display(), distorted_path.display())]
+    Score {
+        /// The source (reference) file
+        source_path: PathBuf,
+        /// The distorted file
+        distorted_path: PathBuf,
+        /// The scoring error
+        #[source]
+        error: Ssimulacra2Error,
+    },
+}
+
+/// Decodes two image files and computes the SSIMULACRA2 score of `distorted`
+/// against `source`.
+///
+/// Any format enabled in the `image` crate is accepted; the format is
+/// detected from the file contents rather than the extension. Images are
+/// rotated and flipped upright according to their EXIF orientation, alpha is
+/// discarded and grayscale is expanded to RGB. 8- and 16-bit images are
+/// treated as sRGB; 32-bit float images (e.g. OpenEXR) as linear RGB, matching
+/// the crate's input convention.
+///
+/// ```no_run
+/// let score = fast_ssim2::compare_files("original.png", "compressed.jpg", Default::default())?;
+/// println!("{:.2}", score);
+/// # Ok::<(), fast_ssim2::CompareFilesError>(())
+/// ```
+///
+/// # Errors
+/// - If either file cannot be read or decoded
+/// - If the decoded images cannot be compared (see [`Ssimulacra2Error`])
+pub fn compare_files(
+    source: impl AsRef<Path>,
+    distorted: impl AsRef<Path>,
+    config: Ssimulacra2Config,
+) -> Result<f64, CompareFilesError> {
+    let (source, distorted) = (source.as_ref(), distorted.as_ref());
+    let source_image = open_linear_rgb(source)?;
+    let distorted_image = open_linear_rgb(distorted)?;
+    compute_ssimulacra2_with_config(&source_image, &distorted_image, config).map_err(|error| {
+        CompareFilesError::Score {
+            source_path: source.to_path_buf(),
+            distorted_path: distorted.to_path_buf(),
+            error,
+        }
+    })
+}
+
+/// Decodes an image file and converts it to linear RGB, the same way
+/// [`compare_files`] does.
+///
+/// Useful when one decoded file is compared more than once, or when the
+/// caller wants the maps or taps rather than only the score.
+///
+/// # Errors
+/// - If the file cannot be read or decoded ([`CompareFilesError::Decode`])
+pub fn open_linear_rgb(path: impl AsRef<Path>) -> Result<LinearRgbImage, CompareFilesError> {
+    let path = path.as_ref();
+    let image = decode_oriented(path).map_err(|error| CompareFilesError::Decode {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    Ok(to_linear_rgb(&image))
+}
+
+fn decode_oriented(path: &Path) -> image::ImageResult<DynamicImage> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn to_linear_rgb(image: &DynamicImage) -> LinearRgbImage {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let data = match image {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            image.to_rgb32f().pixels().map(|px| px.0).collect()
+        }
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => image
+            .to_rgb16()
+            .pixels()
+            .map(|px| px.0.map(|v| SRGB_U16_TO_LINEAR[usize::from(v)]))
+            .collect(),
+        _ => image
+            .to_rgb8()
+            .pixels()
+            .map(|px| px.0.map(|v| SRGB_U8_TO_LINEAR[usize::from(v)]))
+            .collect(),
+    };
+    LinearRgbImage::new(data, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    fn test_file(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join(name)
+    }
+
+    #[test]
+    fn test_compare_files_matches_reference_score() {
+        // Same pair and expected value as the reference test in lib.rs
+        let source = test_file("tank_source.png");
+        let distorted = test_file("tank_distorted.png");
+        let score = compare_files(&source, &distorted, Ssimulacra2Config::default()).unwrap();
+        assert!(
+            (score - 17.398_505_f64).abs() < 0.25,
+            "score {} differs from the reference",
+            score
+        );
+
+        let identical = compare_files(&source, &source, Ssimulacra2Config::default()).unwrap();
+        assert!((identical - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_errors_name_the_file() {
+        let source = test_file("tank_source.png");
+        let err = compare_files(&source, test_file("missing.png"), Default::default()).unwrap_err();
+        assert!(matches!(err, CompareFilesError::Decode { .. }));
+        assert!(err.to_string().contains("missing.png"), "{}", err);
+        assert!(err.source().is_some());
+
+        assert!(matches!(
+            open_linear_rgb(test_file("missing.png")),
+            Err(CompareFilesError::Decode { .. })
+        ));
+    }
+
+    #[test]
+    fn test_score_errors_keep_the_cause() {
+        let dir = std::env::temp_dir().join(format!("fast_ssim2_files_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let small = dir.join("small.png");
+        image::RgbImage::new(16, 16).save(&small).unwrap();
+
+        let err =
+            compare_files(test_file("tank_source.png"), &small, Default::default()).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        match &err {
+            CompareFilesError::Score { error, .. } => {
+                assert_eq!(*error, Ssimulacra2Error::NonMatchingImageDimensions);
+            }
+            other => panic!("unexpected error {}", other),
+        }
+        assert!(err.to_string().contains("small.png"), "{}", err);
+    }
+}
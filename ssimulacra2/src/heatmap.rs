@@ -0,0 +1,777 @@
+//! Where in the image quality was lost.
+//!
+//! [`compute_ssimulacra2_block_map`] pools the per-pixel error maps of every
+//! scale over square blocks of the full-resolution image, with the same norms
+//! and weights as the overall score. Each block gets a local score on the
+//! usual scale, so a codec developer can see which regions pull the score
+//! down. [`BlockMap::render`] turns the scores into a grayscale or color
+//! heatmap buffer, one pixel per block. [`HeatScaling`] quantizes raw
+//! per-pixel errors, such as those of [`ErrorMaps`](crate::ErrorMaps), to
+//! bytes.
+//!
+//! Unlike [`compute_ssimulacra2_tiles`](crate::compute_ssimulacra2_tiles),
+//! blocks are not scored as standalone images: the blurs see the whole image,
+//! so blocks can be smaller than 8x8 and there are no seams at block edges.
+//! [`compute_ssimulacra2_grid`] does the same for rectangular tiles, and
+//! [`compute_ssimulacra2_roi`] for a single [`Region`], e.g. the salient part
+//! of an image or the changed part of a screenshot.
+//!
+//! A combined map can hide chroma-only damage, such as red text smeared by
+//! chroma subsampling, behind the luma structure around it.
+//! [`compute_ssimulacra2_channel_block_maps`] scores each XYB channel on its
+//! own, as if the other two were undistorted.
+
+use std::ops::Range;
+
+use crate::{
+    compute_ssimulacra2_maps, score_scales, ErrorMaps, MapBuffers, MapRequest, Region,
+    ScaleStatistics, Ssimulacra2Config, Ssimulacra2Error, ToLinearRgb, NUM_SCALES,
+};
+
+/// Local SSIMULACRA2 scores of square blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockMap {
+    /// Side length of a block in full-resolution pixels
+    pub block_size: usize,
+    /// Number of blocks per row (edge blocks may be clipped)
+    pub width: usize,
+    /// Number of block rows
+    pub height: usize,
+    /// Scores in row-major order, on the same scale as the overall score
+    pub scores: Vec<f64>,
+}
+
+/// Local SSIMULACRA2 scores of a grid of rectangular tiles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreGrid {
+    /// Tile width in full-resolution pixels
+    pub tile_width: usize,
+    /// Tile height in full-resolution pixels
+    pub tile_height: usize,
+    /// Number of tile columns (the last may be clipped)
+    pub columns: usize,
+    /// Number of tile rows (the last may be clipped)
+    pub rows: usize,
+    /// Scores in row-major order, on the same scale as the overall score
+    pub scores: Vec<f64>,
+}
+
+impl ScoreGrid {
+    /// Score of the tile at `column`, `row`
+    pub fn score(&self, column: usize, row: usize) -> Option<f64> {
+        (column < self.columns && row < self.rows).then(|| self.scores[row * self.columns + column])
+    }
+
+    /// Scores of the tiles of `row`, left to right
+    pub fn row(&self, row: usize) -> Option<&[f64]> {
+        (row < self.rows).then(|| &self.scores[row * self.columns..(row + 1) * self.columns])
+    }
+
+    /// Column, row and score of the lowest scoring tile
+    pub fn worst(&self) -> Option<(usize, usize, f64)> {
+        let (i, score) = self
+            .scores
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+        Some((i % self.columns, i / self.columns, score))
+    }
+}
+
+/// Color scheme for [`BlockMap::render`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeatmapPalette {
+    /// One byte per block, brighter is worse
+    #[default]
+    Grayscale,
+    /// Three bytes (RGB) per block, from dark blue through green and yellow
+    /// to red as quality drops
+    Color,
+}
+
+/// Colors of [`HeatmapPalette::Color`] at evenly spaced points from no to
+/// full error
+const COLOR_STOPS: [[f32; 3]; 5] = [
+    [0.0, 0.0, 128.0],
+    [0.0, 128.0, 255.0],
+    [0.0, 200.0, 0.0],
+    [255.0, 220.0, 0.0],
+    [255.0, 0.0, 0.0],
+];
+
+/// Curve applied to normalized heat values by [`HeatScaling`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum HeatCurve {
+    /// Heat maps proportionally to brightness (default)
+    #[default]
+    Linear,
+    /// Logarithmic over three decades: an error a thousandth of the
+    /// normalization point is still visible, and each tenfold step in error
+    /// adds the same brightness
+    Log,
+    /// `heat.powf(exponent)`; exponents below 1 brighten small errors
+    Gamma(f32),
+}
+
+/// Ratio between the largest and smallest heat [`HeatCurve::Log`] separates
+const LOG_RANGE: f32 = 1000.0;
+
+/// Quantizes non-negative heat values to bytes, for heatmaps that stay
+/// readable whatever the magnitude of the distortion.
+///
+/// Heat is first divided by its `percentile`-th percentile over the map, so
+/// that value and everything above it saturates at 255 and a few extreme
+/// pixels don't leave the rest of the map black. The [`HeatCurve`] is then
+/// applied and the result rounded. Zero heat is always 0.
+///
+/// The default, linear up to the largest value, is what the `ssimulacra2`
+/// binary's `--map` writes unless told otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeatScaling {
+    /// Curve applied after normalization
+    pub curve: HeatCurve,
+    /// Percentile of the heat values that maps to 255, from 0 to 100
+    pub percentile: f64,
+}
+
+impl Default for HeatScaling {
+    fn default() -> Self {
+        Self {
+            curve: HeatCurve::Linear,
+            percentile: 100.0,
+        }
+    }
+}
+
+impl HeatScaling {
+    /// Logarithmic curve, saturating at the 99th percentile
+    pub fn log() -> Self {
+        Self {
+            curve: HeatCurve::Log,
+            percentile: 99.0,
+        }
+    }
+
+    /// `heat.powf(exponent)`, saturating at the 99th percentile
+    pub fn gamma(exponent: f32) -> Self {
+        Self {
+            curve: HeatCurve::Gamma(exponent),
+            percentile: 99.0,
+        }
+    }
+
+    /// Saturate at the `percentile`-th percentile instead, clamped to
+    /// 0..=100.
+    #[must_use]
+    pub fn with_percentile(mut self, percentile: f64) -> Self {
+        self.percentile = percentile.clamp(0.0, 100.0);
+        self
+    }
+
+    /// Quantizes `heat` to one byte per value. Negative and NaN values
+    /// count as zero.
+    pub fn quantize(&self, heat: &[f32]) -> Vec<u8> {
+        let Some(reference) = self.reference(heat) else {
+            return vec![0; heat.len()];
+        };
+        heat.iter()
+            .map(|&v| {
+                let x = (v / reference).clamp(0.0, 1.0);
+                let y = match self.curve {
+                    HeatCurve::Linear => x,
+                    HeatCurve::Log => (x * (LOG_RANGE - 1.0)).ln_1p() / LOG_RANGE.ln(),
+                    HeatCurve::Gamma(exponent) => x.powf(exponent),
+                };
+                (y * 255.0).round().clamp(0.0, 255.0) as u8
+            })
+            .collect()
+    }
+
+    /// The heat value that saturates, or `None` if it isn't positive
+    fn reference(&self, heat: &[f32]) -> Option<f32> {
+        let mut values: Vec<f32> = heat.iter().map(|v| v.max(0.0)).collect();
+        if values.is_empty() {
+            return None;
+        }
+        let rank = (self.percentile.clamp(0.0, 100.0) / 100.0 * (values.len() - 1) as f64).round();
+        let (_, &mut reference, _) =
+            values.select_nth_unstable_by(rank as usize, |a, b| a.total_cmp(b));
+        (reference > 0.0).then_some(reference)
+    }
+}
+
+impl BlockMap {
+    /// Score of the block at column `x`, row `y`
+    pub fn score(&self, x: usize, y: usize) -> Option<f64> {
+        (x < self.width && y < self.height).then(|| self.scores[y * self.width + x])
+    }
+
+    /// Column, row and score of the lowest scoring block
+    pub fn worst(&self) -> Option<(usize, usize, f64)> {
+        let (i, score) = self
+            .scores
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+        Some((i % self.width, i / self.width, score))
+    }
+
+    /// Renders the map with one pixel per block, row-major.
+    ///
+    /// Scores of 100 map to the cold end of the palette and scores of
+    /// `floor` or below to the hot end. A `floor` of 0 suits most content;
+    /// raise it to spread out differences between high-quality blocks.
+    pub fn render(&self, palette: HeatmapPalette, floor: f64) -> Vec<u8> {
+        let range = (100.0 - floor).max(f64::EPSILON);
+        let errors = self
+            .scores
+            .iter()
+            .map(|&score| ((100.0 - score) / range).clamp(0.0, 1.0) as f32);
+        match palette {
+            HeatmapPalette::Grayscale => errors.map(|e| (e * 255.0).round() as u8).collect(),
+            HeatmapPalette::Color => errors.flat_map(color_for).collect(),
+        }
+    }
+}
+
+/// Interpolates [`COLOR_STOPS`] at `error` in [0, 1]
+fn color_for(error: f32) -> [u8; 3] {
+    let position = error * (COLOR_STOPS.len() - 1) as f32;
+    let i = (position as usize).min(COLOR_STOPS.len() - 2);
+    let t = position - i as f32;
+    let (a, b) = (COLOR_STOPS[i], COLOR_STOPS[i + 1]);
+    std::array::from_fn(|c| (a[c] + (b[c] - a[c]) * t).round() as u8)
+}
+
+/// Computes the SSIMULACRA2 score and the local scores of
+/// `block_size`x`block_size` blocks.
+///
+/// The score is identical to
+/// [`compute_ssimulacra2_with_config`](crate::compute_ssimulacra2_with_config).
+/// A single block covering the whole image scores (up to rounding) the same
+/// as the image.
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the image is smaller than 8x8 pixels
+/// - If `block_size` is 0
+pub fn compute_ssimulacra2_block_map<S, D>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+    block_size: usize,
+) -> Result<(f64, BlockMap), Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    if block_size == 0 {
+        return Err(Ssimulacra2Error::InvalidImageSize);
+    }
+    let (score, grid) =
+        compute_ssimulacra2_grid(source, distorted, config, block_size, block_size)?;
+    let map = BlockMap {
+        block_size,
+        width: grid.columns,
+        height: grid.rows,
+        scores: grid.scores,
+    };
+    Ok((score, map))
+}
+
+/// Computes the SSIMULACRA2 score and one [`BlockMap`] per XYB channel
+/// (X, Y, B).
+///
+/// Each map scores its blocks from that channel's error alone, on the usual
+/// scale, so a block with only chroma errors scores 100 in the Y map and
+/// lower in the X or B map. The overall score is that of
+/// [`compute_ssimulacra2_block_map`].
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the image is smaller than 8x8 pixels
+/// - If `block_size` is 0
+/// - [`Ssimulacra2Error::ErrorMapsUnavailable`] with
+///   [`luma_only`](Ssimulacra2Config::luma_only)
+pub fn compute_ssimulacra2_channel_block_maps<S, D>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+    block_size: usize,
+) -> Result<(f64, [BlockMap; 3]), Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    if block_size == 0 {
+        return Err(Ssimulacra2Error::InvalidImageSize);
+    }
+    let (source, distorted) = (source.as_linear_rgb(), distorted.as_linear_rgb());
+    let mut buffers = MapBuffers::new();
+    let score = compute_ssimulacra2_maps(
+        &*source,
+        &*distorted,
+        config,
+        MapRequest::error_maps(),
+        &mut buffers,
+    )?;
+    let maps = PooledMaps::new(&buffers, source.width(), source.height())?;
+    let width = source.width().div_ceil(block_size);
+    let height = source.height().div_ceil(block_size);
+    let mut scores: [Vec<f64>; 3] = Default::default();
+    for y in 0..height {
+        for x in 0..width {
+            let xs = x * block_size..(x + 1) * block_size;
+            let ys = y * block_size..(y + 1) * block_size;
+            let statistics = maps.statistics(xs, ys);
+            for (channel, scores) in scores.iter_mut().enumerate() {
+                scores.push(score_scales(&channel_only(&statistics, channel)));
+            }
+        }
+    }
+    let maps = scores.map(|scores| BlockMap {
+        block_size,
+        width,
+        height,
+        scores,
+    });
+    Ok((score, maps))
+}
+
+/// Computes the SSIMULACRA2 score and the local scores of
+/// `tile_width`x`tile_height` tiles, as [`compute_ssimulacra2_block_map`]
+/// does for square blocks.
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the image is smaller than 8x8 pixels
+/// - If `tile_width` or `tile_height` is 0
+/// - [`Ssimulacra2Error::ErrorMapsUnavailable`] with
+///   [`luma_only`](Ssimulacra2Config::luma_only)
+pub fn compute_ssimulacra2_grid<S, D>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+    tile_width: usize,
+    tile_height: usize,
+) -> Result<(f64, ScoreGrid), Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    if tile_width == 0 || tile_height == 0 {
+        return Err(Ssimulacra2Error::InvalidImageSize);
+    }
+    let (source, distorted) = (source.as_linear_rgb(), distorted.as_linear_rgb());
+    let mut buffers = MapBuffers::new();
+    let score = compute_ssimulacra2_maps(
+        &*source,
+        &*distorted,
+        config,
+        MapRequest::error_maps(),
+        &mut buffers,
+    )?;
+    let maps = PooledMaps::new(&buffers, source.width(), source.height())?;
+    let columns = source.width().div_ceil(tile_width);
+    let rows = source.height().div_ceil(tile_height);
+    let mut scores = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let xs = column * tile_width..(column + 1) * tile_width;
+            let ys = row * tile_height..(row + 1) * tile_height;
+            scores.push(score_scales(&maps.statistics(xs, ys)));
+        }
+    }
+    let grid = ScoreGrid {
+        tile_width,
+        tile_height,
+        columns,
+        rows,
+        scores,
+    };
+    Ok((score, grid))
+}
+
+/// Computes the local SSIMULACRA2 score of `region`.
+///
+/// The whole pair is compared and the error maps are pooled over the
+/// region only, so the blurs see the pixels around it as they do for the
+/// full score, and regions smaller than 8x8 can be scored. To score many
+/// regions of a pair, [`compute_ssimulacra2_grid`] shares that comparison.
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the image is smaller than 8x8 pixels
+/// - If the region is empty or extends past the image
+/// - [`Ssimulacra2Error::ErrorMapsUnavailable`] with
+///   [`luma_only`](Ssimulacra2Config::luma_only)
+pub fn compute_ssimulacra2_roi<S, D>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+    region: Region,
+) -> Result<f64, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    let (source, distorted) = (source.as_linear_rgb(), distorted.as_linear_rgb());
+    if source.width() == distorted.width()
+        && source.height() == distorted.height()
+        && !region.fits(source.width(), source.height())
+    {
+        return Err(Ssimulacra2Error::InvalidRegion);
+    }
+    let mut buffers = MapBuffers::new();
+    compute_ssimulacra2_maps(
+        &*source,
+        &*distorted,
+        config,
+        MapRequest::error_maps(),
+        &mut buffers,
+    )?;
+    let maps = PooledMaps::new(&buffers, source.width(), source.height())?;
+    let xs = region.x..region.x + region.width;
+    let ys = region.y..region.y + region.height;
+    Ok(score_scales(&maps.statistics(xs, ys)))
+}
+
+/// The error maps of every scale of a comparison of `width`x`height`
+/// images.
+///
+/// With a pre-resize or early downscale the first scale is smaller than the
+/// images, so regions are scaled to it before pooling.
+struct PooledMaps<'a> {
+    scales: Vec<ErrorMaps<'a>>,
+    width: usize,
+    height: usize,
+}
+
+impl<'a> PooledMaps<'a> {
+    fn new(buffers: &'a MapBuffers, width: usize, height: usize) -> Result<Self, Ssimulacra2Error> {
+        let scales: Vec<_> = (0..NUM_SCALES)
+            .map_while(|scale| buffers.error_maps(scale))
+            .collect();
+        if scales.is_empty() {
+            return Err(Ssimulacra2Error::ErrorMapsUnavailable);
+        }
+        Ok(Self {
+            scales,
+            width,
+            height,
+        })
+    }
+
+    /// Pools the error maps of every scale over the image pixels `xs` x `ys`
+    fn statistics(&self, xs: Range<usize>, ys: Range<usize>) -> Vec<ScaleStatistics> {
+        let first = &self.scales[0];
+        let xs = to_first_scale(xs, self.width, first.width);
+        let ys = to_first_scale(ys, self.height, first.height);
+        region_statistics(&self.scales, xs, ys)
+    }
+}
+
+/// The pixels of an axis `mapped` long covering `range` of the same axis
+/// `full` long
+fn to_first_scale(range: Range<usize>, full: usize, mapped: usize) -> Range<usize> {
+    if full == mapped {
+        return range;
+    }
+    let start = range.start * mapped / full;
+    let end = (range.end * mapped).div_ceil(full);
+    start..end.max(start + 1)
+}
+
+/// `statistics` with the norms of every channel but `channel` zeroed
+fn channel_only(statistics: &[ScaleStatistics], channel: usize) -> Vec<ScaleStatistics> {
+    statistics
+        .iter()
+        .map(|stats| {
+            let mut only = ScaleStatistics::default();
+            only.avg_ssim[channel * 2..channel * 2 + 2]
+                .copy_from_slice(&stats.avg_ssim[channel * 2..channel * 2 + 2]);
+            only.avg_edgediff[channel * 4..channel * 4 + 4]
+                .copy_from_slice(&stats.avg_edgediff[channel * 4..channel * 4 + 4]);
+            only
+        })
+        .collect()
+}
+
+/// Pools the error maps of every scale over the first-scale pixels
+/// `xs` x `ys`
+fn region_statistics(
+    scales: &[ErrorMaps<'_>],
+    xs: Range<usize>,
+    ys: Range<usize>,
+) -> Vec<ScaleStatistics> {
+    let mut statistics = vec![ScaleStatistics::default(); scales.len()];
+    for (scale, (maps, stats)) in scales.iter().zip(&mut statistics).enumerate() {
+        // Pixels of this scale overlapping the region, at least one
+        let span = |range: &Range<usize>, len: usize| {
+            let first = (range.start >> scale).min(len - 1);
+            let end = range.end.div_ceil(1 << scale).clamp(first + 1, len);
+            first..end
+        };
+        let (xs, ys) = (span(&xs, maps.width), span(&ys, maps.height));
+        let one_per_pixels = 1.0 / (xs.len() * ys.len()) as f64;
+
+        let planes = [maps.ssim, maps.artifact, maps.detail_lost];
+        let mut norms = [[0.0f64; 2]; 9];
+        for (norm, plane) in norms.iter_mut().zip(planes.iter().flat_map(|p| p.iter())) {
+            for y in ys.clone() {
+                for &v in &plane[y * maps.width + xs.start..y * maps.width + xs.end] {
+                    let v = f64::from(v);
+                    norm[0] += v;
+                    norm[1] += v.powi(4);
+                }
+            }
+            norm[0] *= one_per_pixels;
+            norm[1] = (norm[1] * one_per_pixels).sqrt().sqrt();
+        }
+        for c in 0..3 {
+            let [ssim, artifact, detail_lost] = [norms[c], norms[3 + c], norms[6 + c]];
+            stats.avg_ssim[c * 2..c * 2 + 2].copy_from_slice(&ssim);
+            stats.avg_edgediff[c * 4..c * 4 + 2].copy_from_slice(&artifact);
+            stats.avg_edgediff[c * 4 + 2..c * 4 + 4].copy_from_slice(&detail_lost);
+        }
+    }
+    statistics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pattern;
+    use crate::{compute_ssimulacra2_with_config, LinearRgbImage, PreResize};
+
+    /// `source` with noise added to the right half
+    fn distort_right_half(source: &LinearRgbImage) -> LinearRgbImage {
+        let width = source.width();
+        let data = source
+            .data()
+            .iter()
+            .enumerate()
+            .map(|(i, px)| {
+                if i % width < width / 2 {
+                    *px
+                } else {
+                    let noise = ((i as u32).wrapping_mul(2_654_435_761) >> 24) as f32 / 1024.0;
+                    px.map(|v| (v + noise).min(1.0))
+                }
+            })
+            .collect();
+        LinearRgbImage::new(data, width, source.height())
+    }
+
+    #[test]
+    fn test_blocks_locate_the_distortion() {
+        let source = pattern(96, 64, 0.0);
+        let distorted = distort_right_half(&source);
+        let config = Ssimulacra2Config::default();
+        let (score, map) =
+            compute_ssimulacra2_block_map(&source, &distorted, config.clone(), 16).unwrap();
+        assert_eq!(
+            score,
+            compute_ssimulacra2_with_config(&source, &distorted, config).unwrap()
+        );
+        assert_eq!((map.width, map.height, map.scores.len()), (6, 4, 24));
+
+        for y in 0..map.height {
+            // Left blocks away from the seam are nearly untouched
+            assert!(map.score(0, y).unwrap() > map.score(5, y).unwrap() + 10.0);
+        }
+        let (x, _, worst) = map.worst().unwrap();
+        assert!(x >= 3, "worst block {} is in the clean half", x);
+        assert!(worst < score);
+        assert_eq!(map.score(6, 0), None);
+    }
+
+    #[test]
+    fn test_channel_maps_separate_chroma_damage() {
+        let source = pattern(96, 64, 0.0);
+        // Blue-only noise on the right half, which mostly lands in B
+        let distorted = LinearRgbImage::new(
+            source
+                .data()
+                .iter()
+                .enumerate()
+                .map(|(i, &[r, g, b])| {
+                    let noise = ((i as u32).wrapping_mul(2_654_435_761) >> 24) as f32 / 512.0;
+                    [r, g, if i % 96 < 48 { b } else { (b + noise).min(1.0) }]
+                })
+                .collect(),
+            96,
+            64,
+        );
+        let config = Ssimulacra2Config::default();
+        let (score, [x, y, b]) =
+            compute_ssimulacra2_channel_block_maps(&source, &distorted, config.clone(), 16)
+                .unwrap();
+        let (combined_score, combined) =
+            compute_ssimulacra2_block_map(&source, &distorted, config, 16).unwrap();
+        assert_eq!(score, combined_score);
+        assert_eq!((b.width, b.height), (combined.width, combined.height));
+
+        let (b_worst, y_worst, x_worst) = (
+            b.worst().unwrap().2,
+            y.worst().unwrap().2,
+            x.worst().unwrap().2,
+        );
+        assert!(
+            b_worst < y_worst && b_worst < x_worst,
+            "{:?}",
+            (x_worst, y_worst, b_worst)
+        );
+        // Each channel alone loses less than all of them together
+        assert!(b_worst >= combined.worst().unwrap().2);
+        assert!(b.score(0, 0).unwrap() > b_worst + 10.0);
+    }
+
+    #[test]
+    fn test_single_block_matches_image_score() {
+        let source = pattern(50, 37, 0.0);
+        let distorted = distort_right_half(&source);
+        let (score, map) =
+            compute_ssimulacra2_block_map(&source, &distorted, Default::default(), 64).unwrap();
+        assert_eq!((map.width, map.height), (1, 1));
+        assert!(
+            (map.scores[0] - score).abs() < 1e-3,
+            "{:?} vs {}",
+            map,
+            score
+        );
+
+        assert_eq!(
+            compute_ssimulacra2_block_map(&source, &distorted, Default::default(), 0),
+            Err(Ssimulacra2Error::InvalidImageSize)
+        );
+    }
+
+    #[test]
+    fn test_grid_and_roi_scores() {
+        let source = pattern(96, 64, 0.0);
+        let distorted = distort_right_half(&source);
+        let config = Ssimulacra2Config::default();
+        let (score, grid) =
+            compute_ssimulacra2_grid(&source, &distorted, config.clone(), 48, 16).unwrap();
+        assert_eq!((grid.columns, grid.rows), (2, 4));
+        for row in 0..grid.rows {
+            let [left, right] = grid.row(row).unwrap() else {
+                panic!("two columns");
+            };
+            assert!(left > right);
+        }
+        assert_eq!(grid.worst().unwrap().0, 1);
+        assert_eq!(grid.score(2, 0), None);
+
+        // Square tiles are the block map
+        let (_, map) =
+            compute_ssimulacra2_block_map(&source, &distorted, config.clone(), 16).unwrap();
+        let (_, square) =
+            compute_ssimulacra2_grid(&source, &distorted, config.clone(), 16, 16).unwrap();
+        assert_eq!(map.scores, square.scores);
+
+        // A region is scored like the tile covering it
+        let roi = |region| compute_ssimulacra2_roi(&source, &distorted, config.clone(), region);
+        assert_eq!(roi(Region::new(48, 16, 48, 16)), Ok(grid.scores[3]));
+        assert!((roi(Region::new(0, 0, 96, 64)).unwrap() - score).abs() < 1e-3);
+        assert!(roi(Region::new(70, 30, 4, 4)).unwrap() < 100.0);
+        assert_eq!(
+            roi(Region::new(90, 0, 8, 8)),
+            Err(Ssimulacra2Error::InvalidRegion)
+        );
+        assert_eq!(
+            compute_ssimulacra2_grid(&source, &distorted, config, 16, 0),
+            Err(Ssimulacra2Error::InvalidImageSize)
+        );
+    }
+
+    #[test]
+    fn test_downscaled_maps_keep_full_resolution_coordinates() {
+        let source = pattern(96, 64, 0.0);
+        let distorted = distort_right_half(&source);
+        for config in [
+            Ssimulacra2Config::default().with_pre_resize(PreResize::new(2)),
+            Ssimulacra2Config::default().with_early_downscale(0.003),
+        ] {
+            let (score, grid) =
+                compute_ssimulacra2_grid(&source, &distorted, config.clone(), 48, 16).unwrap();
+            assert_eq!((grid.columns, grid.rows), (2, 4));
+            for row in 0..grid.rows {
+                let [left, right] = grid.row(row).unwrap() else {
+                    panic!("two columns");
+                };
+                assert!(left > right, "{:?}", grid);
+            }
+
+            let roi = |region| compute_ssimulacra2_roi(&source, &distorted, config.clone(), region);
+            assert_eq!(roi(Region::new(48, 16, 48, 16)), Ok(grid.scores[3]));
+            assert!((roi(Region::new(0, 0, 96, 64)).unwrap() - score).abs() < 1e-3);
+            assert!(
+                roi(Region::new(0, 0, 40, 64)).unwrap() > roi(Region::new(56, 0, 40, 64)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_luma_only_has_no_maps_to_pool() {
+        let source = pattern(64, 64, 0.0);
+        let distorted = distort_right_half(&source);
+        let config = Ssimulacra2Config::default().with_luma_only(true);
+        assert_eq!(
+            compute_ssimulacra2_grid(&source, &distorted, config.clone(), 16, 16),
+            Err(Ssimulacra2Error::ErrorMapsUnavailable)
+        );
+        assert_eq!(
+            compute_ssimulacra2_roi(&source, &distorted, config.clone(), Region::new(0, 0, 8, 8)),
+            Err(Ssimulacra2Error::ErrorMapsUnavailable)
+        );
+        assert_eq!(
+            compute_ssimulacra2_channel_block_maps(&source, &distorted, config, 16),
+            Err(Ssimulacra2Error::ErrorMapsUnavailable)
+        );
+    }
+
+    #[test]
+    fn test_render_palettes() {
+        let map = BlockMap {
+            block_size: 8,
+            width: 3,
+            height: 1,
+            scores: vec![100.0, 50.0, -20.0],
+        };
+        assert_eq!(map.render(HeatmapPalette::Grayscale, 0.0), [0, 128, 255]);
+        assert_eq!(map.render(HeatmapPalette::Grayscale, 50.0), [0, 255, 255]);
+        let color = map.render(HeatmapPalette::Color, 0.0);
+        assert_eq!(color.len(), 9);
+        assert_eq!(color[..3], COLOR_STOPS[0].map(|c| c as u8));
+        assert_eq!(color[3..6], COLOR_STOPS[2].map(|c| c as u8));
+        assert_eq!(color[6..], COLOR_STOPS[4].map(|c| c as u8));
+    }
+
+    #[test]
+    fn test_heat_scaling() {
+        let heat = [0.0, 0.001, 0.01, 0.2, 0.5, 1.0];
+        assert_eq!(
+            HeatScaling::default().quantize(&heat),
+            [0, 0, 3, 51, 128, 255]
+        );
+        assert_eq!(
+            HeatScaling::log().quantize(&heat),
+            [0, 26, 88, 196, 229, 255]
+        );
+        assert_eq!(HeatScaling::gamma(0.5).quantize(&heat)[4], 180);
+
+        // One hot pixel doesn't darken the rest at a lower percentile
+        let mut heat = vec![0.01f32; 100];
+        heat[0] = 100.0;
+        assert_eq!(HeatScaling::default().quantize(&heat)[1], 0);
+        let robust = HeatScaling::default().with_percentile(95.0).quantize(&heat);
+        assert_eq!((robust[0], robust[1]), (255, 255));
+
+        assert_eq!(HeatScaling::log().quantize(&[0.0, -1.0]), [0, 0]);
+        assert!(HeatScaling::log().quantize(&[]).is_empty());
+    }
+}
@@ -10,22 +10,61 @@
 //! | `ImgRef<[u8; 3]>` | sRGB (gamma) | `/255` + linearize |
 //! | `ImgRef<[u16; 3]>` | sRGB (gamma) | `/65535` + linearize |
 //! | `ImgRef<[f32; 3]>` | Linear RGB | none |
+//! | `ImgRef<[u8; 4]>` | sRGB + alpha | as `[u8; 3]`, alpha `/255` |
+//! | `ImgRef<[u16; 4]>` | sRGB + alpha | as `[u16; 3]`, alpha `/65535` |
+//! | `ImgRef<[f32; 4]>` | Linear RGB + alpha | none |
 //! | `ImgRef<u8>` | sRGB grayscale | `/255` + linearize + expand |
 //! | `ImgRef<f32>` | Linear grayscale | expand to RGB |
 //!
+//! `ImgRef` views skip the padding of strided buffers. Without `imgref`,
+//! padded rows are read in place by
+//! [`LinearRgbImage::from_srgb_u8_strided`],
+//! [`LinearRgbImage::from_strided`] and [`EncodedRgb::new_strided`]. Rows are
+//! packed once, during conversion to linear RGB, and everything after that
+//! (XYB conversion and the planar buffers) works on the packed copy.
+//!
 //! ## Convention
 //!
 //! - Integer types (u8, u16) are assumed to be **sRGB** (gamma-encoded)
 //! - Float types (f32) are assumed to be **linear**
+//! - Alpha is straight (not premultiplied) and kept alongside the pixels;
+//!   [`AlphaMode`](crate::AlphaMode) decides how it affects the score
+//!
+//! Planar YUV video frames go through `YuvPlanes` (with the `yuvxyb`
+//! feature), which describes its color space with a `yuvxyb` `YuvConfig`.
+//! Packed RGB in other transfer
+//! functions, including HDR (PQ and HLG), goes through [`EncodedRgb`], which
+//! also takes 10- and 12-bit and limited-range samples.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+#[cfg(all(feature = "yuvxyb", feature = "simd"))]
+use multiversion::multiversion;
+#[cfg(all(feature = "yuvxyb", feature = "simd"))]
+use wide::f32x8;
+#[cfg(feature = "yuvxyb")]
+use yuvxyb::{
+    CastFromPrimitive, ColorPrimaries, MatrixCoefficients, Pixel, TransferCharacteristic, YuvConfig,
+};
+
+use crate::transfer::{
+    self, Colorimetry, RgbPrimaries, TransferFunction, HDR_REFERENCE_WHITE_NITS,
+};
+use crate::Ssimulacra2Error;
 
 /// Internal linear RGB image representation.
 ///
-/// Stores pixels as `[f32; 3]` in linear RGB color space (0.0-1.0 range).
+/// Stores pixels as `[f32; 3]` in linear RGB color space (0.0-1.0 range),
+/// with an optional straight alpha plane and an optional [`Colorimetry`]
+/// tag describing how the pixels were decoded.
 #[derive(Clone)]
 pub struct LinearRgbImage {
     pub(crate) data: Vec<[f32; 3]>,
     pub(crate) width: usize,
     pub(crate) height: usize,
+    pub(crate) alpha: Option<Vec<f32>>,
+    pub(crate) colorimetry: Option<Colorimetry>,
 }
 
 impl LinearRgbImage {
@@ -36,9 +75,44 @@ impl LinearRgbImage {
             data,
             width,
             height,
+            alpha: None,
+            colorimetry: None,
         }
     }
 
+    /// Attaches a straight (not premultiplied) alpha plane, one value in
+    /// 0.0-1.0 per pixel. How it is used depends on
+    /// [`AlphaMode`](crate::AlphaMode); by default it is ignored.
+    ///
+    /// # Errors
+    /// - If `alpha` doesn't hold exactly `width * height` values
+    pub fn with_alpha(mut self, alpha: Vec<f32>) -> Result<Self, Ssimulacra2Error> {
+        if alpha.len() != self.width * self.height {
+            return Err(Ssimulacra2Error::RowDataMismatch);
+        }
+        self.alpha = Some(alpha);
+        Ok(self)
+    }
+
+    /// Returns the alpha plane, if the image has one.
+    pub fn alpha(&self) -> Option<&[f32]> {
+        self.alpha.as_deref()
+    }
+
+    /// Tags the image with how its pixels were decoded, so comparisons can
+    /// reject a counterpart decoded differently. Pixels tagged with BT.2020
+    /// primaries are converted to BT.709 before scoring.
+    #[must_use]
+    pub fn with_colorimetry(mut self, colorimetry: Colorimetry) -> Self {
+        self.colorimetry = Some(colorimetry);
+        self
+    }
+
+    /// Returns the colorimetry tag, if the image has one.
+    pub fn colorimetry(&self) -> Option<Colorimetry> {
+        self.colorimetry
+    }
+
     /// Returns the image width.
     pub fn width(&self) -> usize {
         self.width
@@ -58,14 +132,255 @@ impl LinearRgbImage {
     pub fn data_mut(&mut self) -> &mut [[f32; 3]] {
         &mut self.data
     }
+
+    /// Assembles an image from rows of linear RGB pixels as they arrive.
+    ///
+    /// Useful with decoders that emit rows progressively: rows are copied into
+    /// the image one at a time, so the caller never has to buffer a full frame
+    /// in its own format.
+    ///
+    /// # Errors
+    /// - If a row doesn't hold exactly `width` pixels
+    /// - If the iterator yields more or fewer than `height` rows
+    pub fn from_rows<'a, I>(rows: I, width: usize, height: usize) -> Result<Self, Ssimulacra2Error>
+    where
+        I: IntoIterator<Item = &'a [[f32; 3]]>,
+    {
+        let mut data = Vec::with_capacity(width * height);
+        let mut row_count = 0;
+        for row in rows {
+            if row.len() != width || row_count == height {
+                return Err(Ssimulacra2Error::RowDataMismatch);
+            }
+            data.extend_from_slice(row);
+            row_count += 1;
+        }
+        if row_count != height {
+            return Err(Ssimulacra2Error::RowDataMismatch);
+        }
+        Ok(Self::new(data, width, height))
+    }
+
+    /// Copies linear RGB rows that start every `stride` pixels, e.g. a
+    /// decoder's frame with padding after each row. The last row may end
+    /// right after its `width` pixels.
+    ///
+    /// # Errors
+    /// - If `stride` is less than `width`, or `pixels` is too short for
+    ///   `height` rows
+    pub fn from_strided(
+        pixels: &[[f32; 3]],
+        width: usize,
+        height: usize,
+        stride: usize,
+    ) -> Result<Self, Ssimulacra2Error> {
+        let rows = strided_rows(pixels, width, height, stride)?;
+        Ok(Self::new(rows.flatten().copied().collect(), width, height))
+    }
+
+    /// Converts packed 8-bit sRGB bytes (`RGBRGB...`, row-major, no padding)
+    /// to linear RGB in a single pass through [`SRGB_U8_TO_LINEAR`].
+    ///
+    /// This is the cheapest way in from decoded 8-bit images without the
+    /// `imgref` feature.
+    ///
+    /// # Errors
+    /// - If `bytes` doesn't hold exactly `width * height * 3` values
+    pub fn from_srgb_u8(
+        bytes: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<Self, Ssimulacra2Error> {
+        if width.checked_mul(height).and_then(|n| n.checked_mul(3)) != Some(bytes.len()) {
+            return Err(Ssimulacra2Error::RowDataMismatch);
+        }
+        Self::from_srgb_u8_strided(bytes, width, height, width * 3)
+    }
+
+    /// Like [`from_srgb_u8`](Self::from_srgb_u8), for rows that start every
+    /// `stride` bytes. Padding between rows is skipped, and the last row may
+    /// end right after its `width * 3` bytes.
+    ///
+    /// # Errors
+    /// - If `stride` is less than `width * 3`, or `bytes` is too short for
+    ///   `height` rows
+    pub fn from_srgb_u8_strided(
+        bytes: &[u8],
+        width: usize,
+        height: usize,
+        stride: usize,
+    ) -> Result<Self, Ssimulacra2Error> {
+        let row_bytes = width
+            .checked_mul(3)
+            .ok_or(Ssimulacra2Error::RowDataMismatch)?;
+        let data = strided_rows(bytes, row_bytes, height, stride)?
+            .flat_map(|row| row.chunks_exact(3))
+            .map(|px| {
+                [
+                    SRGB_U8_TO_LINEAR[usize::from(px[0])],
+                    SRGB_U8_TO_LINEAR[usize::from(px[1])],
+                    SRGB_U8_TO_LINEAR[usize::from(px[2])],
+                ]
+            })
+            .collect();
+        Ok(Self::new(data, width, height).with_colorimetry(Colorimetry::default()))
+    }
+
+    /// Converts packed 8-bit sRGB bytes with alpha (`RGBARGBA...`, the layout
+    /// of canvas `ImageData`) to linear RGB. Alpha is kept as the image's
+    /// alpha plane, which the default [`AlphaMode`](crate::AlphaMode) ignores.
+    ///
+    /// # Errors
+    /// - If `bytes` doesn't hold exactly `width * height * 4` values
+    pub fn from_srgba_u8(
+        bytes: &[u8],
+        width: usize,
+        height: usize,
+    ) -> Result<Self, Ssimulacra2Error> {
+        if width.checked_mul(height).and_then(|n| n.checked_mul(4)) != Some(bytes.len()) {
+            return Err(Ssimulacra2Error::RowDataMismatch);
+        }
+        Self::from_srgba_u8_strided(bytes, width, height, width * 4)
+    }
+
+    /// Like [`from_srgba_u8`](Self::from_srgba_u8), for rows that start
+    /// every `stride` bytes.
+    ///
+    /// # Errors
+    /// - If `stride` is less than `width * 4`, or `bytes` is too short for
+    ///   `height` rows
+    pub fn from_srgba_u8_strided(
+        bytes: &[u8],
+        width: usize,
+        height: usize,
+        stride: usize,
+    ) -> Result<Self, Ssimulacra2Error> {
+        let row_bytes = width
+            .checked_mul(4)
+            .ok_or(Ssimulacra2Error::RowDataMismatch)?;
+        let pixels = || {
+            strided_rows(bytes, row_bytes, height, stride)
+                .map(|rows| rows.flat_map(|row| row.chunks_exact(4)))
+        };
+        let data = pixels()?
+            .map(|px| {
+                [
+                    SRGB_U8_TO_LINEAR[usize::from(px[0])],
+                    SRGB_U8_TO_LINEAR[usize::from(px[1])],
+                    SRGB_U8_TO_LINEAR[usize::from(px[2])],
+                ]
+            })
+            .collect();
+        let alpha = pixels()?.map(|px| alpha_u8(px[3])).collect();
+        Self::new(data, width, height)
+            .with_colorimetry(Colorimetry::default())
+            .with_alpha(alpha)
+    }
+
+    /// Copies out a rectangular region of the image, alpha included.
+    pub(crate) fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        debug_assert!(x + width <= self.width && y + height <= self.height);
+        let data = self
+            .data
+            .chunks_exact(self.width)
+            .skip(y)
+            .take(height)
+            .flat_map(|row| &row[x..x + width])
+            .copied()
+            .collect();
+        let mut cropped = Self::new(data, width, height);
+        cropped.colorimetry = self.colorimetry;
+        cropped.alpha = self.alpha.as_ref().map(|alpha| {
+            alpha
+                .chunks_exact(self.width)
+                .skip(y)
+                .take(height)
+                .flat_map(|row| &row[x..x + width])
+                .copied()
+                .collect()
+        });
+        cropped
+    }
+}
+
+/// Replaces subnormal samples with zero, copying the image only if it has
+/// any (see [`flush_subnormals`](crate::Ssimulacra2Config::flush_subnormals))
+pub(crate) fn flush_subnormals(image: &mut Cow<'_, LinearRgbImage>) {
+    if image.data.iter().flatten().any(|v| v.is_subnormal()) {
+        for v in image.to_mut().data.iter_mut().flatten() {
+            if v.is_subnormal() {
+                *v = 0.0;
+            }
+        }
+    }
+}
+
+/// Returns `true` if `len` elements hold `height` rows of `width` that
+/// start every `stride` elements. The last row needs no padding.
+fn fits_strided(len: usize, width: usize, height: usize, stride: usize) -> bool {
+    height == 0
+        || (stride >= width
+            && (height - 1)
+                .checked_mul(stride)
+                .and_then(|n| n.checked_add(width))
+                .is_some_and(|n| n <= len))
+}
+
+/// The `height` rows of `width` elements in `data`, which starts a row
+/// every `stride` elements.
+///
+/// # Errors
+/// - If the rows don't fit (see [`fits_strided`])
+fn strided_rows<T>(
+    data: &[T],
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> Result<impl Iterator<Item = &[T]>, Ssimulacra2Error> {
+    if !fits_strided(data.len(), width, height, stride) {
+        return Err(Ssimulacra2Error::RowDataMismatch);
+    }
+    Ok((0..height).map(move |y| &data[y * stride..][..width]))
+}
+
+fn alpha_u8(a: u8) -> f32 {
+    f32::from(a) / 255.0
+}
+
+#[cfg(feature = "imgref")]
+fn alpha_u16(a: u16) -> f32 {
+    f32::from(a) / 65535.0
 }
 
 /// Trait for converting image types to linear RGB.
 ///
 /// Implement this trait to add support for custom image types.
+///
+/// References and [`Arc`]s of implementing types implement it too, so callers
+/// can score borrowed or shared images; an already linear
+/// [`LinearRgbImage`] is then used in place without copying.
 pub trait ToLinearRgb {
     /// Convert to linear RGB image.
     fn to_linear_rgb(&self) -> LinearRgbImage;
+
+    /// Borrow as a linear RGB image, converting only if needed.
+    ///
+    /// The default converts with [`to_linear_rgb`](Self::to_linear_rgb);
+    /// types that already hold linear RGB return a borrow.
+    fn as_linear_rgb(&self) -> Cow<'_, LinearRgbImage> {
+        Cow::Owned(self.to_linear_rgb())
+    }
+
+    /// Convert into a linear RGB image, for callers that keep the result.
+    ///
+    /// The default converts with [`to_linear_rgb`](Self::to_linear_rgb);
+    /// an owned [`LinearRgbImage`] is returned as is.
+    fn into_linear_rgb(self) -> LinearRgbImage
+    where
+        Self: Sized,
+    {
+        self.to_linear_rgb()
+    }
 }
 
 /// Identity implementation for already-converted images.
@@ -73,6 +388,551 @@ impl ToLinearRgb for LinearRgbImage {
     fn to_linear_rgb(&self) -> LinearRgbImage {
         self.clone()
     }
+
+    fn as_linear_rgb(&self) -> Cow<'_, LinearRgbImage> {
+        Cow::Borrowed(self)
+    }
+
+    fn into_linear_rgb(self) -> LinearRgbImage {
+        self
+    }
+}
+
+impl<T: ToLinearRgb + ?Sized> ToLinearRgb for &T {
+    fn to_linear_rgb(&self) -> LinearRgbImage {
+        (**self).to_linear_rgb()
+    }
+
+    fn as_linear_rgb(&self) -> Cow<'_, LinearRgbImage> {
+        (**self).as_linear_rgb()
+    }
+}
+
+impl<T: ToLinearRgb + ?Sized> ToLinearRgb for Arc<T> {
+    fn to_linear_rgb(&self) -> LinearRgbImage {
+        (**self).to_linear_rgb()
+    }
+
+    fn as_linear_rgb(&self) -> Cow<'_, LinearRgbImage> {
+        (**self).as_linear_rgb()
+    }
+}
+
+// =============================================================================
+// Planar YUV
+// =============================================================================
+
+/// A planar YUV frame borrowed from a decoder or encoder, e.g. 8-bit 4:2:0 or
+/// 10-bit 4:4:4.
+///
+/// Bit depth, chroma subsampling, range, matrix coefficients, transfer
+/// characteristics and primaries come from `config`, as for `yuvxyb::Yuv`.
+/// Samples are `u8` for 8-bit video and `u16` (low bits) for 8- to 16-bit
+/// video. Chroma planes of subsampled frames are `width.div_ceil(2)` wide
+/// (and as many rows high for 4:2:0).
+///
+/// Conversion skips the intermediate `Frame` and packed RGB copies: chroma is
+/// upsampled by repeating samples and the YUV to RGB matrix is applied eight
+/// pixels at a time. Transfer and primaries are then converted exactly as
+/// `yuvxyb` does, so scores match scoring the same frame as a `yuvxyb::Yuv`.
+#[cfg(feature = "yuvxyb")]
+#[derive(Debug, Clone, Copy)]
+pub struct YuvPlanes<'a, T> {
+    planes: [&'a [T]; 3],
+    luma_stride: usize,
+    chroma_stride: usize,
+    width: usize,
+    height: usize,
+    config: YuvConfig,
+}
+
+#[cfg(feature = "yuvxyb")]
+impl<'a, T: Pixel> YuvPlanes<'a, T> {
+    /// Wraps tightly packed planes (each row immediately follows the previous
+    /// one).
+    ///
+    /// # Errors
+    /// - If a plane is too small for the frame dimensions
+    /// - If `config` has a bit depth that doesn't fit `T`, subsampling other
+    ///   than 4:4:4, 4:2:2 or 4:2:0, or an unspecified or unsupported matrix,
+    ///   transfer or primaries
+    pub fn new(
+        y: &'a [T],
+        u: &'a [T],
+        v: &'a [T],
+        width: usize,
+        height: usize,
+        config: YuvConfig,
+    ) -> Result<Self, Ssimulacra2Error> {
+        let planes = Self {
+            planes: [y, u, v],
+            luma_stride: width,
+            chroma_stride: 0,
+            width,
+            height,
+            config,
+        };
+        let (chroma_width, _) = planes.chroma_size();
+        planes.with_strides(width, chroma_width)
+    }
+
+    /// Uses row strides (in samples) for planes with padding between rows.
+    ///
+    /// # Errors
+    /// - If a stride is shorter than its plane's rows, or a plane is too
+    ///   small for its stride
+    pub fn with_strides(
+        mut self,
+        luma_stride: usize,
+        chroma_stride: usize,
+    ) -> Result<Self, Ssimulacra2Error> {
+        self.luma_stride = luma_stride;
+        self.chroma_stride = chroma_stride;
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Frame width in luma samples
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Frame height in luma samples
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The color description of the frame
+    pub fn config(&self) -> YuvConfig {
+        self.config
+    }
+
+    fn chroma_size(&self) -> (usize, usize) {
+        let subsample = |size: usize, ss: u8| if ss == 1 { size.div_ceil(2) } else { size };
+        (
+            subsample(self.width, self.config.subsampling_x),
+            subsample(self.height, self.config.subsampling_y),
+        )
+    }
+
+    fn validate(&self) -> Result<(), Ssimulacra2Error> {
+        let config = self.config;
+        let depths = if std::mem::size_of::<T>() == 1 {
+            8..=8
+        } else {
+            8..=16
+        };
+        let subsampling = matches!(
+            (config.subsampling_x, config.subsampling_y),
+            (0, 0) | (1, 0) | (1, 1)
+        );
+        if !depths.contains(&config.bit_depth)
+            || !subsampling
+            || YuvToRgb::luma_weights(config.matrix_coefficients).is_none()
+            || config.transfer_characteristics == TransferCharacteristic::Unspecified
+            || config.color_primaries == ColorPrimaries::Unspecified
+            || linearize(vec![[0.5; 3]], 1, 1, config).is_err()
+        {
+            return Err(Ssimulacra2Error::UnsupportedYuvFormat);
+        }
+
+        let (chroma_width, chroma_height) = self.chroma_size();
+        let fits = |plane: &[T], stride: usize, width: usize, height: usize| {
+            fits_strided(plane.len(), width, height, stride)
+        };
+        let [y, u, v] = self.planes;
+        if !fits(y, self.luma_stride, self.width, self.height)
+            || !fits(u, self.chroma_stride, chroma_width, chroma_height)
+            || !fits(v, self.chroma_stride, chroma_width, chroma_height)
+        {
+            return Err(Ssimulacra2Error::YuvPlaneSizeMismatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "yuvxyb")]
+impl<T: Pixel> ToLinearRgb for YuvPlanes<'_, T> {
+    fn to_linear_rgb(&self) -> LinearRgbImage {
+        let (width, height) = (self.width, self.height);
+        let config = self.config;
+        let coefficients = YuvToRgb::new(config);
+        let (ss_x, ss_y) = (config.subsampling_x, config.subsampling_y);
+        let to_f32 = |v: T| f32::from(u16::cast_from(v));
+
+        // Rows are staged as f32 and padded to whole vectors
+        let padded = width.next_multiple_of(LANES);
+        let mut rows = vec![0.0f32; 3 * padded];
+        let mut data = vec![[0.0f32; 3]; width * height];
+        let [y_plane, u_plane, v_plane] = self.planes;
+        for (row, out) in data.chunks_exact_mut(width.max(1)).enumerate() {
+            let (y_row, chroma_rows) = rows.split_at_mut(padded);
+            let (u_row, v_row) = chroma_rows.split_at_mut(padded);
+
+            let luma = &y_plane[row * self.luma_stride..][..width];
+            for (dst, &src) in y_row.iter_mut().zip(luma) {
+                *dst = to_f32(src);
+            }
+            let chroma_start = (row >> ss_y) * self.chroma_stride;
+            for (dst, src) in [(&mut *u_row, u_plane), (&mut *v_row, v_plane)] {
+                let src = &src[chroma_start..];
+                for (x, dst) in dst[..width].iter_mut().enumerate() {
+                    *dst = to_f32(src[x >> ss_x]);
+                }
+            }
+
+            yuv_row_to_rgb(y_row, u_row, v_row, out, &coefficients);
+        }
+
+        linearize(data, width, height, config)
+            .expect("transfer and primaries were validated in YuvPlanes::new")
+    }
+}
+
+/// Converts gamma-encoded RGB in the frame's primaries to linear BT.709 RGB
+/// through `yuvxyb`, as `yuvxyb::Yuv` to `LinearRgb` conversion does.
+#[cfg(feature = "yuvxyb")]
+fn linearize(
+    data: Vec<[f32; 3]>,
+    width: usize,
+    height: usize,
+    config: YuvConfig,
+) -> Result<LinearRgbImage, Ssimulacra2Error> {
+    let rgb = yuvxyb::Rgb::new(
+        data,
+        width,
+        height,
+        config.transfer_characteristics,
+        config.color_primaries,
+    )
+    .map_err(|_| Ssimulacra2Error::UnsupportedYuvFormat)?;
+    let linear =
+        yuvxyb::LinearRgb::try_from(rgb).map_err(|_| Ssimulacra2Error::UnsupportedYuvFormat)?;
+    Ok(linear.into())
+}
+
+#[cfg(feature = "yuvxyb")]
+const LANES: usize = 8;
+
+/// Sample normalization and YUV to RGB matrix for one [`YuvConfig`]
+#[cfg(feature = "yuvxyb")]
+struct YuvToRgb {
+    luma_scale: f32,
+    luma_offset: f32,
+    chroma_scale: f32,
+    chroma_offset: f32,
+    cr_to_r: f32,
+    cb_to_g: f32,
+    cr_to_g: f32,
+    cb_to_b: f32,
+}
+
+#[cfg(feature = "yuvxyb")]
+impl YuvToRgb {
+    /// `(kr, kb)` of the non-constant-luminance matrices
+    fn luma_weights(matrix: MatrixCoefficients) -> Option<(f32, f32)> {
+        Some(match matrix {
+            MatrixCoefficients::BT709 => (0.2126, 0.0722),
+            MatrixCoefficients::BT470BG | MatrixCoefficients::ST170M => (0.299, 0.114),
+            MatrixCoefficients::BT470M => (0.3, 0.11),
+            MatrixCoefficients::ST240M => (0.212, 0.087),
+            MatrixCoefficients::BT2020NonConstantLuminance => (0.2627, 0.0593),
+            _ => return None,
+        })
+    }
+
+    fn new(config: YuvConfig) -> Self {
+        let (kr, kb) = Self::luma_weights(config.matrix_coefficients)
+            .expect("matrix coefficients were validated in YuvPlanes::new");
+        let kg = 1.0 - kr - kb;
+
+        // Same normalization as yuvxyb: limited range luma spans 219 << (bd - 8)
+        // codes from 16 << (bd - 8), chroma 224 << (bd - 8) around the midpoint
+        let bit_depth = config.bit_depth;
+        let full = f64::from((1u32 << bit_depth) - 1);
+        let (luma_range, luma_offset) = if config.full_range {
+            (full, 0.0)
+        } else {
+            (
+                f64::from(219u32 << (bit_depth - 8)),
+                f64::from(16u32 << (bit_depth - 8)),
+            )
+        };
+        let chroma_range = if config.full_range {
+            full
+        } else {
+            f64::from(224u32 << (bit_depth - 8))
+        };
+        let chroma_offset = f64::from(1u32 << (bit_depth - 1));
+
+        Self {
+            luma_scale: (1.0 / luma_range) as f32,
+            luma_offset: (-luma_offset / luma_range) as f32,
+            chroma_scale: (1.0 / chroma_range) as f32,
+            chroma_offset: (-chroma_offset / chroma_range) as f32,
+            cr_to_r: 2.0 * (1.0 - kr),
+            cb_to_g: -2.0 * kb * (1.0 - kb) / kg,
+            cr_to_g: -2.0 * kr * (1.0 - kr) / kg,
+            cb_to_b: 2.0 * (1.0 - kb),
+        }
+    }
+}
+
+/// Converts one row of staged samples to gamma-encoded RGB. The inputs are
+/// padded to a multiple of [`LANES`]; only `out.len()` pixels are written.
+#[cfg(all(feature = "yuvxyb", feature = "simd"))]
+#[multiversion(targets("x86_64+avx2+fma", "x86_64+sse2", "aarch64+neon"))]
+fn yuv_row_to_rgb(y: &[f32], u: &[f32], v: &[f32], out: &mut [[f32; 3]], c: &YuvToRgb) {
+    let splat = f32x8::splat;
+    let (zero, one, half) = (splat(0.0), splat(1.0), splat(0.5));
+    let (luma_scale, luma_offset) = (splat(c.luma_scale), splat(c.luma_offset));
+    let (chroma_scale, chroma_offset) = (splat(c.chroma_scale), splat(c.chroma_offset));
+
+    for (((y, u), v), out) in y
+        .chunks_exact(LANES)
+        .zip(u.chunks_exact(LANES))
+        .zip(v.chunks_exact(LANES))
+        .zip(out.chunks_mut(LANES))
+    {
+        let load = |s: &[f32]| f32x8::from(<[f32; LANES]>::try_from(s).unwrap());
+        let luma = load(y).mul_add(luma_scale, luma_offset).max(zero).min(one);
+        let cb = load(u)
+            .mul_add(chroma_scale, chroma_offset)
+            .max(-half)
+            .min(half);
+        let cr = load(v)
+            .mul_add(chroma_scale, chroma_offset)
+            .max(-half)
+            .min(half);
+
+        let r = cr.mul_add(splat(c.cr_to_r), luma).to_array();
+        let g = cr
+            .mul_add(splat(c.cr_to_g), cb.mul_add(splat(c.cb_to_g), luma))
+            .to_array();
+        let b = cb.mul_add(splat(c.cb_to_b), luma).to_array();
+        for (i, px) in out.iter_mut().enumerate() {
+            *px = [r[i], g[i], b[i]];
+        }
+    }
+}
+
+/// Converts one row of staged samples to gamma-encoded RGB, one pixel at a
+/// time; only `out.len()` pixels are written.
+#[cfg(all(feature = "yuvxyb", not(feature = "simd")))]
+fn yuv_row_to_rgb(y: &[f32], u: &[f32], v: &[f32], out: &mut [[f32; 3]], c: &YuvToRgb) {
+    for (((&y, &u), &v), px) in y.iter().zip(u).zip(v).zip(out) {
+        let luma = y.mul_add(c.luma_scale, c.luma_offset).clamp(0.0, 1.0);
+        let cb = u.mul_add(c.chroma_scale, c.chroma_offset).clamp(-0.5, 0.5);
+        let cr = v.mul_add(c.chroma_scale, c.chroma_offset).clamp(-0.5, 0.5);
+        *px = [
+            cr.mul_add(c.cr_to_r, luma),
+            cr.mul_add(c.cr_to_g, cb.mul_add(c.cb_to_g, luma)),
+            cb.mul_add(c.cb_to_b, luma),
+        ];
+    }
+}
+
+// =============================================================================
+// Encoded RGB with an explicit transfer function
+// =============================================================================
+
+/// Packed RGB samples with an explicit transfer function, e.g. decoded HDR10
+/// (PQ, BT.2020) or HLG frames.
+///
+/// Integer samples are normalized by their full range (`/255`, `/65535`);
+/// `f32` samples are taken as already normalized. 10- and 12-bit samples
+/// stored in the low bits of `u16`, as AV1 and HEVC decoders output them,
+/// declare their depth with [`with_bit_depth`](EncodedRgb::with_bit_depth),
+/// and limited-range ("video" or "TV" range) samples are expanded with
+/// [`with_limited_range`](EncodedRgb::with_limited_range). PQ and HLG decode to
+/// absolute luminance, which is divided by the white level (by default
+/// [`HDR_REFERENCE_WHITE_NITS`](crate::HDR_REFERENCE_WHITE_NITS)) so that
+/// reference white lands at linear 1.0, like white in SDR inputs. The white
+/// level doesn't affect relative transfers.
+#[derive(Debug, Clone, Copy)]
+pub struct EncodedRgb<'a, T> {
+    pixels: &'a [[T; 3]],
+    width: usize,
+    height: usize,
+    stride: usize,
+    transfer: TransferFunction,
+    primaries: RgbPrimaries,
+    white_nits: f32,
+    bit_depth: Option<u8>,
+    limited_range: bool,
+}
+
+impl<'a, T: Copy> EncodedRgb<'a, T> {
+    /// Wraps packed row-major pixels with BT.709 primaries.
+    ///
+    /// # Errors
+    /// - If `pixels` doesn't hold exactly `width * height` pixels
+    pub fn new(
+        pixels: &'a [[T; 3]],
+        width: usize,
+        height: usize,
+        transfer: TransferFunction,
+    ) -> Result<Self, Ssimulacra2Error> {
+        if width.checked_mul(height) != Some(pixels.len()) {
+            return Err(Ssimulacra2Error::RowDataMismatch);
+        }
+        Self::new_strided(pixels, width, height, width, transfer)
+    }
+
+    /// Wraps row-major pixels whose rows start every `stride` pixels, e.g. a
+    /// decoded frame with padding after each row, with BT.709 primaries.
+    /// The last row may end right after its `width` pixels.
+    ///
+    /// # Errors
+    /// - If `stride` is less than `width`, or `pixels` is too short for
+    ///   `height` rows
+    pub fn new_strided(
+        pixels: &'a [[T; 3]],
+        width: usize,
+        height: usize,
+        stride: usize,
+        transfer: TransferFunction,
+    ) -> Result<Self, Ssimulacra2Error> {
+        if !fits_strided(pixels.len(), width, height, stride) {
+            return Err(Ssimulacra2Error::RowDataMismatch);
+        }
+        Ok(Self {
+            pixels,
+            width,
+            height,
+            stride,
+            transfer,
+            primaries: RgbPrimaries::Bt709,
+            white_nits: HDR_REFERENCE_WHITE_NITS,
+            bit_depth: None,
+            limited_range: false,
+        })
+    }
+
+    /// Sets the primaries the samples are in; BT.2020 is converted to the
+    /// metric's BT.709.
+    #[must_use]
+    pub fn with_primaries(mut self, primaries: RgbPrimaries) -> Self {
+        self.primaries = primaries;
+        self
+    }
+
+    /// Sets the luminance in cd/m² that maps to linear 1.0 for PQ and HLG.
+    #[must_use]
+    pub fn with_white_nits(mut self, nits: f32) -> Self {
+        self.white_nits = nits;
+        self
+    }
+
+    /// Marks integer samples as limited range: black and white sit at 16 and
+    /// 235 scaled to the bit depth (64 and 940 for 10-bit), and codes outside
+    /// that span are clipped by the transfer function. Has no effect on `f32`
+    /// samples.
+    #[must_use]
+    pub fn with_limited_range(mut self) -> Self {
+        self.limited_range = true;
+        self
+    }
+
+    /// Image width
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Image height
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Distance between the starts of consecutive rows, in pixels
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// The transfer function of the samples
+    pub fn transfer(&self) -> TransferFunction {
+        self.transfer
+    }
+
+    /// Whether integer samples are limited range
+    pub fn is_limited_range(&self) -> bool {
+        self.limited_range
+    }
+
+    /// Black level and black-to-white span of integer samples that are
+    /// `type_bits` wide unless a bit depth was set.
+    fn integer_range(&self, type_bits: u8) -> (f32, f32) {
+        let bits = self.bit_depth.unwrap_or(type_bits);
+        if self.limited_range {
+            ((16u32 << (bits - 8)) as f32, (219u32 << (bits - 8)) as f32)
+        } else {
+            (0.0, ((1u32 << bits) - 1) as f32)
+        }
+    }
+
+    fn convert(&self, to_unit: impl Fn(T) -> f32) -> LinearRgbImage {
+        let mut data: Vec<[f32; 3]> =
+            strided_rows(self.pixels, self.width, self.height, self.stride)
+                .expect("rows checked on construction")
+                .flatten()
+                .map(|px| px.map(&to_unit))
+                .collect();
+        transfer::to_linear_in_place(self.transfer, data.as_flattened_mut());
+        if self.transfer == TransferFunction::Hlg {
+            transfer::hlg_ootf(&mut data);
+        }
+        let scale = self
+            .transfer
+            .peak_nits()
+            .map_or(1.0, |peak| peak / self.white_nits);
+        self.primaries.to_bt709(&mut data, scale);
+        LinearRgbImage::new(data, self.width, self.height).with_colorimetry(Colorimetry {
+            primaries: RgbPrimaries::Bt709,
+            transfer: self.transfer,
+            limited_range: self.limited_range,
+        })
+    }
+}
+
+impl EncodedRgb<'_, u16> {
+    /// Sets how many low bits of each `u16` hold the sample, e.g. 10 for
+    /// 10-bit video, whose white is 1023 rather than 65535. Defaults to 16.
+    ///
+    /// # Errors
+    /// - If `bits` is not between 8 and 16
+    pub fn with_bit_depth(mut self, bits: u8) -> Result<Self, Ssimulacra2Error> {
+        if !(8..=16).contains(&bits) {
+            return Err(Ssimulacra2Error::UnsupportedBitDepth);
+        }
+        self.bit_depth = Some(bits);
+        Ok(self)
+    }
+
+    /// Bits per sample: 16 unless set with
+    /// [`with_bit_depth`](Self::with_bit_depth)
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth.unwrap_or(16)
+    }
+}
+
+impl ToLinearRgb for EncodedRgb<'_, u8> {
+    fn to_linear_rgb(&self) -> LinearRgbImage {
+        let (black, span) = self.integer_range(8);
+        self.convert(|v| (f32::from(v) - black) / span)
+    }
+}
+
+impl ToLinearRgb for EncodedRgb<'_, u16> {
+    fn to_linear_rgb(&self) -> LinearRgbImage {
+        let (black, span) = self.integer_range(16);
+        self.convert(|v| (f32::from(v) - black) / span)
+    }
+}
+
+impl ToLinearRgb for EncodedRgb<'_, f32> {
+    fn to_linear_rgb(&self) -> LinearRgbImage {
+        self.convert(|v| v)
+    }
 }
 
 // =============================================================================
@@ -94,25 +954,28 @@ pub fn srgb_to_linear(s: f32) -> f32 {
 /// Convert 8-bit sRGB value to linear f32.
 #[inline]
 pub fn srgb_u8_to_linear(v: u8) -> f32 {
-    // Use lookup table for performance
-    SRGB_TO_LINEAR_LUT[v as usize]
+    SRGB_U8_TO_LINEAR[v as usize]
 }
 
 /// Convert 16-bit sRGB value to linear f32.
 #[inline]
 pub fn srgb_u16_to_linear(v: u16) -> f32 {
-    srgb_to_linear(v as f32 / 65535.0)
+    SRGB_U16_TO_LINEAR[v as usize]
 }
 
-// Precomputed lookup table for sRGB u8 -> linear f32
-// Generated with: (0..256).map(|i| srgb_to_linear(i as f32 / 255.0))
-static SRGB_TO_LINEAR_LUT: std::sync::LazyLock<[f32; 256]> = std::sync::LazyLock::new(|| {
-    let mut lut = [0.0f32; 256];
-    for (i, entry) in lut.iter_mut().enumerate() {
-        *entry = srgb_to_linear(i as f32 / 255.0);
-    }
-    lut
-});
+/// Linear value of every 8-bit sRGB code value.
+///
+/// Generated at build time with `srgb_to_linear(v as f32 / 255.0)`, so entries
+/// match [`srgb_to_linear`] exactly and lookups need no runtime initialization.
+pub static SRGB_U8_TO_LINEAR: [f32; 256] =
+    include!(concat!(env!("OUT_DIR"), "/srgb_u8_to_linear.rs"));
+
+/// Linear value of every 16-bit sRGB code value (256 KiB).
+///
+/// Generated at build time with `srgb_to_linear(v as f32 / 65535.0)`, so
+/// entries match [`srgb_to_linear`] exactly.
+pub static SRGB_U16_TO_LINEAR: [f32; 65536] =
+    include!(concat!(env!("OUT_DIR"), "/srgb_u16_to_linear.rs"));
 
 // =============================================================================
 // imgref implementations
@@ -137,6 +1000,7 @@ mod imgref_impl {
                 })
                 .collect();
             LinearRgbImage::new(data, self.width(), self.height())
+                .with_colorimetry(Colorimetry::default())
         }
     }
 
@@ -154,6 +1018,7 @@ mod imgref_impl {
                 })
                 .collect();
             LinearRgbImage::new(data, self.width(), self.height())
+                .with_colorimetry(Colorimetry::default())
         }
     }
 
@@ -165,6 +1030,59 @@ mod imgref_impl {
         }
     }
 
+    /// RGBA u8 (sRGB, straight alpha) -> Linear RGB + alpha
+    impl ToLinearRgb for ImgRef<'_, [u8; 4]> {
+        fn to_linear_rgb(&self) -> LinearRgbImage {
+            let data: Vec<[f32; 3]> = self
+                .pixels()
+                .map(|[r, g, b, _]| {
+                    [
+                        srgb_u8_to_linear(r),
+                        srgb_u8_to_linear(g),
+                        srgb_u8_to_linear(b),
+                    ]
+                })
+                .collect();
+            let alpha = self.pixels().map(|[_, _, _, a]| alpha_u8(a)).collect();
+            LinearRgbImage::new(data, self.width(), self.height())
+                .with_colorimetry(Colorimetry::default())
+                .with_alpha(alpha)
+                .expect("one alpha value per pixel")
+        }
+    }
+
+    /// RGBA u16 (sRGB, straight alpha) -> Linear RGB + alpha
+    impl ToLinearRgb for ImgRef<'_, [u16; 4]> {
+        fn to_linear_rgb(&self) -> LinearRgbImage {
+            let data: Vec<[f32; 3]> = self
+                .pixels()
+                .map(|[r, g, b, _]| {
+                    [
+                        srgb_u16_to_linear(r),
+                        srgb_u16_to_linear(g),
+                        srgb_u16_to_linear(b),
+                    ]
+                })
+                .collect();
+            let alpha = self.pixels().map(|[_, _, _, a]| alpha_u16(a)).collect();
+            LinearRgbImage::new(data, self.width(), self.height())
+                .with_colorimetry(Colorimetry::default())
+                .with_alpha(alpha)
+                .expect("one alpha value per pixel")
+        }
+    }
+
+    /// RGBA f32 (linear, straight alpha) -> Linear RGB + alpha
+    impl ToLinearRgb for ImgRef<'_, [f32; 4]> {
+        fn to_linear_rgb(&self) -> LinearRgbImage {
+            let data: Vec<[f32; 3]> = self.pixels().map(|[r, g, b, _]| [r, g, b]).collect();
+            let alpha = self.pixels().map(|[_, _, _, a]| a).collect();
+            LinearRgbImage::new(data, self.width(), self.height())
+                .with_alpha(alpha)
+                .expect("one alpha value per pixel")
+        }
+    }
+
     /// Grayscale u8 (sRGB) -> Linear RGB
     impl ToLinearRgb for ImgRef<'_, u8> {
         fn to_linear_rgb(&self) -> LinearRgbImage {
@@ -176,6 +1094,7 @@ mod imgref_impl {
                 })
                 .collect();
             LinearRgbImage::new(data, self.width(), self.height())
+                .with_colorimetry(Colorimetry::default())
         }
     }
 
@@ -192,16 +1111,26 @@ mod imgref_impl {
 // yuvxyb compatibility
 // =============================================================================
 
+#[cfg(feature = "yuvxyb")]
 impl ToLinearRgb for yuvxyb::LinearRgb {
     fn to_linear_rgb(&self) -> LinearRgbImage {
         LinearRgbImage::new(self.data().to_vec(), self.width(), self.height())
     }
+
+    fn into_linear_rgb(self) -> LinearRgbImage {
+        self.into()
+    }
 }
 
-// =============================================================================
-// Conversion to yuvxyb::LinearRgb (for internal pipeline)
-// =============================================================================
+#[cfg(feature = "yuvxyb")]
+impl From<yuvxyb::LinearRgb> for LinearRgbImage {
+    fn from(img: yuvxyb::LinearRgb) -> Self {
+        let (width, height) = (img.width(), img.height());
+        LinearRgbImage::new(img.into_data(), width, height)
+    }
+}
 
+#[cfg(feature = "yuvxyb")]
 impl From<LinearRgbImage> for yuvxyb::LinearRgb {
     fn from(img: LinearRgbImage) -> Self {
         yuvxyb::LinearRgb::new(img.data, img.width, img.height)
@@ -209,12 +1138,13 @@ impl From<LinearRgbImage> for yuvxyb::LinearRgb {
     }
 }
 
+#[cfg(feature = "yuvxyb")]
 impl ToLinearRgb for yuvxyb::Rgb {
     fn to_linear_rgb(&self) -> LinearRgbImage {
         // yuvxyb::Rgb handles the sRGB -> linear conversion internally via TryFrom
         let linear: yuvxyb::LinearRgb = yuvxyb::LinearRgb::try_from(self.clone())
             .expect("Rgb to LinearRgb conversion should not fail");
-        linear.to_linear_rgb()
+        linear.into()
     }
 }
 
@@ -222,6 +1152,56 @@ impl ToLinearRgb for yuvxyb::Rgb {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_from_rows() {
+        let rows: Vec<Vec<[f32; 3]>> = (0..3)
+            .map(|y| (0..4).map(|x| [x as f32, y as f32, 0.0]).collect())
+            .collect();
+        let img = LinearRgbImage::from_rows(rows.iter().map(Vec::as_slice), 4, 3).unwrap();
+        assert_eq!(img.data()[4 + 2], [2.0, 1.0, 0.0]);
+
+        let short = LinearRgbImage::from_rows(rows.iter().map(Vec::as_slice), 4, 4);
+        assert!(matches!(short, Err(Ssimulacra2Error::RowDataMismatch)));
+        let narrow = LinearRgbImage::from_rows(rows.iter().map(|r| &r[..3]), 4, 3);
+        assert!(matches!(narrow, Err(Ssimulacra2Error::RowDataMismatch)));
+    }
+
+    #[test]
+    fn test_flush_subnormals() {
+        let (width, height) = (40, 30);
+        let pattern = |scale: f32| {
+            let data = (0..width * height)
+                .map(|i| {
+                    let v = ((i * 37) % 101) as f32 / 100.0;
+                    [v, 1.0 - v, v * 0.5].map(|c| c * scale)
+                })
+                .collect();
+            LinearRgbImage::new(data, width, height)
+        };
+        let (dark, darker) = (pattern(1e-39), pattern(5e-40));
+
+        let mut flushed = Cow::Borrowed(&dark);
+        flush_subnormals(&mut flushed);
+        assert!(flushed.data().iter().flatten().all(|&v| v == 0.0));
+        let ordinary = pattern(1.0);
+        let mut unchanged = Cow::Borrowed(&ordinary);
+        flush_subnormals(&mut unchanged);
+        assert!(matches!(unchanged, Cow::Borrowed(_)));
+
+        // Subnormals vanish in the opsin bias either way
+        let config = crate::Ssimulacra2Config::default();
+        for (source, distorted) in [(&dark, &darker), (&ordinary, &darker)] {
+            assert_eq!(
+                crate::compute_ssimulacra2_with_config(source, distorted, config.clone()),
+                crate::compute_ssimulacra2_with_config(
+                    source,
+                    distorted,
+                    config.clone().with_flush_subnormals(true)
+                )
+            );
+        }
+    }
+
     #[test]
     fn test_srgb_to_linear_bounds() {
         assert!((srgb_to_linear(0.0) - 0.0).abs() < 1e-6);
@@ -241,6 +1221,26 @@ mod tests {
         assert!((srgb_u8_to_linear(255) - 1.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_srgb_tables_match_function() {
+        for v in 0..=u8::MAX {
+            assert_eq!(
+                srgb_u8_to_linear(v).to_bits(),
+                srgb_to_linear(f32::from(v) / 255.0).to_bits(),
+                "u8 {}",
+                v
+            );
+        }
+        for v in 0..=u16::MAX {
+            assert_eq!(
+                srgb_u16_to_linear(v).to_bits(),
+                srgb_to_linear(f32::from(v) / 65535.0).to_bits(),
+                "u16 {}",
+                v
+            );
+        }
+    }
+
     #[test]
     fn test_linear_rgb_image_accessors() {
         let data = vec![[0.5, 0.3, 0.1], [0.2, 0.4, 0.6]];
@@ -251,6 +1251,268 @@ mod tests {
         assert_eq!(img.data(), &data[..]);
     }
 
+    #[test]
+    fn test_from_srgb_u8() {
+        let bytes = [0u8, 128, 255, 10, 20, 30];
+        let img = LinearRgbImage::from_srgb_u8(&bytes, 2, 1).unwrap();
+        assert_eq!((img.width(), img.height()), (2, 1));
+        for (&v, &b) in img.data().iter().flatten().zip(&bytes) {
+            assert_eq!(v, srgb_u8_to_linear(b));
+        }
+        assert!(matches!(
+            LinearRgbImage::from_srgb_u8(&bytes, 2, 2),
+            Err(Ssimulacra2Error::RowDataMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_from_srgba_u8_discards_alpha() {
+        let rgba = [0u8, 128, 255, 7, 10, 20, 30, 255];
+        let rgb = [0u8, 128, 255, 10, 20, 30];
+        let img = LinearRgbImage::from_srgba_u8(&rgba, 2, 1).unwrap();
+        assert_eq!(
+            img.data(),
+            LinearRgbImage::from_srgb_u8(&rgb, 2, 1).unwrap().data()
+        );
+        assert!(matches!(
+            LinearRgbImage::from_srgba_u8(&rgb, 2, 1),
+            Err(Ssimulacra2Error::RowDataMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_strided_inputs_skip_padding() {
+        // 3x2 pixels in rows of 13 bytes (9 of pixels, 4 of padding), with
+        // no padding after the last row
+        let packed: Vec<u8> = (0..18).map(|v| v * 10).collect();
+        let mut padded = Vec::new();
+        for row in packed.chunks(9) {
+            padded.extend_from_slice(row);
+            padded.extend_from_slice(&[99; 4]);
+        }
+        padded.truncate(padded.len() - 4);
+        let expected = LinearRgbImage::from_srgb_u8(&packed, 3, 2).unwrap();
+        let strided = LinearRgbImage::from_srgb_u8_strided(&padded, 3, 2, 13).unwrap();
+        assert_eq!(strided.data(), expected.data());
+
+        let rgba: Vec<u8> = packed
+            .chunks(3)
+            .flat_map(|px| [px[0], px[1], px[2], 200])
+            .collect();
+        let mut padded_rgba = Vec::new();
+        for row in rgba.chunks(12) {
+            padded_rgba.extend_from_slice(row);
+            padded_rgba.extend_from_slice(&[99; 8]);
+        }
+        let strided = LinearRgbImage::from_srgba_u8_strided(&padded_rgba, 3, 2, 20).unwrap();
+        assert_eq!(strided.data(), expected.data());
+        assert_eq!(strided.alpha(), Some(&[alpha_u8(200); 6][..]));
+
+        let linear: Vec<[f32; 3]> = (0..8).map(|v| [v as f32; 3]).collect();
+        let strided = LinearRgbImage::from_strided(&linear, 3, 2, 4).unwrap();
+        let columns: Vec<f32> = strided.data().iter().map(|px| px[0]).collect();
+        assert_eq!(columns, [0.0, 1.0, 2.0, 4.0, 5.0, 6.0]);
+
+        for (stride, len) in [(8, 17), (13, 21)] {
+            assert!(matches!(
+                LinearRgbImage::from_srgb_u8_strided(&padded[..len], 3, 2, stride),
+                Err(Ssimulacra2Error::RowDataMismatch)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_borrowed_and_shared_inputs_avoid_copies() {
+        let img = LinearRgbImage::new(vec![[0.5, 0.3, 0.1]; 16 * 16], 16, 16);
+        assert!(matches!(img.as_linear_rgb(), Cow::Borrowed(_)));
+        assert!(matches!(
+            <&LinearRgbImage as ToLinearRgb>::as_linear_rgb(&&img),
+            Cow::Borrowed(_)
+        ));
+
+        let shared = Arc::new(img.clone());
+        match shared.as_linear_rgb() {
+            Cow::Borrowed(borrowed) => assert!(std::ptr::eq(borrowed, &*shared)),
+            Cow::Owned(_) => panic!("Arc<LinearRgbImage> should not be copied"),
+        }
+
+        let by_value = crate::compute_ssimulacra2(img.clone(), img.clone()).unwrap();
+        assert_eq!(crate::compute_ssimulacra2(&img, &img).unwrap(), by_value);
+        assert_eq!(
+            crate::compute_ssimulacra2(Arc::clone(&shared), &*shared).unwrap(),
+            by_value
+        );
+    }
+
+    #[cfg(feature = "yuvxyb")]
+    fn yuv_config(bit_depth: u8, ss: (u8, u8), full_range: bool) -> YuvConfig {
+        YuvConfig {
+            bit_depth,
+            subsampling_x: ss.0,
+            subsampling_y: ss.1,
+            full_range,
+            matrix_coefficients: MatrixCoefficients::BT709,
+            transfer_characteristics: TransferCharacteristic::BT1886,
+            color_primaries: ColorPrimaries::BT709,
+        }
+    }
+
+    /// Pseudo-random planes covering the whole code range, plus the chroma
+    /// plane size
+    #[cfg(feature = "yuvxyb")]
+    fn yuv_planes<T: Pixel>(
+        width: usize,
+        height: usize,
+        config: YuvConfig,
+    ) -> ([Vec<T>; 3], usize, usize) {
+        let chroma_width = width >> config.subsampling_x;
+        let chroma_height = height >> config.subsampling_y;
+        let max = (1u32 << config.bit_depth) - 1;
+        let plane = |len: usize, seed: usize| {
+            (0..len)
+                .map(|i| T::cast_from(((i * 2_654_435_761 + seed) % (max as usize + 1)) as u16))
+                .collect::<Vec<T>>()
+        };
+        (
+            [
+                plane(width * height, 1),
+                plane(chroma_width * chroma_height, 2),
+                plane(chroma_width * chroma_height, 3),
+            ],
+            chroma_width,
+            chroma_height,
+        )
+    }
+
+    #[cfg(feature = "yuvxyb")]
+    fn yuvxyb_linear<T: Pixel>(
+        planes: &[Vec<T>; 3],
+        width: usize,
+        height: usize,
+        config: YuvConfig,
+    ) -> yuvxyb::LinearRgb {
+        let (ss_x, ss_y) = (config.subsampling_x, config.subsampling_y);
+        let plane = |data: &[T], xdec: u8, ydec: u8| {
+            let (w, h) = (width >> xdec, height >> ydec);
+            let mut plane = yuvxyb::Plane::new(w, h, xdec.into(), ydec.into(), 0, 0);
+            for (row, src) in plane.rows_iter_mut().zip(data.chunks_exact(w)) {
+                row[..w].copy_from_slice(src);
+            }
+            plane
+        };
+        let frame = yuvxyb::Frame {
+            planes: [
+                plane(&planes[0], 0, 0),
+                plane(&planes[1], ss_x, ss_y),
+                plane(&planes[2], ss_x, ss_y),
+            ],
+        };
+        let yuv = yuvxyb::Yuv::new(frame, config).unwrap();
+        yuvxyb::LinearRgb::try_from(yuv).unwrap()
+    }
+
+    #[cfg(feature = "yuvxyb")]
+    fn assert_yuv_matches_yuvxyb<T: Pixel>(config: YuvConfig) {
+        let (width, height) = (24, 10);
+        let (planes, chroma_width, _) = yuv_planes::<T>(width, height, config);
+        let expected = yuvxyb_linear(&planes, width, height, config);
+        let actual = YuvPlanes::new(&planes[0], &planes[1], &planes[2], width, height, config)
+            .unwrap()
+            .to_linear_rgb();
+        assert_eq!(chroma_width, width >> config.subsampling_x);
+        assert_eq!((actual.width(), actual.height()), (width, height));
+        for (i, (a, e)) in actual.data().iter().zip(expected.data()).enumerate() {
+            for c in 0..3 {
+                assert!(
+                    (a[c] - e[c]).abs() < 1e-5,
+                    "{:?} pixel {} channel {}: {} vs {}",
+                    config,
+                    i,
+                    c,
+                    a[c],
+                    e[c]
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "yuvxyb")]
+    #[test]
+    fn test_yuv_planes_match_yuvxyb() {
+        assert_yuv_matches_yuvxyb::<u8>(yuv_config(8, (1, 1), false));
+        assert_yuv_matches_yuvxyb::<u8>(yuv_config(8, (1, 0), true));
+        assert_yuv_matches_yuvxyb::<u16>(yuv_config(10, (0, 0), false));
+        assert_yuv_matches_yuvxyb::<u16>(YuvConfig {
+            matrix_coefficients: MatrixCoefficients::BT2020NonConstantLuminance,
+            color_primaries: ColorPrimaries::BT2020,
+            ..yuv_config(12, (1, 1), true)
+        });
+        assert_yuv_matches_yuvxyb::<u8>(YuvConfig {
+            matrix_coefficients: MatrixCoefficients::ST170M,
+            transfer_characteristics: TransferCharacteristic::SRGB,
+            ..yuv_config(8, (0, 0), true)
+        });
+    }
+
+    #[cfg(feature = "yuvxyb")]
+    #[test]
+    fn test_yuv_planes_strides_and_odd_sizes() {
+        let config = yuv_config(8, (1, 1), false);
+        // 5x3 4:2:0 has 3x2 chroma; pad every row by two samples
+        let y: Vec<u8> = (0..7 * 3).map(|i| (i * 11) as u8).collect();
+        let u: Vec<u8> = (0..5 * 2).map(|i| (100 + i * 7) as u8).collect();
+        let v: Vec<u8> = (0..5 * 2).map(|i| (150 - i * 5) as u8).collect();
+        let padded = YuvPlanes::new(&y, &u, &v, 5, 3, config)
+            .unwrap()
+            .with_strides(7, 5)
+            .unwrap()
+            .to_linear_rgb();
+
+        let strip = |plane: &[u8], stride: usize, width: usize| {
+            plane
+                .chunks(stride)
+                .flat_map(|row| &row[..width])
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        let (y, u, v) = (strip(&y, 7, 5), strip(&u, 5, 3), strip(&v, 5, 3));
+        let packed = YuvPlanes::new(&y, &u, &v, 5, 3, config)
+            .unwrap()
+            .to_linear_rgb();
+        assert_eq!(padded.data(), packed.data());
+        // Odd widths share the last chroma column
+        assert_eq!(packed.data().len(), 15);
+
+        assert!(matches!(
+            YuvPlanes::new(&y, &u[..5], &v, 5, 3, config),
+            Err(Ssimulacra2Error::YuvPlaneSizeMismatch)
+        ));
+        assert!(matches!(
+            YuvPlanes::new(&y, &u, &v, 5, 3, config)
+                .unwrap()
+                .with_strides(4, 3),
+            Err(Ssimulacra2Error::YuvPlaneSizeMismatch)
+        ));
+        for unsupported in [
+            yuv_config(10, (1, 1), false),
+            yuv_config(8, (0, 1), false),
+            YuvConfig {
+                matrix_coefficients: MatrixCoefficients::Unspecified,
+                ..config
+            },
+            YuvConfig {
+                transfer_characteristics: TransferCharacteristic::ST428,
+                ..config
+            },
+        ] {
+            assert!(matches!(
+                YuvPlanes::new(&y, &u, &v, 5, 3, unsupported),
+                Err(Ssimulacra2Error::UnsupportedYuvFormat)
+            ));
+        }
+    }
+
+    #[cfg(feature = "yuvxyb")]
     #[test]
     fn test_yuvxyb_linearrgb_roundtrip() {
         let data = vec![[0.5, 0.3, 0.1]; 4];
@@ -265,6 +1527,153 @@ mod tests {
         let back: yuvxyb::LinearRgb = our_img.into();
         assert_eq!(back.data(), &data[..]);
     }
+
+    #[test]
+    fn test_encoded_rgb_srgb_matches_tables() {
+        let pixels: Vec<[u8; 3]> = (0..=255u8).map(|v| [v, v / 2, 255 - v]).collect();
+        let encoded = EncodedRgb::new(&pixels, 16, 16, TransferFunction::Srgb).unwrap();
+        let linear = encoded.to_linear_rgb();
+        for (px, out) in pixels.iter().zip(linear.data()) {
+            for c in 0..3 {
+                let expected = srgb_u8_to_linear(px[c]);
+                assert!((out[c] - expected).abs() < 1e-5, "{:?} {:?}", px, out);
+            }
+        }
+
+        let short = EncodedRgb::new(&pixels, 16, 17, TransferFunction::Srgb);
+        assert!(matches!(short, Err(Ssimulacra2Error::RowDataMismatch)));
+
+        // The left 10 columns, read in place with the full rows as stride
+        let strided = EncodedRgb::new_strided(&pixels, 10, 16, 16, TransferFunction::Srgb).unwrap();
+        assert_eq!(strided.stride(), 16);
+        let cropped: Vec<[u8; 3]> = pixels
+            .chunks(16)
+            .flat_map(|row| &row[..10])
+            .copied()
+            .collect();
+        let expected = EncodedRgb::new(&cropped, 10, 16, TransferFunction::Srgb).unwrap();
+        assert_eq!(
+            strided.to_linear_rgb().data(),
+            expected.to_linear_rgb().data()
+        );
+        let narrow = EncodedRgb::new_strided(&pixels, 10, 16, 9, TransferFunction::Srgb);
+        assert!(matches!(narrow, Err(Ssimulacra2Error::RowDataMismatch)));
+    }
+
+    #[test]
+    fn test_encoded_rgb_hdr_white_levels() {
+        // PQ for 203 cd/m², the default reference white
+        let code = {
+            let y = (HDR_REFERENCE_WHITE_NITS / 10_000.0).powf(0.159_301_76);
+            ((0.835_937_5 + 18.851_563 * y) / (1.0 + 18.6875 * y)).powf(78.843_75)
+        };
+        let pixels = vec![[code; 3]; 64];
+        let pq = EncodedRgb::new(&pixels, 8, 8, TransferFunction::Pq).unwrap();
+        for v in pq.to_linear_rgb().data()[0] {
+            assert!((v - 1.0).abs() < 1e-3, "{}", v);
+        }
+        let brighter = pq.with_white_nits(101.5).to_linear_rgb();
+        assert!((brighter.data()[0][1] - 2.0).abs() < 2e-3);
+
+        // HLG peak white is the 1000 cd/m² display peak, in any primaries
+        let pixels = vec![[u16::MAX; 3]; 64];
+        let hlg = EncodedRgb::new(&pixels, 8, 8, TransferFunction::Hlg)
+            .unwrap()
+            .with_primaries(RgbPrimaries::Bt2020)
+            .with_white_nits(1000.0);
+        for v in hlg.to_linear_rgb().data()[63] {
+            assert!((v - 1.0).abs() < 1e-3, "{}", v);
+        }
+    }
+
+    #[test]
+    fn test_colorimetry_mismatch_is_rejected() {
+        use crate::{compute_ssimulacra2, Ssimulacra2Reference};
+
+        let bytes: Vec<u8> = (0..16 * 16 * 3).map(|i| (i * 7 % 256) as u8).collect();
+        let pixels: Vec<[u8; 3]> = bytes.chunks(3).map(|p| [p[0], p[1], p[2]]).collect();
+        let srgb = LinearRgbImage::from_srgb_u8(&bytes, 16, 16).unwrap();
+        assert_eq!(srgb.colorimetry(), Some(Colorimetry::default()));
+        let pq = EncodedRgb::new(&pixels, 16, 16, TransferFunction::Pq).unwrap();
+        let encoded_srgb = EncodedRgb::new(&pixels, 16, 16, TransferFunction::Srgb).unwrap();
+
+        let expected = Err(Ssimulacra2Error::ColorimetryMismatch {
+            reference: Colorimetry::default(),
+            distorted: Colorimetry {
+                transfer: TransferFunction::Pq,
+                ..Colorimetry::default()
+            },
+        });
+        assert_eq!(compute_ssimulacra2(&srgb, pq), expected);
+        let reference = Ssimulacra2Reference::new(&srgb).unwrap();
+        assert_eq!(reference.compare(pq), expected);
+
+        // Same decoding, and untagged linear images, compare as before
+        assert!(compute_ssimulacra2(&srgb, encoded_srgb).is_ok());
+        let untagged = LinearRgbImage::new(srgb.data().to_vec(), 16, 16);
+        assert!(compute_ssimulacra2(&untagged, pq).is_ok());
+        assert!(reference.compare(&untagged).is_ok());
+    }
+
+    #[test]
+    fn test_encoded_rgb_bit_depths_and_ranges() {
+        let linear = |pixels: &[[u16; 3]], bits: u8, limited: bool| {
+            let mut encoded = EncodedRgb::new(pixels, 1, pixels.len(), TransferFunction::Linear)
+                .unwrap()
+                .with_bit_depth(bits)
+                .unwrap();
+            if limited {
+                encoded = encoded.with_limited_range();
+            }
+            encoded.to_linear_rgb().data().to_vec()
+        };
+
+        // Full range: 0 is black and 2^bits - 1 is white
+        assert_eq!(linear(&[[0, 1023, 1023]], 10, false), [[0.0, 1.0, 1.0]]);
+        assert_eq!(linear(&[[4095, 0, 4095]], 12, false), [[1.0, 0.0, 1.0]]);
+        // Limited range: 64-940 at 10 bits, 256-3760 at 12 bits
+        assert_eq!(linear(&[[64, 940, 502]], 10, true), [[0.0, 1.0, 0.5]]);
+        assert_eq!(linear(&[[256, 3760, 2008]], 12, true), [[0.0, 1.0, 0.5]]);
+
+        // The same code values in 8-bit samples
+        let pixels = [[16u8, 235, 16]];
+        let encoded = EncodedRgb::new(&pixels, 1, 1, TransferFunction::Linear)
+            .unwrap()
+            .with_limited_range();
+        assert!(encoded.is_limited_range());
+        assert_eq!(encoded.to_linear_rgb().data(), [[0.0, 1.0, 0.0]]);
+
+        // A 10-bit frame decodes like the same frame shifted to 16 bits
+        let ten: Vec<[u16; 3]> = (0..1024u16).map(|v| [v, 1023 - v, v / 2]).collect();
+        let sixteen: Vec<[u16; 3]> = ten
+            .iter()
+            .map(|px| px.map(|v| (v << 6) | (v >> 4)))
+            .collect();
+        let ten = EncodedRgb::new(&ten, 32, 32, TransferFunction::Srgb)
+            .unwrap()
+            .with_bit_depth(10)
+            .unwrap();
+        assert_eq!(ten.bit_depth(), 10);
+        let sixteen = EncodedRgb::new(&sixteen, 32, 32, TransferFunction::Srgb).unwrap();
+        assert_eq!(sixteen.bit_depth(), 16);
+        for (a, b) in ten
+            .to_linear_rgb()
+            .data()
+            .iter()
+            .zip(sixteen.to_linear_rgb().data())
+        {
+            for c in 0..3 {
+                assert!((a[c] - b[c]).abs() < 1e-4, "{:?} {:?}", a, b);
+            }
+        }
+
+        for bits in [0, 7, 17] {
+            assert!(matches!(
+                sixteen.with_bit_depth(bits),
+                Err(Ssimulacra2Error::UnsupportedBitDepth)
+            ));
+        }
+    }
 }
 
 #[cfg(all(test, feature = "imgref"))]
@@ -299,6 +1708,24 @@ mod imgref_tests {
         assert!((linear.data()[3][1] - 0.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_imgref_stride_skips_padding() {
+        // 2x2 view into rows of 3 pixels; the third column must be ignored
+        let pixels: Vec<[u8; 3]> = vec![
+            [0, 0, 0],
+            [255, 255, 255],
+            [7, 7, 7],
+            [255, 0, 0],
+            [0, 255, 0],
+            [7, 7, 7],
+        ];
+        let img = Img::new_stride(pixels.as_slice(), 2, 2, 3);
+        let linear = img.to_linear_rgb();
+        let packed = [0u8, 0, 0, 255, 255, 255, 255, 0, 0, 0, 255, 0];
+        let expected = LinearRgbImage::from_srgb_u8(&packed, 2, 2).unwrap();
+        assert_eq!(linear.data(), expected.data());
+    }
+
     #[test]
     fn test_imgref_f32_passthrough() {
         // f32 is assumed to already be linear - should pass through unchanged
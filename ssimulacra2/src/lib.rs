@@ -46,6 +46,7 @@
 //! ### Without features (using `yuvxyb` types)
 //!
 //! ```
+//! # #[cfg(feature = "yuvxyb")] {
 //! use fast_ssim2::{compute_ssimulacra2, Rgb, TransferCharacteristic, ColorPrimaries};
 //!
 //! let data: Vec<[f32; 3]> = vec![[0.5, 0.5, 0.5]; 64 * 64];
@@ -56,6 +57,7 @@
 //!
 //! let score = compute_ssimulacra2(source, distorted)?;
 //! // compute_ssimulacra2 accepts yuvxyb::Rgb, yuvxyb::LinearRgb, and more
+//! # }
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 //!
@@ -65,6 +67,7 @@
 //! different compression levels), precompute the reference data once:
 //!
 //! ```
+//! # #[cfg(feature = "yuvxyb")] {
 //! use fast_ssim2::{Ssimulacra2Reference, Rgb, TransferCharacteristic, ColorPrimaries};
 //!
 //! // Create test data
@@ -79,6 +82,7 @@
 //! let distorted = Rgb::new(data, 64, 64,
 //!     TransferCharacteristic::SRGB, ColorPrimaries::BT709)?;
 //! let score = reference.compare(distorted)?;
+//! # }
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 //!
@@ -117,18 +121,18 @@
 //! ## SIMD Configuration
 //!
 //! By default, the crate uses safe SIMD via the `wide` crate. For maximum
-//! performance on x86_64, enable the `unsafe-simd` feature:
+//! performance on x86_64 and aarch64, enable the `unsafe-simd` feature:
 //!
 //! ```toml
 //! [dependencies]
-//! fast-ssim2 = { version = "0.6", features = ["unsafe-simd"] }
+//! fast-ssim2 = { version = "0.7", features = ["unsafe-simd"] }
 //! ```
 //!
 //! | Backend | Speed | Platforms |
 //! |---------|-------|-----------|
 //! | `Scalar` | 1.0× (baseline) | All |
 //! | `Simd` (default) | 2.5× | All (via `wide` crate) |
-//! | `UnsafeSimd` | 3.0× | x86_64 with AVX2 |
+//! | `UnsafeSimd` | 3.0× | x86_64 with AVX2, aarch64 (NEON) |
 //!
 //! To explicitly select a backend:
 //!
@@ -150,22 +154,103 @@
 //! | Feature | Default | Description |
 //! |---------|---------|-------------|
 //! | `simd` | ✓ | Safe SIMD via `wide` crate |
-//! | `unsafe-simd` | ✓ | x86_64 intrinsics (faster) |
+//! | `unsafe-simd` | ✓ | x86_64 and aarch64 intrinsics (faster) |
+//! | `yuvxyb` | ✓ | YUV frames and `yuvxyb` type interop |
 //! | `imgref` | | Support for `imgref` image types |
 //! | `rayon` | | Parallel computation |
+//! | `integral-blur` | | Experimental summed-area blur kernel (not reference-exact) |
+//! | `debug-checked` | | Keep unsafe kernel precondition checks in release builds |
+//! | `log` | | Per-comparison diagnostics via the `log` crate |
+//! | `lite` | | Marker for the minimal build (see below) |
+//!
+//! ### Minimal build
+//!
+//! With `default-features = false, features = ["lite"]` the crate builds
+//! only the portable scalar pipeline, with no `wide`, `multiversion`,
+//! `yuvxyb` or `imgref` dependency. Every backend then runs the scalar
+//! kernels, and XYB conversion uses the crate's own scalar code, which
+//! matches `yuvxyb`. Scores are the same as with [`SimdImpl::Scalar`] in a
+//! full build. `lite` itself enables nothing, so features another crate in
+//! the dependency graph turns on are simply added to the minimal build.
 //!
 //! ## Requirements
 //!
 //! - **Minimum image size:** 8×8 pixels
 //! - **MSRV:** 1.89.0
 
+/// Precondition check for unsafe kernels.
+///
+/// Behaves like `debug_assert!`, but also runs in release builds when the
+/// `debug-checked` feature is enabled, so validation deployments can catch
+/// violated kernel preconditions at full speed instead of corrupting memory.
+#[cfg_attr(not(feature = "unsafe-simd"), allow(unused_macros))]
+macro_rules! debug_check {
+    ($($arg:tt)*) => {
+        if cfg!(any(debug_assertions, feature = "debug-checked")) {
+            assert!($($arg)*);
+        }
+    };
+}
+
+pub mod accuracy;
+mod alpha;
+#[cfg(doctest)]
+mod api_guarantees;
+mod banded;
 mod blur;
+mod budget;
+pub mod build_info;
+mod calibration;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "log")]
+mod diagnostics;
+mod explain;
+mod fallback;
+#[cfg(feature = "image")]
+mod files;
+mod heatmap;
 mod input;
+mod luma;
+mod maps;
+mod metrics;
+#[cfg(feature = "monitor")]
+mod monitor;
+mod norms;
+mod parallelism;
+mod planar;
+mod pooling;
 mod precompute;
+#[cfg(feature = "python")]
+mod python;
+mod queue;
+mod resample;
+mod resize;
+mod scales;
+mod scratch;
 // Reference data for parity testing (hidden from docs but accessible for tests)
 #[doc(hidden)]
 pub mod reference_data;
+mod sensitivity;
+#[cfg(feature = "simd")]
 mod simd_ops;
+mod statistics;
+pub mod stats;
+#[cfg(feature = "async")]
+mod stream;
+mod stripes;
+mod taps;
+mod temporal;
+#[cfg(test)]
+mod test_util;
+mod tiles;
+mod transfer;
+mod video;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod xyb;
+mod xyb_scalar;
+#[cfg(feature = "simd")]
 mod xyb_simd;
 
 #[cfg(feature = "unsafe-simd")]
@@ -174,20 +259,87 @@ mod xyb_unsafe_simd;
 #[cfg(feature = "unsafe-simd")]
 mod ssim_unsafe_simd;
 
-pub use blur::Blur;
-pub use input::{LinearRgbImage, ToLinearRgb};
-pub use precompute::Ssimulacra2Reference;
-// Re-export commonly used types from yuvxyb for convenience
+pub use alpha::AlphaMode;
+pub use blur::{
+    verify_kernel, Blur, KernelResponse, KernelVerification, PlanePool, PlaneSet,
+    DEFAULT_MAX_CHUNK_COLUMNS,
+};
+pub use budget::{compute_ssimulacra2_within_budget, BudgetedScore};
+pub use explain::{ErrorKind, ErrorShare, ScoreExplanation};
+pub use fallback::{FallbackChain, Stage, StageBackends};
+#[cfg(feature = "image")]
+pub use files::{compare_files, open_linear_rgb, CompareFilesError};
+pub use heatmap::{
+    compute_ssimulacra2_block_map, compute_ssimulacra2_channel_block_maps,
+    compute_ssimulacra2_grid, compute_ssimulacra2_roi, BlockMap, HeatCurve, HeatScaling,
+    HeatmapPalette, ScoreGrid,
+};
+#[cfg(feature = "yuvxyb")]
+pub use input::YuvPlanes;
+pub use input::{EncodedRgb, LinearRgbImage, ToLinearRgb};
+pub use luma::{compute_ssimulacra2_gray, compute_ssimulacra2_gray_with_config, GraySample};
+pub use maps::{compute_ssimulacra2_maps, MapBuffers, MapRequest, XybPlanes};
+pub use metrics::{compute_metrics, Metrics, MetricsRequest};
+#[cfg(feature = "monitor")]
+pub use monitor::{ScoreMonitor, StreamSnapshot, DEFAULT_MONITOR_WINDOW};
+pub use norms::NormExponents;
+#[cfg(feature = "rayon")]
+pub use parallelism::ThreadPoolHandle;
+pub use parallelism::{Parallelism, PARALLEL_MIN_PIXELS};
+pub use planar::PlanarImage;
+#[cfg(feature = "yuvxyb")]
+pub use pooling::luma_variance;
+pub use pooling::{PoolSummary, ScorePool, DEFAULT_EXACT_FRAME_LIMIT};
+pub use precompute::{CandidateCache, ReferencePrecision, Ssimulacra2Reference};
+pub use queue::{DeliveryOrder, FrameQueue, QueuedScore};
+pub use resample::{resample, ResampleFilter};
+pub use resize::PreResize;
+pub use scales::{ScalePyramid, ScaleWeights};
+#[cfg(feature = "yuvxyb")]
+pub use scratch::compute_frame_ssimulacra2_into;
+pub use scratch::{compute_ssimulacra2_into, Ssimulacra2Scratch};
+pub use sensitivity::{
+    compute_ssimulacra2_sensitivity, Perturbation, Region, RegionSensitivity, SensitivityOptions,
+};
+pub use statistics::{
+    aggregate_scales, positive_xyb_planes, score_from_statistics, DetailedScore, ScaleStatistics,
+};
+#[cfg(feature = "async")]
+pub use stream::{FrameSubmitter, Ssimulacra2Stream, Submit};
+pub use stripes::{
+    compute_ssimulacra2_striped, StripedScorer, DEFAULT_STRIPE_MEMORY_BUDGET, MIN_STRIPE_ROWS,
+};
+pub use taps::{
+    compute_ssimulacra2_with_progress, compute_ssimulacra2_with_taps, ErrorMaps, PipelineTap,
+    ScaleMoments,
+};
+pub use temporal::{TemporalMatch, TemporalWindow};
+pub use tiles::{compute_ssimulacra2_tiles, AdaptiveTiling, TileLayout, TileScore};
+pub use transfer::{Colorimetry, RgbPrimaries, TransferFunction, HDR_REFERENCE_WHITE_NITS};
+pub use video::{VideoScorer, VideoScores};
+pub use xyb::{
+    linear_rgb_to_xyb, linear_rgb_to_xyb_with_config, xyb_to_linear_rgb,
+    xyb_to_linear_rgb_with_config, XybImage,
+};
+// Re-export commonly used types from yuvxyb for convenience. This covers
+// everything rust-av/ssimulacra2 re-exports, so the crate can be used under
+// that name via a dependency rename (see tests/ssimulacra2_compat.rs).
+#[cfg(feature = "yuvxyb")]
 pub use yuvxyb::{
-    ColorPrimaries, Frame, LinearRgb, MatrixCoefficients, Pixel, Plane, Rgb,
-    TransferCharacteristic, Yuv, YuvConfig,
+    CastFromPrimitive, ColorPrimaries, Frame, LinearRgb, MatrixCoefficients, Pixel, Plane, Rgb,
+    TransferCharacteristic, Xyb, Yuv, YuvConfig,
 };
 
 // Re-export sRGB conversion functions for users implementing custom input types
-pub use input::{srgb_to_linear, srgb_u16_to_linear, srgb_u8_to_linear};
+pub use input::{
+    srgb_to_linear, srgb_u16_to_linear, srgb_u8_to_linear, SRGB_U16_TO_LINEAR, SRGB_U8_TO_LINEAR,
+};
 
-// Internal imports for XYB color space
-use yuvxyb::Xyb;
+use norms::{Norms, StandardNorms};
+use std::borrow::Cow;
+use std::ops::Range;
+use std::time::Duration;
+use xyb::linear_rgb_to_positive_planar_into;
 
 #[cfg(all(feature = "unsafe-simd", target_arch = "x86_64"))]
 use safe_unaligned_simd::x86_64 as safe_simd;
@@ -201,10 +353,11 @@ pub(crate) const NUM_SCALES: usize = 6;
 pub enum SimdImpl {
     /// Scalar implementation (baseline, most portable)
     Scalar,
-    /// Safe SIMD via wide crate (default, good balance of speed and safety)
+    /// Safe SIMD via wide crate (default, good balance of speed and safety).
+    /// Runs the scalar kernels in builds without the `simd` feature.
     #[default]
     Simd,
-    /// Raw x86 intrinsics (fastest, requires unsafe-simd feature)
+    /// Raw x86 or NEON intrinsics (fastest, requires unsafe-simd feature)
     #[cfg(feature = "unsafe-simd")]
     UnsafeSimd,
 }
@@ -214,24 +367,197 @@ impl SimdImpl {
     pub fn name(&self) -> &'static str {
         match self {
             SimdImpl::Scalar => "scalar",
+            #[cfg(feature = "simd")]
             SimdImpl::Simd => "simd (wide crate)",
+            #[cfg(not(feature = "simd"))]
+            SimdImpl::Simd => "simd (scalar, simd feature disabled)",
             #[cfg(feature = "unsafe-simd")]
             SimdImpl::UnsafeSimd => "unsafe-simd (raw intrinsics)",
         }
     }
+
+    /// Returns true if this backend runs its own kernels on the current CPU.
+    ///
+    /// The unsafe SIMD kernels need x86-64 with AVX2 and FMA, or aarch64 with
+    /// NEON; elsewhere they degrade to slower portable code, so a
+    /// [`FallbackChain`] skips them.
+    pub fn is_available(&self) -> bool {
+        match self {
+            SimdImpl::Scalar | SimdImpl::Simd => true,
+            #[cfg(feature = "unsafe-simd")]
+            SimdImpl::UnsafeSimd => {
+                #[cfg(target_arch = "x86_64")]
+                return is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma");
+                #[cfg(target_arch = "aarch64")]
+                return std::arch::is_aarch64_feature_detected!("neon");
+                #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+                return false;
+            }
+        }
+    }
+}
+
+/// Kernel used to compute local means and variances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum BlurKernel {
+    /// Recursive Gaussian (σ = 1.5), matching the reference implementation
+    #[default]
+    RecursiveGaussian,
+    /// Experimental box-filter approximation via summed-area tables (requires
+    /// the `integral-blur` feature; without it, comparisons return
+    /// [`Ssimulacra2Error::BlurKernelUnavailable`]). For platforms where the
+    /// IIR blur vectorizes poorly; scores deviate from the reference by up to
+    /// 3.4 points, see `BLUR_BACKEND_EVALUATION.md`.
+    SummedArea,
+}
+
+impl BlurKernel {
+    /// Returns the name of this kernel
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlurKernel::RecursiveGaussian => "recursive gaussian",
+            BlurKernel::SummedArea => "summed-area (experimental)",
+        }
+    }
+
+    /// Returns true if this kernel is compiled into this build.
+    pub fn is_available(&self) -> bool {
+        match self {
+            BlurKernel::RecursiveGaussian => true,
+            BlurKernel::SummedArea => cfg!(feature = "integral-blur"),
+        }
+    }
 }
 
 /// Configuration for SSIMULACRA2 computation.
-#[derive(Debug, Clone, Copy, Default)]
+///
+/// Build one from [`Ssimulacra2Config::default`] or [`Ssimulacra2Config::new`]
+/// and the `with_*` methods; fields can be read but new ones may be added in
+/// minor releases.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct Ssimulacra2Config {
-    /// Implementation backend for all operations
+    /// Implementation backend for all operations, unless
+    /// [`backends`](Self::backends) is set
     pub impl_type: SimdImpl,
+    /// Per-stage fallback chains, overriding [`impl_type`](Self::impl_type)
+    /// when set (see [`StageBackends`])
+    pub backends: Option<StageBackends>,
+    /// Kernel for the local mean/variance blur
+    pub blur_kernel: BlurKernel,
+    /// Threading for the parallel stages (requires the `rayon` feature)
+    pub parallelism: Parallelism,
+    /// Log per-comparison diagnostics (dimensions, backend, scales, timing) at
+    /// debug level under the `fast_ssim2` target. Requires the `log` feature;
+    /// without it this has no effect.
+    pub log_diagnostics: bool,
+    /// Opt-in early downscale for very large inputs, in megapixels.
+    ///
+    /// When set, inputs larger than this are halved (repeatedly, if needed) with
+    /// the same 2x box filter the metric uses between its own scales before the
+    /// pyramid starts. The result is exactly the standard metric computed on the
+    /// downscaled pair, e.g. a single halving matches starting the pyramid at
+    /// the metric's own scale 1. Fine-detail sensitivity is reduced accordingly,
+    /// in exchange for bounded latency on huge images.
+    pub early_downscale_megapixels: Option<f64>,
+    /// Box-filter downscale applied to both inputs before anything else,
+    /// matching the C++ tool's filter (see [`PreResize`]). Unlike
+    /// [`early_downscale_megapixels`](Self::early_downscale_megapixels), this
+    /// always applies, with the given factors.
+    pub pre_resize: Option<PreResize>,
+    /// Record every score computed with this config under this label in
+    /// `ScoreMonitor::global()`. Requires the
+    /// `monitor` feature; without it this has no effect.
+    pub monitor_label: Option<Cow<'static, str>>,
+    /// What to do with the inputs' alpha planes (see [`AlphaMode`]); ignored
+    /// by default
+    pub alpha_mode: AlphaMode,
+    /// Experimental: exponents of the two norms pooling each error map (see
+    /// [`NormExponents`]). Anything but the default changes what the score
+    /// means.
+    pub norm_exponents: NormExponents,
+    /// Blur and pool each scale in bands of rows instead of blurring the
+    /// five moment planes in full first, roughly halving peak memory on
+    /// large images.
+    ///
+    /// The blur then runs row by row with the scalar backend's arithmetic
+    /// whatever the [`impl_type`](Self::impl_type), so scores equal those of
+    /// the scalar blur. Ignored with [`AlphaMode`] weighting, pipeline taps
+    /// and non-Gaussian [`blur_kernel`](Self::blur_kernel)s, which need the
+    /// whole planes.
+    pub streaming_moments: bool,
+    /// Refine the comparison from coarse to fine and stop at the finest
+    /// resolution that fits in this time, for interactive callers that need
+    /// an answer quickly whatever the input size. The score is then that of
+    /// a downscaled pair; [`compute_ssimulacra2_within_budget`] reports
+    /// whether that happened. Ignored with pipeline taps, by scorers that run
+    /// their own pipeline such as [`StripedScorer`], and on
+    /// `wasm32-unknown-unknown`, which has no clock.
+    pub time_budget: Option<Duration>,
+    /// Experimental: how many scales are compared and how small they may
+    /// get (see [`ScalePyramid`]). Anything but the default changes what the
+    /// score means. Scorers that run their own pipeline, such as
+    /// [`StripedScorer`] and [`Ssimulacra2Reference`], ignore it.
+    pub pyramid: ScalePyramid,
+    /// Experimental: multipliers on the score weights of each scale and
+    /// channel (see [`ScaleWeights`]). Anything but the default changes what
+    /// the score means. Scorers that run their own pipeline ignore it.
+    pub scale_weights: ScaleWeights,
+    /// Compare only the luminance (Y) channel, for grayscale content.
+    ///
+    /// The X and B channels of gray images are constant, so skipping them
+    /// blurs and pools a third of the planes and only drops the rounding noise
+    /// the standard pipeline scores in them, about 0.1 points on textured
+    /// grayscale content. On color inputs, chroma differences are ignored
+    /// and the score is not a standard SSIMULACRA2 score. Coverage-weighted
+    /// alpha is not applied. Pipeline taps get progress reports and can
+    /// cancel, but no stage callbacks. Scorers that run their own pipeline
+    /// ignore it.
+    pub luma_only: bool,
+    /// Replace subnormal input samples with zero before comparing.
+    ///
+    /// Arithmetic on subnormal floats is many times slower on many CPUs.
+    /// The blurs never see them, since the XYB conversion adds a bias first,
+    /// but the conversion itself and the downscales between scales run on
+    /// raw linear samples, so inputs with large areas of subnormal values
+    /// (e.g. dark renders scaled by tiny exposures) are slow there. That
+    /// bias also makes subnormals indistinguishable from zero, so flushing
+    /// leaves scores unchanged. It costs a scan of both inputs per
+    /// comparison, plus a copy of an input that has subnormal samples.
+    pub flush_subnormals: bool,
+    /// Resample a distorted image whose size differs from the source's to
+    /// the source's size with this filter (see [`resample()`]), instead of
+    /// failing with [`Ssimulacra2Error::NonMatchingImageDimensions`].
+    ///
+    /// The score then also measures the resampling, e.g. a 1080p encode of
+    /// a 4K master is scored as it looks upscaled to 4K. Scorers that run
+    /// their own pipeline ignore it.
+    pub resample_mismatched: Option<ResampleFilter>,
 }
 
 impl Ssimulacra2Config {
     /// Create configuration with specified implementation
     pub fn new(impl_type: SimdImpl) -> Self {
-        Self { impl_type }
+        Self {
+            impl_type,
+            backends: None,
+            blur_kernel: BlurKernel::default(),
+            parallelism: Parallelism::default(),
+            log_diagnostics: false,
+            early_downscale_megapixels: None,
+            pre_resize: None,
+            monitor_label: None,
+            alpha_mode: AlphaMode::Ignore,
+            norm_exponents: NormExponents::STANDARD,
+            streaming_moments: false,
+            time_budget: None,
+            pyramid: ScalePyramid::STANDARD,
+            scale_weights: ScaleWeights::STANDARD,
+            luma_only: false,
+            flush_subnormals: false,
+            resample_mismatched: None,
+        }
     }
 
     /// Default configuration using safe SIMD for all operations
@@ -249,10 +575,190 @@ impl Ssimulacra2Config {
     pub fn scalar() -> Self {
         Self::new(SimdImpl::Scalar)
     }
+
+    /// Configuration with backends picked for the running CPU.
+    ///
+    /// Uses the intrinsics kernels where they are compiled in and the CPU has
+    /// AVX2 and FMA (or AVX-512) or NEON, the portable SIMD kernels on other
+    /// CPUs with vector units (SSE2), and the scalar kernels elsewhere. The
+    /// CPU is probed once per process.
+    pub fn auto() -> Self {
+        Self::default().with_backends(calibration::probed())
+    }
+
+    /// Like [`auto`](Self::auto), but times every available backend on each
+    /// stage (about a millisecond in total, on the first call in the process)
+    /// and uses the fastest per stage.
+    ///
+    /// Backends differ in the last digits of the score, and the timing can
+    /// pick differently from one process to the next; use
+    /// [`auto`](Self::auto) or a fixed backend where scores must be
+    /// reproducible across runs.
+    pub fn auto_calibrated() -> Self {
+        Self::default().with_backends(calibration::calibrated())
+    }
+
+    /// Enable early downscaling for inputs above `megapixels`
+    /// (see [`early_downscale_megapixels`](Self::early_downscale_megapixels)).
+    #[must_use]
+    pub fn with_early_downscale(mut self, megapixels: f64) -> Self {
+        self.early_downscale_megapixels = Some(megapixels);
+        self
+    }
+
+    /// Answer within `budget`, downscaling if needed
+    /// (see [`time_budget`](Self::time_budget)).
+    #[must_use]
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Downscale both inputs before scoring (see [`PreResize`]).
+    #[must_use]
+    pub fn with_pre_resize(mut self, resize: PreResize) -> Self {
+        self.pre_resize = Some(resize);
+        self
+    }
+
+    /// Set how alpha planes affect the score (see [`AlphaMode`]).
+    #[must_use]
+    pub fn with_alpha_mode(mut self, mode: AlphaMode) -> Self {
+        self.alpha_mode = mode;
+        self
+    }
+
+    /// Pool the error maps with other norm exponents (experimental, see
+    /// [`NormExponents`]).
+    #[must_use]
+    pub fn with_norm_exponents(mut self, exponents: NormExponents) -> Self {
+        self.norm_exponents = exponents;
+        self
+    }
+
+    /// Compare another number or size of scales (experimental, see
+    /// [`ScalePyramid`]).
+    #[must_use]
+    pub fn with_pyramid(mut self, pyramid: ScalePyramid) -> Self {
+        self.pyramid = pyramid;
+        self
+    }
+
+    /// Reweight scales and channels (experimental, see [`ScaleWeights`]).
+    #[must_use]
+    pub fn with_scale_weights(mut self, weights: ScaleWeights) -> Self {
+        self.scale_weights = weights;
+        self
+    }
+
+    /// Compare only the luminance channel
+    /// (see [`luma_only`](Self::luma_only)).
+    #[must_use]
+    pub fn with_luma_only(mut self, enabled: bool) -> Self {
+        self.luma_only = enabled;
+        self
+    }
+
+    /// Replace subnormal input samples with zero
+    /// (see [`flush_subnormals`](Self::flush_subnormals)).
+    #[must_use]
+    pub fn with_flush_subnormals(mut self, enabled: bool) -> Self {
+        self.flush_subnormals = enabled;
+        self
+    }
+
+    /// Resample distorted images of another size to the source's size
+    /// (see [`resample_mismatched`](Self::resample_mismatched)).
+    #[must_use]
+    pub fn with_resample_mismatched(mut self, filter: ResampleFilter) -> Self {
+        self.resample_mismatched = Some(filter);
+        self
+    }
+
+    /// Blur and pool in bands of rows to save memory
+    /// (see [`streaming_moments`](Self::streaming_moments)).
+    #[must_use]
+    pub fn with_streaming_moments(mut self, enabled: bool) -> Self {
+        self.streaming_moments = enabled;
+        self
+    }
+
+    /// Record scores under `label` in the global score monitor
+    /// (see [`monitor_label`](Self::monitor_label)). Takes a literal or a
+    /// label built at runtime, such as one per encoder setting.
+    #[must_use]
+    pub fn with_monitor_label(mut self, label: impl Into<Cow<'static, str>>) -> Self {
+        self.monitor_label = Some(label.into());
+        self
+    }
+
+    /// Enable or disable diagnostics logging
+    /// (see [`log_diagnostics`](Self::log_diagnostics)).
+    #[must_use]
+    pub fn with_diagnostics_logging(mut self, enabled: bool) -> Self {
+        self.log_diagnostics = enabled;
+        self
+    }
+
+    /// Use the given blur kernel (see [`BlurKernel`]). Comparisons fail with
+    /// [`Ssimulacra2Error::BlurKernelUnavailable`] if it isn't compiled in.
+    #[must_use]
+    pub fn with_blur_kernel(mut self, kernel: BlurKernel) -> Self {
+        self.blur_kernel = kernel;
+        self
+    }
+
+    /// Use the given threading setting (see [`Parallelism`]).
+    #[must_use]
+    pub fn with_parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Use at most `threads` threads per comparison, on a pool shared with
+    /// other comparisons capped at the same count. Shorthand for
+    /// [`Parallelism::Threads`].
+    #[must_use]
+    pub fn with_max_threads(self, threads: usize) -> Self {
+        self.with_parallelism(Parallelism::Threads(threads))
+    }
+
+    /// Run the parallel stages on `pool`, keeping comparisons off rayon's
+    /// global pool. The configuration holds the pool, which lives until the
+    /// last configuration holding it is dropped.
+    #[cfg(feature = "rayon")]
+    #[must_use]
+    pub fn with_thread_pool(self, pool: std::sync::Arc<rayon::ThreadPool>) -> Self {
+        self.with_parallelism(Parallelism::Pool(ThreadPoolHandle(pool)))
+    }
+
+    /// Select backends per stage from fallback chains
+    /// (see [`backends`](Self::backends)).
+    #[must_use]
+    pub fn with_backends(mut self, backends: StageBackends) -> Self {
+        self.backends = Some(backends);
+        self
+    }
+
+    /// Use the first available backend of `chain` for every stage.
+    #[must_use]
+    pub fn with_fallback_chain(self, chain: FallbackChain) -> Self {
+        self.with_backends(StageBackends::all(chain))
+    }
+
+    /// The backend `stage` runs on: the resolved fallback chain if
+    /// [`backends`](Self::backends) is set, otherwise [`impl_type`](Self::impl_type).
+    pub fn backend_for(&self, stage: Stage) -> SimdImpl {
+        match &self.backends {
+            Some(backends) => backends.resolve(stage),
+            None => self.impl_type,
+        }
+    }
 }
 
 /// Errors which can occur when attempting to calculate a SSIMULACRA2 score from two input images.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
 pub enum Ssimulacra2Error {
     /// The conversion from input image to [LinearRgb] (via [TryFrom]) returned an [Err].
     #[error("Failed to convert input image to linear RGB")]
@@ -269,9 +775,75 @@ pub enum Ssimulacra2Error {
     /// Gaussian blur operation failed.
     #[error("Gaussian blur operation failed")]
     GaussianBlurError,
+
+    /// Row-wise or packed input did not match the declared width and height.
+    #[error("Row data does not match the declared image dimensions")]
+    RowDataMismatch,
+
+    /// A backend name in a [`FallbackChain`] was not recognized.
+    #[error("Unknown backend name (expected scalar, simd or unsafe-simd)")]
+    UnknownBackend,
+
+    /// A [`PreResize`] factor was zero.
+    #[error("Resize factors must be at least 1")]
+    InvalidResizeFactor,
+
+    /// A [`YuvPlanes`] plane is too small for the frame dimensions and strides.
+    #[error("YUV plane data does not match the declared dimensions and strides")]
+    YuvPlaneSizeMismatch,
+
+    /// A [`YuvPlanes`] bit depth, subsampling or color description can't be converted.
+    #[error("Unsupported YUV bit depth, subsampling, matrix, transfer or primaries")]
+    UnsupportedYuvFormat,
+
+    /// An [`EncodedRgb`] bit depth was outside 8-16 bits.
+    #[error("Unsupported bit depth (expected 8 to 16 bits per sample)")]
+    UnsupportedBitDepth,
+
+    /// The source and distorted images are tagged with [`Colorimetry`] that
+    /// differs in transfer function or range, so they were linearized under
+    /// different assumptions.
+    #[error("Source ({reference}) and distorted ({distorted}) images were decoded with different colorimetry")]
+    ColorimetryMismatch {
+        /// Colorimetry of the source image
+        reference: Colorimetry,
+        /// Colorimetry of the distorted image
+        distorted: Colorimetry,
+    },
+
+    /// Bytes passed to [`Ssimulacra2Reference::from_bytes`] are truncated,
+    /// corrupt, or from an incompatible version.
+    #[error("Invalid or incompatible serialized reference data")]
+    InvalidReferenceData,
+
+    /// A [`Region`] is empty or extends past the image.
+    #[error("Region is empty or lies outside the image")]
+    InvalidRegion,
+
+    /// The finite-difference step of [`SensitivityOptions`] is not finite and
+    /// positive.
+    #[error("Sensitivity step must be finite and positive")]
+    InvalidStep,
+
+    /// A progress callback asked to stop the comparison.
+    #[error("Comparison was cancelled")]
+    Cancelled,
+
+    /// The configured [`BlurKernel`] needs a cargo feature this build
+    /// doesn't have (see [`BlurKernel::is_available`]).
+    #[error(
+        "Blur kernel is not available in this build (summed-area needs the integral-blur feature)"
+    )]
+    BlurKernelUnavailable,
+
+    /// The configuration produces no per-pixel error maps to pool, as with
+    /// [`luma_only`](Ssimulacra2Config::luma_only).
+    #[error("This configuration produces no error maps (luma-only comparisons pool only Y)")]
+    ErrorMapsUnavailable,
 }
 
 /// Computes the SSIMULACRA2 score with default configuration (safe SIMD).
+#[cfg(feature = "yuvxyb")]
 pub fn compute_frame_ssimulacra2<T, U>(source: T, distorted: U) -> Result<f64, Ssimulacra2Error>
 where
     LinearRgb: TryFrom<T> + TryFrom<U>,
@@ -280,6 +852,7 @@ where
 }
 
 /// Computes the SSIMULACRA2 score with custom implementation configuration.
+#[cfg(feature = "yuvxyb")]
 pub fn compute_frame_ssimulacra2_with_config<T, U>(
     source: T,
     distorted: U,
@@ -330,11 +903,85 @@ where
     S: ToLinearRgb,
     D: ToLinearRgb,
 {
-    let img1: LinearRgb = source.to_linear_rgb().into();
-    let img2: LinearRgb = distorted.to_linear_rgb().into();
-    compute_frame_ssimulacra2_impl(img1, img2, config)
+    compute_linear_msssim(
+        &source.as_linear_rgb(),
+        &distorted.as_linear_rgb(),
+        config,
+        None,
+    )
+    .map(|msssim| msssim.score())
+}
+
+/// Computes the SSIMULACRA2 score from rows of linear RGB pixels.
+///
+/// Both images are assembled row by row as the iterators yield them, so rows
+/// can come straight from a progressive decoder. See
+/// [`LinearRgbImage::from_rows`].
+///
+/// # Errors
+/// - If either iterator yields a row that isn't `width` pixels long, or
+///   doesn't yield exactly `height` rows
+/// - If the images are smaller than 8x8 pixels
+pub fn compute_from_rows<'a, R, D>(
+    ref_rows: R,
+    dist_rows: D,
+    width: usize,
+    height: usize,
+) -> Result<f64, Ssimulacra2Error>
+where
+    R: IntoIterator<Item = &'a [[f32; 3]]>,
+    D: IntoIterator<Item = &'a [[f32; 3]]>,
+{
+    compute_from_rows_with_config(
+        ref_rows,
+        dist_rows,
+        width,
+        height,
+        Ssimulacra2Config::default(),
+    )
+}
+
+/// Computes the SSIMULACRA2 score from rows of linear RGB pixels with custom configuration.
+pub fn compute_from_rows_with_config<'a, R, D>(
+    ref_rows: R,
+    dist_rows: D,
+    width: usize,
+    height: usize,
+    config: Ssimulacra2Config,
+) -> Result<f64, Ssimulacra2Error>
+where
+    R: IntoIterator<Item = &'a [[f32; 3]]>,
+    D: IntoIterator<Item = &'a [[f32; 3]]>,
+{
+    let img1 = LinearRgbImage::from_rows(ref_rows, width, height)?;
+    let img2 = LinearRgbImage::from_rows(dist_rows, width, height)?;
+    compute_linear_ssimulacra2(img1, img2, config)
+}
+
+/// Computes the SSIMULACRA2 score together with the per-scale statistics it was derived from.
+pub fn compute_ssimulacra2_detailed<S, D>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+) -> Result<DetailedScore, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    let msssim = compute_linear_msssim(
+        &source.as_linear_rgb(),
+        &distorted.as_linear_rgb(),
+        config,
+        None,
+    )?;
+    Ok(DetailedScore {
+        score: msssim.score(),
+        scales: msssim.scales,
+        error_bound: 0.0,
+    })
 }
 
+#[cfg(feature = "yuvxyb")]
 fn compute_frame_ssimulacra2_impl<T, U>(
     source: T,
     distorted: U,
@@ -343,175 +990,385 @@ fn compute_frame_ssimulacra2_impl<T, U>(
 where
     LinearRgb: TryFrom<T> + TryFrom<U>,
 {
-    let Ok(mut img1) = LinearRgb::try_from(source) else {
+    let Ok(img1) = LinearRgb::try_from(source) else {
         return Err(Ssimulacra2Error::LinearRgbConversionFailed);
     };
 
-    let Ok(mut img2) = LinearRgb::try_from(distorted) else {
+    let Ok(img2) = LinearRgb::try_from(distorted) else {
         return Err(Ssimulacra2Error::LinearRgbConversionFailed);
     };
 
-    if img1.width() != img2.width() || img1.height() != img2.height() {
+    compute_linear_ssimulacra2(img1.into(), img2.into(), config)
+}
+
+fn compute_linear_ssimulacra2(
+    img1: LinearRgbImage,
+    img2: LinearRgbImage,
+    config: Ssimulacra2Config,
+) -> Result<f64, Ssimulacra2Error> {
+    compute_linear_msssim(&img1, &img2, config, None).map(|msssim| msssim.score())
+}
+
+pub(crate) fn compute_linear_msssim(
+    img1: &LinearRgbImage,
+    img2: &LinearRgbImage,
+    config: Ssimulacra2Config,
+    tap: Option<&mut dyn PipelineTap>,
+) -> Result<Msssim, Ssimulacra2Error> {
+    let mut scratch = Ssimulacra2Scratch::with_config(config);
+    compute_msssim_with_scratch(img1, img2, &mut scratch, tap)?;
+    Ok(std::mem::take(&mut scratch.msssim))
+}
+
+/// Runs the comparison in the buffers of `scratch`, leaving the per-scale
+/// statistics in `scratch.msssim`.
+pub(crate) fn compute_msssim_with_scratch(
+    img1: &LinearRgbImage,
+    img2: &LinearRgbImage,
+    scratch: &mut Ssimulacra2Scratch,
+    mut tap: Option<&mut dyn PipelineTap>,
+) -> Result<(), Ssimulacra2Error> {
+    let config = scratch.config.clone();
+    if !config.blur_kernel.is_available() {
+        return Err(Ssimulacra2Error::BlurKernelUnavailable);
+    }
+    let resampled;
+    let img2 = if img1.width() == img2.width() && img1.height() == img2.height() {
+        img2
+    } else if let Some(filter) = config.resample_mismatched {
+        resampled = resample::resample(img2, img1.width(), img1.height(), filter)?;
+        &resampled
+    } else {
         return Err(Ssimulacra2Error::NonMatchingImageDimensions);
+    };
+
+    scratch.msssim.budget_halvings = 0;
+    scratch.msssim.weights = config.scale_weights;
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    if let (Some(budget), None) = (config.time_budget, &tap) {
+        return budget::compute_within(img1, img2, scratch, budget);
+    }
+    #[cfg(feature = "log")]
+    let diagnostics = diagnostics::Comparison::start(&config, img1.width(), img1.height());
+
+    // The full-resolution inputs are only read; the optional pre-resize and
+    // early downscale stages allocate, the pyramid below reuses the scratch
+    let mut img1 = Cow::Borrowed(img1);
+    let mut img2 = Cow::Borrowed(img2);
+    transfer::reconcile_colorimetry(&mut img1, &mut img2)?;
+    // Coverage weights follow the pixels through every resize, so they stay
+    // aligned with the scale being pooled
+    let mut weights = config.alpha_mode.prepare(&mut img1, &mut img2);
+    if config.flush_subnormals {
+        input::flush_subnormals(&mut img1);
+        input::flush_subnormals(&mut img2);
+    }
+
+    if let Some(resize) = config.pre_resize {
+        img1 = Cow::Owned(resize.apply(&img1)?);
+        img2 = Cow::Owned(resize.apply(&img2)?);
+        if let Some(w) = weights.as_mut() {
+            *w = resize.apply(w)?;
+        }
     }
 
-    if img1.width() < 8 || img1.height() < 8 {
+    if !config.pyramid.fits(img1.width(), img1.height()) {
         return Err(Ssimulacra2Error::InvalidImageSize);
     }
 
+    let downscale_impl = config.backend_for(Stage::Xyb);
+    if let Some(megapixels) = config.early_downscale_megapixels {
+        let max_pixels = megapixels * 1_000_000.0;
+        while (img1.width() * img1.height()) as f64 > max_pixels
+            && img1.width() >= 16
+            && img1.height() >= 16
+        {
+            img1 = Cow::Owned(downscale_by_2(&img1, downscale_impl));
+            img2 = Cow::Owned(downscale_by_2(&img2, downscale_impl));
+            if let Some(w) = weights.as_mut() {
+                *w = downscale_by_2(w, downscale_impl);
+            }
+        }
+    }
+
     let mut width = img1.width();
     let mut height = img1.height();
-    let impl_type = config.impl_type;
-
-    // Pre-allocate reusable buffers (sized for initial dimensions, shrunk per scale)
-    let alloc_plane = || vec![0.0f32; width * height];
-    let alloc_3planes = || [alloc_plane(), alloc_plane(), alloc_plane()];
-
-    let mut mul = alloc_3planes();
-    let mut sigma1_sq = alloc_3planes();
-    let mut sigma2_sq = alloc_3planes();
-    let mut sigma12 = alloc_3planes();
-    let mut mu1 = alloc_3planes();
-    let mut mu2 = alloc_3planes();
-    let mut img1_planar = alloc_3planes();
-    let mut img2_planar = alloc_3planes();
-
-    let mut blur = Blur::with_simd_impl(width, height, impl_type);
-    let mut msssim = Msssim::default();
-
-    for scale in 0..NUM_SCALES {
-        if width < 8 || height < 8 {
+    let impl_type = config.backend_for(Stage::Ssim);
+    #[cfg(feature = "log")]
+    let pyramid_size = (width, height);
+
+    let mut progress = taps::Progress::new(&config, width, height);
+
+    if config.luma_only {
+        scratch.msssim.scales = luma::statistics(&img1, &img2, &config, &mut progress, &mut tap)?;
+        finish_comparison(
+            &config,
+            #[cfg(feature = "log")]
+            diagnostics.map(|diagnostics| (diagnostics, pyramid_size)),
+            &scratch.msssim,
+        );
+        return Ok(());
+    }
+
+    let Ssimulacra2Scratch {
+        blur,
+        mul,
+        sigma1_sq,
+        sigma2_sq,
+        sigma12,
+        mu1,
+        mu2,
+        img1_planar,
+        img2_planar,
+        img1_pyramid,
+        img2_pyramid,
+        msssim,
+        banded,
+        ..
+    } = scratch;
+    msssim.scales.clear();
+    let mut error_maps = tap
+        .as_ref()
+        .filter(|tap| tap.wants_error_maps())
+        .map(|_| taps::ErrorMapBuffers::new());
+    // Weighted pooling needs the per-pixel maps, so coverage mode allocates
+    let mut weighted = weights.as_ref().map(|_| {
+        let empty = || LinearRgbImage::new(Vec::new(), 0, 0);
+        let maps: [[Vec<f32>; 3]; 3] = Default::default();
+        ([empty(), empty()], maps)
+    });
+    let mut banded = (config.streaming_moments
+        && tap.is_none()
+        && weights.is_none()
+        && config.blur_kernel == BlurKernel::RecursiveGaussian)
+        .then(|| banded.get_or_insert_with(banded::BandedMoments::new));
+
+    for scale in 0..config.pyramid.num_scales() {
+        if !config.pyramid.fits(width, height) {
             break;
         }
 
         if scale > 0 {
-            img1 = downscale_by_2(&img1);
-            img2 = downscale_by_2(&img2);
-            width = img1.width();
-            height = img2.height();
+            downscale_into_pyramid(&img1, img1_pyramid, scale, downscale_impl);
+            downscale_into_pyramid(&img2, img2_pyramid, scale, downscale_impl);
+        }
+        let (img1, img2) = if scale == 0 {
+            (&*img1, &*img2)
+        } else {
+            (&img1_pyramid[scale % 2], &img2_pyramid[scale % 2])
+        };
+        width = img1.width();
+        height = img1.height();
+        progress.start_scale(width, height);
+
+        linear_rgb_to_positive_planar_into(img1.data(), width, height, img1_planar, &config);
+        linear_rgb_to_positive_planar_into(img2.data(), width, height, img2_planar, &config);
+
+        if let Some(tap) = tap.as_deref_mut() {
+            tap.xyb(scale, img1_planar, img2_planar);
+        }
+        progress.report(&mut tap, scale, 1)?;
+
+        if let Some(banded) = banded.as_deref_mut() {
+            msssim
+                .scales
+                .push(banded.statistics(img1_planar, img2_planar, &config));
+            progress.report(&mut tap, scale, taps::Progress::STEPS)?;
+            continue;
         }
 
-        // Shrink all buffers to current scale size
+        // Fit all buffers to the current scale, keeping their allocations
         let size = width * height;
         for buf in [
-            &mut mul,
-            &mut sigma1_sq,
-            &mut sigma2_sq,
-            &mut sigma12,
-            &mut mu1,
-            &mut mu2,
-            &mut img1_planar,
-            &mut img2_planar,
+            &mut *mul,
+            &mut *sigma1_sq,
+            &mut *sigma2_sq,
+            &mut *sigma12,
+            &mut *mu1,
+            &mut *mu2,
         ] {
             for c in buf.iter_mut() {
-                c.truncate(size);
+                c.resize(size, 0.0);
             }
         }
-        blur.shrink_to(width, height);
+        let blur = blur.get_or_insert_with(|| Blur::with_config(width, height, &config));
+        blur.resize(width, height);
 
-        let mut img1_xyb = linear_rgb_to_xyb(img1.clone(), impl_type);
-        let mut img2_xyb = linear_rgb_to_xyb(img2.clone(), impl_type);
+        let (img1_planes, img2_planes) = (img1_planar.planes(), img2_planar.planes());
 
-        make_positive_xyb(&mut img1_xyb);
-        make_positive_xyb(&mut img2_xyb);
+        image_multiply(img1_planes, img1_planes, mul, impl_type);
+        blur.blur_into(mul, sigma1_sq);
+        progress.report(&mut tap, scale, 2)?;
 
-        xyb_to_planar_into(&img1_xyb, &mut img1_planar);
-        xyb_to_planar_into(&img2_xyb, &mut img2_planar);
+        image_multiply(img2_planes, img2_planes, mul, impl_type);
+        blur.blur_into(mul, sigma2_sq);
+        progress.report(&mut tap, scale, 3)?;
 
-        image_multiply(&img1_planar, &img1_planar, &mut mul, impl_type);
-        blur.blur_into(&mul, &mut sigma1_sq);
+        image_multiply(img1_planes, img2_planes, mul, impl_type);
+        blur.blur_into(mul, sigma12);
+        progress.report(&mut tap, scale, 4)?;
 
-        image_multiply(&img2_planar, &img2_planar, &mut mul, impl_type);
-        blur.blur_into(&mul, &mut sigma2_sq);
+        blur.blur_into(img1_planes, mu1);
+        progress.report(&mut tap, scale, 5)?;
+        blur.blur_into(img2_planes, mu2);
+        progress.report(&mut tap, scale, 6)?;
 
-        image_multiply(&img1_planar, &img2_planar, &mut mul, impl_type);
-        blur.blur_into(&mul, &mut sigma12);
+        let moments = ScaleMoments {
+            width,
+            height,
+            mu1,
+            mu2,
+            s11: sigma1_sq,
+            s22: sigma2_sq,
+            s12: sigma12,
+        };
+        if let Some(tap) = tap.as_deref_mut() {
+            tap.moments(scale, &moments);
+            if let Some(buffers) = error_maps.as_mut() {
+                buffers.emit(tap, scale, &moments, img1_planes, img2_planes);
+            }
+        }
 
-        blur.blur_into(&img1_planar, &mut mu1);
-        blur.blur_into(&img2_planar, &mut mu2);
+        if let (Some(base), Some((pyramid, [ssim, artifact, detail_lost]))) =
+            (weights.as_ref(), weighted.as_mut())
+        {
+            if scale > 0 {
+                downscale_into_pyramid(base, pyramid, scale, downscale_impl);
+            }
+            let coverage = if scale == 0 {
+                base
+            } else {
+                &pyramid[scale % 2]
+            };
+            taps::fill_error_maps(
+                &moments,
+                img1_planes,
+                img2_planes,
+                ssim,
+                artifact,
+                detail_lost,
+            );
+            msssim.scales.push(alpha::weighted_statistics(
+                coverage.data(),
+                config.norm_exponents,
+                ssim,
+                artifact,
+                detail_lost,
+            ));
+            progress.report(&mut tap, scale, taps::Progress::STEPS)?;
+            continue;
+        }
 
+        let exponents = config.norm_exponents;
         let avg_ssim = ssim_map(
-            width, height, &mu1, &mu2, &sigma1_sq, &sigma2_sq, &sigma12, impl_type,
+            width,
+            height,
+            mu1,
+            mu2,
+            sigma1_sq,
+            sigma2_sq,
+            sigma12,
+            impl_type,
+            exponents,
+            &config.parallelism,
         );
         let avg_edgediff = edge_diff_map(
             width,
             height,
-            &img1_planar,
-            &mu1,
-            &img2_planar,
-            &mu2,
+            img1_planes,
+            mu1,
+            img2_planes,
+            mu2,
             impl_type,
+            exponents,
+            &config.parallelism,
         );
-        msssim.scales.push(MsssimScale {
+        msssim.scales.push(ScaleStatistics {
             avg_ssim,
             avg_edgediff,
         });
+        progress.report(&mut tap, scale, taps::Progress::STEPS)?;
     }
 
-    Ok(msssim.score())
+    finish_comparison(
+        &config,
+        #[cfg(feature = "log")]
+        diagnostics.map(|diagnostics| (diagnostics, pyramid_size)),
+        msssim,
+    );
+    Ok(())
 }
 
-/// Convert LinearRgb to Xyb using the specified implementation
-fn linear_rgb_to_xyb(linear_rgb: LinearRgb, impl_type: SimdImpl) -> Xyb {
-    match impl_type {
-        SimdImpl::Scalar => Xyb::from(linear_rgb),
-        SimdImpl::Simd => {
-            let width = linear_rgb.width();
-            let height = linear_rgb.height();
-            let mut data = linear_rgb.into_data();
-            xyb_simd::linear_rgb_to_xyb_simd(&mut data);
-            Xyb::new(data, width, height).expect("XYB construction should not fail")
-        }
-        #[cfg(feature = "unsafe-simd")]
-        SimdImpl::UnsafeSimd => {
-            let width = linear_rgb.width();
-            let height = linear_rgb.height();
-            let mut data = linear_rgb.into_data();
-            xyb_unsafe_simd::linear_rgb_to_xyb_unsafe(&mut data);
-            Xyb::new(data, width, height).expect("XYB construction should not fail")
-        }
+/// Hands a finished comparison to the diagnostics log and the score monitor
+#[cfg_attr(
+    not(any(feature = "log", feature = "monitor")),
+    allow(unused_variables)
+)]
+fn finish_comparison(
+    config: &Ssimulacra2Config,
+    #[cfg(feature = "log")] diagnostics: Option<(diagnostics::Comparison, (usize, usize))>,
+    msssim: &Msssim,
+) {
+    #[cfg(feature = "log")]
+    if let Some((diagnostics, pyramid_size)) = diagnostics {
+        diagnostics.finish(config, pyramid_size, &msssim.scales);
     }
-}
 
-// For backwards compatibility
-pub(crate) fn linear_rgb_to_xyb_simd(linear_rgb: LinearRgb) -> Xyb {
-    linear_rgb_to_xyb(linear_rgb, SimdImpl::Simd)
+    #[cfg(feature = "monitor")]
+    if let Some(label) = &config.monitor_label {
+        monitor::ScoreMonitor::global().record(label, msssim.score());
+    }
 }
 
-pub(crate) fn make_positive_xyb(xyb: &mut Xyb) {
-    for pix in xyb.data_mut().iter_mut() {
-        pix[2] = (pix[2] - pix[1]) + 0.55;
-        pix[0] = (pix[0]).mul_add(14.0, 0.42);
-        pix[1] += 0.01;
+/// Writes scale `scale` (at least 1) of the pyramid over `base` into
+/// `pyramid[scale % 2]`, downscaling the previous scale from the other slot
+fn downscale_into_pyramid(
+    base: &LinearRgbImage,
+    pyramid: &mut [LinearRgbImage; 2],
+    scale: usize,
+    impl_type: SimdImpl,
+) {
+    let [even, odd] = pyramid;
+    match scale {
+        1 => downscale_by_2_into(base, odd, impl_type),
+        _ if scale.is_multiple_of(2) => downscale_by_2_into(odd, even, impl_type),
+        _ => downscale_by_2_into(even, odd, impl_type),
     }
 }
 
-// Note: make_positive_xyb doesn't benefit much from AVX2 due to complex RGB3 deinterleaving
-// The scalar version is already well-optimized by the compiler
-
-// Note: xyb_to_planar doesn't benefit much from AVX2 due to complex RGB3 deinterleaving
-// The scalar version is already well-optimized by the compiler
-pub(crate) fn xyb_to_planar(xyb: &Xyb) -> [Vec<f32>; 3] {
-    let size = xyb.width() * xyb.height();
-    let mut out = [vec![0.0f32; size], vec![0.0f32; size], vec![0.0f32; size]];
-    xyb_to_planar_into(xyb, &mut out);
-    out
+/// Applies the offsets that make all XYB channels non-negative
+pub(crate) fn make_positive_xyb(xyb: &mut XybImage, impl_type: SimdImpl) {
+    match impl_type {
+        SimdImpl::Scalar => {
+            for pix in xyb.data_mut().iter_mut() {
+                pix[2] = (pix[2] - pix[1]) + 0.55;
+                pix[0] = (pix[0]).mul_add(14.0, 0.42);
+                pix[1] += 0.01;
+            }
+        }
+        // No intrinsics version: the portable one already vectorizes the
+        // deinterleave well on x86 and NEON
+        #[cfg(feature = "simd")]
+        _ => simd_ops::make_positive_xyb_simd(xyb.data_mut()),
+        // Without the `simd` feature, `Simd` runs the scalar code
+        #[cfg(not(feature = "simd"))]
+        SimdImpl::Simd => make_positive_xyb(xyb, SimdImpl::Scalar),
+    }
 }
 
 /// Convert XYB to planar format into pre-allocated buffers (zero-allocation)
-pub(crate) fn xyb_to_planar_into(xyb: &Xyb, out: &mut [Vec<f32>; 3]) {
-    let [out0, out1, out2] = out;
-    for (((i, o0), o1), o2) in xyb
-        .data()
-        .iter()
-        .copied()
-        .zip(out0.iter_mut())
-        .zip(out1.iter_mut())
-        .zip(out2.iter_mut())
+pub(crate) fn xyb_to_planar_into(xyb: &XybImage, out: &mut PlanarImage) {
+    debug_assert_eq!(xyb.width() * xyb.height(), out.width() * out.height());
+    #[cfg(feature = "simd")]
     {
-        *o0 = i[0];
-        *o1 = i[1];
-        *o2 = i[2];
+        let [out0, out1, out2] = &mut out.planes;
+        simd_ops::xyb_to_planar_simd(xyb.data(), [out0, out1, out2]);
+    }
+    #[cfg(not(feature = "simd"))]
+    for (i, pix) in xyb.data().iter().enumerate() {
+        for (plane, &v) in out.planes.iter_mut().zip(pix) {
+            plane[i] = v;
+        }
     }
 }
 
@@ -523,6 +1380,7 @@ pub(crate) fn image_multiply(
 ) {
     match impl_type {
         SimdImpl::Scalar => image_multiply_scalar(img1, img2, out),
+        #[cfg(feature = "simd")]
         SimdImpl::Simd => simd_ops::image_multiply_simd(img1, img2, out),
         #[cfg(feature = "unsafe-simd")]
         SimdImpl::UnsafeSimd => {
@@ -536,6 +1394,9 @@ pub(crate) fn image_multiply(
             // Fallback to portable SIMD if AVX2 not available
             simd_ops::image_multiply_simd(img1, img2, out);
         }
+        // Without the `simd` feature, `Simd` runs the scalar code
+        #[cfg(not(feature = "simd"))]
+        SimdImpl::Simd => image_multiply_scalar(img1, img2, out),
     }
 }
 
@@ -576,36 +1437,183 @@ unsafe fn image_multiply_avx2(img1: &[Vec<f32>; 3], img2: &[Vec<f32>; 3], out: &
     }
 }
 
-pub(crate) fn downscale_by_2(in_data: &LinearRgb) -> LinearRgb {
-    const SCALE: usize = 2;
-    let in_w = in_data.width();
-    let in_h = in_data.height();
-    let out_w = in_w.div_ceil(SCALE);
-    let out_h = in_h.div_ceil(SCALE);
-    let mut out_data = vec![[0.0f32; 3]; out_w * out_h];
+pub(crate) fn downscale_by_2(in_data: &LinearRgbImage, impl_type: SimdImpl) -> LinearRgbImage {
+    let mut out = LinearRgbImage::new(Vec::new(), 0, 0);
+    downscale_by_2_into(in_data, &mut out, impl_type);
+    out
+}
 
-    let in_data = &in_data.data();
-    for oy in 0..out_h {
-        for ox in 0..out_w {
-            for c in 0..3 {
-                let mut sum = 0f64;
-                for iy in 0..SCALE {
-                    for ix in 0..SCALE {
-                        let x = (ox * SCALE + ix).min(in_w - 1);
-                        let y = (oy * SCALE + iy).min(in_h - 1);
-                        let in_pix = in_data[y * in_w + x];
-                        sum += f64::from(in_pix[c]);
-                    }
+/// Halves `in_data` into `out`, reusing `out`'s allocation
+pub(crate) fn downscale_by_2_into(
+    in_data: &LinearRgbImage,
+    out: &mut LinearRgbImage,
+    impl_type: SimdImpl,
+) {
+    let (in_w, in_h) = (in_data.width(), in_data.height());
+    let (out_w, out_h) = (in_w.div_ceil(2), in_h.div_ceil(2));
+    out.data.clear();
+    out.data.resize(out_w * out_h, [0.0f32; 3]);
+    downscale_pixels_by_2(in_data.data(), in_w, in_h, &mut out.data, impl_type);
+    out.width = out_w;
+    out.height = out_h;
+}
+
+/// Halves interleaved pixels by averaging 2x2 blocks. `out` must hold
+/// `in_w.div_ceil(2) * in_h.div_ceil(2)` pixels.
+///
+/// Where the size is odd, the last row and column are repeated, as in
+/// libjxl. Dividing those blocks by 4 gives exactly the mean of the pixels
+/// they cover, so edge blocks need no separate sample count. Every backend
+/// sums in f64 in the same order, so all produce bit-identical output.
+pub(crate) fn downscale_pixels_by_2(
+    input: &[[f32; 3]],
+    in_w: usize,
+    in_h: usize,
+    out: &mut [[f32; 3]],
+    impl_type: SimdImpl,
+) {
+    match impl_type {
+        SimdImpl::Scalar => downscale_pixels_by_2_scalar(input, in_w, in_h, out),
+        #[cfg(feature = "simd")]
+        SimdImpl::Simd => simd_ops::downscale_by_2_simd(input, in_w, in_h, out),
+        #[cfg(feature = "unsafe-simd")]
+        SimdImpl::UnsafeSimd => {
+            #[cfg(target_arch = "x86_64")]
+            {
+                if is_x86_feature_detected!("avx2") {
+                    unsafe { downscale_pixels_by_2_avx2(input, in_w, in_h, out) };
+                    return;
                 }
-                let out_pix = &mut out_data[oy * out_w + ox];
-                out_pix[c] = (sum / (SCALE * SCALE) as f64) as f32;
             }
+            // Fallback to portable SIMD if AVX2 not available
+            simd_ops::downscale_by_2_simd(input, in_w, in_h, out);
+        }
+        // Without the `simd` feature, `Simd` runs the scalar code
+        #[cfg(not(feature = "simd"))]
+        SimdImpl::Simd => downscale_pixels_by_2_scalar(input, in_w, in_h, out),
+    }
+}
+
+fn downscale_pixels_by_2_scalar(
+    input: &[[f32; 3]],
+    in_w: usize,
+    in_h: usize,
+    out: &mut [[f32; 3]],
+) {
+    let out_w = in_w.div_ceil(2);
+    for (oy, out_row) in out.chunks_exact_mut(out_w).enumerate() {
+        let row0 = &input[2 * oy * in_w..][..in_w];
+        let row1 = &input[(2 * oy + 1).min(in_h - 1) * in_w..][..in_w];
+        for (ox, out_pix) in out_row.iter_mut().enumerate() {
+            *out_pix = average_block(row0, row1, ox);
+        }
+    }
+}
+
+/// Mean of the 2x2 block of output pixel `ox` over two input rows
+#[inline(always)]
+fn average_block(row0: &[[f32; 3]], row1: &[[f32; 3]], ox: usize) -> [f32; 3] {
+    let (x0, x1) = (2 * ox, (2 * ox + 1).min(row0.len() - 1));
+    std::array::from_fn(|c| {
+        let sum = f64::from(row0[x0][c])
+            + f64::from(row0[x1][c])
+            + f64::from(row1[x0][c])
+            + f64::from(row1[x1][c]);
+        (sum * 0.25) as f32
+    })
+}
+
+/// One output pixel per iteration: each 2x2 block is four loads of a pixel
+/// and the first channel of the next, widened to f64
+#[cfg(all(feature = "unsafe-simd", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+unsafe fn downscale_pixels_by_2_avx2(
+    input: &[[f32; 3]],
+    in_w: usize,
+    in_h: usize,
+    out: &mut [[f32; 3]],
+) {
+    use std::arch::x86_64::*;
+
+    let out_w = in_w.div_ceil(2);
+    // Blocks whose four-float loads stay inside the row: the load of the
+    // block's second pixel reads one float past it
+    let vector_blocks = in_w.saturating_sub(1) / 2;
+    let quarter = _mm256_set1_pd(0.25);
+
+    for (oy, out_row) in out.chunks_exact_mut(out_w).enumerate() {
+        let row0 = &input[2 * oy * in_w..][..in_w];
+        let row1 = &input[(2 * oy + 1).min(in_h - 1) * in_w..][..in_w];
+        let (flat0, flat1) = (row0.as_flattened(), row1.as_flattened());
+
+        for (ox, out_pix) in out_row.iter_mut().enumerate().take(vector_blocks) {
+            let (x0, x1) = (6 * ox, 6 * ox + 3);
+            let a = _mm256_cvtps_pd(safe_simd::_mm_loadu_ps(
+                flat0[x0..].first_chunk::<4>().unwrap(),
+            ));
+            let b = _mm256_cvtps_pd(safe_simd::_mm_loadu_ps(
+                flat0[x1..].first_chunk::<4>().unwrap(),
+            ));
+            let c = _mm256_cvtps_pd(safe_simd::_mm_loadu_ps(
+                flat1[x0..].first_chunk::<4>().unwrap(),
+            ));
+            let d = _mm256_cvtps_pd(safe_simd::_mm_loadu_ps(
+                flat1[x1..].first_chunk::<4>().unwrap(),
+            ));
+            // Same association as the scalar sum
+            let sum = _mm256_add_pd(_mm256_add_pd(_mm256_add_pd(a, b), c), d);
+            let mut avg = [0.0f32; 4];
+            safe_simd::_mm_storeu_ps(&mut avg, _mm256_cvtpd_ps(_mm256_mul_pd(sum, quarter)));
+            *out_pix = [avg[0], avg[1], avg[2]];
+        }
+
+        for (ox, out_pix) in out_row.iter_mut().enumerate().skip(vector_blocks) {
+            *out_pix = average_block(row0, row1, ox);
         }
     }
+}
 
-    LinearRgb::new(out_data, out_w, out_h).expect("Resolution and data size match")
+/// Rows `rows` of each plane in `planes`
+pub(crate) fn plane_rows<'a>(
+    planes: &'a [Vec<f32>; 3],
+    width: usize,
+    rows: &Range<usize>,
+) -> [&'a [f32]; 3] {
+    let pixels = rows.start * width..rows.end * width;
+    [
+        &planes[0][pixels.clone()],
+        &planes[1][pixels.clone()],
+        &planes[2][pixels],
+    ]
+}
+
+/// Turns per-plane sums of `(low, high)` powers into the norms of the means.
+pub(crate) fn pool_sums<const N: usize>(
+    sums: [f64; N],
+    pixels: usize,
+    exponents: NormExponents,
+) -> [f64; N] {
+    fn roots<const N: usize, M: Norms>(sums: [f64; N], pixels: usize, norms: M) -> [f64; N] {
+        let one_per_pixels = 1.0f64 / pixels as f64;
+        let mut averages = sums;
+        for (i, avg) in averages.iter_mut().enumerate() {
+            *avg = if i % 2 == 0 {
+                norms.low_root(one_per_pixels * *avg)
+            } else {
+                norms.high_root(one_per_pixels * *avg)
+            };
+        }
+        averages
+    }
+    if exponents == NormExponents::STANDARD {
+        roots(sums, pixels, StandardNorms)
+    } else {
+        roots(sums, pixels, exponents)
+    }
 }
 
+/// Pools the SSIM error map of each plane into its two norms. Bands of rows
+/// are summed in parallel if `parallelism` allows it.
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn ssim_map(
     width: usize,
@@ -616,30 +1624,73 @@ pub(crate) fn ssim_map(
     s22: &[Vec<f32>; 3],
     s12: &[Vec<f32>; 3],
     impl_type: SimdImpl,
+    exponents: NormExponents,
+    parallelism: &Parallelism,
+) -> [f64; 3 * 2] {
+    let sums = parallelism::sum_bands(parallelism, width, height, |rows| {
+        let rows_of = |planes| plane_rows(planes, width, &rows);
+        ssim_map_sums(
+            width,
+            rows_of(m1),
+            rows_of(m2),
+            rows_of(s11),
+            rows_of(s22),
+            rows_of(s12),
+            impl_type,
+            exponents,
+        )
+    });
+    pool_sums(sums, width * height, exponents)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn ssim_map_sums(
+    width: usize,
+    m1: [&[f32]; 3],
+    m2: [&[f32]; 3],
+    s11: [&[f32]; 3],
+    s22: [&[f32]; 3],
+    s12: [&[f32]; 3],
+    impl_type: SimdImpl,
+    exponents: NormExponents,
 ) -> [f64; 3 * 2] {
+    if exponents != NormExponents::STANDARD {
+        // The intrinsics kernels sum fourth powers in f32 vectors; other
+        // exponents go through the portable kernels, which sum each lane in f64
+        return match impl_type {
+            SimdImpl::Scalar => ssim_map_scalar(width, m1, m2, s11, s22, s12, exponents),
+            #[cfg(feature = "simd")]
+            _ => simd_ops::ssim_map_simd(width, m1, m2, s11, s22, s12, exponents),
+            #[cfg(not(feature = "simd"))]
+            SimdImpl::Simd => ssim_map_scalar(width, m1, m2, s11, s22, s12, exponents),
+        };
+    }
+    let norms = StandardNorms;
     match impl_type {
-        SimdImpl::Scalar => ssim_map_scalar(width, height, m1, m2, s11, s22, s12),
-        SimdImpl::Simd => simd_ops::ssim_map_simd(width, height, m1, m2, s11, s22, s12),
+        SimdImpl::Scalar => ssim_map_scalar(width, m1, m2, s11, s22, s12, norms),
+        #[cfg(feature = "simd")]
+        SimdImpl::Simd => simd_ops::ssim_map_simd(width, m1, m2, s11, s22, s12, norms),
         #[cfg(feature = "unsafe-simd")]
-        SimdImpl::UnsafeSimd => {
-            ssim_unsafe_simd::ssim_map_unsafe(width, height, m1, m2, s11, s22, s12)
-        }
+        SimdImpl::UnsafeSimd => ssim_unsafe_simd::ssim_map_unsafe(width, m1, m2, s11, s22, s12),
+        // Without the `simd` feature, `Simd` runs the scalar kernels
+        #[cfg(not(feature = "simd"))]
+        SimdImpl::Simd => ssim_map_scalar(width, m1, m2, s11, s22, s12, norms),
     }
 }
 
-fn ssim_map_scalar(
+/// Sums of the `(low, high)` powers of the SSIM error of each plane
+pub(crate) fn ssim_map_scalar<N: Norms>(
     width: usize,
-    height: usize,
-    m1: &[Vec<f32>; 3],
-    m2: &[Vec<f32>; 3],
-    s11: &[Vec<f32>; 3],
-    s22: &[Vec<f32>; 3],
-    s12: &[Vec<f32>; 3],
+    m1: [&[f32]; 3],
+    m2: [&[f32]; 3],
+    s11: [&[f32]; 3],
+    s22: [&[f32]; 3],
+    s12: [&[f32]; 3],
+    norms: N,
 ) -> [f64; 3 * 2] {
     const C2: f32 = 0.0009f32;
 
-    let one_per_pixels = 1.0f64 / (width * height) as f64;
-    let mut plane_averages = [0f64; 3 * 2];
+    let mut plane_sums = [0f64; 3 * 2];
 
     for c in 0..3 {
         let mut sum1 = [0.0f64; 2];
@@ -664,17 +1715,20 @@ fn ssim_map_scalar(
                     f64::from(row_s11[x] - mu11) + f64::from(row_s22[x] - mu22) + f64::from(C2);
                 let mut d = 1.0f64 - (num_m * num_s) / denom_s;
                 d = d.max(0.0);
-                sum1[0] += d;
-                sum1[1] += d.powi(4);
+                sum1[0] += norms.low_power(d);
+                sum1[1] += norms.high_power(d);
             }
         }
-        plane_averages[c * 2] = one_per_pixels * sum1[0];
-        plane_averages[c * 2 + 1] = (one_per_pixels * sum1[1]).sqrt().sqrt();
+        plane_sums[c * 2] = sum1[0];
+        plane_sums[c * 2 + 1] = sum1[1];
     }
 
-    plane_averages
+    plane_sums
 }
 
+/// Pools the artifact and detail-lost maps of each plane into their two
+/// norms. Bands of rows are summed in parallel if `parallelism` allows it.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn edge_diff_map(
     width: usize,
     height: usize,
@@ -683,27 +1737,68 @@ pub(crate) fn edge_diff_map(
     img2: &[Vec<f32>; 3],
     mu2: &[Vec<f32>; 3],
     impl_type: SimdImpl,
+    exponents: NormExponents,
+    parallelism: &Parallelism,
+) -> [f64; 3 * 4] {
+    let sums = parallelism::sum_bands(parallelism, width, height, |rows| {
+        let rows_of = |planes| plane_rows(planes, width, &rows);
+        edge_diff_map_sums(
+            width,
+            rows_of(img1),
+            rows_of(mu1),
+            rows_of(img2),
+            rows_of(mu2),
+            impl_type,
+            exponents,
+        )
+    });
+    pool_sums(sums, width * height, exponents)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn edge_diff_map_sums(
+    width: usize,
+    img1: [&[f32]; 3],
+    mu1: [&[f32]; 3],
+    img2: [&[f32]; 3],
+    mu2: [&[f32]; 3],
+    impl_type: SimdImpl,
+    exponents: NormExponents,
 ) -> [f64; 3 * 4] {
+    if exponents != NormExponents::STANDARD {
+        // Same fallback as in `ssim_map_sums`
+        return match impl_type {
+            SimdImpl::Scalar => edge_diff_map_scalar(width, img1, mu1, img2, mu2, exponents),
+            #[cfg(feature = "simd")]
+            _ => simd_ops::edge_diff_map_simd(width, img1, mu1, img2, mu2, exponents),
+            #[cfg(not(feature = "simd"))]
+            SimdImpl::Simd => edge_diff_map_scalar(width, img1, mu1, img2, mu2, exponents),
+        };
+    }
+    let norms = StandardNorms;
     match impl_type {
-        SimdImpl::Scalar => edge_diff_map_scalar(width, height, img1, mu1, img2, mu2),
-        SimdImpl::Simd => simd_ops::edge_diff_map_simd(width, height, img1, mu1, img2, mu2),
+        SimdImpl::Scalar => edge_diff_map_scalar(width, img1, mu1, img2, mu2, norms),
+        #[cfg(feature = "simd")]
+        SimdImpl::Simd => simd_ops::edge_diff_map_simd(width, img1, mu1, img2, mu2, norms),
         #[cfg(feature = "unsafe-simd")]
-        SimdImpl::UnsafeSimd => {
-            ssim_unsafe_simd::edge_diff_map_unsafe(width, height, img1, mu1, img2, mu2)
-        }
+        SimdImpl::UnsafeSimd => ssim_unsafe_simd::edge_diff_map_unsafe(width, img1, mu1, img2, mu2),
+        // Without the `simd` feature, `Simd` runs the scalar kernels
+        #[cfg(not(feature = "simd"))]
+        SimdImpl::Simd => edge_diff_map_scalar(width, img1, mu1, img2, mu2, norms),
     }
 }
 
-fn edge_diff_map_scalar(
+/// Sums of the `(low, high)` powers of the artifact and detail-lost maps of
+/// each plane
+pub(crate) fn edge_diff_map_scalar<N: Norms>(
     width: usize,
-    height: usize,
-    img1: &[Vec<f32>; 3],
-    mu1: &[Vec<f32>; 3],
-    img2: &[Vec<f32>; 3],
-    mu2: &[Vec<f32>; 3],
+    img1: [&[f32]; 3],
+    mu1: [&[f32]; 3],
+    img2: [&[f32]; 3],
+    mu2: [&[f32]; 3],
+    norms: N,
 ) -> [f64; 3 * 4] {
-    let one_per_pixels = 1.0f64 / (width * height) as f64;
-    let mut plane_averages = [0f64; 3 * 4];
+    let mut plane_sums = [0f64; 3 * 4];
 
     for c in 0..3 {
         let mut sum1 = [0.0f64; 4];
@@ -718,189 +1813,255 @@ fn edge_diff_map_scalar(
                     - 1.0;
 
                 let artifact = d1.max(0.0);
-                sum1[0] += artifact;
-                sum1[1] += artifact.powi(4);
+                sum1[0] += norms.low_power(artifact);
+                sum1[1] += norms.high_power(artifact);
 
                 let detail_lost = (-d1).max(0.0);
-                sum1[2] += detail_lost;
-                sum1[3] += detail_lost.powi(4);
+                sum1[2] += norms.low_power(detail_lost);
+                sum1[3] += norms.high_power(detail_lost);
             }
         }
-        plane_averages[c * 4] = one_per_pixels * sum1[0];
-        plane_averages[c * 4 + 1] = (one_per_pixels * sum1[1]).sqrt().sqrt();
-        plane_averages[c * 4 + 2] = one_per_pixels * sum1[2];
-        plane_averages[c * 4 + 3] = (one_per_pixels * sum1[3]).sqrt().sqrt();
+        plane_sums[c * 4..][..4].copy_from_slice(&sum1);
     }
 
-    plane_averages
+    plane_sums
 }
 
 #[derive(Debug, Clone, Default)]
 pub(crate) struct Msssim {
-    pub scales: Vec<MsssimScale>,
-}
-
-#[derive(Debug, Clone, Copy, Default)]
-pub(crate) struct MsssimScale {
-    pub avg_ssim: [f64; 3 * 2],
-    pub avg_edgediff: [f64; 3 * 4],
+    pub scales: Vec<ScaleStatistics>,
+    /// Multipliers on the score weights, from the config
+    pub weights: ScaleWeights,
+    /// Halvings a [`time_budget`](Ssimulacra2Config::time_budget) forced
+    pub budget_halvings: u32,
 }
 
 impl Msssim {
-    #[allow(clippy::too_many_lines)]
     pub fn score(&self) -> f64 {
-        const WEIGHT: [f64; 108] = [
-            0.0,
-            0.000_737_660_670_740_658_6,
-            0.0,
-            0.0,
-            0.000_779_348_168_286_730_9,
-            0.0,
-            0.0,
-            0.000_437_115_573_010_737_9,
-            0.0,
-            1.104_172_642_665_734_6,
-            0.000_662_848_341_292_71,
-            0.000_152_316_327_837_187_52,
-            0.0,
-            0.001_640_643_745_659_975_4,
-            0.0,
-            1.842_245_552_053_929_8,
-            11.441_172_603_757_666,
-            0.0,
-            0.000_798_910_943_601_516_3,
-            0.000_176_816_438_078_653,
-            0.0,
-            1.878_759_497_954_638_7,
-            10.949_069_906_051_42,
-            0.0,
-            0.000_728_934_699_150_807_2,
-            0.967_793_708_062_683_3,
-            0.0,
-            0.000_140_034_242_854_358_84,
-            0.998_176_697_785_496_7,
-            0.000_319_497_559_344_350_53,
-            0.000_455_099_211_379_206_3,
-            0.0,
-            0.0,
-            0.001_364_876_616_324_339_8,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            7.466_890_328_078_848,
-            0.0,
-            17.445_833_984_131_262,
-            0.000_623_560_163_404_146_6,
-            0.0,
-            0.0,
-            6.683_678_146_179_332,
-            0.000_377_244_079_796_112_96,
-            1.027_889_937_768_264,
-            225.205_153_008_492_74,
-            0.0,
-            0.0,
-            19.213_238_186_143_016,
-            0.001_140_152_458_661_836_1,
-            0.001_237_755_635_509_985,
-            176.393_175_984_506_94,
-            0.0,
-            0.0,
-            24.433_009_998_704_76,
-            0.285_208_026_121_177_57,
-            0.000_448_543_692_383_340_8,
-            0.0,
-            0.0,
-            0.0,
-            34.779_063_444_837_72,
-            44.835_625_328_877_896,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.000_868_055_657_329_169_8,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.000_531_319_187_435_874_7,
-            0.0,
-            0.000_165_338_141_613_791_12,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.0,
-            0.000_417_917_180_325_133_6,
-            0.001_729_082_823_472_283_3,
-            0.0,
-            0.002_082_700_584_663_643_7,
-            0.0,
-            0.0,
-            8.826_982_764_996_862,
-            23.192_433_439_989_26,
-            0.0,
-            95.108_049_881_108_6,
-            0.986_397_803_440_068_2,
-            0.983_438_279_246_535_3,
-            0.001_228_640_504_827_849_3,
-            171.266_725_589_730_7,
-            0.980_785_887_243_537_9,
-            0.0,
-            0.0,
-            0.0,
-            0.000_513_006_458_899_067_9,
-            0.0,
-            0.000_108_540_578_584_115_37,
-        ];
-
-        let mut ssim = 0.0f64;
-
-        let mut i = 0usize;
-        for c in 0..3 {
-            for scale in &self.scales {
-                for n in 0..2 {
-                    ssim = WEIGHT[i].mul_add(scale.avg_ssim[c * 2 + n].abs(), ssim);
-                    i += 1;
-                    ssim = WEIGHT[i].mul_add(scale.avg_edgediff[c * 4 + n].abs(), ssim);
-                    i += 1;
-                    ssim = WEIGHT[i].mul_add(scale.avg_edgediff[c * 4 + n + 2].abs(), ssim);
-                    i += 1;
-                }
-            }
-        }
+        score_scales_weighted(&self.scales, &self.weights)
+    }
+}
 
-        ssim *= 0.956_238_261_683_484_4_f64;
-        ssim = (6.248_496_625_763_138e-5 * ssim * ssim).mul_add(
-            ssim,
-            2.326_765_642_916_932f64.mul_add(ssim, -0.020_884_521_182_843_837 * ssim * ssim),
-        );
+/// Weights of the per-scale norms, in the order [`score_scales`] visits them:
+/// per channel, per scale, per norm, the SSIM, artifact and detail-lost terms.
+pub(crate) const SCORE_WEIGHTS: [f64; 108] = [
+    0.0,
+    0.000_737_660_670_740_658_6,
+    0.0,
+    0.0,
+    0.000_779_348_168_286_730_9,
+    0.0,
+    0.0,
+    0.000_437_115_573_010_737_9,
+    0.0,
+    1.104_172_642_665_734_6,
+    0.000_662_848_341_292_71,
+    0.000_152_316_327_837_187_52,
+    0.0,
+    0.001_640_643_745_659_975_4,
+    0.0,
+    1.842_245_552_053_929_8,
+    11.441_172_603_757_666,
+    0.0,
+    0.000_798_910_943_601_516_3,
+    0.000_176_816_438_078_653,
+    0.0,
+    1.878_759_497_954_638_7,
+    10.949_069_906_051_42,
+    0.0,
+    0.000_728_934_699_150_807_2,
+    0.967_793_708_062_683_3,
+    0.0,
+    0.000_140_034_242_854_358_84,
+    0.998_176_697_785_496_7,
+    0.000_319_497_559_344_350_53,
+    0.000_455_099_211_379_206_3,
+    0.0,
+    0.0,
+    0.001_364_876_616_324_339_8,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    7.466_890_328_078_848,
+    0.0,
+    17.445_833_984_131_262,
+    0.000_623_560_163_404_146_6,
+    0.0,
+    0.0,
+    6.683_678_146_179_332,
+    0.000_377_244_079_796_112_96,
+    1.027_889_937_768_264,
+    225.205_153_008_492_74,
+    0.0,
+    0.0,
+    19.213_238_186_143_016,
+    0.001_140_152_458_661_836_1,
+    0.001_237_755_635_509_985,
+    176.393_175_984_506_94,
+    0.0,
+    0.0,
+    24.433_009_998_704_76,
+    0.285_208_026_121_177_57,
+    0.000_448_543_692_383_340_8,
+    0.0,
+    0.0,
+    0.0,
+    34.779_063_444_837_72,
+    44.835_625_328_877_896,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.000_868_055_657_329_169_8,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.000_531_319_187_435_874_7,
+    0.0,
+    0.000_165_338_141_613_791_12,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.0,
+    0.000_417_917_180_325_133_6,
+    0.001_729_082_823_472_283_3,
+    0.0,
+    0.002_082_700_584_663_643_7,
+    0.0,
+    0.0,
+    8.826_982_764_996_862,
+    23.192_433_439_989_26,
+    0.0,
+    95.108_049_881_108_6,
+    0.986_397_803_440_068_2,
+    0.983_438_279_246_535_3,
+    0.001_228_640_504_827_849_3,
+    171.266_725_589_730_7,
+    0.980_785_887_243_537_9,
+    0.0,
+    0.0,
+    0.0,
+    0.000_513_006_458_899_067_9,
+    0.0,
+    0.000_108_540_578_584_115_37,
+];
+
+/// Combines per-scale statistics into the final SSIMULACRA2 score.
+pub(crate) fn score_scales(scales: &[ScaleStatistics]) -> f64 {
+    score_scales_weighted(scales, &ScaleWeights::STANDARD)
+}
 
-        if ssim > 0.0f64 {
-            ssim = ssim
-                .powf(0.627_633_646_783_138_7)
-                .mul_add(-10.0f64, 100.0f64);
-        } else {
-            ssim = 100.0f64;
+/// [`score_scales`] with the weights multiplied by `weights`
+pub(crate) fn score_scales_weighted(scales: &[ScaleStatistics], weights: &ScaleWeights) -> f64 {
+    let mut ssim = 0.0f64;
+
+    let mut i = 0usize;
+    for c in 0..3 {
+        for (s, scale) in scales.iter().enumerate() {
+            // Exactly the fitted weights with the standard multipliers of 1
+            let factor = weights.factor(c, s);
+            let w = |i: usize| SCORE_WEIGHTS[i] * factor;
+            for n in 0..2 {
+                ssim = w(i).mul_add(scale.avg_ssim[c * 2 + n].abs(), ssim);
+                i += 1;
+                ssim = w(i).mul_add(scale.avg_edgediff[c * 4 + n].abs(), ssim);
+                i += 1;
+                ssim = w(i).mul_add(scale.avg_edgediff[c * 4 + n + 2].abs(), ssim);
+                i += 1;
+            }
         }
+    }
 
-        ssim
+    ssim *= 0.956_238_261_683_484_4_f64;
+    ssim = (6.248_496_625_763_138e-5 * ssim * ssim).mul_add(
+        ssim,
+        2.326_765_642_916_932f64.mul_add(ssim, -0.020_884_521_182_843_837 * ssim * ssim),
+    );
+
+    if ssim > 0.0f64 {
+        ssim = ssim
+            .powf(0.627_633_646_783_138_7)
+            .mul_add(-10.0f64, 100.0f64);
+    } else {
+        ssim = 100.0f64;
     }
+
+    ssim
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::test_util::pattern;
     use std::path::PathBuf;
 
     use super::*;
-    use yuvxyb::{ColorPrimaries, Rgb, TransferCharacteristic};
+    #[cfg(feature = "yuvxyb")]
+    use yuvxyb::{ColorPrimaries, Rgb, TransferCharacteristic, Xyb};
+
+    #[test]
+    fn test_compute_from_rows_matches_full_frame() {
+        let source = pattern(40, 24, 0.0);
+        let distorted = pattern(40, 24, 0.3);
+
+        let from_rows = compute_from_rows(
+            source.data().chunks_exact(40),
+            distorted.data().chunks_exact(40),
+            40,
+            24,
+        )
+        .unwrap();
+        assert_eq!(from_rows, compute_ssimulacra2(source, distorted).unwrap());
+    }
+
+    #[test]
+    fn test_early_downscale_matches_downscaled_inputs() {
+        let source = pattern(128, 96, 0.0);
+        let distorted = pattern(128, 96, 0.2);
+
+        // 12288 px; a 0.002 MP threshold requires two halvings (3072 px, then 768 px)
+        let config = Ssimulacra2Config::default().with_early_downscale(0.002);
+        let early =
+            compute_ssimulacra2_with_config(source.clone(), distorted.clone(), config).unwrap();
+
+        let expected = compute_ssimulacra2(
+            downscale_by_2(&downscale_by_2(&source, SimdImpl::Simd), SimdImpl::Simd),
+            downscale_by_2(&downscale_by_2(&distorted, SimdImpl::Simd), SimdImpl::Simd),
+        )
+        .unwrap();
+        assert_eq!(early, expected);
+
+        // Below the threshold nothing changes
+        let config = Ssimulacra2Config::default().with_early_downscale(1.0);
+        assert_eq!(
+            compute_ssimulacra2_with_config(source.clone(), distorted.clone(), config).unwrap(),
+            compute_ssimulacra2(source, distorted).unwrap()
+        );
+    }
 
+    #[test]
+    fn test_unavailable_blur_kernel_is_rejected() {
+        let (source, distorted) = (pattern(40, 24, 0.0), pattern(40, 24, 0.3));
+        let config = Ssimulacra2Config::default().with_blur_kernel(BlurKernel::SummedArea);
+        let score = compute_ssimulacra2_with_config(&source, &distorted, config.clone());
+        let striped = StripedScorer::new(40, 24, config).map(|_| ());
+        if cfg!(feature = "integral-blur") {
+            assert!(score.is_ok() && striped.is_ok());
+        } else {
+            assert_eq!(score, Err(Ssimulacra2Error::BlurKernelUnavailable));
+            assert_eq!(striped, Err(Ssimulacra2Error::BlurKernelUnavailable));
+        }
+    }
+
+    #[cfg(feature = "yuvxyb")]
     #[test]
     fn test_ssimulacra2() {
         let source = image::open(
@@ -955,6 +2116,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ssimulacra2_scalar_backend() {
+        // Same reference score through the scalar pipeline, which is all a
+        // build without the `simd` and `yuvxyb` features has
+        let load = |name: &str| {
+            let img = image::open(
+                PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                    .join("test_data")
+                    .join(name),
+            )
+            .unwrap()
+            .to_rgb8();
+            let (width, height) = (img.width() as usize, img.height() as usize);
+            LinearRgbImage::from_srgb_u8(img.as_raw(), width, height).unwrap()
+        };
+        let result = compute_ssimulacra2_with_config(
+            load("tank_source.png"),
+            load("tank_distorted.png"),
+            Ssimulacra2Config::scalar(),
+        )
+        .unwrap();
+        let expected = 17.398_505_f64;
+        assert!(
+            (result - expected).abs() < 0.25f64,
+            "Result {result:.6} not equal to expected {expected:.6}",
+        );
+    }
+
+    #[cfg(feature = "yuvxyb")]
     #[test]
     fn test_xyb_simd_vs_yuvxyb() {
         use yuvxyb::{ColorPrimaries, TransferCharacteristic};
@@ -995,7 +2185,8 @@ mod tests {
         )
         .unwrap();
         let lrgb_for_simd = LinearRgb::try_from(rgb_for_simd).unwrap();
-        let xyb_simd = linear_rgb_to_xyb_simd(lrgb_for_simd);
+        let xyb_simd =
+            linear_rgb_to_xyb_with_config(&lrgb_for_simd.into(), Ssimulacra2Config::simd());
 
         let mut max_diff = [0.0f32; 3];
         for (yuvxyb_pix, simd_pix) in xyb_yuvxyb.data().iter().zip(xyb_simd.data().iter()) {
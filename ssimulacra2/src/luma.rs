@@ -0,0 +1,294 @@
+//! Luminance-only comparison, for grayscale content.
+//!
+//! Gray pixels have equal R, G and B, which the opsin absorbance matrix maps
+//! to equal L, M and S responses, so the X and B planes of a grayscale image
+//! are constant and add nothing but rounding noise to the score. For
+//! [`Ssimulacra2Config::luma_only`](crate::Ssimulacra2Config::luma_only) this
+//! module blurs and pools only the Y plane and reports no X or B error,
+//! skipping two thirds of the blurs and error maps. That noise is not
+//! negligible: `f32` variances of a near-constant plane are rounding error
+//! divided by a small constant, and the standard pipeline scores it. On
+//! grayscale inputs the luma-only score is therefore about 0.1 higher than the
+//! standard one on textured content, and identical on exact matches. On color
+//! inputs differences in X and B are not seen at all, so the score is not a
+//! SSIMULACRA2 score.
+//!
+//! [`compute_ssimulacra2_gray`] takes single-channel images directly and
+//! always runs this path.
+
+use std::borrow::Cow;
+
+use crate::blur::Blur;
+use crate::input::{srgb_u16_to_linear, srgb_u8_to_linear, LinearRgbImage};
+use crate::norms::StandardNorms;
+use crate::planar::PlanarImage;
+use crate::taps::{PipelineTap, Progress};
+use crate::xyb::linear_rgb_to_positive_planar_into;
+use crate::{
+    compute_linear_ssimulacra2, downscale_by_2, edge_diff_map_scalar, pool_sums, ssim_map_scalar,
+    NormExponents, ScaleStatistics, Ssimulacra2Config, Ssimulacra2Error, Stage,
+};
+
+/// A single-channel sample accepted by [`compute_ssimulacra2_gray`]
+pub trait GraySample: Copy {
+    /// The sample as linear light, 0.0 to 1.0
+    fn to_linear(self) -> f32;
+}
+
+/// sRGB-encoded 8-bit gray
+impl GraySample for u8 {
+    fn to_linear(self) -> f32 {
+        srgb_u8_to_linear(self)
+    }
+}
+
+/// sRGB-encoded 16-bit gray
+impl GraySample for u16 {
+    fn to_linear(self) -> f32 {
+        srgb_u16_to_linear(self)
+    }
+}
+
+/// Linear gray
+impl GraySample for f32 {
+    fn to_linear(self) -> f32 {
+        self
+    }
+}
+
+/// Computes the score of two grayscale images of `width * height` samples
+/// each, stored row by row.
+///
+/// Only the luminance channel is blurred and compared, as with
+/// [`Ssimulacra2Config::luma_only`], so this takes about a third of the
+/// time of scoring the same images as RGB, with the same score up to
+/// rounding.
+///
+/// # Errors
+/// - If either slice doesn't hold exactly `width * height` samples
+/// - If the images are smaller than 8x8 pixels
+pub fn compute_ssimulacra2_gray<T: GraySample>(
+    source: &[T],
+    distorted: &[T],
+    width: usize,
+    height: usize,
+) -> Result<f64, Ssimulacra2Error> {
+    compute_ssimulacra2_gray_with_config(
+        source,
+        distorted,
+        width,
+        height,
+        Ssimulacra2Config::default(),
+    )
+}
+
+/// Computes the score of two grayscale images with custom configuration.
+/// [`luma_only`](Ssimulacra2Config::luma_only) is always enabled.
+pub fn compute_ssimulacra2_gray_with_config<T: GraySample>(
+    source: &[T],
+    distorted: &[T],
+    width: usize,
+    height: usize,
+    config: Ssimulacra2Config,
+) -> Result<f64, Ssimulacra2Error> {
+    if source.len() != width * height || distorted.len() != width * height {
+        return Err(Ssimulacra2Error::RowDataMismatch);
+    }
+    // The XYB conversion is cheap next to the blurs; it sees gray RGB pixels
+    // so that the Y plane is exactly the one color inputs would give
+    let expand = |samples: &[T]| {
+        let data = samples.iter().map(|&v| [v.to_linear(); 3]).collect();
+        LinearRgbImage::new(data, width, height)
+    };
+    compute_linear_ssimulacra2(
+        expand(source),
+        expand(distorted),
+        config.with_luma_only(true),
+    )
+}
+
+/// Index of the Y plane in positive XYB
+const Y: usize = 1;
+
+/// `plane` in the Y slot of a set of planes. The X and B slots are empty,
+/// so the error maps sum nothing there.
+fn luma(plane: &[f32]) -> [&[f32]; 3] {
+    let mut planes: [&[f32]; 3] = [&[]; 3];
+    planes[Y] = plane;
+    planes
+}
+
+/// Per-scale statistics of the pair's Y planes, with zero X and B norms.
+/// Both images must have the same size, at least the pyramid's minimum.
+pub(crate) fn statistics(
+    img1: &LinearRgbImage,
+    img2: &LinearRgbImage,
+    config: &Ssimulacra2Config,
+    progress: &mut Progress,
+    tap: &mut Option<&mut dyn PipelineTap>,
+) -> Result<Vec<ScaleStatistics>, Ssimulacra2Error> {
+    let downscale_impl = config.backend_for(Stage::Xyb);
+    let (mut img1, mut img2) = (Cow::Borrowed(img1), Cow::Borrowed(img2));
+    let mut blur = Blur::with_config(img1.width(), img1.height(), config);
+    let (mut xyb1, mut xyb2) = (PlanarImage::zeros(0, 0), PlanarImage::zeros(0, 0));
+    let [mut mul, mut s11, mut s22, mut s12, mut mu1, mut mu2]: [Vec<f32>; 6] = Default::default();
+
+    let mut scales = Vec::with_capacity(config.pyramid.num_scales());
+    for scale in 0..config.pyramid.num_scales() {
+        if !config.pyramid.fits(img1.width(), img1.height()) {
+            break;
+        }
+        if scale > 0 {
+            img1 = Cow::Owned(downscale_by_2(&img1, downscale_impl));
+            img2 = Cow::Owned(downscale_by_2(&img2, downscale_impl));
+        }
+        let (width, height) = (img1.width(), img1.height());
+        progress.start_scale(width, height);
+        linear_rgb_to_positive_planar_into(img1.data(), width, height, &mut xyb1, config);
+        linear_rgb_to_positive_planar_into(img2.data(), width, height, &mut xyb2, config);
+        progress.report(tap, scale, 1)?;
+        let (y1, y2) = (&xyb1.planes()[Y][..], &xyb2.planes()[Y][..]);
+
+        for buf in [&mut mul, &mut s11, &mut s22, &mut s12, &mut mu1, &mut mu2] {
+            buf.resize(width * height, 0.0);
+        }
+        blur.resize(width, height);
+        let mut blur_product = |a: &[f32], b: &[f32], out: &mut [f32]| {
+            for (m, (a, b)) in mul.iter_mut().zip(a.iter().zip(b)) {
+                *m = a * b;
+            }
+            blur.blur_plane_into(&mul, out);
+        };
+        blur_product(y1, y1, &mut s11);
+        progress.report(tap, scale, 2)?;
+        blur_product(y2, y2, &mut s22);
+        progress.report(tap, scale, 3)?;
+        blur_product(y1, y2, &mut s12);
+        progress.report(tap, scale, 4)?;
+        blur.blur_plane_into(y1, &mut mu1);
+        progress.report(tap, scale, 5)?;
+        blur.blur_plane_into(y2, &mut mu2);
+        progress.report(tap, scale, 6)?;
+
+        let exponents = config.norm_exponents;
+        let (ssim_sums, edge_sums) = if exponents == NormExponents::STANDARD {
+            (
+                ssim_map_scalar(
+                    width,
+                    luma(&mu1),
+                    luma(&mu2),
+                    luma(&s11),
+                    luma(&s22),
+                    luma(&s12),
+                    StandardNorms,
+                ),
+                edge_diff_map_scalar(
+                    width,
+                    luma(y1),
+                    luma(&mu1),
+                    luma(y2),
+                    luma(&mu2),
+                    StandardNorms,
+                ),
+            )
+        } else {
+            (
+                ssim_map_scalar(
+                    width,
+                    luma(&mu1),
+                    luma(&mu2),
+                    luma(&s11),
+                    luma(&s22),
+                    luma(&s12),
+                    exponents,
+                ),
+                edge_diff_map_scalar(width, luma(y1), luma(&mu1), luma(y2), luma(&mu2), exponents),
+            )
+        };
+        scales.push(ScaleStatistics {
+            avg_ssim: pool_sums(ssim_sums, width * height, exponents),
+            avg_edgediff: pool_sums(edge_sums, width * height, exponents),
+        });
+        progress.report(tap, scale, Progress::STEPS)?;
+    }
+    Ok(scales)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_ssimulacra2_detailed, compute_ssimulacra2_with_config};
+
+    fn gray(width: usize, height: usize, phase: f32) -> LinearRgbImage {
+        let data = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                [0.5 + 0.4 * (x * 0.4 + phase).sin() * (y * 0.3).cos(); 3]
+            })
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    }
+
+    #[test]
+    fn test_grayscale_matches_standard_score() {
+        let (source, distorted) = (gray(120, 90, 0.0), gray(120, 90, 0.3));
+        let standard = Ssimulacra2Config::default();
+        let luma = standard.clone().with_luma_only(true);
+        let expected = compute_ssimulacra2_with_config(&source, &distorted, standard).unwrap();
+        let score = compute_ssimulacra2_with_config(&source, &distorted, luma.clone()).unwrap();
+        // The standard score includes X and B rounding noise (0.09 here)
+        assert!((score - expected).abs() < 0.2, "{} vs {}", score, expected);
+        assert!(score >= expected, "{} vs {}", score, expected);
+        assert_eq!(
+            compute_ssimulacra2_with_config(&source, &source, luma.clone()),
+            Ok(100.0)
+        );
+
+        let detailed = compute_ssimulacra2_detailed(&source, &distorted, luma).unwrap();
+        for scale in &detailed.scales {
+            assert_eq!([&scale.avg_ssim[..2], &scale.avg_ssim[4..]], [[0.0; 2]; 2]);
+            assert_eq!(
+                [&scale.avg_edgediff[..4], &scale.avg_edgediff[8..]],
+                [[0.0; 4]; 2]
+            );
+            assert!(scale.avg_ssim[2] > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_chroma_differences_are_not_seen() {
+        let source = gray(64, 64, 0.0);
+        // The same pattern with a color cast
+        let tinted = LinearRgbImage::new(
+            source
+                .data()
+                .iter()
+                .map(|&[r, g, b]| [r * 1.1, g, b * 0.9])
+                .collect(),
+            64,
+            64,
+        );
+        let luma = Ssimulacra2Config::default().with_luma_only(true);
+        let standard =
+            compute_ssimulacra2_with_config(&source, &tinted, Ssimulacra2Config::default());
+        let luma = compute_ssimulacra2_with_config(&source, &tinted, luma);
+        assert!(luma.unwrap() > standard.unwrap());
+    }
+
+    #[test]
+    fn test_gray_entry_points() {
+        let (source, distorted) = (gray(64, 48, 0.0), gray(64, 48, 0.3));
+        let luma = Ssimulacra2Config::default().with_luma_only(true);
+        let expected = compute_ssimulacra2_with_config(&source, &distorted, luma).unwrap();
+        let plane = |image: &LinearRgbImage| image.data().iter().map(|p| p[0]).collect::<Vec<_>>();
+        let (a, b) = (plane(&source), plane(&distorted));
+        assert_eq!(compute_ssimulacra2_gray(&a, &b, 64, 48), Ok(expected));
+
+        let bytes: Vec<u8> = (0..64 * 48).map(|i| (i % 251) as u8).collect();
+        assert_eq!(compute_ssimulacra2_gray(&bytes, &bytes, 64, 48), Ok(100.0));
+        assert_eq!(
+            compute_ssimulacra2_gray(&bytes, &bytes[1..], 64, 48),
+            Err(Ssimulacra2Error::RowDataMismatch)
+        );
+    }
+}
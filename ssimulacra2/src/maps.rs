@@ -0,0 +1,356 @@
+//! Per-scale maps written into reusable buffers.
+//!
+//! [`PipelineTap`] hands out views that are only valid during the call, so
+//! every caller that wants to keep a heatmap copies it into a fresh `Vec`.
+//! For per-frame heatmaps in a monitoring UI that is several full-size
+//! allocations per frame. [`compute_ssimulacra2_maps`] instead writes the
+//! maps selected by a [`MapRequest`] into a caller-owned [`MapBuffers`],
+//! which keeps its planes between calls: once it has seen the largest frame
+//! size, extracting maps allocates nothing.
+//!
+//! The pipeline's own scratch buffers are still allocated per comparison;
+//! only the map outputs are pooled.
+
+use crate::taps::fill_error_maps;
+use crate::{
+    compute_ssimulacra2_with_taps, ErrorMaps, PipelineTap, PlanarImage, ScaleMoments,
+    Ssimulacra2Config, Ssimulacra2Error, ToLinearRgb, NUM_SCALES,
+};
+
+/// Which maps [`compute_ssimulacra2_maps`] extracts, and at which scales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapRequest {
+    /// Bit `n` selects scale `n` (0 is full resolution)
+    pub scales: u8,
+    /// Extract the per-pixel error maps (see [`ErrorMaps`])
+    pub error_maps: bool,
+    /// Extract the offset XYB planes of both images
+    pub xyb: bool,
+}
+
+impl Default for MapRequest {
+    /// Error maps at every scale
+    fn default() -> Self {
+        Self {
+            scales: (1 << NUM_SCALES) - 1,
+            error_maps: true,
+            xyb: false,
+        }
+    }
+}
+
+impl MapRequest {
+    /// Error maps at every scale
+    pub fn error_maps() -> Self {
+        Self::default()
+    }
+
+    /// XYB planes at every scale
+    pub fn xyb() -> Self {
+        Self {
+            error_maps: false,
+            xyb: true,
+            ..Self::default()
+        }
+    }
+
+    /// Restrict extraction to the scales in `scales`.
+    #[must_use]
+    pub fn with_scales(mut self, scales: std::ops::Range<usize>) -> Self {
+        self.scales = 0;
+        for scale in scales.start..scales.end.min(NUM_SCALES) {
+            self.scales |= 1 << scale;
+        }
+        self
+    }
+
+    /// Also extract (or stop extracting) the XYB planes.
+    #[must_use]
+    pub fn with_xyb(mut self, enabled: bool) -> Self {
+        self.xyb = enabled;
+        self
+    }
+
+    /// Also extract (or stop extracting) the error maps.
+    #[must_use]
+    pub fn with_error_maps(mut self, enabled: bool) -> Self {
+        self.error_maps = enabled;
+        self
+    }
+
+    /// Whether `scale` is selected
+    pub fn wants_scale(&self, scale: usize) -> bool {
+        scale < NUM_SCALES && self.scales & (1 << scale) != 0
+    }
+}
+
+/// Offset XYB planes of both images at one scale.
+#[derive(Debug, Clone, Copy)]
+pub struct XybPlanes<'a> {
+    /// Plane width
+    pub width: usize,
+    /// Plane height
+    pub height: usize,
+    /// Source planes
+    pub source: &'a [Vec<f32>; 3],
+    /// Distorted planes
+    pub distorted: &'a [Vec<f32>; 3],
+}
+
+#[derive(Debug, Default)]
+struct ScaleMaps {
+    width: usize,
+    height: usize,
+    has_xyb: bool,
+    has_error_maps: bool,
+    source: [Vec<f32>; 3],
+    distorted: [Vec<f32>; 3],
+    ssim: [Vec<f32>; 3],
+    artifact: [Vec<f32>; 3],
+    detail_lost: [Vec<f32>; 3],
+}
+
+/// Reusable storage for the maps of one comparison.
+///
+/// Holds the maps of the most recent [`compute_ssimulacra2_maps`] call;
+/// each call overwrites them, reusing the existing planes.
+#[derive(Debug, Default)]
+pub struct MapBuffers {
+    scales: Vec<ScaleMaps>,
+}
+
+impl MapBuffers {
+    /// Creates empty buffers; planes are allocated by the first comparison.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Error maps at `scale`, if they were requested and the scale exists
+    pub fn error_maps(&self, scale: usize) -> Option<ErrorMaps<'_>> {
+        let maps = self.scales.get(scale).filter(|m| m.has_error_maps)?;
+        Some(ErrorMaps {
+            width: maps.width,
+            height: maps.height,
+            ssim: &maps.ssim,
+            artifact: &maps.artifact,
+            detail_lost: &maps.detail_lost,
+        })
+    }
+
+    /// XYB planes at `scale`, if they were requested and the scale exists
+    pub fn xyb(&self, scale: usize) -> Option<XybPlanes<'_>> {
+        let maps = self.scales.get(scale).filter(|m| m.has_xyb)?;
+        Some(XybPlanes {
+            width: maps.width,
+            height: maps.height,
+            source: &maps.source,
+            distorted: &maps.distorted,
+        })
+    }
+
+    fn reset(&mut self) {
+        for maps in &mut self.scales {
+            maps.has_xyb = false;
+            maps.has_error_maps = false;
+        }
+    }
+
+    fn scale_mut(&mut self, scale: usize) -> &mut ScaleMaps {
+        if self.scales.len() <= scale {
+            self.scales.resize_with(scale + 1, ScaleMaps::default);
+        }
+        &mut self.scales[scale]
+    }
+}
+
+/// Copies `src` into `dst`, reusing its capacity
+fn copy_planes(dst: &mut [Vec<f32>; 3], src: &[Vec<f32>; 3]) {
+    for (dst, src) in dst.iter_mut().zip(src) {
+        dst.clear();
+        dst.extend_from_slice(src);
+    }
+}
+
+/// Tap filling [`MapBuffers`]. The error maps need the XYB planes, which are
+/// only passed to [`PipelineTap::xyb`], so those are kept for every selected
+/// scale and hidden again if only error maps were requested.
+struct MapCollector<'a> {
+    request: MapRequest,
+    buffers: &'a mut MapBuffers,
+}
+
+impl PipelineTap for MapCollector<'_> {
+    fn xyb(&mut self, scale: usize, source: &PlanarImage, distorted: &PlanarImage) {
+        if !self.request.wants_scale(scale) || !(self.request.xyb || self.request.error_maps) {
+            return;
+        }
+        let maps = self.buffers.scale_mut(scale);
+        maps.width = source.width();
+        maps.height = source.height();
+        copy_planes(&mut maps.source, source.planes());
+        copy_planes(&mut maps.distorted, distorted.planes());
+        maps.has_xyb = self.request.xyb;
+    }
+
+    fn moments(&mut self, scale: usize, moments: &ScaleMoments<'_>) {
+        if !self.request.wants_scale(scale) || !self.request.error_maps {
+            return;
+        }
+        let maps = self.buffers.scale_mut(scale);
+        fill_error_maps(
+            moments,
+            &maps.source,
+            &maps.distorted,
+            &mut maps.ssim,
+            &mut maps.artifact,
+            &mut maps.detail_lost,
+        );
+        maps.has_error_maps = true;
+    }
+}
+
+/// Computes the SSIMULACRA2 score and writes the maps selected by `request`
+/// into `buffers`.
+///
+/// The score is identical to [`compute_ssimulacra2_with_config`](crate::compute_ssimulacra2_with_config).
+/// Read the maps back with [`MapBuffers::error_maps`] and [`MapBuffers::xyb`].
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the image is smaller than 8x8 pixels
+pub fn compute_ssimulacra2_maps<S, D>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+    request: MapRequest,
+    buffers: &mut MapBuffers,
+) -> Result<f64, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    buffers.reset();
+    let mut collector = MapCollector { request, buffers };
+    compute_ssimulacra2_with_taps(source, distorted, config, &mut collector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_ssimulacra2_with_config;
+    use crate::test_util::test_image;
+
+    /// Copies the error maps handed out by the pipeline's own tap point
+    #[derive(Default)]
+    struct CopyMaps(Vec<[[Vec<f32>; 3]; 3]>);
+
+    impl PipelineTap for CopyMaps {
+        fn error_maps(&mut self, _scale: usize, maps: &ErrorMaps<'_>) {
+            self.0.push([
+                maps.ssim.clone(),
+                maps.artifact.clone(),
+                maps.detail_lost.clone(),
+            ]);
+        }
+
+        fn wants_error_maps(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_maps_match_tap_error_maps() {
+        let (source, distorted) = (test_image(64, 48, 1), test_image(64, 48, 2));
+        let config = Ssimulacra2Config::scalar();
+
+        let mut expected = CopyMaps::default();
+        compute_ssimulacra2_with_taps(&source, &distorted, config.clone(), &mut expected).unwrap();
+
+        let mut buffers = MapBuffers::new();
+        let score = compute_ssimulacra2_maps(
+            &source,
+            &distorted,
+            config.clone(),
+            MapRequest::error_maps(),
+            &mut buffers,
+        )
+        .unwrap();
+        assert_eq!(
+            score,
+            compute_ssimulacra2_with_config(&source, &distorted, config).unwrap()
+        );
+
+        assert_eq!(expected.0.len(), 4);
+        for (scale, [ssim, artifact, detail_lost]) in expected.0.iter().enumerate() {
+            let maps = buffers.error_maps(scale).unwrap();
+            assert_eq!(maps.ssim, ssim);
+            assert_eq!(maps.artifact, artifact);
+            assert_eq!(maps.detail_lost, detail_lost);
+        }
+        assert!(buffers.error_maps(4).is_none());
+        assert!(buffers.xyb(0).is_none());
+    }
+
+    #[test]
+    fn test_request_selects_scales_and_maps() {
+        let (source, distorted) = (test_image(64, 48, 3), test_image(64, 48, 4));
+        let mut buffers = MapBuffers::new();
+        let request = MapRequest::xyb().with_scales(1..3);
+        compute_ssimulacra2_maps(
+            &source,
+            &distorted,
+            Default::default(),
+            request,
+            &mut buffers,
+        )
+        .unwrap();
+
+        assert!(buffers.xyb(0).is_none());
+        let xyb = buffers.xyb(2).unwrap();
+        assert_eq!((xyb.width, xyb.height), (16, 12));
+        assert_eq!(xyb.source[0].len(), 16 * 12);
+        assert!(buffers.error_maps(1).is_none());
+
+        // A later request without XYB hides the planes from the earlier one
+        compute_ssimulacra2_maps(
+            &source,
+            &distorted,
+            Default::default(),
+            MapRequest::error_maps().with_scales(0..1),
+            &mut buffers,
+        )
+        .unwrap();
+        assert!(buffers.xyb(2).is_none());
+        assert!(buffers.error_maps(0).is_some());
+        assert!(buffers.error_maps(1).is_none());
+    }
+
+    #[test]
+    fn test_repeated_calls_reuse_planes() {
+        let mut buffers = MapBuffers::new();
+        let request = MapRequest::error_maps().with_xyb(true);
+        let mut run = |seed| {
+            let (source, distorted) = (test_image(64, 48, seed), test_image(64, 48, seed + 1));
+            compute_ssimulacra2_maps(
+                &source,
+                &distorted,
+                Default::default(),
+                request,
+                &mut buffers,
+            )
+            .unwrap();
+            let maps = buffers.error_maps(0).unwrap();
+            let xyb = buffers.xyb(0).unwrap();
+            [
+                maps.ssim[0].as_ptr(),
+                maps.artifact[2].as_ptr(),
+                maps.detail_lost[1].as_ptr(),
+                xyb.source[0].as_ptr(),
+                xyb.distorted[2].as_ptr(),
+            ]
+        };
+        let first = run(5);
+        assert_eq!(run(7), first);
+        assert_eq!(run(9), first);
+    }
+}
@@ -0,0 +1,370 @@
+//! Companion metrics computed from the same converted inputs.
+//!
+//! Pipelines that report SSIMULACRA2 often log a second, better known metric
+//! next to it as a sanity check. [`compute_metrics`] converts both images to
+//! linear RGB once and computes every requested metric from that, instead of
+//! decoding and converting again for another tool.
+//!
+//! PSNR and MS-SSIM are defined on display-encoded values, so they are
+//! computed on the linear pixels re-encoded with the sRGB curve and clamped
+//! to 0-1, as an 8-bit tool would see them up to quantization:
+//!
+//! - PSNR is `10 * log10(1 / MSE)` over all three channels, in dB, and
+//!   infinite for identical images.
+//! - MS-SSIM follows Wang, Simoncelli and Bovik (2003) on BT.709 luma: an
+//!   11x11 Gaussian window with a sigma of 1.5 over the pixels it fully
+//!   covers, 2x2 mean downsampling, and five scales weighted 0.0448, 0.2856,
+//!   0.3001, 0.2363 and 0.1333. Images too small for five 11x11 scales use
+//!   as many as fit, with the weights renormalized, and negative contrast
+//!   terms count as 0, as in most implementations.
+
+use crate::input::{LinearRgbImage, ToLinearRgb};
+use crate::transfer::reconcile_colorimetry;
+use crate::{compute_ssimulacra2_with_config, Ssimulacra2Config, Ssimulacra2Error};
+
+/// MS-SSIM weight of each scale, finest first
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+/// Side of the MS-SSIM Gaussian window
+const WINDOW: usize = 11;
+
+/// Which metrics [`compute_metrics`] computes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricsRequest {
+    /// SSIMULACRA2 score, with the given config
+    pub ssimulacra2: bool,
+    /// PSNR of the sRGB-encoded pixels
+    pub psnr: bool,
+    /// MS-SSIM of the sRGB-encoded luma
+    pub ms_ssim: bool,
+}
+
+impl MetricsRequest {
+    /// Every metric
+    pub fn all() -> Self {
+        Self {
+            ssimulacra2: true,
+            psnr: true,
+            ms_ssim: true,
+        }
+    }
+}
+
+impl Default for MetricsRequest {
+    /// SSIMULACRA2 only
+    fn default() -> Self {
+        Self {
+            ssimulacra2: true,
+            psnr: false,
+            ms_ssim: false,
+        }
+    }
+}
+
+/// Results of [`compute_metrics`]; metrics that weren't requested are
+/// `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Metrics {
+    /// SSIMULACRA2 score
+    pub ssimulacra2: Option<f64>,
+    /// PSNR in dB, infinite for identical images
+    pub psnr: Option<f64>,
+    /// MS-SSIM, 1 for identical images
+    pub ms_ssim: Option<f64>,
+}
+
+/// Computes the requested metrics of `distorted` against `source`, converting
+/// both to linear RGB once. `config` applies to the SSIMULACRA2 score only.
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If SSIMULACRA2 is requested and the images are smaller than 8x8 pixels
+/// - If MS-SSIM is requested and the images are smaller than 11x11 pixels
+pub fn compute_metrics<S, D>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+    request: MetricsRequest,
+) -> Result<Metrics, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    let mut source = source.as_linear_rgb();
+    let mut distorted = distorted.as_linear_rgb();
+    if source.width() != distorted.width() || source.height() != distorted.height() {
+        return Err(Ssimulacra2Error::NonMatchingImageDimensions);
+    }
+    reconcile_colorimetry(&mut source, &mut distorted)?;
+    if request.ms_ssim && (source.width() < WINDOW || source.height() < WINDOW) {
+        return Err(Ssimulacra2Error::InvalidImageSize);
+    }
+
+    let ssimulacra2 = request
+        .ssimulacra2
+        .then(|| compute_ssimulacra2_with_config(&*source, &*distorted, config))
+        .transpose()?;
+    let (psnr, ms_ssim) = if request.psnr || request.ms_ssim {
+        let (encoded1, encoded2) = (encode(&source), encode(&distorted));
+        let (width, height) = (source.width(), source.height());
+        (
+            request.psnr.then(|| psnr(&encoded1, &encoded2)),
+            request.ms_ssim.then(|| {
+                ms_ssim(
+                    luma(&encoded1, width, height),
+                    luma(&encoded2, width, height),
+                )
+            }),
+        )
+    } else {
+        (None, None)
+    };
+    Ok(Metrics {
+        ssimulacra2,
+        psnr,
+        ms_ssim,
+    })
+}
+
+/// The pixels encoded with the sRGB curve, clamped to 0-1
+fn encode(image: &LinearRgbImage) -> Vec<[f64; 3]> {
+    let srgb = |v: f32| {
+        let v = f64::from(v).clamp(0.0, 1.0);
+        if v <= 0.003_130_8 {
+            v * 12.92
+        } else {
+            1.055 * v.powf(1.0 / 2.4) - 0.055
+        }
+    };
+    image.data().iter().map(|pix| pix.map(srgb)).collect()
+}
+
+fn psnr(source: &[[f64; 3]], distorted: &[[f64; 3]]) -> f64 {
+    let squared: f64 = source
+        .iter()
+        .flatten()
+        .zip(distorted.iter().flatten())
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum();
+    let mse = squared / (source.len() * 3) as f64;
+    -10.0 * mse.log10()
+}
+
+/// One plane of f64 samples
+struct Plane {
+    data: Vec<f64>,
+    width: usize,
+    height: usize,
+}
+
+impl Plane {
+    /// Mean of each 2x2 block, dropping an odd last row or column
+    fn downsample(&self) -> Self {
+        let (width, height) = (self.width / 2, self.height / 2);
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (row0, row1) = (2 * y * self.width, (2 * y + 1) * self.width);
+            for x in 0..width {
+                let sum = self.data[row0 + 2 * x]
+                    + self.data[row0 + 2 * x + 1]
+                    + self.data[row1 + 2 * x]
+                    + self.data[row1 + 2 * x + 1];
+                data.push(sum * 0.25);
+            }
+        }
+        Self {
+            data,
+            width,
+            height,
+        }
+    }
+
+    /// Samples multiplied pairwise with `other`'s
+    fn product(&self, other: &Plane) -> Vec<f64> {
+        self.data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| a * b)
+            .collect()
+    }
+
+    /// `data` (of this plane's size) filtered with the Gaussian window where
+    /// it fits entirely
+    fn window_means(&self, data: &[f64], kernel: &[f64; WINDOW]) -> Vec<f64> {
+        let out_width = self.width + 1 - WINDOW;
+        let out_height = self.height + 1 - WINDOW;
+        let mut horizontal = Vec::with_capacity(out_width * self.height);
+        for row in data.chunks_exact(self.width) {
+            horizontal.extend(
+                row.windows(WINDOW)
+                    .map(|w| w.iter().zip(kernel).map(|(v, k)| v * k).sum::<f64>()),
+            );
+        }
+        let mut out = Vec::with_capacity(out_width * out_height);
+        for y in 0..out_height {
+            for x in 0..out_width {
+                out.push(
+                    kernel
+                        .iter()
+                        .enumerate()
+                        .map(|(i, k)| horizontal[(y + i) * out_width + x] * k)
+                        .sum(),
+                );
+            }
+        }
+        out
+    }
+}
+
+/// BT.709 luma of encoded pixels
+fn luma(pixels: &[[f64; 3]], width: usize, height: usize) -> Plane {
+    Plane {
+        data: pixels
+            .iter()
+            .map(|&[r, g, b]| 0.2126 * r + 0.7152 * g + 0.0722 * b)
+            .collect(),
+        width,
+        height,
+    }
+}
+
+/// Normalized Gaussian window with a sigma of 1.5
+fn gaussian_kernel() -> [f64; WINDOW] {
+    let center = (WINDOW / 2) as f64;
+    let mut kernel: [f64; WINDOW] =
+        std::array::from_fn(|i| (-(i as f64 - center).powi(2) / (2.0 * 1.5 * 1.5)).exp());
+    let sum: f64 = kernel.iter().sum();
+    for k in &mut kernel {
+        *k /= sum;
+    }
+    kernel
+}
+
+/// Mean luminance and contrast-structure terms of SSIM over every window
+fn ssim_terms(a: &Plane, b: &Plane, kernel: &[f64; WINDOW]) -> (f64, f64) {
+    const C1: f64 = 0.01 * 0.01;
+    const C2: f64 = 0.03 * 0.03;
+    let mu1 = a.window_means(&a.data, kernel);
+    let mu2 = a.window_means(&b.data, kernel);
+    let s11 = a.window_means(&a.product(a), kernel);
+    let s22 = a.window_means(&b.product(b), kernel);
+    let s12 = a.window_means(&a.product(b), kernel);
+
+    let (mut luminance, mut contrast) = (0.0, 0.0);
+    for i in 0..mu1.len() {
+        let (m1, m2) = (mu1[i], mu2[i]);
+        let (v1, v2, cov) = (s11[i] - m1 * m1, s22[i] - m2 * m2, s12[i] - m1 * m2);
+        luminance += (2.0 * m1 * m2 + C1) / (m1 * m1 + m2 * m2 + C1);
+        contrast += (2.0 * cov + C2) / (v1 + v2 + C2);
+    }
+    let n = mu1.len() as f64;
+    (luminance / n, contrast / n)
+}
+
+/// MS-SSIM of two planes of the same size, at least the window
+fn ms_ssim(mut a: Plane, mut b: Plane) -> f64 {
+    let kernel = gaussian_kernel();
+    let mut terms = Vec::with_capacity(MS_SSIM_WEIGHTS.len());
+    loop {
+        terms.push(ssim_terms(&a, &b, &kernel));
+        if terms.len() == MS_SSIM_WEIGHTS.len() || a.width / 2 < WINDOW || a.height / 2 < WINDOW {
+            break;
+        }
+        a = a.downsample();
+        b = b.downsample();
+    }
+
+    let weights = &MS_SSIM_WEIGHTS[..terms.len()];
+    let total: f64 = weights.iter().sum();
+    // Luminance only counts at the coarsest scale, contrast at every scale
+    let (luminance, _) = terms[terms.len() - 1];
+    let mut result = luminance.max(0.0).powf(weights[terms.len() - 1] / total);
+    for (&(_, contrast), weight) in terms.iter().zip(weights) {
+        result *= contrast.max(0.0).powf(weight / total);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pattern;
+
+    #[test]
+    fn test_identical_images() {
+        let image = pattern(200, 180, 0.0);
+        let metrics = compute_metrics(&image, &image, Default::default(), MetricsRequest::all());
+        assert_eq!(
+            metrics,
+            Ok(Metrics {
+                ssimulacra2: Some(100.0),
+                psnr: Some(f64::INFINITY),
+                ms_ssim: Some(1.0),
+            })
+        );
+    }
+
+    #[test]
+    fn test_metrics_agree_on_ordering() {
+        let source = pattern(200, 180, 0.0);
+        let config = Ssimulacra2Config::default();
+        let metrics = |phase| {
+            compute_metrics(
+                &source,
+                pattern(200, 180, phase),
+                config.clone(),
+                MetricsRequest::all(),
+            )
+            .unwrap()
+        };
+        let (slight, strong) = (metrics(0.05), metrics(0.5));
+        assert_eq!(
+            slight.ssimulacra2,
+            Some(
+                compute_ssimulacra2_with_config(&source, pattern(200, 180, 0.05), config.clone())
+                    .unwrap()
+            )
+        );
+        assert!(slight.psnr.unwrap() > strong.psnr.unwrap());
+        assert!(slight.ms_ssim.unwrap() > strong.ms_ssim.unwrap());
+        assert!(strong.ms_ssim.unwrap() > 0.0 && slight.ms_ssim.unwrap() < 1.0);
+
+        // Too small to halve even once, so MS-SSIM has a single scale
+        let small = compute_metrics(
+            pattern(12, 12, 0.0),
+            pattern(12, 12, 0.3),
+            config.clone(),
+            MetricsRequest::all(),
+        )
+        .unwrap();
+        assert!(small.ms_ssim.unwrap() < 1.0);
+
+        let psnr_only = MetricsRequest {
+            ssimulacra2: false,
+            psnr: true,
+            ms_ssim: false,
+        };
+        let only =
+            compute_metrics(&source, pattern(200, 180, 0.5), config.clone(), psnr_only).unwrap();
+        assert_eq!((only.ssimulacra2, only.psnr), (None, strong.psnr));
+        assert_eq!(
+            compute_metrics(
+                pattern(10, 10, 0.0),
+                pattern(10, 10, 0.0),
+                config.clone(),
+                psnr_only
+            )
+            .map(|m| m.psnr),
+            Ok(Some(f64::INFINITY))
+        );
+        assert_eq!(
+            compute_metrics(
+                pattern(10, 10, 0.0),
+                pattern(10, 10, 0.0),
+                config,
+                MetricsRequest::all()
+            ),
+            Err(Ssimulacra2Error::InvalidImageSize)
+        );
+    }
+}
@@ -0,0 +1,409 @@
+//! Rolling score distributions per labeled stream (requires the `monitor`
+//! feature).
+//!
+//! Services that score every encode usually want "quality over the last hour"
+//! per encoder profile, not individual scores. A [`ScoreMonitor`] keeps that
+//! aggregation in one place: scores are recorded under a label, either
+//! explicitly with [`ScoreMonitor::record`] or automatically for every
+//! comparison whose config sets
+//! [`monitor_label`](crate::Ssimulacra2Config::monitor_label), and can be
+//! queried with [`ScoreMonitor::snapshot`] or exported with
+//! [`ScoreMonitor::to_json`].
+//!
+//! Each stream keeps one histogram per time bucket (a sixtieth of the window)
+//! with one-point score bins, so memory per stream is bounded no matter how
+//! many scores are recorded, and percentiles are accurate to about half a
+//! point. Buckets older than the window are dropped as new scores arrive.
+//!
+//! Uses `std::time::Instant`, which is unavailable on
+//! `wasm32-unknown-unknown`.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Window of [`ScoreMonitor::global`]
+pub const DEFAULT_MONITOR_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Time buckets per window
+const BUCKETS: u64 = 60;
+
+/// Bin 0 holds negative scores, bin `k` scores in `[k - 1, k)`, and the last
+/// bin scores of 100 (identical images).
+const BINS: usize = 102;
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    epoch: u64,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    bins: [u32; BINS],
+}
+
+impl Bucket {
+    fn new(epoch: u64) -> Self {
+        Self {
+            epoch,
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            bins: [0; BINS],
+        }
+    }
+
+    fn push(&mut self, score: f64) {
+        self.count += 1;
+        self.sum += score;
+        self.min = self.min.min(score);
+        self.max = self.max.max(score);
+        let bin = (score.floor() + 1.0).clamp(0.0, (BINS - 1) as f64) as usize;
+        self.bins[bin] = self.bins[bin].saturating_add(1);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Stream {
+    /// Non-empty buckets, oldest first
+    buckets: VecDeque<Bucket>,
+}
+
+impl Stream {
+    fn expire(&mut self, epoch: u64) {
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| bucket.epoch + BUCKETS <= epoch)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn snapshot(&self, epoch: u64) -> Option<StreamSnapshot> {
+        let live = self
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.epoch + BUCKETS > epoch);
+        let mut merged = Bucket::new(epoch);
+        for bucket in live {
+            merged.count += bucket.count;
+            merged.sum += bucket.sum;
+            merged.min = merged.min.min(bucket.min);
+            merged.max = merged.max.max(bucket.max);
+            for (total, &n) in merged.bins.iter_mut().zip(&bucket.bins) {
+                *total = total.saturating_add(n);
+            }
+        }
+        if merged.count == 0 {
+            return None;
+        }
+
+        let quantile = |q: f64| {
+            let rank = (q * merged.count as f64).ceil().max(1.0) as u64;
+            let mut seen = 0u64;
+            for (bin, &n) in merged.bins.iter().enumerate() {
+                seen += u64::from(n);
+                if seen >= rank {
+                    let estimate = match bin {
+                        0 => merged.min,
+                        b if b == BINS - 1 => 100.0,
+                        b => b as f64 - 0.5,
+                    };
+                    return estimate.clamp(merged.min, merged.max);
+                }
+            }
+            merged.max
+        };
+
+        Some(StreamSnapshot {
+            count: merged.count,
+            mean: merged.sum / merged.count as f64,
+            min: merged.min,
+            max: merged.max,
+            p5: quantile(0.05),
+            p50: quantile(0.5),
+            p95: quantile(0.95),
+        })
+    }
+}
+
+/// Summary of one stream over the monitor's window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamSnapshot {
+    /// Number of scores in the window
+    pub count: u64,
+    /// Mean score
+    pub mean: f64,
+    /// Lowest score
+    pub min: f64,
+    /// Highest score
+    pub max: f64,
+    /// 5th percentile (the bad tail), to about half a point
+    pub p5: f64,
+    /// Median, to about half a point
+    pub p50: f64,
+    /// 95th percentile, to about half a point
+    pub p95: f64,
+}
+
+/// Thread-safe rolling score distributions per label.
+#[derive(Debug)]
+pub struct ScoreMonitor {
+    window: Duration,
+    started: Instant,
+    streams: Mutex<HashMap<String, Stream>>,
+}
+
+impl ScoreMonitor {
+    /// Creates a monitor summarizing the last `window` of scores.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window: window.max(Duration::from_millis(BUCKETS)),
+            started: Instant::now(),
+            streams: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The process-wide monitor, with a one-hour window. Comparisons with
+    /// [`monitor_label`](crate::Ssimulacra2Config::monitor_label) set record
+    /// here.
+    pub fn global() -> &'static ScoreMonitor {
+        static GLOBAL: OnceLock<ScoreMonitor> = OnceLock::new();
+        GLOBAL.get_or_init(|| ScoreMonitor::new(DEFAULT_MONITOR_WINDOW))
+    }
+
+    /// The window snapshots cover
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records `score` under `label`. Non-finite scores are ignored.
+    pub fn record(&self, label: &str, score: f64) {
+        self.record_at(label, score, Instant::now());
+    }
+
+    /// Summary of `label` over the window, or `None` if it has no scores in
+    /// the window.
+    pub fn snapshot(&self, label: &str) -> Option<StreamSnapshot> {
+        self.snapshot_at(label, Instant::now())
+    }
+
+    /// Summaries of all labels with scores in the window, sorted by label.
+    pub fn snapshots(&self) -> Vec<(String, StreamSnapshot)> {
+        self.snapshots_at(Instant::now())
+    }
+
+    /// Forgets all recorded scores.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Exports [`snapshots`](Self::snapshots) as a JSON object:
+    ///
+    /// ```json
+    /// {"window_seconds":3600,"streams":{"avif-q50":{"count":120,"mean":71.2,
+    ///   "min":38.5,"max":88.1,"p5":52.5,"p50":72.5,"p95":84.5}}}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = format!(
+            "{{\"window_seconds\":{},\"streams\":{{",
+            self.window.as_secs_f64()
+        );
+        for (i, (label, s)) in self.snapshots().iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write_json_string(&mut json, label);
+            let _ = write!(json, ":{{\"count\":{}", s.count);
+            for (name, value) in [
+                ("mean", s.mean),
+                ("min", s.min),
+                ("max", s.max),
+                ("p5", s.p5),
+                ("p50", s.p50),
+                ("p95", s.p95),
+            ] {
+                let _ = write!(json, ",\"{}\":{}", name, value);
+            }
+            json.push('}');
+        }
+        json.push_str("}}");
+        json
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Stream>> {
+        // A panic while holding the lock can't leave a stream inconsistent
+        // enough to matter for monitoring
+        self.streams.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn epoch(&self, now: Instant) -> u64 {
+        let bucket = self.window.as_nanos() / u128::from(BUCKETS);
+        (now.saturating_duration_since(self.started).as_nanos() / bucket.max(1)) as u64
+    }
+
+    fn record_at(&self, label: &str, score: f64, now: Instant) {
+        if !score.is_finite() {
+            return;
+        }
+        let epoch = self.epoch(now);
+        let mut streams = self.lock();
+        let stream = match streams.get_mut(label) {
+            Some(stream) => stream,
+            None => streams.entry(label.to_owned()).or_default(),
+        };
+        stream.expire(epoch);
+        match stream.buckets.back_mut() {
+            Some(bucket) if bucket.epoch == epoch => bucket.push(score),
+            _ => {
+                let mut bucket = Bucket::new(epoch);
+                bucket.push(score);
+                stream.buckets.push_back(bucket);
+            }
+        }
+    }
+
+    fn snapshot_at(&self, label: &str, now: Instant) -> Option<StreamSnapshot> {
+        let epoch = self.epoch(now);
+        self.lock().get(label)?.snapshot(epoch)
+    }
+
+    fn snapshots_at(&self, now: Instant) -> Vec<(String, StreamSnapshot)> {
+        let epoch = self.epoch(now);
+        let mut snapshots = self
+            .lock()
+            .iter()
+            .filter_map(|(label, stream)| Some((label.clone(), stream.snapshot(epoch)?)))
+            .collect::<Vec<_>>();
+        snapshots.sort_by(|a, b| a.0.cmp(&b.0));
+        snapshots
+    }
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_ssimulacra2_with_config, LinearRgbImage, Ssimulacra2Config};
+
+    #[test]
+    fn test_snapshot_statistics() {
+        let monitor = ScoreMonitor::new(Duration::from_secs(60));
+        for i in 0..100 {
+            monitor.record("avif", f64::from(i));
+        }
+        monitor.record("avif", f64::NAN);
+        monitor.record("jpeg", -12.0);
+
+        let avif = monitor.snapshot("avif").unwrap();
+        assert_eq!(avif.count, 100);
+        assert_eq!((avif.min, avif.max), (0.0, 99.0));
+        assert!((avif.mean - 49.5).abs() < 1e-9);
+        assert!((avif.p50 - 49.5).abs() <= 1.0, "{:?}", avif);
+        assert!((avif.p5 - 4.5).abs() <= 1.0, "{:?}", avif);
+        assert!((avif.p95 - 94.5).abs() <= 1.0, "{:?}", avif);
+
+        // Negative scores are reported by their actual value
+        assert_eq!(monitor.snapshot("jpeg").unwrap().p50, -12.0);
+        assert!(monitor.snapshot("webp").is_none());
+    }
+
+    #[test]
+    fn test_old_scores_expire() {
+        let monitor = ScoreMonitor::new(Duration::from_secs(60));
+        let start = monitor.started;
+        monitor.record_at("x", 10.0, start);
+        monitor.record_at("x", 90.0, start + Duration::from_secs(30));
+
+        let both = monitor
+            .snapshot_at("x", start + Duration::from_secs(59))
+            .unwrap();
+        assert_eq!(both.count, 2);
+
+        let recent = monitor
+            .snapshot_at("x", start + Duration::from_secs(61))
+            .unwrap();
+        assert_eq!((recent.count, recent.min), (1, 90.0));
+        assert!(monitor
+            .snapshot_at("x", start + Duration::from_secs(200))
+            .is_none());
+
+        // Recording after a gap drops the stale buckets
+        monitor.record_at("x", 50.0, start + Duration::from_secs(300));
+        assert_eq!(monitor.lock()["x"].buckets.len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_recording() {
+        let monitor = ScoreMonitor::new(Duration::from_secs(60));
+        std::thread::scope(|scope| {
+            for t in 0..4 {
+                let monitor = &monitor;
+                scope.spawn(move || {
+                    for i in 0..1000 {
+                        monitor.record(if t % 2 == 0 { "a" } else { "b" }, f64::from(i % 100));
+                    }
+                });
+            }
+        });
+        let snapshots = monitor.snapshots();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].0, "a");
+        assert!(snapshots.iter().all(|(_, s)| s.count == 2000));
+    }
+
+    #[test]
+    fn test_json_export() {
+        let monitor = ScoreMonitor::new(Duration::from_secs(3600));
+        assert_eq!(
+            monitor.to_json(),
+            "{\"window_seconds\":3600,\"streams\":{}}"
+        );
+        monitor.record("q\"50\"\n", 80.0);
+        assert_eq!(
+            monitor.to_json(),
+            "{\"window_seconds\":3600,\"streams\":{\"q\\\"50\\\"\\n\":{\"count\":1,\
+             \"mean\":80,\"min\":80,\"max\":80,\"p5\":80,\"p50\":80,\"p95\":80}}}"
+        );
+    }
+
+    #[test]
+    fn test_config_label_records_globally() {
+        let data = (0..32 * 32)
+            .map(|i| [(i % 7) as f32 / 7.0, 0.5, (i % 3) as f32 / 3.0])
+            .collect::<Vec<_>>();
+        let image = LinearRgbImage::new(data, 32, 32);
+        let label = "monitor-test-config-label";
+
+        let config = Ssimulacra2Config::default().with_monitor_label(label);
+        let score = compute_ssimulacra2_with_config(&image, &image, config).unwrap();
+        let snapshot = ScoreMonitor::global().snapshot(label).unwrap();
+        assert_eq!((snapshot.count, snapshot.max), (1, score));
+
+        // Labels can also be built at runtime
+        let label = format!("{}-q{}", label, 90);
+        let config = Ssimulacra2Config::default().with_monitor_label(label.clone());
+        compute_ssimulacra2_with_config(&image, &image, config).unwrap();
+        assert_eq!(ScoreMonitor::global().snapshot(&label).unwrap().count, 1);
+    }
+}
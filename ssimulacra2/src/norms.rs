@@ -0,0 +1,201 @@
+//! Exponents of the norms that pool each error map.
+//!
+//! SSIMULACRA2 summarizes every error map with its 1-norm (the mean error) and
+//! its 4-norm, which emphasizes the worst regions. [`NormExponents`] swaps in
+//! other exponents for research into outlier sensitivity. The pooling kernels
+//! are generic over [`Norms`], so the standard pair compiles to exactly the
+//! original arithmetic and other pairs reuse the same SIMD error computation.
+
+/// The pair of norm exponents used to pool the error maps (experimental).
+///
+/// The score weights were fit for the standard 1-norm and 4-norm, so scores
+/// with any other exponents are not SSIMULACRA2 scores and aren't comparable
+/// with it, only with each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormExponents {
+    low: f64,
+    high: f64,
+}
+
+impl NormExponents {
+    /// The metric's own exponents, 1 and 4
+    pub const STANDARD: Self = Self {
+        low: 1.0,
+        high: 4.0,
+    };
+
+    /// Pools with the `low`-norm in place of the 1-norm and the `high`-norm
+    /// in place of the 4-norm.
+    ///
+    /// # Panics
+    /// If either exponent isn't finite and positive
+    pub fn new(low: f64, high: f64) -> Self {
+        assert!(
+            [low, high].iter().all(|p| p.is_finite() && *p > 0.0),
+            "norm exponents must be finite and positive, got {} and {}",
+            low,
+            high
+        );
+        Self { low, high }
+    }
+
+    /// The exponent replacing the 1-norm
+    pub fn low(&self) -> f64 {
+        self.low
+    }
+
+    /// The exponent replacing the 4-norm
+    pub fn high(&self) -> f64 {
+        self.high
+    }
+}
+
+impl Default for NormExponents {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+/// Accumulation of a norm pair: the pooling kernels sum `low_power(d)` and
+/// `high_power(d)` over an error map and take the roots of the means.
+pub(crate) trait Norms: Copy {
+    fn low_power(self, d: f64) -> f64;
+    fn high_power(self, d: f64) -> f64;
+    fn low_root(self, mean: f64) -> f64;
+    fn high_root(self, mean: f64) -> f64;
+}
+
+/// The 1- and 4-norms, written out so the compiler emits the original code
+#[derive(Clone, Copy)]
+pub(crate) struct StandardNorms;
+
+impl Norms for StandardNorms {
+    #[inline(always)]
+    fn low_power(self, d: f64) -> f64 {
+        d
+    }
+
+    #[inline(always)]
+    fn high_power(self, d: f64) -> f64 {
+        d.powi(4)
+    }
+
+    #[inline(always)]
+    fn low_root(self, mean: f64) -> f64 {
+        mean
+    }
+
+    #[inline(always)]
+    fn high_root(self, mean: f64) -> f64 {
+        mean.sqrt().sqrt()
+    }
+}
+
+impl Norms for NormExponents {
+    #[inline(always)]
+    fn low_power(self, d: f64) -> f64 {
+        d.powf(self.low)
+    }
+
+    #[inline(always)]
+    fn high_power(self, d: f64) -> f64 {
+        d.powf(self.high)
+    }
+
+    #[inline(always)]
+    fn low_root(self, mean: f64) -> f64 {
+        mean.powf(self.low.recip())
+    }
+
+    #[inline(always)]
+    fn high_root(self, mean: f64) -> f64 {
+        mean.powf(self.high.recip())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_image;
+    use crate::{
+        compute_ssimulacra2_detailed, compute_ssimulacra2_with_config, SimdImpl, Ssimulacra2Config,
+    };
+
+    #[test]
+    fn test_standard_exponents_are_default() {
+        assert_eq!(NormExponents::new(1.0, 4.0), NormExponents::STANDARD);
+        assert_eq!(
+            Ssimulacra2Config::default().norm_exponents,
+            NormExponents::STANDARD
+        );
+    }
+
+    #[test]
+    fn test_generic_kernels_match_standard_pair() {
+        // Runs the generic-exponent path with exponents just off the standard
+        // pair, so it must land next to the standard score
+        let (source, distorted) = (test_image(40, 36, 1), test_image(40, 36, 2));
+        let standard =
+            compute_ssimulacra2_with_config(&source, &distorted, Default::default()).unwrap();
+        let config = Ssimulacra2Config::default()
+            .with_norm_exponents(NormExponents::new(1.0 + 1e-12, 4.0 + 1e-12));
+        let generic = compute_ssimulacra2_with_config(&source, &distorted, config).unwrap();
+        assert!(
+            (generic - standard).abs() < 1e-6,
+            "{} vs {}",
+            generic,
+            standard
+        );
+    }
+
+    #[test]
+    fn test_other_exponents_agree_across_backends() {
+        let (source, distorted) = (test_image(40, 36, 1), test_image(40, 36, 2));
+        let exponents = NormExponents::new(2.0, 8.0);
+        #[allow(unused_mut)]
+        let mut backends = vec![SimdImpl::Scalar, SimdImpl::Simd];
+        // Falls back to the portable SIMD kernels for other exponents
+        #[cfg(feature = "unsafe-simd")]
+        backends.push(SimdImpl::UnsafeSimd);
+        let scores: Vec<f64> = backends
+            .into_iter()
+            .map(|backend| {
+                let config = Ssimulacra2Config::new(backend).with_norm_exponents(exponents);
+                compute_ssimulacra2_with_config(&source, &distorted, config).unwrap()
+            })
+            .collect();
+        for score in &scores {
+            assert!((score - scores[0]).abs() < 1e-3, "{:?}", scores);
+        }
+
+        let standard = compute_ssimulacra2_detailed(&source, &distorted, Default::default())
+            .unwrap()
+            .scales[0];
+        let config = Ssimulacra2Config::default().with_norm_exponents(exponents);
+        let squared = compute_ssimulacra2_detailed(&source, &distorted, config.clone())
+            .unwrap()
+            .scales[0];
+        // A 2-norm sits between the 1-norm and the 4-norm, an 8-norm above both
+        for c in 0..3 {
+            let (l1, l4) = (standard.avg_ssim[c * 2], standard.avg_ssim[c * 2 + 1]);
+            let (l2, l8) = (squared.avg_ssim[c * 2], squared.avg_ssim[c * 2 + 1]);
+            assert!(
+                l1 <= l2 && l2 <= l4 && l4 <= l8,
+                "{} {} {} {}",
+                l1,
+                l2,
+                l4,
+                l8
+            );
+        }
+
+        let identical = compute_ssimulacra2_with_config(&source, &source, config).unwrap();
+        assert_eq!(identical, 100.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "finite and positive")]
+    fn test_rejects_non_positive_exponents() {
+        let _ = NormExponents::new(0.0, 4.0);
+    }
+}
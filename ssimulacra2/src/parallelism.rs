@@ -0,0 +1,472 @@
+//! Per-call control over multithreading.
+//!
+//! With the `rayon` feature, the blur runs its three planes on separate
+//! tasks and its horizontal pass runs rows in parallel, and the SIMD XYB
+//! conversion splits the pixels into chunks. That pays off on large frames
+//! but costs more than it saves on thumbnails, so [`Parallelism`] lets each comparison choose. The default,
+//! [`Parallelism::Auto`], decides per scale: the pyramid halves each scale,
+//! so a 4K frame typically runs its first scale in parallel and the rest
+//! sequentially.
+//!
+//! Servers that run their own rayon pools can keep comparisons off them:
+//! [`Parallelism::Threads`] caps a comparison at a number of threads on a
+//! pool shared with other comparisons, and
+//! [`Ssimulacra2Config::with_thread_pool`](crate::Ssimulacra2Config::with_thread_pool)
+//! runs it on a pool the caller built.
+//!
+//! The pooling of the error maps splits each plane into bands of rows that
+//! are summed on separate tasks. The bands and the order their sums are
+//! combined in are fixed, so scores are identical with any thread count.
+
+use std::ops::Range;
+
+/// Smallest plane, in pixels, that [`Parallelism::Auto`] processes in
+/// parallel.
+///
+/// Calibrated with `examples/benchmark_parallelism.rs`: below roughly 1080p
+/// the cost of distributing rows to the pool outweighs the parallel speedup.
+pub const PARALLEL_MIN_PIXELS: usize = 1 << 21;
+
+/// Whether this build can run work on other threads: it needs the `rayon`
+/// feature, and `wasm32` only has threads when built with `atomics`.
+/// Without threads every [`Parallelism`] behaves like
+/// [`Off`](Parallelism::Off).
+pub(crate) const THREADS_AVAILABLE: bool =
+    cfg!(feature = "rayon") && !cfg!(all(target_arch = "wasm32", not(target_feature = "atomics")));
+
+/// How a comparison may use threads.
+///
+/// Only has an effect with the `rayon` feature; without it, and on `wasm32`
+/// builds without `atomics`, everything runs on the calling thread.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Parallelism {
+    /// Run everything on the calling thread
+    Off,
+    /// Use rayon's global thread pool for planes of at least
+    /// [`PARALLEL_MIN_PIXELS`] pixels and run smaller ones sequentially
+    /// (default)
+    #[default]
+    Auto,
+    /// Use a dedicated pool with this many threads. Pools are created on
+    /// first use and shared by all comparisons requesting the same count;
+    /// `Threads(0)` and `Threads(1)` behave like [`Off`](Self::Off).
+    Threads(usize),
+    /// Use a caller's pool, set with
+    /// [`Ssimulacra2Config::with_thread_pool`](crate::Ssimulacra2Config::with_thread_pool)
+    #[cfg(feature = "rayon")]
+    Pool(ThreadPoolHandle),
+}
+
+/// A caller's rayon pool, held for [`Parallelism::Pool`].
+///
+/// The pool lives as long as the configurations holding it. Handles compare
+/// equal when they hold the same pool.
+#[cfg(feature = "rayon")]
+#[derive(Debug, Clone)]
+pub struct ThreadPoolHandle(pub(crate) std::sync::Arc<rayon::ThreadPool>);
+
+#[cfg(feature = "rayon")]
+impl PartialEq for ThreadPoolHandle {
+    fn eq(&self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl Eq for ThreadPoolHandle {}
+
+impl Parallelism {
+    /// Returns true if a plane of `pixels` pixels is processed on more than
+    /// one thread with this setting.
+    pub fn is_parallel_for(&self, pixels: usize) -> bool {
+        THREADS_AVAILABLE
+            && match self {
+                Self::Off => false,
+                Self::Auto => pixels >= PARALLEL_MIN_PIXELS,
+                Self::Threads(n) => *n > 1,
+                #[cfg(feature = "rayon")]
+                Self::Pool(_) => true,
+            }
+    }
+}
+
+/// Applies `f` to each `width`-sized row of `input` and the matching row of
+/// `output`, in parallel if `parallelism` allows it.
+pub(crate) fn for_each_row<F>(
+    parallelism: &Parallelism,
+    input: &[f32],
+    output: &mut [f32],
+    width: usize,
+    f: F,
+) where
+    F: Fn(&[f32], &mut [f32]) + Sync + Send,
+{
+    assert_eq!(input.len(), output.len());
+
+    #[cfg(feature = "rayon")]
+    if parallelism.is_parallel_for(input.len()) {
+        use rayon::prelude::*;
+
+        let run = || {
+            input
+                .par_chunks_exact(width)
+                .zip(output.par_chunks_exact_mut(width))
+                .for_each(|(input, output)| f(input, output));
+        };
+        pools::install(parallelism, run);
+        return;
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = parallelism;
+
+    for (input, output) in input
+        .chunks_exact(width)
+        .zip(output.chunks_exact_mut(width))
+    {
+        f(input, output);
+    }
+}
+
+/// Pixels per work item in [`for_each_chunk`]: large enough to amortize
+/// scheduling, and a multiple of every SIMD batch size so only the last chunk
+/// has a scalar tail.
+#[cfg(feature = "simd")]
+const CHUNK_PIXELS: usize = 1 << 14;
+
+/// Applies `f` to consecutive chunks of `data`, in parallel if `parallelism`
+/// allows it for `data.len()` pixels. `f` must treat each element
+/// independently.
+#[cfg(feature = "simd")]
+pub(crate) fn for_each_chunk<T, F>(parallelism: &Parallelism, data: &mut [T], f: F)
+where
+    T: Send,
+    F: Fn(&mut [T]) + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    if parallelism.is_parallel_for(data.len()) {
+        use rayon::prelude::*;
+
+        let run = || data.par_chunks_mut(CHUNK_PIXELS).for_each(&f);
+        pools::install(parallelism, run);
+        return;
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = (parallelism, CHUNK_PIXELS);
+
+    f(data);
+}
+
+/// Applies `f` to consecutive chunks of interleaved `input` and the
+/// matching chunks of the planes in `out`, in parallel if `parallelism`
+/// allows it for `input.len()` pixels. Chunks break where
+/// [`for_each_chunk`]'s do.
+#[cfg(feature = "simd")]
+pub(crate) fn for_each_planar_chunk<F>(
+    parallelism: &Parallelism,
+    input: &[[f32; 3]],
+    out: [&mut [f32]; 3],
+    f: F,
+) where
+    F: Fn(&[[f32; 3]], [&mut [f32]; 3]) + Sync + Send,
+{
+    debug_assert!(out.iter().all(|plane| plane.len() == input.len()));
+
+    #[cfg(feature = "rayon")]
+    if parallelism.is_parallel_for(input.len()) {
+        use rayon::prelude::*;
+
+        let [out0, out1, out2] = out;
+        let run = || {
+            input
+                .par_chunks(CHUNK_PIXELS)
+                .zip(out0.par_chunks_mut(CHUNK_PIXELS))
+                .zip(out1.par_chunks_mut(CHUNK_PIXELS))
+                .zip(out2.par_chunks_mut(CHUNK_PIXELS))
+                .for_each(|(((input, out0), out1), out2)| f(input, [out0, out1, out2]));
+        };
+        pools::install(parallelism, run);
+        return;
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = parallelism;
+
+    f(input, out);
+}
+
+/// Pixels per band in [`sum_bands`]. The band layout depends only on the
+/// plane's size, never on the thread count, so the sums don't either.
+const BAND_PIXELS: usize = 1 << 16;
+
+/// Rows per band of a `width`-wide plane in [`sum_bands`]
+pub(crate) fn band_rows(width: usize) -> usize {
+    (BAND_PIXELS / width.max(1)).max(1)
+}
+
+/// Sums `f` over consecutive bands of rows of a `width` x `height` plane, in
+/// parallel if `parallelism` allows it for the whole plane.
+///
+/// `f` receives the row range of one band and returns its partial sums. The
+/// partial sums are always added in band order, so the result is the same
+/// with or without threads.
+pub(crate) fn sum_bands<const N: usize, F>(
+    parallelism: &Parallelism,
+    width: usize,
+    height: usize,
+    f: F,
+) -> [f64; N]
+where
+    F: Fn(Range<usize>) -> [f64; N] + Sync + Send,
+{
+    let band_rows = band_rows(width);
+    let bands = height.div_ceil(band_rows);
+    let band = |i: usize| i * band_rows..((i + 1) * band_rows).min(height);
+    let add = |mut total: [f64; N], partial: [f64; N]| {
+        for (t, p) in total.iter_mut().zip(partial) {
+            *t += p;
+        }
+        total
+    };
+
+    #[cfg(feature = "rayon")]
+    if bands > 1 && parallelism.is_parallel_for(width * height) {
+        use rayon::prelude::*;
+
+        let run = || {
+            (0..bands)
+                .into_par_iter()
+                .map(|i| f(band(i)))
+                .collect::<Vec<_>>()
+        };
+        let partials = pools::install(parallelism, run);
+        return partials.into_iter().fold([0.0; N], add);
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = parallelism;
+
+    (0..bands).map(|i| f(band(i))).fold([0.0; N], add)
+}
+
+/// Runs `a`, `b` and `c`, on separate tasks with the `rayon` feature.
+///
+/// Callers decide whether the work is worth splitting; `parallelism` only
+/// selects the pool.
+pub(crate) fn join3<A, B, C>(parallelism: &Parallelism, a: A, b: B, c: C)
+where
+    A: FnOnce() + Send,
+    B: FnOnce() + Send,
+    C: FnOnce() + Send,
+{
+    #[cfg(feature = "rayon")]
+    {
+        let run = || {
+            rayon::join(a, || rayon::join(b, c));
+        };
+        pools::install(parallelism, run);
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        let _ = parallelism;
+        a();
+        b();
+        c();
+    }
+}
+
+#[cfg(feature = "rayon")]
+pub(crate) mod pools {
+    use std::sync::{Arc, Mutex};
+
+    use rayon::{ThreadPool, ThreadPoolBuilder};
+
+    use super::Parallelism;
+
+    static POOLS: Mutex<Vec<(usize, Arc<ThreadPool>)>> = Mutex::new(Vec::new());
+
+    /// Runs `run` on the pool `parallelism` selects, or on the current one.
+    pub(super) fn install<R, F>(parallelism: &Parallelism, run: F) -> R
+    where
+        R: Send,
+        F: FnOnce() -> R + Send,
+    {
+        match parallelism {
+            Parallelism::Threads(n) => get(*n).install(run),
+            Parallelism::Pool(pool) => pool.0.install(run),
+            Parallelism::Off | Parallelism::Auto => run(),
+        }
+    }
+
+    /// Returns the shared pool with `threads` threads, creating it if needed.
+    pub(super) fn get(threads: usize) -> Arc<ThreadPool> {
+        let mut pools = POOLS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, pool)) = pools.iter().find(|(n, _)| *n == threads) {
+            return Arc::clone(pool);
+        }
+        let pool = Arc::new(
+            ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("failed to create thread pool"),
+        );
+        pools.push((threads, Arc::clone(&pool)));
+        pool
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_ssimulacra2_with_config, LinearRgbImage, Ssimulacra2Config};
+
+    #[test]
+    fn test_for_each_row_visits_every_row() {
+        let input: Vec<f32> = (0..6 * 5).map(|i| i as f32).collect();
+        for parallelism in [
+            Parallelism::Off,
+            Parallelism::Auto,
+            Parallelism::Threads(1),
+            Parallelism::Threads(3),
+        ] {
+            let mut output = vec![0.0; input.len()];
+            for_each_row(&parallelism, &input, &mut output, 6, |input, output| {
+                for (o, i) in output.iter_mut().zip(input) {
+                    *o = i * 2.0;
+                }
+            });
+            assert!(output.iter().zip(&input).all(|(o, i)| *o == i * 2.0));
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn test_for_each_chunk_covers_data() {
+        let len = 2 * CHUNK_PIXELS + 37;
+        for parallelism in [Parallelism::Off, Parallelism::Threads(3)] {
+            let mut data: Vec<u32> = (0..len as u32).collect();
+            for_each_chunk(&parallelism, &mut data, |chunk| {
+                for v in chunk {
+                    *v *= 2;
+                }
+            });
+            assert!(data.iter().enumerate().all(|(i, &v)| v == 2 * i as u32));
+        }
+    }
+
+    #[test]
+    fn test_sum_bands_is_deterministic() {
+        let (width, height) = (300, 1000);
+        let data: Vec<f32> = (0..width * height)
+            .map(|i| ((i * 7919) % 1013) as f32 / 1013.0)
+            .collect();
+        let band_sums = |rows: Range<usize>| {
+            let band = &data[rows.start * width..rows.end * width];
+            [
+                band.iter().map(|&v| f64::from(v)).sum::<f64>(),
+                band.len() as f64,
+            ]
+        };
+
+        let sums: Vec<[f64; 2]> = [Parallelism::Off, Parallelism::Threads(3)]
+            .into_iter()
+            .map(|parallelism| sum_bands(&parallelism, width, height, band_sums))
+            .collect();
+        assert_eq!(sums[0], sums[1]);
+        assert_eq!(sums[0][1], (width * height) as f64);
+        let total: f64 = data.iter().map(|&v| f64::from(v)).sum();
+        assert!((sums[0][0] - total).abs() < 1e-6 * total);
+    }
+
+    #[test]
+    fn test_auto_switches_on_plane_size() {
+        let rayon = cfg!(feature = "rayon");
+        assert!(!Parallelism::Auto.is_parallel_for(1920 * 1080));
+        assert_eq!(Parallelism::Auto.is_parallel_for(3840 * 2160), rayon);
+        assert_eq!(Parallelism::Threads(4).is_parallel_for(64), rayon);
+        assert!(!Parallelism::Threads(1).is_parallel_for(3840 * 2160));
+        assert!(!Parallelism::Off.is_parallel_for(3840 * 2160));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_thread_pool_runs_the_work() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(2)
+                .build()
+                .unwrap(),
+        );
+        let config = Ssimulacra2Config::default().with_thread_pool(Arc::clone(&pool));
+        let again = Ssimulacra2Config::default().with_thread_pool(Arc::clone(&pool));
+        assert_eq!(config.parallelism, again.parallelism);
+
+        let on_pool = AtomicUsize::new(0);
+        let mut data = vec![0u8; 2 * CHUNK_PIXELS];
+        for_each_chunk(&config.parallelism, &mut data, |_| {
+            if pool.current_thread_index().is_some() {
+                on_pool.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        assert_eq!(on_pool.into_inner(), 2);
+
+        // The configurations are the only other owners of the pool
+        drop((config, again));
+        assert_eq!(Arc::strong_count(&pool), 1);
+    }
+
+    #[test]
+    fn test_scores_independent_of_parallelism() {
+        let data = |seed: usize| {
+            (0..96 * 64)
+                .map(|i| {
+                    let v = ((i * 7 + seed * 13) % 97) as f32 / 97.0;
+                    [v, 1.0 - v, 0.5 * v]
+                })
+                .collect::<Vec<_>>()
+        };
+        let source = LinearRgbImage::new(data(0), 96, 64);
+        let distorted = LinearRgbImage::new(data(1), 96, 64);
+
+        let scores: Vec<f64> = [Parallelism::Off, Parallelism::Auto, Parallelism::Threads(2)]
+            .into_iter()
+            .map(|parallelism| {
+                let config = Ssimulacra2Config::scalar().with_parallelism(parallelism);
+                compute_ssimulacra2_with_config(source.clone(), distorted.clone(), config).unwrap()
+            })
+            .collect();
+        assert!(scores.iter().all(|&s| s == scores[0]), "{:?}", scores);
+    }
+
+    #[test]
+    fn test_banded_pooling_independent_of_parallelism() {
+        // Tall enough that the first scales pool several bands
+        let (width, height) = (160, 1200);
+        let data = |seed: usize| {
+            (0..width * height)
+                .map(|i| {
+                    let v = ((i * 31 + seed * 17) % 251) as f32 / 251.0;
+                    [v, 0.5 * v, 1.0 - v]
+                })
+                .collect::<Vec<_>>()
+        };
+        let source = LinearRgbImage::new(data(0), width, height);
+        let distorted = LinearRgbImage::new(data(1), width, height);
+
+        #[allow(unused_mut)]
+        let mut configs = vec![Ssimulacra2Config::scalar(), Ssimulacra2Config::simd()];
+        #[cfg(feature = "unsafe-simd")]
+        configs.push(Ssimulacra2Config::unsafe_simd());
+        for config in configs {
+            let scores: Vec<f64> = [Parallelism::Off, Parallelism::Threads(4)]
+                .into_iter()
+                .map(|parallelism| {
+                    let config = config.clone().with_parallelism(parallelism);
+                    compute_ssimulacra2_with_config(source.clone(), distorted.clone(), config)
+                        .unwrap()
+                })
+                .collect();
+            assert_eq!(scores[0], scores[1], "{:?}", config.impl_type);
+        }
+    }
+}
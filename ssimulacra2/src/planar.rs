@@ -0,0 +1,88 @@
+//! Planar (one buffer per channel) image storage.
+//!
+//! The blur and the SSIM/edge maps work on separate channel planes rather than
+//! interleaved pixels. [`PlanarImage`] is that layout with its dimensions
+//! attached.
+
+use crate::xyb::XybImage;
+
+/// Three-channel image stored as one `Vec<f32>` per channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanarImage {
+    pub(crate) planes: [Vec<f32>; 3],
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+impl PlanarImage {
+    /// Creates a new planar image from channel planes.
+    pub fn new(planes: [Vec<f32>; 3], width: usize, height: usize) -> Self {
+        debug_assert!(planes.iter().all(|p| p.len() == width * height));
+        Self {
+            planes,
+            width,
+            height,
+        }
+    }
+
+    /// Creates a zero-filled planar image.
+    pub fn zeros(width: usize, height: usize) -> Self {
+        let size = width * height;
+        Self::new(
+            [vec![0.0f32; size], vec![0.0f32; size], vec![0.0f32; size]],
+            width,
+            height,
+        )
+    }
+
+    /// Returns the image width.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the image height.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns all three channel planes.
+    pub fn planes(&self) -> &[Vec<f32>; 3] {
+        &self.planes
+    }
+
+    /// Returns all three channel planes mutably.
+    pub fn planes_mut(&mut self) -> &mut [Vec<f32>; 3] {
+        &mut self.planes
+    }
+
+    /// Returns a single channel plane.
+    ///
+    /// # Panics
+    /// If `channel` is not 0, 1 or 2.
+    pub fn plane(&self, channel: usize) -> &[f32] {
+        &self.planes[channel]
+    }
+
+    /// Consumes the image and returns the channel planes.
+    pub fn into_planes(self) -> [Vec<f32>; 3] {
+        self.planes
+    }
+
+    /// Resizes the planes to `width` x `height`, keeping their allocations
+    /// so shrinking and growing back within the capacity doesn't allocate.
+    pub(crate) fn resize(&mut self, width: usize, height: usize) {
+        for plane in &mut self.planes {
+            plane.resize(width * height, 0.0);
+        }
+        self.width = width;
+        self.height = height;
+    }
+}
+
+impl From<&XybImage> for PlanarImage {
+    fn from(xyb: &XybImage) -> Self {
+        let mut out = PlanarImage::zeros(xyb.width(), xyb.height());
+        crate::xyb_to_planar_into(xyb, &mut out);
+        out
+    }
+}
@@ -0,0 +1,474 @@
+//! Pooling of per-frame scores into summary statistics.
+//!
+//! Video scoring produces one score per frame. [`ScorePool`] summarizes them
+//! (mean, standard deviation, extremes and the p1/p5/p50/p95 percentiles)
+//! without having to keep every score around for long content: short clips
+//! are pooled exactly, and once the number of frames exceeds a limit the pool
+//! switches to streaming quantile estimation with the P² algorithm (Jain &
+//! Chlamtac, 1985), which uses constant memory per percentile.
+//!
+//! Scores can also carry per-frame weights, e.g. from a complexity or saliency
+//! model, or from [`luma_variance`] so flat scenes don't dilute the pooled
+//! score. Weights only affect [`PoolSummary::weighted_mean`]; the percentiles
+//! describe the unweighted distribution of frame scores.
+
+#[cfg(feature = "yuvxyb")]
+use yuvxyb::{Pixel, Yuv};
+
+/// Percentiles reported in [`PoolSummary`].
+const PERCENTILES: [f64; 4] = [0.01, 0.05, 0.50, 0.95];
+
+/// Default number of frames pooled exactly before switching to streaming.
+pub const DEFAULT_EXACT_FRAME_LIMIT: usize = 100_000;
+
+/// Summary of pooled scores.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolSummary {
+    /// Number of pooled scores
+    pub frames: usize,
+    /// Arithmetic mean
+    pub mean: f64,
+    /// Mean weighted by the per-frame weights (equal to `mean` when
+    /// unweighted, or when all weights are zero)
+    pub weighted_mean: f64,
+    /// Sample standard deviation (zero for fewer than two scores)
+    pub std_dev: f64,
+    /// Lowest score
+    pub min: f64,
+    /// Highest score
+    pub max: f64,
+    /// 1st percentile
+    pub p1: f64,
+    /// 5th percentile
+    pub p5: f64,
+    /// Median
+    pub p50: f64,
+    /// 95th percentile
+    pub p95: f64,
+    /// Whether the percentiles are exact or streaming estimates
+    pub exact: bool,
+}
+
+/// Accumulates scores and summarizes them.
+///
+/// Scores can be pushed in any order. Up to the exact frame limit all scores
+/// are kept and percentiles are computed exactly (linear interpolation between
+/// closest ranks); beyond it the stored scores are folded into streaming
+/// estimators and dropped.
+#[derive(Debug, Clone)]
+pub struct ScorePool {
+    exact_limit: usize,
+    /// All scores while in exact mode; emptied once streaming
+    scores: Vec<f64>,
+    /// Streaming estimators, created when the exact limit is exceeded
+    estimators: Option<[P2Quantile; PERCENTILES.len()]>,
+    count: usize,
+    mean: f64,
+    m2: f64,
+    weight_sum: f64,
+    weighted_sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for ScorePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScorePool {
+    /// Creates a pool that is exact up to [`DEFAULT_EXACT_FRAME_LIMIT`] frames.
+    pub fn new() -> Self {
+        Self::with_exact_limit(DEFAULT_EXACT_FRAME_LIMIT)
+    }
+
+    /// Creates a pool that is exact up to `exact_limit` frames.
+    ///
+    /// A limit of zero streams from the first frame.
+    pub fn with_exact_limit(exact_limit: usize) -> Self {
+        Self {
+            exact_limit,
+            scores: Vec::new(),
+            estimators: None,
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            weight_sum: 0.0,
+            weighted_sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Adds a score with weight 1 to the pool.
+    pub fn push(&mut self, score: f64) {
+        self.push_weighted(score, 1.0);
+    }
+
+    /// Adds a score to the pool with the given non-negative weight.
+    ///
+    /// # Panics
+    /// Panics in debug builds if `weight` is negative or not finite.
+    pub fn push_weighted(&mut self, score: f64, weight: f64) {
+        debug_assert!(
+            weight.is_finite() && weight >= 0.0,
+            "invalid frame weight {}",
+            weight
+        );
+        self.weight_sum += weight;
+        self.weighted_sum += weight * score;
+
+        // Welford's online mean and variance
+        self.count += 1;
+        let delta = score - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (score - self.mean);
+        self.min = self.min.min(score);
+        self.max = self.max.max(score);
+
+        match &mut self.estimators {
+            Some(estimators) => estimators.iter_mut().for_each(|e| e.push(score)),
+            None if self.count > self.exact_limit => {
+                let mut estimators = PERCENTILES.map(P2Quantile::new);
+                for &s in self.scores.iter().chain(std::iter::once(&score)) {
+                    estimators.iter_mut().for_each(|e| e.push(s));
+                }
+                self.scores = Vec::new();
+                self.estimators = Some(estimators);
+            }
+            None => self.scores.push(score),
+        }
+    }
+
+    /// Returns the number of pooled scores.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if no scores have been pooled.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the running mean, or `None` if the pool is empty.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    /// Summarizes the pooled scores, or returns `None` if the pool is empty.
+    pub fn summary(&self) -> Option<PoolSummary> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let (percentiles, exact) = match &self.estimators {
+            Some(estimators) => (estimators.each_ref().map(P2Quantile::estimate), false),
+            None => {
+                let mut sorted = self.scores.clone();
+                sorted.sort_by(f64::total_cmp);
+                (PERCENTILES.map(|p| exact_quantile(&sorted, p)), true)
+            }
+        };
+        let std_dev = if self.count > 1 {
+            (self.m2 / (self.count - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+
+        let weighted_mean = if self.weight_sum > 0.0 {
+            self.weighted_sum / self.weight_sum
+        } else {
+            self.mean
+        };
+
+        Some(PoolSummary {
+            frames: self.count,
+            mean: self.mean,
+            weighted_mean,
+            std_dev,
+            min: self.min,
+            max: self.max,
+            p1: percentiles[0],
+            p5: percentiles[1],
+            p50: percentiles[2],
+            p95: percentiles[3],
+            exact,
+        })
+    }
+}
+
+/// Variance of a frame's luma plane, normalized to `[0, 1]` sample values.
+///
+/// Useful as a per-frame weight for [`ScorePool::push_weighted`]: flat frames
+/// (fades, black frames, static title cards) get little weight, so they don't
+/// inflate the pooled score of content with detail.
+#[cfg(feature = "yuvxyb")]
+pub fn luma_variance<T: Pixel>(frame: &Yuv<T>) -> f64 {
+    let max_value = f64::from((1u32 << frame.config().bit_depth) - 1);
+    let luma = &frame.data()[0];
+
+    let mut count = 0usize;
+    let mut mean = 0.0;
+    let mut m2 = 0.0;
+    for pixel in luma.iter() {
+        let value = f64::from(Into::<u32>::into(pixel)) / max_value;
+        count += 1;
+        let delta = value - mean;
+        mean += delta / count as f64;
+        m2 += delta * (value - mean);
+    }
+    if count == 0 {
+        0.0
+    } else {
+        m2 / count as f64
+    }
+}
+
+/// Quantile of sorted data, interpolating linearly between closest ranks.
+pub(crate) fn exact_quantile(sorted: &[f64], p: f64) -> f64 {
+    let h = (sorted.len() - 1) as f64 * p;
+    let lo = h.floor() as usize;
+    let hi = h.ceil() as usize;
+    sorted[lo] + (h - lo as f64) * (sorted[hi] - sorted[lo])
+}
+
+/// P² streaming estimator for a single quantile.
+#[derive(Debug, Clone)]
+struct P2Quantile {
+    p: f64,
+    count: usize,
+    /// Marker heights
+    q: [f64; 5],
+    /// Actual marker positions (1-based)
+    n: [f64; 5],
+    /// Desired marker positions
+    desired: [f64; 5],
+    /// Desired position increments per observation
+    increment: [f64; 5],
+}
+
+impl P2Quantile {
+    fn new(p: f64) -> Self {
+        Self {
+            p,
+            count: 0,
+            q: [0.0; 5],
+            n: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increment: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    fn push(&mut self, x: f64) {
+        if self.count < 5 {
+            self.q[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.q.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        // Find the cell containing x, extending the extremes if needed
+        let k = if x < self.q[0] {
+            self.q[0] = x;
+            0
+        } else if x >= self.q[4] {
+            self.q[4] = x;
+            3
+        } else {
+            (0..4).rfind(|&i| self.q[i] <= x).unwrap_or(0)
+        };
+
+        for n in &mut self.n[k + 1..] {
+            *n += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increment) {
+            *desired += increment;
+        }
+
+        // Adjust the middle markers towards their desired positions
+        for i in 1..4 {
+            let d = self.desired[i] - self.n[i];
+            if (d >= 1.0 && self.n[i + 1] - self.n[i] > 1.0)
+                || (d <= -1.0 && self.n[i - 1] - self.n[i] < -1.0)
+            {
+                let d = d.signum();
+                let parabolic = self.parabolic(i, d);
+                self.q[i] = if self.q[i - 1] < parabolic && parabolic < self.q[i + 1] {
+                    parabolic
+                } else {
+                    self.linear(i, d)
+                };
+                self.n[i] += d;
+            }
+        }
+    }
+
+    fn parabolic(&self, i: usize, d: f64) -> f64 {
+        let (q, n) = (&self.q, &self.n);
+        q[i] + d / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, d: f64) -> f64 {
+        let j = if d > 0.0 { i + 1 } else { i - 1 };
+        self.q[i] + d * (self.q[j] - self.q[i]) / (self.n[j] - self.n[i])
+    }
+
+    fn estimate(&self) -> f64 {
+        if self.count >= 5 {
+            return self.q[2];
+        }
+        // Too few observations for the markers: fall back to the exact value
+        let mut sorted = self.q[..self.count].to_vec();
+        sorted.sort_by(f64::total_cmp);
+        exact_quantile(&sorted, self.p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random scores with a long low tail, like real video
+    fn scores(count: usize) -> Vec<f64> {
+        let mut state = 12345u64;
+        (0..count)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let u = (state >> 11) as f64 / (1u64 << 53) as f64;
+                90.0 - 40.0 * u.powi(4) + 5.0 * (u * 17.0).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_exact_pool() {
+        let mut pool = ScorePool::new();
+        assert!(pool.summary().is_none());
+        for score in [10.0, 20.0, 30.0, 40.0, 50.0] {
+            pool.push(score);
+        }
+        let summary = pool.summary().unwrap();
+        assert!(summary.exact);
+        assert_eq!(summary.frames, 5);
+        assert_eq!(summary.mean, 30.0);
+        assert_eq!(summary.p50, 30.0);
+        assert_eq!((summary.min, summary.max), (10.0, 50.0));
+        assert!((summary.p95 - 48.0).abs() < 1e-12);
+        assert!((summary.std_dev - 250.0f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_streaming_matches_exact() {
+        let data = scores(20_000);
+        let mut exact = ScorePool::new();
+        let mut streaming = ScorePool::with_exact_limit(0);
+        for &score in &data {
+            exact.push(score);
+            streaming.push(score);
+        }
+        let exact = exact.summary().unwrap();
+        let streaming = streaming.summary().unwrap();
+        assert!(exact.exact);
+        assert!(!streaming.exact);
+
+        assert!((exact.mean - streaming.mean).abs() < 1e-9);
+        assert!((exact.std_dev - streaming.std_dev).abs() < 1e-9);
+        for (e, s) in [
+            (exact.p1, streaming.p1),
+            (exact.p5, streaming.p5),
+            (exact.p50, streaming.p50),
+            (exact.p95, streaming.p95),
+        ] {
+            assert!((e - s).abs() < 0.25, "exact {} vs streaming {}", e, s);
+        }
+    }
+
+    #[test]
+    fn test_switches_to_streaming_past_limit() {
+        let data = scores(1_000);
+        let mut pool = ScorePool::with_exact_limit(100);
+        for &score in &data[..100] {
+            pool.push(score);
+        }
+        assert!(pool.summary().unwrap().exact);
+
+        for &score in &data[100..] {
+            pool.push(score);
+        }
+        let summary = pool.summary().unwrap();
+        assert!(!summary.exact);
+        assert_eq!(summary.frames, 1_000);
+        assert!(summary.p1 <= summary.p5 && summary.p5 <= summary.p50);
+        assert!(summary.p50 <= summary.p95 && summary.p95 <= summary.max);
+    }
+
+    #[test]
+    fn test_weighted_mean() {
+        let mut pool = ScorePool::new();
+        pool.push_weighted(90.0, 3.0);
+        pool.push_weighted(50.0, 1.0);
+        pool.push_weighted(10.0, 0.0);
+        let summary = pool.summary().unwrap();
+        assert_eq!(summary.mean, 50.0);
+        assert_eq!(summary.weighted_mean, 80.0);
+
+        // All-zero weights fall back to the plain mean
+        let mut pool = ScorePool::new();
+        pool.push_weighted(90.0, 0.0);
+        pool.push_weighted(70.0, 0.0);
+        assert_eq!(pool.summary().unwrap().weighted_mean, 80.0);
+    }
+
+    #[cfg(feature = "yuvxyb")]
+    #[test]
+    fn test_luma_variance() {
+        use yuvxyb::{
+            ColorPrimaries, Frame, MatrixCoefficients, Plane, TransferCharacteristic, YuvConfig,
+        };
+
+        let config = YuvConfig {
+            bit_depth: 8,
+            subsampling_x: 0,
+            subsampling_y: 0,
+            full_range: true,
+            matrix_coefficients: MatrixCoefficients::BT709,
+            transfer_characteristics: TransferCharacteristic::BT1886,
+            color_primaries: ColorPrimaries::BT709,
+        };
+        let frame = |f: &dyn Fn(usize) -> u8| {
+            let mut luma = Plane::new(16, 16, 0, 0, 0, 0);
+            for (y, row) in luma.rows_iter_mut().enumerate() {
+                for (x, pixel) in row[..16].iter_mut().enumerate() {
+                    *pixel = f(y * 16 + x);
+                }
+            }
+            let chroma = Plane::new(16, 16, 0, 0, 0, 0);
+            let frame = Frame {
+                planes: [luma, chroma.clone(), chroma],
+            };
+            Yuv::new(frame, config).unwrap()
+        };
+
+        assert_eq!(luma_variance(&frame(&|_| 128)), 0.0);
+        // Half black, half white: variance of a fair coin
+        let split = luma_variance(&frame(&|i| if i < 128 { 0 } else { 255 }));
+        assert!((split - 0.25).abs() < 1e-12, "variance {}", split);
+    }
+
+    #[test]
+    fn test_streaming_with_few_frames() {
+        let mut pool = ScorePool::with_exact_limit(0);
+        pool.push(70.0);
+        pool.push(80.0);
+        let summary = pool.summary().unwrap();
+        assert_eq!(summary.p50, 75.0);
+    }
+}
@@ -6,6 +6,7 @@
 //! # Example
 //!
 //! ```
+//! # #[cfg(feature = "yuvxyb")] {
 //! use fast_ssim2::Ssimulacra2Reference;
 //! use yuvxyb::{Rgb, TransferCharacteristic, ColorPrimaries};
 //!
@@ -33,25 +34,424 @@
 //! ).unwrap();
 //! let score = precomputed.compare(distorted).unwrap();
 //! println!("SSIMULACRA2 score: {}", score);
+//! # }
 //! ```
 
-use crate::blur::Blur;
-use crate::input::ToLinearRgb;
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::blur::{Blur, SUPPORT_RADIUS};
+use crate::input::{LinearRgbImage, ToLinearRgb};
+#[cfg(feature = "rayon")]
+use crate::parallelism::THREADS_AVAILABLE;
+use crate::planar::PlanarImage;
+use crate::statistics::DetailedScore;
+use crate::transfer::{
+    check_colorimetry, to_metric_primaries, Colorimetry, RgbPrimaries, TransferFunction,
+};
+use crate::xyb::linear_rgb_to_positive_planar_into;
 use crate::{
-    downscale_by_2, edge_diff_map, image_multiply, linear_rgb_to_xyb_simd, make_positive_xyb,
-    ssim_map, xyb_to_planar, LinearRgb, Msssim, MsssimScale, SimdImpl, Ssimulacra2Error,
-    NUM_SCALES,
+    downscale_by_2, downscale_pixels_by_2, edge_diff_map, image_multiply, score_scales, ssim_map,
+    Msssim, NormExponents, Parallelism, Region, ScaleStatistics, SimdImpl, Ssimulacra2Config,
+    Ssimulacra2Error, NUM_SCALES,
 };
 
+/// Storage precision for the precomputed reference planes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReferencePrecision {
+    /// 32-bit float storage (results identical to a full computation)
+    #[default]
+    Full,
+    /// 16-bit fixed point per plane, halving reference memory.
+    ///
+    /// Each comparison reports a bound on the induced score error in
+    /// [`DetailedScore::error_bound`].
+    Quantized16,
+    /// IEEE 754 half-precision floats for the reference image and its
+    /// variance, cutting reference memory by a third.
+    ///
+    /// Half precision keeps about three significant digits, too few for the
+    /// local means: the SSIM map subtracts their products from the second
+    /// moments, and on low-contrast content the rounding would swamp the
+    /// variances. The means stay in `f32`, the variance is stored instead of
+    /// the second moment, and the image as its difference from the middle
+    /// of each channel's range, so that all stored values are small where
+    /// contrast is low. Error bounds are reported as for
+    /// [`Quantized16`](Self::Quantized16).
+    Half,
+}
+
+/// Reference planes at the configured storage precision.
+#[derive(Clone, Debug)]
+enum StoredPlanes {
+    Full([Vec<f32>; 3]),
+    Quantized {
+        data: [Vec<u16>; 3],
+        offset: [f32; 3],
+        step: [f32; 3],
+        /// Largest absolute difference between a stored and an original value
+        max_error: [f32; 3],
+    },
+    Half {
+        /// Bit patterns of the half-precision differences from `offset`
+        data: [Vec<u16>; 3],
+        offset: [f32; 3],
+    },
+}
+
+/// How far restored reference planes can be from the ones they were stored
+/// from
+#[derive(Clone, Copy, Debug)]
+enum StorageError {
+    /// Up to a fixed amount per channel in each of `img1`, `mu1` and `s11`
+    Absolute([[f32; 3]; 3]),
+    /// Exact `mu1`, and `img1 - img1_offset` and the variance off by up to
+    /// half a unit in the last place of each half-precision value
+    Relative { img1_offset: [f32; 3] },
+}
+
+impl StoredPlanes {
+    fn store(planes: [Vec<f32>; 3], precision: ReferencePrecision) -> Self {
+        match precision {
+            ReferencePrecision::Full => StoredPlanes::Full(planes),
+            ReferencePrecision::Quantized16 => {
+                let mut offset = [0.0f32; 3];
+                let mut step = [0.0f32; 3];
+                let mut max_error = [0.0f32; 3];
+                let data = std::array::from_fn(|c| {
+                    let plane = &planes[c];
+                    let min = plane.iter().copied().fold(f32::INFINITY, f32::min);
+                    let max = plane.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                    offset[c] = min;
+                    step[c] = (max - min) / f32::from(u16::MAX);
+                    plane
+                        .iter()
+                        .map(|&v| {
+                            let q = if step[c] > 0.0 {
+                                ((v - min) / step[c]).round() as u16
+                            } else {
+                                0
+                            };
+                            let restored = f32::from(q).mul_add(step[c], min);
+                            max_error[c] = max_error[c].max((restored - v).abs());
+                            q
+                        })
+                        .collect()
+                });
+                StoredPlanes::Quantized {
+                    data,
+                    offset,
+                    step,
+                    max_error,
+                }
+            }
+            ReferencePrecision::Half => Self::half(planes, [0.0; 3]),
+        }
+    }
+
+    /// Stores the differences of `planes` from `offset` as half-precision
+    /// values, which are most precise near zero.
+    fn half(planes: [Vec<f32>; 3], offset: [f32; 3]) -> Self {
+        let data = std::array::from_fn(|c| {
+            planes[c]
+                .iter()
+                .map(|&v| f32_to_f16(v - offset[c]))
+                .collect()
+        });
+        StoredPlanes::Half { data, offset }
+    }
+
+    /// Returns the planes, dequantizing if necessary.
+    fn planes(&self) -> Cow<'_, [Vec<f32>; 3]> {
+        match self {
+            StoredPlanes::Full(planes) => Cow::Borrowed(planes),
+            StoredPlanes::Quantized {
+                data, offset, step, ..
+            } => Cow::Owned(std::array::from_fn(|c| {
+                data[c]
+                    .iter()
+                    .map(|&q| f32::from(q).mul_add(step[c], offset[c]))
+                    .collect()
+            })),
+            StoredPlanes::Half { data, offset } => Cow::Owned(std::array::from_fn(|c| {
+                data[c].iter().map(|&h| f16_to_f32(h) + offset[c]).collect()
+            })),
+        }
+    }
+
+    /// Largest absolute error per channel. Half-precision errors are only
+    /// bounded relative to each value (see [`StorageError::Relative`]).
+    fn max_error(&self) -> [f32; 3] {
+        match self {
+            StoredPlanes::Full(_) | StoredPlanes::Half { .. } => [0.0; 3],
+            StoredPlanes::Quantized { max_error, .. } => *max_error,
+        }
+    }
+
+    fn storage_bytes(&self) -> usize {
+        match self {
+            StoredPlanes::Full(planes) => planes.iter().map(|p| p.len() * 4).sum(),
+            StoredPlanes::Quantized { data, .. } | StoredPlanes::Half { data, .. } => {
+                data.iter().map(|p| p.len() * 2).sum()
+            }
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            StoredPlanes::Full(planes) => {
+                for v in planes.iter().flatten() {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+            StoredPlanes::Quantized {
+                data,
+                offset,
+                step,
+                max_error,
+            } => {
+                for v in offset.iter().chain(step).chain(max_error) {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                for q in data.iter().flatten() {
+                    out.extend_from_slice(&q.to_le_bytes());
+                }
+            }
+            StoredPlanes::Half { data, offset } => {
+                for v in offset {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                for h in data.iter().flatten() {
+                    out.extend_from_slice(&h.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    /// Reads planes of `len` values each, as written by [`write`](Self::write).
+    fn read(
+        input: &mut ByteReader<'_>,
+        precision: ReferencePrecision,
+        len: usize,
+    ) -> Result<Self, Ssimulacra2Error> {
+        let mut floats = |n: usize| -> Result<Vec<f32>, Ssimulacra2Error> {
+            Ok(input
+                .take(n, 4)?
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect())
+        };
+        Ok(match precision {
+            ReferencePrecision::Full => {
+                StoredPlanes::Full([floats(len)?, floats(len)?, floats(len)?])
+            }
+            ReferencePrecision::Quantized16 => {
+                let header = floats(9)?;
+                let triple = |i: usize| [header[i], header[i + 1], header[i + 2]];
+                StoredPlanes::Quantized {
+                    offset: triple(0),
+                    step: triple(3),
+                    max_error: triple(6),
+                    data: [input.u16s(len)?, input.u16s(len)?, input.u16s(len)?],
+                }
+            }
+            ReferencePrecision::Half => {
+                let header = floats(3)?;
+                StoredPlanes::Half {
+                    offset: [header[0], header[1], header[2]],
+                    data: [input.u16s(len)?, input.u16s(len)?, input.u16s(len)?],
+                }
+            }
+        })
+    }
+}
+
+/// Converts to the nearest IEEE 754 half-precision value (ties to even),
+/// returning its bit pattern.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let man = bits & 0x7f_ffff;
+    if exp == 0xff {
+        // Infinity stays infinite, NaN stays NaN
+        return sign | 0x7c00 | if man != 0 { 0x200 } else { 0 };
+    }
+    let half_exp = exp - 127 + 15;
+    if half_exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    // Shift the mantissa (with its implicit bit for subnormal results) down to
+    // 10 bits and round to nearest even; a carry correctly bumps the exponent
+    let (base, man, shift) = if half_exp > 0 {
+        ((half_exp as u32) << 10, man, 13)
+    } else if half_exp >= -10 {
+        (0, man | 0x80_0000, (14 - half_exp) as u32)
+    } else {
+        return sign;
+    };
+    let rest = man & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    let mut half = base | (man >> shift);
+    if rest > halfway || (rest == halfway && half & 1 == 1) {
+        half += 1;
+    }
+    sign | half as u16
+}
+
+/// Converts the bit pattern of an IEEE 754 half-precision value to `f32`.
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = u32::from(half & 0x8000) << 16;
+    let exp = u32::from(half >> 10) & 0x1f;
+    let man = u32::from(half & 0x3ff);
+    match exp {
+        0 => {
+            // Zero or subnormal: man * 2^-24
+            let v = man as f32 / (1u32 << 24) as f32;
+            if sign != 0 {
+                -v
+            } else {
+                v
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (man << 13)),
+        _ => f32::from_bits(sign | ((exp + 112) << 23) | (man << 13)),
+    }
+}
+
+/// Bounds-checked little-endian reads for [`Ssimulacra2Reference::from_bytes`].
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    /// Takes `count` values of `size` bytes each.
+    fn take(&mut self, count: usize, size: usize) -> Result<&'a [u8], Ssimulacra2Error> {
+        let len = count
+            .checked_mul(size)
+            .filter(|&len| len <= self.bytes.len())
+            .ok_or(Ssimulacra2Error::InvalidReferenceData)?;
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, Ssimulacra2Error> {
+        Ok(self.take(1, 1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Ssimulacra2Error> {
+        let b = self.take(1, 4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u16s(&mut self, count: usize) -> Result<Vec<u16>, Ssimulacra2Error> {
+        Ok(self
+            .take(count, 2)?
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect())
+    }
+}
+
+/// Leading bytes of a serialized [`Ssimulacra2Reference`]
+const REFERENCE_MAGIC: &[u8; 8] = b"SSIM2REF";
+/// Version of the serialized layout, bumped whenever it changes
+const REFERENCE_VERSION: u32 = 2;
+
 /// Precomputed reference data for a single scale.
+///
+/// With [`ReferencePrecision::Half`], `sigma1_sq` holds the variance
+/// `blur(img1 * img1) - mu1 * mu1`, and [`planes`](Self::planes) adds the
+/// squared mean back.
 #[derive(Clone, Debug)]
 struct ScaleData {
     /// Planar XYB representation of reference image
-    img1_planar: [Vec<f32>; 3],
+    img1_planar: StoredPlanes,
     /// blur(img1) - mean of reference
-    mu1: [Vec<f32>; 3],
+    mu1: StoredPlanes,
     /// blur(img1 * img1) - variance component of reference
-    sigma1_sq: [Vec<f32>; 3],
+    sigma1_sq: StoredPlanes,
+}
+
+impl ScaleData {
+    fn store(
+        img1_planar: [Vec<f32>; 3],
+        mu1: [Vec<f32>; 3],
+        sigma1_sq: [Vec<f32>; 3],
+        precision: ReferencePrecision,
+    ) -> Self {
+        if precision != ReferencePrecision::Half {
+            return Self {
+                img1_planar: StoredPlanes::store(img1_planar, precision),
+                mu1: StoredPlanes::store(mu1, precision),
+                sigma1_sq: StoredPlanes::store(sigma1_sq, precision),
+            };
+        }
+        // The image as its difference from the middle of each channel's
+        // range, which is small on low-contrast content
+        let offset = img1_planar.each_ref().map(|plane| {
+            let min = plane.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = plane.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            0.5 * (min + max)
+        });
+        let mut variance = sigma1_sq;
+        for (plane, mu1) in variance.iter_mut().zip(&mu1) {
+            for (v, &m) in plane.iter_mut().zip(mu1) {
+                *v = m.mul_add(-m, *v);
+            }
+        }
+        Self {
+            img1_planar: StoredPlanes::half(img1_planar, offset),
+            mu1: StoredPlanes::Full(mu1),
+            sigma1_sq: StoredPlanes::half(variance, [0.0; 3]),
+        }
+    }
+
+    /// Precision `mu1` is stored at
+    fn mean_precision(precision: ReferencePrecision) -> ReferencePrecision {
+        match precision {
+            ReferencePrecision::Half => ReferencePrecision::Full,
+            precision => precision,
+        }
+    }
+
+    /// `img1`, `mu1` and `blur(img1 * img1)`, restored to full planes
+    #[allow(clippy::type_complexity)]
+    fn planes(&self, precision: ReferencePrecision) -> [Cow<'_, [Vec<f32>; 3]>; 3] {
+        let (img1, mu1, s11) = (
+            self.img1_planar.planes(),
+            self.mu1.planes(),
+            self.sigma1_sq.planes(),
+        );
+        if precision != ReferencePrecision::Half {
+            return [img1, mu1, s11];
+        }
+        let mut s11 = s11.into_owned();
+        for (plane, mu1) in s11.iter_mut().zip(mu1.iter()) {
+            for (v, &m) in plane.iter_mut().zip(mu1) {
+                *v = m.mul_add(m, *v);
+            }
+        }
+        [img1, mu1, Cow::Owned(s11)]
+    }
+
+    fn storage_error(&self) -> StorageError {
+        match self.img1_planar {
+            StoredPlanes::Half { offset, .. } => StorageError::Relative {
+                img1_offset: offset,
+            },
+            _ => StorageError::Absolute([
+                self.img1_planar.max_error(),
+                self.mu1.max_error(),
+                self.sigma1_sq.max_error(),
+            ]),
+        }
+    }
+
+    fn storage_bytes(&self) -> usize {
+        self.img1_planar.storage_bytes() + self.mu1.storage_bytes() + self.sigma1_sq.storage_bytes()
+    }
 }
 
 /// Precomputed SSIMULACRA2 reference data for fast repeated comparisons.
@@ -67,6 +467,8 @@ pub struct Ssimulacra2Reference {
     scales: Vec<ScaleData>,
     original_width: usize,
     original_height: usize,
+    precision: ReferencePrecision,
+    colorimetry: Option<Colorimetry>,
 }
 
 impl Ssimulacra2Reference {
@@ -80,10 +482,26 @@ impl Ssimulacra2Reference {
     /// # Errors
     /// - If the image is smaller than 8x8 pixels
     pub fn new<T: ToLinearRgb>(source: T) -> Result<Self, Ssimulacra2Error> {
-        let mut img1: LinearRgb = source.to_linear_rgb().into();
+        Self::with_precision(source, ReferencePrecision::Full)
+    }
+
+    /// Precompute reference data, storing it at the given precision.
+    ///
+    /// Reduced precision trades a small, tracked score error for memory; use
+    /// [`compare_detailed`](Self::compare_detailed) to check the error bound.
+    ///
+    /// # Errors
+    /// - If the image is smaller than 8x8 pixels
+    pub fn with_precision<T: ToLinearRgb>(
+        source: T,
+        precision: ReferencePrecision,
+    ) -> Result<Self, Ssimulacra2Error> {
+        let mut img1 = source.as_linear_rgb();
         if img1.width() < 8 || img1.height() < 8 {
             return Err(Ssimulacra2Error::InvalidImageSize);
         }
+        to_metric_primaries(&mut img1);
+        let colorimetry = img1.colorimetry();
 
         let original_width = img1.width();
         let original_height = img1.height();
@@ -104,7 +522,7 @@ impl Ssimulacra2Reference {
             }
 
             if scale > 0 {
-                img1 = downscale_by_2(&img1);
+                img1 = Cow::Owned(downscale_by_2(&img1, SimdImpl::default()));
                 width = img1.width();
                 height = img1.height();
             }
@@ -112,31 +530,36 @@ impl Ssimulacra2Reference {
             for c in &mut mul {
                 c.truncate(width * height);
             }
-            blur.shrink_to(width, height);
-
-            let mut img1_xyb = linear_rgb_to_xyb_simd(img1.clone());
-            make_positive_xyb(&mut img1_xyb);
+            blur.resize(width, height);
 
-            let img1_planar = xyb_to_planar(&img1_xyb);
+            let img1_planar = positive_xyb_planar(&img1);
 
             // Precompute mu1 = blur(img1)
-            let mu1 = blur.blur(&img1_planar);
+            let mu1 = blur.blur(img1_planar.planes());
 
             // Precompute sigma1_sq = blur(img1 * img1)
-            image_multiply(&img1_planar, &img1_planar, &mut mul, SimdImpl::default());
+            image_multiply(
+                img1_planar.planes(),
+                img1_planar.planes(),
+                &mut mul,
+                SimdImpl::default(),
+            );
             let sigma1_sq = blur.blur(&mul);
 
-            scales.push(ScaleData {
-                img1_planar,
+            scales.push(ScaleData::store(
+                img1_planar.into_planes(),
                 mu1,
                 sigma1_sq,
-            });
+                precision,
+            ));
         }
 
         Ok(Self {
             scales,
             original_width,
             original_height,
+            precision,
+            colorimetry,
         })
     }
 
@@ -147,22 +570,83 @@ impl Ssimulacra2Reference {
     ///
     /// # Errors
     /// - If the distorted image dimensions don't match the reference
+    /// - If both images carry [`Colorimetry`] tags that differ in transfer or
+    ///   range
     pub fn compare<T: ToLinearRgb>(&self, distorted: T) -> Result<f64, Ssimulacra2Error> {
-        let mut img2: LinearRgb = distorted.to_linear_rgb().into();
+        self.compare_detailed(distorted)
+            .map(|detailed| detailed.score)
+    }
+
+    /// Compare a distorted image and return the score with per-scale statistics.
+    ///
+    /// With reduced-precision storage, [`DetailedScore::error_bound`] bounds how
+    /// far the score may be from the full-precision result. The bound is a
+    /// first-order estimate from the largest quantization error of each stored
+    /// plane, or with [`ReferencePrecision::Half`] from the rounding error of
+    /// each stored value. It is conservative in practice, typically a few to
+    /// ten times larger than the actual deviation.
+    ///
+    /// # Errors
+    /// - If the distorted image dimensions don't match the reference
+    pub fn compare_detailed<T: ToLinearRgb>(
+        &self,
+        distorted: T,
+    ) -> Result<DetailedScore, Ssimulacra2Error> {
+        let mut buffers = CompareBuffers::new(self.original_width, self.original_height);
+        self.compare_with(&distorted, &mut buffers)
+    }
+
+    /// Compares several distorted images against the reference, e.g. the
+    /// candidates of an encoder's quantizer search, and returns their scores
+    /// in the same order.
+    ///
+    /// With the `rayon` feature the images are compared in parallel on
+    /// rayon's global thread pool. The reference is shared by all of them,
+    /// and each worker reuses one set of scratch buffers across the images
+    /// it compares. Without threads they are compared one after another.
+    pub fn compare_batch<T: ToLinearRgb + Sync>(
+        &self,
+        distorted: &[T],
+    ) -> Vec<Result<f64, Ssimulacra2Error>> {
+        let new_buffers = || CompareBuffers::new(self.original_width, self.original_height);
+        let compare = |buffers: &mut CompareBuffers, image: &T| {
+            self.compare_with(image, buffers)
+                .map(|detailed| detailed.score)
+        };
+
+        #[cfg(feature = "rayon")]
+        if THREADS_AVAILABLE {
+            use rayon::prelude::*;
+
+            return distorted
+                .par_iter()
+                .map_init(new_buffers, compare)
+                .collect();
+        }
+
+        let mut buffers = new_buffers();
+        distorted
+            .iter()
+            .map(|image| compare(&mut buffers, image))
+            .collect()
+    }
+
+    fn compare_with<T: ToLinearRgb + ?Sized>(
+        &self,
+        distorted: &T,
+        buffers: &mut CompareBuffers,
+    ) -> Result<DetailedScore, Ssimulacra2Error> {
+        let mut img2 = distorted.as_linear_rgb();
         if img2.width() != self.original_width || img2.height() != self.original_height {
             return Err(Ssimulacra2Error::NonMatchingImageDimensions);
         }
+        check_colorimetry(self.colorimetry, img2.colorimetry())?;
+        to_metric_primaries(&mut img2);
 
         let mut width = img2.width();
         let mut height = img2.height();
-
-        let mut mul = [
-            vec![0.0f32; width * height],
-            vec![0.0f32; width * height],
-            vec![0.0f32; width * height],
-        ];
-        let mut blur = Blur::new(width, height);
         let mut msssim = Msssim::default();
+        let mut bounds = Vec::new();
 
         for (scale_idx, scale_data) in self.scales.iter().enumerate() {
             if width < 8 || height < 8 {
@@ -170,63 +654,162 @@ impl Ssimulacra2Reference {
             }
 
             if scale_idx > 0 {
-                img2 = downscale_by_2(&img2);
+                img2 = Cow::Owned(downscale_by_2(&img2, SimdImpl::default()));
                 width = img2.width();
                 height = img2.height();
             }
 
-            for c in &mut mul {
-                c.truncate(width * height);
-            }
-            blur.shrink_to(width, height);
+            let [img1_planar, mu1, sigma1_sq] = scale_data.planes(self.precision);
 
-            let mut img2_xyb = linear_rgb_to_xyb_simd(img2.clone());
-            make_positive_xyb(&mut img2_xyb);
+            let img2_planar = positive_xyb_planar(&img2);
+            distorted_moments(buffers, &img1_planar, img2_planar.planes(), width, height);
+            let CompareBuffers {
+                moments: [mu2, sigma2_sq, sigma12],
+                blur,
+                ..
+            } = &mut *buffers;
 
-            let img2_planar = xyb_to_planar(&img2_xyb);
+            // Use precomputed mu1 and sigma1_sq from reference
+            msssim.scales.push(pooled_statistics(
+                (width, height),
+                [&img1_planar, &mu1, &sigma1_sq],
+                [img2_planar.planes(), mu2, sigma2_sq, sigma12],
+                blur.parallelism(),
+            ));
 
-            // Compute mu2 = blur(img2)
-            let mu2 = blur.blur(&img2_planar);
+            if self.precision != ReferencePrecision::Full {
+                bounds.push(statistics_error_bound(
+                    width,
+                    height,
+                    [&img1_planar, &mu1, &sigma1_sq],
+                    [img2_planar.planes(), mu2, sigma2_sq, sigma12],
+                    scale_data.storage_error(),
+                    blur,
+                ));
+            }
+        }
 
-            // Compute sigma2_sq = blur(img2 * img2)
-            image_multiply(&img2_planar, &img2_planar, &mut mul, SimdImpl::default());
-            let sigma2_sq = blur.blur(&mul);
+        let score = msssim.score();
+        let error_bound = if bounds.is_empty() {
+            0.0
+        } else {
+            score_error_bound(&msssim.scales, &bounds, score)
+        };
 
-            // Compute sigma12 = blur(img1 * img2) - cross-term
-            image_multiply(
-                &scale_data.img1_planar,
-                &img2_planar,
-                &mut mul,
-                SimdImpl::default(),
-            );
-            let sigma12 = blur.blur(&mul);
+        Ok(DetailedScore {
+            score,
+            scales: msssim.scales,
+            error_bound,
+        })
+    }
 
-            // Use precomputed mu1 and sigma1_sq from reference
-            let avg_ssim = ssim_map(
-                width,
-                height,
-                &scale_data.mu1,
-                &mu2,
-                &scale_data.sigma1_sq,
-                &sigma2_sq,
-                &sigma12,
-                SimdImpl::default(),
-            );
+    /// Compares a candidate that differs from the one last compared through
+    /// `cache` only inside `changed`, recomputing the distorted-side XYB
+    /// conversion and blurs just for the rows those regions touch.
+    ///
+    /// For search loops that re-encode the same source with different
+    /// settings, where consecutive decodes share large identical areas.
+    /// Changed rows are recomputed across the full width, and blurs cover
+    /// the rows the Gaussian reaches from them. The error maps are still
+    /// pooled over the whole image, so the result matches
+    /// [`compare`](Self::compare) up to `f32` rounding in the blur at the
+    /// edges of the recomputed bands. The SSIM map amplifies that rounding,
+    /// so scores can differ by up to about 0.01.
+    ///
+    /// With `changed` set to `None`, or when `cache` is empty or was filled
+    /// for an image of another size, everything is computed and cached. A
+    /// cache must only be used with the reference that filled it; call
+    /// [`CandidateCache::clear`] before reusing it with another.
+    ///
+    /// # Errors
+    /// - If the distorted image dimensions don't match the reference
+    /// - If both images carry [`Colorimetry`] tags that differ in transfer or
+    ///   range
+    /// - If a changed region is empty or extends past the image
+    pub fn compare_incremental<T: ToLinearRgb>(
+        &self,
+        distorted: T,
+        cache: &mut CandidateCache,
+        changed: Option<&[Region]>,
+    ) -> Result<f64, Ssimulacra2Error> {
+        let mut img2 = distorted.as_linear_rgb();
+        let (width, height) = (self.original_width, self.original_height);
+        if img2.width() != width || img2.height() != height {
+            return Err(Ssimulacra2Error::NonMatchingImageDimensions);
+        }
+        check_colorimetry(self.colorimetry, img2.colorimetry())?;
+        if changed.is_some_and(|regions| regions.iter().any(|r| !r.fits(width, height))) {
+            return Err(Ssimulacra2Error::InvalidRegion);
+        }
+        to_metric_primaries(&mut img2);
 
-            let avg_edgediff = edge_diff_map(
-                width,
-                height,
-                &scale_data.img1_planar,
-                &scale_data.mu1,
-                &img2_planar,
-                &mu2,
-                SimdImpl::default(),
-            );
+        let cached_size = cache
+            .scales
+            .first()
+            .map(|scale| (scale.planar.width(), scale.planar.height()));
+        let mut changed_rows = changed
+            .filter(|_| {
+                cache.scales.len() == self.scales.len() && cached_size == Some((width, height))
+            })
+            .map(|regions| merge_rows(regions.iter().map(|r| r.y..r.y + r.height)));
+        if changed_rows.is_none() {
+            cache.scales.clear();
+        }
+        let buffers = cache
+            .buffers
+            .get_or_insert_with(|| CompareBuffers::new(width, height));
+        let mut msssim = Msssim::default();
 
-            msssim.scales.push(MsssimScale {
-                avg_ssim,
-                avg_edgediff,
-            });
+        for (scale_idx, scale_data) in self.scales.iter().enumerate() {
+            let [img1_planar, mu1, sigma1_sq] = scale_data.planes(self.precision);
+            let (done, rest) = cache.scales.split_at_mut(scale_idx);
+            let previous = done.last().map(|scale| &scale.linear);
+
+            let cached = match changed_rows.as_mut() {
+                None => {
+                    let linear = previous.map_or_else(
+                        || (*img2).clone(),
+                        |linear| downscale_by_2(linear, SimdImpl::default()),
+                    );
+                    let planar = positive_xyb_planar(&linear);
+                    let (w, h) = (planar.width(), planar.height());
+                    distorted_moments(buffers, &img1_planar, planar.planes(), w, h);
+                    let [mu2, sigma2_sq, sigma12] = std::mem::take(&mut buffers.moments);
+                    cache.scales.push(CachedScale {
+                        linear,
+                        planar,
+                        mu2,
+                        sigma2_sq,
+                        sigma12,
+                    });
+                    &cache.scales[scale_idx]
+                }
+                Some(rows) => {
+                    let cached = &mut rest[0];
+                    if scale_idx > 0 {
+                        *rows = merge_rows(rows.iter().map(|r| r.start / 2..r.end.div_ceil(2)));
+                    }
+                    match previous {
+                        Some(previous) => cached.update_rows(rows, previous, true),
+                        None => cached.update_rows(rows, &img2, false),
+                    }
+                    cached.update_moments(rows, buffers, &img1_planar);
+                    &*cached
+                }
+            };
+
+            let (w, h) = (cached.planar.width(), cached.planar.height());
+            msssim.scales.push(pooled_statistics(
+                (w, h),
+                [&img1_planar, &mu1, &sigma1_sq],
+                [
+                    cached.planar.planes(),
+                    &cached.mu2,
+                    &cached.sigma2_sq,
+                    &cached.sigma12,
+                ],
+                buffers.blur.parallelism(),
+            ));
         }
 
         Ok(msssim.score())
@@ -249,14 +832,510 @@ impl Ssimulacra2Reference {
     pub fn num_scales(&self) -> usize {
         self.scales.len()
     }
+
+    /// Get the colorimetry tag of the reference image, if it had one.
+    #[must_use]
+    pub fn colorimetry(&self) -> Option<Colorimetry> {
+        self.colorimetry
+    }
+
+    /// Get the storage precision of the reference planes.
+    #[must_use]
+    pub fn precision(&self) -> ReferencePrecision {
+        self.precision
+    }
+
+    /// Serializes the precomputed reference, at its storage precision, so it
+    /// can be saved and loaded by another process with
+    /// [`from_bytes`](Self::from_bytes).
+    ///
+    /// The blob is a versioned little-endian layout, about
+    /// [`storage_bytes`](Self::storage_bytes) plus a small header. Build the
+    /// reference with [`ReferencePrecision::Quantized16`] to halve its size,
+    /// or with [`ReferencePrecision::Half`] to cut it by a third.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.storage_bytes() + 64);
+        out.extend_from_slice(REFERENCE_MAGIC);
+        out.extend_from_slice(&REFERENCE_VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.original_width as u32).to_le_bytes());
+        out.extend_from_slice(&(self.original_height as u32).to_le_bytes());
+        out.push(match self.precision {
+            ReferencePrecision::Full => 0,
+            ReferencePrecision::Quantized16 => 1,
+            ReferencePrecision::Half => 2,
+        });
+        match self.colorimetry {
+            None => out.push(0),
+            Some(colorimetry) => out.extend_from_slice(&[
+                1,
+                match colorimetry.primaries {
+                    RgbPrimaries::Bt709 => 0,
+                    RgbPrimaries::Bt2020 => 1,
+                },
+                match colorimetry.transfer {
+                    TransferFunction::Srgb => 0,
+                    TransferFunction::Linear => 1,
+                    TransferFunction::Gamma22 => 2,
+                    TransferFunction::Gamma28 => 3,
+                    TransferFunction::Pq => 4,
+                    TransferFunction::Hlg => 5,
+                },
+                u8::from(colorimetry.limited_range),
+            ]),
+        }
+        out.push(self.scales.len() as u8);
+        for scale in &self.scales {
+            scale.img1_planar.write(&mut out);
+            scale.mu1.write(&mut out);
+            scale.sigma1_sq.write(&mut out);
+        }
+        out
+    }
+
+    /// Loads a reference serialized with [`to_bytes`](Self::to_bytes).
+    ///
+    /// # Errors
+    /// - [`Ssimulacra2Error::InvalidReferenceData`] if `bytes` is truncated,
+    ///   corrupt, or was written by an incompatible version of this crate
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Ssimulacra2Error> {
+        const INVALID: Ssimulacra2Error = Ssimulacra2Error::InvalidReferenceData;
+
+        let mut input = ByteReader { bytes };
+        if input.take(1, REFERENCE_MAGIC.len())? != REFERENCE_MAGIC
+            || input.u32()? != REFERENCE_VERSION
+        {
+            return Err(INVALID);
+        }
+        let original_width = input.u32()? as usize;
+        let original_height = input.u32()? as usize;
+        if original_width < 8 || original_height < 8 {
+            return Err(INVALID);
+        }
+        let precision = match input.u8()? {
+            0 => ReferencePrecision::Full,
+            1 => ReferencePrecision::Quantized16,
+            2 => ReferencePrecision::Half,
+            _ => return Err(INVALID),
+        };
+        let colorimetry = match input.u8()? {
+            0 => None,
+            1 => Some(Colorimetry {
+                primaries: match input.u8()? {
+                    0 => RgbPrimaries::Bt709,
+                    1 => RgbPrimaries::Bt2020,
+                    _ => return Err(INVALID),
+                },
+                transfer: match input.u8()? {
+                    0 => TransferFunction::Srgb,
+                    1 => TransferFunction::Linear,
+                    2 => TransferFunction::Gamma22,
+                    3 => TransferFunction::Gamma28,
+                    4 => TransferFunction::Pq,
+                    5 => TransferFunction::Hlg,
+                    _ => return Err(INVALID),
+                },
+                limited_range: match input.u8()? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(INVALID),
+                },
+            }),
+            _ => return Err(INVALID),
+        };
+
+        // The scales must be exactly those `with_precision` builds
+        let num_scales = usize::from(input.u8()?);
+        let (mut width, mut height) = (original_width, original_height);
+        let mut scales = Vec::with_capacity(NUM_SCALES);
+        for scale in 0..NUM_SCALES {
+            if width < 8 || height < 8 {
+                break;
+            }
+            if scale > 0 {
+                width = width.div_ceil(2);
+                height = height.div_ceil(2);
+            }
+            if scale == num_scales {
+                return Err(INVALID);
+            }
+            let len = width.checked_mul(height).ok_or(INVALID)?;
+            scales.push(ScaleData {
+                img1_planar: StoredPlanes::read(&mut input, precision, len)?,
+                mu1: StoredPlanes::read(&mut input, ScaleData::mean_precision(precision), len)?,
+                sigma1_sq: StoredPlanes::read(&mut input, precision, len)?,
+            });
+        }
+        if scales.len() != num_scales || !input.bytes.is_empty() {
+            return Err(INVALID);
+        }
+
+        Ok(Self {
+            scales,
+            original_width,
+            original_height,
+            precision,
+            colorimetry,
+        })
+    }
+
+    /// Get the number of bytes used by the stored reference planes.
+    #[must_use]
+    pub fn storage_bytes(&self) -> usize {
+        self.scales.iter().map(ScaleData::storage_bytes).sum()
+    }
+}
+
+/// SSIM and edge-difference norms of one scale, from the reference planes
+/// (`img1`, `mu1`, `sigma1_sq`) and the distorted ones (`img2`, `mu2`,
+/// `sigma2_sq`, `sigma12`)
+fn pooled_statistics(
+    (width, height): (usize, usize),
+    [img1, mu1, s11]: [&[Vec<f32>; 3]; 3],
+    [img2, mu2, s22, s12]: [&[Vec<f32>; 3]; 4],
+    parallelism: &Parallelism,
+) -> ScaleStatistics {
+    ScaleStatistics {
+        avg_ssim: ssim_map(
+            width,
+            height,
+            mu1,
+            mu2,
+            s11,
+            s22,
+            s12,
+            SimdImpl::default(),
+            NormExponents::STANDARD,
+            parallelism,
+        ),
+        avg_edgediff: edge_diff_map(
+            width,
+            height,
+            img1,
+            mu1,
+            img2,
+            mu2,
+            SimdImpl::default(),
+            NormExponents::STANDARD,
+            parallelism,
+        ),
+    }
+}
+
+/// Distorted-side buffers of one comparison, reusable across comparisons
+/// against references of the same size.
+struct CompareBuffers {
+    mul: [Vec<f32>; 3],
+    /// `mu2`, `sigma2_sq` and `sigma12` of the last [`distorted_moments`]
+    moments: [[Vec<f32>; 3]; 3],
+    blur: Blur,
+}
+
+impl CompareBuffers {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            mul: std::array::from_fn(|_| vec![0.0f32; width * height]),
+            moments: Default::default(),
+            blur: Blur::new(width, height),
+        }
+    }
+}
+
+/// Distorted-side planes of the candidate last compared through
+/// [`Ssimulacra2Reference::compare_incremental`], so the next comparison can
+/// recompute only the rows that changed.
+///
+/// Holds about the same memory as a full-precision reference.
+#[derive(Default)]
+pub struct CandidateCache {
+    scales: Vec<CachedScale>,
+    buffers: Option<CompareBuffers>,
+}
+
+impl CandidateCache {
+    /// Creates an empty cache; the first comparison through it computes
+    /// everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the cached candidate, so the next comparison computes
+    /// everything.
+    pub fn clear(&mut self) {
+        self.scales.clear();
+    }
+}
+
+/// One scale of a cached candidate
+struct CachedScale {
+    /// The candidate at this scale, in the metric's primaries
+    linear: LinearRgbImage,
+    /// Its positive XYB planes
+    planar: PlanarImage,
+    mu2: [Vec<f32>; 3],
+    sigma2_sq: [Vec<f32>; 3],
+    sigma12: [Vec<f32>; 3],
+}
+
+impl CachedScale {
+    /// Refreshes `rows` of the linear and XYB planes from `source`: the
+    /// full-resolution candidate at the first scale, or the previous scale's
+    /// linear image to `downscale` after it.
+    fn update_rows(&mut self, rows: &[Range<usize>], source: &LinearRgbImage, downscale: bool) {
+        let width = self.linear.width();
+        for band in rows {
+            let out = &mut self.linear.data[band.start * width..band.end * width];
+            if downscale {
+                let in_w = source.width();
+                let in_rows = 2 * band.start..(2 * band.end).min(source.height());
+                downscale_pixels_by_2(
+                    &source.data()[in_rows.start * in_w..in_rows.end * in_w],
+                    in_w,
+                    in_rows.len(),
+                    out,
+                    SimdImpl::default(),
+                );
+            } else {
+                out.copy_from_slice(&source.data()[band.start * width..band.end * width]);
+            }
+            let xyb = positive_xyb_planar(&self.linear.crop(0, band.start, width, band.len()));
+            copy_rows(
+                xyb.planes(),
+                0..band.len(),
+                self.planar.planes_mut(),
+                band.start,
+                width,
+            );
+        }
+    }
+
+    /// Reblurs the moments on every row the blur reaches from `rows`.
+    fn update_moments(
+        &mut self,
+        rows: &[Range<usize>],
+        buffers: &mut CompareBuffers,
+        img1_planar: &[Vec<f32>; 3],
+    ) {
+        let (width, height) = (self.planar.width(), self.planar.height());
+        let reach = |r: &Range<usize>| {
+            r.start.saturating_sub(SUPPORT_RADIUS)..(r.end + SUPPORT_RADIUS).min(height)
+        };
+        for affected in merge_rows(rows.iter().map(reach)) {
+            // Blurring a window that extends the blur's reach past the
+            // affected rows gives them their whole-image values
+            let window = reach(&affected);
+            let slice = |planes: &[Vec<f32>; 3]| -> [Vec<f32>; 3] {
+                std::array::from_fn(|c| {
+                    planes[c][window.start * width..window.end * width].to_vec()
+                })
+            };
+            distorted_moments(
+                buffers,
+                &slice(img1_planar),
+                &slice(self.planar.planes()),
+                width,
+                window.len(),
+            );
+            let keep = affected.start - window.start..affected.end - window.start;
+            for (blurred, cached) in
+                buffers
+                    .moments
+                    .iter()
+                    .zip([&mut self.mu2, &mut self.sigma2_sq, &mut self.sigma12])
+            {
+                copy_rows(blurred, keep.clone(), cached, affected.start, width);
+            }
+        }
+    }
+}
+
+/// Converts linear RGB to the positive XYB planes the metric compares, as
+/// [`Ssimulacra2Reference::compare`] does.
+fn positive_xyb_planar(image: &LinearRgbImage) -> PlanarImage {
+    let mut planar = PlanarImage::zeros(0, 0);
+    linear_rgb_to_positive_planar_into(
+        image.data(),
+        image.width(),
+        image.height(),
+        &mut planar,
+        &Ssimulacra2Config::simd(),
+    );
+    planar
+}
+
+/// Blurs `img2`, `img2 * img2` and `img1 * img2` into `buffers.moments`
+fn distorted_moments(
+    buffers: &mut CompareBuffers,
+    img1: &[Vec<f32>; 3],
+    img2: &[Vec<f32>; 3],
+    width: usize,
+    height: usize,
+) {
+    let CompareBuffers { mul, moments, blur } = buffers;
+    for plane in mul.iter_mut().chain(moments.iter_mut().flatten()) {
+        plane.resize(width * height, 0.0);
+    }
+    blur.resize(width, height);
+    let [mu2, sigma2_sq, sigma12] = moments;
+    blur.blur_into(img2, mu2);
+    image_multiply(img2, img2, mul, SimdImpl::default());
+    blur.blur_into(mul, sigma2_sq);
+    image_multiply(img1, img2, mul, SimdImpl::default());
+    blur.blur_into(mul, sigma12);
+}
+
+/// Copies rows `rows` of `src` to `dst`, starting at row `dst_start`.
+fn copy_rows(
+    src: &[Vec<f32>; 3],
+    rows: Range<usize>,
+    dst: &mut [Vec<f32>; 3],
+    dst_start: usize,
+    width: usize,
+) {
+    let len = rows.len() * width;
+    for (src, dst) in src.iter().zip(dst.iter_mut()) {
+        dst[dst_start * width..][..len].copy_from_slice(&src[rows.start * width..][..len]);
+    }
+}
+
+/// Sorts row ranges and merges those that overlap or touch.
+fn merge_rows(rows: impl Iterator<Item = Range<usize>>) -> Vec<Range<usize>> {
+    let mut rows: Vec<_> = rows.filter(|r| !r.is_empty()).collect();
+    rows.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(rows.len());
+    for r in rows {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+/// First-order bound on how far each statistic of one scale can move when the
+/// reference planes (`img1`, `mu1`, `sigma1_sq`) are off by up to `errors` per
+/// channel.
+///
+/// `sigma12` inherits the `img1` error weighted by the distorted values. With
+/// absolute errors that is at most the error times the largest distorted
+/// value, since the blur kernel is normalized. Relative errors vary from
+/// pixel to pixel, so they are weighted and blurred like `sigma12` itself.
+fn statistics_error_bound(
+    width: usize,
+    height: usize,
+    [img1, mu1, s11]: [&[Vec<f32>; 3]; 3],
+    [img2, mu2, s22, s12]: [&[Vec<f32>; 3]; 4],
+    error: StorageError,
+    blur: &mut Blur,
+) -> ScaleStatistics {
+    const C2: f64 = 0.0009;
+    // Half a unit in the last place of a half-precision value: 2^-11
+    // relative to a normal value, 2^-25 absolute in the subnormal range
+    let half_error = |v: f32| v.abs().mul_add(1.0 / 2048.0, 1.0 / 33_554_432.0);
+
+    let one_per_pixels = 1.0f64 / (width * height) as f64;
+    let mut bound = ScaleStatistics::default();
+    let d_s12_planes = match error {
+        StorageError::Absolute(_) => None,
+        StorageError::Relative { img1_offset } => Some(blur.blur(&std::array::from_fn(|c| {
+            (0..width * height)
+                .map(|i| half_error(img1[c][i] - img1_offset[c]) * img2[c][i].abs())
+                .collect()
+        }))),
+    };
+
+    for c in 0..3 {
+        let max_img2 = f64::from(img2[c].iter().fold(0.0f32, |m, &v| m.max(v.abs())));
+
+        let mut ssim_sum = [0.0f64; 2];
+        let mut edge_sum = [0.0f64; 2];
+        for i in 0..width * height {
+            let m1 = f64::from(mu1[c][i]);
+            let m2 = f64::from(mu2[c][i]);
+            let (d_img1, d_mu1, d_s11, d_s12) = match (error, &d_s12_planes) {
+                (StorageError::Absolute([img1_err, mu1_err, s11_err]), _) => {
+                    let d_img1 = f64::from(img1_err[c]);
+                    (
+                        d_img1,
+                        f64::from(mu1_err[c]),
+                        f64::from(s11_err[c]),
+                        d_img1 * max_img2,
+                    )
+                }
+                (StorageError::Relative { img1_offset }, planes) => (
+                    f64::from(half_error(img1[c][i] - img1_offset[c])),
+                    0.0,
+                    f64::from(half_error(mu1[c][i].mul_add(-mu1[c][i], s11[c][i]))),
+                    planes
+                        .as_ref()
+                        .map_or(0.0, |planes| f64::from(planes[c][i])),
+                ),
+            };
+            let mu_diff = m1 - m2;
+            let num_m = mu_diff.mul_add(-mu_diff, 1.0);
+            let num_s = 2.0f64.mul_add(f64::from(s12[c][i]) - m1 * m2, C2);
+            let denom_s = (f64::from(s11[c][i]) - m1 * m1) + (f64::from(s22[c][i]) - m2 * m2) + C2;
+            let q = num_m * num_s / denom_s;
+
+            let dq_dm1 = (2.0 * m1).mul_add(q, -2.0 * (mu_diff * num_s + m2 * num_m)) / denom_s;
+            let dq_ds11 = q / denom_s;
+            let dq_ds12 = 2.0 * num_m / denom_s;
+            let e = dq_dm1.abs() * d_mu1 + dq_ds11.abs() * d_s11 + dq_ds12.abs() * d_s12;
+            ssim_sum[0] += e;
+            ssim_sum[1] += e.powi(4);
+
+            let diff1 = 1.0 + (f64::from(img1[c][i]) - m1).abs();
+            let diff2 = 1.0 + (f64::from(img2[c][i]) - m2).abs();
+            let e = diff2 / (diff1 * diff1) * (d_img1 + d_mu1);
+            edge_sum[0] += e;
+            edge_sum[1] += e.powi(4);
+        }
+
+        bound.avg_ssim[c * 2] = one_per_pixels * ssim_sum[0];
+        bound.avg_ssim[c * 2 + 1] = (one_per_pixels * ssim_sum[1]).sqrt().sqrt();
+        // Artifact and detail-lost terms are both 1-Lipschitz in the same ratio
+        for offset in [0, 2] {
+            bound.avg_edgediff[c * 4 + offset] = one_per_pixels * edge_sum[0];
+            bound.avg_edgediff[c * 4 + offset + 1] = (one_per_pixels * edge_sum[1]).sqrt().sqrt();
+        }
+    }
+
+    bound
 }
 
-#[cfg(test)]
+/// Largest score change when every statistic moves by up to its bound.
+fn score_error_bound(scales: &[ScaleStatistics], bounds: &[ScaleStatistics], score: f64) -> f64 {
+    let shifted = |sign: f64| -> Vec<ScaleStatistics> {
+        scales
+            .iter()
+            .zip(bounds)
+            .map(|(s, b)| ScaleStatistics {
+                avg_ssim: std::array::from_fn(|i| {
+                    sign.mul_add(b.avg_ssim[i], s.avg_ssim[i].abs()).max(0.0)
+                }),
+                avg_edgediff: std::array::from_fn(|i| {
+                    sign.mul_add(b.avg_edgediff[i], s.avg_edgediff[i].abs())
+                        .max(0.0)
+                }),
+            })
+            .collect()
+    };
+
+    let worst = score_scales(&shifted(1.0));
+    let best = score_scales(&shifted(-1.0));
+    (score - worst).abs().max((best - score).abs())
+}
+
+#[cfg(all(test, feature = "yuvxyb"))]
 mod tests {
     use super::*;
+    #[cfg(feature = "yuvxyb")]
     use crate::compute_frame_ssimulacra2;
+    use crate::LinearRgbImage;
+    #[cfg(feature = "yuvxyb")]
     use yuvxyb::{ColorPrimaries, Rgb, TransferCharacteristic};
 
+    #[cfg(feature = "yuvxyb")]
     #[test]
     fn test_precompute_matches_full_compute() {
         // Create a simple test image
@@ -317,6 +1396,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "yuvxyb")]
     #[test]
     fn test_precompute_dimension_mismatch() {
         let source_data: Vec<[f32; 3]> = vec![[0.5, 0.5, 0.5]; 64 * 64];
@@ -349,6 +1429,7 @@ mod tests {
         ));
     }
 
+    #[cfg(feature = "yuvxyb")]
     #[test]
     fn test_precompute_metadata() {
         let data: Vec<[f32; 3]> = vec![[0.5, 0.5, 0.5]; 128 * 96];
@@ -368,4 +1449,261 @@ mod tests {
         assert!(precomputed.num_scales() > 0);
         assert!(precomputed.num_scales() <= NUM_SCALES);
     }
+
+    fn textured(width: usize, height: usize, scale: f32) -> LinearRgbImage {
+        let data = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                let v = 0.5 + 0.4 * (x * 0.3).sin() * (y * 0.2).cos();
+                [v * scale, v, (1.0 - v) * scale]
+            })
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    }
+
+    #[test]
+    fn test_full_precision_has_no_error_bound() {
+        let reference = Ssimulacra2Reference::new(textured(64, 64, 1.0)).unwrap();
+        let detailed = reference.compare_detailed(textured(64, 64, 0.9)).unwrap();
+
+        assert_eq!(detailed.error_bound, 0.0);
+        assert_eq!(detailed.scales.len(), reference.num_scales());
+        assert_eq!(
+            detailed.score,
+            reference.compare(textured(64, 64, 0.9)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_quantized_reference_within_error_bound() {
+        let full = Ssimulacra2Reference::new(textured(96, 80, 1.0)).unwrap();
+        let quantized = Ssimulacra2Reference::with_precision(
+            textured(96, 80, 1.0),
+            ReferencePrecision::Quantized16,
+        )
+        .unwrap();
+
+        assert_eq!(quantized.precision(), ReferencePrecision::Quantized16);
+        assert_eq!(quantized.storage_bytes() * 2, full.storage_bytes());
+
+        for scale in [0.7, 0.9, 0.98] {
+            let expected = full.compare(textured(96, 80, scale)).unwrap();
+            let detailed = quantized.compare_detailed(textured(96, 80, scale)).unwrap();
+            let actual_error = (detailed.score - expected).abs();
+            assert!(detailed.error_bound > 0.0);
+            assert!(
+                actual_error <= detailed.error_bound,
+                "error {} exceeds bound {}",
+                actual_error,
+                detailed.error_bound
+            );
+            assert!(
+                detailed.error_bound < 5.0,
+                "bound {} too loose",
+                detailed.error_bound
+            );
+        }
+    }
+
+    #[test]
+    fn test_f16_conversion() {
+        for v in [
+            0.0f32,
+            1.0,
+            -2.5,
+            0.333_251_95,
+            65504.0,
+            6.103_515_6e-5,
+            5.960_464_5e-8,
+        ] {
+            assert_eq!(f16_to_f32(f32_to_f16(v)), v, "{}", v);
+        }
+        // Ties round to even, overflow saturates to infinity
+        assert_eq!(f32_to_f16(1.0 + 1.0 / 2048.0), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 / 2048.0), 0x3c02);
+        assert_eq!(f32_to_f16(1e6), 0x7c00);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+        assert_eq!(f32_to_f16(1e-9), 0);
+    }
+
+    #[test]
+    fn test_serialized_reference_round_trips() {
+        let source = textured(80, 72, 1.0).with_colorimetry(Colorimetry::default());
+        for precision in [
+            ReferencePrecision::Full,
+            ReferencePrecision::Quantized16,
+            ReferencePrecision::Half,
+        ] {
+            let reference = Ssimulacra2Reference::with_precision(&source, precision).unwrap();
+            let bytes = reference.to_bytes();
+            // 26 bytes for the magic, version, size, precision, colorimetry
+            // and scale count, then the per-channel constants of each stored
+            // set of planes: offset, step and error when quantized, the
+            // offset of the two half-precision sets
+            let plane_headers = match precision {
+                ReferencePrecision::Full => 0,
+                ReferencePrecision::Quantized16 => 3 * 36,
+                ReferencePrecision::Half => 2 * 12,
+            };
+            assert_eq!(
+                bytes.len(),
+                26 + reference.storage_bytes() + plane_headers * reference.num_scales()
+            );
+
+            let loaded = Ssimulacra2Reference::from_bytes(&bytes).unwrap();
+            assert_eq!(loaded.precision(), precision);
+            assert_eq!(loaded.colorimetry(), reference.colorimetry());
+            assert_eq!(loaded.num_scales(), reference.num_scales());
+            assert_eq!(loaded.to_bytes(), bytes);
+            let distorted = textured(80, 72, 0.9);
+            assert_eq!(
+                loaded.compare_detailed(&distorted).unwrap(),
+                reference.compare_detailed(&distorted).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_half_reference_within_error_bound() {
+        let full = Ssimulacra2Reference::new(textured(96, 80, 1.0)).unwrap();
+        let half =
+            Ssimulacra2Reference::with_precision(textured(96, 80, 1.0), ReferencePrecision::Half)
+                .unwrap();
+        assert_eq!(half.storage_bytes() * 3, full.storage_bytes() * 2);
+
+        let expected = full.compare(textured(96, 80, 0.9)).unwrap();
+        let detailed = half.compare_detailed(textured(96, 80, 0.9)).unwrap();
+        assert!((detailed.score - expected).abs() <= detailed.error_bound);
+    }
+
+    #[test]
+    fn test_half_reference_on_low_contrast() {
+        // Within 1% of mid-gray, where the raw second moments are about 1000
+        // times the variances
+        let low_contrast = |phase: f32| {
+            let data = (0..96 * 80)
+                .map(|i| {
+                    let (x, y) = ((i % 96) as f32, (i / 96) as f32);
+                    let v = 0.45 + 0.005 * (x * 0.3 + phase).sin() * (y * 0.2).cos();
+                    [v, v * 0.98, v * 1.02]
+                })
+                .collect();
+            LinearRgbImage::new(data, 96, 80)
+        };
+        let full = Ssimulacra2Reference::new(low_contrast(0.0)).unwrap();
+        let half =
+            Ssimulacra2Reference::with_precision(low_contrast(0.0), ReferencePrecision::Half)
+                .unwrap();
+
+        for phase in [0.2, 1.0] {
+            let expected = full.compare(low_contrast(phase)).unwrap();
+            let detailed = half.compare_detailed(low_contrast(phase)).unwrap();
+            let actual_error = (detailed.score - expected).abs();
+            assert!(
+                actual_error <= detailed.error_bound,
+                "error {} exceeds bound {}",
+                actual_error,
+                detailed.error_bound
+            );
+            assert!(
+                detailed.error_bound < 5.0,
+                "bound {} too loose",
+                detailed.error_bound
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_serialized_reference() {
+        let bytes = Ssimulacra2Reference::new(textured(64, 64, 1.0))
+            .unwrap()
+            .to_bytes();
+        let invalid = Err(Ssimulacra2Error::InvalidReferenceData);
+
+        assert_eq!(Ssimulacra2Reference::from_bytes(&[]).map(|_| ()), invalid);
+        assert_eq!(
+            Ssimulacra2Reference::from_bytes(&bytes[..bytes.len() - 1]).map(|_| ()),
+            invalid
+        );
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert_eq!(
+            Ssimulacra2Reference::from_bytes(&extended).map(|_| ()),
+            invalid
+        );
+        let mut wrong_version = bytes.clone();
+        wrong_version[8] = 99;
+        assert_eq!(
+            Ssimulacra2Reference::from_bytes(&wrong_version).map(|_| ()),
+            invalid
+        );
+    }
+
+    #[test]
+    fn test_compare_batch_matches_compare() {
+        let reference = Ssimulacra2Reference::new(textured(72, 64, 1.0)).unwrap();
+        let scales = [0.6, 0.8, 0.9, 0.95, 0.99, 1.0];
+        let distorted: Vec<LinearRgbImage> = scales.iter().map(|&s| textured(72, 64, s)).collect();
+
+        let batch = reference.compare_batch(&distorted);
+        assert_eq!(batch.len(), scales.len());
+        for (image, result) in distorted.iter().zip(&batch) {
+            assert_eq!(*result, reference.compare(image));
+        }
+
+        // Errors stay with their image and don't affect the others
+        let mixed = [textured(72, 64, 0.9), textured(64, 64, 0.9)];
+        let batch = reference.compare_batch(&mixed);
+        assert_eq!(batch[0], reference.compare(&mixed[0]));
+        assert_eq!(batch[1], Err(Ssimulacra2Error::NonMatchingImageDimensions));
+        assert!(reference.compare_batch::<LinearRgbImage>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_incremental_compare_tracks_full_compare() {
+        let (width, height) = (96, 80);
+        let reference = Ssimulacra2Reference::new(textured(width, height, 1.0)).unwrap();
+        let mut cache = CandidateCache::new();
+
+        let mut candidate = textured(width, height, 0.9);
+        let full = reference
+            .compare_incremental(&candidate, &mut cache, None)
+            .unwrap();
+        assert_eq!(full, reference.compare(&candidate).unwrap());
+
+        // Brighten a patch, as a re-encode that only touched one area might
+        let patch = Region::new(20, 30, 24, 10);
+        for y in patch.y..patch.y + patch.height {
+            for pixel in &mut candidate.data_mut()[y * width + patch.x..][..patch.width] {
+                *pixel = pixel.map(|v| v * 1.2);
+            }
+        }
+        let incremental = reference
+            .compare_incremental(&candidate, &mut cache, Some(&[patch]))
+            .unwrap();
+        let expected = reference.compare(&candidate).unwrap();
+        assert_ne!(incremental, full);
+        // Blurred moments differ by about 1e-6, which moves this score by 0.006
+        assert!(
+            (incremental - expected).abs() < 1e-2,
+            "incremental {} vs full {}",
+            incremental,
+            expected
+        );
+
+        // Nothing changed since the last comparison
+        let unchanged = reference
+            .compare_incremental(&candidate, &mut cache, Some(&[]))
+            .unwrap();
+        assert_eq!(unchanged, incremental);
+
+        assert_eq!(
+            reference.compare_incremental(
+                &candidate,
+                &mut cache,
+                Some(&[Region::new(90, 0, 8, 8)])
+            ),
+            Err(Ssimulacra2Error::InvalidRegion)
+        );
+    }
 }
@@ -0,0 +1,213 @@
+//! Python bindings (requires the `python` feature).
+//!
+//! Builds the `pyssimulacra2` extension module with
+//! [maturin](https://www.maturin.rs), using `pyproject.toml` next to this
+//! crate's `Cargo.toml`:
+//!
+//! ```text
+//! pip install maturin
+//! maturin build --release -m ssimulacra2/pyproject.toml
+//! ```
+//!
+//! Images are numpy arrays of shape `(height, width, 3)`: `uint8` and
+//! `uint16` arrays are sRGB, `float32` arrays are linear RGB. Any memory
+//! layout is accepted. Pixels are copied while holding the GIL, which is
+//! then released for the comparison, so Python threads can score several
+//! pairs at once.
+//!
+//! ```python
+//! import pyssimulacra2
+//!
+//! score = pyssimulacra2.compute(original, compressed)
+//! reference = pyssimulacra2.Reference(original)
+//! scores = [reference.compare(candidate) for candidate in candidates]
+//! ```
+
+// pyo3 0.22's function wrappers convert every returned `PyResult` into a
+// `PyResult`, which clippy flags at each signature
+#![allow(clippy::useless_conversion)]
+
+use numpy::{Element, PyReadonlyArray3};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::{
+    compute_ssimulacra2_with_config, EncodedRgb, FallbackChain, LinearRgbImage, Ssimulacra2Config,
+    Ssimulacra2Error, Ssimulacra2Reference, ToLinearRgb, TransferFunction,
+};
+
+fn value_error(e: Ssimulacra2Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// Copies a `(height, width, 3)` array into packed pixels
+fn pixels<T: Element + Copy>(
+    array: &PyReadonlyArray3<'_, T>,
+) -> PyResult<(Vec<[T; 3]>, usize, usize)> {
+    let view = array.as_array();
+    let (height, width, channels) = view.dim();
+    if channels != 3 {
+        return Err(PyValueError::new_err(
+            "expected an array of shape (height, width, 3)",
+        ));
+    }
+    // Iterates in logical order whatever the strides
+    let samples: Vec<T> = view.iter().copied().collect();
+    let pixels = samples
+        .chunks_exact(3)
+        .map(|px| [px[0], px[1], px[2]])
+        .collect();
+    Ok((pixels, width, height))
+}
+
+/// Converts a numpy image to linear RGB
+fn image(array: &Bound<'_, PyAny>) -> PyResult<LinearRgbImage> {
+    if let Ok(array) = array.extract::<PyReadonlyArray3<'_, u8>>() {
+        let (pixels, width, height) = pixels(&array)?;
+        let encoded = EncodedRgb::new(&pixels, width, height, TransferFunction::Srgb);
+        Ok(encoded.map_err(value_error)?.to_linear_rgb())
+    } else if let Ok(array) = array.extract::<PyReadonlyArray3<'_, u16>>() {
+        let (pixels, width, height) = pixels(&array)?;
+        let encoded = EncodedRgb::new(&pixels, width, height, TransferFunction::Srgb);
+        Ok(encoded.map_err(value_error)?.to_linear_rgb())
+    } else if let Ok(array) = array.extract::<PyReadonlyArray3<'_, f32>>() {
+        let (pixels, width, height) = pixels(&array)?;
+        Ok(LinearRgbImage::new(pixels, width, height))
+    } else {
+        Err(PyTypeError::new_err(
+            "expected a 3-dimensional uint8, uint16 or float32 numpy array",
+        ))
+    }
+}
+
+/// `"auto"` or a backend name as accepted by [`FallbackChain::parse`]
+fn config(backend: &str) -> PyResult<Ssimulacra2Config> {
+    if backend == "auto" {
+        return Ok(Ssimulacra2Config::auto());
+    }
+    let chain = FallbackChain::parse(backend).map_err(value_error)?;
+    Ok(Ssimulacra2Config::default().with_fallback_chain(chain))
+}
+
+/// Computes the SSIMULACRA2 score of `dst` against `src`.
+///
+/// `backend` is "auto" (the fastest backend the CPU supports), "scalar",
+/// "simd" or "unsafe-simd".
+#[pyfunction]
+#[pyo3(signature = (src, dst, backend = "auto"))]
+fn compute(
+    py: Python<'_>,
+    src: &Bound<'_, PyAny>,
+    dst: &Bound<'_, PyAny>,
+    backend: &str,
+) -> PyResult<f64> {
+    let config = config(backend)?;
+    let (source, distorted) = (image(src)?, image(dst)?);
+    py.allow_threads(|| compute_ssimulacra2_with_config(&source, &distorted, config))
+        .map_err(value_error)
+}
+
+/// A source image prepared for repeated comparisons, e.g. against every
+/// quality setting of an encoder.
+#[pyclass(name = "Reference", frozen)]
+struct Reference(Ssimulacra2Reference);
+
+#[pymethods]
+impl Reference {
+    /// Precomputes the reference-side data of `src`.
+    #[new]
+    fn new(py: Python<'_>, src: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let source = image(src)?;
+        let reference = py.allow_threads(|| Ssimulacra2Reference::new(&source));
+        Ok(Self(reference.map_err(value_error)?))
+    }
+
+    /// Computes the SSIMULACRA2 score of `dst` against the reference.
+    fn compare(&self, py: Python<'_>, dst: &Bound<'_, PyAny>) -> PyResult<f64> {
+        let distorted = image(dst)?;
+        py.allow_threads(|| self.0.compare(&distorted))
+            .map_err(value_error)
+    }
+
+    /// Width of the reference image
+    #[getter]
+    fn width(&self) -> usize {
+        self.0.width()
+    }
+
+    /// Height of the reference image
+    #[getter]
+    fn height(&self) -> usize {
+        self.0.height()
+    }
+}
+
+#[pymodule]
+fn pyssimulacra2(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(compute, m)?)?;
+    m.add_class::<Reference>()?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accuracy;
+    use numpy::PyArray3;
+
+    /// A `(height, width, 3)` sRGB gradient
+    fn gradient(width: usize, height: usize) -> Vec<Vec<Vec<u8>>> {
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        (0..3)
+                            .map(|c| ((x * 7 + y * 3 + c * 50) % 256) as u8)
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_unknown_backend_is_a_value_error() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let none = py.None().into_bound(py);
+            let err = compute(py, &none, &none, "avx9000").unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py), "{err}");
+        });
+    }
+
+    // Needs numpy in the embedded interpreter
+    #[test]
+    fn test_compute_and_reference_agree() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut pixels = gradient(32, 24);
+            let source = PyArray3::from_vec3_bound(py, &pixels).unwrap();
+            pixels[5][7][1] ^= 0x40;
+            let distorted = PyArray3::from_vec3_bound(py, &pixels).unwrap();
+
+            let identical = compute(py, source.as_any(), source.as_any(), "scalar").unwrap();
+            assert_eq!(identical, 100.0);
+
+            // "auto" may pick another backend than the reference's default
+            let score = compute(py, source.as_any(), distorted.as_any(), "simd").unwrap();
+            let reference = Reference::new(py, source.as_any()).unwrap();
+            assert_eq!((reference.width(), reference.height()), (32, 24));
+            let compared = reference.compare(py, distorted.as_any()).unwrap();
+            assert!(
+                accuracy::backends_agree(compared, score),
+                "{compared} vs {score}"
+            );
+            assert!(score < 100.0);
+
+            let gray = PyArray3::<u8>::zeros_bound(py, [24, 32, 1], false);
+            let err = compute(py, source.as_any(), gray.as_any(), "auto").unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py), "{err}");
+        });
+    }
+}
@@ -0,0 +1,404 @@
+//! Queued frame-pair scoring with sequence-numbered results.
+//!
+//! [`FrameQueue`] hands each pushed source/distorted pair a sequence number
+//! and, with the `rayon` feature, scores it on rayon's global thread pool
+//! while the caller keeps decoding. Pairs finish in whatever order the pool
+//! gets to them; [`drain`](FrameQueue::drain) collects the finished results
+//! and, by default, releases them in sequence order, holding back results
+//! that finished ahead of an earlier frame. That is the order per-frame
+//! scores are muxed back into a video timeline in, so callers don't need a
+//! reorder buffer of their own. [`DeliveryOrder::Completion`] skips the
+//! reordering for callers that key results by sequence number themselves.
+//!
+//! Without threads, pairs are scored on the calling thread as they are
+//! pushed, and results come out in sequence order either way.
+
+use std::collections::{BTreeMap, VecDeque};
+#[cfg(feature = "rayon")]
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "rayon")]
+use crate::parallelism::THREADS_AVAILABLE;
+use crate::{
+    compute_ssimulacra2_into, LinearRgbImage, Ssimulacra2Config, Ssimulacra2Error,
+    Ssimulacra2Scratch, ToLinearRgb,
+};
+
+/// Order in which a [`FrameQueue`] delivers results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeliveryOrder {
+    /// In the order the pairs were pushed; a result is held back until all
+    /// earlier ones have been delivered (default)
+    #[default]
+    Sequence,
+    /// As soon as each pair finishes
+    Completion,
+}
+
+/// Result of one queued frame pair.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueuedScore {
+    /// Sequence number returned by [`FrameQueue::push`], counting from 0
+    pub sequence: u64,
+    /// The score, or why the pair couldn't be compared
+    pub score: Result<f64, Ssimulacra2Error>,
+}
+
+/// Scores frame pairs in the background and delivers sequence-numbered
+/// results.
+///
+/// ```
+/// use fast_ssim2::{FrameQueue, LinearRgbImage};
+///
+/// let frame = |v: f32| LinearRgbImage::new(vec![[v; 3]; 16 * 16], 16, 16);
+/// let mut queue = FrameQueue::new();
+/// let mut results = Vec::new();
+/// for v in [0.2, 0.4, 0.6] {
+///     queue.push(frame(v), frame(v));
+///     results.extend(queue.drain());
+/// }
+/// results.extend(queue.finish());
+/// let sequences: Vec<u64> = results.iter().map(|r| r.sequence).collect();
+/// assert_eq!(sequences, [0, 1, 2]);
+/// assert!(results.iter().all(|r| r.score == Ok(100.0)));
+/// ```
+pub struct FrameQueue {
+    config: Ssimulacra2Config,
+    order: DeliveryOrder,
+    max_in_flight: usize,
+    next_sequence: u64,
+    /// Next sequence number to deliver in [`DeliveryOrder::Sequence`]
+    next_delivery: u64,
+    /// Pairs pushed but not yet received back from the pool
+    in_flight: usize,
+    /// Received results in completion order
+    completed: VecDeque<QueuedScore>,
+    /// Received results waiting for an earlier sequence number
+    reorder: BTreeMap<u64, Result<f64, Ssimulacra2Error>>,
+    /// Idle scratch buffers, shared with the pool's tasks
+    scratch: Arc<Mutex<Vec<Ssimulacra2Scratch>>>,
+    #[cfg(feature = "rayon")]
+    sender: Sender<QueuedScore>,
+    #[cfg(feature = "rayon")]
+    receiver: Receiver<QueuedScore>,
+}
+
+impl Default for FrameQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameQueue {
+    /// Creates a queue using the default configuration.
+    pub fn new() -> Self {
+        Self::with_config(Ssimulacra2Config::default())
+    }
+
+    /// Creates a queue comparing frames with `config`.
+    ///
+    /// At most as many pairs as rayon has threads are scored at once.
+    pub fn with_config(config: Ssimulacra2Config) -> Self {
+        #[cfg(feature = "rayon")]
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            config,
+            order: DeliveryOrder::default(),
+            max_in_flight: default_max_in_flight(),
+            next_sequence: 0,
+            next_delivery: 0,
+            in_flight: 0,
+            completed: VecDeque::new(),
+            reorder: BTreeMap::new(),
+            scratch: Arc::new(Mutex::new(Vec::new())),
+            #[cfg(feature = "rayon")]
+            sender,
+            #[cfg(feature = "rayon")]
+            receiver,
+        }
+    }
+
+    /// Sets the order results are delivered in.
+    #[must_use]
+    pub fn with_delivery_order(mut self, order: DeliveryOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Limits how many pairs are scored at once. [`push`](Self::push)
+    /// blocks while the limit is reached, so this bounds the converted
+    /// frames and scratch buffers held in memory. Values below 1 are
+    /// treated as 1.
+    #[must_use]
+    pub fn with_max_in_flight(mut self, pairs: usize) -> Self {
+        self.max_in_flight = pairs.max(1);
+        self
+    }
+
+    /// The configuration frames are compared with
+    pub fn config(&self) -> &Ssimulacra2Config {
+        &self.config
+    }
+
+    /// The order results are delivered in
+    pub fn delivery_order(&self) -> DeliveryOrder {
+        self.order
+    }
+
+    /// Most pairs scored at once
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// Queues the next source/distorted pair and returns its sequence
+    /// number.
+    ///
+    /// Both images are converted to linear RGB on the calling thread before
+    /// the pair is handed to the pool. Comparison errors, such as differing
+    /// dimensions, are delivered with the pair's result. Blocks while
+    /// [`max_in_flight`](Self::max_in_flight) pairs are being scored, so
+    /// don't push from a task running on rayon's global pool.
+    pub fn push<S, D>(&mut self, source: S, distorted: D) -> u64
+    where
+        S: ToLinearRgb,
+        D: ToLinearRgb,
+    {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        let source = source.to_linear_rgb();
+        let distorted = distorted.to_linear_rgb();
+
+        #[cfg(feature = "rayon")]
+        if THREADS_AVAILABLE {
+            while self.in_flight >= self.max_in_flight {
+                self.receive(true);
+            }
+            let (config, scratch, sender) = (
+                self.config.clone(),
+                Arc::clone(&self.scratch),
+                self.sender.clone(),
+            );
+            rayon::spawn(move || {
+                let score = score_pair(config, &scratch, &source, &distorted);
+                // The queue may have been dropped; its results are unwanted
+                let _ = sender.send(QueuedScore { sequence, score });
+            });
+            self.in_flight += 1;
+            return sequence;
+        }
+
+        let score = score_pair(self.config.clone(), &self.scratch, &source, &distorted);
+        self.completed.push_back(QueuedScore { sequence, score });
+        sequence
+    }
+
+    /// Number of pairs pushed so far
+    pub fn pushed(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// Number of pairs pushed but not yet delivered
+    pub fn pending(&self) -> usize {
+        self.in_flight + self.completed.len() + self.reorder.len()
+    }
+
+    /// Returns the results that can be delivered now, without waiting.
+    ///
+    /// In [`DeliveryOrder::Sequence`] this is the run of finished results
+    /// following the last delivered one; later results that finished early
+    /// are kept until the gap is filled.
+    pub fn drain(&mut self) -> Vec<QueuedScore> {
+        while self.in_flight > 0 && self.receive(false) {}
+        self.deliver()
+    }
+
+    /// Waits for every queued pair and returns the remaining results.
+    pub fn finish(mut self) -> Vec<QueuedScore> {
+        while self.in_flight > 0 {
+            self.receive(true);
+        }
+        self.deliver()
+    }
+
+    /// Moves one result from the pool into `completed`, waiting for it if
+    /// `block` is set. Returns `false` if none was ready.
+    #[cfg(feature = "rayon")]
+    fn receive(&mut self, block: bool) -> bool {
+        let result = if block {
+            // The queue holds a sender, so the channel never disconnects
+            self.receiver.recv().ok()
+        } else {
+            self.receiver.try_recv().ok()
+        };
+        match result {
+            Some(result) => {
+                self.in_flight -= 1;
+                self.completed.push_back(result);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn receive(&mut self, _block: bool) -> bool {
+        // Pairs are only sent to the pool with rayon
+        debug_assert_eq!(self.in_flight, 0);
+        false
+    }
+
+    fn deliver(&mut self) -> Vec<QueuedScore> {
+        match self.order {
+            DeliveryOrder::Completion => self.completed.drain(..).collect(),
+            DeliveryOrder::Sequence => {
+                for result in self.completed.drain(..) {
+                    self.reorder.insert(result.sequence, result.score);
+                }
+                let mut delivered = Vec::new();
+                while let Some(score) = self.reorder.remove(&self.next_delivery) {
+                    delivered.push(QueuedScore {
+                        sequence: self.next_delivery,
+                        score,
+                    });
+                    self.next_delivery += 1;
+                }
+                delivered
+            }
+        }
+    }
+}
+
+fn default_max_in_flight() -> usize {
+    #[cfg(feature = "rayon")]
+    if THREADS_AVAILABLE {
+        return rayon::current_num_threads();
+    }
+    1
+}
+
+/// Scores one pair with an idle scratch buffer, creating one if all are in
+/// use.
+fn score_pair(
+    config: Ssimulacra2Config,
+    scratch: &Mutex<Vec<Ssimulacra2Scratch>>,
+    source: &LinearRgbImage,
+    distorted: &LinearRgbImage,
+) -> Result<f64, Ssimulacra2Error> {
+    let idle = scratch.lock().unwrap_or_else(|e| e.into_inner()).pop();
+    let mut buffers = idle.unwrap_or_else(|| Ssimulacra2Scratch::with_config(config));
+    let score = compute_ssimulacra2_into(&mut buffers, source, distorted);
+    scratch
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(buffers);
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_ssimulacra2_with_config;
+    use crate::test_util::frame;
+
+    /// Frame pairs of alternating size, so that large and small pairs
+    /// finish out of order on the pool
+    fn pairs() -> Vec<(LinearRgbImage, LinearRgbImage)> {
+        (0..12)
+            .map(|i| {
+                let width = if i % 3 == 0 { 256 } else { 16 };
+                let noise = 0.02 * (i % 5) as f32;
+                (frame(width, i, 0.0), frame(width, i, noise))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sequence_order_matches_single_comparisons() {
+        let config = Ssimulacra2Config::default();
+        let expected: Vec<f64> = pairs()
+            .iter()
+            .map(|(s, d)| compute_ssimulacra2_with_config(s, d, config.clone()).unwrap())
+            .collect();
+
+        for max_in_flight in [1, 3, 16] {
+            let mut queue =
+                FrameQueue::with_config(config.clone()).with_max_in_flight(max_in_flight);
+            let mut results = Vec::new();
+            for (i, (source, distorted)) in pairs().into_iter().enumerate() {
+                assert_eq!(queue.push(source, distorted), i as u64);
+                results.extend(queue.drain());
+            }
+            assert_eq!(queue.pushed(), 12);
+            results.extend(queue.finish());
+
+            let sequences: Vec<u64> = results.iter().map(|r| r.sequence).collect();
+            assert_eq!(sequences, (0..12).collect::<Vec<_>>());
+            let scores: Vec<f64> = results.iter().map(|r| r.score.unwrap()).collect();
+            assert_eq!(scores, expected, "{} in flight", max_in_flight);
+        }
+    }
+
+    #[test]
+    fn test_completion_order_delivers_every_pair() {
+        let mut queue = FrameQueue::new()
+            .with_delivery_order(DeliveryOrder::Completion)
+            .with_max_in_flight(0);
+        assert_eq!(queue.delivery_order(), DeliveryOrder::Completion);
+        assert_eq!(queue.max_in_flight(), 1);
+        let mut results = Vec::new();
+        for (source, distorted) in pairs() {
+            queue.push(source, distorted);
+            results.extend(queue.drain());
+        }
+        results.extend(queue.finish());
+
+        let mut sequences: Vec<u64> = results.iter().map(|r| r.sequence).collect();
+        sequences.sort_unstable();
+        assert_eq!(sequences, (0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_errors_keep_their_sequence() {
+        let mut queue = FrameQueue::new();
+        queue.push(frame(16, 0, 0.0), frame(16, 0, 0.1));
+        queue.push(frame(16, 1, 0.0), frame(32, 1, 0.0));
+        queue.push(frame(16, 2, 0.0), frame(16, 2, 0.0));
+        assert!(queue.pending() <= 3);
+        let results = queue.finish();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].score.is_ok());
+        assert_eq!(
+            results[1],
+            QueuedScore {
+                sequence: 1,
+                score: Err(Ssimulacra2Error::NonMatchingImageDimensions),
+            }
+        );
+        assert_eq!(results[2].score, Ok(100.0));
+    }
+
+    #[test]
+    fn test_reorder_holds_back_later_results() {
+        let mut queue = FrameQueue::new();
+        queue.next_sequence = 3;
+        for sequence in [2, 0] {
+            queue.completed.push_back(QueuedScore {
+                sequence,
+                score: Ok(sequence as f64),
+            });
+        }
+        let delivered = queue.drain();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].sequence, 0);
+        assert_eq!(queue.pending(), 1);
+
+        queue.completed.push_back(QueuedScore {
+            sequence: 1,
+            score: Ok(1.0),
+        });
+        let sequences: Vec<u64> = queue.drain().iter().map(|r| r.sequence).collect();
+        assert_eq!(sequences, [1, 2]);
+        assert_eq!(queue.pending(), 0);
+    }
+}
@@ -0,0 +1,276 @@
+//! Resampling to arbitrary sizes, for scoring a distorted image whose size
+//! differs from the source's.
+//!
+//! Scoring a 1080p encode of a 4K master means bringing one of them to the
+//! other's size first, and the resampler then becomes part of the score.
+//! [`resample`] filters in linear light, where averaging matches how light
+//! mixes (resizing sRGB-encoded values darkens fine detail), and widens its
+//! kernel when downscaling so detail above the new Nyquist limit is filtered
+//! out rather than aliased into the result. Both images of every comparison
+//! should go through the same filter; the CLI's `--resample` and
+//! [`Ssimulacra2Config::resample_mismatched`](crate::Ssimulacra2Config::resample_mismatched)
+//! use this implementation.
+//!
+//! Unlike [`PreResize`](crate::PreResize), which reproduces the C++ tool's
+//! box filter, this doesn't match any other tool's pixels exactly.
+
+use std::f64::consts::PI;
+
+use crate::{LinearRgbImage, Ssimulacra2Error};
+
+/// Interpolation filter for [`resample`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleFilter {
+    /// Windowed sinc with three lobes: sharpest, with slight ringing at hard
+    /// edges (default)
+    #[default]
+    Lanczos3,
+    /// Catmull-Rom cubic spline: a little softer, with less ringing
+    CatmullRom,
+}
+
+impl ResampleFilter {
+    /// Name as accepted by [`parse`](Self::parse)
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lanczos3 => "lanczos3",
+            Self::CatmullRom => "catmull-rom",
+        }
+    }
+
+    /// Parses `lanczos3` or `catmull-rom`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "lanczos3" => Some(Self::Lanczos3),
+            "catmull-rom" => Some(Self::CatmullRom),
+            _ => None,
+        }
+    }
+
+    /// Distance from the center at which the kernel reaches zero
+    fn radius(self) -> f64 {
+        match self {
+            Self::Lanczos3 => 3.0,
+            Self::CatmullRom => 2.0,
+        }
+    }
+
+    fn kernel(self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            Self::Lanczos3 => {
+                if x < 1e-8 {
+                    1.0
+                } else if x < 3.0 {
+                    let px = PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+            Self::CatmullRom => {
+                if x < 1.0 {
+                    (1.5 * x - 2.5) * x * x + 1.0
+                } else if x < 2.0 {
+                    ((-0.5 * x + 2.5) * x - 4.0) * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Source samples and weights of one output sample along one axis
+struct Taps {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// Taps of every output sample when resampling `input` samples to `output`.
+/// Samples past the edges repeat the edge sample, which is folded into the
+/// edge weight.
+fn axis_taps(input: usize, output: usize, filter: ResampleFilter) -> Vec<Taps> {
+    let scale = input as f64 / output as f64;
+    // Downscaling stretches the kernel over the input to low-pass it
+    let stretch = scale.max(1.0);
+    let support = filter.radius() * stretch;
+    (0..output)
+        .map(|o| {
+            let center = (o as f64 + 0.5) * scale - 0.5;
+            let first = (center - support).floor() as isize + 1;
+            let last = (center + support).ceil() as isize - 1;
+            let start = first.clamp(0, input as isize - 1) as usize;
+            let end = last.clamp(0, input as isize - 1) as usize;
+            let mut weights = vec![0.0f64; end - start + 1];
+            for i in first..=last {
+                let w = filter.kernel((i as f64 - center) / stretch);
+                let clamped = i.clamp(start as isize, end as isize) as usize;
+                weights[clamped - start] += w;
+            }
+            let sum: f64 = weights.iter().sum();
+            Taps {
+                start,
+                weights: weights.iter().map(|w| (w / sum) as f32).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Resamples `width * height` pixels of `N` channels to `out_w * out_h`,
+/// horizontally then vertically.
+fn resample_pixels<const N: usize>(
+    data: &[[f32; N]],
+    width: usize,
+    height: usize,
+    out_w: usize,
+    out_h: usize,
+    filter: ResampleFilter,
+) -> Vec<[f32; N]> {
+    let columns = axis_taps(width, out_w, filter);
+    let mut horizontal = Vec::with_capacity(out_w * height);
+    for row in data.chunks_exact(width) {
+        for taps in &columns {
+            let mut sum = [0.0f32; N];
+            for (px, &w) in row[taps.start..].iter().zip(&taps.weights) {
+                for c in 0..N {
+                    sum[c] += px[c] * w;
+                }
+            }
+            horizontal.push(sum);
+        }
+    }
+
+    let rows = axis_taps(height, out_h, filter);
+    let mut out = Vec::with_capacity(out_w * out_h);
+    for taps in &rows {
+        for x in 0..out_w {
+            let mut sum = [0.0f32; N];
+            for (dy, &w) in taps.weights.iter().enumerate() {
+                let px = horizontal[(taps.start + dy) * out_w + x];
+                for c in 0..N {
+                    sum[c] += px[c] * w;
+                }
+            }
+            out.push(sum);
+        }
+    }
+    out
+}
+
+/// Resamples `image` to `width` × `height` in linear light.
+///
+/// Lobes of negative weight can overshoot below black near hard edges;
+/// samples are clamped to be non-negative, and alpha to 0-1. Alpha is
+/// resampled with the same filter and the colorimetry tag is kept. Resampling
+/// to the image's own size returns a copy.
+///
+/// # Errors
+/// - If the image or the requested size is empty
+pub fn resample(
+    image: &LinearRgbImage,
+    width: usize,
+    height: usize,
+    filter: ResampleFilter,
+) -> Result<LinearRgbImage, Ssimulacra2Error> {
+    let (in_w, in_h) = (image.width(), image.height());
+    if in_w == 0 || in_h == 0 || width == 0 || height == 0 {
+        return Err(Ssimulacra2Error::InvalidImageSize);
+    }
+    if (in_w, in_h) == (width, height) {
+        return Ok(image.clone());
+    }
+
+    let data = resample_pixels(image.data(), in_w, in_h, width, height, filter)
+        .into_iter()
+        .map(|px| px.map(|v| v.max(0.0)))
+        .collect();
+    let mut out = LinearRgbImage::new(data, width, height);
+    out.colorimetry = image.colorimetry();
+    if let Some(alpha) = image.alpha() {
+        let alpha: Vec<[f32; 1]> = alpha.iter().map(|&a| [a]).collect();
+        let alpha = resample_pixels(&alpha, in_w, in_h, width, height, filter);
+        out.alpha = Some(alpha.into_iter().map(|[a]| a.clamp(0.0, 1.0)).collect());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_ssimulacra2_with_config, Ssimulacra2Config};
+
+    fn gradient(width: usize, height: usize) -> LinearRgbImage {
+        let data = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                [x / width as f32, y / height as f32, 0.25]
+            })
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    }
+
+    #[test]
+    fn test_flat_images_stay_flat() {
+        let flat = LinearRgbImage::new(vec![[0.2, 0.5, 0.8]; 40 * 30], 40, 30)
+            .with_alpha(vec![0.5; 40 * 30])
+            .unwrap();
+        for filter in [ResampleFilter::Lanczos3, ResampleFilter::CatmullRom] {
+            for (w, h) in [(17, 11), (40, 30), (97, 64)] {
+                let out = resample(&flat, w, h, filter).unwrap();
+                assert_eq!((out.width(), out.height()), (w, h));
+                for px in out.data() {
+                    for (v, e) in px.iter().zip([0.2, 0.5, 0.8]) {
+                        assert!((v - e).abs() < 1e-5, "{:?} {:?}", filter, px);
+                    }
+                }
+                assert!(out.alpha().unwrap().iter().all(|a| (a - 0.5).abs() < 1e-5));
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_keeps_smooth_content() {
+        let image = gradient(64, 48);
+        for filter in [ResampleFilter::Lanczos3, ResampleFilter::CatmullRom] {
+            let up = resample(&image, 128, 96, filter).unwrap();
+            let back = resample(&up, 64, 48, filter).unwrap();
+            // Away from the edges, where the clamped border bends the ramp
+            for y in 4..44 {
+                for x in 4..60 {
+                    let (a, b) = (image.data()[y * 64 + x], back.data()[y * 64 + x]);
+                    assert!((a[0] - b[0]).abs() < 2e-3, "{:?} at {},{}", filter, x, y);
+                    assert!((a[1] - b[1]).abs() < 2e-3, "{:?} at {},{}", filter, x, y);
+                }
+            }
+        }
+        assert_eq!(
+            resample(&image, 0, 10, ResampleFilter::default()).map(|_| ()),
+            Err(Ssimulacra2Error::InvalidImageSize)
+        );
+        assert_eq!(
+            ResampleFilter::parse("catmull-rom"),
+            Some(ResampleFilter::CatmullRom)
+        );
+    }
+
+    #[test]
+    fn test_mismatched_sizes_are_resampled_when_enabled() {
+        let (source, half) = (gradient(64, 48), gradient(32, 24));
+        let config = Ssimulacra2Config::default();
+        assert_eq!(
+            compute_ssimulacra2_with_config(&source, &half, config.clone()),
+            Err(Ssimulacra2Error::NonMatchingImageDimensions)
+        );
+
+        let filter = ResampleFilter::CatmullRom;
+        let upscaled = resample(&half, 64, 48, filter).unwrap();
+        let expected = compute_ssimulacra2_with_config(&source, &upscaled, config.clone());
+        let config = config.with_resample_mismatched(filter);
+        assert_eq!(
+            compute_ssimulacra2_with_config(&source, &half, config),
+            expected
+        );
+        assert!(expected.unwrap() > 50.0);
+    }
+}
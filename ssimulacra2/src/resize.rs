@@ -0,0 +1,145 @@
+//! Optional pre-resize stage matching the reference C++ tool.
+//!
+//! Scores from different tools only line up if they compare the same pixels.
+//! Pipelines built around the C++ `ssimulacra2` tool sometimes shrink both
+//! images before scoring (e.g. to compare at display size); [`PreResize`]
+//! applies the same box filter the C++ tool uses between its own scales,
+//! `Downsample(in, fx, fy)`, with the same edge handling and `f32`
+//! arithmetic, so pre-resized scores match those pipelines exactly.
+//!
+//! Other resamplers (Lanczos, Mitchell, ...) give different pixels and
+//! therefore different scores; resize with the same tool on both sides if
+//! scores need to be compared with those.
+
+use crate::{LinearRgbImage, Ssimulacra2Error};
+
+/// Box-filter downscale applied to both images before scoring.
+///
+/// Each output pixel is the mean of a `factor_x` × `factor_y` block of linear
+/// RGB pixels. The output is `ceil(width / factor_x)` ×
+/// `ceil(height / factor_y)`; blocks overhanging the right or bottom edge
+/// repeat the last column or row, as in the C++ tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreResize {
+    /// Horizontal downscale factor
+    pub factor_x: usize,
+    /// Vertical downscale factor
+    pub factor_y: usize,
+}
+
+impl PreResize {
+    /// Downscale by `factor` in both directions.
+    pub fn new(factor: usize) -> Self {
+        Self::with_factors(factor, factor)
+    }
+
+    /// Downscale by separate horizontal and vertical factors.
+    pub fn with_factors(factor_x: usize, factor_y: usize) -> Self {
+        Self { factor_x, factor_y }
+    }
+
+    /// Output dimensions for a `width` × `height` input
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        (
+            width.div_ceil(self.factor_x.max(1)),
+            height.div_ceil(self.factor_y.max(1)),
+        )
+    }
+
+    /// Downscales `image`.
+    ///
+    /// # Errors
+    /// - If either factor is zero
+    pub fn apply(&self, image: &LinearRgbImage) -> Result<LinearRgbImage, Ssimulacra2Error> {
+        let (fx, fy) = (self.factor_x, self.factor_y);
+        if fx == 0 || fy == 0 {
+            return Err(Ssimulacra2Error::InvalidResizeFactor);
+        }
+        let (in_w, in_h) = (image.width(), image.height());
+        let (out_w, out_h) = self.output_size(in_w, in_h);
+        let normalize = 1.0f32 / (fx * fy) as f32;
+
+        let data = image.data();
+        let mut out = Vec::with_capacity(out_w * out_h);
+        for oy in 0..out_h {
+            for ox in 0..out_w {
+                let mut sum = [0.0f32; 3];
+                for iy in 0..fy {
+                    let y = (oy * fy + iy).min(in_h - 1);
+                    for ix in 0..fx {
+                        let x = (ox * fx + ix).min(in_w - 1);
+                        let px = data[y * in_w + x];
+                        for c in 0..3 {
+                            sum[c] += px[c];
+                        }
+                    }
+                }
+                out.push(sum.map(|s| s * normalize));
+            }
+        }
+        Ok(LinearRgbImage::new(out, out_w, out_h))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_ssimulacra2, compute_ssimulacra2_with_config, Ssimulacra2Config};
+
+    fn gradient(width: usize, height: usize, offset: f32) -> LinearRgbImage {
+        let data = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                [
+                    x / width as f32,
+                    y / height as f32,
+                    (offset + x * y * 0.01).fract(),
+                ]
+            })
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    }
+
+    #[test]
+    fn test_box_mean_and_edge_replication() {
+        // 5x1 row, factor 2: the last block repeats the final pixel
+        let data = [1.0, 3.0, 5.0, 7.0, 9.0].map(|v| [v, 0.0, -v]).to_vec();
+        let out = PreResize::with_factors(2, 1)
+            .apply(&LinearRgbImage::new(data, 5, 1))
+            .unwrap();
+        assert_eq!((out.width(), out.height()), (3, 1));
+        assert_eq!(
+            out.data(),
+            &[[2.0, 0.0, -2.0], [6.0, 0.0, -6.0], [9.0, 0.0, -9.0]]
+        );
+
+        assert_eq!(PreResize::new(3).output_size(10, 9), (4, 3));
+        assert_eq!(
+            PreResize::new(0).apply(&gradient(8, 8, 0.0)).map(|_| ()),
+            Err(Ssimulacra2Error::InvalidResizeFactor)
+        );
+    }
+
+    #[test]
+    fn test_config_matches_resizing_inputs() {
+        let source = gradient(100, 70, 0.0);
+        let distorted = gradient(100, 70, 0.3);
+        let resize = PreResize::with_factors(3, 2);
+
+        let config = Ssimulacra2Config::default().with_pre_resize(resize);
+        let score = compute_ssimulacra2_with_config(&source, &distorted, config).unwrap();
+        let expected = compute_ssimulacra2(
+            resize.apply(&source).unwrap(),
+            resize.apply(&distorted).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(score, expected);
+
+        // Too small after resizing
+        let config = Ssimulacra2Config::default().with_pre_resize(PreResize::new(16));
+        assert_eq!(
+            compute_ssimulacra2_with_config(&source, &distorted, config),
+            Err(Ssimulacra2Error::InvalidImageSize)
+        );
+    }
+}
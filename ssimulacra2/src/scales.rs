@@ -0,0 +1,205 @@
+//! Pyramid depth and scale weighting, for research into metric variants.
+//!
+//! The metric compares up to six scales, each half the size of the one
+//! before, halving while both sides are at least 8, and weights every norm
+//! of every scale and channel with a fitted constant. [`ScalePyramid`]
+//! changes how many scales are compared
+//! and how small they may get; [`ScaleWeights`] multiplies the fitted
+//! weights of whole scales or channels. The defaults reproduce the standard
+//! metric exactly; anything else gives scores that are only comparable with
+//! each other, as with [`NormExponents`](crate::NormExponents).
+
+use crate::NUM_SCALES;
+
+/// How many scales are compared, and how small they may get
+/// (experimental).
+///
+/// As in the standard metric, a scale is only halved into the next one if
+/// both its sides are at least the minimum, so the coarsest scale may be
+/// down to half the minimum. Inputs shorter than the minimum are rejected.
+/// Weights are taken in order, as for images too small for all six scales,
+/// so a shallower pyramid scores like the standard metric on a smaller
+/// image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScalePyramid {
+    num_scales: usize,
+    min_size: usize,
+}
+
+impl ScalePyramid {
+    /// The metric's own pyramid: six scales, halving while both sides are
+    /// at least 8
+    pub const STANDARD: Self = Self {
+        num_scales: NUM_SCALES,
+        min_size: 8,
+    };
+
+    /// Compares at most `num_scales` scales, halving while both sides are
+    /// at least `min_size`.
+    ///
+    /// # Panics
+    /// If `num_scales` isn't between 1 and 6 (there are no weights for
+    /// further scales), or `min_size` is 0
+    pub fn new(num_scales: usize, min_size: usize) -> Self {
+        assert!(
+            (1..=NUM_SCALES).contains(&num_scales),
+            "the pyramid must have 1 to {} scales, got {}",
+            NUM_SCALES,
+            num_scales
+        );
+        assert!(min_size > 0, "the minimum scale size must be positive");
+        Self {
+            num_scales,
+            min_size,
+        }
+    }
+
+    /// Most scales compared
+    pub fn num_scales(&self) -> usize {
+        self.num_scales
+    }
+
+    /// Shortest side a scale may have to be halved further
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    /// Whether a scale of this size is compared, or halved into the next
+    pub(crate) fn fits(&self, width: usize, height: usize) -> bool {
+        width >= self.min_size && height >= self.min_size
+    }
+}
+
+impl Default for ScalePyramid {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+/// Multipliers on the fitted score weights of each scale and channel
+/// (experimental).
+///
+/// The weight of every norm at scale `s` of channel `c` (X, Y, B) is
+/// multiplied by `scale[s] * channel[c]`. A multiplier of 0 drops that
+/// scale or channel from the score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaleWeights {
+    scale: [f64; NUM_SCALES],
+    channel: [f64; 3],
+}
+
+impl ScaleWeights {
+    /// The fitted weights, unchanged
+    pub const STANDARD: Self = Self {
+        scale: [1.0; NUM_SCALES],
+        channel: [1.0; 3],
+    };
+
+    /// Multiplies the weights of scale `s` by `scale[s]` (finest first) and
+    /// of channel `c` by `channel[c]`.
+    ///
+    /// # Panics
+    /// If any multiplier isn't finite and non-negative
+    pub fn new(scale: [f64; 6], channel: [f64; 3]) -> Self {
+        assert!(
+            scale
+                .iter()
+                .chain(&channel)
+                .all(|w| w.is_finite() && *w >= 0.0),
+            "weight multipliers must be finite and non-negative, got {:?} and {:?}",
+            scale,
+            channel
+        );
+        Self { scale, channel }
+    }
+
+    /// Multipliers per scale, finest first
+    pub fn scale(&self) -> [f64; 6] {
+        self.scale
+    }
+
+    /// Multipliers per channel (X, Y, B)
+    pub fn channel(&self) -> [f64; 3] {
+        self.channel
+    }
+
+    /// Multiplier of the weights of `channel` at `scale`
+    pub(crate) fn factor(&self, channel: usize, scale: usize) -> f64 {
+        self.channel[channel] * self.scale[scale]
+    }
+}
+
+impl Default for ScaleWeights {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pattern;
+    use crate::{
+        aggregate_scales, compute_ssimulacra2_detailed, compute_ssimulacra2_with_config,
+        Ssimulacra2Config, Ssimulacra2Error,
+    };
+
+    #[test]
+    fn test_standard_settings_match_default() {
+        let (source, distorted) = (pattern(120, 90, 0.0), pattern(120, 90, 0.3));
+        let default = Ssimulacra2Config::default();
+        let explicit = default
+            .clone()
+            .with_pyramid(ScalePyramid::new(6, 8))
+            .with_scale_weights(ScaleWeights::new([1.0; 6], [1.0; 3]));
+        assert_eq!(
+            compute_ssimulacra2_with_config(&source, &distorted, default),
+            compute_ssimulacra2_with_config(&source, &distorted, explicit)
+        );
+    }
+
+    #[test]
+    fn test_shallow_pyramid_scores_its_scales() {
+        let (source, distorted) = (pattern(120, 90, 0.0), pattern(120, 90, 0.3));
+        let config = Ssimulacra2Config::default();
+        let full = compute_ssimulacra2_detailed(&source, &distorted, config.clone()).unwrap();
+
+        let shallow = config.clone().with_pyramid(ScalePyramid::new(2, 8));
+        let detailed = compute_ssimulacra2_detailed(&source, &distorted, shallow).unwrap();
+        assert_eq!(detailed.scales, full.scales[..2]);
+        assert_eq!(detailed.score, aggregate_scales(&full.scales[..2]));
+
+        // 120x90 halves to 60x45, 30x23 and 15x12, which is below 16 and
+        // not halved again
+        let coarse_limit = config.with_pyramid(ScalePyramid::new(6, 16));
+        let detailed =
+            compute_ssimulacra2_detailed(&source, &distorted, coarse_limit.clone()).unwrap();
+        assert_eq!(detailed.scales, full.scales[..4]);
+
+        let small = pattern(12, 12, 0.0);
+        assert_eq!(
+            compute_ssimulacra2_with_config(&small, &small, coarse_limit),
+            Err(Ssimulacra2Error::InvalidImageSize)
+        );
+    }
+
+    #[test]
+    fn test_weights_scale_the_error() {
+        let (source, distorted) = (pattern(120, 90, 0.0), pattern(120, 90, 0.3));
+        let config = Ssimulacra2Config::default();
+        let score = |weights| {
+            let config = config.clone().with_scale_weights(weights);
+            compute_ssimulacra2_with_config(&source, &distorted, config).unwrap()
+        };
+        let standard = score(ScaleWeights::STANDARD);
+        assert_eq!(score(ScaleWeights::new([0.0; 6], [1.0; 3])), 100.0);
+        assert!(score(ScaleWeights::new([2.0; 6], [1.0; 3])) < standard);
+        assert!(score(ScaleWeights::new([1.0; 6], [1.0, 0.0, 1.0])) > standard);
+    }
+
+    #[test]
+    #[should_panic(expected = "1 to 6 scales")]
+    fn test_pyramid_needs_a_scale() {
+        let _ = ScalePyramid::new(0, 8);
+    }
+}
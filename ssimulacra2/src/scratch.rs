@@ -0,0 +1,217 @@
+//! Reusable buffers for repeated comparisons.
+//!
+//! Each comparison needs a few dozen planes the size of the input: XYB
+//! conversions, products, blurred moments and the downscaled pyramid. The
+//! plain entry points allocate them per call, which at 4K is hundreds of MB
+//! of allocator churn per score. An encoder scoring every candidate can keep
+//! one [`Ssimulacra2Scratch`] instead and call [`compute_ssimulacra2_into`]:
+//! buffers grow to the largest size seen and are reused from then on, so
+//! scoring [`LinearRgbImage`](crate::LinearRgbImage) inputs with the SIMD
+//! backends allocates nothing after the first call.
+//!
+//! Things that still allocate: converting inputs that aren't already linear
+//! RGB, the scalar XYB backend (which goes through `yuvxyb` when that
+//! feature is enabled), and the optional
+//! [`pre_resize`](Ssimulacra2Config::pre_resize) and
+//! [`early_downscale_megapixels`](Ssimulacra2Config::early_downscale_megapixels)
+//! stages.
+
+use crate::banded::BandedMoments;
+#[cfg(feature = "yuvxyb")]
+use crate::LinearRgb;
+use crate::{
+    compute_msssim_with_scratch, Blur, LinearRgbImage, Msssim, PlanarImage, Ssimulacra2Config,
+    Ssimulacra2Error, ToLinearRgb,
+};
+
+/// Owns every intermediate buffer of a comparison, for reuse across calls.
+///
+/// A scratch is tied to the [`Ssimulacra2Config`] it was created with. It can
+/// be reused for images of any size; buffers only grow.
+pub struct Ssimulacra2Scratch {
+    pub(crate) config: Ssimulacra2Config,
+    pub(crate) blur: Option<Blur>,
+    pub(crate) mul: [Vec<f32>; 3],
+    pub(crate) sigma1_sq: [Vec<f32>; 3],
+    pub(crate) sigma2_sq: [Vec<f32>; 3],
+    pub(crate) sigma12: [Vec<f32>; 3],
+    pub(crate) mu1: [Vec<f32>; 3],
+    pub(crate) mu2: [Vec<f32>; 3],
+    pub(crate) img1_planar: PlanarImage,
+    pub(crate) img2_planar: PlanarImage,
+    /// Downscaled scales of each image, alternating between the two slots
+    pub(crate) img1_pyramid: [LinearRgbImage; 2],
+    pub(crate) img2_pyramid: [LinearRgbImage; 2],
+    pub(crate) msssim: Msssim,
+    /// Band buffers for [`streaming_moments`](Ssimulacra2Config::streaming_moments)
+    pub(crate) banded: Option<BandedMoments>,
+}
+
+impl Default for Ssimulacra2Scratch {
+    fn default() -> Self {
+        Self::with_config(Ssimulacra2Config::default())
+    }
+}
+
+impl Ssimulacra2Scratch {
+    /// Creates empty scratch for the default configuration. Buffers are
+    /// allocated by the first comparison.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates empty scratch for comparisons with `config`.
+    pub fn with_config(config: Ssimulacra2Config) -> Self {
+        let empty_image = || LinearRgbImage::new(Vec::new(), 0, 0);
+        Self {
+            config,
+            blur: None,
+            mul: Default::default(),
+            sigma1_sq: Default::default(),
+            sigma2_sq: Default::default(),
+            sigma12: Default::default(),
+            mu1: Default::default(),
+            mu2: Default::default(),
+            img1_planar: PlanarImage::zeros(0, 0),
+            img2_planar: PlanarImage::zeros(0, 0),
+            img1_pyramid: [empty_image(), empty_image()],
+            img2_pyramid: [empty_image(), empty_image()],
+            msssim: Msssim::default(),
+            banded: None,
+        }
+    }
+
+    /// The configuration comparisons with this scratch use
+    pub fn config(&self) -> &Ssimulacra2Config {
+        &self.config
+    }
+}
+
+/// Computes the SSIMULACRA2 score using the buffers and configuration of
+/// `scratch`.
+///
+/// Scores are identical to
+/// [`compute_ssimulacra2_with_config`](crate::compute_ssimulacra2_with_config)
+/// with the scratch's configuration. With `&LinearRgbImage` inputs and a SIMD
+/// backend, no allocation happens once the scratch has seen an image of
+/// this size.
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the image is smaller than 8x8 pixels
+pub fn compute_ssimulacra2_into<S, D>(
+    scratch: &mut Ssimulacra2Scratch,
+    source: S,
+    distorted: D,
+) -> Result<f64, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    compute_msssim_with_scratch(
+        &source.as_linear_rgb(),
+        &distorted.as_linear_rgb(),
+        scratch,
+        None,
+    )?;
+    Ok(scratch.msssim.score())
+}
+
+/// Computes the SSIMULACRA2 score of `yuvxyb`-convertible frames using the
+/// buffers of `scratch`.
+///
+/// The conversion to linear RGB allocates; the comparison itself reuses the
+/// scratch as in [`compute_ssimulacra2_into`].
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the source or distorted image cannot be converted to linear RGB
+/// - If the image is smaller than 8x8 pixels
+#[cfg(feature = "yuvxyb")]
+pub fn compute_frame_ssimulacra2_into<T, U>(
+    scratch: &mut Ssimulacra2Scratch,
+    source: T,
+    distorted: U,
+) -> Result<f64, Ssimulacra2Error>
+where
+    LinearRgb: TryFrom<T> + TryFrom<U>,
+{
+    let Ok(img1) = LinearRgb::try_from(source) else {
+        return Err(Ssimulacra2Error::LinearRgbConversionFailed);
+    };
+    let Ok(img2) = LinearRgb::try_from(distorted) else {
+        return Err(Ssimulacra2Error::LinearRgbConversionFailed);
+    };
+    compute_ssimulacra2_into(
+        scratch,
+        LinearRgbImage::from(img1),
+        LinearRgbImage::from(img2),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_ssimulacra2_with_config;
+    use crate::test_util::{backend_configs, test_image};
+    #[cfg(feature = "yuvxyb")]
+    use crate::{compute_frame_ssimulacra2, ColorPrimaries, Rgb, TransferCharacteristic};
+
+    #[test]
+    fn test_reused_scratch_matches_fresh_calls() {
+        // Large, small, larger than the first and odd sizes, so buffers both
+        // shrink and grow between calls
+        let sizes = [(96, 64), (17, 11), (130, 97), (96, 64)];
+        for config in backend_configs() {
+            let mut scratch = Ssimulacra2Scratch::with_config(config.clone());
+            for (i, &(width, height)) in sizes.iter().enumerate() {
+                let source = test_image(width, height, i as u32);
+                let distorted = test_image(width, height, i as u32 + 10);
+                let reused = compute_ssimulacra2_into(&mut scratch, &source, &distorted).unwrap();
+                let fresh =
+                    compute_ssimulacra2_with_config(&source, &distorted, config.clone()).unwrap();
+                assert_eq!(
+                    reused,
+                    fresh,
+                    "{} at {}x{}",
+                    config.impl_type.name(),
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_frame_into_and_errors() {
+        let mut scratch = Ssimulacra2Scratch::new();
+        #[cfg(feature = "yuvxyb")]
+        {
+            let frame = |seed: u32| {
+                Rgb::new(
+                    test_image(32, 24, seed).data().to_vec(),
+                    32,
+                    24,
+                    TransferCharacteristic::SRGB,
+                    ColorPrimaries::BT709,
+                )
+                .unwrap()
+            };
+            assert_eq!(
+                compute_frame_ssimulacra2_into(&mut scratch, frame(1), frame(2)).unwrap(),
+                compute_frame_ssimulacra2(frame(1), frame(2)).unwrap()
+            );
+        }
+
+        assert_eq!(
+            compute_ssimulacra2_into(&mut scratch, test_image(32, 24, 1), test_image(24, 32, 1)),
+            Err(Ssimulacra2Error::NonMatchingImageDimensions)
+        );
+        // An error leaves the scratch usable
+        let (a, b) = (test_image(40, 40, 3), test_image(40, 40, 4));
+        assert_eq!(
+            compute_ssimulacra2_into(&mut scratch, &a, &b).unwrap(),
+            compute_ssimulacra2_with_config(&a, &b, Ssimulacra2Config::default()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,410 @@
+//! Numerical sensitivity of the score to changes in image regions.
+//!
+//! [`compute_ssimulacra2_sensitivity`] perturbs the distorted image inside
+//! each requested region, rescores it, and reports the central finite
+//! difference of the score. An encoder parameter search can use the
+//! gradients to decide where extra bits pay off most, and a large gradient
+//! over otherwise unremarkable content points at what the metric is
+//! reacting to.
+//!
+//! The source side is precomputed once with [`Ssimulacra2Reference`], so each
+//! region costs two comparisons. With
+//! [`SensitivityOptions::margin`] set, each region is instead scored in a
+//! window cropped around it, which is much cheaper on large images but
+//! measures the window's score rather than the whole image's.
+
+use crate::input::{LinearRgbImage, ToLinearRgb};
+use crate::transfer::reconcile_colorimetry;
+use crate::{Ssimulacra2Error, Ssimulacra2Reference};
+
+/// Smallest window the metric can score.
+const MIN_WINDOW_DIMENSION: usize = 8;
+
+/// A rectangle of pixels to perturb.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// Left edge in pixels
+    pub x: usize,
+    /// Top edge in pixels
+    pub y: usize,
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+}
+
+impl Region {
+    /// Creates a region from its top-left corner and size.
+    #[must_use]
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub(crate) fn fits(&self, width: usize, height: usize) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self
+                .x
+                .checked_add(self.width)
+                .is_some_and(|end| end <= width)
+            && self
+                .y
+                .checked_add(self.height)
+                .is_some_and(|end| end <= height)
+    }
+}
+
+/// How the distorted pixels of a region are changed by a step `h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Perturbation {
+    /// Blend toward the source: `distorted + h * (source - distorted)`.
+    ///
+    /// The gradient is how much the score gains per unit of the remaining
+    /// error fixed in the region, the quantity an encoder trades bits for.
+    #[default]
+    TowardSource,
+    /// Add `h` to every linear-light channel, clamped at zero.
+    ///
+    /// The gradient shows how strongly the metric reacts to a brightness
+    /// change in the region, whether or not the region is distorted.
+    Offset,
+}
+
+/// Parameters for [`compute_ssimulacra2_sensitivity`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensitivityOptions {
+    /// What is perturbed
+    pub perturbation: Perturbation,
+    /// Finite-difference step; the score is sampled at `-step` and `+step`
+    pub step: f32,
+    /// Score each region in a window extending this many pixels around it
+    /// (clamped to the image and grown to at least 8x8) instead of the whole
+    /// image; `None` scores the whole image
+    pub margin: Option<usize>,
+}
+
+impl Default for SensitivityOptions {
+    fn default() -> Self {
+        Self {
+            perturbation: Perturbation::TowardSource,
+            step: 0.1,
+            margin: None,
+        }
+    }
+}
+
+impl SensitivityOptions {
+    /// Sets the perturbation.
+    #[must_use]
+    pub fn with_perturbation(mut self, perturbation: Perturbation) -> Self {
+        self.perturbation = perturbation;
+        self
+    }
+
+    /// Sets the finite-difference step.
+    #[must_use]
+    pub fn with_step(mut self, step: f32) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Scores each region in a window with this margin around it.
+    #[must_use]
+    pub fn with_margin(mut self, margin: usize) -> Self {
+        self.margin = Some(margin);
+        self
+    }
+}
+
+/// Score sensitivity of one region.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionSensitivity {
+    /// The perturbed region
+    pub region: Region,
+    /// Score of the unperturbed pair: the whole image's, or the window's
+    /// with a margin set
+    pub base_score: f64,
+    /// Central difference of the score per unit step
+    pub gradient: f64,
+}
+
+/// Estimates how the SSIMULACRA2 score changes when the distorted image
+/// changes inside each of `regions`.
+///
+/// For every region the distorted pixels inside it are perturbed by `-step`
+/// and `+step` (see [`Perturbation`]), and the gradient is
+/// `(score(+step) - score(-step)) / (2 * step)`. Regions are perturbed one at
+/// a time, so each gradient is independent of the others; overlapping
+/// regions are allowed.
+///
+/// Scoring goes through [`Ssimulacra2Reference`], so it uses the default
+/// SIMD backend.
+///
+/// # Errors
+/// - If the image dimensions don't match
+/// - If either image is smaller than 8x8 pixels
+/// - If a region is empty or extends past the image
+/// - If `step` is not finite and positive
+pub fn compute_ssimulacra2_sensitivity<S, D>(
+    source: S,
+    distorted: D,
+    regions: &[Region],
+    options: SensitivityOptions,
+) -> Result<Vec<RegionSensitivity>, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    let mut source = source.as_linear_rgb();
+    let mut distorted = distorted.as_linear_rgb();
+    if source.width() != distorted.width() || source.height() != distorted.height() {
+        return Err(Ssimulacra2Error::NonMatchingImageDimensions);
+    }
+    reconcile_colorimetry(&mut source, &mut distorted)?;
+    let (width, height) = (source.width(), source.height());
+    if width < MIN_WINDOW_DIMENSION || height < MIN_WINDOW_DIMENSION {
+        return Err(Ssimulacra2Error::InvalidImageSize);
+    }
+    if !(options.step.is_finite() && options.step > 0.0) {
+        return Err(Ssimulacra2Error::InvalidStep);
+    }
+    if regions.iter().any(|region| !region.fits(width, height)) {
+        return Err(Ssimulacra2Error::InvalidRegion);
+    }
+
+    match options.margin {
+        None => {
+            let reference = Ssimulacra2Reference::new(&*source)?;
+            let mut probe = Probe::new(&source, distorted.into_owned(), options);
+            let base_score = reference.compare(&probe.image)?;
+            regions
+                .iter()
+                .map(|&region| {
+                    let gradient = probe.gradient(&reference, region)?;
+                    Ok(RegionSensitivity {
+                        region,
+                        base_score,
+                        gradient,
+                    })
+                })
+                .collect()
+        }
+        Some(margin) => regions
+            .iter()
+            .map(|&region| {
+                let window = window(region, margin, width, height);
+                let crop = |image: &LinearRgbImage| {
+                    image.crop(window.x, window.y, window.width, window.height)
+                };
+                let window_source = crop(&source);
+                let reference = Ssimulacra2Reference::new(&window_source)?;
+                let mut probe = Probe::new(&window_source, crop(&distorted), options);
+                let base_score = reference.compare(&probe.image)?;
+                let local = Region::new(
+                    region.x - window.x,
+                    region.y - window.y,
+                    region.width,
+                    region.height,
+                );
+                let gradient = probe.gradient(&reference, local)?;
+                Ok(RegionSensitivity {
+                    region,
+                    base_score,
+                    gradient,
+                })
+            })
+            .collect(),
+    }
+}
+
+/// `region` grown by `margin` on every side, clamped to the image, and grown
+/// again to the smallest scorable size where the clamp left it too small.
+fn window(region: Region, margin: usize, width: usize, height: usize) -> Region {
+    let span = |start: usize, len: usize, limit: usize| {
+        let first = start.saturating_sub(margin);
+        let end = start.saturating_add(len).saturating_add(margin).min(limit);
+        let len = (end - first).max(MIN_WINDOW_DIMENSION);
+        (first.min(limit - len), len)
+    };
+    let (x, width) = span(region.x, region.width, width);
+    let (y, height) = span(region.y, region.height, height);
+    Region::new(x, y, width, height)
+}
+
+/// A distorted image perturbed in place, one region at a time.
+struct Probe<'a> {
+    source: &'a LinearRgbImage,
+    image: LinearRgbImage,
+    /// The region's unperturbed pixels, row by row
+    saved: Vec<[f32; 3]>,
+    options: SensitivityOptions,
+}
+
+impl<'a> Probe<'a> {
+    fn new(source: &'a LinearRgbImage, image: LinearRgbImage, options: SensitivityOptions) -> Self {
+        Self {
+            source,
+            image,
+            saved: Vec::new(),
+            options,
+        }
+    }
+
+    fn gradient(
+        &mut self,
+        reference: &Ssimulacra2Reference,
+        region: Region,
+    ) -> Result<f64, Ssimulacra2Error> {
+        let step = self.options.step;
+        self.save(region);
+        self.perturb(region, step);
+        let plus = reference.compare(&self.image);
+        self.perturb(region, -step);
+        let minus = reference.compare(&self.image);
+        self.restore(region);
+        Ok((plus? - minus?) / (2.0 * f64::from(step)))
+    }
+
+    fn rows(region: Region, width: usize) -> impl Iterator<Item = std::ops::Range<usize>> {
+        (region.y..region.y + region.height).map(move |y| {
+            let start = y * width + region.x;
+            start..start + region.width
+        })
+    }
+
+    fn save(&mut self, region: Region) {
+        self.saved.clear();
+        for row in Self::rows(region, self.image.width()) {
+            self.saved.extend_from_slice(&self.image.data()[row]);
+        }
+    }
+
+    fn restore(&mut self, region: Region) {
+        let width = self.image.width();
+        for (row, saved) in Self::rows(region, width).zip(self.saved.chunks_exact(region.width)) {
+            self.image.data_mut()[row].copy_from_slice(saved);
+        }
+    }
+
+    /// Sets the region to its saved pixels perturbed by `h`.
+    fn perturb(&mut self, region: Region, h: f32) {
+        let width = self.image.width();
+        let source = self.source.data();
+        let perturbation = self.options.perturbation;
+        for (row, saved) in Self::rows(region, width).zip(self.saved.chunks_exact(region.width)) {
+            let source = &source[row.clone()];
+            let pixels = &mut self.image.data_mut()[row];
+            for ((pixel, saved), source) in pixels.iter_mut().zip(saved).zip(source) {
+                *pixel = std::array::from_fn(|c| {
+                    let value = match perturbation {
+                        Perturbation::TowardSource => h.mul_add(source[c] - saved[c], saved[c]),
+                        Perturbation::Offset => saved[c] + h,
+                    };
+                    value.max(0.0)
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ramp;
+
+    /// `source` with uniform noise added inside `region` only
+    fn distorted_in(source: &LinearRgbImage, region: Region) -> LinearRgbImage {
+        let mut distorted = source.clone();
+        let width = distorted.width();
+        for row in Probe::rows(region, width) {
+            for (i, pixel) in distorted.data_mut()[row.clone()].iter_mut().enumerate() {
+                let noise = ((row.start + i) * 7919 % 13) as f32 / 13.0 - 0.5;
+                *pixel = pixel.map(|v| (v + 0.2 * noise).max(0.0));
+            }
+        }
+        distorted
+    }
+
+    #[test]
+    fn test_repairing_distortion_raises_score() {
+        let source = ramp(64, 64, 0);
+        let damaged = Region::new(8, 8, 16, 16);
+        let clean = Region::new(40, 40, 16, 16);
+        let distorted = distorted_in(&source, damaged);
+
+        for options in [
+            SensitivityOptions::default(),
+            SensitivityOptions::default().with_margin(16),
+        ] {
+            let result =
+                compute_ssimulacra2_sensitivity(&source, &distorted, &[damaged, clean], options)
+                    .unwrap();
+            assert!(result[0].gradient > 0.0, "{:?}", result[0]);
+            // Nothing to repair where the images already agree
+            assert_eq!(result[1].gradient, 0.0, "{:?}", result[1]);
+        }
+    }
+
+    #[test]
+    fn test_distorted_image_is_restored() {
+        let source = ramp(48, 40, 0);
+        let distorted = ramp(48, 40, 1);
+        let region = Region::new(4, 4, 20, 20);
+        let options = SensitivityOptions::default().with_perturbation(Perturbation::Offset);
+        let result =
+            compute_ssimulacra2_sensitivity(&source, &distorted, &[region, region], options)
+                .unwrap();
+        assert_eq!(result[0], result[1]);
+        assert_eq!(
+            result[0].base_score,
+            Ssimulacra2Reference::new(&source)
+                .unwrap()
+                .compare(&distorted)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_window_is_clamped_and_scorable() {
+        assert_eq!(
+            window(Region::new(0, 30, 2, 2), 1, 40, 32),
+            Region::new(0, 24, 8, 8)
+        );
+        assert_eq!(
+            window(Region::new(10, 10, 4, 4), 16, 40, 32),
+            Region::new(0, 0, 30, 30)
+        );
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let image = ramp(16, 16, 0);
+        let run = |regions: &[Region], step: f32| {
+            compute_ssimulacra2_sensitivity(
+                &image,
+                &image,
+                regions,
+                SensitivityOptions::default().with_step(step),
+            )
+        };
+        assert!(matches!(
+            run(&[Region::new(8, 8, 9, 1)], 0.1),
+            Err(Ssimulacra2Error::InvalidRegion)
+        ));
+        assert!(matches!(
+            run(&[Region::new(0, 0, 0, 4)], 0.1),
+            Err(Ssimulacra2Error::InvalidRegion)
+        ));
+        for step in [0.0, -0.1, f32::NAN] {
+            assert!(matches!(
+                run(&[Region::new(0, 0, 4, 4)], step),
+                Err(Ssimulacra2Error::InvalidStep)
+            ));
+        }
+    }
+}
@@ -2,21 +2,25 @@
 ///
 /// Uses the `wide` crate for portable SIMD across x86 (SSE/AVX) and ARM (NEON)
 use multiversion::multiversion;
-use wide::f32x16;
+use wide::{f32x16, f32x8, f64x4};
+
+use crate::norms::Norms;
 
 /// SIMD-optimized SSIM map computation
 ///
-/// Processes 16 pixels at once using f32x16, then accumulates in f64 for precision
+/// Processes 16 pixels at once using f32x16, then accumulates in f64 for precision.
+/// Returns the per-plane sums of the `(low, high)` powers.
 #[inline(always)]
 #[multiversion(targets("x86_64+avx2+fma", "x86_64+sse2", "aarch64+neon"))]
-pub(crate) fn ssim_map_simd(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn ssim_map_simd<N: Norms>(
     width: usize,
-    height: usize,
-    m1: &[Vec<f32>; 3],
-    m2: &[Vec<f32>; 3],
-    s11: &[Vec<f32>; 3],
-    s22: &[Vec<f32>; 3],
-    s12: &[Vec<f32>; 3],
+    m1: [&[f32]; 3],
+    m2: [&[f32]; 3],
+    s11: [&[f32]; 3],
+    s22: [&[f32]; 3],
+    s12: [&[f32]; 3],
+    norms: N,
 ) -> [f64; 3 * 2] {
     const C2: f32 = 0.0009f32;
     let c2_simd = f32x16::splat(C2);
@@ -24,8 +28,7 @@ pub(crate) fn ssim_map_simd(
     let two_simd = f32x16::splat(2.0);
     let zero_simd = f32x16::splat(0.0);
 
-    let one_per_pixels = 1.0f64 / (width * height) as f64;
-    let mut plane_averages = [0f64; 3 * 2];
+    let mut plane_sums = [0f64; 3 * 2];
 
     for c in 0..3 {
         let mut sum1 = [0.0f64; 2];
@@ -157,8 +160,8 @@ pub(crate) fn ssim_map_simd(
                 // Extract values and accumulate in f64 for precision
                 for d_val in d.to_array() {
                     let d_f64 = f64::from(d_val);
-                    sum1[0] += d_f64;
-                    sum1[1] += d_f64.powi(4);
+                    sum1[0] += norms.low_power(d_f64);
+                    sum1[1] += norms.high_power(d_f64);
                 }
 
                 x += 16;
@@ -179,31 +182,31 @@ pub(crate) fn ssim_map_simd(
                     f64::from(row_s11[x] - mu11) + f64::from(row_s22[x] - mu22) + f64::from(C2);
                 let mut d = 1.0f64 - (num_m * num_s) / denom_s;
                 d = d.max(0.0);
-                sum1[0] += d;
-                sum1[1] += d.powi(4);
+                sum1[0] += norms.low_power(d);
+                sum1[1] += norms.high_power(d);
             }
         }
 
-        plane_averages[c * 2] = one_per_pixels * sum1[0];
-        plane_averages[c * 2 + 1] = (one_per_pixels * sum1[1]).sqrt().sqrt();
+        plane_sums[c * 2] = sum1[0];
+        plane_sums[c * 2 + 1] = sum1[1];
     }
 
-    plane_averages
+    plane_sums
 }
 
-/// SIMD-optimized edge difference map computation
+/// SIMD-optimized edge difference map computation, returning the per-plane
+/// sums of the `(low, high)` powers of the artifact and detail-lost maps
 #[inline(always)]
 #[multiversion(targets("x86_64+avx2+fma", "x86_64+sse2", "aarch64+neon"))]
-pub(crate) fn edge_diff_map_simd(
+pub(crate) fn edge_diff_map_simd<N: Norms>(
     width: usize,
-    height: usize,
-    img1: &[Vec<f32>; 3],
-    mu1: &[Vec<f32>; 3],
-    img2: &[Vec<f32>; 3],
-    mu2: &[Vec<f32>; 3],
+    img1: [&[f32]; 3],
+    mu1: [&[f32]; 3],
+    img2: [&[f32]; 3],
+    mu2: [&[f32]; 3],
+    norms: N,
 ) -> [f64; 3 * 4] {
-    let one_per_pixels = 1.0f64 / (width * height) as f64;
-    let mut plane_averages = [0f64; 3 * 4];
+    let mut plane_sums = [0f64; 3 * 4];
 
     let one_simd = f32x16::splat(1.0);
     let zero_simd = f32x16::splat(0.0);
@@ -314,10 +317,10 @@ pub(crate) fn edge_diff_map_simd(
                 for i in 0..16 {
                     let a = f64::from(artifact_arr[i]);
                     let d = f64::from(detail_arr[i]);
-                    sum1[0] += a;
-                    sum1[1] += a.powi(4);
-                    sum1[2] += d;
-                    sum1[3] += d.powi(4);
+                    sum1[0] += norms.low_power(a);
+                    sum1[1] += norms.high_power(a);
+                    sum1[2] += norms.low_power(d);
+                    sum1[3] += norms.high_power(d);
                 }
 
                 x += 16;
@@ -330,21 +333,17 @@ pub(crate) fn edge_diff_map_simd(
                     - 1.0;
                 let artifact = d1.max(0.0);
                 let detail_lost = (-d1).max(0.0);
-                sum1[0] += artifact;
-                sum1[1] += artifact.powi(4);
-                sum1[2] += detail_lost;
-                sum1[3] += detail_lost.powi(4);
+                sum1[0] += norms.low_power(artifact);
+                sum1[1] += norms.high_power(artifact);
+                sum1[2] += norms.low_power(detail_lost);
+                sum1[3] += norms.high_power(detail_lost);
             }
         }
 
-        for i in 0..4 {
-            plane_averages[c * 4 + i] = one_per_pixels * sum1[i];
-        }
-        plane_averages[c * 4 + 1] = plane_averages[c * 4 + 1].sqrt().sqrt();
-        plane_averages[c * 4 + 3] = plane_averages[c * 4 + 3].sqrt().sqrt();
+        plane_sums[c * 4..][..4].copy_from_slice(&sum1);
     }
 
-    plane_averages
+    plane_sums
 }
 
 /// SIMD-optimized image multiplication
@@ -414,3 +413,235 @@ pub(crate) fn image_multiply_simd(
         }
     }
 }
+
+/// Applies the SSIMULACRA2 XYB offsets in place, 8 pixels at a time
+///
+/// Matches the scalar version except that the X scale is only fused into a
+/// single rounding where `wide` uses FMA (aarch64, or x86 built with FMA).
+#[inline(always)]
+#[multiversion(targets("x86_64+avx2+fma", "x86_64+sse2", "aarch64+neon"))]
+pub(crate) fn make_positive_xyb_simd(data: &mut [[f32; 3]]) {
+    let mut chunks = data.chunks_exact_mut(8);
+    for chunk in &mut chunks {
+        let x = f32x8::new(std::array::from_fn(|i| chunk[i][0]));
+        let y = f32x8::new(std::array::from_fn(|i| chunk[i][1]));
+        let b = f32x8::new(std::array::from_fn(|i| chunk[i][2]));
+
+        let b = ((b - y) + f32x8::splat(0.55)).to_array();
+        let x = x.mul_add(f32x8::splat(14.0), f32x8::splat(0.42)).to_array();
+        let y = (y + f32x8::splat(0.01)).to_array();
+        for (i, pix) in chunk.iter_mut().enumerate() {
+            *pix = [x[i], y[i], b[i]];
+        }
+    }
+
+    for pix in chunks.into_remainder() {
+        pix[2] = (pix[2] - pix[1]) + 0.55;
+        pix[0] = (pix[0]).mul_add(14.0, 0.42);
+        pix[1] += 0.01;
+    }
+}
+
+/// Splits interleaved pixels into planes, 8 pixels at a time
+#[inline(always)]
+#[multiversion(targets("x86_64+avx2+fma", "x86_64+sse2", "aarch64+neon"))]
+pub(crate) fn xyb_to_planar_simd(data: &[[f32; 3]], out: [&mut [f32]; 3]) {
+    let [out0, out1, out2] = out;
+    let (out0, out1, out2) = (
+        &mut out0[..data.len()],
+        &mut out1[..data.len()],
+        &mut out2[..data.len()],
+    );
+    let chunks = data.chunks_exact(8);
+    let tail = chunks.len() * 8;
+    for (((chunk, o0), o1), o2) in chunks
+        .zip(out0.chunks_exact_mut(8))
+        .zip(out1.chunks_exact_mut(8))
+        .zip(out2.chunks_exact_mut(8))
+    {
+        o0.copy_from_slice(&std::array::from_fn::<f32, 8, _>(|i| chunk[i][0]));
+        o1.copy_from_slice(&std::array::from_fn::<f32, 8, _>(|i| chunk[i][1]));
+        o2.copy_from_slice(&std::array::from_fn::<f32, 8, _>(|i| chunk[i][2]));
+    }
+
+    for (i, pix) in data.iter().enumerate().skip(tail) {
+        out0[i] = pix[0];
+        out1[i] = pix[1];
+        out2[i] = pix[2];
+    }
+}
+
+/// Pixels per block of [`linear_rgb_to_positive_planar_simd`]: small enough
+/// for the block's interleaved copy to stay in L1, and a multiple of every
+/// batch size of the conversion kernels.
+const FUSED_BLOCK_PIXELS: usize = 256;
+
+/// Converts linear RGB to the positive XYB planes in one pass over the
+/// image. Each block of pixels is copied to the stack, converted in place by
+/// `convert` (one of the in-place XYB kernels), offset as
+/// [`make_positive_xyb_simd`] does, and split into `out`.
+///
+/// Only the last block has a partial batch, and it holds the same pixels as
+/// the whole image's tail, so the planes are bit-identical to running the
+/// three stages over the whole image.
+pub(crate) fn linear_rgb_to_positive_planar_simd(
+    input: &[[f32; 3]],
+    out: [&mut [f32]; 3],
+    convert: fn(&mut [[f32; 3]]),
+) {
+    let [out0, out1, out2] = out;
+    let mut block = [[0.0f32; 3]; FUSED_BLOCK_PIXELS];
+    for (((input, out0), out1), out2) in input
+        .chunks(FUSED_BLOCK_PIXELS)
+        .zip(out0.chunks_mut(FUSED_BLOCK_PIXELS))
+        .zip(out1.chunks_mut(FUSED_BLOCK_PIXELS))
+        .zip(out2.chunks_mut(FUSED_BLOCK_PIXELS))
+    {
+        let block = &mut block[..input.len()];
+        block.copy_from_slice(input);
+        convert(block);
+        make_positive_xyb_simd(block);
+        xyb_to_planar_simd(block, [out0, out1, out2]);
+    }
+}
+
+/// Halves an interleaved image by averaging 2x2 blocks, 4 output pixels at
+/// a time. `out` must hold `in_w.div_ceil(2) * in_h.div_ceil(2)` pixels.
+///
+/// Sums in f64 in the same order as the scalar version, so results are
+/// bit-identical.
+#[inline(always)]
+#[multiversion(targets("x86_64+avx2+fma", "x86_64+sse2", "aarch64+neon"))]
+pub(crate) fn downscale_by_2_simd(
+    input: &[[f32; 3]],
+    in_w: usize,
+    in_h: usize,
+    out: &mut [[f32; 3]],
+) {
+    let out_w = in_w.div_ceil(2);
+    // Output pixels whose 2x2 block lies fully inside the row
+    let full_pairs = in_w / 2;
+    let quarter = f64x4::splat(0.25);
+
+    for (oy, out_row) in out.chunks_exact_mut(out_w).enumerate() {
+        let row0 = &input[2 * oy * in_w..][..in_w];
+        let row1 = &input[(2 * oy + 1).min(in_h - 1) * in_w..][..in_w];
+
+        let mut ox = 0;
+        while ox + 4 <= full_pairs {
+            for c in 0..3 {
+                let lanes = |row: &[[f32; 3]], dx: usize| {
+                    f64x4::new(std::array::from_fn(|i| {
+                        f64::from(row[2 * (ox + i) + dx][c])
+                    }))
+                };
+                let sum = lanes(row0, 0) + lanes(row0, 1) + lanes(row1, 0) + lanes(row1, 1);
+                let avg = (sum * quarter).to_array();
+                for (i, &v) in avg.iter().enumerate() {
+                    out_row[ox + i][c] = v as f32;
+                }
+            }
+            ox += 4;
+        }
+
+        for (ox, out_pix) in out_row.iter_mut().enumerate().skip(ox) {
+            let (x0, x1) = (2 * ox, (2 * ox + 1).min(in_w - 1));
+            for c in 0..3 {
+                let sum = f64::from(row0[x0][c])
+                    + f64::from(row0[x1][c])
+                    + f64::from(row1[x0][c])
+                    + f64::from(row1[x1][c]);
+                out_pix[c] = (sum * 0.25) as f32;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xyb::XybImage;
+    use crate::{downscale_by_2_into, make_positive_xyb, xyb_to_planar_into};
+    use crate::{LinearRgbImage, PlanarImage, SimdImpl};
+
+    fn pixels(len: usize, seed: u32) -> Vec<[f32; 3]> {
+        (0..len as u32)
+            .map(|i| {
+                let v = |k: u32| {
+                    (i.wrapping_mul(2_654_435_761).wrapping_add(seed + k) >> 8) as f32
+                        / (1 << 24) as f32
+                };
+                [v(0), v(1) * 0.5, v(2) - 0.25]
+            })
+            .collect()
+    }
+
+    /// Sizes with and without SIMD tails and odd edges
+    const SIZES: [(usize, usize); 5] = [(1, 1), (7, 3), (8, 8), (17, 9), (64, 33)];
+
+    #[test]
+    fn test_make_positive_matches_scalar() {
+        for (width, height) in SIZES {
+            let data = pixels(width * height, 1);
+            let mut scalar = XybImage::new(data.clone(), width, height);
+            make_positive_xyb(&mut scalar, SimdImpl::Scalar);
+            let mut simd = XybImage::new(data, width, height);
+            make_positive_xyb(&mut simd, SimdImpl::Simd);
+
+            for (s, v) in scalar.data().iter().zip(simd.data()) {
+                // Y and B are exact; X may differ by the rounding FMA saves
+                assert_eq!(s[1..], v[1..], "{}x{}", width, height);
+                assert!((s[0] - v[0]).abs() <= 2.0 * f32::EPSILON * s[0].abs().max(1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_planar_and_downscale_are_bit_identical() {
+        for (width, height) in SIZES {
+            let data = pixels(width * height, 2);
+            let xyb = XybImage::new(data.clone(), width, height);
+            let mut planar = PlanarImage::zeros(width, height);
+            xyb_to_planar_into(&xyb, &mut planar);
+            for (c, plane) in planar.planes().iter().enumerate() {
+                assert!(plane.iter().zip(&data).all(|(p, px)| *p == px[c]));
+            }
+
+            let image = LinearRgbImage::new(data, width, height);
+            for impl_type in [
+                SimdImpl::Scalar,
+                SimdImpl::Simd,
+                #[cfg(feature = "unsafe-simd")]
+                SimdImpl::UnsafeSimd,
+            ] {
+                let mut simd = LinearRgbImage::new(Vec::new(), 0, 0);
+                downscale_by_2_into(&image, &mut simd, impl_type);
+                assert_eq!(
+                    (simd.width(), simd.height()),
+                    (width.div_ceil(2), height.div_ceil(2))
+                );
+                for (oy, row) in simd.data().chunks_exact(simd.width()).enumerate() {
+                    for (ox, pix) in row.iter().enumerate() {
+                        for (c, &v) in pix.iter().enumerate() {
+                            let mut sum = 0f64;
+                            for iy in 0..2 {
+                                for ix in 0..2 {
+                                    let x = (ox * 2 + ix).min(width - 1);
+                                    let y = (oy * 2 + iy).min(height - 1);
+                                    sum += f64::from(image.data()[y * width + x][c]);
+                                }
+                            }
+                            assert_eq!(
+                                v,
+                                (sum / 4.0) as f32,
+                                "{}x{} {:?}",
+                                width,
+                                height,
+                                impl_type
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
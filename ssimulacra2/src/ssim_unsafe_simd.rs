@@ -1,15 +1,77 @@
 //! SIMD implementation of SSIM map and edge diff map
 //!
-//! Uses AVX2/SSE intrinsics with safe memory access via safe_unaligned_simd.
+//! The kernels return raw per-plane sums; the caller divides by the pixel
+//! count and takes the roots, so bands of rows can be summed separately.
+//!
+//! Uses AVX-512 or AVX2/SSE intrinsics on x86_64 (the AVX2 kernels with safe
+//! memory access via safe_unaligned_simd), and NEON intrinsics on aarch64.
 
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::*;
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
 #[cfg(target_arch = "x86_64")]
 use safe_unaligned_simd::x86_64 as safe_simd;
+#[cfg(target_arch = "x86_64")]
+use std::sync::OnceLock;
 
 const C2: f32 = 0.0009f32;
 
+/// Checks that every plane holds the same whole number of `width`-sized rows.
+fn check_planes(width: usize, planes: &[[&[f32]; 3]]) {
+    let len = planes[0][0].len();
+    debug_check!(width > 0 && len > 0, "empty image");
+    debug_check!(len.is_multiple_of(width), "partial row");
+    for image in planes {
+        for plane in image.iter() {
+            debug_check!(plane.len() == len, "plane size mismatch");
+        }
+    }
+}
+
+/// x86 features the kernels dispatch on
+#[cfg(target_arch = "x86_64")]
+#[derive(Clone, Copy)]
+struct X86Features {
+    avx512f: bool,
+    avx2: bool,
+    fma: bool,
+}
+
+/// The running CPU's [`X86Features`], detected once: the maps run once per
+/// band of every scale, so the checks would otherwise repeat hundreds of
+/// times per comparison
+#[cfg(target_arch = "x86_64")]
+fn x86_features() -> X86Features {
+    static FEATURES: OnceLock<X86Features> = OnceLock::new();
+    *FEATURES.get_or_init(|| X86Features {
+        avx512f: is_x86_feature_detected!("avx512f"),
+        avx2: is_x86_feature_detected!("avx2"),
+        fma: is_x86_feature_detected!("fma"),
+    })
+}
+
+/// Lanes of a 16-lane block starting at `base` that lie before `len`: all of
+/// them except in the tail
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn lane_mask(base: usize, len: usize) -> __mmask16 {
+    let lanes = (len - base).min(16);
+    (u32::MAX >> (32 - lanes)) as __mmask16
+}
+
+/// Fast horizontal sum of 16 f32s in an AVX-512 register
+#[cfg(target_arch = "x86_64")]
+#[inline]
+#[target_feature(enable = "avx512f")]
+unsafe fn hsum_ps_avx512(v: __m512) -> f32 {
+    // Add high 256 to low 256 (the f64 extract needs only AVX-512F)
+    let low = _mm512_castps512_ps256(v);
+    let high = _mm256_castpd_ps(_mm512_extractf64x4_pd(_mm512_castps_pd(v), 1));
+    hsum_ps_avx(_mm256_add_ps(low, high))
+}
+
 /// Fast horizontal sum of 8 f32s in an AVX register
 #[cfg(target_arch = "x86_64")]
 #[inline]
@@ -27,38 +89,47 @@ unsafe fn hsum_ps_avx(v: __m256) -> f32 {
     _mm_cvtss_f32(sums)
 }
 
-/// Computes SSIM map using unsafe SIMD
+/// Sums the `(d, d^4)` SSIM error of each plane using unsafe SIMD
 pub fn ssim_map_unsafe(
     width: usize,
-    height: usize,
-    m1: &[Vec<f32>; 3],
-    m2: &[Vec<f32>; 3],
-    s11: &[Vec<f32>; 3],
-    s22: &[Vec<f32>; 3],
-    s12: &[Vec<f32>; 3],
+    m1: [&[f32]; 3],
+    m2: [&[f32]; 3],
+    s11: [&[f32]; 3],
+    s22: [&[f32]; 3],
+    s12: [&[f32]; 3],
 ) -> [f64; 3 * 2] {
     #[cfg(target_arch = "x86_64")]
     {
-        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
-            return unsafe { ssim_map_avx2(width, height, m1, m2, s11, s22, s12) };
+        let features = x86_features();
+        if features.avx512f {
+            return unsafe { ssim_map_avx512(width, m1, m2, s11, s22, s12) };
+        }
+        if features.avx2 && features.fma {
+            return unsafe { ssim_map_avx2(width, m1, m2, s11, s22, s12) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { ssim_map_neon(width, m1, m2, s11, s22, s12) };
         }
     }
-    ssim_map_scalar(width, height, m1, m2, s11, s22, s12)
+    ssim_map_scalar(width, m1, m2, s11, s22, s12)
 }
 
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx2", enable = "fma")]
 unsafe fn ssim_map_avx2(
     width: usize,
-    height: usize,
-    m1: &[Vec<f32>; 3],
-    m2: &[Vec<f32>; 3],
-    s11: &[Vec<f32>; 3],
-    s22: &[Vec<f32>; 3],
-    s12: &[Vec<f32>; 3],
+    m1: [&[f32]; 3],
+    m2: [&[f32]; 3],
+    s11: [&[f32]; 3],
+    s22: [&[f32]; 3],
+    s12: [&[f32]; 3],
 ) -> [f64; 3 * 2] {
-    let one_per_pixels = 1.0f64 / (width * height) as f64;
-    let mut plane_averages = [0f64; 3 * 2];
+    debug_check!(is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma"));
+    check_planes(width, &[m1, m2, s11, s22, s12]);
+    let mut plane_sums = [0f64; 3 * 2];
 
     let c2_vec = _mm256_set1_ps(C2);
     let one_vec = _mm256_set1_ps(1.0);
@@ -68,11 +139,11 @@ unsafe fn ssim_map_avx2(
         let mut sum_d = 0.0f64;
         let mut sum_d4 = 0.0f64;
 
-        let m1_plane = &m1[c];
-        let m2_plane = &m2[c];
-        let s11_plane = &s11[c];
-        let s22_plane = &s22[c];
-        let s12_plane = &s12[c];
+        let m1_plane = m1[c];
+        let m2_plane = m2[c];
+        let s11_plane = s11[c];
+        let s22_plane = s22[c];
+        let s12_plane = s12[c];
 
         let chunks_8 = m1_plane.len() / 8;
 
@@ -146,24 +217,221 @@ unsafe fn ssim_map_avx2(
             sum_d4 += d.powi(4);
         }
 
-        plane_averages[c * 2] = one_per_pixels * sum_d;
-        plane_averages[c * 2 + 1] = (one_per_pixels * sum_d4).sqrt().sqrt();
+        plane_sums[c * 2] = sum_d;
+        plane_sums[c * 2 + 1] = sum_d4;
     }
 
-    plane_averages
+    plane_sums
+}
+
+/// AVX-512 version of [`ssim_map_avx2`], 16 pixels at a time with a masked
+/// tail instead of a scalar remainder
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn ssim_map_avx512(
+    width: usize,
+    m1: [&[f32]; 3],
+    m2: [&[f32]; 3],
+    s11: [&[f32]; 3],
+    s22: [&[f32]; 3],
+    s12: [&[f32]; 3],
+) -> [f64; 3 * 2] {
+    debug_check!(is_x86_feature_detected!("avx512f"));
+    check_planes(width, &[m1, m2, s11, s22, s12]);
+    let mut plane_sums = [0f64; 3 * 2];
+
+    let c2_vec = _mm512_set1_ps(C2);
+    let one_vec = _mm512_set1_ps(1.0);
+    let zero_vec = _mm512_setzero_ps();
+
+    for c in 0..3 {
+        let mut sum_d = 0.0f64;
+        let mut sum_d4 = 0.0f64;
+
+        let m1_plane = m1[c];
+        let m2_plane = m2[c];
+        let s11_plane = s11[c];
+        let s22_plane = s22[c];
+        let s12_plane = s12[c];
+        let len = m1_plane.len();
+
+        let mut base = 0;
+        while base < len {
+            // Masked-off lanes load as zero and never touch memory
+            let mask = lane_mask(base, len);
+            let mu1 = _mm512_maskz_loadu_ps(mask, m1_plane[base..].as_ptr());
+            let mu2 = _mm512_maskz_loadu_ps(mask, m2_plane[base..].as_ptr());
+            let sigma11 = _mm512_maskz_loadu_ps(mask, s11_plane[base..].as_ptr());
+            let sigma22 = _mm512_maskz_loadu_ps(mask, s22_plane[base..].as_ptr());
+            let sigma12 = _mm512_maskz_loadu_ps(mask, s12_plane[base..].as_ptr());
+
+            let mu11 = _mm512_mul_ps(mu1, mu1);
+            let mu22 = _mm512_mul_ps(mu2, mu2);
+            let mu12 = _mm512_mul_ps(mu1, mu2);
+            let mu_diff = _mm512_sub_ps(mu1, mu2);
+
+            // num_m = 1 - mu_diff * mu_diff
+            let num_m = _mm512_sub_ps(one_vec, _mm512_mul_ps(mu_diff, mu_diff));
+
+            // num_s = 2 * (sigma12 - mu12) + C2
+            let s12_minus_mu12 = _mm512_sub_ps(sigma12, mu12);
+            let num_s = _mm512_add_ps(_mm512_add_ps(s12_minus_mu12, s12_minus_mu12), c2_vec);
+
+            // denom_s = (sigma11 - mu11) + (sigma22 - mu22) + C2
+            let s11_minus_mu11 = _mm512_sub_ps(sigma11, mu11);
+            let s22_minus_mu22 = _mm512_sub_ps(sigma22, mu22);
+            let denom_s = _mm512_add_ps(_mm512_add_ps(s11_minus_mu11, s22_minus_mu22), c2_vec);
+
+            // d = max(1 - (num_m * num_s) / denom_s, 0), zero in the masked lanes
+            let ratio = _mm512_div_ps(_mm512_mul_ps(num_m, num_s), denom_s);
+            let d = _mm512_max_ps(_mm512_sub_ps(one_vec, ratio), zero_vec);
+            let d = _mm512_maskz_mov_ps(mask, d);
+
+            let d2 = _mm512_mul_ps(d, d);
+            let d4 = _mm512_mul_ps(d2, d2);
+
+            sum_d += hsum_ps_avx512(d) as f64;
+            sum_d4 += hsum_ps_avx512(d4) as f64;
+            base += 16;
+        }
+
+        plane_sums[c * 2] = sum_d;
+        plane_sums[c * 2 + 1] = sum_d4;
+    }
+
+    plane_sums
+}
+
+/// Loads four floats from `plane` at `base`, bounds-checked
+#[cfg(target_arch = "aarch64")]
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn load4(plane: &[f32], base: usize) -> float32x4_t {
+    vld1q_f32(plane[base..][..4].as_ptr())
+}
+
+/// SSIM error `max(1 - num / denom, 0)` of four pixels
+#[cfg(target_arch = "aarch64")]
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn ssim_error_neon(
+    mu1: float32x4_t,
+    mu2: float32x4_t,
+    sigma11: float32x4_t,
+    sigma22: float32x4_t,
+    sigma12: float32x4_t,
+) -> float32x4_t {
+    let c2_vec = vdupq_n_f32(C2);
+    let one_vec = vdupq_n_f32(1.0);
+
+    let mu11 = vmulq_f32(mu1, mu1);
+    let mu22 = vmulq_f32(mu2, mu2);
+    let mu12 = vmulq_f32(mu1, mu2);
+    let mu_diff = vsubq_f32(mu1, mu2);
+
+    // num_m = 1 - mu_diff * mu_diff
+    let num_m = vsubq_f32(one_vec, vmulq_f32(mu_diff, mu_diff));
+
+    // num_s = 2 * (sigma12 - mu12) + C2
+    let s12_minus_mu12 = vsubq_f32(sigma12, mu12);
+    let num_s = vaddq_f32(vaddq_f32(s12_minus_mu12, s12_minus_mu12), c2_vec);
+
+    // denom_s = (sigma11 - mu11) + (sigma22 - mu22) + C2
+    let denom_s = vaddq_f32(
+        vaddq_f32(vsubq_f32(sigma11, mu11), vsubq_f32(sigma22, mu22)),
+        c2_vec,
+    );
+
+    // d = max(1 - (num_m * num_s) / denom_s, 0)
+    let d = vsubq_f32(one_vec, vdivq_f32(vmulq_f32(num_m, num_s), denom_s));
+    vmaxq_f32(d, vdupq_n_f32(0.0))
+}
+
+/// NEON version of `ssim_map_avx2`, 4-wide and unrolled twice
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn ssim_map_neon(
+    width: usize,
+    m1: [&[f32]; 3],
+    m2: [&[f32]; 3],
+    s11: [&[f32]; 3],
+    s22: [&[f32]; 3],
+    s12: [&[f32]; 3],
+) -> [f64; 3 * 2] {
+    debug_check!(std::arch::is_aarch64_feature_detected!("neon"));
+    check_planes(width, &[m1, m2, s11, s22, s12]);
+    let mut plane_sums = [0f64; 3 * 2];
+
+    for c in 0..3 {
+        let mut sum_d = 0.0f64;
+        let mut sum_d4 = 0.0f64;
+
+        let planes = [m1[c], m2[c], s11[c], s22[c], s12[c]];
+        let len = planes[0].len();
+
+        let chunks_8 = len / 8;
+
+        for chunk in 0..chunks_8 {
+            let base = chunk * 8;
+            let [d_lo, d_hi] = [base, base + 4].map(|base| {
+                let [mu1, mu2, sigma11, sigma22, sigma12] = planes.map(|plane| load4(plane, base));
+                ssim_error_neon(mu1, mu2, sigma11, sigma22, sigma12)
+            });
+
+            // d^4 = d * d * d * d
+            let d2_lo = vmulq_f32(d_lo, d_lo);
+            let d2_hi = vmulq_f32(d_hi, d_hi);
+            let d4 = vaddq_f32(vmulq_f32(d2_lo, d2_lo), vmulq_f32(d2_hi, d2_hi));
+
+            sum_d += vaddvq_f32(vaddq_f32(d_lo, d_hi)) as f64;
+            sum_d4 += vaddvq_f32(d4) as f64;
+        }
+
+        // Handle remainder with scalar
+        for x in chunks_8 * 8..len {
+            let d = ssim_error(
+                planes[0][x],
+                planes[1][x],
+                planes[2][x],
+                planes[3][x],
+                planes[4][x],
+            );
+            sum_d += d;
+            sum_d4 += d.powi(4);
+        }
+
+        plane_sums[c * 2] = sum_d;
+        plane_sums[c * 2 + 1] = sum_d4;
+    }
+
+    plane_sums
+}
+
+/// SSIM error of one pixel, in f64
+#[inline]
+fn ssim_error(mu1: f32, mu2: f32, sigma11: f32, sigma22: f32, sigma12: f32) -> f64 {
+    let mu11 = mu1 * mu1;
+    let mu22 = mu2 * mu2;
+    let mu12 = mu1 * mu2;
+    let mu_diff = mu1 - mu2;
+
+    let num_m = f64::from(mu_diff).mul_add(-f64::from(mu_diff), 1.0f64);
+    let num_s = 2f64.mul_add(f64::from(sigma12 - mu12), f64::from(C2));
+    let denom_s = f64::from(sigma11 - mu11) + f64::from(sigma22 - mu22) + f64::from(C2);
+    let d = 1.0f64 - (num_m * num_s) / denom_s;
+    d.max(0.0)
 }
 
 fn ssim_map_scalar(
     width: usize,
-    height: usize,
-    m1: &[Vec<f32>; 3],
-    m2: &[Vec<f32>; 3],
-    s11: &[Vec<f32>; 3],
-    s22: &[Vec<f32>; 3],
-    s12: &[Vec<f32>; 3],
+    m1: [&[f32]; 3],
+    m2: [&[f32]; 3],
+    s11: [&[f32]; 3],
+    s22: [&[f32]; 3],
+    s12: [&[f32]; 3],
 ) -> [f64; 3 * 2] {
-    let one_per_pixels = 1.0f64 / (width * height) as f64;
-    let mut plane_averages = [0f64; 3 * 2];
+    check_planes(width, &[m1, m2, s11, s22, s12]);
+    let mut plane_sums = [0f64; 3 * 2];
 
     for c in 0..3 {
         let mut sum1 = [0.0f64; 2];
@@ -175,60 +443,58 @@ fn ssim_map_scalar(
             ),
         ) {
             for x in 0..width {
-                let mu1 = row_m1[x];
-                let mu2 = row_m2[x];
-                let mu11 = mu1 * mu1;
-                let mu22 = mu2 * mu2;
-                let mu12 = mu1 * mu2;
-                let mu_diff = mu1 - mu2;
-
-                let num_m = f64::from(mu_diff).mul_add(-f64::from(mu_diff), 1.0f64);
-                let num_s = 2f64.mul_add(f64::from(row_s12[x] - mu12), f64::from(C2));
-                let denom_s =
-                    f64::from(row_s11[x] - mu11) + f64::from(row_s22[x] - mu22) + f64::from(C2);
-                let mut d = 1.0f64 - (num_m * num_s) / denom_s;
-                d = d.max(0.0);
+                let d = ssim_error(row_m1[x], row_m2[x], row_s11[x], row_s22[x], row_s12[x]);
                 sum1[0] += d;
                 sum1[1] += d.powi(4);
             }
         }
-        plane_averages[c * 2] = one_per_pixels * sum1[0];
-        plane_averages[c * 2 + 1] = (one_per_pixels * sum1[1]).sqrt().sqrt();
+        plane_sums[c * 2] = sum1[0];
+        plane_sums[c * 2 + 1] = sum1[1];
     }
 
-    plane_averages
+    plane_sums
 }
 
-/// Computes edge diff map using unsafe SIMD
+/// Sums the `(d, d^4)` artifact and detail-lost maps of each plane using
+/// unsafe SIMD
 pub fn edge_diff_map_unsafe(
     width: usize,
-    height: usize,
-    img1: &[Vec<f32>; 3],
-    mu1: &[Vec<f32>; 3],
-    img2: &[Vec<f32>; 3],
-    mu2: &[Vec<f32>; 3],
+    img1: [&[f32]; 3],
+    mu1: [&[f32]; 3],
+    img2: [&[f32]; 3],
+    mu2: [&[f32]; 3],
 ) -> [f64; 3 * 4] {
     #[cfg(target_arch = "x86_64")]
     {
-        if is_x86_feature_detected!("avx2") {
-            return unsafe { edge_diff_map_avx2(width, height, img1, mu1, img2, mu2) };
+        let features = x86_features();
+        if features.avx512f {
+            return unsafe { edge_diff_map_avx512(width, img1, mu1, img2, mu2) };
+        }
+        if features.avx2 {
+            return unsafe { edge_diff_map_avx2(width, img1, mu1, img2, mu2) };
         }
     }
-    edge_diff_map_scalar(width, height, img1, mu1, img2, mu2)
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return unsafe { edge_diff_map_neon(width, img1, mu1, img2, mu2) };
+        }
+    }
+    edge_diff_map_scalar(width, img1, mu1, img2, mu2)
 }
 
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx2")]
 unsafe fn edge_diff_map_avx2(
     width: usize,
-    height: usize,
-    img1: &[Vec<f32>; 3],
-    mu1: &[Vec<f32>; 3],
-    img2: &[Vec<f32>; 3],
-    mu2: &[Vec<f32>; 3],
+    img1: [&[f32]; 3],
+    mu1: [&[f32]; 3],
+    img2: [&[f32]; 3],
+    mu2: [&[f32]; 3],
 ) -> [f64; 3 * 4] {
-    let one_per_pixels = 1.0f64 / (width * height) as f64;
-    let mut plane_averages = [0f64; 3 * 4];
+    debug_check!(is_x86_feature_detected!("avx2"));
+    check_planes(width, &[img1, mu1, img2, mu2]);
+    let mut plane_sums = [0f64; 3 * 4];
 
     let one_vec = _mm256_set1_ps(1.0);
     let zero_vec = _mm256_setzero_ps();
@@ -240,10 +506,10 @@ unsafe fn edge_diff_map_avx2(
         let mut sum_detail_lost = 0.0f64;
         let mut sum_detail_lost4 = 0.0f64;
 
-        let img1_plane = &img1[c];
-        let mu1_plane = &mu1[c];
-        let img2_plane = &img2[c];
-        let mu2_plane = &mu2[c];
+        let img1_plane = img1[c];
+        let mu1_plane = mu1[c];
+        let img2_plane = img2[c];
+        let mu2_plane = mu2[c];
 
         let chunks_8 = img1_plane.len() / 8;
 
@@ -306,25 +572,198 @@ unsafe fn edge_diff_map_avx2(
             sum_detail_lost4 += detail_lost.powi(4);
         }
 
-        plane_averages[c * 4] = one_per_pixels * sum_artifact;
-        plane_averages[c * 4 + 1] = (one_per_pixels * sum_artifact4).sqrt().sqrt();
-        plane_averages[c * 4 + 2] = one_per_pixels * sum_detail_lost;
-        plane_averages[c * 4 + 3] = (one_per_pixels * sum_detail_lost4).sqrt().sqrt();
+        plane_sums[c * 4] = sum_artifact;
+        plane_sums[c * 4 + 1] = sum_artifact4;
+        plane_sums[c * 4 + 2] = sum_detail_lost;
+        plane_sums[c * 4 + 3] = sum_detail_lost4;
+    }
+
+    plane_sums
+}
+
+/// AVX-512 version of [`edge_diff_map_avx2`], 16 pixels at a time with a
+/// masked tail instead of a scalar remainder
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn edge_diff_map_avx512(
+    width: usize,
+    img1: [&[f32]; 3],
+    mu1: [&[f32]; 3],
+    img2: [&[f32]; 3],
+    mu2: [&[f32]; 3],
+) -> [f64; 3 * 4] {
+    debug_check!(is_x86_feature_detected!("avx512f"));
+    check_planes(width, &[img1, mu1, img2, mu2]);
+    let mut plane_sums = [0f64; 3 * 4];
+
+    let one_vec = _mm512_set1_ps(1.0);
+    let zero_vec = _mm512_setzero_ps();
+
+    for c in 0..3 {
+        let mut sum_artifact = 0.0f64;
+        let mut sum_artifact4 = 0.0f64;
+        let mut sum_detail_lost = 0.0f64;
+        let mut sum_detail_lost4 = 0.0f64;
+
+        let img1_plane = img1[c];
+        let mu1_plane = mu1[c];
+        let img2_plane = img2[c];
+        let mu2_plane = mu2[c];
+        let len = img1_plane.len();
+
+        let mut base = 0;
+        while base < len {
+            // Masked-off lanes load as zero, which gives d1 = 0
+            let mask = lane_mask(base, len);
+            let row1 = _mm512_maskz_loadu_ps(mask, img1_plane[base..].as_ptr());
+            let rowm1 = _mm512_maskz_loadu_ps(mask, mu1_plane[base..].as_ptr());
+            let row2 = _mm512_maskz_loadu_ps(mask, img2_plane[base..].as_ptr());
+            let rowm2 = _mm512_maskz_loadu_ps(mask, mu2_plane[base..].as_ptr());
+
+            // edge = |row - rowm| (AVX-512F has abs; andnot needs DQ)
+            let edge1 = _mm512_abs_ps(_mm512_sub_ps(row1, rowm1));
+            let edge2 = _mm512_abs_ps(_mm512_sub_ps(row2, rowm2));
+
+            // d1 = (1 + edge2) / (1 + edge1) - 1
+            let ratio = _mm512_div_ps(_mm512_add_ps(one_vec, edge2), _mm512_add_ps(one_vec, edge1));
+            let d1 = _mm512_sub_ps(ratio, one_vec);
+
+            // artifact = max(d1, 0), detail_lost = max(-d1, 0)
+            let artifact = _mm512_max_ps(d1, zero_vec);
+            let detail_lost = _mm512_max_ps(_mm512_sub_ps(zero_vec, d1), zero_vec);
+
+            let artifact2 = _mm512_mul_ps(artifact, artifact);
+            let artifact4 = _mm512_mul_ps(artifact2, artifact2);
+            let detail_lost2 = _mm512_mul_ps(detail_lost, detail_lost);
+            let detail_lost4 = _mm512_mul_ps(detail_lost2, detail_lost2);
+
+            sum_artifact += hsum_ps_avx512(artifact) as f64;
+            sum_artifact4 += hsum_ps_avx512(artifact4) as f64;
+            sum_detail_lost += hsum_ps_avx512(detail_lost) as f64;
+            sum_detail_lost4 += hsum_ps_avx512(detail_lost4) as f64;
+            base += 16;
+        }
+
+        plane_sums[c * 4] = sum_artifact;
+        plane_sums[c * 4 + 1] = sum_artifact4;
+        plane_sums[c * 4 + 2] = sum_detail_lost;
+        plane_sums[c * 4 + 3] = sum_detail_lost4;
+    }
+
+    plane_sums
+}
+
+/// `(1 + edge2) / (1 + edge1) - 1` of four pixels, where the edges are the
+/// absolute differences between each image and its blurred mean
+#[cfg(target_arch = "aarch64")]
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn edge_diff_neon(
+    row1: float32x4_t,
+    rowm1: float32x4_t,
+    row2: float32x4_t,
+    rowm2: float32x4_t,
+) -> float32x4_t {
+    let one_vec = vdupq_n_f32(1.0);
+    let edge1 = vabsq_f32(vsubq_f32(row1, rowm1));
+    let edge2 = vabsq_f32(vsubq_f32(row2, rowm2));
+    let ratio = vdivq_f32(vaddq_f32(one_vec, edge2), vaddq_f32(one_vec, edge1));
+    vsubq_f32(ratio, one_vec)
+}
+
+/// NEON version of `edge_diff_map_avx2`, 4-wide and unrolled twice
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn edge_diff_map_neon(
+    width: usize,
+    img1: [&[f32]; 3],
+    mu1: [&[f32]; 3],
+    img2: [&[f32]; 3],
+    mu2: [&[f32]; 3],
+) -> [f64; 3 * 4] {
+    debug_check!(std::arch::is_aarch64_feature_detected!("neon"));
+    check_planes(width, &[img1, mu1, img2, mu2]);
+    let mut plane_sums = [0f64; 3 * 4];
+
+    let zero_vec = vdupq_n_f32(0.0);
+
+    for c in 0..3 {
+        let mut sum_artifact = 0.0f64;
+        let mut sum_artifact4 = 0.0f64;
+        let mut sum_detail_lost = 0.0f64;
+        let mut sum_detail_lost4 = 0.0f64;
+
+        let planes = [img1[c], mu1[c], img2[c], mu2[c]];
+        let len = planes[0].len();
+
+        let chunks_8 = len / 8;
+
+        for chunk in 0..chunks_8 {
+            let base = chunk * 8;
+            let [d1_lo, d1_hi] = [base, base + 4].map(|base| {
+                let [row1, rowm1, row2, rowm2] = planes.map(|plane| load4(plane, base));
+                edge_diff_neon(row1, rowm1, row2, rowm2)
+            });
+
+            // artifact = max(d1, 0), detail_lost = max(-d1, 0)
+            let artifact = [d1_lo, d1_hi].map(|d1| vmaxq_f32(d1, zero_vec));
+            let detail_lost = [d1_lo, d1_hi].map(|d1| vmaxq_f32(vnegq_f32(d1), zero_vec));
+
+            // Sums of both halves and of their 4th powers
+            let sums = |[lo, hi]: [float32x4_t; 2]| {
+                let lo2 = vmulq_f32(lo, lo);
+                let hi2 = vmulq_f32(hi, hi);
+                let sum4 = vaddq_f32(vmulq_f32(lo2, lo2), vmulq_f32(hi2, hi2));
+                (
+                    vaddvq_f32(vaddq_f32(lo, hi)) as f64,
+                    vaddvq_f32(sum4) as f64,
+                )
+            };
+            let (artifact, artifact4) = sums(artifact);
+            let (detail_lost, detail_lost4) = sums(detail_lost);
+            sum_artifact += artifact;
+            sum_artifact4 += artifact4;
+            sum_detail_lost += detail_lost;
+            sum_detail_lost4 += detail_lost4;
+        }
+
+        // Handle remainder with scalar
+        for x in chunks_8 * 8..len {
+            let d1 = edge_diff(planes[0][x], planes[1][x], planes[2][x], planes[3][x]);
+
+            let artifact = d1.max(0.0);
+            sum_artifact += artifact;
+            sum_artifact4 += artifact.powi(4);
+
+            let detail_lost = (-d1).max(0.0);
+            sum_detail_lost += detail_lost;
+            sum_detail_lost4 += detail_lost.powi(4);
+        }
+
+        plane_sums[c * 4] = sum_artifact;
+        plane_sums[c * 4 + 1] = sum_artifact4;
+        plane_sums[c * 4 + 2] = sum_detail_lost;
+        plane_sums[c * 4 + 3] = sum_detail_lost4;
     }
 
-    plane_averages
+    plane_sums
+}
+
+/// Edge difference of one pixel, in f64
+#[inline]
+fn edge_diff(row1: f32, rowm1: f32, row2: f32, rowm2: f32) -> f64 {
+    (1.0 + f64::from((row2 - rowm2).abs())) / (1.0 + f64::from((row1 - rowm1).abs())) - 1.0
 }
 
 fn edge_diff_map_scalar(
     width: usize,
-    height: usize,
-    img1: &[Vec<f32>; 3],
-    mu1: &[Vec<f32>; 3],
-    img2: &[Vec<f32>; 3],
-    mu2: &[Vec<f32>; 3],
+    img1: [&[f32]; 3],
+    mu1: [&[f32]; 3],
+    img2: [&[f32]; 3],
+    mu2: [&[f32]; 3],
 ) -> [f64; 3 * 4] {
-    let one_per_pixels = 1.0f64 / (width * height) as f64;
-    let mut plane_averages = [0f64; 3 * 4];
+    check_planes(width, &[img1, mu1, img2, mu2]);
+    let mut plane_sums = [0f64; 3 * 4];
 
     for c in 0..3 {
         let mut sum1 = [0.0f64; 4];
@@ -334,9 +773,7 @@ fn edge_diff_map_scalar(
                 .zip(mu1[c].chunks_exact(width).zip(mu2[c].chunks_exact(width))),
         ) {
             for x in 0..width {
-                let d1: f64 = (1.0 + f64::from((row2[x] - rowm2[x]).abs()))
-                    / (1.0 + f64::from((row1[x] - rowm1[x]).abs()))
-                    - 1.0;
+                let d1 = edge_diff(row1[x], rowm1[x], row2[x], rowm2[x]);
 
                 let artifact = d1.max(0.0);
                 sum1[0] += artifact;
@@ -347,11 +784,119 @@ fn edge_diff_map_scalar(
                 sum1[3] += detail_lost.powi(4);
             }
         }
-        plane_averages[c * 4] = one_per_pixels * sum1[0];
-        plane_averages[c * 4 + 1] = (one_per_pixels * sum1[1]).sqrt().sqrt();
-        plane_averages[c * 4 + 2] = one_per_pixels * sum1[2];
-        plane_averages[c * 4 + 3] = (one_per_pixels * sum1[3]).sqrt().sqrt();
+        plane_sums[c * 4] = sum1[0];
+        plane_sums[c * 4 + 1] = sum1[1];
+        plane_sums[c * 4 + 2] = sum1[2];
+        plane_sums[c * 4 + 3] = sum1[3];
     }
 
-    plane_averages
+    plane_sums
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Planes = [Vec<f32>; 3];
+
+    fn planes(width: usize, height: usize, seed: usize) -> Planes {
+        std::array::from_fn(|c| {
+            (0..width * height)
+                .map(|i| ((i * 7919 + (seed * 3 + c) * 104_729) % 1009) as f32 / 1009.0)
+                .collect()
+        })
+    }
+
+    fn view(p: &Planes) -> [&[f32]; 3] {
+        p.each_ref().map(Vec::as_slice)
+    }
+
+    /// Images, means and plausible second moments (above the squared means)
+    /// in the order `[img1, img2, mu1, mu2, s11, s22, s12]`
+    fn fixture(width: usize, height: usize) -> [Planes; 7] {
+        let [img1, img2, mu1, mu2, noise] = [0, 1, 2, 3, 4].map(|seed| planes(width, height, seed));
+        let moment = |mean: &Planes, extra: &Planes| -> Planes {
+            std::array::from_fn(|c| {
+                mean[c]
+                    .iter()
+                    .zip(&extra[c])
+                    .map(|(m, v)| m * m + 0.1 * v)
+                    .collect()
+            })
+        };
+        let (s11, s22, s12) = (
+            moment(&mu1, &img1),
+            moment(&mu2, &img2),
+            moment(&mu1, &noise),
+        );
+        [img1, img2, mu1, mu2, s11, s22, s12]
+    }
+
+    fn assert_sums_close(simd: &[f64], scalar: &[f64]) {
+        for (i, (a, b)) in simd.iter().zip(scalar).enumerate() {
+            assert!(
+                (a - b).abs() <= 1e-4 * b.abs().max(1.0),
+                "sum {}: {} vs {}",
+                i,
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn test_simd_sums_match_scalar() {
+        // 37 wide: whole 8- and 16-pixel blocks plus a tail
+        let width = 37;
+        let [img1, img2, mu1, mu2, s11, s22, s12] = fixture(width, 11);
+        let [img1, img2, mu1, mu2, s11, s22, s12] =
+            [&img1, &img2, &mu1, &mu2, &s11, &s22, &s12].map(view);
+
+        assert_sums_close(
+            &ssim_map_unsafe(width, mu1, mu2, s11, s22, s12),
+            &ssim_map_scalar(width, mu1, mu2, s11, s22, s12),
+        );
+        assert_sums_close(
+            &edge_diff_map_unsafe(width, img1, mu1, img2, mu2),
+            &edge_diff_map_scalar(width, img1, mu1, img2, mu2),
+        );
+    }
+
+    /// The dispatch prefers AVX-512, so check the AVX2 kernels separately,
+    /// and the AVX-512 tail at every length
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_x86_kernels_match_scalar() {
+        let features = x86_features();
+        for width in 1..=33 {
+            let [img1, img2, mu1, mu2, s11, s22, s12] = fixture(width, 1);
+            let [img1, img2, mu1, mu2, s11, s22, s12] =
+                [&img1, &img2, &mu1, &mu2, &s11, &s22, &s12].map(view);
+            let ssim = ssim_map_scalar(width, mu1, mu2, s11, s22, s12);
+            let edge = edge_diff_map_scalar(width, img1, mu1, img2, mu2);
+
+            if features.avx2 && features.fma {
+                assert_sums_close(
+                    &unsafe { ssim_map_avx2(width, mu1, mu2, s11, s22, s12) },
+                    &ssim,
+                );
+            }
+            if features.avx2 {
+                assert_sums_close(
+                    &unsafe { edge_diff_map_avx2(width, img1, mu1, img2, mu2) },
+                    &edge,
+                );
+            }
+            if features.avx512f {
+                assert_sums_close(
+                    &unsafe { ssim_map_avx512(width, mu1, mu2, s11, s22, s12) },
+                    &ssim,
+                );
+                assert_sums_close(
+                    &unsafe { edge_diff_map_avx512(width, img1, mu1, img2, mu2) },
+                    &edge,
+                );
+            }
+        }
+    }
 }
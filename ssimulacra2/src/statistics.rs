@@ -0,0 +1,221 @@
+//! Expert API for scoring from externally computed statistics.
+//!
+//! SSIMULACRA2 blurs five planar images per scale (the two inputs and the
+//! three second moments). Systems that already maintain Gaussian pyramids of
+//! the images, such as an encoder's lookahead, can compute those themselves and
+//! call [`score_from_statistics`] for each scale, then combine the scales with
+//! [`aggregate_scales`]. This skips the blurs and the XYB conversion this crate
+//! would otherwise redo.
+//!
+//! The statistics must match what the built-in pipeline computes:
+//! - `img1`/`img2`: planar XYB with the SSIMULACRA2 offsets applied
+//!   (see [`positive_xyb_planes`])
+//! - `mu1`/`mu2`: Gaussian blur (sigma 1.5) of `img1`/`img2`
+//! - `s11`/`s22`/`s12`: blur of `img1 * img1`, `img2 * img2` and `img1 * img2`
+//!   (raw second moments, not variances)
+//!
+//! Scales are 2x box-downscaled from the previous scale in linear RGB, up to
+//! six scales. A scale is only halved into the next while both its sides are
+//! at least 8, so the coarsest scale may be as small as 4 (64x48 gives four
+//! scales, down to 8x6).
+
+use crate::input::LinearRgbImage;
+use crate::planar::PlanarImage;
+use crate::xyb::linear_rgb_to_xyb_with_config;
+use crate::{
+    edge_diff_map, make_positive_xyb, score_scales, ssim_map, Ssimulacra2Config, Ssimulacra2Error,
+    Stage, NUM_SCALES,
+};
+
+/// Per-scale averages that feed the final score.
+///
+/// For each channel `c` (X, Y, B):
+/// - `avg_ssim[c * 2]` and `avg_ssim[c * 2 + 1]` are the 1-norm and 4-norm of
+///   the SSIM error map
+/// - `avg_edgediff[c * 4]` and `avg_edgediff[c * 4 + 1]` are the 1-norm and
+///   4-norm of the ringing/blocking artifact map
+/// - `avg_edgediff[c * 4 + 2]` and `avg_edgediff[c * 4 + 3]` are the 1-norm and
+///   4-norm of the detail-lost (blurring) map
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScaleStatistics {
+    /// SSIM error norms per channel
+    pub avg_ssim: [f64; 3 * 2],
+    /// Edge difference norms per channel
+    pub avg_edgediff: [f64; 3 * 4],
+}
+
+/// Score together with the per-scale statistics it was derived from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetailedScore {
+    /// SSIMULACRA2 score
+    pub score: f64,
+    /// Statistics for each scale, finest first
+    pub scales: Vec<ScaleStatistics>,
+    /// Upper bound on how far `score` may deviate from the full-precision
+    /// result because of reduced-precision reference storage (see
+    /// [`ReferencePrecision`](crate::ReferencePrecision)). Zero when all data
+    /// was kept at full precision.
+    pub error_bound: f64,
+}
+
+/// Computes the statistics for one scale from pre-blurred inputs.
+///
+/// All planes must hold `width * height` values. See the [module
+/// documentation](self) for what each argument must contain.
+///
+/// # Errors
+/// - If any plane doesn't hold `width * height` values
+/// - If `width` or `height` is zero
+#[allow(clippy::too_many_arguments)]
+pub fn score_from_statistics(
+    mu1: &[Vec<f32>; 3],
+    mu2: &[Vec<f32>; 3],
+    s11: &[Vec<f32>; 3],
+    s22: &[Vec<f32>; 3],
+    s12: &[Vec<f32>; 3],
+    img1: &[Vec<f32>; 3],
+    img2: &[Vec<f32>; 3],
+    (width, height): (usize, usize),
+    config: Ssimulacra2Config,
+) -> Result<ScaleStatistics, Ssimulacra2Error> {
+    if width == 0 || height == 0 {
+        return Err(Ssimulacra2Error::InvalidImageSize);
+    }
+    let size = width * height;
+    let all_planes = [mu1, mu2, s11, s22, s12, img1, img2];
+    if all_planes
+        .iter()
+        .any(|planes| planes.iter().any(|p| p.len() != size))
+    {
+        return Err(Ssimulacra2Error::NonMatchingImageDimensions);
+    }
+
+    let impl_type = config.backend_for(Stage::Ssim);
+    Ok(ScaleStatistics {
+        avg_ssim: ssim_map(
+            width,
+            height,
+            mu1,
+            mu2,
+            s11,
+            s22,
+            s12,
+            impl_type,
+            config.norm_exponents,
+            &config.parallelism,
+        ),
+        avg_edgediff: edge_diff_map(
+            width,
+            height,
+            img1,
+            mu1,
+            img2,
+            mu2,
+            impl_type,
+            config.norm_exponents,
+            &config.parallelism,
+        ),
+    })
+}
+
+/// Combines per-scale statistics, finest scale first, into the final score.
+///
+/// Scales beyond the sixth are ignored, matching the built-in pipeline.
+pub fn aggregate_scales(scales: &[ScaleStatistics]) -> f64 {
+    score_scales(&scales[..scales.len().min(NUM_SCALES)])
+}
+
+/// Converts one scale of linear RGB to the offset planar XYB the statistics
+/// are computed on.
+pub fn positive_xyb_planes(image: &LinearRgbImage, config: Ssimulacra2Config) -> PlanarImage {
+    let impl_type = config.backend_for(Stage::Xyb);
+    let mut xyb = linear_rgb_to_xyb_with_config(image, config);
+    make_positive_xyb(&mut xyb, impl_type);
+    PlanarImage::from(&xyb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_image;
+    use crate::{compute_ssimulacra2_with_config, downscale_by_2, image_multiply, Blur, Stage};
+
+    #[test]
+    fn test_external_statistics_match_pipeline() {
+        let config = Ssimulacra2Config::default();
+        let mut img1 = test_image(64, 48, 1);
+        let mut img2 = test_image(64, 48, 0x5555_5555);
+        let expected =
+            compute_ssimulacra2_with_config(img1.clone(), img2.clone(), config.clone()).unwrap();
+
+        let mut scales = Vec::new();
+        // As in the pipeline, the size is checked before halving
+        for scale in 0..NUM_SCALES {
+            if img1.width() < 8 || img1.height() < 8 {
+                break;
+            }
+            if scale > 0 {
+                img1 = downscale_by_2(&img1, config.backend_for(Stage::Xyb));
+                img2 = downscale_by_2(&img2, config.backend_for(Stage::Xyb));
+            }
+            let (width, height) = (img1.width(), img1.height());
+
+            let p1 = positive_xyb_planes(&img1, config.clone());
+            let p2 = positive_xyb_planes(&img2, config.clone());
+            let mut blur = Blur::with_config(width, height, &config);
+            let mut mul = PlanarImage::zeros(width, height).into_planes();
+            let mut moment = |a: &PlanarImage, b: &PlanarImage| {
+                image_multiply(
+                    a.planes(),
+                    b.planes(),
+                    &mut mul,
+                    config.backend_for(Stage::Ssim),
+                );
+                blur.blur(&mul)
+            };
+            let s11 = moment(&p1, &p1);
+            let s22 = moment(&p2, &p2);
+            let s12 = moment(&p1, &p2);
+            let mu1 = blur.blur(p1.planes());
+            let mu2 = blur.blur(p2.planes());
+
+            scales.push(
+                score_from_statistics(
+                    &mu1,
+                    &mu2,
+                    &s11,
+                    &s22,
+                    &s12,
+                    p1.planes(),
+                    p2.planes(),
+                    (width, height),
+                    config.clone(),
+                )
+                .unwrap(),
+            );
+        }
+
+        // 64x48, 32x24, 16x12 and 8x6
+        assert_eq!(scales.len(), 4);
+        assert_ne!(scales[3], ScaleStatistics::default());
+        assert_eq!(aggregate_scales(&scales), expected);
+    }
+
+    #[test]
+    fn test_statistics_reject_mismatched_planes() {
+        let planes = PlanarImage::zeros(16, 16).into_planes();
+        let short = PlanarImage::zeros(16, 8).into_planes();
+        let result = score_from_statistics(
+            &planes,
+            &planes,
+            &planes,
+            &planes,
+            &short,
+            &planes,
+            &planes,
+            (16, 16),
+            Ssimulacra2Config::default(),
+        );
+        assert_eq!(result, Err(Ssimulacra2Error::NonMatchingImageDimensions));
+    }
+}
@@ -0,0 +1,217 @@
+//! Correlation between metric scores and subjective ratings.
+//!
+//! Quality metrics are judged by how well they agree with mean opinion scores
+//! (MOS) collected from viewers. [`Correlation`] reports the three
+//! coefficients the literature uses: Pearson's linear correlation (PLCC),
+//! Spearman's rank-order correlation (SROCC) and Kendall's rank-order
+//! correlation (KROCC). The rank correlations only depend on ordering, so they
+//! are unaffected by the metric's nonlinear scale and are usually the headline
+//! number when comparing configurations.
+//!
+//! SSIMULACRA2 scores and MOS both increase with quality, so a good fit gives
+//! coefficients close to 1.
+
+use std::fmt;
+
+/// Correlation of a set of scores with subjective ratings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Correlation {
+    /// Number of score/rating pairs
+    pub samples: usize,
+    /// Pearson linear correlation coefficient (PLCC)
+    pub pearson: f64,
+    /// Spearman rank-order correlation coefficient (SROCC), with tied values
+    /// assigned their average rank
+    pub spearman: f64,
+    /// Kendall rank correlation coefficient (KROCC), as tau-b so ties in
+    /// either input are accounted for
+    pub kendall: f64,
+}
+
+impl Correlation {
+    /// Computes all coefficients between `scores` and `mos`, where `mos[i]` is
+    /// the rating of the pair scored `scores[i]`.
+    ///
+    /// Returns `None` if the slices differ in length, hold fewer than two
+    /// values, contain non-finite values, or either is constant (the
+    /// coefficients are undefined then).
+    pub fn compute(scores: &[f64], mos: &[f64]) -> Option<Self> {
+        let pearson = pearson(scores, mos)?;
+        Some(Self {
+            samples: scores.len(),
+            pearson,
+            spearman: pearson_unchecked(&ranks(scores), &ranks(mos)),
+            kendall: kendall_tau_b(scores, mos),
+        })
+    }
+
+    /// Spearman rank-order correlation, the usual headline figure
+    pub fn srocc(&self) -> f64 {
+        self.spearman
+    }
+}
+
+impl fmt::Display for Correlation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PLCC {:.4}, SROCC {:.4}, KROCC {:.4} ({} pairs)",
+            self.pearson, self.spearman, self.kendall, self.samples
+        )
+    }
+}
+
+/// Pearson linear correlation of `x` and `y`.
+///
+/// Returns `None` under the same conditions as [`Correlation::compute`].
+pub fn pearson(x: &[f64], y: &[f64]) -> Option<f64> {
+    let valid = x.len() == y.len()
+        && x.len() >= 2
+        && x.iter().chain(y).all(|v| v.is_finite())
+        && !is_constant(x)
+        && !is_constant(y);
+    valid.then(|| pearson_unchecked(x, y))
+}
+
+/// Spearman rank-order correlation of `x` and `y`.
+///
+/// Returns `None` under the same conditions as [`Correlation::compute`].
+pub fn spearman(x: &[f64], y: &[f64]) -> Option<f64> {
+    pearson(x, y)?;
+    Some(pearson_unchecked(&ranks(x), &ranks(y)))
+}
+
+/// Kendall rank correlation (tau-b) of `x` and `y`.
+///
+/// Returns `None` under the same conditions as [`Correlation::compute`].
+pub fn kendall(x: &[f64], y: &[f64]) -> Option<f64> {
+    pearson(x, y)?;
+    Some(kendall_tau_b(x, y))
+}
+
+fn is_constant(values: &[f64]) -> bool {
+    values.iter().all(|&v| v == values[0])
+}
+
+fn pearson_unchecked(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (&a, &b) in x.iter().zip(y) {
+        let (dx, dy) = (a - mean_x, b - mean_y);
+        cov += dx * dy;
+        var_x += dx * dx;
+        var_y += dy * dy;
+    }
+    (cov / (var_x * var_y).sqrt()).clamp(-1.0, 1.0)
+}
+
+/// 1-based ranks, with tied values sharing the average of their ranks
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Tau-b over all pairs. Quadratic, which is fine for subjective corpora of a
+/// few thousand ratings.
+fn kendall_tau_b(x: &[f64], y: &[f64]) -> f64 {
+    let (mut concordant, mut discordant) = (0i64, 0i64);
+    let (mut tied_x, mut tied_y) = (0i64, 0i64);
+    for i in 0..x.len() {
+        for j in i + 1..x.len() {
+            let dx = x[i].total_cmp(&x[j]) as i64;
+            let dy = y[i].total_cmp(&y[j]) as i64;
+            match (dx, dy) {
+                (0, 0) => {}
+                (0, _) => tied_x += 1,
+                (_, 0) => tied_y += 1,
+                _ if dx == dy => concordant += 1,
+                _ => discordant += 1,
+            }
+        }
+    }
+    let untied_x = (concordant + discordant + tied_y) as f64;
+    let untied_y = (concordant + discordant + tied_x) as f64;
+    ((concordant - discordant) as f64 / (untied_x * untied_y).sqrt()).clamp(-1.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-12,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_perfect_and_inverse_agreement() {
+        let x = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let c = Correlation::compute(&x, &[2.0, 4.0, 6.0, 8.0, 10.0]).unwrap();
+        assert_close(c.pearson, 1.0);
+        assert_close(c.spearman, 1.0);
+        assert_close(c.kendall, 1.0);
+        assert_eq!(c.samples, 5);
+
+        let c = Correlation::compute(&x, &[5.0, 4.0, 3.0, 2.0, 1.0]).unwrap();
+        assert_close(c.pearson, -1.0);
+        assert_close(c.srocc(), -1.0);
+        assert_close(c.kendall, -1.0);
+    }
+
+    #[test]
+    fn test_rank_correlations_ignore_monotonic_scale() {
+        let x = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let y = [1.0, 4.0, 9.0, 16.0, 100.0];
+        let c = Correlation::compute(&x, &y).unwrap();
+        assert!(c.pearson < 0.95);
+        assert_close(c.spearman, 1.0);
+        assert_close(c.kendall, 1.0);
+    }
+
+    #[test]
+    fn test_known_values_with_ties() {
+        assert_eq!(ranks(&[3.0, 1.0, 2.0, 1.0]), vec![4.0, 1.5, 3.0, 1.5]);
+
+        // Computed independently; tau-b matches scipy.stats.kendalltau's default
+        let x = [1.0, 2.0, 2.0, 3.0, 4.0, 5.0];
+        let y = [2.0, 1.0, 3.0, 3.0, 6.0, 5.0];
+        let c = Correlation::compute(&x, &y).unwrap();
+        assert!((c.pearson - 0.827_057_2).abs() < 1e-6, "{}", c);
+        assert!((c.spearman - 0.808_823_5).abs() < 1e-6, "{}", c);
+        assert!((c.kendall - 0.642_857_1).abs() < 1e-6, "{}", c);
+    }
+
+    #[test]
+    fn test_undefined_inputs() {
+        assert_eq!(Correlation::compute(&[1.0, 2.0], &[1.0]), None);
+        assert_eq!(Correlation::compute(&[1.0], &[1.0]), None);
+        assert_eq!(
+            Correlation::compute(&[1.0, 1.0, 1.0], &[1.0, 2.0, 3.0]),
+            None
+        );
+        assert_eq!(pearson(&[1.0, f64::NAN], &[1.0, 2.0]), None);
+        assert_eq!(spearman(&[], &[]), None);
+        assert_eq!(kendall(&[1.0, 2.0], &[3.0, 3.0]), None);
+    }
+}
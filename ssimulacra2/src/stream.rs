@@ -0,0 +1,510 @@
+//! Async frame-pair scoring for video pipelines.
+//!
+//! [`Ssimulacra2Stream`] is [`FrameQueue`](crate::FrameQueue) for async
+//! code. Decoded pairs go in through a [`FrameSubmitter`], dedicated worker
+//! threads score them, and the stream yields [`QueuedScore`]s in submission
+//! order as a [`futures_core::Stream`]. At most
+//! [`max_in_flight`](Ssimulacra2Stream::max_in_flight) pairs are held between
+//! submission and delivery: [`FrameSubmitter::submit`] waits for room instead
+//! of buffering, so a consumer that falls behind slows the decoder down
+//! rather than growing memory.
+//!
+//! The workers are plain threads, so the stream works with any executor and
+//! never blocks one. It ends once every submitter has been dropped and all
+//! submitted pairs have been delivered.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+use futures_core::Stream;
+
+use crate::{
+    compute_ssimulacra2_into, LinearRgbImage, QueuedScore, Ssimulacra2Config, Ssimulacra2Error,
+    Ssimulacra2Scratch, ToLinearRgb,
+};
+
+/// A submitted pair waiting for a worker
+struct Job {
+    sequence: u64,
+    source: LinearRgbImage,
+    distorted: LinearRgbImage,
+}
+
+/// State shared by the stream, its submitters and its workers
+#[derive(Default)]
+struct State {
+    max_in_flight: usize,
+    jobs: VecDeque<Job>,
+    /// Finished results waiting for an earlier sequence number to be yielded
+    results: BTreeMap<u64, Result<f64, Ssimulacra2Error>>,
+    /// Sequence number of the next submitted pair
+    submitted: u64,
+    /// Sequence number of the next result to yield
+    delivered: u64,
+    /// Live [`FrameSubmitter`]s
+    submitters: usize,
+    /// Set when the stream is dropped: workers exit and submissions are
+    /// discarded
+    closed: bool,
+    stream_waker: Option<Waker>,
+    /// Submissions waiting for room, by [`Submit::waiter`] id
+    submit_wakers: BTreeMap<u64, Waker>,
+    /// Id of the next submission to wait for room
+    next_waiter: u64,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Signalled when a job is queued or the stream is dropped
+    work: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Scores frame pairs on worker threads and yields the results, in
+/// submission order, as an async stream.
+///
+/// ```
+/// use fast_ssim2::{LinearRgbImage, Ssimulacra2Stream};
+/// # use std::future::Future;
+/// # fn block_on<F: Future>(future: F) -> F::Output {
+/// #     let mut future = std::pin::pin!(future);
+/// #     let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+/// #     loop {
+/// #         if let std::task::Poll::Ready(v) = future.as_mut().poll(&mut cx) {
+/// #             return v;
+/// #         }
+/// #         std::thread::yield_now();
+/// #     }
+/// # }
+///
+/// let frame = |v: f32| LinearRgbImage::new(vec![[v; 3]; 16 * 16], 16, 16);
+/// let mut stream = Ssimulacra2Stream::new().with_workers(2);
+/// let submitter = stream.submitter();
+/// std::thread::spawn(move || {
+///     for v in [0.2, 0.4, 0.6] {
+///         block_on(submitter.submit(frame(v), frame(v)));
+///     }
+/// });
+/// let mut sequences = Vec::new();
+/// while let Some(result) = block_on(std::future::poll_fn(|cx| {
+///     futures_core::Stream::poll_next(std::pin::Pin::new(&mut stream), cx)
+/// })) {
+///     assert_eq!(result.score, Ok(100.0));
+///     sequences.push(result.sequence);
+/// }
+/// assert_eq!(sequences, [0, 1, 2]);
+/// ```
+pub struct Ssimulacra2Stream {
+    config: Ssimulacra2Config,
+    workers: usize,
+    max_in_flight: Option<usize>,
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Default for Ssimulacra2Stream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ssimulacra2Stream {
+    /// Creates a stream using the default configuration.
+    pub fn new() -> Self {
+        Self::with_config(Ssimulacra2Config::default())
+    }
+
+    /// Creates a stream comparing frames with `config`.
+    ///
+    /// It uses one worker per available CPU unless
+    /// [`with_workers`](Self::with_workers) says otherwise.
+    pub fn with_config(config: Ssimulacra2Config) -> Self {
+        Self {
+            config,
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            max_in_flight: None,
+            shared: Arc::new(Shared {
+                state: Mutex::new(State::default()),
+                work: Condvar::new(),
+            }),
+            threads: Vec::new(),
+        }
+    }
+
+    /// Sets the number of worker threads, each scoring one pair at a time
+    /// with its own scratch buffers. Values below 1 are treated as 1.
+    /// Workers start with the first [`submitter`](Self::submitter), so set
+    /// this before taking one.
+    #[must_use]
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Limits how many pairs are held between submission and delivery,
+    /// twice the number of workers by default. This bounds the converted
+    /// frames in memory, including results waiting for an earlier frame.
+    /// Values below 1 are treated as 1. Set this before taking a
+    /// [`submitter`](Self::submitter).
+    #[must_use]
+    pub fn with_max_in_flight(mut self, pairs: usize) -> Self {
+        self.max_in_flight = Some(pairs.max(1));
+        self
+    }
+
+    /// The configuration frames are compared with
+    pub fn config(&self) -> &Ssimulacra2Config {
+        &self.config
+    }
+
+    /// Number of worker threads
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Most pairs held between submission and delivery
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight.unwrap_or(2 * self.workers)
+    }
+
+    /// Returns a handle that submits pairs to this stream, starting the
+    /// workers on first use.
+    ///
+    /// Take submitters before polling the stream: it ends as soon as it has
+    /// no submitters and nothing left to deliver.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a worker thread can't be spawned.
+    pub fn submitter(&mut self) -> FrameSubmitter {
+        if self.threads.is_empty() {
+            self.shared.lock().max_in_flight = self.max_in_flight();
+            self.threads = (0..self.workers)
+                .map(|_| {
+                    let (shared, config) = (Arc::clone(&self.shared), self.config.clone());
+                    thread::Builder::new()
+                        .name("ssimulacra2-stream".into())
+                        .spawn(move || work(&shared, config))
+                        .expect("failed to spawn a scoring worker")
+                })
+                .collect();
+        }
+        self.shared.lock().submitters += 1;
+        FrameSubmitter {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Number of pairs submitted so far
+    pub fn submitted(&self) -> u64 {
+        self.shared.lock().submitted
+    }
+}
+
+impl Stream for Ssimulacra2Stream {
+    type Item = QueuedScore;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<QueuedScore>> {
+        let mut state = self.shared.lock();
+        let sequence = state.delivered;
+        if let Some(score) = state.results.remove(&sequence) {
+            state.delivered += 1;
+            let waiting = std::mem::take(&mut state.submit_wakers);
+            drop(state);
+            waiting.into_values().for_each(Waker::wake);
+            return Poll::Ready(Some(QueuedScore { sequence, score }));
+        }
+        if state.submitters == 0 && state.delivered == state.submitted {
+            return Poll::Ready(None);
+        }
+        match &mut state.stream_waker {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            slot => *slot = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Ssimulacra2Stream {
+    fn drop(&mut self) {
+        let waiting = {
+            let mut state = self.shared.lock();
+            state.closed = true;
+            state.jobs.clear();
+            std::mem::take(&mut state.submit_wakers)
+        };
+        self.shared.work.notify_all();
+        waiting.into_values().for_each(Waker::wake);
+        for thread in self.threads.drain(..) {
+            // A worker that panicked has nothing left to clean up
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Submits frame pairs to an [`Ssimulacra2Stream`].
+///
+/// Clones submit to the same stream and share its sequence numbers, so
+/// several decoders can feed one stream. The stream ends once every
+/// submitter has been dropped.
+pub struct FrameSubmitter {
+    shared: Arc<Shared>,
+}
+
+impl FrameSubmitter {
+    /// Queues the next source/distorted pair, resolving to its sequence
+    /// number once there is room for it.
+    ///
+    /// Both images are converted to linear RGB on the calling thread. The
+    /// pair is numbered when the returned future completes, so awaiting
+    /// submissions one after the other numbers them in that order.
+    /// Comparison errors, such as differing dimensions, are delivered with
+    /// the pair's result. If the stream has been dropped, the pair is
+    /// discarded.
+    pub fn submit<S, D>(&self, source: S, distorted: D) -> Submit<'_>
+    where
+        S: ToLinearRgb,
+        D: ToLinearRgb,
+    {
+        Submit {
+            shared: &self.shared,
+            pair: Some((source.to_linear_rgb(), distorted.to_linear_rgb())),
+            waiter: None,
+        }
+    }
+}
+
+impl Clone for FrameSubmitter {
+    fn clone(&self) -> Self {
+        self.shared.lock().submitters += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Drop for FrameSubmitter {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.submitters -= 1;
+        if state.submitters == 0 {
+            // The stream may be waiting to find out it has ended
+            let waker = state.stream_waker.take();
+            drop(state);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Future returned by [`FrameSubmitter::submit`].
+#[must_use = "the pair is only submitted once the future is awaited"]
+pub struct Submit<'a> {
+    shared: &'a Shared,
+    pair: Option<(LinearRgbImage, LinearRgbImage)>,
+    /// Key of this submission's waker while it waits for room, so that
+    /// polling again replaces the waker instead of adding another
+    waiter: Option<u64>,
+}
+
+impl Future for Submit<'_> {
+    type Output = u64;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u64> {
+        let shared = self.shared;
+        let mut state = shared.lock();
+        if !state.closed && state.submitted - state.delivered >= state.max_in_flight as u64 {
+            // Waking takes every waker, so a stale key is registered anew
+            let waiter = *self.waiter.get_or_insert_with(|| {
+                state.next_waiter += 1;
+                state.next_waiter
+            });
+            match state.submit_wakers.get_mut(&waiter) {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                Some(waker) => *waker = cx.waker().clone(),
+                None => {
+                    state.submit_wakers.insert(waiter, cx.waker().clone());
+                }
+            }
+            return Poll::Pending;
+        }
+        if let Some(waiter) = self.waiter.take() {
+            state.submit_wakers.remove(&waiter);
+        }
+        let (source, distorted) = self.pair.take().expect("Submit polled after completion");
+        let sequence = state.submitted;
+        state.submitted += 1;
+        if !state.closed {
+            state.jobs.push_back(Job {
+                sequence,
+                source,
+                distorted,
+            });
+            shared.work.notify_one();
+        }
+        Poll::Ready(sequence)
+    }
+}
+
+impl Drop for Submit<'_> {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter {
+            self.shared.lock().submit_wakers.remove(&waiter);
+        }
+    }
+}
+
+/// A worker: scores queued pairs until the stream is dropped.
+fn work(shared: &Shared, config: Ssimulacra2Config) {
+    let mut scratch = Ssimulacra2Scratch::with_config(config);
+    loop {
+        let job = {
+            let mut state = shared.lock();
+            loop {
+                if state.closed {
+                    return;
+                }
+                if let Some(job) = state.jobs.pop_front() {
+                    break job;
+                }
+                state = shared.work.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+        };
+        let score = compute_ssimulacra2_into(&mut scratch, &job.source, &job.distorted);
+        let waker = {
+            let mut state = shared.lock();
+            state.results.insert(job.sequence, score);
+            state.stream_waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_ssimulacra2_with_config;
+    use crate::test_util::frame;
+    use std::task::Wake;
+
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    fn next(stream: &mut Ssimulacra2Stream) -> Option<QueuedScore> {
+        block_on(std::future::poll_fn(|cx| {
+            Pin::new(&mut *stream).poll_next(cx)
+        }))
+    }
+
+    #[test]
+    fn test_results_arrive_in_submission_order() {
+        let config = Ssimulacra2Config::default();
+        // Alternating sizes so that workers finish out of order
+        let pairs: Vec<_> = (0..12)
+            .map(|i| {
+                let width = if i % 3 == 0 { 256 } else { 16 };
+                (frame(width, i, 0.0), frame(width, i, 0.02 * (i % 5) as f32))
+            })
+            .collect();
+        let expected: Vec<f64> = pairs
+            .iter()
+            .map(|(s, d)| compute_ssimulacra2_with_config(s, d, config.clone()).unwrap())
+            .collect();
+
+        let mut stream = Ssimulacra2Stream::with_config(config)
+            .with_workers(3)
+            .with_max_in_flight(4);
+        let submitter = stream.submitter();
+        let producer = thread::spawn(move || {
+            let second = submitter.clone();
+            for (i, (source, distorted)) in pairs.into_iter().enumerate() {
+                let submitter = if i % 2 == 0 { &submitter } else { &second };
+                assert_eq!(block_on(submitter.submit(source, distorted)), i as u64);
+            }
+        });
+
+        let mut results = Vec::new();
+        while let Some(result) = next(&mut stream) {
+            results.push(result);
+        }
+        producer.join().unwrap();
+        assert_eq!(stream.submitted(), 12);
+        let sequences: Vec<u64> = results.iter().map(|r| r.sequence).collect();
+        assert_eq!(sequences, (0..12).collect::<Vec<_>>());
+        let scores: Vec<f64> = results.iter().map(|r| r.score.unwrap()).collect();
+        assert_eq!(scores, expected);
+    }
+
+    #[test]
+    fn test_submit_waits_for_room() {
+        let mut stream = Ssimulacra2Stream::new()
+            .with_workers(0)
+            .with_max_in_flight(2);
+        assert_eq!((stream.workers(), stream.max_in_flight()), (1, 2));
+        let submitter = stream.submitter();
+        assert_eq!(
+            block_on(submitter.submit(frame(16, 0, 0.0), frame(16, 0, 0.1))),
+            0
+        );
+        assert_eq!(
+            block_on(submitter.submit(frame(16, 1, 0.0), frame(32, 1, 0.0))),
+            1
+        );
+
+        {
+            let mut third = std::pin::pin!(submitter.submit(frame(16, 2, 0.0), frame(16, 2, 0.0)));
+            let mut cx = Context::from_waker(Waker::noop());
+            assert!(third.as_mut().poll(&mut cx).is_pending());
+            // Polling again, with the same or another waker, keeps one waker
+            assert!(third.as_mut().poll(&mut cx).is_pending());
+            let other = Waker::from(Arc::new(Unpark(thread::current())));
+            assert!(third
+                .as_mut()
+                .poll(&mut Context::from_waker(&other))
+                .is_pending());
+            assert_eq!(stream.shared.lock().submit_wakers.len(), 1);
+            // Delivering a result makes room
+            assert_eq!(next(&mut stream).map(|r| r.sequence), Some(0));
+            assert_eq!(block_on(third), 2);
+        }
+        drop(submitter);
+        assert_eq!(
+            next(&mut stream),
+            Some(QueuedScore {
+                sequence: 1,
+                score: Err(Ssimulacra2Error::NonMatchingImageDimensions),
+            })
+        );
+        assert_eq!(next(&mut stream).map(|r| r.score), Some(Ok(100.0)));
+        assert_eq!(next(&mut stream), None);
+    }
+}
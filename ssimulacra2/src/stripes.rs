@@ -0,0 +1,747 @@
+//! Stripe-by-stripe scoring with bounded memory.
+//!
+//! The whole-image pipeline keeps about ten full-resolution float planes per
+//! scale alive at once, which for 100+ megapixel scans no longer fits in RAM.
+//! [`StripedScorer`] takes the images as rows, in order, and scores every
+//! scale in horizontal stripes as soon as enough rows have arrived. Each
+//! stripe is blurred together with the rows the blur reaches across its
+//! seams, so seams add no zero padding that the whole-image blur wouldn't
+//! see. The SSIM and edge-difference norms are accumulated stripe by stripe,
+//! and rows are downscaled into the next scale as they come in, so no scale
+//! is ever held in full.
+//!
+//! Scores match the whole-image pipeline up to `f32` rounding in the
+//! recursive blur, whose state restarts at every stripe instead of carrying
+//! over from the top of the image.
+
+use crate::blur::SUPPORT_RADIUS;
+use crate::norms::Norms;
+use crate::transfer::reconcile_colorimetry;
+use crate::xyb::linear_rgb_to_positive_planar_into;
+use crate::{
+    downscale_pixels_by_2, edge_diff_map, image_multiply, score_scales, ssim_map, Blur,
+    DetailedScore, LinearRgbImage, NormExponents, PlanarImage, ScaleStatistics, Ssimulacra2Config,
+    Ssimulacra2Error, Stage, ToLinearRgb, NUM_SCALES,
+};
+
+/// Memory budget used unless [`StripedScorer::with_memory_budget`] sets one:
+/// 256 MiB.
+pub const DEFAULT_STRIPE_MEMORY_BUDGET: usize = 256 << 20;
+
+/// Fewest rows a stripe is shrunk to, however small the budget
+pub const MIN_STRIPE_ROWS: usize = 16;
+
+/// Rows blurred above and below each stripe
+const HALO: usize = SUPPORT_RADIUS;
+
+/// Working memory per pixel of a full-resolution stripe window, covering the
+/// buffered input rows of every scale and the shared pipeline buffers
+const WINDOW_BYTES_PER_PIXEL: usize = 192;
+
+/// Scores an image pair fed in rows, holding only a few stripes in memory.
+///
+/// ```
+/// use fast_ssim2::{Ssimulacra2Config, StripedScorer};
+///
+/// let (width, height) = (64, 256);
+/// let row = vec![[0.5f32; 3]; width];
+/// let mut scorer = StripedScorer::new(width, height, Ssimulacra2Config::default())?
+///     .with_memory_budget(1 << 20);
+/// for _ in 0..height {
+///     // e.g. rows straight from a decoder
+///     scorer.push_rows(&row, &row)?;
+/// }
+/// assert_eq!(scorer.finish()?.score, 100.0);
+/// # Ok::<(), fast_ssim2::Ssimulacra2Error>(())
+/// ```
+///
+/// [`pre_resize`](Ssimulacra2Config::pre_resize) and
+/// [`early_downscale_megapixels`](Ssimulacra2Config::early_downscale_megapixels)
+/// are applied as rows arrive. Pipeline taps and maps are not available.
+pub struct StripedScorer {
+    config: Ssimulacra2Config,
+    width: usize,
+    height: usize,
+    rows_received: usize,
+    stripe_rows: usize,
+    /// Input rows waiting for a full pre-resize block
+    pre_resize_rows: Option<[LinearRgbImage; 2]>,
+    /// Pyramid levels, finest first. Levels skipped by the early downscale
+    /// only pass their rows on.
+    levels: Vec<Level>,
+    work: StripeWork,
+}
+
+impl StripedScorer {
+    /// Creates a scorer for `width` x `height` images compared with
+    /// `config`, within [`DEFAULT_STRIPE_MEMORY_BUDGET`].
+    ///
+    /// # Errors
+    /// - If the images (after any pre-resize) are smaller than 8x8 pixels
+    /// - If a pre-resize factor is zero
+    /// - If the configured blur kernel isn't compiled in
+    pub fn new(
+        width: usize,
+        height: usize,
+        config: Ssimulacra2Config,
+    ) -> Result<Self, Ssimulacra2Error> {
+        if !config.blur_kernel.is_available() {
+            return Err(Ssimulacra2Error::BlurKernelUnavailable);
+        }
+        let (mut w, mut h) = (width, height);
+        if let Some(resize) = config.pre_resize {
+            if resize.factor_x == 0 || resize.factor_y == 0 {
+                return Err(Ssimulacra2Error::InvalidResizeFactor);
+            }
+            (w, h) = resize.output_size(width, height);
+        }
+        if w < 8 || h < 8 {
+            return Err(Ssimulacra2Error::InvalidImageSize);
+        }
+
+        let mut levels = Vec::new();
+        if let Some(megapixels) = config.early_downscale_megapixels {
+            let max_pixels = megapixels * 1_000_000.0;
+            while (w * h) as f64 > max_pixels && w >= 16 && h >= 16 {
+                levels.push(Level::new(w, h, false));
+                (w, h) = (w.div_ceil(2), h.div_ceil(2));
+            }
+        }
+        let scored_width = w;
+        // Same stopping rule as the whole-image pyramid: a scale exists if
+        // the one before it is at least 8x8
+        for scale in 0..NUM_SCALES {
+            if w < 8 || h < 8 {
+                break;
+            }
+            if scale > 0 {
+                (w, h) = (w.div_ceil(2), h.div_ceil(2));
+            }
+            levels.push(Level::new(w, h, true));
+        }
+        let coarsest = levels.len() - 1;
+        levels[coarsest].downscales = false;
+
+        let pre_resize_rows = config.pre_resize.map(|_| {
+            [
+                LinearRgbImage::new(Vec::new(), width, 0),
+                LinearRgbImage::new(Vec::new(), width, 0),
+            ]
+        });
+
+        let mut scorer = Self {
+            config,
+            width,
+            height,
+            rows_received: 0,
+            stripe_rows: 0,
+            pre_resize_rows,
+            levels,
+            work: StripeWork::new(),
+        };
+        scorer.set_stripe_rows(scored_width, DEFAULT_STRIPE_MEMORY_BUDGET);
+        Ok(scorer)
+    }
+
+    /// Sizes stripes so the working memory stays within about `bytes`.
+    ///
+    /// Stripes are never shorter than [`MIN_STRIPE_ROWS`], so very small
+    /// budgets on very wide images can be exceeded. Input rows not yet
+    /// pushed don't count. Call before pushing the first row; later calls
+    /// only affect stripes not yet started.
+    #[must_use]
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self {
+        let scored_width = self
+            .levels
+            .iter()
+            .find(|level| level.scored)
+            .map_or(self.width, |level| level.width);
+        self.set_stripe_rows(scored_width, bytes);
+        self
+    }
+
+    fn set_stripe_rows(&mut self, scored_width: usize, bytes: usize) {
+        let window_rows = bytes / (scored_width * WINDOW_BYTES_PER_PIXEL);
+        self.stripe_rows = window_rows.saturating_sub(2 * HALO).max(MIN_STRIPE_ROWS);
+        for level in &mut self.levels {
+            level.stripe_rows = self.stripe_rows;
+        }
+    }
+
+    /// Rows per stripe at every scale
+    pub fn stripe_rows(&self) -> usize {
+        self.stripe_rows
+    }
+
+    /// Rows pushed so far
+    pub fn rows_received(&self) -> usize {
+        self.rows_received
+    }
+
+    /// The configuration the images are compared with
+    pub fn config(&self) -> &Ssimulacra2Config {
+        &self.config
+    }
+
+    /// Adds the next rows of both images, top to bottom.
+    ///
+    /// Both slices hold the same number of whole rows of linear RGB pixels;
+    /// any number of rows can be pushed at once. Stripes are scored as soon
+    /// as their rows, and the rows their blur reaches, have arrived.
+    ///
+    /// # Errors
+    /// - If the slices differ in length, don't hold whole rows, or would
+    ///   take the images past their height
+    pub fn push_rows(
+        &mut self,
+        source: &[[f32; 3]],
+        distorted: &[[f32; 3]],
+    ) -> Result<(), Ssimulacra2Error> {
+        let width = self.width;
+        if source.len() != distorted.len()
+            || !source.len().is_multiple_of(width)
+            || self.rows_received + source.len() / width > self.height
+        {
+            return Err(Ssimulacra2Error::RowDataMismatch);
+        }
+
+        for (row1, row2) in source
+            .chunks_exact(width)
+            .zip(distorted.chunks_exact(width))
+        {
+            self.rows_received += 1;
+            let last = self.rows_received == self.height;
+            match (&mut self.pre_resize_rows, self.config.pre_resize) {
+                (Some(block), Some(resize)) => {
+                    for (image, row) in block.iter_mut().zip([row1, row2]) {
+                        image.data.extend_from_slice(row);
+                        image.height += 1;
+                    }
+                    // The final block may be short; PreResize repeats its
+                    // last row, as it would the image's
+                    if block[0].height == resize.factor_y || last {
+                        let [resized1, resized2] =
+                            [resize.apply(&block[0])?, resize.apply(&block[1])?];
+                        for image in block.iter_mut() {
+                            image.data.clear();
+                            image.height = 0;
+                        }
+                        feed(
+                            &mut self.levels,
+                            &mut self.work,
+                            &self.config,
+                            resized1.data(),
+                            resized2.data(),
+                        );
+                    }
+                }
+                _ => feed(&mut self.levels, &mut self.work, &self.config, row1, row2),
+            }
+        }
+        Ok(())
+    }
+
+    /// Scores the last stripes and returns the score with its per-scale
+    /// statistics.
+    ///
+    /// # Errors
+    /// - If fewer rows than the image height were pushed
+    pub fn finish(self) -> Result<DetailedScore, Ssimulacra2Error> {
+        if self.rows_received != self.height {
+            return Err(Ssimulacra2Error::RowDataMismatch);
+        }
+        let scales: Vec<ScaleStatistics> = self
+            .levels
+            .iter()
+            .filter(|level| level.scored)
+            .map(|level| level.statistics(self.config.norm_exponents))
+            .collect();
+        let score = score_scales(&scales);
+
+        #[cfg(feature = "monitor")]
+        if let Some(label) = &self.config.monitor_label {
+            crate::monitor::ScoreMonitor::global().record(label, score);
+        }
+
+        Ok(DetailedScore {
+            score,
+            scales,
+            error_bound: 0.0,
+        })
+    }
+}
+
+/// Passes a row pair to the first level, and any downscaled rows on to the
+/// coarser ones.
+fn feed(
+    levels: &mut [Level],
+    work: &mut StripeWork,
+    config: &Ssimulacra2Config,
+    row1: &[[f32; 3]],
+    row2: &[[f32; 3]],
+) {
+    let Some((level, coarser)) = levels.split_first_mut() else {
+        return;
+    };
+    if level.push(row1, row2, work, config) {
+        let [down1, down2] = &level.downscaled;
+        feed(coarser, work, config, down1, down2);
+    }
+}
+
+/// One scale of the pyramid, receiving its rows one at a time.
+struct Level {
+    width: usize,
+    height: usize,
+    /// Whether this scale contributes to the score (false for scales
+    /// skipped by the early downscale)
+    scored: bool,
+    /// Whether rows are downscaled into a coarser level
+    downscales: bool,
+    stripe_rows: usize,
+    received: usize,
+    /// Buffered rows of both images, starting at image row `first_row`
+    rows: [Vec<[f32; 3]>; 2],
+    first_row: usize,
+    /// Rows above this one have been scored
+    scored_rows: usize,
+    /// Rows waiting for their pair to be downscaled
+    pair: [Vec<[f32; 3]>; 2],
+    pair_rows: usize,
+    /// The most recent downscaled row of each image
+    downscaled: [Vec<[f32; 3]>; 2],
+    /// Per channel: sum of the SSIM error and of its fourth power
+    ssim_sums: [f64; 3 * 2],
+    /// Per channel: the same sums for artifacts, then detail lost
+    edgediff_sums: [f64; 3 * 4],
+}
+
+impl Level {
+    fn new(width: usize, height: usize, scored: bool) -> Self {
+        Self {
+            width,
+            height,
+            scored,
+            downscales: true,
+            stripe_rows: MIN_STRIPE_ROWS,
+            received: 0,
+            rows: Default::default(),
+            first_row: 0,
+            scored_rows: 0,
+            pair: Default::default(),
+            pair_rows: 0,
+            downscaled: Default::default(),
+            ssim_sums: [0.0; 3 * 2],
+            edgediff_sums: [0.0; 3 * 4],
+        }
+    }
+
+    /// Adds a row, scoring any stripe it completes. Returns true if a
+    /// downscaled row is ready in `downscaled`.
+    fn push(
+        &mut self,
+        row1: &[[f32; 3]],
+        row2: &[[f32; 3]],
+        work: &mut StripeWork,
+        config: &Ssimulacra2Config,
+    ) -> bool {
+        self.received += 1;
+        if self.scored {
+            self.rows[0].extend_from_slice(row1);
+            self.rows[1].extend_from_slice(row2);
+            while let Some((start, end)) = self.ready_stripe() {
+                self.score_stripe(start, end, work, config);
+            }
+        }
+
+        if !self.downscales {
+            return false;
+        }
+        self.pair[0].extend_from_slice(row1);
+        self.pair[1].extend_from_slice(row2);
+        self.pair_rows += 1;
+        if self.pair_rows < 2 && self.received < self.height {
+            return false;
+        }
+        // A lone final row is paired with itself, like the whole-image
+        // downscale repeats the last row
+        let out_width = self.width.div_ceil(2);
+        for (pair, out) in self.pair.iter_mut().zip(&mut self.downscaled) {
+            out.resize(out_width, [0.0; 3]);
+            downscale_pixels_by_2(
+                pair,
+                self.width,
+                self.pair_rows,
+                out,
+                config.backend_for(Stage::Xyb),
+            );
+            pair.clear();
+        }
+        self.pair_rows = 0;
+        true
+    }
+
+    /// The next stripe, if its rows and the rows below it that its blur
+    /// reaches have all arrived
+    fn ready_stripe(&self) -> Option<(usize, usize)> {
+        let start = self.scored_rows;
+        let end = start.saturating_add(self.stripe_rows).min(self.height);
+        (start < self.height && self.received >= (end + HALO).min(self.height))
+            .then_some((start, end))
+    }
+
+    fn score_stripe(
+        &mut self,
+        start: usize,
+        end: usize,
+        work: &mut StripeWork,
+        config: &Ssimulacra2Config,
+    ) {
+        let width = self.width;
+        let top = start.saturating_sub(HALO);
+        let bottom = (end + HALO).min(self.height);
+
+        // Rows above the window are no longer needed
+        let drop = (top - self.first_row) * width;
+        for rows in &mut self.rows {
+            rows.drain(..drop);
+        }
+        self.first_row = top;
+
+        let window = (bottom - top) * width;
+        let (avg_ssim, avg_edgediff) = work.score(
+            width,
+            [&self.rows[0][..window], &self.rows[1][..window]],
+            start - top,
+            end - start,
+            config,
+        );
+
+        // Undo each stripe's roots so the sums add up over the whole scale
+        let exponents = config.norm_exponents;
+        let pixels = ((end - start) * width) as f64;
+        for (sums, norms) in self
+            .ssim_sums
+            .chunks_exact_mut(2)
+            .zip(avg_ssim.chunks_exact(2))
+            .chain(
+                self.edgediff_sums
+                    .chunks_exact_mut(2)
+                    .zip(avg_edgediff.chunks_exact(2)),
+            )
+        {
+            sums[0] += exponents.low_power(norms[0]) * pixels;
+            sums[1] += exponents.high_power(norms[1]) * pixels;
+        }
+        self.scored_rows = end;
+    }
+
+    /// The norms over the whole scale
+    fn statistics(&self, exponents: NormExponents) -> ScaleStatistics {
+        let one_per_pixels = 1.0 / (self.width * self.height) as f64;
+        let norms = |sums: &[f64], out: &mut [f64]| {
+            for (sums, out) in sums.chunks_exact(2).zip(out.chunks_exact_mut(2)) {
+                out[0] = exponents.low_root(one_per_pixels * sums[0]);
+                out[1] = exponents.high_root(one_per_pixels * sums[1]);
+            }
+        };
+        let mut statistics = ScaleStatistics::default();
+        norms(&self.ssim_sums[..], &mut statistics.avg_ssim[..]);
+        norms(&self.edgediff_sums[..], &mut statistics.avg_edgediff[..]);
+        statistics
+    }
+}
+
+/// Pipeline buffers shared by every level, sized for the largest window.
+struct StripeWork {
+    blur: Option<Blur>,
+    planar: [PlanarImage; 2],
+    mul: [Vec<f32>; 3],
+    sigma1_sq: [Vec<f32>; 3],
+    sigma2_sq: [Vec<f32>; 3],
+    sigma12: [Vec<f32>; 3],
+    mu1: [Vec<f32>; 3],
+    mu2: [Vec<f32>; 3],
+}
+
+impl StripeWork {
+    fn new() -> Self {
+        Self {
+            blur: None,
+            planar: [PlanarImage::zeros(0, 0), PlanarImage::zeros(0, 0)],
+            mul: Default::default(),
+            sigma1_sq: Default::default(),
+            sigma2_sq: Default::default(),
+            sigma12: Default::default(),
+            mu1: Default::default(),
+            mu2: Default::default(),
+        }
+    }
+
+    /// Runs the pipeline over a window of rows and returns the SSIM and
+    /// edge-difference norms of the `rows` rows starting `offset` rows down.
+    fn score(
+        &mut self,
+        width: usize,
+        windows: [&[[f32; 3]]; 2],
+        offset: usize,
+        rows: usize,
+        config: &Ssimulacra2Config,
+    ) -> ([f64; 3 * 2], [f64; 3 * 4]) {
+        let height = windows[0].len() / width;
+        let impl_type = config.backend_for(Stage::Ssim);
+
+        for (data, planar) in windows.into_iter().zip(&mut self.planar) {
+            linear_rgb_to_positive_planar_into(data, width, height, planar, config);
+        }
+
+        let size = width * height;
+        for buf in [
+            &mut self.mul,
+            &mut self.sigma1_sq,
+            &mut self.sigma2_sq,
+            &mut self.sigma12,
+            &mut self.mu1,
+            &mut self.mu2,
+        ] {
+            for c in buf.iter_mut() {
+                c.resize(size, 0.0);
+            }
+        }
+        let blur = self
+            .blur
+            .get_or_insert_with(|| Blur::with_config(width, height, config));
+        blur.resize(width, height);
+
+        let [img1, img2] = &mut self.planar;
+        let (img1_planes, img2_planes) = (img1.planes(), img2.planes());
+        image_multiply(img1_planes, img1_planes, &mut self.mul, impl_type);
+        blur.blur_into(&self.mul, &mut self.sigma1_sq);
+        image_multiply(img2_planes, img2_planes, &mut self.mul, impl_type);
+        blur.blur_into(&self.mul, &mut self.sigma2_sq);
+        image_multiply(img1_planes, img2_planes, &mut self.mul, impl_type);
+        blur.blur_into(&self.mul, &mut self.sigma12);
+        blur.blur_into(img1_planes, &mut self.mu1);
+        blur.blur_into(img2_planes, &mut self.mu2);
+
+        // Keep only the stripe; the halo rows were there for the blur
+        let stripe = offset * width..(offset + rows) * width;
+        for buf in [
+            &mut self.sigma1_sq,
+            &mut self.sigma2_sq,
+            &mut self.sigma12,
+            &mut self.mu1,
+            &mut self.mu2,
+            &mut img1.planes,
+            &mut img2.planes,
+        ] {
+            for c in buf.iter_mut() {
+                c.copy_within(stripe.clone(), 0);
+                c.truncate(rows * width);
+            }
+        }
+        img1.height = rows;
+        img2.height = rows;
+
+        let avg_ssim = ssim_map(
+            width,
+            rows,
+            &self.mu1,
+            &self.mu2,
+            &self.sigma1_sq,
+            &self.sigma2_sq,
+            &self.sigma12,
+            impl_type,
+            config.norm_exponents,
+            &config.parallelism,
+        );
+        let avg_edgediff = edge_diff_map(
+            width,
+            rows,
+            img1.planes(),
+            &self.mu1,
+            img2.planes(),
+            &self.mu2,
+            impl_type,
+            config.norm_exponents,
+            &config.parallelism,
+        );
+        (avg_ssim, avg_edgediff)
+    }
+}
+
+/// Computes the SSIMULACRA2 score stripe by stripe, keeping the working
+/// memory within about `memory_budget` bytes (see [`StripedScorer`]).
+///
+/// The inputs themselves are still held in full; to bound memory end to
+/// end, push rows from a decoder into a [`StripedScorer`] instead.
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the image is smaller than 8x8 pixels
+pub fn compute_ssimulacra2_striped<S, D>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+    memory_budget: usize,
+) -> Result<f64, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    let mut source = source.as_linear_rgb();
+    let mut distorted = distorted.as_linear_rgb();
+    if source.width() != distorted.width() || source.height() != distorted.height() {
+        return Err(Ssimulacra2Error::NonMatchingImageDimensions);
+    }
+    reconcile_colorimetry(&mut source, &mut distorted)?;
+    let mut scorer = StripedScorer::new(source.width(), source.height(), config)?
+        .with_memory_budget(memory_budget);
+    scorer.push_rows(source.data(), distorted.data())?;
+    Ok(scorer.finish()?.score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_ssimulacra2_detailed, PreResize};
+
+    fn noise(width: usize, height: usize, seed: u32) -> LinearRgbImage {
+        let data = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                let hash = |k: u32| {
+                    ((i as u32 ^ seed.wrapping_mul(k)).wrapping_mul(2_654_435_761) >> 24) as f32
+                        / 255.0
+                };
+                let base = 0.3 + 0.2 * (x * 0.11).sin() * (y * 0.07).cos();
+                [
+                    base + 0.1 * hash(3),
+                    base + 0.1 * hash(5),
+                    base + 0.1 * hash(7),
+                ]
+            })
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    }
+
+    fn striped(
+        source: &LinearRgbImage,
+        distorted: &LinearRgbImage,
+        config: Ssimulacra2Config,
+        budget: usize,
+    ) -> DetailedScore {
+        let mut scorer = StripedScorer::new(source.width(), source.height(), config)
+            .unwrap()
+            .with_memory_budget(budget);
+        scorer.push_rows(source.data(), distorted.data()).unwrap();
+        scorer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_single_stripe_matches_whole_image() {
+        let (source, distorted) = (noise(96, 80, 1), noise(96, 80, 2));
+        for config in [Ssimulacra2Config::scalar(), Ssimulacra2Config::simd()] {
+            let whole = compute_ssimulacra2_detailed(&source, &distorted, config.clone()).unwrap();
+            let striped = striped(&source, &distorted, config, usize::MAX);
+            assert_eq!(striped.scales.len(), whole.scales.len());
+            assert!(
+                (striped.score - whole.score).abs() < 1e-9,
+                "{} vs {}",
+                striped.score,
+                whole.score
+            );
+        }
+    }
+
+    #[test]
+    fn test_many_stripes_match_whole_image() {
+        // Odd sizes exercise the lone last row of every downscale
+        let (source, distorted) = (noise(72, 301, 1), noise(72, 301, 2));
+        for config in [Ssimulacra2Config::scalar(), Ssimulacra2Config::simd()] {
+            let whole = compute_ssimulacra2_detailed(&source, &distorted, config.clone()).unwrap();
+            let striped = striped(&source, &distorted, config, 0);
+            assert_eq!(striped.scales.len(), whole.scales.len());
+            assert!(
+                (striped.score - whole.score).abs() < 0.01,
+                "{} vs {}",
+                striped.score,
+                whole.score
+            );
+        }
+    }
+
+    #[test]
+    fn test_row_chunking_does_not_change_the_score() {
+        let (source, distorted) = (noise(40, 170, 3), noise(40, 170, 4));
+        let config = Ssimulacra2Config::default();
+        let all_at_once = striped(&source, &distorted, config.clone(), 0);
+
+        let mut scorer = StripedScorer::new(40, 170, config)
+            .unwrap()
+            .with_memory_budget(0);
+        let mut row = 0;
+        for chunk in [1, 7, 30, 2, 100, 30] {
+            let range = row * 40..(row + chunk) * 40;
+            scorer
+                .push_rows(&source.data()[range.clone()], &distorted.data()[range])
+                .unwrap();
+            row += chunk;
+        }
+        assert_eq!(scorer.finish().unwrap(), all_at_once);
+    }
+
+    #[test]
+    fn test_pre_resize_and_early_downscale() {
+        let (source, distorted) = (noise(130, 97, 5), noise(130, 97, 6));
+        for config in [
+            Ssimulacra2Config::default().with_pre_resize(PreResize::with_factors(2, 3)),
+            Ssimulacra2Config::default().with_early_downscale(0.004),
+        ] {
+            let whole = compute_ssimulacra2_detailed(&source, &distorted, config.clone()).unwrap();
+            let striped = striped(&source, &distorted, config.clone(), usize::MAX);
+            assert_eq!(striped.scales.len(), whole.scales.len());
+            assert!((striped.score - whole.score).abs() < 1e-9, "{:?}", config);
+        }
+    }
+
+    #[test]
+    fn test_budget_sets_stripe_height() {
+        let config = Ssimulacra2Config::default();
+        let scorer = StripedScorer::new(1000, 1000, config).unwrap();
+        let small = scorer.with_memory_budget(8 << 20);
+        assert!(small.stripe_rows() * 1000 * WINDOW_BYTES_PER_PIXEL <= 8 << 20);
+        assert!(small.stripe_rows() > MIN_STRIPE_ROWS);
+        let tiny = small.with_memory_budget(1);
+        assert_eq!(tiny.stripe_rows(), MIN_STRIPE_ROWS);
+    }
+
+    #[test]
+    fn test_row_errors() {
+        let config = Ssimulacra2Config::default();
+        assert_eq!(
+            StripedScorer::new(7, 100, config.clone()).err(),
+            Some(Ssimulacra2Error::InvalidImageSize)
+        );
+
+        let row = vec![[0.5f32; 3]; 16];
+        let mut scorer = StripedScorer::new(16, 16, config.clone()).unwrap();
+        assert_eq!(
+            scorer.push_rows(&row[..15], &row[..15]),
+            Err(Ssimulacra2Error::RowDataMismatch)
+        );
+        assert_eq!(
+            scorer.push_rows(&row, &row[..0]),
+            Err(Ssimulacra2Error::RowDataMismatch)
+        );
+        scorer.push_rows(&row, &row).unwrap();
+        assert_eq!(scorer.finish(), Err(Ssimulacra2Error::RowDataMismatch));
+
+        let rows = vec![[0.5f32; 3]; 16 * 17];
+        let mut scorer = StripedScorer::new(16, 16, config).unwrap();
+        assert_eq!(
+            scorer.push_rows(&rows, &rows),
+            Err(Ssimulacra2Error::RowDataMismatch)
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! Tap points exposing intermediate planes of the pipeline.
+//!
+//! A [`PipelineTap`] passed to [`compute_ssimulacra2_with_taps`] is called at
+//! every scale with views of the buffers the metric computes anyway: the
+//! offset XYB planes of both images and their blurred moments. Taps that also
+//! want the per-pixel error maps the score is pooled from opt in via
+//! [`PipelineTap::wants_error_maps`]; those are computed in an extra pass only
+//! when requested.
+//!
+//! Views are only valid for the duration of the call, since the buffers are
+//! reused by the next scale. Copy what you need to keep.
+//!
+//! Taps also receive progress between the steps of each scale and may cancel
+//! the comparison there; [`compute_ssimulacra2_with_progress`] takes just a
+//! progress callback.
+
+use std::ops::ControlFlow;
+
+use crate::{compute_linear_msssim, PlanarImage, Ssimulacra2Config, Ssimulacra2Error, ToLinearRgb};
+
+/// Blurred first and second moments of one scale.
+///
+/// These are the same planes [`score_from_statistics`](crate::score_from_statistics)
+/// takes: `s11`/`s22`/`s12` are raw second moments, not variances.
+#[derive(Debug, Clone, Copy)]
+pub struct ScaleMoments<'a> {
+    /// Plane width
+    pub width: usize,
+    /// Plane height
+    pub height: usize,
+    /// Blurred source
+    pub mu1: &'a [Vec<f32>; 3],
+    /// Blurred distorted
+    pub mu2: &'a [Vec<f32>; 3],
+    /// Blurred `source * source`
+    pub s11: &'a [Vec<f32>; 3],
+    /// Blurred `distorted * distorted`
+    pub s22: &'a [Vec<f32>; 3],
+    /// Blurred `source * distorted`
+    pub s12: &'a [Vec<f32>; 3],
+}
+
+/// Per-pixel error maps of one scale, before pooling.
+///
+/// The score pools the 1-norm and 4-norm of each map per channel.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorMaps<'a> {
+    /// Map width
+    pub width: usize,
+    /// Map height
+    pub height: usize,
+    /// SSIM error `d`, clamped to be non-negative
+    pub ssim: &'a [Vec<f32>; 3],
+    /// Ringing/blocking artifacts (edges added by the distortion)
+    pub artifact: &'a [Vec<f32>; 3],
+    /// Detail lost (edges removed by the distortion)
+    pub detail_lost: &'a [Vec<f32>; 3],
+}
+
+/// Receives intermediate planes during a comparison.
+///
+/// All methods default to doing nothing, so implementors only override the
+/// stages they need. `scale` counts from 0 (full resolution).
+pub trait PipelineTap {
+    /// Called with the offset planar XYB of both images.
+    fn xyb(&mut self, scale: usize, source: &PlanarImage, distorted: &PlanarImage) {
+        let _ = (scale, source, distorted);
+    }
+
+    /// Called with the blurred moments.
+    fn moments(&mut self, scale: usize, moments: &ScaleMoments<'_>) {
+        let _ = (scale, moments);
+    }
+
+    /// Called with the per-pixel error maps when
+    /// [`wants_error_maps`](Self::wants_error_maps) returns true.
+    fn error_maps(&mut self, scale: usize, maps: &ErrorMaps<'_>) {
+        let _ = (scale, maps);
+    }
+
+    /// Whether to compute per-pixel error maps, which costs an extra pass
+    /// over each scale.
+    fn wants_error_maps(&self) -> bool {
+        false
+    }
+
+    /// Called after each step of a scale (the XYB conversion, each of the
+    /// five blurs, and the error maps) with the fraction of the whole
+    /// comparison done, estimated from the pixel counts of the scales.
+    /// Returning [`ControlFlow::Break`] stops the comparison with
+    /// [`Ssimulacra2Error::Cancelled`].
+    fn progress(&mut self, scale: usize, fraction: f64) -> ControlFlow<()> {
+        let _ = (scale, fraction);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Computes the SSIMULACRA2 score, feeding intermediate planes to `tap`.
+///
+/// The score is identical to [`compute_ssimulacra2_with_config`](crate::compute_ssimulacra2_with_config).
+///
+/// # Errors
+/// - If the source and distorted image width and height do not match
+/// - If the source or distorted image cannot be converted to XYB successfully
+/// - If the image is smaller than 8x8 pixels
+pub fn compute_ssimulacra2_with_taps<S, D>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+    tap: &mut dyn PipelineTap,
+) -> Result<f64, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    compute_linear_msssim(
+        &source.as_linear_rgb(),
+        &distorted.as_linear_rgb(),
+        config,
+        Some(tap),
+    )
+    .map(|msssim| msssim.score())
+}
+
+/// Computes the SSIMULACRA2 score, calling `progress` with the scale and the
+/// fraction of the comparison done after every step (see
+/// [`PipelineTap::progress`]).
+///
+/// Returning [`ControlFlow::Break`] from `progress` cancels the comparison
+/// before its next blur. As with other taps, a
+/// [`time_budget`](Ssimulacra2Config::time_budget) is ignored.
+/// [`luma_only`](Ssimulacra2Config::luma_only) comparisons report progress
+/// and can be cancelled the same way.
+///
+/// ```
+/// use std::ops::ControlFlow;
+/// use fast_ssim2::{compute_ssimulacra2_with_progress, LinearRgbImage, Ssimulacra2Config};
+///
+/// let image = LinearRgbImage::new(vec![[0.5; 3]; 64 * 64], 64, 64);
+/// let score = compute_ssimulacra2_with_progress(
+///     &image,
+///     &image,
+///     Ssimulacra2Config::default(),
+///     |scale, fraction| {
+///         println!("scale {}: {:.0}%", scale, fraction * 100.0);
+///         ControlFlow::Continue(())
+///     },
+/// )?;
+/// # Ok::<(), fast_ssim2::Ssimulacra2Error>(())
+/// ```
+///
+/// # Errors
+/// - [`Ssimulacra2Error::Cancelled`] if `progress` returned
+///   [`ControlFlow::Break`]
+/// - As [`compute_ssimulacra2_with_taps`]
+pub fn compute_ssimulacra2_with_progress<S, D, F>(
+    source: S,
+    distorted: D,
+    config: Ssimulacra2Config,
+    progress: F,
+) -> Result<f64, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+    F: FnMut(usize, f64) -> ControlFlow<()>,
+{
+    struct Progress<F>(F);
+
+    impl<F: FnMut(usize, f64) -> ControlFlow<()>> PipelineTap for Progress<F> {
+        fn progress(&mut self, scale: usize, fraction: f64) -> ControlFlow<()> {
+            (self.0)(scale, fraction)
+        }
+    }
+
+    compute_ssimulacra2_with_taps(source, distorted, config, &mut Progress(progress))
+}
+
+/// Tracks how much of a comparison is done, for [`PipelineTap::progress`].
+pub(crate) struct Progress {
+    /// Pixels of all scales the comparison will run
+    total: f64,
+    /// Pixels of the scales before the current one
+    done: f64,
+    /// Pixels of the current scale
+    current: f64,
+}
+
+impl Progress {
+    /// Steps per scale: the XYB conversion, five blurs, and the error maps
+    pub(crate) const STEPS: usize = 7;
+
+    pub(crate) fn new(config: &Ssimulacra2Config, width: usize, height: usize) -> Self {
+        // Mirrors the pyramid loop: each scale is halved from the previous one
+        // if that one fits
+        let (mut w, mut h, mut total) = (width, height, 0);
+        for scale in 0..config.pyramid.num_scales() {
+            if !config.pyramid.fits(w, h) {
+                break;
+            }
+            if scale > 0 {
+                (w, h) = (w.div_ceil(2), h.div_ceil(2));
+            }
+            total += w * h;
+        }
+        Self {
+            total: total as f64,
+            done: 0.0,
+            current: 0.0,
+        }
+    }
+
+    pub(crate) fn start_scale(&mut self, width: usize, height: usize) {
+        self.done += self.current;
+        self.current = (width * height) as f64;
+    }
+
+    /// Reports `step` of `scale` as finished to the tap, if any.
+    pub(crate) fn report(
+        &self,
+        tap: &mut Option<&mut dyn PipelineTap>,
+        scale: usize,
+        step: usize,
+    ) -> Result<(), Ssimulacra2Error> {
+        let Some(tap) = tap.as_deref_mut() else {
+            return Ok(());
+        };
+        let done = self.done + self.current * step as f64 / Self::STEPS as f64;
+        match tap.progress(scale, (done / self.total).min(1.0)) {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(()) => Err(Ssimulacra2Error::Cancelled),
+        }
+    }
+}
+
+/// Buffers for the per-pixel error maps, reused across scales.
+pub(crate) struct ErrorMapBuffers {
+    ssim: [Vec<f32>; 3],
+    artifact: [Vec<f32>; 3],
+    detail_lost: [Vec<f32>; 3],
+}
+
+impl ErrorMapBuffers {
+    pub(crate) fn new() -> Self {
+        Self {
+            ssim: Default::default(),
+            artifact: Default::default(),
+            detail_lost: Default::default(),
+        }
+    }
+
+    /// Computes the maps for one scale and passes them to `tap`.
+    pub(crate) fn emit(
+        &mut self,
+        tap: &mut dyn PipelineTap,
+        scale: usize,
+        moments: &ScaleMoments<'_>,
+        img1: &[Vec<f32>; 3],
+        img2: &[Vec<f32>; 3],
+    ) {
+        fill_error_maps(
+            moments,
+            img1,
+            img2,
+            &mut self.ssim,
+            &mut self.artifact,
+            &mut self.detail_lost,
+        );
+        tap.error_maps(
+            scale,
+            &ErrorMaps {
+                width: moments.width,
+                height: moments.height,
+                ssim: &self.ssim,
+                artifact: &self.artifact,
+                detail_lost: &self.detail_lost,
+            },
+        );
+    }
+}
+
+/// Computes the per-pixel error maps of one scale into the given planes,
+/// mirroring the scalar SSIM and edge difference kernels. The planes are
+/// resized to the scale, reusing their capacity.
+pub(crate) fn fill_error_maps(
+    moments: &ScaleMoments<'_>,
+    img1: &[Vec<f32>; 3],
+    img2: &[Vec<f32>; 3],
+    ssim: &mut [Vec<f32>; 3],
+    artifact: &mut [Vec<f32>; 3],
+    detail_lost: &mut [Vec<f32>; 3],
+) {
+    const C2: f32 = 0.0009f32;
+
+    let size = moments.width * moments.height;
+    for c in 0..3 {
+        ssim[c].resize(size, 0.0);
+        artifact[c].resize(size, 0.0);
+        detail_lost[c].resize(size, 0.0);
+
+        for i in 0..size {
+            let mu1 = moments.mu1[c][i];
+            let mu2 = moments.mu2[c][i];
+            let mu11 = mu1 * mu1;
+            let mu22 = mu2 * mu2;
+            let mu12 = mu1 * mu2;
+            let mu_diff = mu1 - mu2;
+
+            let num_m = f64::from(mu_diff).mul_add(-f64::from(mu_diff), 1.0f64);
+            let num_s = 2f64.mul_add(f64::from(moments.s12[c][i] - mu12), f64::from(C2));
+            let denom_s = f64::from(moments.s11[c][i] - mu11)
+                + f64::from(moments.s22[c][i] - mu22)
+                + f64::from(C2);
+            ssim[c][i] = (1.0f64 - (num_m * num_s) / denom_s).max(0.0) as f32;
+
+            let d1: f64 = (1.0 + f64::from((img2[c][i] - mu2).abs()))
+                / (1.0 + f64::from((img1[c][i] - mu1).abs()))
+                - 1.0;
+            artifact[c][i] = d1.max(0.0) as f32;
+            detail_lost[c][i] = (-d1).max(0.0) as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_ssimulacra2_detailed;
+    use crate::test_util::test_image;
+
+    #[derive(Default)]
+    struct Recorder {
+        xyb_sizes: Vec<(usize, usize)>,
+        moment_scales: Vec<usize>,
+        /// Pooled 1-norm of the SSIM and artifact maps per scale, channel X
+        pooled: Vec<(f64, f64)>,
+    }
+
+    impl PipelineTap for Recorder {
+        fn xyb(&mut self, _scale: usize, source: &PlanarImage, distorted: &PlanarImage) {
+            assert_eq!(source.width(), distorted.width());
+            self.xyb_sizes.push((source.width(), source.height()));
+        }
+
+        fn moments(&mut self, scale: usize, moments: &ScaleMoments<'_>) {
+            assert_eq!(moments.mu1[0].len(), moments.width * moments.height);
+            self.moment_scales.push(scale);
+        }
+
+        fn error_maps(&mut self, _scale: usize, maps: &ErrorMaps<'_>) {
+            let mean = |plane: &[f32]| {
+                plane.iter().map(|&v| f64::from(v)).sum::<f64>() / plane.len() as f64
+            };
+            self.pooled
+                .push((mean(&maps.ssim[0]), mean(&maps.artifact[0])));
+        }
+
+        fn wants_error_maps(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_taps_see_every_scale_without_changing_score() {
+        let (source, distorted) = (test_image(64, 48, 1), test_image(64, 48, 2));
+        let config = Ssimulacra2Config::scalar();
+        let detailed =
+            compute_ssimulacra2_detailed(source.clone(), distorted.clone(), config.clone())
+                .unwrap();
+
+        let mut recorder = Recorder::default();
+        let score =
+            compute_ssimulacra2_with_taps(source, distorted, config, &mut recorder).unwrap();
+
+        assert_eq!(score, detailed.score);
+        assert_eq!(
+            recorder.xyb_sizes,
+            vec![(64, 48), (32, 24), (16, 12), (8, 6)]
+        );
+        assert_eq!(recorder.moment_scales, vec![0, 1, 2, 3]);
+
+        // Pooling the maps reproduces the statistics the score was built from
+        for (pooled, stats) in recorder.pooled.iter().zip(&detailed.scales) {
+            assert!((pooled.0 - stats.avg_ssim[0]).abs() < 1e-5, "{:?}", pooled);
+            assert!(
+                (pooled.1 - stats.avg_edgediff[0]).abs() < 1e-5,
+                "{:?}",
+                pooled
+            );
+        }
+    }
+
+    #[test]
+    fn test_progress_reaches_one_and_cancels() {
+        let (source, distorted) = (test_image(64, 48, 1), test_image(64, 48, 2));
+        let config = Ssimulacra2Config::default();
+        let expected = compute_ssimulacra2_detailed(&source, &distorted, config.clone())
+            .unwrap()
+            .score;
+
+        let mut reports = Vec::new();
+        let score =
+            compute_ssimulacra2_with_progress(&source, &distorted, config.clone(), |scale, f| {
+                reports.push((scale, f));
+                ControlFlow::Continue(())
+            });
+        assert_eq!(score, Ok(expected));
+        // Four scales of seven steps each
+        assert_eq!(reports.len(), 4 * Progress::STEPS);
+        assert!(reports
+            .windows(2)
+            .all(|w| w[0].1 < w[1].1 && w[0].0 <= w[1].0));
+        assert_eq!(reports.last(), Some(&(3, 1.0)));
+
+        let mut calls = 0;
+        let cancelled = compute_ssimulacra2_with_progress(&source, &distorted, config, |_, _| {
+            calls += 1;
+            if calls == 3 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(cancelled, Err(Ssimulacra2Error::Cancelled));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_progress_covers_luma_only() {
+        let (source, distorted) = (test_image(64, 48, 1), test_image(64, 48, 2));
+        let config = Ssimulacra2Config::default().with_luma_only(true);
+        let mut reports = Vec::new();
+        compute_ssimulacra2_with_progress(&source, &distorted, config.clone(), |scale, f| {
+            reports.push((scale, f));
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(reports.len(), 4 * Progress::STEPS);
+        assert_eq!(reports.last(), Some(&(3, 1.0)));
+
+        let cancelled = compute_ssimulacra2_with_progress(&source, &distorted, config, |_, _| {
+            ControlFlow::Break(())
+        });
+        assert_eq!(cancelled, Err(Ssimulacra2Error::Cancelled));
+    }
+
+    #[test]
+    fn test_default_tap_is_noop() {
+        struct Nothing;
+        impl PipelineTap for Nothing {}
+
+        let image = test_image(16, 16, 3);
+        let score = compute_ssimulacra2_with_taps(
+            image.clone(),
+            image,
+            Ssimulacra2Config::default(),
+            &mut Nothing,
+        )
+        .unwrap();
+        assert_eq!(score, 100.0);
+    }
+}
@@ -0,0 +1,239 @@
+//! Experimental multi-reference temporal scoring for video.
+//!
+//! Temporal denoisers move grain and fine texture between neighboring frames.
+//! Compared frame-by-frame against the source, such an encode is penalized
+//! for every grain particle that moved, even when it looks the same in
+//! motion. [`TemporalWindow`] compares each distorted frame against the
+//! co-located source frame and its neighbors within a small radius and keeps
+//! the best score.
+//!
+//! **This is an experimental variant of the metric.** Scores are never lower
+//! than frame-by-frame scores and are not comparable with them; report which
+//! mode was used. Each source frame is precomputed once with
+//! [`Ssimulacra2Reference`], so a radius of `r` costs roughly `2r + 1`
+//! comparisons per frame and keeps `2r + 1` references in memory.
+
+use std::collections::VecDeque;
+
+use crate::{LinearRgbImage, Ssimulacra2Error, Ssimulacra2Reference, ToLinearRgb};
+
+/// Best match for one distorted frame within its temporal window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemporalMatch {
+    /// Index of the distorted frame, counting pushed frames from zero
+    pub frame: usize,
+    /// Position of the best-matching source frame relative to `frame`
+    /// (0 = co-located, -1 = previous frame, ...)
+    pub offset: isize,
+    /// Score against the best-matching source frame
+    pub score: f64,
+}
+
+/// Sliding window of precomputed source frames for best-of-neighbors scoring.
+///
+/// Push source/distorted pairs in display order. A distorted frame is scored
+/// as soon as all source frames within `radius` after it are available, so
+/// results lag the input by `radius` frames; call [`finish`](Self::finish)
+/// after the last pair to score the remaining frames.
+///
+/// ```
+/// use fast_ssim2::{LinearRgbImage, TemporalWindow};
+///
+/// let frame = |v: f32| LinearRgbImage::new(vec![[v; 3]; 16 * 16], 16, 16);
+/// let mut window = TemporalWindow::new(1);
+/// let mut matches = Vec::new();
+/// for v in [0.2, 0.4, 0.6] {
+///     matches.extend(window.push(frame(v), frame(v))?);
+/// }
+/// matches.extend(window.finish()?);
+/// assert_eq!(matches.len(), 3);
+/// assert!(matches.iter().all(|m| m.offset == 0 && m.score == 100.0));
+/// # Ok::<(), fast_ssim2::Ssimulacra2Error>(())
+/// ```
+pub struct TemporalWindow {
+    radius: usize,
+    /// Source frames that can still be in a pending frame's window
+    references: VecDeque<(usize, Ssimulacra2Reference)>,
+    /// Distorted frames waiting for their window to fill
+    pending: VecDeque<(usize, LinearRgbImage)>,
+    pushed: usize,
+}
+
+impl TemporalWindow {
+    /// Creates a window comparing each frame against source frames up to
+    /// `radius` frames before and after it. A radius of zero is plain
+    /// frame-by-frame scoring.
+    pub fn new(radius: usize) -> Self {
+        Self {
+            radius,
+            references: VecDeque::with_capacity(2 * radius + 1),
+            pending: VecDeque::with_capacity(radius + 1),
+            pushed: 0,
+        }
+    }
+
+    /// Returns the window radius.
+    pub fn radius(&self) -> usize {
+        self.radius
+    }
+
+    /// Adds the next source/distorted pair and returns the match for the
+    /// frame whose window is now complete, if any.
+    ///
+    /// # Errors
+    /// - If the images are smaller than 8x8 pixels or their dimensions differ
+    pub fn push<S: ToLinearRgb, D: ToLinearRgb>(
+        &mut self,
+        source: S,
+        distorted: D,
+    ) -> Result<Option<TemporalMatch>, Ssimulacra2Error> {
+        let reference = Ssimulacra2Reference::new(source)?;
+        let distorted = distorted.into_linear_rgb();
+        if distorted.width() != reference.width() || distorted.height() != reference.height() {
+            return Err(Ssimulacra2Error::NonMatchingImageDimensions);
+        }
+
+        let index = self.pushed;
+        self.pushed += 1;
+        self.references.push_back((index, reference));
+        self.pending.push_back((index, distorted));
+
+        if index >= self.radius {
+            self.score_next().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Scores all frames still waiting for their window, using whatever
+    /// source frames are available at the end of the sequence.
+    ///
+    /// # Errors
+    /// - Propagates comparison errors
+    pub fn finish(mut self) -> Result<Vec<TemporalMatch>, Ssimulacra2Error> {
+        let mut matches = Vec::with_capacity(self.pending.len());
+        while !self.pending.is_empty() {
+            matches.push(self.score_next()?);
+        }
+        Ok(matches)
+    }
+
+    fn score_next(&mut self) -> Result<TemporalMatch, Ssimulacra2Error> {
+        let (frame, distorted) = self
+            .pending
+            .pop_front()
+            .expect("score_next called without pending frames");
+
+        let first = frame.saturating_sub(self.radius);
+        let last = frame + self.radius;
+        let mut best: Option<TemporalMatch> = None;
+        for (index, reference) in &self.references {
+            if *index < first || *index > last {
+                continue;
+            }
+            let candidate = TemporalMatch {
+                frame,
+                offset: *index as isize - frame as isize,
+                score: reference.compare(&distorted)?,
+            };
+            // On ties, prefer the source frame closest to the distorted one
+            let better = best.is_none_or(|best| {
+                candidate.score > best.score
+                    || (candidate.score == best.score && candidate.offset.abs() < best.offset.abs())
+            });
+            if better {
+                best = Some(candidate);
+            }
+        }
+
+        // Source frames before the next pending frame's window are done
+        let next_first = (frame + 1).saturating_sub(self.radius);
+        while self
+            .references
+            .front()
+            .is_some_and(|(index, _)| *index < next_first)
+        {
+            self.references.pop_front();
+        }
+
+        Ok(best.expect("the co-located source frame is always in the window"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_ssimulacra2;
+
+    /// Deterministic grain pattern that changes with `phase`
+    fn grainy(phase: usize) -> LinearRgbImage {
+        let (width, height) = (32, 32);
+        let data = (0..width * height)
+            .map(|i| {
+                let grain = ((i * 7 + phase * 13) % 11) as f32 / 110.0;
+                [0.4 + grain, 0.4 + grain, 0.4 + grain]
+            })
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    }
+
+    #[test]
+    fn test_radius_zero_matches_frame_by_frame() {
+        let mut window = TemporalWindow::new(0);
+        for phase in 0..3 {
+            let expected = compute_ssimulacra2(grainy(phase), grainy(phase + 1)).unwrap();
+            let matched = window
+                .push(grainy(phase), grainy(phase + 1))
+                .unwrap()
+                .expect("radius 0 scores immediately");
+            assert_eq!(matched.frame, phase);
+            assert_eq!(matched.offset, 0);
+            assert!((matched.score - expected).abs() < 1e-9);
+        }
+        assert!(window.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_shifted_grain_finds_neighbor() {
+        // The distorted stream carries each frame's grain one frame late, as a
+        // temporal filter might
+        let mut window = TemporalWindow::new(1);
+        let mut matches = Vec::new();
+        for frame in 0..5 {
+            let distorted = grainy(frame.max(1) - 1);
+            matches.extend(window.push(grainy(frame), distorted).unwrap());
+        }
+        matches.extend(window.finish().unwrap());
+
+        assert_eq!(matches.len(), 5);
+        for (i, matched) in matches.iter().enumerate() {
+            assert_eq!(matched.frame, i);
+        }
+        for matched in &matches[1..] {
+            assert_eq!(matched.offset, -1, "{:?}", matched);
+            assert_eq!(matched.score, 100.0);
+        }
+        // Frame-by-frame scoring penalizes the moved grain
+        let per_frame = compute_ssimulacra2(grainy(2), grainy(1)).unwrap();
+        assert!(per_frame < 100.0);
+    }
+
+    #[test]
+    fn test_owned_frames_are_kept_without_copying() {
+        let mut window = TemporalWindow::new(1);
+        let distorted = grainy(1);
+        let pixels = distorted.data().as_ptr();
+        window.push(grainy(0), distorted).unwrap();
+        assert_eq!(window.pending[0].1.data().as_ptr(), pixels);
+    }
+
+    #[test]
+    fn test_rejects_mismatched_dimensions() {
+        let mut window = TemporalWindow::new(1);
+        let small = LinearRgbImage::new(vec![[0.5; 3]; 16 * 16], 16, 16);
+        assert_eq!(
+            window.push(grainy(0), small),
+            Err(Ssimulacra2Error::NonMatchingImageDimensions)
+        );
+    }
+}
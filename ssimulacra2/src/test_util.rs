@@ -0,0 +1,59 @@
+//! Fixtures shared by the unit tests.
+
+use crate::{LinearRgbImage, Ssimulacra2Config};
+
+/// Frame `index` of a 24-row test clip, with deterministic per-pixel
+/// `noise` added on top
+pub(crate) fn frame(width: usize, index: usize, noise: f32) -> LinearRgbImage {
+    let data = (0..width * 24)
+        .map(|i| {
+            let v = ((i * 7 + index * 13) % 64) as f32 / 64.0;
+            let n = ((i as u32).wrapping_mul(2_654_435_761) >> 24) as f32 / 255.0;
+            [v, 0.5 * v, 1.0 - v].map(|c| (c + noise * n).min(1.0))
+        })
+        .collect();
+    LinearRgbImage::new(data, width, 24)
+}
+
+/// Smooth sine pattern; `phase` shifts it horizontally
+pub(crate) fn pattern(width: usize, height: usize, phase: f32) -> LinearRgbImage {
+    let data = (0..width * height)
+        .map(|i| {
+            let (x, y) = ((i % width) as f32, (i / width) as f32);
+            let v = 0.5 + 0.4 * (x * 0.4 + phase).sin() * (y * 0.3).cos();
+            [v, v * 0.8, 1.0 - v]
+        })
+        .collect();
+    LinearRgbImage::new(data, width, height)
+}
+
+/// Hashed noise; different seeds give unrelated images
+pub(crate) fn test_image(width: usize, height: usize, seed: u32) -> LinearRgbImage {
+    let data = (0..width * height)
+        .map(|i| {
+            let v = ((i as u32).wrapping_mul(2_654_435_761).wrapping_add(seed) >> 24) as f32;
+            [v / 255.0, 0.5 * v / 255.0, 1.0 - v / 255.0]
+        })
+        .collect();
+    LinearRgbImage::new(data, width, height)
+}
+
+/// Repeating diagonal ramps, shifted by `seed`
+pub(crate) fn ramp(width: usize, height: usize, seed: usize) -> LinearRgbImage {
+    let data = (0..width * height)
+        .map(|i| {
+            let v = ((i * 31 + seed * 17) % 251) as f32 / 251.0;
+            [v, 0.5 * v, 1.0 - v]
+        })
+        .collect();
+    LinearRgbImage::new(data, width, height)
+}
+
+/// One config per backend compiled into this build
+pub(crate) fn backend_configs() -> Vec<Ssimulacra2Config> {
+    #[allow(unused_mut)]
+    let mut configs = vec![Ssimulacra2Config::scalar(), Ssimulacra2Config::simd()];
+    #[cfg(feature = "unsafe-simd")]
+    configs.push(Ssimulacra2Config::unsafe_simd());
+    configs
+}
@@ -0,0 +1,398 @@
+//! Localized scoring over image tiles.
+//!
+//! [`compute_ssimulacra2_tiles`] scores rectangular regions of an image pair
+//! independently. With an explicit tile size the image is split into a regular
+//! grid; without one, tiles are chosen adaptively with a quadtree that keeps
+//! subdividing where the reference has high local variance. Mixed content such
+//! as text over a photo then gets small tiles around the text and large tiles
+//! over smooth areas, instead of a fixed grid that is either too coarse for the
+//! text or needlessly fine for the background.
+//!
+//! Each tile is scored as a standalone image, so tiles must be at least 8x8.
+
+use crate::input::{LinearRgbImage, ToLinearRgb};
+use crate::transfer::reconcile_colorimetry;
+use crate::{compute_ssimulacra2_with_config, Ssimulacra2Config, Ssimulacra2Error};
+
+/// Smallest tile the metric can score.
+const MIN_TILE_DIMENSION: usize = 8;
+
+/// Parameters for adaptive (quadtree) tile selection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveTiling {
+    /// Tiles are never split below this width or height (clamped to at least 8).
+    pub min_tile_size: usize,
+    /// Maximum quadtree depth below the whole-image root.
+    pub max_depth: u32,
+    /// A tile is split when its luminance variance exceeds this multiple of the
+    /// mean variance of `min_tile_size` blocks across the whole image.
+    pub variance_ratio: f64,
+}
+
+impl Default for AdaptiveTiling {
+    fn default() -> Self {
+        Self {
+            min_tile_size: 64,
+            max_depth: 4,
+            variance_ratio: 1.0,
+        }
+    }
+}
+
+/// How tiles are laid out over the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileLayout {
+    /// Regular grid of the given tile size. Edge tiles are clipped to the image,
+    /// and remainders narrower than 8 pixels are merged into the preceding tile.
+    Grid {
+        /// Tile width in pixels
+        width: usize,
+        /// Tile height in pixels
+        height: usize,
+    },
+    /// Quadtree subdivision driven by local variance of the reference image.
+    Adaptive(AdaptiveTiling),
+}
+
+impl Default for TileLayout {
+    fn default() -> Self {
+        TileLayout::Adaptive(AdaptiveTiling::default())
+    }
+}
+
+/// Score of a rectangular region, with any subdivisions of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileScore {
+    /// Left edge in pixels
+    pub x: usize,
+    /// Top edge in pixels
+    pub y: usize,
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+    /// SSIMULACRA2 score of this region scored on its own
+    pub score: f64,
+    /// Subdivisions of this region (empty for leaf tiles)
+    pub children: Vec<TileScore>,
+}
+
+impl TileScore {
+    /// Returns `true` if this tile has no subdivisions.
+    #[must_use]
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Iterates over the leaf tiles, which together cover this region exactly.
+    pub fn leaves(&self) -> Box<dyn Iterator<Item = &TileScore> + '_> {
+        if self.is_leaf() {
+            Box::new(std::iter::once(self))
+        } else {
+            Box::new(self.children.iter().flat_map(TileScore::leaves))
+        }
+    }
+
+    /// Returns the leaf tile with the lowest score.
+    #[must_use]
+    pub fn worst_leaf(&self) -> &TileScore {
+        self.leaves()
+            .min_by(|a, b| a.score.total_cmp(&b.score))
+            .expect("a tile always has at least one leaf")
+    }
+}
+
+/// Computes SSIMULACRA2 scores for tiles of the image pair.
+///
+/// With `layout` set to `None` the tiles are chosen adaptively with the default
+/// [`AdaptiveTiling`] parameters. The returned root covers the whole image and
+/// carries the whole-image score; its descendants form the tile hierarchy (a
+/// single level for [`TileLayout::Grid`]).
+///
+/// # Errors
+/// - If the image dimensions don't match
+/// - If either image is smaller than 8x8 pixels
+pub fn compute_ssimulacra2_tiles<S, D>(
+    source: S,
+    distorted: D,
+    layout: Option<TileLayout>,
+    config: Ssimulacra2Config,
+) -> Result<TileScore, Ssimulacra2Error>
+where
+    S: ToLinearRgb,
+    D: ToLinearRgb,
+{
+    let mut source = source.as_linear_rgb();
+    let mut distorted = distorted.as_linear_rgb();
+    if source.width() != distorted.width() || source.height() != distorted.height() {
+        return Err(Ssimulacra2Error::NonMatchingImageDimensions);
+    }
+    reconcile_colorimetry(&mut source, &mut distorted)?;
+    if source.width() < MIN_TILE_DIMENSION || source.height() < MIN_TILE_DIMENSION {
+        return Err(Ssimulacra2Error::InvalidImageSize);
+    }
+
+    let scorer = TileScorer {
+        source: &source,
+        distorted: &distorted,
+        config,
+    };
+    let (width, height) = (source.width(), source.height());
+
+    match layout.unwrap_or_default() {
+        TileLayout::Grid {
+            width: tile_w,
+            height: tile_h,
+        } => {
+            let mut root = scorer.score(0, 0, width, height)?;
+            for (y, h) in grid_spans(height, tile_h) {
+                for (x, w) in grid_spans(width, tile_w) {
+                    root.children.push(scorer.score(x, y, w, h)?);
+                }
+            }
+            Ok(root)
+        }
+        TileLayout::Adaptive(params) => {
+            let luma = luminance(&source);
+            let threshold = mean_block_variance(&luma, width, height, params.min_tile_size)
+                * params.variance_ratio;
+            scorer.score_quadtree(&luma, &params, threshold, 0, 0, width, height, 0)
+        }
+    }
+}
+
+struct TileScorer<'a> {
+    source: &'a LinearRgbImage,
+    distorted: &'a LinearRgbImage,
+    config: Ssimulacra2Config,
+}
+
+impl TileScorer<'_> {
+    fn score(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Result<TileScore, Ssimulacra2Error> {
+        let score = compute_ssimulacra2_with_config(
+            self.source.crop(x, y, width, height),
+            self.distorted.crop(x, y, width, height),
+            self.config.clone(),
+        )?;
+        Ok(TileScore {
+            x,
+            y,
+            width,
+            height,
+            score,
+            children: Vec::new(),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn score_quadtree(
+        &self,
+        luma: &[f32],
+        params: &AdaptiveTiling,
+        threshold: f64,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        depth: u32,
+    ) -> Result<TileScore, Ssimulacra2Error> {
+        let mut tile = self.score(x, y, width, height)?;
+
+        let min_size = params.min_tile_size.max(MIN_TILE_DIMENSION);
+        let can_split = depth < params.max_depth && width >= 2 * min_size && height >= 2 * min_size;
+        if can_split && region_variance(luma, self.source.width(), x, y, width, height) > threshold
+        {
+            let left = width / 2;
+            let top = height / 2;
+            for (cy, ch) in [(y, top), (y + top, height - top)] {
+                for (cx, cw) in [(x, left), (x + left, width - left)] {
+                    tile.children.push(self.score_quadtree(
+                        luma,
+                        params,
+                        threshold,
+                        cx,
+                        cy,
+                        cw,
+                        ch,
+                        depth + 1,
+                    )?);
+                }
+            }
+        }
+
+        Ok(tile)
+    }
+}
+
+/// Splits `0..len` into spans of `tile` pixels, merging a final remainder that
+/// is too small to score into the preceding span.
+fn grid_spans(len: usize, tile: usize) -> Vec<(usize, usize)> {
+    let tile = tile.max(MIN_TILE_DIMENSION).min(len);
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    while start < len {
+        let size = tile.min(len - start);
+        if size < MIN_TILE_DIMENSION {
+            if let Some(last) = spans.last_mut() {
+                last.1 += size;
+            }
+            break;
+        }
+        spans.push((start, size));
+        start += size;
+    }
+    spans
+}
+
+/// Relative luminance of each pixel (BT.709 weights on linear RGB).
+fn luminance(image: &LinearRgbImage) -> Vec<f32> {
+    image
+        .data()
+        .iter()
+        .map(|&[r, g, b]| 0.2126 * r + 0.7152 * g + 0.0722 * b)
+        .collect()
+}
+
+/// Average luminance variance over a grid of `block`-sized regions.
+fn mean_block_variance(luma: &[f32], width: usize, height: usize, block: usize) -> f64 {
+    let rows = grid_spans(height, block);
+    let cols = grid_spans(width, block);
+    let total: f64 = rows
+        .iter()
+        .flat_map(|&(y, h)| {
+            cols.iter()
+                .map(move |&(x, w)| region_variance(luma, width, x, y, w, h))
+        })
+        .sum();
+    total / (rows.len() * cols.len()) as f64
+}
+
+fn region_variance(
+    luma: &[f32],
+    stride: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> f64 {
+    let mut sum = 0.0f64;
+    let mut sum_sq = 0.0f64;
+    for row in luma[y * stride..].chunks(stride).take(height) {
+        for &v in &row[x..x + width] {
+            let v = f64::from(v);
+            sum += v;
+            sum_sq += v * v;
+        }
+    }
+    let n = (width * height) as f64;
+    let mean = sum / n;
+    (sum_sq / n - mean * mean).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_with_text(width: usize, height: usize) -> LinearRgbImage {
+        // Smooth gradient everywhere except a high-contrast "text" block in the top-left
+        let data = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let v = if x < width / 4 && y < height / 4 {
+                    if (x / 2 + y / 3) % 2 == 0 {
+                        0.0
+                    } else {
+                        1.0
+                    }
+                } else {
+                    0.4 + 0.1 * x as f32 / width as f32
+                };
+                [v, v, v]
+            })
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    }
+
+    fn distort(image: &LinearRgbImage) -> LinearRgbImage {
+        let data = image
+            .data()
+            .iter()
+            .map(|&[r, g, b]| [r * 0.9, g * 0.95, (b * 1.05).min(1.0)])
+            .collect();
+        LinearRgbImage::new(data, image.width(), image.height())
+    }
+
+    fn assert_covers(tile: &TileScore, width: usize, height: usize) {
+        let area: usize = tile.leaves().map(|t| t.width * t.height).sum();
+        assert_eq!(area, width * height);
+        for leaf in tile.leaves() {
+            assert!(leaf.x + leaf.width <= width && leaf.y + leaf.height <= height);
+        }
+    }
+
+    #[test]
+    fn test_grid_tiles_cover_image() {
+        let source = flat_with_text(100, 70);
+        let tiles = compute_ssimulacra2_tiles(
+            source.clone(),
+            distort(&source),
+            Some(TileLayout::Grid {
+                width: 32,
+                height: 32,
+            }),
+            Ssimulacra2Config::default(),
+        )
+        .unwrap();
+
+        // 100 = 32 + 32 + 36 (remainder of 4 merged), 70 = 32 + 38 (remainder of 6 merged)
+        assert_eq!(tiles.children.len(), 6);
+        assert!(tiles.children.iter().all(TileScore::is_leaf));
+        assert_covers(&tiles, 100, 70);
+    }
+
+    #[test]
+    fn test_adaptive_tiles_refine_busy_regions() {
+        let source = flat_with_text(256, 256);
+        let tiles = compute_ssimulacra2_tiles(
+            source.clone(),
+            distort(&source),
+            None,
+            Ssimulacra2Config::default(),
+        )
+        .unwrap();
+
+        assert_covers(&tiles, 256, 256);
+        assert_eq!(tiles.children.len(), 4);
+        // The quadrant containing the text block is refined further than the smooth ones
+        assert!(!tiles.children[0].is_leaf());
+        assert!(tiles.children[1..].iter().all(TileScore::is_leaf));
+    }
+
+    #[test]
+    fn test_identical_tiles_score_100() {
+        let source = flat_with_text(128, 128);
+        let tiles =
+            compute_ssimulacra2_tiles(source.clone(), source, None, Ssimulacra2Config::default())
+                .unwrap();
+        assert!(tiles.leaves().all(|t| t.score == 100.0));
+        assert_eq!(tiles.score, 100.0);
+    }
+
+    #[test]
+    fn test_tiles_dimension_mismatch() {
+        let source = flat_with_text(64, 64);
+        let distorted = flat_with_text(32, 32);
+        let result =
+            compute_ssimulacra2_tiles(source, distorted, None, Ssimulacra2Config::default());
+        assert!(matches!(
+            result,
+            Err(Ssimulacra2Error::NonMatchingImageDimensions)
+        ));
+    }
+}
@@ -0,0 +1,429 @@
+//! Transfer functions for encoded RGB inputs, including HDR.
+//!
+//! [`TransferFunction`] decodes sample values to linear light: sRGB, plain
+//! gamma 2.2 and 2.8, linear, and the two HDR curves of ITU-R BT.2100, PQ
+//! (SMPTE ST 2084) and HLG. With the `simd` feature, rows are decoded eight
+//! values at a time; the scalar [`TransferFunction::to_linear`] is the
+//! reference for the remainder and for callers converting single values.
+//!
+//! PQ and HLG describe absolute display luminance, so turning them into the
+//! metric's relative linear RGB needs a white level: [`EncodedRgb`] maps
+//! [`HDR_REFERENCE_WHITE_NITS`] (the BT.2408 graphics white) to 1.0 unless
+//! told otherwise. Highlights above it exceed 1.0, which XYB handles.
+//!
+//! [`Colorimetry`] records how a [`LinearRgbImage`] was decoded. Comparisons
+//! refuse to score two tagged images decoded with different transfers or
+//! ranges, and bring tagged BT.2020 pixels into BT.709 first.
+//!
+//! [`EncodedRgb`]: crate::EncodedRgb
+
+use std::borrow::Cow;
+use std::fmt;
+
+#[cfg(feature = "simd")]
+use multiversion::multiversion;
+#[cfg(feature = "simd")]
+use wide::f32x8;
+
+use crate::{LinearRgbImage, Ssimulacra2Error};
+
+/// Luminance mapped to linear 1.0 for PQ and HLG inputs by default: the HDR
+/// reference white of ITU-R BT.2408, in cd/m².
+pub const HDR_REFERENCE_WHITE_NITS: f32 = 203.0;
+
+#[cfg(feature = "simd")]
+const LANES: usize = 8;
+
+// SMPTE ST 2084
+const PQ_M1: f32 = 0.159_301_76;
+const PQ_M2: f32 = 78.843_75;
+const PQ_C1: f32 = 0.835_937_5;
+const PQ_C2: f32 = 18.851_563;
+const PQ_C3: f32 = 18.6875;
+const PQ_PEAK_NITS: f32 = 10_000.0;
+
+// ITU-R BT.2100 HLG, for the nominal 1000 cd/m² display
+const HLG_A: f32 = 0.178_832_77;
+const HLG_B: f32 = 0.284_668_92;
+const HLG_C: f32 = 0.559_910_7;
+const HLG_PEAK_NITS: f32 = 1000.0;
+const HLG_SYSTEM_GAMMA: f32 = 1.2;
+
+/// Transfer function (EOTF) of encoded RGB samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferFunction {
+    /// sRGB (IEC 61966-2-1), the convention for 8- and 16-bit inputs
+    #[default]
+    Srgb,
+    /// Samples are already linear
+    Linear,
+    /// Pure power law with exponent 2.2
+    Gamma22,
+    /// Pure power law with exponent 2.8 (BT.470 System B/G)
+    Gamma28,
+    /// Perceptual quantizer (SMPTE ST 2084), used by HDR10; absolute up to
+    /// 10000 cd/m²
+    Pq,
+    /// Hybrid log-gamma (ITU-R BT.2100), decoded for a 1000 cd/m² display
+    Hlg,
+}
+
+impl TransferFunction {
+    /// Decodes one sample. Encoded values are clamped to 0.0-1.0, except for
+    /// `Linear`, which passes values through unchanged.
+    ///
+    /// Relative transfers return linear light with 1.0 at peak white. PQ
+    /// returns luminance as a fraction of 10000 cd/m². HLG returns scene light
+    /// (the inverse OETF); the display's OOTF couples the three channels, so
+    /// it is only applied when decoding whole pixels through
+    /// [`EncodedRgb`](crate::EncodedRgb).
+    pub fn to_linear(self, encoded: f32) -> f32 {
+        let e = encoded.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Srgb => crate::input::srgb_to_linear(e),
+            TransferFunction::Linear => encoded,
+            TransferFunction::Gamma22 => e.powf(2.2),
+            TransferFunction::Gamma28 => e.powf(2.8),
+            TransferFunction::Pq => {
+                let p = e.powf(PQ_M2.recip());
+                ((p - PQ_C1).max(0.0) / PQ_C3.mul_add(-p, PQ_C2)).powf(PQ_M1.recip())
+            }
+            TransferFunction::Hlg => {
+                if e <= 0.5 {
+                    e * e / 3.0
+                } else {
+                    (((e - HLG_C) / HLG_A).exp() + HLG_B) / 12.0
+                }
+            }
+        }
+    }
+
+    /// Nominal peak luminance in cd/m² of absolute (HDR) transfers, `None`
+    /// for relative ones
+    pub fn peak_nits(self) -> Option<f32> {
+        match self {
+            TransferFunction::Pq => Some(PQ_PEAK_NITS),
+            TransferFunction::Hlg => Some(HLG_PEAK_NITS),
+            _ => None,
+        }
+    }
+
+    /// [`to_linear`](Self::to_linear) for eight samples
+    #[cfg(feature = "simd")]
+    #[inline(always)]
+    fn to_linear_x8(self, encoded: f32x8) -> f32x8 {
+        let splat = f32x8::splat;
+        let e = encoded.max(splat(0.0)).min(splat(1.0));
+        match self {
+            TransferFunction::Srgb => {
+                let curve = ((e + splat(0.055)) / splat(1.055)).powf_simd(splat(2.4));
+                e.simd_le(splat(0.04045)).select(e / splat(12.92), curve)
+            }
+            TransferFunction::Linear => encoded,
+            TransferFunction::Gamma22 => e.powf_simd(splat(2.2)),
+            TransferFunction::Gamma28 => e.powf_simd(splat(2.8)),
+            TransferFunction::Pq => {
+                let p = e.powf_simd(splat(PQ_M2.recip()));
+                ((p - splat(PQ_C1)).max(splat(0.0)) / splat(PQ_C3).mul_neg_add(p, splat(PQ_C2)))
+                    .powf_simd(splat(PQ_M1.recip()))
+            }
+            TransferFunction::Hlg => {
+                let log = (((e - splat(HLG_C)) / splat(HLG_A)).exp() + splat(HLG_B)) / splat(12.0);
+                e.simd_le(splat(0.5)).select(e * e / splat(3.0), log)
+            }
+        }
+    }
+}
+
+/// Decodes `values` in place, eight at a time.
+#[cfg(feature = "simd")]
+#[multiversion(targets("x86_64+avx2+fma", "x86_64+sse2", "aarch64+neon"))]
+pub(crate) fn to_linear_in_place(transfer: TransferFunction, values: &mut [f32]) {
+    if transfer == TransferFunction::Linear {
+        return;
+    }
+    let mut chunks = values.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        let encoded = f32x8::from(<[f32; LANES]>::try_from(&*chunk).unwrap());
+        chunk.copy_from_slice(&transfer.to_linear_x8(encoded).to_array());
+    }
+    for v in chunks.into_remainder() {
+        *v = transfer.to_linear(*v);
+    }
+}
+
+/// Decodes `values` in place.
+#[cfg(not(feature = "simd"))]
+pub(crate) fn to_linear_in_place(transfer: TransferFunction, values: &mut [f32]) {
+    if transfer == TransferFunction::Linear {
+        return;
+    }
+    for v in values {
+        *v = transfer.to_linear(*v);
+    }
+}
+
+/// Applies the HLG OOTF of a 1000 cd/m² display to scene-linear BT.2020
+/// pixels, giving display light relative to the display peak.
+pub(crate) fn hlg_ootf(pixels: &mut [[f32; 3]]) {
+    for px in pixels {
+        let luma = 0.2627f32.mul_add(px[0], 0.6780f32.mul_add(px[1], 0.0593 * px[2]));
+        let gain = luma.max(0.0).powf(HLG_SYSTEM_GAMMA - 1.0);
+        for v in px.iter_mut() {
+            *v *= gain;
+        }
+    }
+}
+
+/// RGB primaries of encoded samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RgbPrimaries {
+    /// ITU-R BT.709, shared by sRGB; what the metric works in
+    #[default]
+    Bt709,
+    /// ITU-R BT.2020, used by HDR10 and HLG video; converted to BT.709 with
+    /// out-of-gamut colors clipped
+    Bt2020,
+}
+
+/// Linear BT.2020 to BT.709 RGB (ITU-R BT.2087)
+const BT2020_TO_BT709: [[f32; 3]; 3] = [
+    [1.660_491, -0.587_641, -0.072_850],
+    [-0.124_550, 1.132_9, -0.008_349],
+    [-0.018_151, -0.100_579, 1.118_73],
+];
+
+impl RgbPrimaries {
+    /// Converts linear pixels in these primaries to BT.709 and scales them by
+    /// `scale` in the same pass.
+    pub(crate) fn to_bt709(self, pixels: &mut [[f32; 3]], scale: f32) {
+        match self {
+            RgbPrimaries::Bt709 => {
+                if scale != 1.0 {
+                    for v in pixels.as_flattened_mut() {
+                        *v *= scale;
+                    }
+                }
+            }
+            RgbPrimaries::Bt2020 => {
+                let m = BT2020_TO_BT709;
+                for px in pixels {
+                    let [r, g, b] = *px;
+                    *px = std::array::from_fn(|i| {
+                        let v = m[i][0].mul_add(r, m[i][1].mul_add(g, m[i][2] * b));
+                        v.max(0.0) * scale
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// How the pixels of a [`LinearRgbImage`] were decoded, attached with
+/// [`LinearRgbImage::with_colorimetry`].
+///
+/// [`EncodedRgb`](crate::EncodedRgb) and the built-in 8- and 16-bit sRGB
+/// conversions tag their output; images built from linear values are
+/// untagged unless the caller tags them. Two tagged inputs must agree on
+/// transfer and range, or the comparison fails with
+/// [`Ssimulacra2Error::ColorimetryMismatch`]. Untagged inputs are compared
+/// with anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Colorimetry {
+    /// Primaries of the linear values. Images in other primaries than
+    /// BT.709 are converted before scoring.
+    pub primaries: RgbPrimaries,
+    /// Transfer function the samples were decoded from
+    pub transfer: TransferFunction,
+    /// Whether integer samples were limited range
+    pub limited_range: bool,
+}
+
+impl fmt::Display for Colorimetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let transfer = match self.transfer {
+            TransferFunction::Srgb => "sRGB",
+            TransferFunction::Linear => "linear",
+            TransferFunction::Gamma22 => "gamma 2.2",
+            TransferFunction::Gamma28 => "gamma 2.8",
+            TransferFunction::Pq => "PQ",
+            TransferFunction::Hlg => "HLG",
+        };
+        let primaries = match self.primaries {
+            RgbPrimaries::Bt709 => "BT.709",
+            RgbPrimaries::Bt2020 => "BT.2020",
+        };
+        let range = if self.limited_range {
+            "limited"
+        } else {
+            "full"
+        };
+        write!(f, "{}, {} primaries, {} range", transfer, primaries, range)
+    }
+}
+
+/// Fails if both colorimetries are known and differ in transfer or range.
+pub(crate) fn check_colorimetry(
+    reference: Option<Colorimetry>,
+    distorted: Option<Colorimetry>,
+) -> Result<(), Ssimulacra2Error> {
+    match (reference, distorted) {
+        (Some(r), Some(d)) if (r.transfer, r.limited_range) != (d.transfer, d.limited_range) => {
+            Err(Ssimulacra2Error::ColorimetryMismatch {
+                reference: r,
+                distorted: d,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Converts an image tagged with other primaries to BT.709, copying it if
+/// it is borrowed.
+pub(crate) fn to_metric_primaries(image: &mut Cow<'_, LinearRgbImage>) {
+    let Some(colorimetry) = image.colorimetry() else {
+        return;
+    };
+    if colorimetry.primaries != RgbPrimaries::Bt709 {
+        let image = image.to_mut();
+        colorimetry.primaries.to_bt709(&mut image.data, 1.0);
+        image.colorimetry = Some(Colorimetry {
+            primaries: RgbPrimaries::Bt709,
+            ..colorimetry
+        });
+    }
+}
+
+/// Checks that a source/distorted pair was decoded consistently and brings
+/// both into the metric's primaries.
+pub(crate) fn reconcile_colorimetry(
+    source: &mut Cow<'_, LinearRgbImage>,
+    distorted: &mut Cow<'_, LinearRgbImage>,
+) -> Result<(), Ssimulacra2Error> {
+    check_colorimetry(source.colorimetry(), distorted.colorimetry())?;
+    to_metric_primaries(source);
+    to_metric_primaries(distorted);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [TransferFunction; 6] = [
+        TransferFunction::Srgb,
+        TransferFunction::Linear,
+        TransferFunction::Gamma22,
+        TransferFunction::Gamma28,
+        TransferFunction::Pq,
+        TransferFunction::Hlg,
+    ];
+
+    #[test]
+    fn test_curve_endpoints() {
+        for transfer in ALL {
+            assert_eq!(transfer.to_linear(0.0), 0.0, "{:?}", transfer);
+            assert!(
+                (transfer.to_linear(1.0) - 1.0).abs() < 1e-4,
+                "{:?}: {}",
+                transfer,
+                transfer.to_linear(1.0)
+            );
+        }
+    }
+
+    #[test]
+    fn test_pq_reference_points() {
+        // Code values from the ST 2084 tables, 10-bit full range
+        let nits = |code: f32| TransferFunction::Pq.to_linear(code / 1023.0) * PQ_PEAK_NITS;
+        assert!((nits(520.0) - 100.0).abs() < 1.5, "{}", nits(520.0));
+        assert!((nits(769.0) - 1000.0).abs() < 15.0, "{}", nits(769.0));
+    }
+
+    #[test]
+    fn test_hlg_is_continuous() {
+        let below = TransferFunction::Hlg.to_linear(0.5);
+        let above = TransferFunction::Hlg.to_linear(0.500_001);
+        assert!((below - 1.0 / 12.0).abs() < 1e-6);
+        assert!((above - below).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        // 1003 values, so the scalar remainder runs too
+        let ramp: Vec<f32> = (0..1003).map(|i| i as f32 / 1002.0).collect();
+        for transfer in ALL {
+            let mut simd = ramp.clone();
+            to_linear_in_place(transfer, &mut simd);
+            for (&e, &v) in ramp.iter().zip(&simd) {
+                let expected = transfer.to_linear(e);
+                assert!(
+                    (v - expected).abs() <= 1e-4 * expected.max(1e-4),
+                    "{:?} at {}: {} vs {}",
+                    transfer,
+                    e,
+                    v,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bt2020_white_stays_white() {
+        let mut pixels = [[1.0f32; 3], [0.25; 3]];
+        RgbPrimaries::Bt2020.to_bt709(&mut pixels, 2.0);
+        for (px, expected) in pixels.iter().zip([2.0, 0.5]) {
+            for v in px {
+                assert!((v - expected).abs() < 1e-5, "{:?}", pixels);
+            }
+        }
+    }
+
+    #[test]
+    fn test_colorimetry_reconciliation() {
+        let hdr10 = Colorimetry {
+            primaries: RgbPrimaries::Bt2020,
+            transfer: TransferFunction::Pq,
+            limited_range: true,
+        };
+        let image = |colorimetry: Option<Colorimetry>| {
+            let image = LinearRgbImage::new(vec![[0.0, 1.0, 0.0]; 4], 2, 2);
+            match colorimetry {
+                Some(c) => image.with_colorimetry(c),
+                None => image,
+            }
+        };
+
+        // Untagged images match anything; BT.2020 pixels become BT.709
+        let (a, b) = (image(Some(hdr10)), image(None));
+        let (mut a, mut b) = (Cow::Borrowed(&a), Cow::Borrowed(&b));
+        reconcile_colorimetry(&mut a, &mut b).unwrap();
+        assert!(matches!(a, Cow::Owned(_)));
+        assert!(matches!(b, Cow::Borrowed(_)));
+        assert_eq!(a.colorimetry().unwrap().primaries, RgbPrimaries::Bt709);
+        assert!((a.data()[0][0] - 0.0).abs() < 1e-6);
+        assert!((a.data()[0][1] - 1.1329).abs() < 1e-4);
+
+        // Primaries alone don't conflict, transfer and range do
+        let bt709 = Colorimetry {
+            primaries: RgbPrimaries::Bt709,
+            ..hdr10
+        };
+        assert_eq!(check_colorimetry(Some(hdr10), Some(bt709)), Ok(()));
+        let sdr = Colorimetry::default();
+        assert_eq!(
+            check_colorimetry(Some(hdr10), Some(sdr)),
+            Err(Ssimulacra2Error::ColorimetryMismatch {
+                reference: hdr10,
+                distorted: sdr,
+            })
+        );
+        let full = Colorimetry {
+            limited_range: false,
+            ..hdr10
+        };
+        assert!(check_colorimetry(Some(full), Some(hdr10)).is_err());
+        assert_eq!(hdr10.to_string(), "PQ, BT.2020 primaries, limited range");
+    }
+}
@@ -0,0 +1,438 @@
+//! Frame-by-frame scoring of a video sequence.
+//!
+//! [`VideoScorer`] takes source/distorted frame pairs in display order and
+//! scores each with a reused [`Ssimulacra2Scratch`], so a long sequence
+//! allocates its pipeline buffers once. [`finish`](VideoScorer::finish)
+//! returns the per-frame scores as [`VideoScores`], which pools them into the
+//! usual sequence-level numbers: mean, harmonic mean, percentiles and the
+//! worst frame.
+//!
+//! With the `rayon` feature, [`VideoScorer::with_parallel_frames`] scores
+//! batches of frames concurrently, each with its own scratch. To consume
+//! per-frame scores while frames are still being pushed, use
+//! [`FrameQueue`](crate::FrameQueue), which delivers them with sequence
+//! numbers.
+//!
+//! Screen captures and slideshows repeat many frames exactly, and an encoder
+//! often reproduces them exactly too.
+//! [`with_identical_frame_prescreen`](VideoScorer::with_identical_frame_prescreen)
+//! compares each pair sample by sample first and scores pairs that match
+//! within a tolerance as 100, without running the pipeline.
+
+use crate::parallelism::THREADS_AVAILABLE;
+use crate::pooling::exact_quantile;
+use crate::{
+    compute_ssimulacra2_into, LinearRgbImage, PoolSummary, ScorePool, Ssimulacra2Config,
+    Ssimulacra2Error, Ssimulacra2Scratch, ToLinearRgb,
+};
+
+/// Scores a video sequence one frame pair at a time.
+///
+/// ```
+/// use fast_ssim2::{LinearRgbImage, VideoScorer};
+///
+/// let frame = |v: f32| LinearRgbImage::new(vec![[v; 3]; 16 * 16], 16, 16);
+/// let mut scorer = VideoScorer::new();
+/// for v in [0.2, 0.4, 0.6] {
+///     scorer.push_frame(frame(v), frame(v))?;
+/// }
+/// let scores = scorer.finish()?;
+/// assert_eq!(scores.len(), 3);
+/// assert_eq!(scores.mean(), Some(100.0));
+/// # Ok::<(), fast_ssim2::Ssimulacra2Error>(())
+/// ```
+pub struct VideoScorer {
+    /// One scratch per frame scored concurrently
+    scratch: Vec<Ssimulacra2Scratch>,
+    /// Frames waiting for a full batch (only with parallel frames)
+    pending: Vec<PendingFrame>,
+    scores: Vec<f64>,
+    /// Largest sample difference of pairs scored as identical
+    prescreen: Option<f32>,
+    /// Pairs the prescreen scored
+    prescreened: usize,
+}
+
+/// A frame pushed while a batch is filling
+enum PendingFrame {
+    // Only read back when rayon scores the batch
+    #[cfg_attr(not(feature = "rayon"), allow(dead_code))]
+    Compare(LinearRgbImage, LinearRgbImage),
+    /// Matched by the prescreen, scores 100
+    Identical,
+}
+
+impl Default for VideoScorer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VideoScorer {
+    /// Creates a scorer using the default configuration.
+    pub fn new() -> Self {
+        Self::with_config(Ssimulacra2Config::default())
+    }
+
+    /// Creates a scorer comparing frames with `config`.
+    pub fn with_config(config: Ssimulacra2Config) -> Self {
+        Self {
+            scratch: vec![Ssimulacra2Scratch::with_config(config)],
+            pending: Vec::new(),
+            scores: Vec::new(),
+            prescreen: None,
+            prescreened: 0,
+        }
+    }
+
+    /// Scores pairs whose samples all differ by at most `max_difference`
+    /// as 100 without running the pipeline.
+    ///
+    /// The check compares linear RGB (and alpha, if present) sample by
+    /// sample, which costs far less than scoring, and stops at the first
+    /// larger difference. With a `max_difference` of 0 only exact copies are
+    /// skipped and scores are unchanged, since the metric scores those 100
+    /// too. Anything larger counts near-copies as perfect; 1e-4 is below
+    /// one 8-bit code value everywhere. Pairs with differing dimensions,
+    /// colorimetry or alpha planes, or smaller than 8x8, are always
+    /// compared, so their errors are still reported.
+    #[must_use]
+    pub fn with_identical_frame_prescreen(mut self, max_difference: f32) -> Self {
+        self.prescreen = Some(max_difference.max(0.0));
+        self
+    }
+
+    /// Scores up to `frames` frames at once on rayon's global thread pool.
+    ///
+    /// Frames are buffered until a batch is full, so this keeps `frames`
+    /// converted frame pairs and `frames` scratch buffers in memory. Only
+    /// has an effect with the `rayon` feature on a target with threads;
+    /// values below 2 score each frame as it is pushed.
+    #[must_use]
+    pub fn with_parallel_frames(mut self, frames: usize) -> Self {
+        let frames = if THREADS_AVAILABLE { frames.max(1) } else { 1 };
+        let config = self.scratch[0].config().clone();
+        self.scratch
+            .resize_with(frames, || Ssimulacra2Scratch::with_config(config.clone()));
+        self
+    }
+
+    /// The configuration frames are compared with
+    pub fn config(&self) -> &Ssimulacra2Config {
+        self.scratch[0].config()
+    }
+
+    /// Adds the next source/distorted frame pair.
+    ///
+    /// Without parallel frames the pair is scored immediately; otherwise it
+    /// is scored once its batch is full or in [`finish`](Self::finish).
+    ///
+    /// # Errors
+    /// - If the frames are smaller than 8x8 pixels or their dimensions
+    ///   differ. With parallel frames, the error is reported by the push
+    ///   that completes the batch, and the whole batch is dropped.
+    pub fn push_frame<S, D>(&mut self, source: S, distorted: D) -> Result<(), Ssimulacra2Error>
+    where
+        S: ToLinearRgb,
+        D: ToLinearRgb,
+    {
+        let (source, distorted) = (source.as_linear_rgb(), distorted.as_linear_rgb());
+        let identical = self
+            .prescreen
+            .is_some_and(|max_difference| identical(&source, &distorted, max_difference));
+        if identical {
+            self.prescreened += 1;
+        }
+
+        if self.scratch.len() == 1 {
+            let score = if identical {
+                100.0
+            } else {
+                compute_ssimulacra2_into(&mut self.scratch[0], &*source, &*distorted)?
+            };
+            self.scores.push(score);
+            return Ok(());
+        }
+
+        self.pending.push(if identical {
+            PendingFrame::Identical
+        } else {
+            PendingFrame::Compare(source.into_owned(), distorted.into_owned())
+        });
+        let to_compare = self
+            .pending
+            .iter()
+            .filter(|frame| matches!(frame, PendingFrame::Compare(..)))
+            .count();
+        if to_compare == self.scratch.len() {
+            self.score_pending()?;
+        }
+        Ok(())
+    }
+
+    /// Number of frames the
+    /// [prescreen](Self::with_identical_frame_prescreen) scored without
+    /// running the pipeline
+    pub fn prescreened_frames(&self) -> usize {
+        self.prescreened
+    }
+
+    /// Number of frames pushed so far
+    pub fn frames(&self) -> usize {
+        self.scores.len() + self.pending.len()
+    }
+
+    /// Scores any buffered frames and returns the scores of the sequence.
+    ///
+    /// # Errors
+    /// - Propagates comparison errors of buffered frames
+    pub fn finish(mut self) -> Result<VideoScores, Ssimulacra2Error> {
+        self.score_pending()?;
+        Ok(VideoScores {
+            scores: self.scores,
+        })
+    }
+
+    #[cfg(feature = "rayon")]
+    fn score_pending(&mut self) -> Result<(), Ssimulacra2Error> {
+        use rayon::prelude::*;
+
+        let pairs: Vec<_> = self
+            .pending
+            .iter()
+            .filter_map(|frame| match frame {
+                PendingFrame::Compare(source, distorted) => Some((source, distorted)),
+                PendingFrame::Identical => None,
+            })
+            .collect();
+        let results: Result<Vec<f64>, _> = pairs
+            .into_par_iter()
+            .zip(self.scratch.par_iter_mut())
+            .map(|((source, distorted), scratch)| {
+                compute_ssimulacra2_into(scratch, source, distorted)
+            })
+            .collect();
+        let pending = std::mem::take(&mut self.pending);
+        let mut results = results?.into_iter();
+        self.scores.extend(pending.iter().map(|frame| match frame {
+            PendingFrame::Compare(..) => results.next().expect("one result per compared pair"),
+            PendingFrame::Identical => 100.0,
+        }));
+        Ok(())
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn score_pending(&mut self) -> Result<(), Ssimulacra2Error> {
+        // Frames are only buffered with parallel frames, which need rayon
+        debug_assert!(self.pending.is_empty());
+        Ok(())
+    }
+}
+
+/// Whether the prescreen may score the pair as 100: every sample within
+/// `max_difference`, and nothing the pipeline would reject or weigh
+fn identical(source: &LinearRgbImage, distorted: &LinearRgbImage, max_difference: f32) -> bool {
+    let close = |a: &f32, b: &f32| (a - b).abs() <= max_difference;
+    let alpha_matches = match (source.alpha(), distorted.alpha()) {
+        (None, None) => true,
+        (Some(a), Some(b)) => a.iter().zip(b).all(|(a, b)| close(a, b)),
+        _ => false,
+    };
+    source.width() == distorted.width()
+        && source.height() == distorted.height()
+        && source.width() >= 8
+        && source.height() >= 8
+        && source.colorimetry() == distorted.colorimetry()
+        && alpha_matches
+        && source
+            .data()
+            .iter()
+            .zip(distorted.data())
+            .all(|(a, b)| a.iter().zip(b).all(|(a, b)| close(a, b)))
+}
+
+/// Per-frame scores of a sequence, with sequence-level pooling.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VideoScores {
+    scores: Vec<f64>,
+}
+
+impl VideoScores {
+    /// Scores in the order the frames were pushed
+    pub fn scores(&self) -> &[f64] {
+        &self.scores
+    }
+
+    /// Number of scored frames
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Returns `true` if no frames were scored
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Arithmetic mean of the frame scores
+    pub fn mean(&self) -> Option<f64> {
+        (!self.is_empty()).then(|| self.scores.iter().sum::<f64>() / self.len() as f64)
+    }
+
+    /// Harmonic mean of the frame scores, which weighs bad frames more
+    /// heavily than [`mean`](Self::mean).
+    ///
+    /// Negative scores are clamped to 0 and, as in VMAF's harmonic mean, 1 is
+    /// added to each score before averaging and subtracted afterwards, so a
+    /// single zero doesn't zero the result.
+    pub fn harmonic_mean(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        let inverse_sum: f64 = self.scores.iter().map(|s| 1.0 / (s.max(0.0) + 1.0)).sum();
+        Some(self.len() as f64 / inverse_sum - 1.0)
+    }
+
+    /// The `percent`th percentile of the frame scores (clamped to 0..=100),
+    /// interpolating linearly between closest ranks. The 5th percentile is
+    /// a common summary of the worst stretches of a sequence.
+    pub fn percentile(&self, percent: f64) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut sorted = self.scores.clone();
+        sorted.sort_by(f64::total_cmp);
+        Some(exact_quantile(&sorted, percent.clamp(0.0, 100.0) / 100.0))
+    }
+
+    /// Index and score of the lowest scoring frame (the first one on ties)
+    pub fn worst_frame(&self) -> Option<(usize, f64)> {
+        self.scores
+            .iter()
+            .copied()
+            .enumerate()
+            .reduce(|worst, frame| if frame.1 < worst.1 { frame } else { worst })
+    }
+
+    /// Summary statistics of the frame scores (see [`ScorePool`])
+    pub fn summary(&self) -> Option<PoolSummary> {
+        let mut pool = ScorePool::new();
+        for &score in &self.scores {
+            pool.push(score);
+        }
+        pool.summary()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_ssimulacra2_with_config;
+    use crate::test_util::frame;
+
+    #[test]
+    fn test_scores_match_single_comparisons() {
+        let config = Ssimulacra2Config::default();
+        let noise = [0.0, 0.05, 0.2, 0.01, 0.1];
+        let expected: Vec<f64> = noise
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| {
+                compute_ssimulacra2_with_config(frame(32, i, 0.0), frame(32, i, n), config.clone())
+            })
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        for parallel in [1, 2, 4] {
+            let mut scorer =
+                VideoScorer::with_config(config.clone()).with_parallel_frames(parallel);
+            for (i, &n) in noise.iter().enumerate() {
+                scorer
+                    .push_frame(frame(32, i, 0.0), frame(32, i, n))
+                    .unwrap();
+            }
+            assert_eq!(scorer.frames(), noise.len());
+            let scores = scorer.finish().unwrap();
+            assert_eq!(scores.scores(), expected, "{} parallel frames", parallel);
+        }
+    }
+
+    #[test]
+    fn test_identical_frame_prescreen() {
+        let config = Ssimulacra2Config::default();
+        let noise = [0.0, 0.05, 0.0, 1e-6, 0.1];
+        for parallel in [1, 2] {
+            let mut plain = VideoScorer::with_config(config.clone()).with_parallel_frames(parallel);
+            let mut exact = VideoScorer::with_config(config.clone())
+                .with_parallel_frames(parallel)
+                .with_identical_frame_prescreen(0.0);
+            let mut tolerant = VideoScorer::with_config(config.clone())
+                .with_parallel_frames(parallel)
+                .with_identical_frame_prescreen(1e-4);
+            for (i, &n) in noise.iter().enumerate() {
+                for scorer in [&mut plain, &mut exact, &mut tolerant] {
+                    scorer
+                        .push_frame(frame(32, i, 0.0), frame(32, i, n))
+                        .unwrap();
+                }
+            }
+            assert_eq!(
+                (exact.prescreened_frames(), tolerant.prescreened_frames()),
+                (2, 3)
+            );
+            let plain = plain.finish().unwrap();
+            assert_eq!(exact.finish().unwrap(), plain);
+            let tolerant = tolerant.finish().unwrap();
+            assert_eq!(tolerant.scores()[3], 100.0);
+            assert_eq!(
+                (tolerant.scores()[1], tolerant.scores()[4]),
+                (plain.scores()[1], plain.scores()[4])
+            );
+        }
+
+        // Mismatched dimensions are still reported
+        let mut scorer = VideoScorer::new().with_identical_frame_prescreen(1.0);
+        let small = LinearRgbImage::new(vec![[0.5; 3]; 16 * 16], 16, 16);
+        assert!(scorer.push_frame(frame(32, 0, 0.0), &small).is_err());
+        assert_eq!(scorer.prescreened_frames(), 0);
+    }
+
+    #[test]
+    fn test_pooling() {
+        let scores = VideoScores {
+            scores: vec![90.0, 50.0, -10.0, 70.0],
+        };
+        assert_eq!(scores.mean(), Some(50.0));
+        assert_eq!(scores.worst_frame(), Some((2, -10.0)));
+        assert_eq!(scores.percentile(0.0), Some(-10.0));
+        assert_eq!(scores.percentile(50.0), Some(60.0));
+        assert_eq!(scores.percentile(150.0), Some(90.0));
+
+        // Clamped -10 counts as 0: 4 / (1/91 + 1/51 + 1 + 1/71) - 1
+        let harmonic = scores.harmonic_mean().unwrap();
+        let expected = 4.0 / (1.0 / 91.0 + 1.0 / 51.0 + 1.0 + 1.0 / 71.0) - 1.0;
+        assert!((harmonic - expected).abs() < 1e-12);
+        assert!(harmonic < scores.mean().unwrap());
+        assert_eq!(scores.summary().unwrap().min, -10.0);
+
+        let empty = VideoScores::default();
+        assert_eq!(empty.mean(), None);
+        assert_eq!(empty.harmonic_mean(), None);
+        assert_eq!(empty.percentile(5.0), None);
+        assert_eq!(empty.worst_frame(), None);
+    }
+
+    #[test]
+    fn test_errors_propagate() {
+        let mut scorer = VideoScorer::new();
+        let small = LinearRgbImage::new(vec![[0.5; 3]; 16 * 16], 16, 16);
+        assert_eq!(
+            scorer.push_frame(frame(32, 0, 0.0), &small),
+            Err(Ssimulacra2Error::NonMatchingImageDimensions)
+        );
+        // Failed frames are not counted
+        scorer
+            .push_frame(frame(32, 0, 0.0), frame(32, 0, 0.1))
+            .unwrap();
+        assert_eq!(scorer.finish().unwrap().len(), 1);
+    }
+}
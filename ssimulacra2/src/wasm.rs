@@ -0,0 +1,129 @@
+//! JavaScript bindings for browsers (requires the `wasm` feature).
+//!
+//! Build a `cdylib` with WebAssembly SIMD enabled, so the default backend
+//! runs on `simd128`, and generate the JavaScript glue with the
+//! `wasm-bindgen` CLI:
+//!
+//! ```text
+//! RUSTFLAGS="-C target-feature=+simd128" cargo rustc --release \
+//!     --target wasm32-unknown-unknown -p fast-ssim2 \
+//!     --no-default-features --features simd,wasm --crate-type cdylib
+//! wasm-bindgen --target web target/wasm32-unknown-unknown/release/fast_ssim2.wasm --out-dir pkg
+//! ```
+//!
+//! Images are passed as canvas `ImageData.data`: 8-bit sRGB RGBA in a
+//! `Uint8ClampedArray`, with alpha discarded.
+//!
+//! ```js
+//! import init, { computeSsimulacra2 } from "./pkg/fast_ssim2.js";
+//! await init();
+//! const score = computeSsimulacra2(original.data, compressed.data, width, height);
+//! ```
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::Clamped;
+
+use crate::{compute_ssimulacra2, LinearRgbImage, Ssimulacra2Reference};
+
+fn image(rgba: &[u8], width: u32, height: u32) -> Result<LinearRgbImage, JsError> {
+    Ok(LinearRgbImage::from_srgba_u8(
+        rgba,
+        width as usize,
+        height as usize,
+    )?)
+}
+
+/// Computes the SSIMULACRA2 score of `distorted` against `source`, both
+/// `width`x`height` RGBA.
+///
+/// # Errors
+/// - If a buffer doesn't hold `width * height * 4` bytes
+/// - If the image is smaller than 8x8 pixels
+#[wasm_bindgen(js_name = computeSsimulacra2)]
+pub fn compute_ssimulacra2_rgba(
+    source: Clamped<Vec<u8>>,
+    distorted: Clamped<Vec<u8>>,
+    width: u32,
+    height: u32,
+) -> Result<f64, JsError> {
+    let source = image(&source, width, height)?;
+    let distorted = image(&distorted, width, height)?;
+    Ok(compute_ssimulacra2(&source, &distorted)?)
+}
+
+/// A source image prepared for repeated comparisons, e.g. against every
+/// quality setting of an encoder.
+#[wasm_bindgen(js_name = Ssimulacra2Reference)]
+pub struct WasmReference(Ssimulacra2Reference);
+
+#[wasm_bindgen(js_class = Ssimulacra2Reference)]
+impl WasmReference {
+    /// Precomputes the reference-side data of a `width`x`height` RGBA image.
+    ///
+    /// # Errors
+    /// - If `source` doesn't hold `width * height * 4` bytes
+    /// - If the image is smaller than 8x8 pixels
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        source: Clamped<Vec<u8>>,
+        width: u32,
+        height: u32,
+    ) -> Result<WasmReference, JsError> {
+        let source = image(&source, width, height)?;
+        Ok(Self(Ssimulacra2Reference::new(source)?))
+    }
+
+    /// Computes the SSIMULACRA2 score of `distorted` against the reference.
+    ///
+    /// # Errors
+    /// - If `distorted` doesn't hold `width * height * 4` bytes
+    /// - If the dimensions differ from the reference's
+    pub fn compare(
+        &self,
+        distorted: Clamped<Vec<u8>>,
+        width: u32,
+        height: u32,
+    ) -> Result<f64, JsError> {
+        Ok(self.0.compare(image(&distorted, width, height)?)?)
+    }
+}
+
+// Natively only the success paths can run: building a `JsError` calls into
+// JavaScript
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(width: u32, height: u32, seed: u32) -> Clamped<Vec<u8>> {
+        let data = (0..width * height * 4)
+            .map(|i| {
+                if i % 4 == 3 {
+                    255
+                } else {
+                    (i.wrapping_mul(2_654_435_761).wrapping_add(seed) >> 24) as u8
+                }
+            })
+            .collect();
+        Clamped(data)
+    }
+
+    #[test]
+    fn test_exports_match_the_rust_api() {
+        let (source, distorted) = (rgba(32, 24, 1), rgba(32, 24, 2));
+        let expected = compute_ssimulacra2(
+            image(&source, 32, 24).unwrap(),
+            image(&distorted, 32, 24).unwrap(),
+        )
+        .unwrap();
+
+        let score = compute_ssimulacra2_rgba(source.clone(), distorted.clone(), 32, 24).unwrap();
+        assert_eq!(score, expected);
+        assert_eq!(
+            compute_ssimulacra2_rgba(source.clone(), source.clone(), 32, 24).unwrap(),
+            100.0
+        );
+
+        let reference = WasmReference::new(source, 32, 24).unwrap();
+        assert_eq!(reference.compare(distorted, 32, 24).unwrap(), expected);
+    }
+}
@@ -0,0 +1,435 @@
+//! Public access to the XYB color conversion used by SSIMULACRA2.
+//!
+//! The metric compares images in the XYB color space (from JPEG XL). When a
+//! score looks wrong, converting the inputs with [`linear_rgb_to_xyb`] and
+//! inspecting the result separates color conversion problems from problems in
+//! the structural comparison. [`xyb_to_linear_rgb`] provides the inverse.
+//!
+//! These functions return raw XYB, before the offsets SSIMULACRA2 applies to
+//! make all channels positive.
+
+use crate::input::LinearRgbImage;
+use crate::{Parallelism, PlanarImage, SimdImpl, Ssimulacra2Config, Stage};
+#[cfg(feature = "yuvxyb")]
+use yuvxyb::{LinearRgb, Xyb};
+
+#[cfg(feature = "simd")]
+use crate::parallelism::{for_each_chunk, for_each_planar_chunk};
+#[cfg(feature = "simd")]
+use crate::{simd_ops, xyb_simd};
+
+#[cfg(feature = "unsafe-simd")]
+use crate::xyb_unsafe_simd;
+
+/// Image in the XYB color space, stored as interleaved `[X, Y, B]` pixels.
+#[derive(Debug, Clone)]
+pub struct XybImage {
+    pub(crate) data: Vec<[f32; 3]>,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+impl XybImage {
+    /// Creates a new XYB image from raw data.
+    pub fn new(data: Vec<[f32; 3]>, width: usize, height: usize) -> Self {
+        debug_assert_eq!(data.len(), width * height);
+        Self {
+            data,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the image width.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the image height.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the pixel data.
+    pub fn data(&self) -> &[[f32; 3]] {
+        &self.data
+    }
+
+    /// Returns mutable pixel data.
+    pub fn data_mut(&mut self) -> &mut [[f32; 3]] {
+        &mut self.data
+    }
+
+    /// Consumes the image and returns the pixel data.
+    pub fn into_data(self) -> Vec<[f32; 3]> {
+        self.data
+    }
+}
+
+/// Converts linear RGB to XYB with the default backend (safe SIMD).
+pub fn linear_rgb_to_xyb(image: &LinearRgbImage) -> XybImage {
+    linear_rgb_to_xyb_with_config(image, Ssimulacra2Config::default())
+}
+
+/// Converts linear RGB to XYB with the backend and parallelism selected in
+/// `config`.
+///
+/// This is the same conversion the score computation runs for each scale.
+/// The SIMD backends split large images across threads; the scalar backend
+/// always runs on the calling thread.
+pub fn linear_rgb_to_xyb_with_config(
+    image: &LinearRgbImage,
+    config: Ssimulacra2Config,
+) -> XybImage {
+    let (width, height) = (image.width(), image.height());
+    let data = linear_rgb_to_xyb_data(
+        image.data.clone(),
+        width,
+        height,
+        config.backend_for(Stage::Xyb),
+        &config.parallelism,
+    );
+    XybImage::new(data, width, height)
+}
+
+/// Converts XYB back to linear RGB with the default backend (safe SIMD).
+pub fn xyb_to_linear_rgb(image: &XybImage) -> LinearRgbImage {
+    xyb_to_linear_rgb_with_config(image, Ssimulacra2Config::default())
+}
+
+/// Converts XYB back to linear RGB with the backend selected in `config`.
+///
+/// The unsafe SIMD backend has no dedicated inverse and uses the safe SIMD path.
+pub fn xyb_to_linear_rgb_with_config(
+    image: &XybImage,
+    config: Ssimulacra2Config,
+) -> LinearRgbImage {
+    let (width, height) = (image.width(), image.height());
+    let data = match config.backend_for(Stage::Xyb) {
+        SimdImpl::Scalar => xyb_to_linear_rgb_scalar(image.data.clone(), width, height),
+        #[cfg(feature = "simd")]
+        _ => {
+            let mut data = image.data.clone();
+            xyb_simd::xyb_to_linear_rgb_simd(&mut data);
+            data
+        }
+        // Without the `simd` feature, `Simd` runs the scalar conversion
+        #[cfg(not(feature = "simd"))]
+        SimdImpl::Simd => xyb_to_linear_rgb_scalar(image.data.clone(), width, height),
+    };
+    LinearRgbImage::new(data, width, height)
+}
+
+/// Converts `width` x `height` interleaved linear RGB pixels into the
+/// positive XYB planes the metric compares, resizing `out` to fit, with the
+/// backend and parallelism selected in `config`.
+///
+/// The planes match converting to XYB, applying `make_positive_xyb` and
+/// splitting into planes as separate passes, but the SIMD backends fuse all
+/// three into one pass over the image and need no interleaved XYB buffer.
+pub(crate) fn linear_rgb_to_positive_planar_into(
+    input: &[[f32; 3]],
+    width: usize,
+    height: usize,
+    out: &mut PlanarImage,
+    config: &Ssimulacra2Config,
+) {
+    debug_assert_eq!(input.len(), width * height);
+    out.resize(width, height);
+    match config.backend_for(Stage::Xyb) {
+        SimdImpl::Scalar => positive_planar_scalar(input, width, height, out),
+        #[cfg(feature = "simd")]
+        SimdImpl::Simd => {
+            positive_planar_fused(input, out, config, xyb_simd::linear_rgb_to_xyb_simd)
+        }
+        #[cfg(feature = "unsafe-simd")]
+        SimdImpl::UnsafeSimd => positive_planar_fused(
+            input,
+            out,
+            config,
+            xyb_unsafe_simd::linear_rgb_to_xyb_unsafe,
+        ),
+        // Without the `simd` feature, `Simd` runs the scalar conversion
+        #[cfg(not(feature = "simd"))]
+        SimdImpl::Simd => positive_planar_scalar(input, width, height, out),
+    }
+}
+
+#[cfg(feature = "simd")]
+fn positive_planar_fused(
+    input: &[[f32; 3]],
+    out: &mut PlanarImage,
+    config: &Ssimulacra2Config,
+    convert: fn(&mut [[f32; 3]]),
+) {
+    let [out0, out1, out2] = &mut out.planes;
+    for_each_planar_chunk(
+        &config.parallelism,
+        input,
+        [out0, out1, out2],
+        |input, planes| simd_ops::linear_rgb_to_positive_planar_simd(input, planes, convert),
+    );
+}
+
+fn positive_planar_scalar(input: &[[f32; 3]], width: usize, height: usize, out: &mut PlanarImage) {
+    let xyb = linear_rgb_to_xyb_scalar(input.to_vec(), width, height);
+    let [out0, out1, out2] = &mut out.planes;
+    for (((pix, x), y), b) in xyb.iter().zip(out0).zip(out1).zip(out2) {
+        *x = pix[0].mul_add(14.0, 0.42);
+        *y = pix[1] + 0.01;
+        *b = (pix[2] - pix[1]) + 0.55;
+    }
+}
+
+/// Convert interleaved linear RGB to XYB using the specified implementation
+pub(crate) fn linear_rgb_to_xyb_data(
+    #[cfg_attr(not(feature = "simd"), allow(unused_mut))] mut data: Vec<[f32; 3]>,
+    width: usize,
+    height: usize,
+    impl_type: SimdImpl,
+    parallelism: &Parallelism,
+) -> Vec<[f32; 3]> {
+    #[cfg(not(feature = "simd"))]
+    let _ = parallelism;
+    match impl_type {
+        SimdImpl::Scalar => linear_rgb_to_xyb_scalar(data, width, height),
+        #[cfg(feature = "simd")]
+        SimdImpl::Simd => {
+            for_each_chunk(parallelism, &mut data, xyb_simd::linear_rgb_to_xyb_simd);
+            data
+        }
+        #[cfg(feature = "unsafe-simd")]
+        SimdImpl::UnsafeSimd => {
+            for_each_chunk(
+                parallelism,
+                &mut data,
+                xyb_unsafe_simd::linear_rgb_to_xyb_unsafe,
+            );
+            data
+        }
+        // Without the `simd` feature, `Simd` runs the scalar conversion
+        #[cfg(not(feature = "simd"))]
+        SimdImpl::Simd => linear_rgb_to_xyb_scalar(data, width, height),
+    }
+}
+
+/// The scalar backend's conversion: yuvxyb's, or the crate's port of it in
+/// builds without the `yuvxyb` feature
+#[cfg(feature = "yuvxyb")]
+fn linear_rgb_to_xyb_scalar(data: Vec<[f32; 3]>, width: usize, height: usize) -> Vec<[f32; 3]> {
+    let linear_rgb =
+        LinearRgb::new(data, width, height).expect("linear RGB construction should not fail");
+    Xyb::from(linear_rgb).into_data()
+}
+
+#[cfg(not(feature = "yuvxyb"))]
+fn linear_rgb_to_xyb_scalar(
+    mut data: Vec<[f32; 3]>,
+    _width: usize,
+    _height: usize,
+) -> Vec<[f32; 3]> {
+    crate::xyb_scalar::linear_rgb_to_xyb_scalar(&mut data);
+    data
+}
+
+/// Inverse of [`linear_rgb_to_xyb_scalar`]
+#[cfg(feature = "yuvxyb")]
+fn xyb_to_linear_rgb_scalar(data: Vec<[f32; 3]>, width: usize, height: usize) -> Vec<[f32; 3]> {
+    let xyb = Xyb::new(data, width, height).expect("XYB construction should not fail");
+    LinearRgb::from(xyb).into_data()
+}
+
+#[cfg(not(feature = "yuvxyb"))]
+fn xyb_to_linear_rgb_scalar(
+    mut data: Vec<[f32; 3]>,
+    _width: usize,
+    _height: usize,
+) -> Vec<[f32; 3]> {
+    crate::xyb_scalar::xyb_to_linear_rgb_scalar(&mut data);
+    data
+}
+
+// =============================================================================
+// yuvxyb compatibility
+// =============================================================================
+
+#[cfg(feature = "yuvxyb")]
+impl From<Xyb> for XybImage {
+    fn from(xyb: Xyb) -> Self {
+        let (width, height) = (xyb.width(), xyb.height());
+        XybImage::new(xyb.into_data(), width, height)
+    }
+}
+
+#[cfg(feature = "yuvxyb")]
+impl From<XybImage> for Xyb {
+    fn from(img: XybImage) -> Self {
+        Xyb::new(img.data, img.width, img.height).expect("XybImage dimensions are always valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::backend_configs;
+
+    /// 37 pixels, so the SIMD paths also exercise their remainder handling
+    fn test_image() -> LinearRgbImage {
+        let data = (0..37)
+            .map(|i| {
+                let t = i as f32 / 36.0;
+                [t, (t * 7.0).fract(), 1.0 - t]
+            })
+            .collect();
+        LinearRgbImage::new(data, 37, 1)
+    }
+
+    fn max_abs_diff(a: &[[f32; 3]], b: &[[f32; 3]]) -> f32 {
+        a.iter()
+            .flatten()
+            .zip(b.iter().flatten())
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let image = test_image();
+        for config in backend_configs() {
+            let xyb = linear_rgb_to_xyb_with_config(&image, config.clone());
+            let back = xyb_to_linear_rgb_with_config(&xyb, config.clone());
+            let diff = max_abs_diff(image.data(), back.data());
+            assert!(
+                diff < 1e-4,
+                "{} round trip error {}",
+                config.impl_type.name(),
+                diff
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let image = test_image();
+        let scalar = linear_rgb_to_xyb_with_config(&image, Ssimulacra2Config::scalar());
+        for config in backend_configs() {
+            let xyb = linear_rgb_to_xyb_with_config(&image, config.clone());
+            let diff = max_abs_diff(scalar.data(), xyb.data());
+            assert!(
+                diff < 1e-5,
+                "{} differs by {}",
+                config.impl_type.name(),
+                diff
+            );
+
+            let back = xyb_to_linear_rgb_with_config(&scalar, config.clone());
+            let scalar_back = xyb_to_linear_rgb_with_config(&scalar, Ssimulacra2Config::scalar());
+            let diff = max_abs_diff(scalar_back.data(), back.data());
+            assert!(
+                diff < 1e-5,
+                "{} inverse differs by {}",
+                config.impl_type.name(),
+                diff
+            );
+        }
+    }
+
+    #[test]
+    fn test_subnormal_inputs_give_normal_planes() {
+        // Subnormals slow float arithmetic on many CPUs. The opsin bias and
+        // the positive offsets keep every plane the blur sees far from zero,
+        // however dark the input.
+        let values = [0.0, -0.0, f32::MIN_POSITIVE / 8.0, 1e-42, 1e-30, 1e-7];
+        let data = (0..37)
+            .map(|i| {
+                let v = values[i % values.len()];
+                [v, values[(i + 1) % values.len()], v]
+            })
+            .collect();
+        let image = LinearRgbImage::new(data, 37, 1);
+        for config in backend_configs() {
+            let mut planar = PlanarImage::zeros(0, 0);
+            linear_rgb_to_positive_planar_into(image.data(), 37, 1, &mut planar, &config);
+            for plane in planar.planes() {
+                assert!(
+                    plane.iter().all(|v| v.is_normal() && *v > 0.001),
+                    "{}: {:?}",
+                    config.impl_type.name(),
+                    plane
+                );
+            }
+        }
+    }
+
+    /// Several threading chunks plus a remainder that isn't a multiple of
+    /// the SIMD width
+    fn large_image() -> LinearRgbImage {
+        let (width, height) = (301, 131);
+        let data = (0..width * height)
+            .map(|i| {
+                let t = (i % 997) as f32 / 996.0;
+                [t, (t * 7.0).fract(), 1.0 - t]
+            })
+            .collect();
+        LinearRgbImage::new(data, width, height)
+    }
+
+    #[test]
+    fn test_threaded_conversion_is_identical() {
+        let image = large_image();
+        for config in backend_configs() {
+            let sequential = linear_rgb_to_xyb_with_config(
+                &image,
+                config.clone().with_parallelism(Parallelism::Off),
+            );
+            let threaded = linear_rgb_to_xyb_with_config(
+                &image,
+                config.clone().with_parallelism(Parallelism::Threads(3)),
+            );
+            assert_eq!(
+                sequential.data(),
+                threaded.data(),
+                "{}",
+                config.impl_type.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_fused_planar_matches_separate_passes() {
+        let image = large_image();
+        let (width, height) = (image.width(), image.height());
+        for config in backend_configs() {
+            let mut xyb = linear_rgb_to_xyb_with_config(&image, config.clone());
+            crate::make_positive_xyb(&mut xyb, config.backend_for(Stage::Xyb));
+            let expected = PlanarImage::from(&xyb);
+            for parallelism in [Parallelism::Off, Parallelism::Threads(3)] {
+                let mut fused = PlanarImage::zeros(0, 0);
+                linear_rgb_to_positive_planar_into(
+                    image.data(),
+                    width,
+                    height,
+                    &mut fused,
+                    &config.clone().with_parallelism(parallelism),
+                );
+                assert_eq!(
+                    fused.planes(),
+                    expected.planes(),
+                    "{}",
+                    config.impl_type.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_gray_has_no_chroma() {
+        let image = LinearRgbImage::new(vec![[0.5, 0.5, 0.5]; 64], 8, 8);
+        let xyb = linear_rgb_to_xyb(&image);
+        assert_eq!((xyb.width(), xyb.height()), (8, 8));
+        for &[x, y, _] in xyb.data() {
+            assert!(x.abs() < 1e-6);
+            assert!(y > 0.0);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Scalar RGB ↔ XYB conversions, ported from yuvxyb.
+//!
+//! The scalar backend converts through `yuvxyb` when the `yuvxyb` feature is
+//! enabled. Without it (e.g. in the `lite` build) these functions take its
+//! place: they follow the yuvxyb code step by step, including its `mul_add`
+//! ordering and its cube root, so the scalar backend scores the same either
+//! way.
+//!
+//! Original code from: https://github.com/rust-av/yuvxyb
+//! License: BSD-2-Clause
+
+#![cfg_attr(feature = "yuvxyb", allow(dead_code))]
+
+// XYB color space constants from jpegli
+const K_M02: f32 = 0.078f32;
+const K_M00: f32 = 0.30f32;
+const K_M01: f32 = 1.0f32 - K_M02 - K_M00;
+const K_M12: f32 = 0.078f32;
+const K_M10: f32 = 0.23f32;
+const K_M11: f32 = 1.0f32 - K_M12 - K_M10;
+const K_M20: f32 = 0.243_422_69_f32;
+const K_M21: f32 = 0.204_767_45_f32;
+const K_M22: f32 = 1.0f32 - K_M20 - K_M21;
+const K_B0: f32 = 0.003_793_073_4_f32;
+const K_B1: f32 = K_B0;
+const K_B2: f32 = K_B0;
+
+const OPSIN_ABSORBANCE_MATRIX: [f32; 9] = [
+    K_M00, K_M01, K_M02, K_M10, K_M11, K_M12, K_M20, K_M21, K_M22,
+];
+
+const OPSIN_ABSORBANCE_BIAS: [f32; 3] = [K_B0, K_B1, K_B2];
+
+const INVERSE_OPSIN_ABSORBANCE_MATRIX: [f32; 9] = [
+    11.031_567_f32,
+    -9.866_944_f32,
+    -0.164_622_99_f32,
+    -3.254_147_3_f32,
+    4.418_770_3_f32,
+    -0.164_622_99_f32,
+    -3.658_851_4_f32,
+    2.712_923_f32,
+    1.945_928_2_f32,
+];
+
+const NEG_OPSIN_ABSORBANCE_BIAS: [f32; 3] = [-K_B0, -K_B1, -K_B2];
+
+/// Cube root as in musl and yuvxyb: a bit-level estimate refined by two
+/// Newton steps in f64
+fn cbrtf(x: f32) -> f32 {
+    // B1 = (127-127.0/3-0.03306235651)*2**23
+    const B1: u32 = 709_958_130;
+    // B2 = (127-127.0/3-24/3-0.03306235651)*2**23
+    const B2: u32 = 642_849_266;
+
+    let mut ui = x.to_bits();
+    let mut hx = ui & 0x7FFF_FFFF;
+    if hx >= 0x7F80_0000 {
+        // NaN or infinity
+        return x + x;
+    }
+    if hx < 0x0080_0000 {
+        // Zero or subnormal
+        if hx == 0 {
+            return x;
+        }
+        // Scale by 2^24 into the normal range; B2 divides it back out
+        ui = (x * 16_777_216.0).to_bits();
+        hx = (ui & 0x7FFF_FFFF) / 3 + B2;
+    } else {
+        hx = hx / 3 + B1;
+    }
+    ui &= 0x8000_0000;
+    ui |= hx;
+
+    let x = f64::from(x);
+    let mut t = f64::from(f32::from_bits(ui));
+    let mut r = t * t * t;
+    t = t * (x + x + r) / (x + r + r);
+    r = t * t * t;
+    t = t * (x + x + r) / (x + r + r);
+    t as f32
+}
+
+#[inline]
+fn opsin_absorbance(rgb: &[f32; 3]) -> [f32; 3] {
+    let m = &OPSIN_ABSORBANCE_MATRIX;
+    std::array::from_fn(|i| {
+        m[i * 3].mul_add(
+            rgb[0],
+            m[i * 3 + 1].mul_add(
+                rgb[1],
+                m[i * 3 + 2].mul_add(rgb[2], OPSIN_ABSORBANCE_BIAS[i]),
+            ),
+        )
+    })
+}
+
+/// Converts linear RGB to XYB, in place.
+///
+/// Input/output: [[R, G, B]] → [[X, Y, B]]
+pub(crate) fn linear_rgb_to_xyb_scalar(input: &mut [[f32; 3]]) {
+    let absorbance_bias = OPSIN_ABSORBANCE_BIAS.map(|b| -cbrtf(b));
+    for pix in input {
+        let mut mixed = opsin_absorbance(pix);
+        for (m, absorb) in mixed.iter_mut().zip(absorbance_bias) {
+            *m = cbrtf(m.max(0.0)) + absorb;
+        }
+        *pix = [
+            0.5 * (mixed[0] - mixed[1]),
+            0.5 * (mixed[0] + mixed[1]),
+            mixed[2],
+        ];
+    }
+}
+
+/// Converts XYB back to linear RGB, in place.
+///
+/// Input/output: [[X, Y, B]] → [[R, G, B]]
+pub(crate) fn xyb_to_linear_rgb_scalar(input: &mut [[f32; 3]]) {
+    let biases_cbrt = NEG_OPSIN_ABSORBANCE_BIAS.map(cbrtf);
+    let m = &INVERSE_OPSIN_ABSORBANCE_MATRIX;
+    for pix in input {
+        let mut gamma_rgb = [pix[1] + pix[0], pix[1] - pix[0], pix[2]];
+        for ((rgb, bias_cbrt), neg_bias) in gamma_rgb
+            .iter_mut()
+            .zip(biases_cbrt)
+            .zip(NEG_OPSIN_ABSORBANCE_BIAS)
+        {
+            *rgb -= bias_cbrt;
+            let tmp = *rgb * *rgb;
+            *rgb = tmp.mul_add(*rgb, neg_bias);
+        }
+        *pix = std::array::from_fn(|i| {
+            m[i * 3 + 2].mul_add(
+                gamma_rgb[2],
+                m[i * 3 + 1].mul_add(gamma_rgb[1], m[i * 3] * gamma_rgb[0]),
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixels() -> Vec<[f32; 3]> {
+        (0..1000u32)
+            .map(|i| {
+                let v = |k: u32| ((i * 7 + k * 13) % 101) as f32 / 100.0;
+                [v(0), v(1), v(2)]
+            })
+            .chain([[0.0; 3], [1.0; 3]])
+            .collect()
+    }
+
+    fn max_abs_diff(a: &[[f32; 3]], b: &[[f32; 3]]) -> f32 {
+        a.iter()
+            .flatten()
+            .zip(b.iter().flatten())
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_cbrtf_matches_std() {
+        // Zero, a subnormal, the opsin bias and values across the range
+        let values = [0.0f32, 1e-40, 1e-8, K_B0, 0.5, 1.0, 8.0, 1e30, -27.0];
+        for x in values {
+            let (ours, expected) = (cbrtf(x), x.cbrt());
+            assert!(
+                (ours - expected).abs() <= expected.abs() * 2.0 * f32::EPSILON,
+                "{}: {} vs {}",
+                x,
+                ours,
+                expected
+            );
+        }
+        assert!(cbrtf(f32::NAN).is_nan());
+        assert_eq!(cbrtf(f32::INFINITY), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = pixels();
+        let mut data = original.clone();
+        linear_rgb_to_xyb_scalar(&mut data);
+        xyb_to_linear_rgb_scalar(&mut data);
+        let diff = max_abs_diff(&original, &data);
+        assert!(diff < 1e-4, "round trip error {}", diff);
+    }
+
+    #[cfg(feature = "yuvxyb")]
+    #[test]
+    fn test_matches_yuvxyb() {
+        use yuvxyb::{LinearRgb, Xyb};
+
+        let original = pixels();
+        let len = original.len();
+        let mut data = original.clone();
+        linear_rgb_to_xyb_scalar(&mut data);
+        let expected = Xyb::from(LinearRgb::new(original, len, 1).unwrap());
+        let diff = max_abs_diff(&data, expected.data());
+        assert!(diff <= 1e-6, "forward differs by {}", diff);
+
+        let mut data = expected.data().to_vec();
+        xyb_to_linear_rgb_scalar(&mut data);
+        let inverse = LinearRgb::from(expected).into_data();
+        let diff = max_abs_diff(&data, &inverse);
+        assert!(diff <= 1e-6, "inverse differs by {}", diff);
+    }
+}
@@ -29,6 +29,20 @@ const OPSIN_ABSORBANCE_MATRIX: [f32; 9] = [
 
 const OPSIN_ABSORBANCE_BIAS: [f32; 3] = [K_B0, K_B1, K_B2];
 
+const INVERSE_OPSIN_ABSORBANCE_MATRIX: [f32; 9] = [
+    11.031_567_f32,
+    -9.866_944_f32,
+    -0.164_622_99_f32,
+    -3.254_147_3_f32,
+    4.418_770_3_f32,
+    -0.164_622_99_f32,
+    -3.658_851_4_f32,
+    2.712_923_f32,
+    1.945_928_2_f32,
+];
+
+const NEG_OPSIN_ABSORBANCE_BIAS: [f32; 3] = [-K_B0, -K_B1, -K_B2];
+
 // SIMD cube root implementation - initial approximation via bit manipulation
 #[inline]
 fn initial_approx(x: f32) -> f32 {
@@ -460,3 +474,94 @@ fn mixed_to_xyb_scalar(mixed: &[f32; 3]) -> [f32; 3] {
         mixed[2],
     ]
 }
+
+/// Converts XYB back to linear RGB using f32x8 SIMD, in place.
+///
+/// The inverse only needs a cube (no cube root), so this is a straight port of
+/// the yuvxyb scalar code with the same `mul_add` ordering.
+///
+/// Input/output: [[X, Y, B]] → [[R, G, B]]
+#[inline]
+pub fn xyb_to_linear_rgb_simd(input: &mut [[f32; 3]]) {
+    let biases_cbrt: [f32; 3] = [
+        cbrtf_fast(NEG_OPSIN_ABSORBANCE_BIAS[0]),
+        cbrtf_fast(NEG_OPSIN_ABSORBANCE_BIAS[1]),
+        cbrtf_fast(NEG_OPSIN_ABSORBANCE_BIAS[2]),
+    ];
+
+    let chunks_8 = input.len() / 8;
+
+    for chunk_idx in 0..chunks_8 {
+        let base = chunk_idx * 8;
+
+        // Load 8 pixels and transpose to SoA
+        let mut x_arr = [0.0f32; 8];
+        let mut y_arr = [0.0f32; 8];
+        let mut b_arr = [0.0f32; 8];
+
+        for i in 0..8 {
+            let p = input[base + i];
+            x_arr[i] = p[0];
+            y_arr[i] = p[1];
+            b_arr[i] = p[2];
+        }
+
+        let x = f32x8::new(x_arr);
+        let y = f32x8::new(y_arr);
+        let b = f32x8::new(b_arr);
+
+        // XYB -> gamma RGB, then undo the cube root
+        let gamma = [
+            y + x - f32x8::splat(biases_cbrt[0]),
+            y - x - f32x8::splat(biases_cbrt[1]),
+            b - f32x8::splat(biases_cbrt[2]),
+        ];
+        let [g0, g1, g2] = [0, 1, 2].map(|c| {
+            (gamma[c] * gamma[c]).mul_add(gamma[c], f32x8::splat(NEG_OPSIN_ABSORBANCE_BIAS[c]))
+        });
+
+        // Matrix multiply: rgb = M^-1 * mixed
+        let m = INVERSE_OPSIN_ABSORBANCE_MATRIX.map(f32x8::splat);
+        let r = m[2].mul_add(g2, m[1].mul_add(g1, m[0] * g0));
+        let g = m[5].mul_add(g2, m[4].mul_add(g1, m[3] * g0));
+        let b_out = m[8].mul_add(g2, m[7].mul_add(g1, m[6] * g0));
+
+        // Transpose back to AoS and store
+        let r_arr: [f32; 8] = r.into();
+        let g_arr: [f32; 8] = g.into();
+        let b_arr: [f32; 8] = b_out.into();
+
+        for i in 0..8 {
+            input[base + i] = [r_arr[i], g_arr[i], b_arr[i]];
+        }
+    }
+
+    for pix in &mut input[chunks_8 * 8..] {
+        let mut gamma_rgb = [pix[1] + pix[0], pix[1] - pix[0], pix[2]];
+        for ((rgb, bias_cbrt), neg_bias) in gamma_rgb
+            .iter_mut()
+            .zip(biases_cbrt.iter())
+            .zip(NEG_OPSIN_ABSORBANCE_BIAS.iter())
+        {
+            *rgb -= *bias_cbrt;
+            let tmp = (*rgb) * (*rgb);
+            *rgb = tmp.mul_add(*rgb, *neg_bias);
+        }
+
+        let m = &INVERSE_OPSIN_ABSORBANCE_MATRIX;
+        *pix = [
+            m[2].mul_add(
+                gamma_rgb[2],
+                m[1].mul_add(gamma_rgb[1], m[0] * gamma_rgb[0]),
+            ),
+            m[5].mul_add(
+                gamma_rgb[2],
+                m[4].mul_add(gamma_rgb[1], m[3] * gamma_rgb[0]),
+            ),
+            m[8].mul_add(
+                gamma_rgb[2],
+                m[7].mul_add(gamma_rgb[1], m[6] * gamma_rgb[0]),
+            ),
+        ];
+    }
+}
@@ -52,13 +52,19 @@ pub fn linear_rgb_to_xyb_unsafe(input: &mut [[f32; 3]]) {
             return;
         }
     }
-    // Fallback to scalar
-    linear_rgb_to_xyb_scalar(input);
+    // No NEON version: the portable kernels already compile to NEON.
+    // Elsewhere, fall back to scalar
+    if cfg!(target_arch = "aarch64") {
+        crate::xyb_simd::linear_rgb_to_xyb_simd(input);
+    } else {
+        linear_rgb_to_xyb_scalar(input);
+    }
 }
 
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx2", enable = "fma")]
 unsafe fn linear_rgb_to_xyb_avx2(input: &mut [[f32; 3]]) {
+    debug_check!(is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma"));
     let absorbance_bias = -cbrtf_fast(K_B0);
 
     // Process 8 pixels at a time with AVX2
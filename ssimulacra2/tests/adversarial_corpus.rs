@@ -0,0 +1,305 @@
+//! Adversarial inputs that stress numerical edge cases of the metric.
+//!
+//! Each case is generated in code and run through every backend. The
+//! assertions pin down expected behavior (finite, in range, ordered, backends
+//! agreeing) rather than exact scores, so SIMD kernel optimizations can't
+//! silently regress extreme contrast, tiny signals, denormals or minimum sizes.
+
+use fast_ssim2::accuracy::backends_agree;
+use fast_ssim2::{
+    compute_ssimulacra2_with_config, LinearRgbImage, Ssimulacra2Config, Ssimulacra2Error,
+};
+
+mod common;
+
+use common::backend_configs;
+
+fn image_from_fn(
+    width: usize,
+    height: usize,
+    f: impl Fn(usize, usize) -> [f32; 3],
+) -> LinearRgbImage {
+    let data = (0..width * height)
+        .map(|i| f(i % width, i / width))
+        .collect();
+    LinearRgbImage::new(data, width, height)
+}
+
+fn flat(width: usize, height: usize, value: f32) -> LinearRgbImage {
+    image_from_fn(width, height, |_, _| [value; 3])
+}
+
+/// Scores the pair with every backend, checking that all results are finite,
+/// at most 100 and agree with each other. Returns the scalar score.
+fn score_all_backends(name: &str, source: &LinearRgbImage, distorted: &LinearRgbImage) -> f64 {
+    let scores: Vec<(Ssimulacra2Config, f64)> = backend_configs()
+        .into_iter()
+        .map(|config| {
+            let score =
+                compute_ssimulacra2_with_config(source.clone(), distorted.clone(), config.clone())
+                    .unwrap_or_else(|e| {
+                        panic!("{}: {} failed: {}", name, config.impl_type.name(), e)
+                    });
+            (config, score)
+        })
+        .collect();
+
+    let reference = scores[0].1;
+    for &(ref config, score) in &scores {
+        assert!(
+            score.is_finite(),
+            "{}: {} produced non-finite score {}",
+            name,
+            config.impl_type.name(),
+            score
+        );
+        assert!(
+            score <= 100.0,
+            "{}: {} score {} exceeds 100",
+            name,
+            config.impl_type.name(),
+            score
+        );
+        assert!(
+            backends_agree(score, reference),
+            "{}: {} score {} differs from scalar {}",
+            name,
+            config.impl_type.name(),
+            score,
+            reference
+        );
+    }
+    reference
+}
+
+// ============================================================================
+// Extreme contrast edges
+// ============================================================================
+
+#[test]
+fn test_hard_edge_shifted_by_one_pixel() {
+    let source = image_from_fn(64, 64, |x, _| if x < 32 { [0.0; 3] } else { [1.0; 3] });
+    let distorted = image_from_fn(64, 64, |x, _| if x < 33 { [0.0; 3] } else { [1.0; 3] });
+    let score = score_all_backends("hard edge", &source, &distorted);
+    assert!(
+        score < 100.0,
+        "shifted edge must be detected, got {}",
+        score
+    );
+}
+
+#[test]
+fn test_pixel_checkerboard_inverted() {
+    // A 1-pixel pattern is below the blur scale: both phases blur to the same
+    // gray, so the metric sees only a mild difference. Pinned so a kernel change
+    // that starts aliasing Nyquist content shows up.
+    let source = image_from_fn(32, 32, |x, y| [((x + y) % 2) as f32; 3]);
+    let distorted = image_from_fn(32, 32, |x, y| [((x + y + 1) % 2) as f32; 3]);
+    let score = score_all_backends("inverted checkerboard", &source, &distorted);
+    assert!(
+        (90.0..100.0).contains(&score),
+        "Nyquist inversion should score high but not 100, got {}",
+        score
+    );
+}
+
+#[test]
+fn test_black_vs_white() {
+    let score = score_all_backends("black vs white", &flat(16, 16, 0.0), &flat(16, 16, 1.0));
+    assert!(
+        score < 70.0,
+        "black vs white must score badly, got {}",
+        score
+    );
+}
+
+#[test]
+fn test_out_of_gamut_values() {
+    // Linear values outside [0, 1] can come from HDR or wide-gamut sources
+    let source = image_from_fn(32, 32, |x, y| [x as f32 / 8.0, -0.1, (y as f32) * 0.5]);
+    let distorted = image_from_fn(32, 32, |x, y| {
+        [x as f32 / 8.0 + 0.01, -0.1, (y as f32) * 0.5]
+    });
+    score_all_backends("out of gamut", &source, &distorted);
+}
+
+// ============================================================================
+// Tiny signals
+// ============================================================================
+
+#[test]
+fn test_single_hot_pixel() {
+    let source = flat(64, 64, 0.0);
+    let mut distorted = flat(64, 64, 0.0);
+    distorted.data_mut()[32 * 64 + 32] = [1.0; 3];
+    // On pure black the local variances are tiny, so a single pixel drives the
+    // score far down (even below zero). That is the metric's behavior, not a bug.
+    let score = score_all_backends("hot pixel", &source, &distorted);
+    assert!(
+        score < 50.0,
+        "hot pixel on black must be severe, got {}",
+        score
+    );
+
+    // The same pixel on mid gray is a much milder distortion
+    let gray = flat(64, 64, 0.5);
+    let mut gray_distorted = gray.clone();
+    gray_distorted.data_mut()[32 * 64 + 32] = [1.0; 3];
+    let gray_score = score_all_backends("hot pixel on gray", &gray, &gray_distorted);
+    assert!(
+        gray_score > score,
+        "gray background ({}) should mask better than black ({})",
+        gray_score,
+        score
+    );
+}
+
+#[test]
+fn test_hot_pixel_at_corner() {
+    // Corner pixels exercise the blur boundary handling and odd downscale blocks
+    let source = flat(33, 17, 0.5);
+    let mut distorted = flat(33, 17, 0.5);
+    let last = distorted.data().len() - 1;
+    distorted.data_mut()[last] = [1.0; 3];
+    let score = score_all_backends("corner hot pixel", &source, &distorted);
+    assert!(
+        score < 100.0,
+        "corner pixel must be detected, got {}",
+        score
+    );
+}
+
+#[test]
+fn test_denormal_inputs() {
+    let denormal = f32::MIN_POSITIVE / 4.0;
+    assert!(denormal.is_subnormal());
+
+    let score = score_all_backends("denormal", &flat(16, 16, 0.0), &flat(16, 16, denormal));
+    assert!(
+        score > 99.9,
+        "denormal noise must be imperceptible, got {}",
+        score
+    );
+}
+
+#[test]
+fn test_denormal_texture() {
+    let denormal = f32::MIN_POSITIVE / 2.0;
+    let source = image_from_fn(24, 24, |x, y| [((x ^ y) & 1) as f32 * denormal; 3]);
+    let score = score_all_backends("denormal texture", &source, &flat(24, 24, 0.0));
+    assert!(
+        score > 99.9,
+        "denormal texture must be imperceptible, got {}",
+        score
+    );
+}
+
+// ============================================================================
+// Minimum sizes
+// ============================================================================
+
+#[test]
+fn test_minimum_size_8x8() {
+    let source = image_from_fn(8, 8, |x, y| [x as f32 / 7.0, y as f32 / 7.0, 0.5]);
+    let distorted = image_from_fn(8, 8, |x, y| [y as f32 / 7.0, x as f32 / 7.0, 0.5]);
+    let score = score_all_backends("8x8", &source, &distorted);
+    assert!(score < 100.0);
+
+    assert_eq!(score_all_backends("8x8 identical", &source, &source), 100.0);
+}
+
+#[test]
+fn test_below_minimum_size_rejected() {
+    for (width, height) in [(7, 8), (8, 7), (1, 100), (100, 1)] {
+        for config in backend_configs() {
+            let result = compute_ssimulacra2_with_config(
+                flat(width, height, 0.5),
+                flat(width, height, 0.5),
+                config.clone(),
+            );
+            assert_eq!(
+                result,
+                Err(Ssimulacra2Error::InvalidImageSize),
+                "{}x{} with {}",
+                width,
+                height,
+                config.impl_type.name()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_thin_images() {
+    // One dimension at the minimum, the other long: every scale but the first is skipped
+    let source = image_from_fn(256, 8, |x, _| [(x % 7) as f32 / 6.0; 3]);
+    let distorted = image_from_fn(256, 8, |x, _| [(x % 7) as f32 / 6.5; 3]);
+    score_all_backends("256x8", &source, &distorted);
+    score_all_backends(
+        "8x256 (transposed)",
+        &transpose(&source),
+        &transpose(&distorted),
+    );
+}
+
+#[test]
+fn test_taller_than_wide_beyond_4096_rows() {
+    // Scratch buffers must follow the image height, not a fixed row budget
+    let source = image_from_fn(16, 4200, |x, y| [((x + y) % 5) as f32 / 4.0; 3]);
+    let distorted = image_from_fn(16, 4200, |x, y| [((x + y) % 5) as f32 / 4.5; 3]);
+    score_all_backends("16x4200", &source, &distorted);
+}
+
+fn transpose(image: &LinearRgbImage) -> LinearRgbImage {
+    let (width, height) = (image.width(), image.height());
+    image_from_fn(height, width, |x, y| image.data()[x * width + y])
+}
+
+// ============================================================================
+// Expected ordering
+// ============================================================================
+
+#[test]
+fn test_identical_adversarial_inputs_score_100() {
+    let cases = [
+        flat(8, 8, 0.0),
+        flat(16, 16, f32::MIN_POSITIVE / 4.0),
+        image_from_fn(32, 32, |x, y| [((x + y) % 2) as f32; 3]),
+        image_from_fn(33, 17, |x, _| [x as f32 * 10.0, -1.0, 0.0]),
+    ];
+    for (i, image) in cases.iter().enumerate() {
+        for config in backend_configs() {
+            let score =
+                compute_ssimulacra2_with_config(image.clone(), image.clone(), config.clone())
+                    .unwrap();
+            assert_eq!(
+                score,
+                100.0,
+                "case {} with {}: identical inputs must score exactly 100",
+                i,
+                config.impl_type.name()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_score_decreases_with_contrast_loss() {
+    let source = image_from_fn(48, 48, |x, y| [((x / 3 + y / 3) % 2) as f32; 3]);
+    let mut previous = 100.0;
+    for amount in [0.05f32, 0.2, 0.5, 0.9] {
+        let distorted = image_from_fn(48, 48, |x, y| {
+            let v = ((x / 3 + y / 3) % 2) as f32;
+            [v * (1.0 - amount) + 0.5 * amount; 3]
+        });
+        let score = score_all_backends("contrast loss", &source, &distorted);
+        assert!(
+            score < previous,
+            "contrast loss {} scored {} (previous {})",
+            amount,
+            score,
+            previous
+        );
+        previous = score;
+    }
+}
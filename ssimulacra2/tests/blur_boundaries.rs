@@ -0,0 +1,180 @@
+//! Boundary behavior of the blur backends.
+//!
+//! Whole-image score comparisons average away differences confined to the
+//! image borders, which is where the SIMD and transposing variants handle
+//! partial chunks and the zero padding differently from the scalar baseline.
+//! These tests compare the first and last `RADIUS` rows and columns of each
+//! backend against the scalar f64 IIR on impulse and step inputs.
+
+use fast_ssim2::{verify_kernel, Blur, SimdImpl};
+
+mod common;
+
+use common::backends;
+
+/// Support radius of the compiled recursive Gaussian
+fn radius() -> usize {
+    verify_kernel().radius
+}
+
+/// Largest allowed absolute difference from the scalar baseline. Inputs are
+/// in [0, 1], so this is well above f32 rounding and far below any
+/// boundary-handling error.
+const TOLERANCE: f32 = 1e-5;
+
+/// Sizes covering widths below, at and past the SIMD lane counts and the
+/// scalar vertical pass's 128-column chunks.
+const SIZES: [(usize, usize); 6] = [(8, 8), (13, 11), (16, 16), (37, 29), (64, 9), (131, 70)];
+
+fn blur_with(impl_type: SimdImpl, plane: &[f32], width: usize, height: usize) -> Vec<f32> {
+    let mut blur = Blur::with_simd_impl(width, height, impl_type);
+    let planes = [plane.to_vec(), plane.to_vec(), plane.to_vec()];
+    let [out, ..] = blur.blur(&planes);
+    out
+}
+
+fn is_boundary(x: usize, y: usize, width: usize, height: usize, r: usize) -> bool {
+    x < r || y < r || x + r >= width || y + r >= height
+}
+
+/// Compares the boundary rows and columns of every backend against scalar.
+fn assert_boundaries_match(name: &str, plane: &[f32], width: usize, height: usize) {
+    let r = radius();
+    let expected = blur_with(SimdImpl::Scalar, plane, width, height);
+    for impl_type in backends().into_iter().filter(|&b| b != SimdImpl::Scalar) {
+        let actual = blur_with(impl_type, plane, width, height);
+        for y in 0..height {
+            for x in 0..width {
+                if !is_boundary(x, y, width, height, r) {
+                    continue;
+                }
+                let (e, a) = (expected[y * width + x], actual[y * width + x]);
+                assert!(
+                    (e - a).abs() <= TOLERANCE,
+                    "{} {}x{}, {} at ({}, {}): scalar {} vs {}",
+                    name,
+                    width,
+                    height,
+                    impl_type.name(),
+                    x,
+                    y,
+                    e,
+                    a
+                );
+            }
+        }
+    }
+}
+
+fn impulse(width: usize, height: usize, x: usize, y: usize) -> Vec<f32> {
+    let mut plane = vec![0.0; width * height];
+    plane[y * width + x] = 1.0;
+    plane
+}
+
+#[test]
+fn test_impulses_at_corners_and_edges() {
+    for (width, height) in SIZES {
+        let (last_x, last_y) = (width - 1, height - 1);
+        let r = radius();
+        let positions = [
+            (0, 0),
+            (last_x, 0),
+            (0, last_y),
+            (last_x, last_y),
+            (width / 2, 0),
+            (width / 2, last_y),
+            (0, height / 2),
+            (last_x, height / 2),
+            // Just inside the boundary band, so the response straddles it
+            (r.min(last_x), r.min(last_y)),
+            (last_x.saturating_sub(r), last_y.saturating_sub(r)),
+        ];
+        for (x, y) in positions {
+            let plane = impulse(width, height, x, y);
+            assert_boundaries_match(&format!("impulse at ({}, {})", x, y), &plane, width, height);
+        }
+    }
+}
+
+#[test]
+fn test_steps_across_each_axis() {
+    for (width, height) in SIZES {
+        let horizontal: Vec<f32> = (0..width * height)
+            .map(|i| if i % width < width / 2 { 1.0 } else { 0.0 })
+            .collect();
+        assert_boundaries_match("horizontal step", &horizontal, width, height);
+
+        let vertical: Vec<f32> = (0..width * height)
+            .map(|i| if i / width < height / 2 { 0.0 } else { 1.0 })
+            .collect();
+        assert_boundaries_match("vertical step", &vertical, width, height);
+    }
+}
+
+#[test]
+fn test_constant_plane_falls_off_at_borders() {
+    // Samples outside the image are zero, so a constant plane darkens towards
+    // every border; all backends must reproduce the same falloff
+    for (width, height) in SIZES {
+        let plane = vec![1.0; width * height];
+        assert_boundaries_match("constant", &plane, width, height);
+
+        let blurred = blur_with(SimdImpl::Scalar, &plane, width, height);
+        assert!(blurred[0] < blurred[(height / 2) * width + width / 2]);
+    }
+}
+
+// The chunk cap only applies to the SIMD backend
+#[cfg(feature = "simd")]
+#[test]
+fn test_chunk_cap_does_not_change_output() {
+    use fast_ssim2::DEFAULT_MAX_CHUNK_COLUMNS;
+
+    // Every width from one lane up to past the widest chunk, so each mix of
+    // chunk sizes and scalar remainder columns is exercised
+    let height = 12;
+    for width in 4..=140 {
+        let plane: Vec<f32> = (0..width * height)
+            .map(|i| ((i * 7_919) % 101) as f32 / 101.0)
+            .collect();
+        let planes = [plane.clone(), plane.clone(), plane];
+        let mut blur = Blur::with_simd_impl(width, height, SimdImpl::Simd);
+        let expected = blur.blur(&planes);
+        for cap in [4, 8, 16, 32, 64] {
+            blur.set_max_chunk_columns(cap);
+            assert_eq!(blur.blur(&planes), expected, "width {} cap {}", width, cap);
+        }
+    }
+
+    let mut blur = Blur::new(16, 16);
+    assert_eq!(blur.max_chunk_columns(), DEFAULT_MAX_CHUNK_COLUMNS);
+    blur.set_max_chunk_columns(100);
+    assert_eq!(blur.max_chunk_columns(), 64);
+    blur.set_max_chunk_columns(0);
+    assert_eq!(blur.max_chunk_columns(), 4);
+}
+
+#[test]
+fn test_parallel_planes_match_sequential() {
+    use fast_ssim2::Parallelism;
+
+    let (width, height) = (67, 45);
+    let planes: [Vec<f32>; 3] = std::array::from_fn(|c| {
+        (0..width * height)
+            .map(|i| ((i * 7_919 + c * 31) % 101) as f32 / 101.0)
+            .collect()
+    });
+    for impl_type in backends() {
+        let mut blur = Blur::with_simd_impl(width, height, impl_type);
+        blur.set_parallelism(Parallelism::Off);
+        let expected = blur.blur(&planes);
+
+        // Reuses the sequential blur, so the per-plane scratch is created
+        // on first use
+        blur.set_parallelism(Parallelism::Threads(3));
+        assert_eq!(blur.blur(&planes), expected, "{}", impl_type.name());
+        blur.set_parallelism(Parallelism::Off);
+        assert_eq!(blur.blur(&planes), expected, "{}", impl_type.name());
+    }
+}
@@ -0,0 +1,173 @@
+//! Golden outputs of the blur, stored as compressed fixtures.
+//!
+//! `test_data/blur_golden/<w>x<h>.f32.gz` holds the scalar backend's blur of
+//! three deterministic planes (an impulse, uniform noise and a diagonal step)
+//! as gzipped little-endian f32. The scalar backend must reproduce them bit
+//! for bit; every SIMD backend must stay within [`MAX_ULPS`] of them. This
+//! checks the blur alone, without the rest of the pipeline averaging errors
+//! away.
+//!
+//! The fixtures depend on the compiled kernel constants. After an intended
+//! change to the scalar blur, regenerate them with
+//!
+//! ```text
+//! cargo test --test blur_golden -- --ignored regenerate
+//! ```
+
+use fast_ssim2::{Blur, SimdImpl};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+/// Fixture sizes: a SIMD-aligned square and odd sizes that leave chunk tails
+const SIZES: [(usize, usize); 2] = [(64, 64), (37, 29)];
+
+/// Largest allowed deviation of a SIMD backend from the golden output, about
+/// three times the largest seen on x86_64 (82)
+const MAX_ULPS: f32 = 256.0;
+
+/// Values smaller than this are measured in ULPs of this, so rounding noise
+/// in the near-zero tails of the impulse response doesn't read as billions
+/// of ULPs
+const ULP_FLOOR: f32 = 1.0 / 256.0;
+
+fn fixture_path(width: usize, height: usize) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("test_data")
+        .join("blur_golden")
+        .join(format!("{}x{}.f32.gz", width, height))
+}
+
+/// Impulse, xorshift noise in [0, 1) and a diagonal step
+fn input_planes(width: usize, height: usize) -> [Vec<f32>; 3] {
+    let center = (height / 2) * width + width / 2;
+    let impulse = (0..width * height)
+        .map(|i| if i == center { 1.0 } else { 0.0 })
+        .collect();
+
+    let mut state = 0x2545_f491u32;
+    let noise = (0..width * height)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 8) as f32 / (1u32 << 24) as f32
+        })
+        .collect();
+
+    let step = (0..width * height)
+        .map(|i| {
+            if (i % width) * 3 + (i / width) * 2 > width * 2 {
+                0.9
+            } else {
+                0.1
+            }
+        })
+        .collect();
+
+    [impulse, noise, step]
+}
+
+fn blur(impl_type: SimdImpl, width: usize, height: usize) -> [Vec<f32>; 3] {
+    Blur::with_simd_impl(width, height, impl_type).blur(&input_planes(width, height))
+}
+
+fn read_fixture(width: usize, height: usize) -> [Vec<f32>; 3] {
+    let path = fixture_path(width, height);
+    let file = std::fs::File::open(&path)
+        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path.display(), e));
+    let mut bytes = Vec::new();
+    GzDecoder::new(file)
+        .read_to_end(&mut bytes)
+        .unwrap_or_else(|e| panic!("Failed to decompress {}: {}", path.display(), e));
+    assert_eq!(bytes.len(), 3 * width * height * 4, "{}", path.display());
+
+    let mut values = bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    std::array::from_fn(|_| values.by_ref().take(width * height).collect())
+}
+
+/// Deviation of `actual` from `golden` in ULPs of `golden`
+fn ulps(actual: f32, golden: f32) -> f32 {
+    let magnitude = golden.abs().max(ULP_FLOOR);
+    let ulp = f32::from_bits(magnitude.to_bits() + 1) - magnitude;
+    (actual - golden).abs() / ulp
+}
+
+#[test]
+fn test_scalar_matches_golden_exactly() {
+    for (width, height) in SIZES {
+        let golden = read_fixture(width, height);
+        let actual = blur(SimdImpl::Scalar, width, height);
+        for (plane, (a, g)) in actual.iter().zip(&golden).enumerate() {
+            let mismatch = a
+                .iter()
+                .zip(g)
+                .position(|(a, g)| a.to_bits() != g.to_bits());
+            assert_eq!(
+                mismatch, None,
+                "{}x{} plane {}: scalar differs from the golden output",
+                width, height, plane
+            );
+        }
+    }
+}
+
+#[test]
+fn test_simd_backends_within_ulps_of_golden() {
+    #[allow(unused_mut)]
+    let mut backends = vec![SimdImpl::Simd];
+    #[cfg(feature = "unsafe-simd")]
+    backends.push(SimdImpl::UnsafeSimd);
+
+    for (width, height) in SIZES {
+        let golden = read_fixture(width, height);
+        for &impl_type in &backends {
+            let actual = blur(impl_type, width, height);
+            for (plane, (a, g)) in actual.iter().zip(&golden).enumerate() {
+                let (i, worst) = a
+                    .iter()
+                    .zip(g)
+                    .map(|(&a, &g)| ulps(a, g))
+                    .enumerate()
+                    .max_by(|x, y| x.1.total_cmp(&y.1))
+                    .unwrap();
+                assert!(
+                    worst <= MAX_ULPS,
+                    "{:?} {}x{} plane {}: {} ULPs at ({}, {}): golden {} vs {}",
+                    impl_type,
+                    width,
+                    height,
+                    plane,
+                    worst,
+                    i % width,
+                    i / width,
+                    g[i],
+                    a[i]
+                );
+            }
+        }
+    }
+}
+
+/// Rewrites the fixtures from the scalar backend
+#[test]
+#[ignore]
+fn regenerate() {
+    for (width, height) in SIZES {
+        let path = fixture_path(width, height);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut encoder =
+            GzEncoder::new(std::fs::File::create(&path).unwrap(), Compression::best());
+        for plane in blur(SimdImpl::Scalar, width, height) {
+            for v in plane {
+                encoder.write_all(&v.to_le_bytes()).unwrap();
+            }
+        }
+        encoder.finish().unwrap();
+        println!("wrote {}", path.display());
+    }
+}
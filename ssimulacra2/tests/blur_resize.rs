@@ -0,0 +1,97 @@
+//! Reusing one `Blur` across frames of different sizes.
+//!
+//! Callers scoring mixed-size content keep a single `Blur` and resize it per
+//! frame. Each backend keeps scratch buffers sized for some earlier frame, so
+//! these tests alternate large, small and larger-than-initial sizes and check
+//! every result against a freshly created `Blur`.
+
+#[cfg(feature = "integral-blur")]
+use fast_ssim2::BlurKernel;
+use fast_ssim2::{Blur, SimdImpl};
+
+mod common;
+
+use common::backends;
+
+/// Large, small, back to large, then larger than the initial size
+const SIZES: [(usize, usize); 5] = [(64, 48), (16, 12), (64, 48), (200, 150), (9, 8)];
+
+fn planes(width: usize, height: usize) -> [Vec<f32>; 3] {
+    let plane = |seed: usize| {
+        (0..width * height)
+            .map(|i| ((i * 31 + seed * 17) % 101) as f32 / 100.0)
+            .collect::<Vec<_>>()
+    };
+    [plane(0), plane(1), plane(2)]
+}
+
+fn fresh(impl_type: SimdImpl, width: usize, height: usize, img: &[Vec<f32>; 3]) -> [Vec<f32>; 3] {
+    Blur::with_simd_impl(width, height, impl_type).blur(img)
+}
+
+#[test]
+fn test_resize_alternating_sizes() {
+    for impl_type in backends() {
+        let (width, height) = SIZES[0];
+        let mut blur = Blur::with_simd_impl(width, height, impl_type);
+        for (width, height) in SIZES {
+            blur.resize(width, height);
+            let img = planes(width, height);
+            let out = blur.blur(&img);
+            assert_eq!(
+                out,
+                fresh(impl_type, width, height, &img),
+                "{} at {}x{}",
+                impl_type.name(),
+                width,
+                height
+            );
+        }
+    }
+}
+
+#[test]
+fn test_blur_into_after_resize() {
+    for impl_type in backends() {
+        let mut blur = Blur::with_simd_impl(8, 8, impl_type);
+        for (width, height) in SIZES {
+            blur.resize(width, height);
+            let img = planes(width, height);
+            let mut out = [
+                vec![0.0; width * height],
+                vec![0.0; width * height],
+                vec![0.0; width * height],
+            ];
+            blur.blur_into(&img, &mut out);
+            assert_eq!(out, fresh(impl_type, width, height, &img));
+        }
+    }
+}
+
+#[test]
+fn test_shrink_to_grows_again() {
+    // shrink_to predates resize and is kept for compatibility; growing
+    // through it must work too
+    for impl_type in backends() {
+        let mut blur = Blur::with_simd_impl(32, 32, impl_type);
+        blur.shrink_to(8, 8);
+        blur.blur(&planes(8, 8));
+        blur.shrink_to(48, 40);
+        let img = planes(48, 40);
+        assert_eq!(blur.blur(&img), fresh(impl_type, 48, 40, &img));
+    }
+}
+
+#[cfg(feature = "integral-blur")]
+#[test]
+fn test_summed_area_resize() {
+    let mut blur = Blur::new(64, 48);
+    blur.set_kernel(BlurKernel::SummedArea);
+    for (width, height) in SIZES {
+        blur.resize(width, height);
+        let img = planes(width, height);
+        let mut expected = Blur::new(width, height);
+        expected.set_kernel(BlurKernel::SummedArea);
+        assert_eq!(blur.blur(&img), expected.blur(&img));
+    }
+}
@@ -0,0 +1,166 @@
+//! Reusing one `Blur` across the scales of the metric.
+//!
+//! The pipeline creates a single `Blur` per comparison and resizes it at each
+//! of the 6 scales, blurring 15 planes per scale through the same scratch
+//! buffers (the horizontal pass output and the vertical pass's
+//! prev/prev2/out state). Any state leaking from one plane or scale into the
+//! next would shift scores slightly rather than fail loudly, so these tests
+//! require output bit-identical to a freshly constructed `Blur`.
+
+use fast_ssim2::{
+    compute_ssimulacra2_with_taps, Blur, LinearRgbImage, PipelineTap, PlanarImage, ScaleMoments,
+    SimdImpl, Ssimulacra2Config,
+};
+
+mod common;
+
+use common::backends;
+
+/// Planes with a different pattern per seed, so stale data from a previous
+/// plane would show up in the output
+fn planes(width: usize, height: usize, seed: usize) -> [Vec<f32>; 3] {
+    let plane = |c: usize| {
+        (0..width * height)
+            .map(|i| {
+                let v = (i * 2_654_435_761 + (seed * 3 + c) * 40_503) % 65_521;
+                v as f32 / 65_521.0
+            })
+            .collect::<Vec<_>>()
+    };
+    [plane(0), plane(1), plane(2)]
+}
+
+fn fresh_blur(
+    impl_type: SimdImpl,
+    width: usize,
+    height: usize,
+    img: &[Vec<f32>; 3],
+) -> [Vec<f32>; 3] {
+    Blur::with_simd_impl(width, height, impl_type).blur(img)
+}
+
+#[test]
+fn test_reuse_across_pyramid_matches_fresh() {
+    for impl_type in backends() {
+        let (mut width, mut height) = (256, 192);
+        let mut blur = Blur::with_simd_impl(width, height, impl_type);
+        for scale in 0..6 {
+            blur.resize(width, height);
+            // Several planes per scale, as the pipeline blurs five images
+            for seed in 0..5 {
+                let img = planes(width, height, scale * 5 + seed);
+                let mut out = [
+                    vec![f32::NAN; width * height],
+                    vec![f32::NAN; width * height],
+                    vec![f32::NAN; width * height],
+                ];
+                blur.blur_into(&img, &mut out);
+                assert_eq!(
+                    out,
+                    fresh_blur(impl_type, width, height, &img),
+                    "{} scale {} ({}x{}) plane set {}",
+                    impl_type.name(),
+                    scale,
+                    width,
+                    height,
+                    seed
+                );
+            }
+            width = width.div_ceil(2);
+            height = height.div_ceil(2);
+        }
+    }
+}
+
+/// Checks every blurred moment the pipeline produces against fresh blurs of
+/// the XYB planes it reported for the same scale.
+struct FreshBlurCheck {
+    impl_type: SimdImpl,
+    xyb: Option<(PlanarImage, PlanarImage)>,
+    scales: usize,
+}
+
+impl PipelineTap for FreshBlurCheck {
+    fn xyb(&mut self, _scale: usize, source: &PlanarImage, distorted: &PlanarImage) {
+        self.xyb = Some((source.clone(), distorted.clone()));
+    }
+
+    fn moments(&mut self, scale: usize, moments: &ScaleMoments<'_>) {
+        let (source, distorted) = self.xyb.take().expect("xyb tap runs before moments");
+        let (width, height) = (moments.width, moments.height);
+        let blur = |img: &[Vec<f32>; 3]| fresh_blur(self.impl_type, width, height, img);
+        let product = |a: &PlanarImage, b: &PlanarImage| {
+            let mut out = a.planes().clone();
+            for (out, b) in out.iter_mut().zip(b.planes()) {
+                for (o, b) in out.iter_mut().zip(b) {
+                    *o *= b;
+                }
+            }
+            out
+        };
+
+        let name = self.impl_type.name();
+        assert_eq!(
+            moments.mu1,
+            &blur(source.planes()),
+            "{} mu1 scale {}",
+            name,
+            scale
+        );
+        assert_eq!(
+            moments.mu2,
+            &blur(distorted.planes()),
+            "{} mu2 scale {}",
+            name,
+            scale
+        );
+        assert_eq!(
+            moments.s11,
+            &blur(&product(&source, &source)),
+            "{} s11 scale {}",
+            name,
+            scale
+        );
+        assert_eq!(
+            moments.s22,
+            &blur(&product(&distorted, &distorted)),
+            "{} s22 scale {}",
+            name,
+            scale
+        );
+        assert_eq!(
+            moments.s12,
+            &blur(&product(&source, &distorted)),
+            "{} s12 scale {}",
+            name,
+            scale
+        );
+        self.scales += 1;
+    }
+}
+
+#[test]
+fn test_pipeline_blur_matches_fresh_at_every_scale() {
+    let image = |seed: usize| {
+        let [r, g, b] = planes(256, 256, seed);
+        let data = (0..256 * 256).map(|i| [r[i], g[i], b[i]]).collect();
+        LinearRgbImage::new(data, 256, 256)
+    };
+    let (source, distorted) = (image(0), image(1));
+
+    for impl_type in backends() {
+        let mut check = FreshBlurCheck {
+            impl_type,
+            xyb: None,
+            scales: 0,
+        };
+        compute_ssimulacra2_with_taps(
+            &source,
+            &distorted,
+            Ssimulacra2Config::new(impl_type),
+            &mut check,
+        )
+        .unwrap();
+        assert_eq!(check.scales, 6, "{}", impl_type.name());
+    }
+}
@@ -0,0 +1,148 @@
+//! Correlation with subjective scores on the calibration corpus.
+//!
+//! Scores every pair in `test_data/calibration/scores.csv` and reports how
+//! well the metric tracks the mean opinion scores. Changes that affect scores
+//! (new blur backends, constant tweaks) should come with this report.
+//!
+//! The corpus is not committed. Fetch it first:
+//!   cargo run --release --example fetch_calibration_corpus
+//!   cargo test --release --test calibration_correlation -- --ignored --nocapture
+//!
+//! `scores.csv` has one `reference,distorted,mos` row per pair, with paths
+//! relative to `test_data/calibration` and an optional header row.
+
+use fast_ssim2::stats::Correlation;
+use fast_ssim2::Ssimulacra2Reference;
+use image::ImageReader;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use yuvxyb::Rgb;
+
+/// Minimum Spearman rank correlation with MOS. SSIMULACRA2 was tuned on
+/// CID22 and stays well above this on its validation set; override with
+/// `CALIBRATION_MIN_SROCC` for corpora with a different baseline.
+const DEFAULT_MIN_SROCC: f64 = 0.8;
+
+fn corpus_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("test_data")
+        .join("calibration")
+}
+
+fn load_image(path: &Path) -> Rgb {
+    let img = ImageReader::open(path)
+        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path.display(), e))
+        .decode()
+        .unwrap_or_else(|e| panic!("Failed to decode {}: {}", path.display(), e))
+        .to_rgb8();
+
+    let (width, height) = img.dimensions();
+    let data: Vec<[f32; 3]> = img
+        .pixels()
+        .map(|p| {
+            [
+                f32::from(p[0]) / 255.0,
+                f32::from(p[1]) / 255.0,
+                f32::from(p[2]) / 255.0,
+            ]
+        })
+        .collect();
+
+    Rgb::new(
+        data,
+        width as usize,
+        height as usize,
+        yuvxyb::TransferCharacteristic::SRGB,
+        yuvxyb::ColorPrimaries::BT709,
+    )
+    .expect("Failed to create Rgb")
+}
+
+/// One `reference,distorted,mos` row
+struct Pair {
+    reference: String,
+    distorted: String,
+    mos: f64,
+}
+
+fn parse_scores(contents: &str) -> Vec<Pair> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [reference, distorted, mos] = fields[..] else {
+                panic!("scores.csv line {}: expected 3 fields: {:?}", i + 1, line);
+            };
+            match mos.parse() {
+                Ok(mos) => Some(Pair {
+                    reference: reference.to_string(),
+                    distorted: distorted.to_string(),
+                    mos,
+                }),
+                // Header row
+                Err(_) if i == 0 => None,
+                Err(e) => panic!("scores.csv line {}: invalid MOS {:?}: {}", i + 1, mos, e),
+            }
+        })
+        .collect()
+}
+
+#[test]
+#[ignore = "requires the calibration corpus; run fetch_calibration_corpus first"]
+fn test_correlation_with_subjective_scores() {
+    let root = corpus_path();
+    let scores_path = root.join("scores.csv");
+    let contents = std::fs::read_to_string(&scores_path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read {}: {}\nAdd a subjective dataset to manifest.txt and run \
+             `cargo run --release --example fetch_calibration_corpus`",
+            scores_path.display(),
+            e
+        )
+    });
+    let pairs = parse_scores(&contents);
+    assert!(
+        pairs.len() >= 3,
+        "need at least 3 pairs, got {}",
+        pairs.len()
+    );
+
+    // Corpora reuse each reference for many distortions
+    let mut references = HashMap::new();
+    let mut metric = Vec::with_capacity(pairs.len());
+    for pair in &pairs {
+        let reference = references.entry(pair.reference.clone()).or_insert_with(|| {
+            Ssimulacra2Reference::new(load_image(&root.join(&pair.reference)))
+                .expect("Failed to precompute reference")
+        });
+        let score = reference
+            .compare(load_image(&root.join(&pair.distorted)))
+            .expect("SSIMULACRA2 computation failed");
+        metric.push(score);
+    }
+    let mos: Vec<f64> = pairs.iter().map(|p| p.mos).collect();
+
+    let correlation = Correlation::compute(&metric, &mos)
+        .expect("correlation is undefined for constant scores or ratings");
+    println!("{} references: {}", references.len(), correlation);
+
+    let min_srocc = std::env::var("CALIBRATION_MIN_SROCC")
+        .map(|v| v.parse().expect("CALIBRATION_MIN_SROCC must be a number"))
+        .unwrap_or(DEFAULT_MIN_SROCC);
+    assert!(
+        correlation.srocc() >= min_srocc,
+        "SROCC {:.4} below minimum {:.4}",
+        correlation.srocc(),
+        min_srocc
+    );
+}
+
+#[test]
+fn test_parse_scores() {
+    let pairs = parse_scores("reference,distorted,mos\na.png,b.png,71.5\n\na.png,c.png,40\n");
+    assert_eq!(pairs.len(), 2);
+    assert_eq!(pairs[1].distorted, "c.png");
+    assert_eq!(pairs[1].mos, 40.0);
+}
@@ -0,0 +1,65 @@
+//! Fixtures shared by the integration tests. Each test crate uses only some.
+#![allow(dead_code)]
+
+use fast_ssim2::{LinearRgbImage, SimdImpl, Ssimulacra2Config};
+
+/// Hashed noise; different seeds give unrelated images
+pub fn test_image(width: usize, height: usize, seed: u32) -> LinearRgbImage {
+    let data = (0..width * height)
+        .map(|i| {
+            let v = ((i as u32).wrapping_mul(2_654_435_761).wrapping_add(seed) >> 24) as f32;
+            [v / 255.0, 0.5 * v / 255.0, 1.0 - v / 255.0]
+        })
+        .collect();
+    LinearRgbImage::new(data, width, height)
+}
+
+/// Backends compiled into this build, scalar first
+pub fn backends() -> Vec<SimdImpl> {
+    #[allow(unused_mut)]
+    let mut backends = vec![SimdImpl::Scalar, SimdImpl::Simd];
+    #[cfg(feature = "unsafe-simd")]
+    backends.push(SimdImpl::UnsafeSimd);
+    backends
+}
+
+/// One config per backend compiled into this build, scalar first
+pub fn backend_configs() -> Vec<Ssimulacra2Config> {
+    backends().into_iter().map(Ssimulacra2Config::new).collect()
+}
+
+/// Loads an image of the JPEG quality corpus in `test_data/jpeg_quality`
+/// as 8-bit sRGB
+#[cfg(feature = "yuvxyb")]
+pub fn load_image(filename: &str) -> yuvxyb::Rgb {
+    let path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("test_data")
+        .join("jpeg_quality")
+        .join(filename);
+    let img = image::ImageReader::open(&path)
+        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path.display(), e))
+        .decode()
+        .unwrap_or_else(|e| panic!("Failed to decode {}: {}", path.display(), e))
+        .to_rgb8();
+
+    let (width, height) = img.dimensions();
+    let data: Vec<[f32; 3]> = img
+        .pixels()
+        .map(|p| {
+            [
+                f32::from(p[0]) / 255.0,
+                f32::from(p[1]) / 255.0,
+                f32::from(p[2]) / 255.0,
+            ]
+        })
+        .collect();
+
+    yuvxyb::Rgb::new(
+        data,
+        width as usize,
+        height as usize,
+        yuvxyb::TransferCharacteristic::SRGB,
+        yuvxyb::ColorPrimaries::BT709,
+    )
+    .expect("Failed to create Rgb")
+}
@@ -1,47 +1,17 @@
 //! Tests that verify all SIMD implementations produce matching scores.
 //!
-//! This ensures Scalar, Simd, and UnsafeSimd backends compute the same results.
+//! This ensures Scalar, Simd, and UnsafeSimd backends compute the same results,
+//! both as whole-pipeline presets and mixed per stage.
 
-use fast_ssim2::{compute_frame_ssimulacra2_with_config, Ssimulacra2Config};
-use image::ImageReader;
-use std::path::PathBuf;
+use fast_ssim2::accuracy::{self, BACKEND_RELATIVE_TOLERANCE};
+use fast_ssim2::{
+    compute_frame_ssimulacra2_with_config, FallbackChain, Ssimulacra2Config, StageBackends,
+};
 use yuvxyb::{ColorPrimaries, Rgb, TransferCharacteristic};
 
-fn test_data_path() -> PathBuf {
-    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .join("test_data")
-        .join("jpeg_quality")
-}
+mod common;
 
-fn load_image(filename: &str) -> Rgb {
-    let path = test_data_path().join(filename);
-    let img = ImageReader::open(&path)
-        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path.display(), e))
-        .decode()
-        .unwrap_or_else(|e| panic!("Failed to decode {}: {}", path.display(), e))
-        .to_rgb8();
-
-    let (width, height) = img.dimensions();
-    let data: Vec<[f32; 3]> = img
-        .pixels()
-        .map(|p| {
-            [
-                f32::from(p[0]) / 255.0,
-                f32::from(p[1]) / 255.0,
-                f32::from(p[2]) / 255.0,
-            ]
-        })
-        .collect();
-
-    Rgb::new(
-        data,
-        width as usize,
-        height as usize,
-        TransferCharacteristic::SRGB,
-        ColorPrimaries::BT709,
-    )
-    .expect("Failed to create Rgb")
-}
+use common::{backends, load_image};
 
 /// Create synthetic gradient test images
 fn create_synthetic_images(width: usize, height: usize) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
@@ -225,7 +195,7 @@ fn test_scalar_vs_simd_real_images() {
 
         let diff = (scalar_score - simd_score).abs();
         // 1% relative tolerance for FP differences between f64 scalar and f32 SIMD
-        let tolerance = simd_score.abs() * 0.01;
+        let tolerance = simd_score.abs() * BACKEND_RELATIVE_TOLERANCE;
 
         assert!(
             diff < tolerance,
@@ -263,7 +233,7 @@ fn test_simd_vs_unsafe_simd_real_images() {
 
         let diff = (simd_score - unsafe_score).abs();
         // 1% relative tolerance
-        let tolerance = simd_score.abs() * 0.01;
+        let tolerance = simd_score.abs() * BACKEND_RELATIVE_TOLERANCE;
 
         assert!(
             diff < tolerance,
@@ -304,7 +274,7 @@ fn test_scalar_vs_simd_synthetic() {
         );
 
         let diff = (scalar_score - simd_score).abs();
-        let tolerance = scalar_score.abs() * 0.01;
+        let tolerance = scalar_score.abs() * BACKEND_RELATIVE_TOLERANCE;
 
         assert!(
             diff < tolerance,
@@ -342,7 +312,7 @@ fn test_simd_vs_unsafe_simd_synthetic() {
         );
 
         let diff = (simd_score - unsafe_score).abs();
-        let tolerance = simd_score.abs() * 0.01;
+        let tolerance = simd_score.abs() * BACKEND_RELATIVE_TOLERANCE;
 
         assert!(
             diff < tolerance,
@@ -356,6 +326,103 @@ fn test_simd_vs_unsafe_simd_synthetic() {
     }
 }
 
+// ============================================================================
+// Mixed backend matrix - every XYB x blur x SSIM backend combination
+// ============================================================================
+
+/// One config per combination of stage backends. Backends the CPU lacks
+/// resolve to scalar, as they would for callers.
+fn backend_matrix() -> Vec<(String, Ssimulacra2Config)> {
+    let only = |backend| FallbackChain::new(&[backend]);
+    let backends = backends();
+    let mut configs = Vec::new();
+    for &xyb in &backends {
+        for &blur in &backends {
+            for &ssim in &backends {
+                let name = format!(
+                    "xyb={} blur={} ssim={}",
+                    xyb.name(),
+                    blur.name(),
+                    ssim.name()
+                );
+                let config = Ssimulacra2Config::default().with_backends(StageBackends {
+                    xyb: only(xyb),
+                    blur: only(blur),
+                    ssim: only(ssim),
+                });
+                configs.push((name, config));
+            }
+        }
+    }
+    configs
+}
+
+#[test]
+fn test_backend_matrix_identical_images() {
+    let source = load_image("source.png");
+    for (name, config) in backend_matrix() {
+        let score =
+            compute_frame_ssimulacra2_with_config(source.clone(), source.clone(), config).unwrap();
+        assert_eq!(score, 100.0, "{}: identical images scored {}", name, score);
+    }
+}
+
+#[test]
+fn test_backend_matrix_real_images() {
+    let source = load_image("source.png");
+    for file in ["q20.jpg", "q90.jpg"] {
+        let distorted = load_image(file);
+        let scalar = compute_frame_ssimulacra2_with_config(
+            source.clone(),
+            distorted.clone(),
+            Ssimulacra2Config::scalar(),
+        )
+        .unwrap();
+        for (name, config) in backend_matrix() {
+            let score =
+                compute_frame_ssimulacra2_with_config(source.clone(), distorted.clone(), config)
+                    .unwrap();
+            assert!(
+                accuracy::backends_agree(scalar, score),
+                "{} {}: scalar={:.6}, mixed={:.6}",
+                file,
+                name,
+                scalar,
+                score
+            );
+        }
+    }
+}
+
+#[test]
+fn test_backend_matrix_synthetic() {
+    // Odd sizes take the kernels' remainder paths and the downscale's
+    // repeated edge rows and columns
+    for (width, height) in [(64, 64), (67, 45)] {
+        let (source_data, distorted_data) = create_synthetic_images(width, height);
+        let scalar = compute_score_from_data(
+            &source_data,
+            &distorted_data,
+            width,
+            height,
+            Ssimulacra2Config::scalar(),
+        );
+        for (name, config) in backend_matrix() {
+            let score =
+                compute_score_from_data(&source_data, &distorted_data, width, height, config);
+            assert!(
+                accuracy::backends_agree(scalar, score),
+                "{}x{} {}: scalar={:.6}, mixed={:.6}",
+                width,
+                height,
+                name,
+                scalar,
+                score
+            );
+        }
+    }
+}
+
 // ============================================================================
 // Quality ordering test - higher quality = higher score
 // ============================================================================
@@ -0,0 +1,119 @@
+//! Deviation of the experimental summed-area blur from the reference kernel.
+//!
+//! The summed-area kernel approximates the recursive Gaussian, so scores are
+//! not expected to match. These tests report the deviation on the JPEG corpus
+//! and pin down that it stays bounded and preserves quality ordering.
+
+#![cfg(feature = "integral-blur")]
+
+use fast_ssim2::{compute_frame_ssimulacra2_with_config, BlurKernel, Ssimulacra2Config};
+use image::ImageReader;
+use std::path::PathBuf;
+use yuvxyb::Rgb;
+
+mod common;
+
+use common::backend_configs;
+
+/// Largest allowed score deviation from the recursive Gaussian on the corpus
+const MAX_DEVIATION: f64 = 5.0;
+
+fn load_image(filename: &str) -> Rgb {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("test_data")
+        .join("jpeg_quality")
+        .join(filename);
+    let img = ImageReader::open(&path)
+        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path.display(), e))
+        .decode()
+        .unwrap_or_else(|e| panic!("Failed to decode {}: {}", path.display(), e))
+        .to_rgb32f();
+    let (width, height) = img.dimensions();
+    let data = img
+        .chunks_exact(3)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect::<Vec<_>>();
+    Rgb::new(
+        data,
+        width as usize,
+        height as usize,
+        yuvxyb::TransferCharacteristic::SRGB,
+        yuvxyb::ColorPrimaries::BT709,
+    )
+    .expect("Failed to create Rgb")
+}
+
+#[test]
+fn test_summed_area_deviation_from_reference() {
+    let source = load_image("source.png");
+    let reference_config = Ssimulacra2Config::default();
+    let config = reference_config
+        .clone()
+        .with_blur_kernel(BlurKernel::SummedArea);
+
+    let mut previous = f64::NEG_INFINITY;
+    for quality in [20, 45, 70, 90] {
+        let distorted = load_image(&format!("q{}.jpg", quality));
+        let reference = compute_frame_ssimulacra2_with_config(
+            source.clone(),
+            distorted.clone(),
+            reference_config.clone(),
+        )
+        .unwrap();
+        let score =
+            compute_frame_ssimulacra2_with_config(source.clone(), distorted, config.clone())
+                .unwrap();
+        let deviation = (score - reference).abs();
+
+        println!(
+            "Q{}: gaussian={:.6}, summed-area={:.6}, deviation={:.6}",
+            quality, reference, score, deviation
+        );
+        assert!(
+            deviation <= MAX_DEVIATION,
+            "Q{}: summed-area score {} deviates from {} by {}",
+            quality,
+            score,
+            reference,
+            deviation
+        );
+        assert!(
+            score > previous,
+            "Q{}: summed-area score {} should exceed the lower quality's {}",
+            quality,
+            score,
+            previous
+        );
+        previous = score;
+    }
+}
+
+#[test]
+fn test_summed_area_backends_agree() {
+    let source = load_image("source.png");
+    let distorted = load_image("q45.jpg");
+
+    let scores: Vec<f64> = backend_configs()
+        .into_iter()
+        .map(|config| config.with_blur_kernel(BlurKernel::SummedArea))
+        .map(|config| {
+            let identical = compute_frame_ssimulacra2_with_config(
+                source.clone(),
+                source.clone(),
+                config.clone(),
+            )
+            .unwrap();
+            assert_eq!(identical, 100.0, "{}", config.impl_type.name());
+            compute_frame_ssimulacra2_with_config(source.clone(), distorted.clone(), config)
+                .unwrap()
+        })
+        .collect();
+
+    for score in &scores {
+        assert!(
+            (score - scores[0]).abs() <= scores[0].abs() * 0.01,
+            "backends disagree with the summed-area kernel: {:?}",
+            scores
+        );
+    }
+}
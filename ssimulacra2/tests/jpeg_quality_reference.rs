@@ -10,9 +10,9 @@
 //! Captured: 2026-01-04
 
 use fast_ssim2::{compute_frame_ssimulacra2, Ssimulacra2Config};
-use image::ImageReader;
-use std::path::PathBuf;
-use yuvxyb::Rgb;
+mod common;
+
+use common::load_image;
 
 /// JPEG quality test case with C++ verified score
 struct JpegQualityCase {
@@ -50,42 +50,6 @@ const JPEG_QUALITY_CASES: &[JpegQualityCase] = &[
     },
 ];
 
-fn test_data_path() -> PathBuf {
-    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .join("test_data")
-        .join("jpeg_quality")
-}
-
-fn load_image(filename: &str) -> Rgb {
-    let path = test_data_path().join(filename);
-    let img = ImageReader::open(&path)
-        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path.display(), e))
-        .decode()
-        .unwrap_or_else(|e| panic!("Failed to decode {}: {}", path.display(), e))
-        .to_rgb8();
-
-    let (width, height) = img.dimensions();
-    let data: Vec<[f32; 3]> = img
-        .pixels()
-        .map(|p| {
-            [
-                f32::from(p[0]) / 255.0,
-                f32::from(p[1]) / 255.0,
-                f32::from(p[2]) / 255.0,
-            ]
-        })
-        .collect();
-
-    Rgb::new(
-        data,
-        width as usize,
-        height as usize,
-        yuvxyb::TransferCharacteristic::SRGB,
-        yuvxyb::ColorPrimaries::BT709,
-    )
-    .expect("Failed to create Rgb")
-}
-
 #[test]
 fn test_jpeg_quality_vs_cpp_reference() {
     let source = load_image("source.png");
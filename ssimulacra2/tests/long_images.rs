@@ -0,0 +1,112 @@
+//! Extreme aspect ratios: strip panoramas, sprite sheets and single columns.
+//!
+//! The backends process rows and column chunks in SIMD lanes, so a
+//! 16384-wide row or a 32-column image exercises tails and chunk sizes a
+//! square image never reaches. The recursive blur is marginally stable, so
+//! f32 rounding also accumulates along a line; these tests check that every
+//! backend still scores such images, close to scalar.
+
+use fast_ssim2::{compute_ssimulacra2_with_config, LinearRgbImage};
+
+mod common;
+
+use common::backend_configs;
+
+/// Wide strips, tall columns and the narrowest images the metric accepts
+const SHAPES: [(usize, usize); 4] = [(16384, 32), (32, 16384), (4096, 8), (8, 4096)];
+
+/// Relative tolerance between backends (matches the implementation parity tests)
+const BACKEND_RELATIVE_TOLERANCE: f64 = 0.01;
+
+/// Smooth content plus hashed noise of up to `noise` at (`x`, `y`)
+fn pixel(x: usize, y: usize, seed: u32, noise: f32) -> [f32; 3] {
+    let hash = (x as u32).wrapping_mul(73_856_093) ^ (y as u32).wrapping_mul(19_349_663) ^ seed;
+    let n = (hash.wrapping_mul(2_654_435_761) >> 24) as f32 / 255.0 * noise;
+    let v = 0.5 + 0.3 * (x as f32 * 0.05).sin() * (y as f32 * 0.07).cos();
+    [(v + n).min(1.0), v * 0.8, (1.0 - v + n).min(1.0)]
+}
+
+/// A `width`x`height` image, or the transpose of the `height`x`width` one
+fn image(width: usize, height: usize, seed: u32, noise: f32, transposed: bool) -> LinearRgbImage {
+    let data = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            if transposed {
+                pixel(y, x, seed, noise)
+            } else {
+                pixel(x, y, seed, noise)
+            }
+        })
+        .collect();
+    LinearRgbImage::new(data, width, height)
+}
+
+fn pair(width: usize, height: usize, noise: f32) -> (LinearRgbImage, LinearRgbImage) {
+    (
+        image(width, height, 0, 0.0, false),
+        image(width, height, 77, noise, false),
+    )
+}
+
+#[test]
+fn test_identical_long_images_score_100() {
+    for (width, height) in SHAPES {
+        let source = image(width, height, 0, 0.0, false);
+        for config in backend_configs() {
+            let name = config.impl_type.name();
+            let score = compute_ssimulacra2_with_config(&source, &source, config).unwrap();
+            assert_eq!(score, 100.0, "{} {}x{}", name, width, height);
+        }
+    }
+}
+
+#[test]
+fn test_long_images_match_scalar_on_every_backend() {
+    for (width, height) in SHAPES {
+        let (source, distorted) = pair(width, height, 0.125);
+        let mut scores = backend_configs().into_iter().map(|config| {
+            let name = config.impl_type.name();
+            let score = compute_ssimulacra2_with_config(&source, &distorted, config).unwrap();
+            (name, score)
+        });
+        let (_, scalar) = scores.next().unwrap();
+        assert!(scalar.is_finite() && scalar < 100.0);
+        for (name, score) in scores {
+            assert!(
+                (score - scalar).abs() <= scalar.abs() * BACKEND_RELATIVE_TOLERANCE,
+                "{} {}x{}: scalar {:.6} vs {:.6}",
+                name,
+                width,
+                height,
+                scalar,
+                score
+            );
+        }
+    }
+}
+
+#[test]
+fn test_transposed_strips_score_alike() {
+    for (width, height) in [(16384, 32), (4096, 8)] {
+        let source = image(width, height, 0, 0.0, false);
+        let distorted = image(width, height, 77, 0.125, false);
+        let source_t = image(height, width, 0, 0.0, true);
+        let distorted_t = image(height, width, 77, 0.125, true);
+        for config in backend_configs() {
+            let name = config.impl_type.name();
+            let score =
+                compute_ssimulacra2_with_config(&source, &distorted, config.clone()).unwrap();
+            let transposed =
+                compute_ssimulacra2_with_config(&source_t, &distorted_t, config).unwrap();
+            assert!(
+                (score - transposed).abs() <= score.abs() * BACKEND_RELATIVE_TOLERANCE,
+                "{} {}x{}: {:.6} vs transposed {:.6}",
+                name,
+                width,
+                height,
+                score,
+                transposed
+            );
+        }
+    }
+}
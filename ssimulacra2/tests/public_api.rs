@@ -0,0 +1,193 @@
+//! Pins the public API that downstream crates build against.
+//!
+//! Everything here is checked at compile time: function signatures are
+//! coerced to fn pointers, struct fields are named in patterns or read with
+//! their types, and trait implementations are required by bounds. If this
+//! file stops compiling, the change breaks callers and needs a semver-major
+//! release. The runtime assertions only keep the checked items from being
+//! optimized into no-ops.
+//!
+//! Items that must stay private are checked by the compile-fail doctests in
+//! `src/api_guarantees.rs`; `cargo semver-checks` in CI covers the rest.
+
+use fast_ssim2::{
+    compute_ssimulacra2, compute_ssimulacra2_detailed, compute_ssimulacra2_into,
+    compute_ssimulacra2_maps, compute_ssimulacra2_with_config, BlurKernel, DetailedScore,
+    ErrorMaps, FallbackChain, LinearRgbImage, MapBuffers, MapRequest, Parallelism, PreResize,
+    ReferencePrecision, ScaleStatistics, SimdImpl, Ssimulacra2Config, Ssimulacra2Error,
+    Ssimulacra2Reference, Ssimulacra2Scratch, Stage, StageBackends, XybPlanes,
+};
+use std::borrow::Cow;
+
+fn assert_send_sync<T: Send + Sync>() {}
+fn assert_value_type<T: std::fmt::Debug + Clone + Copy + PartialEq + Default>() {}
+fn assert_clone_type<T: std::fmt::Debug + Clone + PartialEq + Default + Send + Sync>() {}
+
+fn image(value: f32) -> LinearRgbImage {
+    LinearRgbImage::new(vec![[value; 3]; 16 * 16], 16, 16)
+}
+
+#[test]
+fn test_entry_point_signatures() {
+    let _: fn(LinearRgbImage, LinearRgbImage) -> Result<f64, Ssimulacra2Error> =
+        compute_ssimulacra2::<LinearRgbImage, LinearRgbImage>;
+    let _: fn(LinearRgbImage, LinearRgbImage, Ssimulacra2Config) -> Result<f64, Ssimulacra2Error> =
+        compute_ssimulacra2_with_config::<LinearRgbImage, LinearRgbImage>;
+    let _: fn(
+        LinearRgbImage,
+        LinearRgbImage,
+        Ssimulacra2Config,
+    ) -> Result<DetailedScore, Ssimulacra2Error> =
+        compute_ssimulacra2_detailed::<LinearRgbImage, LinearRgbImage>;
+    let _: fn(
+        &mut Ssimulacra2Scratch,
+        LinearRgbImage,
+        LinearRgbImage,
+    ) -> Result<f64, Ssimulacra2Error> = compute_ssimulacra2_into::<LinearRgbImage, LinearRgbImage>;
+
+    let source = image(0.5);
+    assert_eq!(compute_ssimulacra2(&source, &source), Ok(100.0));
+}
+
+#[test]
+fn test_config_and_backends() {
+    assert_value_type::<SimdImpl>();
+    assert_value_type::<BlurKernel>();
+    // Holds a shared thread pool, so it is cloned rather than copied
+    assert_clone_type::<Parallelism>();
+    assert_value_type::<StageBackends>();
+    assert_value_type::<FallbackChain>();
+    assert_value_type::<ReferencePrecision>();
+    assert_send_sync::<Ssimulacra2Config>();
+
+    // The config is non-exhaustive: it is built with `with_*` methods and its
+    // fields stay readable with their types
+    let config = Ssimulacra2Config::new(SimdImpl::Scalar)
+        .with_parallelism(Parallelism::Off)
+        .with_blur_kernel(BlurKernel::RecursiveGaussian)
+        .with_diagnostics_logging(false);
+    let _: SimdImpl = config.impl_type;
+    let _: Option<StageBackends> = config.backends;
+    let _: BlurKernel = config.blur_kernel;
+    let _: Parallelism = config.parallelism;
+    let _: bool = config.log_diagnostics;
+    let _: Option<f64> = config.early_downscale_megapixels;
+    let _: Option<PreResize> = config.pre_resize;
+    let _: Option<Cow<'static, str>> = config.monitor_label;
+    assert_eq!(config.impl_type, SimdImpl::Scalar);
+    assert_eq!(Ssimulacra2Config::scalar().impl_type, SimdImpl::Scalar);
+    assert_eq!(Ssimulacra2Config::simd().impl_type, SimdImpl::Simd);
+    #[cfg(feature = "unsafe-simd")]
+    assert_eq!(
+        Ssimulacra2Config::unsafe_simd().impl_type,
+        SimdImpl::UnsafeSimd
+    );
+
+    let chain: Result<FallbackChain, Ssimulacra2Error> = FallbackChain::parse("simd,scalar");
+    let chain = chain.unwrap();
+    let backends: &[SimdImpl] = chain.backends();
+    assert_eq!(backends, [SimdImpl::Simd, SimdImpl::Scalar]);
+    let stages = StageBackends {
+        xyb: chain,
+        blur: FallbackChain::new(&[SimdImpl::Scalar]),
+        ssim: chain,
+    };
+    let config = config.with_backends(stages);
+    let backend: SimdImpl = config.backend_for(Stage::Blur);
+    assert_eq!(backend, SimdImpl::Scalar);
+    let _: fn(&SimdImpl) -> &'static str = SimdImpl::name;
+    let _: fn(&SimdImpl) -> bool = SimdImpl::is_available;
+}
+
+#[test]
+fn test_precompute() {
+    assert_send_sync::<Ssimulacra2Reference>();
+    let _: fn(LinearRgbImage) -> Result<Ssimulacra2Reference, Ssimulacra2Error> =
+        Ssimulacra2Reference::new::<LinearRgbImage>;
+    let _: fn(
+        LinearRgbImage,
+        ReferencePrecision,
+    ) -> Result<Ssimulacra2Reference, Ssimulacra2Error> =
+        Ssimulacra2Reference::with_precision::<LinearRgbImage>;
+    let _: fn(&Ssimulacra2Reference, LinearRgbImage) -> Result<f64, Ssimulacra2Error> =
+        Ssimulacra2Reference::compare::<LinearRgbImage>;
+    let _: fn(&Ssimulacra2Reference, LinearRgbImage) -> Result<DetailedScore, Ssimulacra2Error> =
+        Ssimulacra2Reference::compare_detailed::<LinearRgbImage>;
+
+    let reference = Ssimulacra2Reference::new(image(0.5)).unwrap();
+    let (width, height, scales): (usize, usize, usize) = (
+        reference.width(),
+        reference.height(),
+        reference.num_scales(),
+    );
+    assert_eq!((width, height), (16, 16));
+    assert!(scales > 0);
+    let _: ReferencePrecision = reference.precision();
+    let _: usize = reference.storage_bytes();
+
+    let detailed = reference.compare_detailed(image(0.4)).unwrap();
+    let DetailedScore {
+        score,
+        scales,
+        error_bound,
+    } = detailed;
+    let _: f64 = score;
+    let _: f64 = error_bound;
+    let ScaleStatistics {
+        avg_ssim,
+        avg_edgediff,
+    } = scales[0];
+    let _: [f64; 6] = avg_ssim;
+    let _: [f64; 12] = avg_edgediff;
+}
+
+#[test]
+fn test_maps() {
+    assert_send_sync::<MapBuffers>();
+    let _: fn(
+        LinearRgbImage,
+        LinearRgbImage,
+        Ssimulacra2Config,
+        MapRequest,
+        &mut MapBuffers,
+    ) -> Result<f64, Ssimulacra2Error> = compute_ssimulacra2_maps::<LinearRgbImage, LinearRgbImage>;
+
+    let request = MapRequest {
+        scales: 0b1u8,
+        error_maps: true,
+        xyb: true,
+    };
+    assert_eq!(
+        request,
+        MapRequest::error_maps().with_scales(0..1).with_xyb(true)
+    );
+
+    let mut buffers = MapBuffers::new();
+    let (source, distorted) = (image(0.5), image(0.4));
+    compute_ssimulacra2_maps(
+        &source,
+        &distorted,
+        Ssimulacra2Config::default(),
+        request,
+        &mut buffers,
+    )
+    .unwrap();
+    let ErrorMaps {
+        width,
+        height,
+        ssim,
+        artifact,
+        detail_lost,
+    } = buffers.error_maps(0).unwrap();
+    let _: [&[Vec<f32>; 3]; 3] = [ssim, artifact, detail_lost];
+    assert_eq!((width, height), (16, 16));
+    let XybPlanes {
+        width,
+        height,
+        source,
+        distorted,
+    } = buffers.xyb(0).unwrap();
+    let _: [&[Vec<f32>; 3]; 2] = [source, distorted];
+    assert_eq!((width, height), (16, 16));
+    assert!(buffers.error_maps(1).is_none());
+}
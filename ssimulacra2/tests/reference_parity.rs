@@ -8,6 +8,7 @@
 //!
 //! Run tests with: cargo test --test reference_parity
 
+use fast_ssim2::accuracy::DeviationFamily;
 use fast_ssim2::compute_frame_ssimulacra2;
 use fast_ssim2::reference_data::{ReferenceCase, REFERENCE_CASES};
 use sha2::{Digest, Sha256};
@@ -83,11 +84,9 @@ fn gen_gradient_diag(width: usize, height: usize) -> Vec<u8> {
     let max_dist = width + height - 2;
     for y in 0..height {
         for x in 0..width {
-            let val = if max_dist > 0 {
-                ((x + y) * 255 / max_dist) as u8
-            } else {
-                128
-            };
+            let val = ((x + y) * 255)
+                .checked_div(max_dist)
+                .map_or(128, |v| v as u8);
             data.extend_from_slice(&[val, val, val]);
         }
     }
@@ -246,6 +245,33 @@ fn apply_yuv_roundtrip(input: &[u8], width: usize, height: usize) -> Vec<u8> {
 // Test Case Generator
 // ============================================================================
 
+/// Deviation family of a corpus case, by its name
+fn deviation_family(name: &str) -> DeviationFamily {
+    if name.contains("uniform_shift") {
+        DeviationFamily::UniformShift
+    } else if name.contains("boxblur8x8")
+        || name.contains("sharpen")
+        || name.contains("yuv_roundtrip")
+    {
+        DeviationFamily::Distortion
+    } else if name.contains("_vs_")
+        || [
+            "perfect_match",
+            "gradient_h_",
+            "gradient_v_",
+            "checkerboard_",
+            "noise_seed_",
+            "edge_",
+        ]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+    {
+        DeviationFamily::Synthetic
+    } else {
+        DeviationFamily::Other
+    }
+}
+
 fn generate_test_image(case: &ReferenceCase) -> (Vec<u8>, Vec<u8>) {
     let name = case.name;
     let width = case.width;
@@ -329,6 +355,19 @@ fn generate_test_image(case: &ReferenceCase) -> (Vec<u8>, Vec<u8>) {
 // Tests
 // ============================================================================
 
+#[test]
+fn test_deviation_families() {
+    for (name, family) in [
+        ("uniform_shift_128_to_130", DeviationFamily::UniformShift),
+        ("noise_seed_1_boxblur8x8", DeviationFamily::Distortion),
+        ("gradient_h_vs_gradient_v", DeviationFamily::Synthetic),
+        ("perfect_match_32x32", DeviationFamily::Synthetic),
+        ("gradient_diag_64x64", DeviationFamily::Other),
+    ] {
+        assert_eq!(deviation_family(name), family, "{}", name);
+    }
+}
+
 #[test]
 fn test_reference_parity() {
     if REFERENCE_CASES.is_empty() {
@@ -410,30 +449,9 @@ fn test_reference_parity() {
         let error = (score - case.expected_score).abs();
         max_error = max_error.max(error);
 
-        // Per-pattern tolerance based on observed error characteristics
-        // SIMD implementations may have different FP rounding than C++ reference
-        let tolerance = if case.name.contains("uniform_shift") {
-            // For uniform gray images, different FP rounding in SIMD paths can cause
-            // significant score differences that don't affect real-world images.
-            10.0 // Large tolerance for extreme uniform color edge cases
-        } else if case.name.contains("boxblur8x8")
-            || case.name.contains("sharpen")
-            || case.name.contains("yuv_roundtrip")
-        {
-            // SIMD implementations use different rounding than C++ reference
-            0.5 // Allow larger variance for these patterns
-        } else if case.name.contains("_vs_")
-            || case.name.starts_with("perfect_match")
-            || case.name.starts_with("gradient_h_")
-            || case.name.starts_with("gradient_v_")
-            || case.name.starts_with("checkerboard_")
-            || case.name.starts_with("noise_seed_")
-            || case.name.starts_with("edge_")
-        {
-            0.01 // Synthetic patterns and non-identical comparisons should be close
-        } else {
-            0.05 // Fallback for any other patterns
-        };
+        // Per-family tolerance based on observed error characteristics; SIMD
+        // implementations may have different FP rounding than C++ reference
+        let tolerance = deviation_family(case.name).max_cpp_deviation();
 
         if error > tolerance {
             failures.push((i, case.name, case.expected_score, score, error));
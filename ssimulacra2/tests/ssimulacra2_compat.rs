@@ -0,0 +1,129 @@
+//! Guards the API inherited from rust-av/ssimulacra2.
+//!
+//! Downstream users can depend on this crate as
+//! `ssimulacra2 = { package = "fast-ssim2", ... }` and keep their
+//! `use ssimulacra2::...` imports. This test compiles against the crate under
+//! that name, using only the upstream public surface; if it stops compiling,
+//! the change breaks those users.
+
+extern crate fast_ssim2 as ssimulacra2;
+
+use ssimulacra2::{
+    compute_frame_ssimulacra2, Blur, CastFromPrimitive, ColorPrimaries, Frame, LinearRgb,
+    MatrixCoefficients, Pixel, Plane, Rgb, Ssimulacra2Error, TransferCharacteristic, Xyb, Yuv,
+    YuvConfig,
+};
+
+fn gradient(width: usize, height: usize) -> Vec<[f32; 3]> {
+    (0..width * height)
+        .map(|i| {
+            let x = (i % width) as f32 / width as f32;
+            let y = (i / width) as f32 / height as f32;
+            [x, y, (x + y) / 2.0]
+        })
+        .collect()
+}
+
+#[test]
+fn test_upstream_rgb_api() {
+    let (width, height) = (32, 24);
+    let source = Rgb::new(
+        gradient(width, height),
+        width,
+        height,
+        TransferCharacteristic::SRGB,
+        ColorPrimaries::BT709,
+    )
+    .unwrap();
+    let distorted = Rgb::new(
+        gradient(width, height)
+            .into_iter()
+            .map(|[r, g, b]| [r * 0.9, g, b])
+            .collect(),
+        width,
+        height,
+        TransferCharacteristic::SRGB,
+        ColorPrimaries::BT709,
+    )
+    .unwrap();
+
+    let identical = compute_frame_ssimulacra2(source.clone(), source.clone()).unwrap();
+    assert_eq!(identical, 100.0);
+    let score = compute_frame_ssimulacra2(source.clone(), distorted).unwrap();
+    assert!(score < 100.0);
+
+    // Upstream accepts anything convertible to LinearRgb, including the
+    // converted types themselves
+    let linear = LinearRgb::try_from(source).unwrap();
+    let xyb = Xyb::from(linear.clone());
+    assert_eq!(xyb.width(), width);
+    assert_eq!(
+        compute_frame_ssimulacra2(linear.clone(), linear).unwrap(),
+        100.0
+    );
+}
+
+#[test]
+fn test_upstream_yuv_api() {
+    let (width, height) = (16, 16);
+    let mut frame: Frame<u8> = Frame {
+        planes: [
+            Plane::new(width, height, 0, 0, 0, 0),
+            Plane::new(width, height, 0, 0, 0, 0),
+            Plane::new(width, height, 0, 0, 0, 0),
+        ],
+    };
+    for (y, row) in frame.planes[0].rows_iter_mut().enumerate() {
+        for (x, px) in row[..width].iter_mut().enumerate() {
+            *px = u8::cast_from(16 + x * 8 + y);
+        }
+    }
+    for plane in &mut frame.planes[1..] {
+        for row in plane.rows_iter_mut() {
+            row[..width].fill(128);
+        }
+    }
+
+    let config = YuvConfig {
+        bit_depth: 8,
+        subsampling_x: 0,
+        subsampling_y: 0,
+        full_range: false,
+        matrix_coefficients: MatrixCoefficients::BT709,
+        transfer_characteristics: TransferCharacteristic::BT1886,
+        color_primaries: ColorPrimaries::BT709,
+    };
+    let source = Yuv::new(frame.clone(), config).unwrap();
+    let distorted = Yuv::new(frame, config).unwrap();
+    assert_eq!(compute_frame_ssimulacra2(source, distorted).unwrap(), 100.0);
+}
+
+#[test]
+fn test_upstream_blur_and_errors() {
+    let (width, height) = (16, 16);
+    let mut blur = Blur::new(width, height);
+    let plane = vec![0.5f32; width * height];
+    let out = blur.blur(&[plane.clone(), plane.clone(), plane]);
+    assert_eq!(out[0].len(), width * height);
+
+    let small = LinearRgb::new(vec![[0.5; 3]; 4 * 4], 4, 4).unwrap();
+    assert_eq!(
+        compute_frame_ssimulacra2(small.clone(), small),
+        Err(Ssimulacra2Error::InvalidImageSize)
+    );
+}
+
+/// Generic code written against upstream's `Pixel` bound keeps working.
+fn max_value<T: Pixel>(values: &[T]) -> u32 {
+    values
+        .iter()
+        .map(|&v| -> u32 { v.into() })
+        .max()
+        .unwrap_or(0)
+}
+
+#[test]
+fn test_upstream_pixel_bound() {
+    assert_eq!(max_value(&[1u8, 200, 3]), 200);
+    assert_eq!(max_value(&[1u16, 1023]), 1023);
+}
@@ -0,0 +1,91 @@
+//! `compute_ssimulacra2_into` must not allocate once its scratch is warm.
+//!
+//! Allocations are only counted on the thread running the comparison, so the
+//! test harness's own threads don't interfere. The scalar XYB conversion
+//! goes through a temporary buffer, so this needs the SIMD backends.
+#![cfg(feature = "simd")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use fast_ssim2::{
+    compute_ssimulacra2_into, compute_ssimulacra2_with_config, Ssimulacra2Config,
+    Ssimulacra2Scratch,
+};
+
+mod common;
+use common::test_image;
+
+struct CountingAllocator;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    if COUNTING.with(Cell::get) {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Runs `f` and returns how many allocations it made on this thread
+fn allocations_in<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    ALLOCATIONS.with(|count| count.set(0));
+    COUNTING.with(|counting| counting.set(true));
+    let result = f();
+    COUNTING.with(|counting| counting.set(false));
+    (result, ALLOCATIONS.with(Cell::get))
+}
+
+#[test]
+fn warm_scratch_does_not_allocate() {
+    #[allow(unused_mut)]
+    let mut configs = vec![Ssimulacra2Config::simd()];
+    #[cfg(feature = "unsafe-simd")]
+    configs.push(Ssimulacra2Config::unsafe_simd());
+
+    let (source, distorted) = (test_image(123, 77, 1), test_image(123, 77, 2));
+    let (small_source, small_distorted) = (test_image(40, 30, 3), test_image(40, 30, 4));
+    for config in configs {
+        let expected =
+            compute_ssimulacra2_with_config(&source, &distorted, config.clone()).unwrap();
+        let mut scratch = Ssimulacra2Scratch::with_config(config.clone());
+        compute_ssimulacra2_into(&mut scratch, &source, &distorted).unwrap();
+
+        let (score, allocations) =
+            allocations_in(|| compute_ssimulacra2_into(&mut scratch, &source, &distorted));
+        assert_eq!(score.unwrap(), expected);
+        assert_eq!(allocations, 0, "{} after warmup", config.impl_type.name());
+
+        // Smaller images fit in the buffers the larger ones left behind
+        let (_, allocations) = allocations_in(|| {
+            compute_ssimulacra2_into(&mut scratch, &small_source, &small_distorted)
+        });
+        assert_eq!(
+            allocations,
+            0,
+            "{} at a smaller size",
+            config.impl_type.name()
+        );
+    }
+}
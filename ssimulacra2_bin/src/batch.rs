@@ -0,0 +1,149 @@
+//! Scoring many distorted images against one source.
+//!
+//! Decoding usually costs as much as scoring, so upcoming images are decoded
+//! on background threads while earlier ones are scored. Decoded images wait in
+//! a bounded queue whose depth comes from [`prefetch_depth`], so a corpus of
+//! thousands of large images never has more than a memory budget's worth of
+//! pixels resident at once.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use fast_ssim2::{LinearRgbImage, PreResize, Ssimulacra2Reference, ToLinearRgb};
+
+use crate::{image_to_rgb, open_image};
+
+/// Bytes held by one decoded image waiting to be scored: linear RGB as three
+/// `f32` per pixel.
+pub fn queued_image_bytes(width: usize, height: usize) -> usize {
+    width * height * 3 * std::mem::size_of::<f32>()
+}
+
+/// Number of decoded images that fit in `budget_bytes`, at least one so the
+/// pipeline always makes progress, and no more than there are images.
+pub fn prefetch_depth(budget_bytes: usize, image_bytes: usize, images: usize) -> usize {
+    (budget_bytes / image_bytes.max(1)).clamp(1, images.max(1))
+}
+
+/// Expands directories into the files they contain (not recursive, sorted
+/// by name); other paths are kept as given.
+fn expand_paths(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut expanded = Vec::with_capacity(paths.len());
+    for path in paths {
+        if path.is_dir() {
+            let mut files = std::fs::read_dir(&path)
+                .unwrap_or_else(|e| panic!("Failed to read directory {}: {}", path.display(), e))
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>();
+            files.sort();
+            expanded.extend(files);
+        } else {
+            expanded.push(path);
+        }
+    }
+    expanded
+}
+
+fn decode(
+    path: &Path,
+    apply_orientation: bool,
+    pre_resize: Option<PreResize>,
+) -> Result<LinearRgbImage, String> {
+    let image = open_image(path, apply_orientation).map_err(|e| e.to_string())?;
+    let image = image_to_rgb(&image).to_linear_rgb();
+    match pre_resize {
+        Some(resize) => resize.apply(&image).map_err(|e| e.to_string()),
+        None => Ok(image),
+    }
+}
+
+pub fn compare_batch(
+    source: &Path,
+    distorted: Vec<PathBuf>,
+    apply_orientation: bool,
+    pre_resize: Option<PreResize>,
+    decode_threads: usize,
+    score_threads: usize,
+    prefetch_mib: usize,
+) {
+    let paths = expand_paths(distorted);
+    let source = decode(source, apply_orientation, pre_resize)
+        .unwrap_or_else(|e| panic!("Failed to open source file: {}", e));
+    let image_bytes = queued_image_bytes(source.width(), source.height());
+    let reference =
+        Ssimulacra2Reference::new(source).expect("Failed to precompute the source image");
+
+    let depth = prefetch_depth(prefetch_mib << 20, image_bytes, paths.len());
+    let next = AtomicUsize::new(0);
+    let (decoded_tx, decoded_rx) = mpsc::sync_channel(depth);
+    let decoded_rx = Mutex::new(decoded_rx);
+    let (score_tx, score_rx) = mpsc::channel();
+
+    let mut failures = 0;
+    thread::scope(|scope| {
+        for _ in 0..decode_threads {
+            let decoded_tx = decoded_tx.clone();
+            let (next, paths) = (&next, &paths);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(index) else {
+                    break;
+                };
+                // Blocks while the queue is full, which bounds memory
+                if decoded_tx
+                    .send((index, decode(path, apply_orientation, pre_resize)))
+                    .is_err()
+                {
+                    break;
+                }
+            });
+        }
+        // Needs to be dropped or the scoring threads never stop waiting
+        drop(decoded_tx);
+
+        for _ in 0..score_threads {
+            let score_tx = score_tx.clone();
+            let (decoded_rx, reference) = (&decoded_rx, &reference);
+            scope.spawn(move || loop {
+                let received = decoded_rx.lock().unwrap().recv();
+                let Ok((index, image)) = received else {
+                    break;
+                };
+                let score = image.and_then(|image| {
+                    reference
+                        .compare(&image)
+                        .map_err(|e| format!("Failed to calculate ssimulacra2: {}", e))
+                });
+                score_tx.send((index, score)).unwrap();
+            });
+        }
+        drop(score_tx);
+
+        // Scores arrive out of order; print them in input order
+        let mut pending = BTreeMap::new();
+        let mut printed = 0;
+        for (index, score) in score_rx {
+            pending.insert(index, score);
+            while let Some(score) = pending.remove(&printed) {
+                let path = paths[printed].display();
+                match score {
+                    Ok(score) => println!("{}: {:.8}", path, score),
+                    Err(e) => {
+                        eprintln!("{}: {}", path, e);
+                        failures += 1;
+                    }
+                }
+                printed += 1;
+            }
+        }
+    });
+
+    if failures > 0 {
+        eprintln!("{} of {} images failed", failures, paths.len());
+        std::process::exit(1);
+    }
+}
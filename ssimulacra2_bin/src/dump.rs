@@ -0,0 +1,88 @@
+//! `--dump-scales`: the image pyramid the metric compares, written to disk.
+//!
+//! A score that looks wrong often comes from a coarse scale, where an
+//! artifact that is invisible at full size (or a shift that is obvious)
+//! looks very different. Each scale is written as `scale{N}_source.png` and
+//! `scale{N}_distorted.png` at that scale's own size, and `scales.txt` lists
+//! the size of every scale and how many points of the score it cost.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use fast_ssim2::{DetailedScore, LinearRgbImage, PreResize};
+use image::RgbImage;
+
+/// Writes every scale `detailed` has statistics for into `dir`, creating it
+/// if needed. `source` and `distorted` are the full-resolution images that
+/// were scored, after any pre-resize.
+pub fn dump_scales(
+    dir: &Path,
+    source: &LinearRgbImage,
+    distorted: &LinearRgbImage,
+    detailed: &DetailedScore,
+) {
+    fs::create_dir_all(dir).unwrap_or_else(|e| panic!("Failed to create {}: {}", dir.display(), e));
+
+    // Points lost at each scale, summed over the kinds of error
+    let explanation = detailed.explain();
+    let mut lost = vec![0.0f64; detailed.scales.len()];
+    for error in &explanation.errors {
+        for (lost, share) in lost.iter_mut().zip(&error.scales) {
+            *lost += error.points * share;
+        }
+    }
+
+    // The same 2x2 box filter the metric's pyramid uses
+    let halve = PreResize::new(2);
+    let (mut source, mut distorted) = (source.clone(), distorted.clone());
+    let mut summary = format!("score: {:.8}\n", detailed.score);
+    for (scale, lost) in lost.iter().enumerate() {
+        if scale > 0 {
+            source = halve.apply(&source).expect("Failed to downscale");
+            distorted = halve.apply(&distorted).expect("Failed to downscale");
+        }
+        write_png(&dir.join(format!("scale{}_source.png", scale)), &source);
+        write_png(
+            &dir.join(format!("scale{}_distorted.png", scale)),
+            &distorted,
+        );
+        writeln!(
+            summary,
+            "scale {}: {}x{}, {:.2} points lost",
+            scale,
+            source.width(),
+            source.height(),
+            lost
+        )
+        .unwrap();
+    }
+    writeln!(summary, "{}", explanation).unwrap();
+
+    let path = dir.join("scales.txt");
+    fs::write(&path, summary)
+        .unwrap_or_else(|e| panic!("Failed to write {}: {}", path.display(), e));
+}
+
+/// Saves linear RGB as an 8-bit sRGB PNG
+fn write_png(path: &Path, image: &LinearRgbImage) {
+    let pixels = image
+        .data()
+        .iter()
+        .flat_map(|pixel| pixel.map(linear_to_srgb8))
+        .collect();
+    RgbImage::from_raw(image.width() as u32, image.height() as u32, pixels)
+        .expect("pixel count matches the image size")
+        .save(path)
+        .unwrap_or_else(|e| panic!("Failed to write {}: {}", path.display(), e));
+}
+
+fn linear_to_srgb8(v: f32) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    let encoded = if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
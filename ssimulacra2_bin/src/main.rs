@@ -1,3 +1,6 @@
+mod batch;
+mod dump;
+mod selftest;
 #[cfg(feature = "video")]
 mod video;
 
@@ -6,11 +9,29 @@ use self::video::*;
 use clap::{Parser, Subcommand};
 #[cfg(feature = "video")]
 use fast_ssim2::MatrixCoefficients;
-use fast_ssim2::{compute_frame_ssimulacra2, ColorPrimaries, Rgb, TransferCharacteristic};
+use fast_ssim2::{
+    compute_frame_ssimulacra2_with_config, compute_ssimulacra2_detailed, ColorPrimaries,
+    LinearRgbImage, PreResize, ResampleFilter, Rgb, Ssimulacra2Config, ToLinearRgb,
+    TransferCharacteristic,
+};
+use image::{DynamicImage, ImageDecoder, ImageReader, ImageResult};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// `--version` output: the CLI version followed by how the library was built
+fn long_version() -> &'static str {
+    static LONG_VERSION: OnceLock<String> = OnceLock::new();
+    LONG_VERSION.get_or_init(|| {
+        format!(
+            "{}\n\n{}",
+            env!("CARGO_PKG_VERSION"),
+            fast_ssim2::build_info::summary()
+        )
+    })
+}
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, long_version = long_version(), about, long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
     #[command(subcommand)]
@@ -20,7 +41,9 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 #[allow(clippy::large_enum_variant)]
 enum Commands {
-    /// Compare two still images. Resolutions must be identical.
+    /// Compare two still images. Resolutions must be identical unless
+    /// --resample is given.
+    #[command(verbatim_doc_comment)]
     Image {
         /// Source image
         #[arg(help = "Original unmodified image", value_hint = clap::ValueHint::FilePath)]
@@ -29,6 +52,79 @@ enum Commands {
         /// Distorted image
         #[arg(help = "Distorted image", value_hint = clap::ValueHint::FilePath)]
         distorted: PathBuf,
+
+        /// Compare pixels as stored, without applying EXIF orientation.
+        #[arg(long)]
+        ignore_orientation: bool,
+
+        /// Box-downscale both images by this factor before scoring, with the
+        /// same filter as the C++ tool's Downsample.
+        #[arg(long, value_name = "FACTOR", verbatim_doc_comment)]
+        pre_resize: Option<usize>,
+
+        /// Resample a distorted image of another size to the source's size
+        /// in linear light with this filter: lanczos3 or catmull-rom.
+        #[arg(long, value_name = "FILTER", value_parser = parse_resample_filter, verbatim_doc_comment)]
+        resample: Option<ResampleFilter>,
+
+        /// Write each scale the metric compares, as source and distorted
+        /// PNGs, to this directory, with the points each scale cost in
+        /// scales.txt.
+        #[arg(long, value_name = "DIR", value_hint = clap::ValueHint::DirPath, verbatim_doc_comment)]
+        dump_scales: Option<PathBuf>,
+    },
+    /// Compare one source image against many distorted images, printing one
+    /// score per line in input order. Directories are expanded to the files
+    /// they contain.
+    ///
+    /// Upcoming images are decoded in the background while earlier ones are
+    /// scored; decoded images waiting to be scored are limited to
+    /// --prefetch-memory.
+    #[command(verbatim_doc_comment)]
+    Batch {
+        /// Source image
+        #[arg(help = "Original unmodified image", value_hint = clap::ValueHint::FilePath)]
+        source: PathBuf,
+
+        /// Distorted images or directories of images
+        #[arg(required = true, value_hint = clap::ValueHint::AnyPath)]
+        distorted: Vec<PathBuf>,
+
+        /// Compare pixels as stored, without applying EXIF orientation.
+        #[arg(long)]
+        ignore_orientation: bool,
+
+        /// Box-downscale both images by this factor before scoring, with the
+        /// same filter as the C++ tool's Downsample.
+        #[arg(long, value_name = "FACTOR", verbatim_doc_comment)]
+        pre_resize: Option<usize>,
+
+        /// How many threads decode upcoming images.
+        #[arg(long, default_value_t = 2)]
+        decode_threads: usize,
+
+        /// How many threads score decoded images. Defaults to the number of CPUs.
+        #[arg(long, short)]
+        threads: Option<usize>,
+
+        /// Memory for decoded images waiting to be scored, in MiB. Each
+        /// decode and scoring thread holds one more image on top of this.
+        #[arg(long, default_value_t = 1024, verbatim_doc_comment)]
+        prefetch_memory: usize,
+    },
+    /// Score deterministic synthetic image pairs: a quick sanity check and
+    /// benchmark that needs no sample images. The same size and seed print
+    /// the same scores on every machine with the same build; the exit code is
+    /// non-zero if a score is implausible.
+    #[command(verbatim_doc_comment)]
+    Selftest {
+        /// Image size as WIDTHxHEIGHT
+        #[arg(long, default_value = "512x512", value_parser = selftest::parse_size)]
+        size: (usize, usize),
+
+        /// Seed of the test pattern and noise.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
     },
     /// Compare two videos. Resolutions and frame counts must be identical.
     #[cfg(feature = "video")]
@@ -66,6 +162,23 @@ enum Commands {
         #[arg(long, short)]
         verbose: bool,
 
+        /// Text file with one weight per line for each frame (by frame number),
+        /// e.g. from a complexity or saliency model. Reports a weighted mean.
+        #[arg(long, value_hint = clap::ValueHint::FilePath, verbatim_doc_comment)]
+        weights: Option<PathBuf>,
+
+        /// Weight frames by the luma variance of the source frame, so flat
+        /// scenes don't dilute the pooled score. Reports a weighted mean.
+        #[arg(long, conflicts_with = "weights", verbatim_doc_comment)]
+        weight_by_variance: bool,
+
+        /// EXPERIMENTAL: compare each distorted frame against the source frames
+        /// up to RADIUS frames before and after it and keep the best score, so
+        /// temporal denoising that moves grain isn't heavily penalized.
+        /// Scores are not comparable with the default mode. Runs on one worker.
+        #[arg(long, value_name = "RADIUS", verbatim_doc_comment)]
+        temporal_window: Option<usize>,
+
         /// Source color matrix
         #[arg(long)]
         src_matrix: Option<String>,
@@ -102,7 +215,47 @@ enum Commands {
 
 fn main() {
     match Cli::parse().command {
-        Commands::Image { source, distorted } => compare_images(&source, &distorted),
+        Commands::Image {
+            source,
+            distorted,
+            ignore_orientation,
+            pre_resize,
+            resample,
+            dump_scales,
+        } => compare_images(
+            &source,
+            &distorted,
+            !ignore_orientation,
+            pre_resize.map(PreResize::new),
+            resample,
+            dump_scales.as_deref(),
+        ),
+        Commands::Batch {
+            source,
+            distorted,
+            ignore_orientation,
+            pre_resize,
+            decode_threads,
+            threads,
+            prefetch_memory,
+        } => {
+            let threads = threads
+                .or_else(|| std::thread::available_parallelism().ok().map(usize::from))
+                .unwrap_or(1);
+            batch::compare_batch(
+                &source,
+                distorted,
+                !ignore_orientation,
+                pre_resize.map(PreResize::new),
+                decode_threads.max(1),
+                threads.max(1),
+                prefetch_memory,
+            )
+        }
+        Commands::Selftest {
+            size: (width, height),
+            seed,
+        } => selftest::run(width, height, seed),
         #[cfg(feature = "video")]
         Commands::Video {
             source,
@@ -113,6 +266,9 @@ fn main() {
             increment,
             graph,
             verbose,
+            weights,
+            weight_by_variance,
+            temporal_window,
             src_matrix,
             src_transfer,
             src_primaries,
@@ -142,6 +298,17 @@ fn main() {
             let dst_primaries = dst_primaries
                 .map(|i| parse_primaries(&i))
                 .unwrap_or(ColorPrimaries::Unspecified);
+            let weighting = match weights {
+                Some(path) => match read_frame_weights(&path) {
+                    Ok(weights) => FrameWeighting::PerFrame(weights),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                },
+                None if weight_by_variance => FrameWeighting::LumaVariance,
+                None => FrameWeighting::Uniform,
+            };
             compare_videos(
                 &source,
                 &distorted,
@@ -151,6 +318,8 @@ fn main() {
                 inc,
                 graph,
                 verbose,
+                weighting,
+                temporal_window,
                 src_matrix,
                 src_transfer,
                 src_primaries,
@@ -164,43 +333,98 @@ fn main() {
     }
 }
 
-fn compare_images(source: &Path, distorted: &Path) {
-    // For now just assumes the input is sRGB. Trying to keep this as simple as possible for now.
-    let source = image::open(source).expect("Failed to open source file");
-    let distorted = image::open(distorted).expect("Failed to open distorted file");
-
-    let source_data = source
-        .to_rgb32f()
-        .chunks_exact(3)
-        .map(|chunk| [chunk[0], chunk[1], chunk[2]])
-        .collect::<Vec<_>>();
+fn parse_resample_filter(name: &str) -> Result<ResampleFilter, String> {
+    ResampleFilter::parse(name)
+        .ok_or_else(|| format!("unknown filter {name:?} (expected lanczos3 or catmull-rom)"))
+}
 
-    let source_data = Rgb::new(
-        source_data,
-        source.width() as usize,
-        source.height() as usize,
-        TransferCharacteristic::SRGB,
-        ColorPrimaries::BT709,
-    )
-    .expect("Failed to process source_data into RGB");
+/// Decodes an image, rotating/flipping it upright according to its EXIF
+/// orientation unless `apply_orientation` is false.
+///
+/// Phone photos are commonly stored sideways with an orientation tag, while
+/// re-encoded versions are often stored upright, so comparing the raw pixels
+/// would compare a rotated image against an upright one.
+fn open_image(path: &Path, apply_orientation: bool) -> ImageResult<DynamicImage> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    if apply_orientation {
+        image.apply_orientation(orientation);
+    }
+    Ok(image)
+}
 
-    let distorted_data = distorted
+/// Wraps decoded pixels as sRGB with BT.709 primaries
+fn image_to_rgb(image: &DynamicImage) -> Rgb {
+    let data = image
         .to_rgb32f()
         .chunks_exact(3)
         .map(|chunk| [chunk[0], chunk[1], chunk[2]])
         .collect::<Vec<_>>();
 
-    let distorted_data = Rgb::new(
-        distorted_data,
-        distorted.width() as usize,
-        distorted.height() as usize,
+    Rgb::new(
+        data,
+        image.width() as usize,
+        image.height() as usize,
         TransferCharacteristic::SRGB,
         ColorPrimaries::BT709,
     )
-    .expect("Failed to process distorted_data into RGB");
+    .expect("Failed to process image data into RGB")
+}
+
+fn compare_images(
+    source: &Path,
+    distorted: &Path,
+    apply_orientation: bool,
+    pre_resize: Option<PreResize>,
+    resample: Option<ResampleFilter>,
+    dump_scales: Option<&Path>,
+) {
+    // For now just assumes the input is sRGB. Trying to keep this as simple as possible for now.
+    let source = open_image(source, apply_orientation).expect("Failed to open source file");
+    let distorted =
+        open_image(distorted, apply_orientation).expect("Failed to open distorted file");
 
-    let result = compute_frame_ssimulacra2(source_data, distorted_data)
-        .expect("Failed to calculate ssimulacra2");
+    let result = match dump_scales {
+        None => {
+            let mut config = Ssimulacra2Config::default();
+            if let Some(pre_resize) = pre_resize {
+                config = config.with_pre_resize(pre_resize);
+            }
+            if let Some(resample) = resample {
+                config = config.with_resample_mismatched(resample);
+            }
+            compute_frame_ssimulacra2_with_config(
+                image_to_rgb(&source),
+                image_to_rgb(&distorted),
+                config,
+            )
+            .expect("Failed to calculate ssimulacra2")
+        }
+        Some(dir) => {
+            // Pre-resize here rather than in the config, so the dump starts
+            // from the same full-resolution scale the metric scores
+            let prepare = |image: LinearRgbImage| match pre_resize {
+                Some(resize) => resize.apply(&image).expect("Failed to pre-resize"),
+                None => image,
+            };
+            let source = image_to_rgb(&source).to_linear_rgb();
+            let mut distorted = image_to_rgb(&distorted).to_linear_rgb();
+            if let Some(filter) = resample {
+                distorted =
+                    fast_ssim2::resample(&distorted, source.width(), source.height(), filter)
+                        .expect("Failed to resample");
+            }
+            let (source, distorted) = (prepare(source), prepare(distorted));
+            let detailed =
+                compute_ssimulacra2_detailed(&source, &distorted, Ssimulacra2Config::default())
+                    .expect("Failed to calculate ssimulacra2");
+            dump::dump_scales(dir, &source, &distorted, &detailed);
+            detailed.score
+        }
+    };
 
     println!("Score: {result:.8}");
 }
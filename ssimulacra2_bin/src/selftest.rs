@@ -0,0 +1,228 @@
+//! Scoring synthetic image pairs, so a deployment can be checked without
+//! sample images on the box.
+//!
+//! The source is a seeded pattern of gradients, flat rectangles and texture,
+//! and each distortion is a classic encoder artifact applied to it. The same
+//! size and seed give the same pixels on every machine, so the printed
+//! scores can be diffed across hosts, and the timings double as a quick
+//! benchmark.
+
+use std::time::{Duration, Instant};
+
+use fast_ssim2::{
+    compute_ssimulacra2_with_config, srgb_u8_to_linear, LinearRgbImage, Ssimulacra2Config,
+};
+
+/// Parses `WxH`, e.g. `1920x1080`.
+pub fn parse_size(size: &str) -> Result<(usize, usize), String> {
+    let (width, height) = size
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got {:?}", size))?;
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .map_err(|e| format!("invalid dimension {:?}: {}", n, e))
+    };
+    let (width, height) = (parse(width)?, parse(height)?);
+    if width < 8 || height < 8 {
+        return Err("images must be at least 8x8 pixels".to_string());
+    }
+    Ok((width, height))
+}
+
+/// SplitMix64: tiny, seedable and identical on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Uniform in `-amplitude..=amplitude`
+    fn offset(&mut self, amplitude: i32) -> i32 {
+        self.below(2 * amplitude as usize + 1) as i32 - amplitude
+    }
+}
+
+/// 8-bit sRGB pixels, row-major
+#[derive(Clone)]
+struct Pattern {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Pattern {
+    /// Diagonal gradients overlaid with flat rectangles and fine texture.
+    fn source(width: usize, height: usize, seed: u64) -> Self {
+        let mut rng = Rng(seed);
+        let mut pixels: Vec<[u8; 3]> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                [
+                    (x * 255 / width) as u8,
+                    (y * 255 / height) as u8,
+                    ((x + y) * 255 / (width + height)) as u8,
+                ]
+            })
+            .collect();
+        for _ in 0..16 {
+            let (x0, y0) = (rng.below(width), rng.below(height));
+            let (x1, y1) = (
+                (x0 + 1 + rng.below(width / 3 + 1)).min(width),
+                (y0 + 1 + rng.below(height / 3 + 1)).min(height),
+            );
+            let color: [u8; 3] = std::array::from_fn(|_| rng.below(256) as u8);
+            for y in y0..y1 {
+                pixels[y * width + x0..y * width + x1].fill(color);
+            }
+        }
+        let mut pattern = Self {
+            width,
+            height,
+            pixels,
+        };
+        pattern.map(|v| v, &mut rng, 12);
+        pattern
+    }
+
+    /// Applies `f` to every channel value, then adds uniform noise of up to
+    /// `noise` levels.
+    fn map(&mut self, f: impl Fn(u8) -> u8, rng: &mut Rng, noise: i32) {
+        for pixel in &mut self.pixels {
+            for v in pixel.iter_mut() {
+                let value = i32::from(f(*v));
+                let offset = if noise > 0 { rng.offset(noise) } else { 0 };
+                *v = (value + offset).clamp(0, 255) as u8;
+            }
+        }
+    }
+
+    fn noisy(&self, amplitude: i32, seed: u64) -> Self {
+        let mut out = self.clone();
+        out.map(|v| v, &mut Rng(seed), amplitude);
+        out
+    }
+
+    fn quantized(&self, levels: u32) -> Self {
+        let step = 256 / levels;
+        let mut out = self.clone();
+        out.map(
+            |v| (u32::from(v) / step * step + step / 2).min(255) as u8,
+            &mut Rng(0),
+            0,
+        );
+        out
+    }
+
+    /// 3x3 box blur, clamping at the edges
+    fn blurred(&self) -> Self {
+        let (width, height) = (self.width, self.height);
+        let pixels = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let mut sum = [0u32; 3];
+                for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                    for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                        for (sum, v) in sum.iter_mut().zip(self.pixels[ny * width + nx]) {
+                            *sum += u32::from(v);
+                        }
+                    }
+                }
+                let count = ((x + 2).min(width) - x.saturating_sub(1)) as u32
+                    * ((y + 2).min(height) - y.saturating_sub(1)) as u32;
+                sum.map(|sum| ((sum + count / 2) / count) as u8)
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn to_linear(&self) -> LinearRgbImage {
+        let data = self
+            .pixels
+            .iter()
+            .map(|pixel| pixel.map(srgb_u8_to_linear))
+            .collect();
+        LinearRgbImage::new(data, self.width, self.height)
+    }
+}
+
+pub fn run(width: usize, height: usize, seed: u64) {
+    let source = Pattern::source(width, height, seed);
+    // Distortions in the order their scores must decrease within each family
+    let distorted = [
+        ("identical", source.clone()),
+        ("noise +-2", source.noisy(2, seed ^ 1)),
+        ("noise +-8", source.noisy(8, seed ^ 2)),
+        ("noise +-24", source.noisy(24, seed ^ 3)),
+        ("quantize 32 levels", source.quantized(32)),
+        ("quantize 8 levels", source.quantized(8)),
+        ("box blur 3x3", source.blurred()),
+    ];
+
+    println!("selftest {}x{}, seed {}", width, height, seed);
+    println!(
+        "kernels: {}",
+        fast_ssim2::build_info::runtime_target().unwrap_or("generic")
+    );
+    let source = source.to_linear();
+    let config = Ssimulacra2Config::default();
+    let mut total = Duration::ZERO;
+    let mut scores = Vec::with_capacity(distorted.len());
+    for (name, pattern) in &distorted {
+        let image = pattern.to_linear();
+        let start = Instant::now();
+        let score = compute_ssimulacra2_with_config(&source, &image, config.clone())
+            .expect("Failed to calculate ssimulacra2");
+        let elapsed = start.elapsed();
+        total += elapsed;
+        println!(
+            "{:<20} {:>14.8} {:>10.2} ms",
+            name,
+            score,
+            elapsed.as_secs_f64() * 1e3
+        );
+        scores.push(score);
+    }
+    let megapixels = (width * height * distorted.len()) as f64 / 1e6;
+    println!(
+        "{} comparisons, {:.2} Mpx/s",
+        distorted.len(),
+        megapixels / total.as_secs_f64()
+    );
+
+    let mut failures = Vec::new();
+    if (scores[0] - 100.0).abs() > 1e-6 {
+        failures.push(format!("identical images scored {:.8}, not 100", scores[0]));
+    }
+    for family in [&scores[1..4], &scores[4..6]] {
+        if family.windows(2).any(|pair| pair[0] <= pair[1]) {
+            failures.push(format!(
+                "scores {:?} don't decrease with the distortion",
+                family
+            ));
+        }
+    }
+    if scores.iter().any(|score| !score.is_finite()) {
+        failures.push("non-finite score".to_string());
+    }
+    if !failures.is_empty() {
+        for failure in &failures {
+            eprintln!("FAILED: {}", failure);
+        }
+        std::process::exit(1);
+    }
+}
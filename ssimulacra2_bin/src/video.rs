@@ -1,4 +1,5 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
 use std::io::stderr;
 use std::sync::{mpsc, Arc, Mutex};
 use std::time::Duration;
@@ -10,13 +11,12 @@ use std::{
 use av_metrics_decoders::{y4m::new_decoder_from_stdin, Decoder, VapoursynthDecoder};
 use crossterm::tty::IsTty;
 use fast_ssim2::{
-    compute_frame_ssimulacra2, ColorPrimaries, MatrixCoefficients, Pixel, TransferCharacteristic,
-    Yuv, YuvConfig,
+    compute_frame_ssimulacra2, luma_variance, ColorPrimaries, LinearRgb, MatrixCoefficients, Pixel,
+    ScorePool, TemporalMatch, TemporalWindow, TransferCharacteristic, Yuv, YuvConfig,
 };
 use image::ColorType;
 use indicatif::{HumanDuration, ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
 use num_traits::FromPrimitive;
-use statrs::statistics::{Data, Distribution, Median, OrderStatistics};
 
 const PROGRESS_CHARS: &str = "█▉▊▋▌▍▎▏  ";
 const INDICATIF_PROGRESS_TEMPLATE: &str = if cfg!(windows) {
@@ -123,14 +123,16 @@ struct VideoCompare<E: Decoder, F: Decoder> {
     distorted: F,
 }
 
-fn calc_score<S: Pixel, D: Pixel, E: Decoder, F: Decoder>(
+/// Reads the next source/distorted frame pair, skipping frames according to
+/// `inc`. Returns `None` once either input runs out or `end_frame` is reached.
+fn read_frame_pair<S: Pixel, D: Pixel, E: Decoder, F: Decoder>(
     mtx: &VideoCompareMutex<E, F>,
     src_yuvcfg: &YuvConfig,
     dst_yuvcfg: &YuvConfig,
     inc: usize,
     end_frame: Option<usize>,
     verbose: bool,
-) -> Option<(usize, f64)> {
+) -> Option<(usize, Yuv<S>, Yuv<D>)> {
     let (frame_idx, (src_frame, dst_frame)) = {
         let mut guard = mtx.lock().unwrap();
 
@@ -169,13 +171,134 @@ fn calc_score<S: Pixel, D: Pixel, E: Decoder, F: Decoder>(
 
     let src_yuv = Yuv::new(src_frame, *src_yuvcfg).unwrap();
     let dst_yuv = Yuv::new(dst_frame, *dst_yuvcfg).unwrap();
+    Some((frame_idx, src_yuv, dst_yuv))
+}
+
+fn calc_score<S: Pixel, D: Pixel, E: Decoder, F: Decoder>(
+    mtx: &VideoCompareMutex<E, F>,
+    src_yuvcfg: &YuvConfig,
+    dst_yuvcfg: &YuvConfig,
+    inc: usize,
+    end_frame: Option<usize>,
+    verbose: bool,
+    weight_by_variance: bool,
+) -> Option<(usize, f64, f64)> {
+    let (frame_idx, src_yuv, dst_yuv) =
+        read_frame_pair::<S, D, _, _>(mtx, src_yuvcfg, dst_yuvcfg, inc, end_frame, verbose)?;
+    let weight = if weight_by_variance {
+        luma_variance(&src_yuv)
+    } else {
+        1.0
+    };
 
     Some((
         frame_idx,
         compute_frame_ssimulacra2(src_yuv, dst_yuv).expect("Failed to calculate ssimulacra2"),
+        weight,
     ))
 }
 
+/// Experimental temporal mode: scores every distorted frame against the best
+/// of the source frames within `radius` of it. Frames must be pushed in order,
+/// so this runs on a single worker and sends results as windows complete.
+#[allow(clippy::too_many_arguments)]
+fn calc_temporal_scores<S: Pixel, D: Pixel, E: Decoder, F: Decoder>(
+    mtx: &VideoCompareMutex<E, F>,
+    src_yuvcfg: &YuvConfig,
+    dst_yuvcfg: &YuvConfig,
+    inc: usize,
+    end_frame: Option<usize>,
+    verbose: bool,
+    weight_by_variance: bool,
+    radius: usize,
+    result_tx: &mpsc::Sender<(usize, f64, f64)>,
+) {
+    let mut window = TemporalWindow::new(radius);
+    // Frame numbers and weights of the frames still waiting in the window
+    let mut pending = VecDeque::with_capacity(radius + 1);
+    let send = |matched: TemporalMatch, pending: &mut VecDeque<(usize, f64)>| {
+        let (frame_idx, weight) = pending.pop_front().unwrap();
+        if verbose && matched.offset != 0 {
+            println!(
+                "Frame {}: best match is source frame {:+}",
+                frame_idx, matched.offset
+            );
+        }
+        result_tx.send((frame_idx, matched.score, weight)).unwrap();
+    };
+
+    while let Some((frame_idx, src_yuv, dst_yuv)) =
+        read_frame_pair::<S, D, _, _>(mtx, src_yuvcfg, dst_yuvcfg, inc, end_frame, verbose)
+    {
+        let weight = if weight_by_variance {
+            luma_variance(&src_yuv)
+        } else {
+            1.0
+        };
+        pending.push_back((frame_idx, weight));
+
+        let source = LinearRgb::try_from(src_yuv)
+            .unwrap_or_else(|_| panic!("Failed to convert source frame {}", frame_idx));
+        let distorted = LinearRgb::try_from(dst_yuv)
+            .unwrap_or_else(|_| panic!("Failed to convert distorted frame {}", frame_idx));
+        if let Some(matched) = window
+            .push(source, distorted)
+            .expect("Failed to calculate ssimulacra2")
+        {
+            send(matched, &mut pending);
+        }
+    }
+
+    for matched in window.finish().expect("Failed to calculate ssimulacra2") {
+        send(matched, &mut pending);
+    }
+}
+
+/// How frames are weighted when pooling scores
+pub enum FrameWeighting {
+    /// Every frame counts the same
+    Uniform,
+    /// Caller-supplied weight per frame number
+    PerFrame(Vec<f64>),
+    /// Weight by the luma variance of the source frame
+    LumaVariance,
+}
+
+/// Reads one non-negative weight per line; blank lines and `#` comments are skipped.
+///
+/// Errors name the file and the 1-based line of the first bad weight.
+pub fn read_frame_weights(path: &Path) -> Result<Vec<f64>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read weights file {}: {}", path.display(), e))?;
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            let weight: f64 = line.parse().map_err(|_| {
+                format!(
+                    "{}:{}: invalid frame weight: {}",
+                    path.display(),
+                    number,
+                    line
+                )
+            })?;
+            // NaN fails both comparisons
+            if weight.is_finite() && weight >= 0.0 {
+                Ok(weight)
+            } else {
+                Err(format!(
+                    "{}:{}: frame weights must be finite and non-negative, got {}",
+                    path.display(),
+                    number,
+                    line
+                ))
+            }
+        })
+        .collect()
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn compare_videos(
     source: &str,
@@ -186,6 +309,8 @@ pub fn compare_videos(
     inc: usize,
     graph: bool,
     verbose: bool,
+    weighting: FrameWeighting,
+    temporal_window: Option<usize>,
     src_matrix: MatrixCoefficients,
     src_transfer: TransferCharacteristic,
     src_primaries: ColorPrimaries,
@@ -221,6 +346,8 @@ pub fn compare_videos(
             inc,
             graph,
             verbose,
+            weighting,
+            temporal_window,
             src_matrix,
             src_transfer,
             src_primaries,
@@ -254,6 +381,8 @@ pub fn compare_videos(
             inc,
             graph,
             verbose,
+            weighting,
+            temporal_window,
             src_matrix,
             src_transfer,
             src_primaries,
@@ -296,6 +425,8 @@ pub fn compare_videos(
         inc,
         graph,
         verbose,
+        weighting,
+        temporal_window,
         src_matrix,
         src_transfer,
         src_primaries,
@@ -319,6 +450,8 @@ fn compare_videos_inner<D: Decoder + 'static, E: Decoder + 'static>(
     inc: usize,
     graph: bool,
     verbose: bool,
+    weighting: FrameWeighting,
+    temporal_window: Option<usize>,
     mut src_matrix: MatrixCoefficients,
     mut src_transfer: TransferCharacteristic,
     mut src_primaries: ColorPrimaries,
@@ -385,6 +518,7 @@ fn compare_videos_inner<D: Decoder + 'static, E: Decoder + 'static>(
         color_primaries: dst_primaries,
     };
 
+    let weight_by_variance = matches!(weighting, FrameWeighting::LumaVariance);
     let (result_tx, result_rx) = mpsc::channel();
     let src_bd = src_config.bit_depth;
     let dst_bd = dst_config.bit_depth;
@@ -400,7 +534,64 @@ fn compare_videos_inner<D: Decoder + 'static, E: Decoder + 'static>(
         distorted,
     }));
 
-    for _ in 0..frame_threads {
+    if let Some(radius) = temporal_window {
+        let video_compare = Arc::clone(&video_compare);
+        let result_tx = result_tx.clone();
+
+        std::thread::spawn(move || match (src_bd, dst_bd) {
+            (8, 8) => calc_temporal_scores::<u8, u8, _, _>(
+                &video_compare,
+                &src_config,
+                &dst_config,
+                inc,
+                end_frame,
+                verbose,
+                weight_by_variance,
+                radius,
+                &result_tx,
+            ),
+            (8, _) => calc_temporal_scores::<u8, u16, _, _>(
+                &video_compare,
+                &src_config,
+                &dst_config,
+                inc,
+                end_frame,
+                verbose,
+                weight_by_variance,
+                radius,
+                &result_tx,
+            ),
+            (_, 8) => calc_temporal_scores::<u16, u8, _, _>(
+                &video_compare,
+                &src_config,
+                &dst_config,
+                inc,
+                end_frame,
+                verbose,
+                weight_by_variance,
+                radius,
+                &result_tx,
+            ),
+            (_, _) => calc_temporal_scores::<u16, u16, _, _>(
+                &video_compare,
+                &src_config,
+                &dst_config,
+                inc,
+                end_frame,
+                verbose,
+                weight_by_variance,
+                radius,
+                &result_tx,
+            ),
+        });
+    }
+
+    let worker_threads = if temporal_window.is_some() {
+        0
+    } else {
+        frame_threads
+    };
+    for _ in 0..worker_threads {
         let video_compare = Arc::clone(&video_compare);
         let result_tx = result_tx.clone();
 
@@ -414,6 +605,7 @@ fn compare_videos_inner<D: Decoder + 'static, E: Decoder + 'static>(
                         inc,
                         end_frame,
                         verbose,
+                        weight_by_variance,
                     ),
                     (8, _) => calc_score::<u8, u16, _, _>(
                         &video_compare,
@@ -422,6 +614,7 @@ fn compare_videos_inner<D: Decoder + 'static, E: Decoder + 'static>(
                         inc,
                         end_frame,
                         verbose,
+                        weight_by_variance,
                     ),
                     (_, 8) => calc_score::<u16, u8, _, _>(
                         &video_compare,
@@ -430,6 +623,7 @@ fn compare_videos_inner<D: Decoder + 'static, E: Decoder + 'static>(
                         inc,
                         end_frame,
                         verbose,
+                        weight_by_variance,
                     ),
                     (_, _) => calc_score::<u16, u16, _, _>(
                         &video_compare,
@@ -438,6 +632,7 @@ fn compare_videos_inner<D: Decoder + 'static, E: Decoder + 'static>(
                         inc,
                         end_frame,
                         verbose,
+                        weight_by_variance,
                     ),
                 };
 
@@ -478,30 +673,56 @@ fn compare_videos_inner<D: Decoder + 'static, E: Decoder + 'static>(
         ProgressBar::hidden()
     };
 
+    // Per-frame scores are only kept when graphing; the pool summarizes long
+    // content in constant memory
     let mut results = BTreeMap::new();
-    let mut rolling_mean = 0f64;
+    let mut pool = ScorePool::new();
     for score in result_rx {
         if verbose {
             println!("Frame {}: {:.8}", score.0, score.1);
         }
 
-        results.insert(score.0, score.1);
-        rolling_mean = rolling_mean + (score.1 - rolling_mean) / (results.len() as f64);
+        if graph {
+            results.insert(score.0, score.1);
+        }
+        let weight = match &weighting {
+            FrameWeighting::PerFrame(weights) => *weights
+                .get(score.0)
+                .unwrap_or_else(|| panic!("No weight given for frame {}", score.0)),
+            FrameWeighting::Uniform | FrameWeighting::LumaVariance => score.2,
+        };
+        pool.push_weighted(score.1, weight);
+        let rolling_mean = pool.mean().unwrap_or_default();
         progress.set_message(format!(", mean: {rolling_mean:.2}"));
         progress.inc(1);
     }
 
     progress.finish();
 
-    let results: Vec<f64> = results.into_values().collect();
-    let frames = results.len();
-    let mut data = Data::new(results.clone());
+    let Some(summary) = pool.summary() else {
+        println!("Video Score for 0 frames");
+        return;
+    };
+    let frames = summary.frames;
     println!("Video Score for {} frames", frames);
-    println!("Mean: {:.8}", data.mean().unwrap());
-    println!("Median: {:.8}", data.median());
-    println!("Std Dev: {:.8}", data.std_dev().unwrap());
-    println!("5th Percentile: {:.8}", data.percentile(5));
-    println!("95th Percentile: {:.8}", data.percentile(95));
+    if let Some(radius) = temporal_window {
+        println!(
+            "(EXPERIMENTAL temporal mode: best match within {} frames, not comparable with frame-by-frame scores)",
+            radius
+        );
+    }
+    println!("Mean: {:.8}", summary.mean);
+    if !matches!(weighting, FrameWeighting::Uniform) {
+        println!("Weighted Mean: {:.8}", summary.weighted_mean);
+    }
+    println!("Median: {:.8}", summary.p50);
+    println!("Std Dev: {:.8}", summary.std_dev);
+    println!("1st Percentile: {:.8}", summary.p1);
+    println!("5th Percentile: {:.8}", summary.p5);
+    println!("95th Percentile: {:.8}", summary.p95);
+    if !summary.exact {
+        println!("(percentiles estimated in streaming mode)");
+    }
 
     if graph {
         use plotters::prelude::*;
@@ -541,7 +762,10 @@ fn compare_videos_inner<D: Decoder + 'static, E: Decoder + 'static>(
             chart
                 .draw_series(
                     AreaSeries::new(
-                        results.into_iter().enumerate().map(|(i, v)| (i, v as f32)),
+                        results
+                            .into_values()
+                            .enumerate()
+                            .map(|(i, v)| (i, v as f32)),
                         0.0,
                         CYAN.mix(0.5),
                     )